@@ -26,6 +26,14 @@ pub struct Config {
     pub enable_redis: bool,
     pub redis_url: String,
 
+    // Rate limiting
+    pub enable_rate_limit: bool,
+    pub rate_limit_storage: String,
+    pub rate_limit_window: u64,
+    pub rate_limit_auth_requests: u32,
+    pub rate_limit_api_requests: u32,
+    pub rate_limit_trust_proxy: bool,
+
     // Authentication
     pub jwt_expires_in: String,
     pub enable_signup: bool,
@@ -55,6 +63,10 @@ pub struct Config {
     pub ldap_ca_cert_file: Option<String>,
     pub ldap_validate_cert: bool,
     pub ldap_ciphers: Option<String>,
+    pub ldap_attribute_for_groups: String,
+    pub ldap_admin_groups: Vec<String>,
+    pub enable_ldap_group_management: bool,
+    pub enable_ldap_group_creation: bool,
 
     // SCIM 2.0
     pub scim_enabled: bool,
@@ -70,6 +82,7 @@ pub struct Config {
 
     // Features
     pub enable_openai_api: bool,
+    pub enable_ollama_api: bool,
     pub enable_channels: bool,
     pub enable_image_generation: bool,
     pub enable_code_execution: bool,
@@ -88,6 +101,9 @@ pub struct Config {
 
     // Logging
     pub global_log_level: String,
+    pub audit_log_level: String,
+    pub audit_excluded_paths: Vec<String>,
+    pub max_body_log_size: usize,
 
     // OpenAI
     pub openai_api_base_url: String,
@@ -96,6 +112,10 @@ pub struct Config {
     pub openai_api_keys: Vec<String>,
     pub openai_api_configs: serde_json::Value,
 
+    // Ollama
+    pub ollama_base_urls: Vec<String>,
+    pub ollama_api_configs: serde_json::Value,
+
     // Audio - TTS
     pub tts_openai_api_base_url: String,
     pub tts_openai_api_key: String,
@@ -145,11 +165,16 @@ pub struct Config {
     pub images_gemini_api_key: String,
 
     pub image_generation_engine: String,
+    pub image_generation_model: String,
+    pub image_size: String,
+    pub image_steps: u32,
     pub enable_image_prompt_generation: bool,
 
     // RAG/Retrieval
     pub chunk_size: usize,
     pub chunk_overlap: usize,
+    pub text_splitter: String,
+    pub tiktoken_encoding_name: String,
     pub rag_top_k: usize,
     pub rag_embedding_model: String,
     pub rag_embedding_engine: String,
@@ -162,11 +187,38 @@ pub struct Config {
     pub top_k_reranker: i32,
     pub relevance_threshold: f64,
     pub hybrid_bm25_weight: f64,
+    pub rag_reranking_model: String,
     pub content_extraction_engine: String,
+    pub tika_server_url: String,
     pub pdf_extract_images: bool,
     pub rag_embedding_model_trust_remote_code: bool,
     pub rag_reranking_model_trust_remote_code: bool,
 
+    // Web Search
+    pub web_search_engine: String,
+    pub web_search_result_count: usize,
+    pub web_search_concurrent_requests: usize,
+    pub web_search_domain_filter_list: Vec<String>,
+    pub bypass_web_search_embedding_and_retrieval: bool,
+    pub searxng_query_url: String,
+    pub brave_search_api_key: String,
+    pub tavily_api_key: String,
+    pub google_pse_api_key: String,
+    pub google_pse_engine_id: String,
+
+    // Web and YouTube loaders
+    pub enable_rag_local_web_fetch: bool,
+    pub web_fetch_filter_list: Vec<String>,
+    pub web_loader_max_size: usize,
+    pub web_loader_timeout: u64,
+    pub youtube_loader_language: Vec<String>,
+    pub youtube_loader_proxy_url: String,
+
+    // WebAssembly Functions
+    pub function_wasm_fuel: u64,
+    pub function_wasm_max_memory: usize,
+    pub function_wasm_timeout: u64,
+
     // Sentence Transformers
     pub sentence_transformers_home: Option<String>,
     pub sentence_transformers_backend: String,
@@ -235,6 +287,7 @@ pub struct Config {
     pub image_prompt_generation_prompt_template: String,
     pub query_generation_prompt_template: String,
     pub tools_function_calling_prompt_template: String,
+    pub tool_calling_max_rounds: usize,
 
     // User permissions
     pub enable_user_webhooks: bool,
@@ -260,7 +313,7 @@ pub type MutableConfig = Arc<RwLock<Config>>;
 
 impl Config {
     /// Expand tilde (~) to home directory in path
-    fn expand_home_dir(path: &str) -> String {
+    pub fn expand_home_dir(path: &str) -> String {
         if path.starts_with("~/") {
             // Windows uses USERPROFILE, Unix uses HOME
             let home = if cfg!(windows) {
@@ -355,6 +408,31 @@ impl Config {
             redis_url: env::var("REDIS_URL")
                 .unwrap_or_else(|_| "redis://localhost:6379".to_string()),
 
+            // Rate limiting
+            enable_rate_limit: env::var("ENABLE_RATE_LIMIT")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            rate_limit_storage: env::var("RATE_LIMIT_STORAGE")
+                .unwrap_or_else(|_| "memory".to_string())
+                .to_lowercase(),
+            rate_limit_window: env::var("RATE_LIMIT_WINDOW")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .unwrap_or(60),
+            rate_limit_auth_requests: env::var("RATE_LIMIT_AUTH_REQUESTS")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .unwrap_or(10),
+            rate_limit_api_requests: env::var("RATE_LIMIT_API_REQUESTS")
+                .unwrap_or_else(|_| "600".to_string())
+                .parse()
+                .unwrap_or(600),
+            rate_limit_trust_proxy: env::var("RATE_LIMIT_TRUST_PROXY")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+
             // Authentication
             jwt_expires_in: env::var("JWT_EXPIRES_IN").unwrap_or_else(|_| "168h".to_string()),
             enable_signup: env::var("ENABLE_SIGNUP")
@@ -419,6 +497,22 @@ impl Config {
                 .parse()
                 .unwrap_or(true),
             ldap_ciphers: env::var("LDAP_CIPHERS").ok(),
+            ldap_attribute_for_groups: env::var("LDAP_ATTRIBUTE_FOR_GROUPS")
+                .unwrap_or_else(|_| "memberOf".to_string()),
+            ldap_admin_groups: env::var("LDAP_ADMIN_GROUPS")
+                .unwrap_or_else(|_| "admin,administrators".to_string())
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            enable_ldap_group_management: env::var("ENABLE_LDAP_GROUP_MANAGEMENT")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            enable_ldap_group_creation: env::var("ENABLE_LDAP_GROUP_CREATION")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
 
             // SCIM 2.0
             scim_enabled: env::var("SCIM_ENABLED")
@@ -444,6 +538,10 @@ impl Config {
                 .unwrap_or_else(|_| "true".to_string())
                 .parse()
                 .unwrap_or(true),
+            enable_ollama_api: env::var("ENABLE_OLLAMA_API")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .unwrap_or(true),
             enable_channels: env::var("ENABLE_CHANNELS")
                 .unwrap_or_else(|_| "false".to_string())
                 .parse()
@@ -511,6 +609,19 @@ impl Config {
 
             // Logging
             global_log_level: env::var("GLOBAL_LOG_LEVEL").unwrap_or_else(|_| "INFO".to_string()),
+            audit_log_level: env::var("AUDIT_LOG_LEVEL")
+                .unwrap_or_else(|_| "NONE".to_string())
+                .to_uppercase(),
+            audit_excluded_paths: env::var("AUDIT_EXCLUDED_PATHS")
+                .unwrap_or_else(|_| "/chats,/chat,/folders".to_string())
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            max_body_log_size: env::var("MAX_BODY_LOG_SIZE")
+                .unwrap_or_else(|_| "2048".to_string())
+                .parse()
+                .unwrap_or(2048),
 
             // OpenAI
             openai_api_base_url: env::var("OPENAI_API_BASE_URL")
@@ -552,6 +663,16 @@ impl Config {
             },
             openai_api_configs: serde_json::json!({}),
 
+            // Ollama
+            ollama_base_urls: env::var("OLLAMA_BASE_URLS")
+                .or_else(|_| env::var("OLLAMA_BASE_URL"))
+                .unwrap_or_default()
+                .split(';')
+                .map(|s| s.trim().trim_end_matches('/').to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            ollama_api_configs: serde_json::json!({}),
+
             // Audio - TTS
             tts_openai_api_base_url: env::var("TTS_OPENAI_API_BASE_URL")
                 .unwrap_or_else(|_| "https://api.openai.com/v1".to_string()),
@@ -605,7 +726,10 @@ impl Config {
             comfyui_base_url: env::var("COMFYUI_BASE_URL").unwrap_or_default(),
             comfyui_api_key: env::var("COMFYUI_API_KEY").unwrap_or_default(),
             comfyui_workflow: env::var("COMFYUI_WORKFLOW").unwrap_or_default(),
-            comfyui_workflow_nodes: serde_json::json!([]),
+            comfyui_workflow_nodes: env::var("COMFYUI_WORKFLOW_NODES")
+                .ok()
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .unwrap_or_else(|| serde_json::json!([])),
 
             // Image Generation - Gemini
             images_gemini_api_base_url: env::var("IMAGES_GEMINI_API_BASE_URL")
@@ -614,6 +738,12 @@ impl Config {
 
             image_generation_engine: env::var("IMAGE_GENERATION_ENGINE")
                 .unwrap_or_else(|_| "openai".to_string()),
+            image_generation_model: env::var("IMAGE_GENERATION_MODEL").unwrap_or_default(),
+            image_size: env::var("IMAGE_SIZE").unwrap_or_else(|_| "512x512".to_string()),
+            image_steps: env::var("IMAGE_STEPS")
+                .unwrap_or_else(|_| "50".to_string())
+                .parse()
+                .unwrap_or(50),
             enable_image_prompt_generation: env::var("ENABLE_IMAGE_PROMPT_GENERATION")
                 .unwrap_or_else(|_| "false".to_string())
                 .parse()
@@ -628,6 +758,9 @@ impl Config {
                 .unwrap_or_else(|_| "100".to_string())
                 .parse()
                 .unwrap_or(100),
+            text_splitter: env::var("TEXT_SPLITTER").unwrap_or_default(),
+            tiktoken_encoding_name: env::var("TIKTOKEN_ENCODING_NAME")
+                .unwrap_or_else(|_| "cl100k_base".to_string()),
             rag_top_k: env::var("RAG_TOP_K")
                 .unwrap_or_else(|_| "5".to_string())
                 .parse()
@@ -668,8 +801,11 @@ impl Config {
                 .unwrap_or_else(|_| "0.5".to_string())
                 .parse()
                 .unwrap_or(0.5),
+            rag_reranking_model: env::var("RAG_RERANKING_MODEL").unwrap_or_default(),
             content_extraction_engine: env::var("CONTENT_EXTRACTION_ENGINE")
-                .unwrap_or_else(|_| "tika".to_string()),
+                .unwrap_or_else(|_| "".to_string()),
+            tika_server_url: env::var("TIKA_SERVER_URL")
+                .unwrap_or_else(|_| "http://localhost:9998".to_string()),
             pdf_extract_images: env::var("PDF_EXTRACT_IMAGES")
                 .unwrap_or_else(|_| "false".to_string())
                 .parse()
@@ -687,6 +823,75 @@ impl Config {
             .parse()
             .unwrap_or(true),
 
+            // Web Search
+            web_search_engine: env::var("WEB_SEARCH_ENGINE").unwrap_or_default(),
+            web_search_result_count: env::var("WEB_SEARCH_RESULT_COUNT")
+                .unwrap_or_else(|_| "3".to_string())
+                .parse()
+                .unwrap_or(3),
+            web_search_concurrent_requests: env::var("WEB_SEARCH_CONCURRENT_REQUESTS")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .unwrap_or(10),
+            web_search_domain_filter_list: env::var("WEB_SEARCH_DOMAIN_FILTER_LIST")
+                .unwrap_or_default()
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            bypass_web_search_embedding_and_retrieval: env::var(
+                "BYPASS_WEB_SEARCH_EMBEDDING_AND_RETRIEVAL",
+            )
+            .unwrap_or_else(|_| "false".to_string())
+            .parse()
+            .unwrap_or(false),
+            searxng_query_url: env::var("SEARXNG_QUERY_URL").unwrap_or_default(),
+            brave_search_api_key: env::var("BRAVE_SEARCH_API_KEY").unwrap_or_default(),
+            tavily_api_key: env::var("TAVILY_API_KEY").unwrap_or_default(),
+            google_pse_api_key: env::var("GOOGLE_PSE_API_KEY").unwrap_or_default(),
+            google_pse_engine_id: env::var("GOOGLE_PSE_ENGINE_ID").unwrap_or_default(),
+
+            // Web and YouTube loaders
+            enable_rag_local_web_fetch: env::var("ENABLE_RAG_LOCAL_WEB_FETCH")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            web_fetch_filter_list: env::var("WEB_FETCH_FILTER_LIST")
+                .unwrap_or_default()
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            web_loader_max_size: env::var("WEB_LOADER_MAX_SIZE")
+                .unwrap_or_else(|_| "10485760".to_string())
+                .parse()
+                .unwrap_or(10 * 1024 * 1024),
+            web_loader_timeout: env::var("WEB_LOADER_TIMEOUT")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .unwrap_or(10),
+            youtube_loader_language: env::var("YOUTUBE_LOADER_LANGUAGE")
+                .unwrap_or_else(|_| "en".to_string())
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            youtube_loader_proxy_url: env::var("YOUTUBE_LOADER_PROXY_URL").unwrap_or_default(),
+
+            // WebAssembly Functions
+            function_wasm_fuel: env::var("FUNCTION_WASM_FUEL")
+                .unwrap_or_else(|_| "1000000000".to_string())
+                .parse()
+                .unwrap_or(1_000_000_000),
+            function_wasm_max_memory: env::var("FUNCTION_WASM_MAX_MEMORY")
+                .unwrap_or_else(|_| "67108864".to_string())
+                .parse()
+                .unwrap_or(64 * 1024 * 1024),
+            function_wasm_timeout: env::var("FUNCTION_WASM_TIMEOUT")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .unwrap_or(30),
+
             // Sentence Transformers
             sentence_transformers_home: env::var("SENTENCE_TRANSFORMERS_HOME").ok(),
             sentence_transformers_backend: env::var("SENTENCE_TRANSFORMERS_BACKEND")
@@ -850,6 +1055,10 @@ impl Config {
                 "TOOLS_FUNCTION_CALLING_PROMPT_TEMPLATE",
             )
             .unwrap_or_else(|_| String::new()),
+            tool_calling_max_rounds: env::var("TOOL_CALLING_MAX_ROUNDS")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .unwrap_or(10),
 
             // User permissions
             enable_user_webhooks: env::var("ENABLE_USER_WEBHOOKS")
@@ -4,6 +4,7 @@ mod db;
 mod error;
 mod middleware;
 mod models;
+mod plugins;
 mod retrieval;
 mod routes;
 mod services;
@@ -47,8 +48,16 @@ pub struct AppState {
     pub vector_db: Option<Arc<dyn retrieval::VectorDB>>,
     // Embedding provider for generating embeddings
     pub embedding_provider: Option<Arc<dyn retrieval::EmbeddingProvider>>,
+    // Cross-encoder reranker for hybrid search (RAG_RERANKING_MODEL)
+    pub reranker: Option<Arc<dyn retrieval::Reranker>>,
     // Sandbox executor client for secure code execution
     pub sandbox_executor_client: Option<Arc<SandboxExecutorClient>>,
+    // WebAssembly runtime for filter/action/pipe Functions
+    pub plugin_runtime: Option<Arc<plugins::PluginRuntime>>,
+    // Running chat generations and background jobs, by chat
+    pub task_manager: utils::tasks::TaskManager,
+    // HTTP request counts for ENABLE_RATE_LIMIT, shared by all workers
+    pub rate_limiter: Option<middleware::HttpRateLimiter>,
 }
 
 #[actix_web::main]
@@ -151,21 +160,6 @@ async fn main() -> anyhow::Result<()> {
                 Ok(adapter) => {
                     let adapter_arc = Arc::new(adapter);
 
-                    // Spawn Redis subscription handler
-                    let adapter_sub = adapter_arc.clone();
-                    tokio::spawn(async move {
-                        // TODO: Handle incoming Redis messages and route to local sessions
-                        if let Err(e) = adapter_sub
-                            .subscribe(move |msg| {
-                                tracing::debug!("Received Redis message: {:?}", msg);
-                                // Route message to local connections
-                            })
-                            .await
-                        {
-                            tracing::error!("Redis subscription error: {:?}", e);
-                        }
-                    });
-
                     info!(
                         "✅ Redis adapter initialized for horizontal scaling (server: {})",
                         server_id
@@ -193,6 +187,9 @@ async fn main() -> anyhow::Result<()> {
             db.clone(),
         );
 
+        // Route messages from other nodes to local sessions
+        handler.spawn_redis_listener();
+
         // Spawn background cleanup tasks
         let manager_cleanup = manager.clone();
         tokio::spawn(async move {
@@ -287,7 +284,8 @@ async fn main() -> anyhow::Result<()> {
             Err(e) => {
                 warn!("⚠️  Failed to initialize vector database: {}", e);
                 warn!("   RAG features will be disabled. To enable:");
-                warn!("   - Set VECTOR_DB=chroma (or other supported DB)");
+                warn!("   - Set VECTOR_DB=local for the embedded store (no server needed)");
+                warn!("   - Or set VECTOR_DB=chroma (or other supported DB)");
                 warn!("   - Set CHROMA_HTTP_HOST and CHROMA_HTTP_PORT");
                 warn!("   - Ensure vector database is running");
                 None
@@ -475,6 +473,24 @@ async fn main() -> anyhow::Result<()> {
         None
     };
 
+    // Initialize reranker for hybrid search if a reranking model is configured
+    let reranker = if vector_db_enabled {
+        match retrieval::reranker::create_reranker(&config.rag_reranking_model) {
+            Ok(Some(reranker)) => {
+                info!("✅ Reranker initialized ({})", reranker.model_name());
+                Some(reranker)
+            }
+            Ok(None) => None,
+            Err(e) => {
+                warn!("⚠️  Failed to initialize reranker: {}", e);
+                warn!("   Hybrid search will rerank with embedding similarity");
+                None
+            }
+        }
+    } else {
+        None
+    };
+
     // Initialize sandbox executor client if enabled
     let sandbox_executor_client = if config.enable_code_execution {
         let sandbox_url = config
@@ -490,6 +506,33 @@ async fn main() -> anyhow::Result<()> {
         None
     };
 
+    // Initialize the WebAssembly runtime for Functions
+    let plugin_runtime = match plugins::PluginRuntime::from_config(&config) {
+        Ok(runtime) => Some(Arc::new(runtime)),
+        Err(e) => {
+            warn!("⚠️  Failed to initialize WebAssembly runtime: {}", e);
+            warn!("   Filter, action and pipe Functions will not run");
+            None
+        }
+    };
+
+    // Time embedding requests and vector database operations for /metrics
+    let vector_db = vector_db.map(utils::metrics::InstrumentedVectorDB::wrap);
+    let embedding_provider = embedding_provider.map(utils::metrics::InstrumentedEmbeddings::wrap);
+
+    // Track chat generations and background jobs; with Redis, stop
+    // requests reach tasks running on other nodes
+    let task_manager = utils::tasks::TaskManager::new(
+        redis.clone(),
+        config.enable_redis.then(|| config.redis_url.clone()),
+        "open-webui".to_string(),
+    );
+    if let Err(e) = task_manager.start_redis_listener() {
+        warn!("⚠️  Failed to start task command listener: {}", e);
+    }
+
+    let rate_limiter = middleware::HttpRateLimiter::from_config(&config, redis.clone());
+
     let state = web::Data::new(AppState {
         db: db.clone(),
         config: Arc::new(RwLock::new(config.clone())),
@@ -500,7 +543,11 @@ async fn main() -> anyhow::Result<()> {
         http_client,
         vector_db,
         embedding_provider,
+        reranker,
         sandbox_executor_client,
+        plugin_runtime,
+        task_manager,
+        rate_limiter,
     });
 
     // Start server
@@ -572,9 +619,18 @@ async fn main() -> anyhow::Result<()> {
             .wrap(Logger::default())
             .wrap(NormalizePath::trim())
             .wrap(middleware::SecurityHeaders) // Security headers middleware
+            .wrap(middleware::HttpMetrics) // Request latency for /metrics
+            .wrap(middleware::RateLimit) // 429 once ENABLE_RATE_LIMIT limits are exceeded
+            .wrap(middleware::AuditLogger) // Who did what, per AUDIT_LOG_LEVEL
             // Health checks
             .route("/health", web::get().to(health_check))
             .route("/health/db", web::get().to(health_check_db))
+            // Prometheus metrics (admin only)
+            .service(
+                web::resource("/metrics")
+                    .wrap(middleware::AuthMiddleware)
+                    .route(web::get().to(get_metrics)),
+            )
             // Config and version
             .route("/api/config", web::get().to(get_app_config))
             .route("/api/version", web::get().to(get_app_version))
@@ -589,6 +645,8 @@ async fn main() -> anyhow::Result<()> {
             .service(web::scope("/api/v1").configure(create_routes))
             // OpenAI compatible API
             .service(web::scope("/openai").configure(routes::openai::create_routes))
+            // Native Ollama API
+            .service(web::scope("/ollama").configure(routes::ollama::create_routes))
             // Chat endpoints (legacy routes without /v1 prefix)
             .service(
                 web::resource("/api/chat/completions")
@@ -615,11 +673,20 @@ async fn main() -> anyhow::Result<()> {
             // Embeddings endpoint (legacy route without /v1 prefix)
             .route("/api/embeddings", web::post().to(embeddings))
             // Task management
-            .route("/api/tasks", web::get().to(list_tasks))
-            .route("/api/tasks/stop/{task_id}", web::post().to(stop_task))
-            .route(
-                "/api/tasks/chat/{chat_id}",
-                web::get().to(list_tasks_by_chat),
+            .service(
+                web::resource("/api/tasks")
+                    .wrap(middleware::AuthMiddleware)
+                    .route(web::get().to(list_tasks)),
+            )
+            .service(
+                web::resource("/api/tasks/stop/{task_id}")
+                    .wrap(middleware::AuthMiddleware)
+                    .route(web::post().to(stop_task)),
+            )
+            .service(
+                web::resource("/api/tasks/chat/{chat_id}")
+                    .wrap(middleware::AuthMiddleware)
+                    .route(web::get().to(list_tasks_by_chat)),
             )
             // Usage and webhook
             .route("/api/usage", web::get().to(get_usage))
@@ -801,7 +868,7 @@ async fn get_app_config(state: web::Data<AppState>, req: HttpRequest) -> HttpRes
             "enable_signup": config.enable_signup,
             "enable_login_form": config.enable_login_form,
             "enable_api_key": config.enable_api_key,
-            "enable_ldap": false,
+            "enable_ldap": config.enable_ldap,
             "enable_websocket": config.enable_websocket_support,
             "enable_version_update_check": config.enable_version_update_check,
             "enable_signup_password_confirmation": false,
@@ -1213,28 +1280,42 @@ async fn socketio_auth(
 }
 
 async fn chat_completed(
-    _state: web::Data<AppState>,
-    _payload: web::Json<serde_json::Value>,
-) -> HttpResponse {
+    state: web::Data<AppState>,
+    payload: web::Json<serde_json::Value>,
+    auth_user: middleware::AuthUser,
+) -> Result<HttpResponse, crate::error::AppError> {
     use serde_json::json;
 
-    // TODO: Implement chat completed handler
-    HttpResponse::Ok().json(json!({
-        "status": "ok"
-    }))
+    // Run the outlet hooks of the chat's filter Functions over the finished chat
+    let form_data = payload.into_inner();
+    let model_item = form_data.get("model_item").cloned().unwrap_or(json!({}));
+    let metadata = json!({
+        "chat_id": form_data.get("chat_id"),
+        "message_id": form_data.get("id"),
+        "session_id": form_data.get("session_id"),
+        "model": form_data.get("model"),
+    });
+    let filters = utils::functions::FunctionFilters::load(
+        &state,
+        &utils::functions::request_filter_ids(&form_data, &model_item),
+        &auth_user.user,
+        &metadata,
+    )
+    .await?;
+    let form_data = filters.apply(plugins::Hook::Outlet, form_data).await?;
+
+    Ok(HttpResponse::Ok().json(form_data))
 }
 
 async fn chat_action(
-    _state: web::Data<AppState>,
-    _action_id: web::Path<String>,
-    _payload: web::Json<serde_json::Value>,
-) -> HttpResponse {
-    use serde_json::json;
-
-    // TODO: Implement chat action handler
-    HttpResponse::Ok().json(json!({
-        "status": "ok"
-    }))
+    state: web::Data<AppState>,
+    action_id: web::Path<String>,
+    payload: web::Json<serde_json::Value>,
+    auth_user: middleware::AuthUser,
+) -> Result<HttpResponse, crate::error::AppError> {
+    let result =
+        utils::functions::run_action(&state, &action_id, &payload, &auth_user.user).await?;
+    Ok(HttpResponse::Ok().json(result))
 }
 
 // Embeddings endpoint
@@ -1368,34 +1449,76 @@ async fn embeddings(
     }
 }
 
+// Prometheus metrics
+async fn get_metrics(
+    state: web::Data<AppState>,
+    auth_user: middleware::AuthUser,
+) -> Result<HttpResponse, crate::error::AppError> {
+    if auth_user.user.role != "admin" {
+        return Err(crate::error::AppError::Forbidden(
+            "Admin access required".to_string(),
+        ));
+    }
+
+    let socketio = state
+        .socketio_handler
+        .as_ref()
+        .map(|handler| handler.prometheus_exporter());
+    let body = utils::metrics::render(socketio)
+        .await
+        .map_err(|e| crate::error::AppError::InternalServerError(e.to_string()))?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4; charset=utf-8")
+        .body(body))
+}
+
 // Task management
-async fn list_tasks(_state: web::Data<AppState>) -> HttpResponse {
+async fn list_tasks(
+    state: web::Data<AppState>,
+    auth_user: middleware::AuthUser,
+) -> Result<HttpResponse, crate::error::AppError> {
     use serde_json::json;
 
-    // TODO: Implement task listing from Redis
-    HttpResponse::Ok().json(json!({
-        "tasks": []
-    }))
+    if auth_user.user.role != "admin" {
+        return Err(crate::error::AppError::Forbidden(
+            "Admin access required".to_string(),
+        ));
+    }
+
+    let tasks = state.task_manager.list_tasks().await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "tasks": tasks
+    })))
 }
 
 async fn stop_task(
     state: web::Data<AppState>,
+    auth_user: middleware::AuthUser,
     task_id: web::Path<String>,
 ) -> Result<HttpResponse, crate::error::AppError> {
     use serde_json::json;
 
-    // Emit cancel event through Socket.IO
-    // The task_id is the chat_id in our Socket.IO streaming implementation
-    if let Some(ref socket_state) = state.socket_state {
-        // Send cancel event to all connected clients for this chat
-        // This will trigger the frontend to stop listening for streaming events
-        tracing::info!("Sending stop signal for chat: {}", task_id.as_str());
+    let task = state
+        .task_manager
+        .get_task(&task_id)
+        .await
+        .ok_or_else(|| crate::error::AppError::NotFound("Task not found".to_string()))?;
+    if auth_user.user.role != "admin" && task.user_id.as_deref() != Some(&auth_user.user.id) {
+        return Err(crate::error::AppError::NotFound(
+            "Task not found".to_string(),
+        ));
+    }
+
+    let task = state.task_manager.stop_task(&task_id).await?;
+    tracing::info!("Stopped task {} for chat {:?}", task_id, task.item_id);
 
-        // Emit a chat:tasks:cancel event that the frontend listens for
+    // Tell the chat's clients that generation has stopped
+    if let (Some(socket_state), Some(user_id)) = (&state.socket_state, task.user_id) {
         let event_emitter = crate::socket::get_event_emitter(
             socket_state.clone(),
-            "system".to_string(), // system user for admin actions
-            Some(task_id.to_string()),
+            user_id,
+            task.item_id,
             None,
             None,
         );
@@ -1408,20 +1531,31 @@ async fn stop_task(
 
     Ok(HttpResponse::Ok().json(json!({
         "status": true,
-        "message": format!("Stop signal sent for {}", task_id.as_str())
+        "message": format!("Stopped task {}", task_id.as_str())
     })))
 }
 
 async fn list_tasks_by_chat(
-    _state: web::Data<AppState>,
-    _chat_id: web::Path<String>,
-) -> HttpResponse {
+    state: web::Data<AppState>,
+    auth_user: middleware::AuthUser,
+    chat_id: web::Path<String>,
+) -> Result<HttpResponse, crate::error::AppError> {
     use serde_json::json;
 
-    // TODO: Implement task listing by chat ID
-    HttpResponse::Ok().json(json!({
-        "task_ids": []
-    }))
+    let chat = services::chat::ChatService::new(&state.db)
+        .get_chat_by_id(&chat_id)
+        .await?
+        .ok_or_else(|| crate::error::AppError::NotFound("Chat not found".to_string()))?;
+    if auth_user.user.role != "admin" && chat.user_id != auth_user.user.id {
+        return Err(crate::error::AppError::NotFound(
+            "Chat not found".to_string(),
+        ));
+    }
+
+    let task_ids = state.task_manager.list_tasks_by_item(&chat_id).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "task_ids": task_ids
+    })))
 }
 
 // Usage and webhook
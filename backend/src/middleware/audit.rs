@@ -1,2 +1,444 @@
-// TODO: Implement audit middleware for actix-web
-// This middleware will log important actions for audit purposes
+//! Audit logging of state-changing API requests
+//!
+//! Who did what is appended to the `audit_log` table: sign-ins and sign-outs,
+//! and every `POST`, `PUT`, `PATCH` and `DELETE` under `/api` outside
+//! `AUDIT_EXCLUDED_PATHS`. `AUDIT_LOG_LEVEL` decides how much is kept, like the
+//! Python backend: `NONE`, `METADATA`, `REQUEST` or `REQUEST_RESPONSE`. Bodies
+//! are only kept for JSON, with secrets redacted and their size capped at
+//! `MAX_BODY_LOG_SIZE`.
+
+use actix_web::{
+    body::{self, BoxBody},
+    dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform},
+    http::{header, Method},
+    web, Error, HttpMessage, HttpRequest,
+};
+use bytes::{Bytes, BytesMut};
+use futures_util::{future::LocalBoxFuture, StreamExt};
+use serde_json::Value;
+use std::future::{ready, Ready};
+use std::rc::Rc;
+use uuid::Uuid;
+
+use crate::db::Database;
+use crate::middleware::auth::AuthUser;
+use crate::models::audit::AuditLog;
+use crate::services::audit::AuditService;
+use crate::utils::time::current_timestamp_seconds;
+use crate::AppState;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Bytes read past `MAX_BODY_LOG_SIZE`, so that bodies slightly over it can
+/// still be redacted before they are truncated
+const BODY_READ_MARGIN: usize = 64 * 1024;
+
+/// Auth endpoints that are audited whatever their method or the exclusions
+const AUTH_EVENTS: &[&str] = &[
+    "/auths/signin",
+    "/auths/signup",
+    "/auths/signout",
+    "/auths/ldap",
+];
+
+/// How much of a request the audit log keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AuditLevel {
+    None,
+    Metadata,
+    Request,
+    RequestResponse,
+}
+
+impl AuditLevel {
+    pub fn parse(level: &str) -> Self {
+        match level.trim().to_uppercase().as_str() {
+            "METADATA" => AuditLevel::Metadata,
+            "REQUEST" => AuditLevel::Request,
+            "REQUEST_RESPONSE" => AuditLevel::RequestResponse,
+            _ => AuditLevel::None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditLevel::None => "NONE",
+            AuditLevel::Metadata => "METADATA",
+            AuditLevel::Request => "REQUEST",
+            AuditLevel::RequestResponse => "REQUEST_RESPONSE",
+        }
+    }
+}
+
+/// Path below `/api/v1` or `/api`, or `None` outside the API
+fn api_path(path: &str) -> Option<&str> {
+    let path = path.trim_end_matches('/');
+    path.strip_prefix("/api/v1")
+        .or_else(|| path.strip_prefix("/api"))
+        .filter(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Whether `path` is `prefix` or below it
+fn under(path: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    path.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+fn is_auth_event(path: &str) -> bool {
+    api_path(path).is_some_and(|path| AUTH_EVENTS.iter().any(|event| under(path, event)))
+}
+
+/// Whether a request is written to the audit log
+///
+/// Share links are always audited, even below an excluded path such as
+/// `/chats`.
+fn is_audited(method: &Method, path: &str, excluded_paths: &[String]) -> bool {
+    let Some(api_path) = api_path(path) else {
+        return false;
+    };
+    if is_auth_event(path) {
+        return true;
+    }
+    if !matches!(
+        *method,
+        Method::POST | Method::PUT | Method::PATCH | Method::DELETE
+    ) {
+        return false;
+    }
+    api_path.ends_with("/share")
+        || !excluded_paths
+            .iter()
+            .any(|excluded| under(api_path, excluded))
+}
+
+/// First segment below `/api/v1`, such as `models` or `auths`
+fn resource(path: &str) -> Option<String> {
+    api_path(path)?
+        .split('/')
+        .find(|segment| !segment.is_empty())
+        .map(str::to_string)
+}
+
+fn is_secret_key(key: &str) -> bool {
+    let key = key.to_lowercase();
+    [
+        "password",
+        "secret",
+        "token",
+        "key",
+        "authorization",
+        "cookie",
+    ]
+    .iter()
+    .any(|word| key.contains(word))
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if is_secret_key(key) && !value.is_null() {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// A JSON body as stored in the audit log, with secrets redacted and cut to
+/// `max_size` bytes
+fn redact_body(body: &[u8], max_size: usize) -> String {
+    let mut body = match serde_json::from_slice::<Value>(body) {
+        Ok(mut value) => {
+            redact(&mut value);
+            value.to_string()
+        }
+        Err(_) => String::from_utf8_lossy(body).into_owned(),
+    };
+    if body.len() > max_size {
+        let mut end = max_size;
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+        body.truncate(end);
+        body.push_str("...[truncated]");
+    }
+    body
+}
+
+fn is_json(headers: &header::HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("json"))
+}
+
+/// Read the request body for the log, stopping once it passes `limit` bytes
+///
+/// The payload is put back as the bytes read followed by the untouched rest
+/// of the stream. Longer bodies give `None`: cut-off JSON can't be redacted.
+async fn read_payload(req: &mut ServiceRequest, limit: usize) -> Result<Option<Bytes>, Error> {
+    let mut payload = req.take_payload();
+    let mut body = BytesMut::new();
+    let mut complete = true;
+    while let Some(chunk) = payload.next().await {
+        body.extend_from_slice(&chunk?);
+        if body.len() > limit {
+            complete = false;
+            break;
+        }
+    }
+    let body = body.freeze();
+    let head = futures_util::stream::once(ready(Ok(body.clone())));
+    req.set_payload(Payload::Stream {
+        payload: Box::pin(head.chain(payload)),
+    });
+    Ok(complete.then_some(body))
+}
+
+/// Middleware that appends state-changing API requests to the audit log
+pub struct AuditLogger;
+
+impl<S, B> Transform<S, ServiceRequest> for AuditLogger
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = AuditLoggerMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuditLoggerMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct AuditLoggerMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for AuditLoggerMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+
+        Box::pin(async move {
+            let settings = req.app_data::<web::Data<AppState>>().and_then(|state| {
+                let config = state.config.read().unwrap();
+                let level = AuditLevel::parse(&config.audit_log_level);
+                (level != AuditLevel::None
+                    && is_audited(req.method(), req.path(), &config.audit_excluded_paths))
+                .then(|| (state.db.clone(), level, config.max_body_log_size))
+            });
+            let Some((db, level, max_body_size)) = settings else {
+                return service.call(req).await.map(|res| res.map_into_boxed_body());
+            };
+
+            // Sign-ins have no user yet, so their body is read for the email
+            let auth_event = is_auth_event(req.path());
+            let request_body =
+                if (level >= AuditLevel::Request || auth_event) && is_json(req.headers()) {
+                    read_payload(&mut req, max_body_size + BODY_READ_MARGIN).await?
+                } else {
+                    None
+                };
+
+            let http_req = req.request().clone();
+            let result = service.call(req).await;
+
+            let (status_code, response_body, res) = match result {
+                Ok(res) => {
+                    let status_code = res.status().as_u16();
+                    if level == AuditLevel::RequestResponse && is_json(res.headers()) {
+                        let (request, response) = res.into_parts();
+                        let (response, body) = response.into_parts();
+                        let bytes = body::to_bytes(body)
+                            .await
+                            .map_err(|e| actix_web::error::ErrorInternalServerError(e.into()))?;
+                        let response = response.set_body(BoxBody::new(bytes.clone()));
+                        (
+                            status_code,
+                            Some(bytes),
+                            Ok(ServiceResponse::new(request, response)),
+                        )
+                    } else {
+                        (status_code, None, Ok(res.map_into_boxed_body()))
+                    }
+                }
+                Err(e) => (e.as_response_error().status_code().as_u16(), None, Err(e)),
+            };
+
+            let entry = build_entry(
+                &http_req,
+                level,
+                status_code,
+                request_body.as_deref(),
+                response_body.as_deref(),
+                max_body_size,
+            );
+            record(db, entry);
+
+            res
+        })
+    }
+}
+
+fn build_entry(
+    req: &HttpRequest,
+    level: AuditLevel,
+    status_code: u16,
+    request_body: Option<&[u8]>,
+    response_body: Option<&[u8]>,
+    max_body_size: usize,
+) -> AuditLog {
+    let auth_user = req.extensions().get::<AuthUser>().cloned();
+    let (user_id, user_email, user_role) = match auth_user {
+        Some(auth_user) => (
+            Some(auth_user.user.id),
+            Some(auth_user.user.email),
+            Some(auth_user.user.role),
+        ),
+        None => {
+            let email = request_body
+                .and_then(|body| serde_json::from_slice::<Value>(body).ok())
+                .and_then(|body| {
+                    body.get("email")
+                        .or_else(|| body.get("user"))
+                        .and_then(|email| email.as_str())
+                        .map(|email| email.to_lowercase())
+                });
+            (None, email, None)
+        }
+    };
+    let keep_request = level >= AuditLevel::Request;
+
+    AuditLog {
+        id: Uuid::new_v4().to_string(),
+        user_id,
+        user_email,
+        user_role,
+        method: req.method().to_string(),
+        path: req.path().to_string(),
+        route: req.match_pattern(),
+        resource: resource(req.path()),
+        status_code: status_code as i64,
+        source_ip: req
+            .connection_info()
+            .realip_remote_addr()
+            .map(str::to_string),
+        user_agent: req
+            .headers()
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string),
+        audit_level: level.as_str().to_string(),
+        request_body: request_body
+            .filter(|_| keep_request)
+            .map(|body| redact_body(body, max_body_size)),
+        response_body: response_body.map(|body| redact_body(body, max_body_size)),
+        created_at: current_timestamp_seconds(),
+    }
+}
+
+/// Write an entry without holding up the response
+fn record(db: Database, entry: AuditLog) {
+    actix_web::rt::spawn(async move {
+        if let Err(e) = AuditService::new(&db).insert(&entry).await {
+            tracing::error!(
+                "Failed to write audit log entry for {} {}: {}",
+                entry.method,
+                entry.path,
+                e
+            );
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audited_requests() {
+        let excluded = vec!["/chats".to_string(), "/folders".to_string()];
+        assert!(is_audited(
+            &Method::POST,
+            "/api/v1/models/create",
+            &excluded
+        ));
+        assert!(is_audited(&Method::DELETE, "/api/v1/files/abc/", &excluded));
+        assert!(is_audited(&Method::GET, "/api/v1/auths/signout", &excluded));
+        assert!(is_audited(
+            &Method::POST,
+            "/api/v1/chats/abc/share",
+            &excluded
+        ));
+        assert!(!is_audited(&Method::GET, "/api/v1/models", &excluded));
+        assert!(!is_audited(&Method::POST, "/api/v1/chats/new", &excluded));
+        assert!(!is_audited(&Method::POST, "/api/v1/folders", &excluded));
+        assert!(!is_audited(&Method::POST, "/apiary", &excluded));
+        assert!(!is_audited(&Method::POST, "/oauth/google/login", &excluded));
+
+        assert_eq!(
+            resource("/api/v1/users/abc/update").as_deref(),
+            Some("users")
+        );
+        assert_eq!(resource("/api/chat/completions").as_deref(), Some("chat"));
+        assert_eq!(
+            AuditLevel::parse("request_response"),
+            AuditLevel::RequestResponse
+        );
+        assert_eq!(AuditLevel::parse("bogus"), AuditLevel::None);
+    }
+
+    #[test]
+    fn test_bodies_are_redacted_and_truncated() {
+        let body = br#"{"email":"a@example.com","password":"hunter2","config":{"OPENAI_API_KEYS":["sk-1"],"name":"x"}}"#;
+        let redacted: Value = serde_json::from_str(&redact_body(body, 1024)).unwrap();
+        assert_eq!(redacted["email"], "a@example.com");
+        assert_eq!(redacted["password"], REDACTED);
+        assert_eq!(redacted["config"]["OPENAI_API_KEYS"], REDACTED);
+        assert_eq!(redacted["config"]["name"], "x");
+
+        let truncated = redact_body("\"ééééé\"".as_bytes(), 4);
+        assert_eq!(truncated, "\"é...[truncated]");
+    }
+
+    #[actix_web::test]
+    async fn test_long_bodies_are_passed_through_unread() {
+        for (body, logged) in [("{\"a\":1}", true), ("{\"password\":\"hunter2\"}", false)] {
+            let mut req = actix_web::test::TestRequest::post()
+                .set_payload(body)
+                .to_srv_request();
+            let read = read_payload(&mut req, 8).await.unwrap();
+            assert_eq!(read.is_some(), logged);
+
+            let mut payload = req.take_payload();
+            let mut forwarded = BytesMut::new();
+            while let Some(chunk) = payload.next().await {
+                forwarded.extend_from_slice(&chunk.unwrap());
+            }
+            assert_eq!(forwarded, body.as_bytes());
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    Error,
+};
+use futures_util::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+use std::time::Instant;
+
+use crate::utils::metrics::metrics;
+
+/// Middleware that records the latency of every request
+///
+/// Requests are labelled with their matched route pattern (such as
+/// `/api/v1/chats/{id}`) rather than their path, so the number of series stays
+/// bounded. Requests that match no route are labelled `unmatched`.
+pub struct HttpMetrics;
+
+impl<S, B> Transform<S, ServiceRequest> for HttpMetrics
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = HttpMetricsMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(HttpMetricsMiddleware { service }))
+    }
+}
+
+pub struct HttpMetricsMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for HttpMetricsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let started = Instant::now();
+        let method = req.method().to_string();
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let res = fut.await;
+            let (route, status) = match &res {
+                Ok(res) => (res.request().match_pattern(), res.status().as_u16()),
+                Err(e) => (None, e.as_response_error().status_code().as_u16()),
+            };
+            metrics().observe_http_request(
+                &method,
+                route.as_deref().unwrap_or("unmatched"),
+                status,
+                started.elapsed().as_secs_f64(),
+            );
+            res
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, web, App, HttpResponse};
+
+    #[actix_web::test]
+    async fn test_requests_are_labelled_by_route() {
+        let app = test::init_service(App::new().wrap(HttpMetrics).route(
+            "/metrics-test/{id}",
+            web::get().to(|| async { HttpResponse::NotFound().finish() }),
+        ))
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/metrics-test/42")
+            .to_request();
+        test::call_service(&app, req).await;
+
+        let output = crate::utils::metrics::render(None).await.unwrap();
+        assert!(output.contains(
+            r#"http_request_duration_seconds_count{method="GET",route="/metrics-test/{id}",status="404"} 1"#
+        ));
+    }
+}
@@ -1,9 +1,13 @@
 pub mod audit;
 pub mod auth;
 pub mod code_interpreter;
+pub mod metrics;
 pub mod rate_limit;
 pub mod request_id;
 pub mod security_headers;
 
+pub use audit::AuditLogger;
 pub use auth::*;
+pub use metrics::HttpMetrics;
+pub use rate_limit::{HttpRateLimiter, RateLimit};
 pub use security_headers::SecurityHeaders;
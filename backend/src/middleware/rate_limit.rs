@@ -1,2 +1,393 @@
-// TODO: Implement rate limiting middleware for actix-web
-// This middleware will limit the number of requests per time window
+//! Rate limiting of HTTP API requests
+//!
+//! Sign-in, sign-up and LDAP login are limited per client IP; every other
+//! request under `/api`, `/openai` and `/ollama` is limited per user or API
+//! key, falling back to the client IP when the request carries no valid
+//! credentials. The client IP is the peer address unless
+//! `RATE_LIMIT_TRUST_PROXY` allows forwarding headers. Counts are kept in
+//! process with `governor`, or in Redis when `RATE_LIMIT_STORAGE=redis` so
+//! that all replicas share them.
+
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{self, HeaderValue},
+    web, Error, ResponseError,
+};
+use futures_util::future::LocalBoxFuture;
+use governor::{clock::Clock, clock::DefaultClock, DefaultKeyedRateLimiter, Quota};
+use sha2::{Digest, Sha256};
+use std::future::{ready, Ready};
+use std::num::NonZeroU32;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::services::user::UserService;
+use crate::utils::auth::verify_jwt;
+use crate::utils::time::current_timestamp_seconds;
+use crate::AppState;
+
+/// Keys kept by an in-process limiter before idle ones are dropped
+const MAX_MEMORY_KEYS: usize = 10_000;
+
+const AUTH_PATHS: &[&str] = &[
+    "/api/v1/auths/signin",
+    "/api/v1/auths/signup",
+    "/api/v1/auths/ldap",
+];
+
+const API_PREFIXES: &[&str] = &["/api", "/openai", "/ollama"];
+
+/// Which limit a request counts against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bucket {
+    Auth,
+    Api,
+}
+
+impl Bucket {
+    fn for_path(path: &str) -> Option<Self> {
+        let path = path.trim_end_matches('/');
+        let under = |prefix: &str| {
+            path.strip_prefix(prefix)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        };
+        if AUTH_PATHS.contains(&path) {
+            Some(Bucket::Auth)
+        } else if API_PREFIXES.iter().any(|prefix| under(prefix)) {
+            Some(Bucket::Api)
+        } else {
+            None
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Bucket::Auth => "auth",
+            Bucket::Api => "api",
+        }
+    }
+}
+
+enum Store {
+    Memory {
+        auth: Option<DefaultKeyedRateLimiter<String>>,
+        api: Option<DefaultKeyedRateLimiter<String>>,
+    },
+    Redis(deadpool_redis::Pool),
+}
+
+/// Request counts for [`RateLimit`], shared by all workers
+#[derive(Clone)]
+pub struct HttpRateLimiter {
+    window: Duration,
+    auth_requests: u32,
+    api_requests: u32,
+    trust_proxy: bool,
+    store: Arc<Store>,
+}
+
+fn memory_limiter(requests: u32, window: Duration) -> Option<DefaultKeyedRateLimiter<String>> {
+    let burst = NonZeroU32::new(requests)?;
+    let quota = Quota::with_period(window / requests)?.allow_burst(burst);
+    Some(DefaultKeyedRateLimiter::keyed(quota))
+}
+
+impl HttpRateLimiter {
+    /// The limiter configured by `ENABLE_RATE_LIMIT`, or `None` when rate
+    /// limiting is off
+    pub fn from_config(config: &Config, redis: Option<deadpool_redis::Pool>) -> Option<Self> {
+        if !config.enable_rate_limit {
+            return None;
+        }
+
+        let window = Duration::from_secs(config.rate_limit_window.max(1));
+        let store = match (config.rate_limit_storage.as_str(), redis) {
+            ("redis", Some(pool)) => Store::Redis(pool),
+            (storage, _) => {
+                if storage == "redis" {
+                    tracing::warn!(
+                        "RATE_LIMIT_STORAGE is redis but Redis is not enabled; counting requests in memory"
+                    );
+                }
+                Store::Memory {
+                    auth: memory_limiter(config.rate_limit_auth_requests, window),
+                    api: memory_limiter(config.rate_limit_api_requests, window),
+                }
+            }
+        };
+
+        Some(Self {
+            window,
+            auth_requests: config.rate_limit_auth_requests,
+            api_requests: config.rate_limit_api_requests,
+            trust_proxy: config.rate_limit_trust_proxy,
+            store: Arc::new(store),
+        })
+    }
+
+    /// Count a request against `bucket` for `key`; the error is how long the
+    /// client should wait before retrying
+    async fn check(&self, bucket: Bucket, key: &str) -> Result<(), Duration> {
+        match self.store.as_ref() {
+            Store::Memory { auth, api } => {
+                let limiter = match bucket {
+                    Bucket::Auth => auth,
+                    Bucket::Api => api,
+                };
+                let Some(limiter) = limiter else {
+                    return Ok(());
+                };
+                if limiter.len() > MAX_MEMORY_KEYS {
+                    limiter.retain_recent();
+                    limiter.shrink_to_fit();
+                }
+                limiter
+                    .check_key(&key.to_string())
+                    .map_err(|not_until| not_until.wait_time_from(DefaultClock::default().now()))
+            }
+            Store::Redis(pool) => self.check_redis(pool, bucket, key).await,
+        }
+    }
+
+    /// Fixed-window counting in Redis; requests are let through when Redis is
+    /// unavailable
+    async fn check_redis(
+        &self,
+        pool: &deadpool_redis::Pool,
+        bucket: Bucket,
+        key: &str,
+    ) -> Result<(), Duration> {
+        let limit = match bucket {
+            Bucket::Auth => self.auth_requests,
+            Bucket::Api => self.api_requests,
+        };
+        if limit == 0 {
+            return Ok(());
+        }
+
+        let window = self.window.as_secs() as i64;
+        let now = current_timestamp_seconds();
+        let window_start = now - now.rem_euclid(window);
+        let redis_key = format!(
+            "open-webui:rate_limit:{}:{}:{}",
+            bucket.as_str(),
+            key,
+            window_start
+        );
+
+        let count: Result<(u64,), redis::RedisError> = match pool.get().await {
+            Ok(mut conn) => {
+                redis::pipe()
+                    .atomic()
+                    .incr(&redis_key, 1)
+                    .expire(&redis_key, window)
+                    .ignore()
+                    .query_async(&mut conn)
+                    .await
+            }
+            Err(e) => {
+                tracing::warn!("Rate limiter could not reach Redis: {}", e);
+                return Ok(());
+            }
+        };
+        match count {
+            Ok((count,)) if count > limit as u64 => Err(Duration::from_secs(
+                (window_start + window - now).max(1) as u64,
+            )),
+            Ok(_) => Ok(()),
+            Err(e) => {
+                tracing::warn!("Rate limiter could not update Redis: {}", e);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The client IP, from forwarding headers only when they are trusted
+fn client_ip(req: &ServiceRequest, trust_proxy: bool) -> String {
+    let ip = if trust_proxy {
+        req.connection_info()
+            .realip_remote_addr()
+            .map(str::to_string)
+    } else {
+        req.peer_addr().map(|addr| addr.ip().to_string())
+    };
+    format!("ip:{}", ip.as_deref().unwrap_or("unknown"))
+}
+
+/// Who a request is counted for: the user of a valid session token, a hash
+/// of a valid API key, or else the client IP
+async fn client_key(
+    req: &ServiceRequest,
+    state: &AppState,
+    bucket: Bucket,
+    trust_proxy: bool,
+) -> String {
+    if bucket == Bucket::Auth {
+        return client_ip(req, trust_proxy);
+    }
+
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_string)
+        .or_else(|| req.cookie("token").map(|c| c.value().to_string()));
+    let (enable_api_key, secret) = {
+        let config = state.config.read().unwrap();
+        (config.enable_api_key, config.webui_secret_key.clone())
+    };
+    match token {
+        Some(token) if token.starts_with("sk-") => {
+            let valid = enable_api_key
+                && matches!(
+                    UserService::new(&state.db)
+                        .get_user_by_api_key(&token)
+                        .await,
+                    Ok(Some(_))
+                );
+            if valid {
+                format!("key:{:x}", Sha256::digest(token.as_bytes()))
+            } else {
+                client_ip(req, trust_proxy)
+            }
+        }
+        Some(token) => match verify_jwt(&token, &secret) {
+            Ok(claims) => format!("user:{}", claims.sub),
+            Err(_) => client_ip(req, trust_proxy),
+        },
+        None => client_ip(req, trust_proxy),
+    }
+}
+
+/// Middleware that answers `429 Too Many Requests` once a client exceeds its
+/// rate limit
+pub struct RateLimit;
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RateLimitMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+
+        Box::pin(async move {
+            let limited = req.app_data::<web::Data<AppState>>().and_then(|state| {
+                let limiter = state.rate_limiter.clone()?;
+                let bucket = Bucket::for_path(req.path())?;
+                Some((state.clone(), limiter, bucket))
+            });
+
+            if let Some((state, limiter, bucket)) = limited {
+                let key = client_key(&req, &state, bucket, limiter.trust_proxy).await;
+                if let Err(wait) = limiter.check(bucket, &key).await {
+                    let retry_after = wait.as_secs().max(1);
+                    tracing::debug!("Rate limited {} on {} requests", key, bucket.as_str());
+                    let mut response = AppError::TooManyRequests(format!(
+                        "Rate limit exceeded. Try again in {} seconds.",
+                        retry_after
+                    ))
+                    .error_response();
+                    response
+                        .headers_mut()
+                        .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+                    return Ok(req.into_response(response).map_into_right_body());
+                }
+            }
+
+            service
+                .call(req)
+                .await
+                .map(ServiceResponse::map_into_left_body)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buckets() {
+        assert_eq!(Bucket::for_path("/api/v1/auths/signin"), Some(Bucket::Auth));
+        assert_eq!(
+            Bucket::for_path("/api/v1/auths/signin/"),
+            Some(Bucket::Auth)
+        );
+        assert_eq!(Bucket::for_path("/api/v1/auths/"), Some(Bucket::Api));
+        assert_eq!(Bucket::for_path("/api/chat/completions"), Some(Bucket::Api));
+        assert_eq!(Bucket::for_path("/ollama/api/tags"), Some(Bucket::Api));
+        assert_eq!(Bucket::for_path("/apiary"), None);
+        assert_eq!(Bucket::for_path("/static/favicon.png"), None);
+    }
+
+    #[test]
+    fn test_client_ip_ignores_forwarding_headers_unless_trusted() {
+        let req = actix_web::test::TestRequest::get()
+            .peer_addr("10.0.0.1:4000".parse().unwrap())
+            .insert_header(("X-Forwarded-For", "203.0.113.9"))
+            .to_srv_request();
+        assert_eq!(client_ip(&req, false), "ip:10.0.0.1");
+        assert_eq!(client_ip(&req, true), "ip:203.0.113.9");
+    }
+
+    #[tokio::test]
+    async fn test_memory_limits_per_key() {
+        let mut config = Config::from_env().unwrap();
+        config.enable_rate_limit = true;
+        config.rate_limit_window = 60;
+        config.rate_limit_auth_requests = 2;
+        config.rate_limit_api_requests = 0;
+        let limiter = HttpRateLimiter::from_config(&config, None).unwrap();
+
+        assert!(limiter.check(Bucket::Auth, "ip:10.0.0.1").await.is_ok());
+        assert!(limiter.check(Bucket::Auth, "ip:10.0.0.1").await.is_ok());
+        let wait = limiter
+            .check(Bucket::Auth, "ip:10.0.0.1")
+            .await
+            .unwrap_err();
+        assert!(wait > Duration::ZERO && wait <= Duration::from_secs(60));
+        assert!(limiter.check(Bucket::Auth, "ip:10.0.0.2").await.is_ok());
+
+        // A limit of zero turns the bucket off
+        for _ in 0..10 {
+            assert!(limiter.check(Bucket::Api, "user:1").await.is_ok());
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// One entry of the append-only audit log
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AuditLog {
+    pub id: String,
+    pub user_id: Option<String>,
+    pub user_email: Option<String>,
+    pub user_role: Option<String>,
+    pub method: String,
+    pub path: String,
+    /// Matched route pattern, such as `/api/v1/models/model/update`
+    pub route: Option<String>,
+    /// API area the request touched, such as `models` or `auths`
+    pub resource: Option<String>,
+    pub status_code: i64,
+    pub source_ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub audit_level: String,
+    pub request_body: Option<String>,
+    pub response_body: Option<String>,
+    pub created_at: i64,
+}
+
+/// Filters for querying the audit log; unset fields match everything
+#[derive(Debug, Default, Clone)]
+pub struct AuditLogFilter {
+    pub user_id: Option<String>,
+    pub resource: Option<String>,
+    pub method: Option<String>,
+    pub status_code: Option<i64>,
+    /// Only entries created at or after this Unix timestamp
+    pub start: Option<i64>,
+    /// Only entries created before this Unix timestamp
+    pub end: Option<i64>,
+    /// Substring of the request path
+    pub query: Option<String>,
+}
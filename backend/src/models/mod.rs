@@ -1,3 +1,4 @@
+pub mod audit;
 pub mod auth;
 pub mod channel;
 pub mod chat;
@@ -14,6 +15,7 @@ pub mod model;
 pub mod note;
 pub mod oauth_session;
 pub mod prompt;
+pub mod quota;
 pub mod tag;
 pub mod tool;
 pub mod tool_runtime;
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// Chat completion limits for a user or for every member of a group
+///
+/// A quota without `model_id` counts usage of all models together.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Quota {
+    pub id: String,
+    /// `user` or `group`
+    pub target_type: String,
+    pub target_id: String,
+    pub model_id: Option<String>,
+    pub requests_per_day: Option<i64>,
+    pub tokens_per_month: Option<i64>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct QuotaForm {
+    pub target_type: String,
+    pub target_id: String,
+    #[serde(default)]
+    pub model_id: Option<String>,
+    #[serde(default)]
+    pub requests_per_day: Option<i64>,
+    #[serde(default)]
+    pub tokens_per_month: Option<i64>,
+}
+
+/// The quota a chat completion request would exceed
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaExceeded {
+    pub quota_id: String,
+    pub target_type: String,
+    pub model_id: Option<String>,
+    /// `requests_per_day` or `tokens_per_month`
+    pub limit_type: String,
+    pub limit: i64,
+    pub used: i64,
+    /// Unix timestamp at which the usage counted against the limit resets
+    pub resets_at: i64,
+}
+
+impl QuotaExceeded {
+    pub fn detail(&self) -> String {
+        let (what, period) = match self.limit_type.as_str() {
+            "requests_per_day" => ("requests", "day"),
+            _ => ("tokens", "month"),
+        };
+        let model = match &self.model_id {
+            Some(model_id) => format!(" for model {}", model_id),
+            None => String::new(),
+        };
+        format!(
+            "Usage quota exceeded: {} of {} {} per {}{} used",
+            self.used, self.limit, what, period, model
+        )
+    }
+}
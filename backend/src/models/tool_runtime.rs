@@ -127,15 +127,107 @@ pub struct McpServerConfig {
     pub auth_type: Option<String>,
     #[serde(default)]
     pub auth_token: Option<String>,
+    /// `streamable_http` (default) or `sse`
+    #[serde(default)]
+    pub transport: Option<String>,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
 }
 
 /// Environment configuration
+///
+/// `required` and `optional` variables are the tool's Valves, set by an
+/// admin; `user` variables are UserValves each user sets for themselves.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct EnvironmentConfig {
     #[serde(default)]
-    pub required: Vec<String>,
+    pub required: Vec<EnvVar>,
+    #[serde(default)]
+    pub optional: Vec<EnvVar>,
+    #[serde(default)]
+    pub user: Vec<EnvVar>,
+}
+
+/// An environment variable, given by name or with details
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EnvVar {
+    Name(String),
+    Spec(EnvVarSpec),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvVarSpec {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub default: Option<String>,
+    /// Masked when the valves are read back
     #[serde(default)]
-    pub optional: Vec<String>,
+    pub secret: bool,
+}
+
+impl EnvVar {
+    pub fn name(&self) -> &str {
+        match self {
+            EnvVar::Name(name) => name,
+            EnvVar::Spec(spec) => &spec.name,
+        }
+    }
+}
+
+impl EnvironmentConfig {
+    fn spec<'a>(
+        title: &str,
+        variables: impl Iterator<Item = (&'a EnvVar, bool)>,
+    ) -> Option<serde_json::Value> {
+        let mut properties = serde_json::Map::new();
+        let mut required = Vec::new();
+        for (variable, is_required) in variables {
+            let mut property = serde_json::json!({
+                "title": variable.name(),
+                "type": "string",
+            });
+            if let EnvVar::Spec(spec) = variable {
+                if let Some(description) = &spec.description {
+                    property["description"] = serde_json::json!(description);
+                }
+                if let Some(default) = &spec.default {
+                    property["default"] = serde_json::json!(default);
+                }
+                if spec.secret {
+                    property["input"] = serde_json::json!({ "type": "password" });
+                }
+            }
+            if is_required {
+                required.push(variable.name().to_string());
+            }
+            properties.insert(variable.name().to_string(), property);
+        }
+        if properties.is_empty() {
+            return None;
+        }
+
+        Some(serde_json::json!({
+            "title": title,
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        }))
+    }
+
+    /// JSON Schema of the admin valves, if the tool declares any
+    pub fn valves_spec(&self) -> Option<serde_json::Value> {
+        let required = self.required.iter().map(|v| (v, true));
+        let optional = self.optional.iter().map(|v| (v, false));
+        Self::spec("Valves", required.chain(optional))
+    }
+
+    /// JSON Schema of the UserValves, if the tool declares any
+    pub fn user_valves_spec(&self) -> Option<serde_json::Value> {
+        Self::spec("UserValves", self.user.iter().map(|v| (v, false)))
+    }
 }
 
 /// Tool execution request
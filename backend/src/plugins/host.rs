@@ -0,0 +1,180 @@
+//! Host side of the `open-webui:plugin/host` interface
+
+use wasmtime::StoreLimits;
+
+use crate::retrieval::web::DomainFilter;
+
+wasmtime::component::bindgen!({
+    path: "src/plugins/plugin.wit",
+    world: "function",
+    async: {
+        only_imports: ["http-fetch"],
+    },
+});
+
+pub use open_webui::plugin::host::{add_to_linker, HttpRequest, HttpResponse, LogLevel};
+
+/// Largest response body handed to a component
+const MAX_RESPONSE_SIZE: usize = 10 * 1024 * 1024;
+
+/// Per-call state of a component instance
+pub struct PluginState {
+    pub function_id: String,
+    pub limits: StoreLimits,
+    pub http: HttpCapability,
+}
+
+/// HTTP access restricted to the hosts a Function's manifest lists
+pub struct HttpCapability {
+    client: reqwest::Client,
+    allowed_hosts: DomainFilter,
+}
+
+impl HttpCapability {
+    /// `client` must not follow redirects, so every hop goes through the allow-list
+    pub fn new(client: reqwest::Client, allowed_hosts: &[String]) -> Self {
+        Self {
+            client,
+            allowed_hosts: DomainFilter::new(allowed_hosts),
+        }
+    }
+
+    pub async fn fetch(&self, request: HttpRequest) -> Result<HttpResponse, String> {
+        let url = url::Url::parse(&request.url).map_err(|e| format!("Invalid URL: {}", e))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(format!("Unsupported URL scheme: {}", url.scheme()));
+        }
+        let host = url.host_str().ok_or("URL has no host")?;
+        if !self.allowed_hosts.is_listed(host) {
+            return Err(format!(
+                "Host {} is not in the function's allowed_hosts",
+                host
+            ));
+        }
+        let method = reqwest::Method::from_bytes(request.method.to_uppercase().as_bytes())
+            .map_err(|e| format!("Invalid method: {}", e))?;
+
+        let mut builder = self.client.request(method, url);
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        if let Some(body) = request.body {
+            builder = builder.body(body);
+        }
+        let mut response = builder.send().await.map_err(|e| e.to_string())?;
+
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+            if body.len() + chunk.len() > MAX_RESPONSE_SIZE {
+                return Err(format!(
+                    "Response is larger than {} bytes",
+                    MAX_RESPONSE_SIZE
+                ));
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        Ok(HttpResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+impl open_webui::plugin::host::Host for PluginState {
+    async fn http_fetch(&mut self, request: HttpRequest) -> Result<HttpResponse, String> {
+        self.http.fetch(request).await
+    }
+
+    fn log(&mut self, level: LogLevel, message: String) {
+        let id = &self.function_id;
+        match level {
+            LogLevel::Debug => tracing::debug!("[function {}] {}", id, message),
+            LogLevel::Info => tracing::info!("[function {}] {}", id, message),
+            LogLevel::Warn => tracing::warn!("[function {}] {}", id, message),
+            LogLevel::Error => tracing::error!("[function {}] {}", id, message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{web, App, HttpResponse as ActixResponse, HttpServer};
+
+    #[actix_web::test]
+    async fn test_fetch_only_reaches_allowed_hosts() {
+        let server = HttpServer::new(|| {
+            App::new()
+                .route(
+                    "/echo",
+                    web::post().to(|body: String| async move {
+                        ActixResponse::Ok()
+                            .insert_header(("x-echo", "1"))
+                            .body(body)
+                    }),
+                )
+                .route(
+                    "/redirect",
+                    web::get().to(|| async {
+                        ActixResponse::Found()
+                            .insert_header(("location", "http://example.com/"))
+                            .finish()
+                    }),
+                )
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        let http = HttpCapability::new(client, &["127.0.0.1".to_string()]);
+        let request = |method: &str, url: String| HttpRequest {
+            method: method.to_string(),
+            url,
+            headers: vec![("content-type".to_string(), "text/plain".to_string())],
+            body: Some(b"ping".to_vec()),
+        };
+
+        let response = http
+            .fetch(request("post", format!("http://{}/echo", addr)))
+            .await
+            .unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"ping");
+        assert!(response
+            .headers
+            .contains(&("x-echo".to_string(), "1".to_string())));
+
+        let response = http
+            .fetch(request("GET", format!("http://{}/redirect", addr)))
+            .await
+            .unwrap();
+        assert_eq!(response.status, 302);
+
+        let error = http
+            .fetch(request(
+                "GET",
+                format!("http://localhost:{}/echo", addr.port()),
+            ))
+            .await
+            .unwrap_err();
+        assert!(error.contains("allowed_hosts"), "{}", error);
+        assert!(http
+            .fetch(request("GET", format!("file://{}/echo", addr)))
+            .await
+            .is_err());
+    }
+}
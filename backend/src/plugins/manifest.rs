@@ -0,0 +1,128 @@
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::PluginError;
+
+/// Kind of Function, stored in the `type` column
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FunctionType {
+    Filter,
+    Action,
+    Pipe,
+}
+
+impl FunctionType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FunctionType::Filter => "filter",
+            FunctionType::Action => "action",
+            FunctionType::Pipe => "pipe",
+        }
+    }
+}
+
+/// What a WebAssembly Function is and what it may do
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginManifest {
+    #[serde(rename = "type")]
+    pub function_type: FunctionType,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Specification of the admin-configurable valves
+    #[serde(default)]
+    pub valves: Value,
+    /// Specification of the per-user valves
+    #[serde(default)]
+    pub user_valves: Value,
+    /// Hosts (and their subdomains) the component may send HTTP requests to
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct RawPackage {
+    manifest: PluginManifest,
+    component: String,
+}
+
+#[derive(Deserialize)]
+struct RawManifest {
+    manifest: PluginManifest,
+}
+
+/// The `content` of a WebAssembly Function
+///
+/// ```json
+/// { "manifest": { "type": "filter", "allowed_hosts": ["api.example.com"] },
+///   "component": "<base64-encoded component>" }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PluginPackage {
+    pub manifest: PluginManifest,
+    pub component: Vec<u8>,
+}
+
+impl PluginPackage {
+    /// Whether Function content is a WebAssembly package rather than source code
+    pub fn is_package(content: &str) -> bool {
+        content.trim_start().starts_with('{')
+    }
+
+    /// Read just the manifest of a WebAssembly Function, leaving the
+    /// component undecoded
+    pub fn parse_manifest(content: &str) -> Result<PluginManifest, PluginError> {
+        serde_json::from_str::<RawManifest>(content)
+            .map(|raw| raw.manifest)
+            .map_err(|e| PluginError::InvalidPackage(e.to_string()))
+    }
+
+    /// Parse the content of a WebAssembly Function
+    pub fn parse(content: &str) -> Result<Self, PluginError> {
+        let raw: RawPackage = serde_json::from_str(content)
+            .map_err(|e| PluginError::InvalidPackage(e.to_string()))?;
+        let component = base64::engine::general_purpose::STANDARD
+            .decode(raw.component.trim())
+            .map_err(|e| PluginError::InvalidPackage(format!("Invalid component: {}", e)))?;
+
+        Ok(Self {
+            manifest: raw.manifest,
+            component,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_package() {
+        let content = r#"{
+            "manifest": { "type": "filter", "allowed_hosts": ["api.example.com"] },
+            "component": "AGFzbQ0AAQA="
+        }"#;
+        assert!(PluginPackage::is_package(content));
+
+        let package = PluginPackage::parse(content).unwrap();
+        assert_eq!(package.manifest.function_type, FunctionType::Filter);
+        assert_eq!(package.manifest.allowed_hosts, vec!["api.example.com"]);
+        assert_eq!(package.component, b"\0asm\x0d\0\x01\0");
+        assert_eq!(
+            PluginPackage::parse_manifest(content)
+                .unwrap()
+                .allowed_hosts,
+            package.manifest.allowed_hosts
+        );
+
+        assert!(!PluginPackage::is_package("class Filter:\n    pass"));
+        assert!(
+            PluginPackage::parse(r#"{ "manifest": { "type": "tool" }, "component": "" }"#).is_err()
+        );
+        assert!(
+            PluginPackage::parse(r#"{ "manifest": { "type": "pipe" }, "component": "%%%" }"#)
+                .is_err()
+        );
+    }
+}
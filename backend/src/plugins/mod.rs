@@ -0,0 +1,41 @@
+//! Sandboxed WebAssembly runtime for filter, action and pipe Functions
+//!
+//! A WebAssembly Function is stored in the `function` table like any other;
+//! its `content` is a JSON package holding a manifest and a base64-encoded
+//! WebAssembly component implementing the `function` world of
+//! `plugin.wit`. Components run with a fuel budget, a memory cap and a
+//! wall-clock timeout, and can only reach the network through the host's
+//! `http-fetch`, which is restricted to the hosts listed in the manifest.
+
+mod host;
+pub mod manifest;
+pub mod runtime;
+
+pub use manifest::{PluginManifest, PluginPackage};
+pub use runtime::{Hook, PluginRuntime};
+
+/// Error types for WebAssembly Functions
+#[derive(Debug, thiserror::Error)]
+pub enum PluginError {
+    #[error("Invalid function package: {0}")]
+    InvalidPackage(String),
+
+    #[error("Failed to compile function: {0}")]
+    Compile(String),
+
+    #[error("Function failed: {0}")]
+    Runtime(String),
+
+    #[error("Function ran out of fuel")]
+    FuelExhausted,
+
+    #[error("Function timed out after {0} seconds")]
+    Timeout(u64),
+
+    /// The hook itself returned an error
+    #[error("{0}")]
+    Hook(String),
+
+    #[error("Function returned invalid JSON: {0}")]
+    Output(String),
+}
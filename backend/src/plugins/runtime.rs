@@ -0,0 +1,363 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use wasmtime::component::{Component, InstancePre, Linker};
+use wasmtime::{Engine, Store, StoreLimitsBuilder, Trap};
+
+use super::host::{self, HttpCapability, PluginState};
+use super::{PluginError, PluginManifest, PluginPackage};
+use crate::config::Config;
+use crate::models::function::Function;
+use crate::utils::misc::sha256_hash;
+
+/// Fuel burned between yields to the async executor, so timeouts can fire
+const FUEL_YIELD_INTERVAL: u64 = 100_000;
+
+/// A hook exported by a Function component
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    Inlet,
+    Outlet,
+    Stream,
+    Action,
+    Pipe,
+}
+
+impl Hook {
+    pub fn export_name(&self) -> &'static str {
+        match self {
+            Hook::Inlet => "inlet",
+            Hook::Outlet => "outlet",
+            Hook::Stream => "stream",
+            Hook::Action => "action",
+            Hook::Pipe => "pipe",
+        }
+    }
+}
+
+/// Resources a single hook call may use
+#[derive(Debug, Clone)]
+pub struct PluginLimits {
+    /// Fuel (roughly, WebAssembly instructions) per call
+    pub fuel: u64,
+    /// Linear memory per instance, in bytes
+    pub max_memory: usize,
+    pub timeout: Duration,
+}
+
+impl PluginLimits {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            fuel: config.function_wasm_fuel,
+            max_memory: config.function_wasm_max_memory,
+            timeout: Duration::from_secs(config.function_wasm_timeout),
+        }
+    }
+}
+
+/// A compiled Function, linked against the host interface
+struct Prepared {
+    digest: String,
+    manifest: PluginManifest,
+    pre: InstancePre<PluginState>,
+}
+
+/// Compiles WebAssembly Functions and runs their hooks
+///
+/// Compiled components are cached per Function and recompiled when its
+/// content changes. Every call gets a fresh instance.
+pub struct PluginRuntime {
+    engine: Engine,
+    linker: Linker<PluginState>,
+    http_client: reqwest::Client,
+    limits: PluginLimits,
+    cache: RwLock<HashMap<String, Arc<Prepared>>>,
+}
+
+impl PluginRuntime {
+    pub fn new(limits: PluginLimits) -> Result<Self, PluginError> {
+        let mut config = wasmtime::Config::new();
+        config
+            .wasm_component_model(true)
+            .async_support(true)
+            .consume_fuel(true);
+        let engine = Engine::new(&config).map_err(|e| PluginError::Runtime(e.to_string()))?;
+
+        let mut linker = Linker::new(&engine);
+        host::add_to_linker(&mut linker, |state: &mut PluginState| state)
+            .map_err(|e| PluginError::Runtime(e.to_string()))?;
+
+        let http_client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .timeout(limits.timeout)
+            .build()
+            .map_err(|e| PluginError::Runtime(e.to_string()))?;
+
+        Ok(Self {
+            engine,
+            linker,
+            http_client,
+            limits,
+            cache: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// Create a runtime with the FUNCTION_WASM_* limits
+    pub fn from_config(config: &Config) -> Result<Self, PluginError> {
+        Self::new(PluginLimits::from_config(config))
+    }
+
+    async fn compile(
+        &self,
+        content: &str,
+    ) -> Result<(PluginManifest, InstancePre<PluginState>), PluginError> {
+        let package = PluginPackage::parse(content)?;
+        let engine = self.engine.clone();
+        let component =
+            tokio::task::spawn_blocking(move || Component::new(&engine, &package.component))
+                .await
+                .map_err(|e| PluginError::Compile(e.to_string()))?
+                .map_err(|e| PluginError::Compile(format!("{:#}", e)))?;
+
+        // Fails when the component imports anything besides the host interface
+        let pre = self
+            .linker
+            .instantiate_pre(&component)
+            .map_err(|e| PluginError::Compile(format!("{:#}", e)))?;
+        Ok((package.manifest, pre))
+    }
+
+    /// Check that Function content is a runnable package and return its manifest
+    pub async fn validate(&self, content: &str) -> Result<PluginManifest, PluginError> {
+        self.compile(content).await.map(|(manifest, _)| manifest)
+    }
+
+    async fn prepare(&self, function: &Function) -> Result<Arc<Prepared>, PluginError> {
+        let digest = sha256_hash(&function.content);
+        if let Some(prepared) = self.cache.read().unwrap().get(&function.id) {
+            if prepared.digest == digest {
+                return Ok(prepared.clone());
+            }
+        }
+
+        let (manifest, pre) = self.compile(&function.content).await?;
+        let prepared = Arc::new(Prepared {
+            digest,
+            manifest,
+            pre,
+        });
+        self.cache
+            .write()
+            .unwrap()
+            .insert(function.id.clone(), prepared.clone());
+        Ok(prepared)
+    }
+
+    /// Call `hook` with JSON-encoded `body` and `context`
+    ///
+    /// Returns `None` when the component does not export the hook.
+    pub async fn call(
+        &self,
+        function: &Function,
+        hook: Hook,
+        body: &Value,
+        context: &Value,
+    ) -> Result<Option<Value>, PluginError> {
+        let prepared = self.prepare(function).await?;
+
+        let mut store = Store::new(
+            &self.engine,
+            PluginState {
+                function_id: function.id.clone(),
+                limits: StoreLimitsBuilder::new()
+                    .memory_size(self.limits.max_memory)
+                    .build(),
+                http: HttpCapability::new(
+                    self.http_client.clone(),
+                    &prepared.manifest.allowed_hosts,
+                ),
+            },
+        );
+        store.limiter(|state| &mut state.limits);
+        let runtime_error = |e: wasmtime::Error| PluginError::Runtime(e.to_string());
+        store.set_fuel(self.limits.fuel).map_err(runtime_error)?;
+        store
+            .fuel_async_yield_interval(Some(FUEL_YIELD_INTERVAL))
+            .map_err(runtime_error)?;
+
+        let body = body.to_string();
+        let context = context.to_string();
+        let run = async {
+            let instance = prepared.pre.instantiate_async(&mut store).await?;
+            let Some(func) = instance.get_func(&mut store, hook.export_name()) else {
+                return Ok(None);
+            };
+            let func = func.typed::<(&str, &str), (Result<String, String>,)>(&store)?;
+            let (result,) = func.call_async(&mut store, (&body, &context)).await?;
+            func.post_return_async(&mut store).await?;
+            Ok::<_, wasmtime::Error>(Some(result))
+        };
+
+        let result = tokio::time::timeout(self.limits.timeout, run)
+            .await
+            .map_err(|_| PluginError::Timeout(self.limits.timeout.as_secs()))?
+            .map_err(|e| match e.downcast_ref::<Trap>() {
+                Some(Trap::OutOfFuel) => PluginError::FuelExhausted,
+                _ => PluginError::Runtime(format!("{:#}", e)),
+            })?;
+
+        match result {
+            None => Ok(None),
+            Some(Ok(output)) => serde_json::from_str(&output)
+                .map(Some)
+                .map_err(|e| PluginError::Output(e.to_string())),
+            Some(Err(message)) => Err(PluginError::Hook(message)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine as _;
+    use serde_json::json;
+
+    /// `inlet` returns its body, `action` fails with its context and `outlet`
+    /// spins forever; `pages` is the initial memory size
+    fn component(pages: u32) -> Vec<u8> {
+        wat::parse_str(format!(
+            r#"(component
+                (core module $m
+                    (memory (export "memory") {pages})
+                    (global $heap (mut i32) (i32.const 1024))
+                    (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+                        (local $ptr i32)
+                        (local.set $ptr (global.get $heap))
+                        (global.set $heap (i32.add (global.get $heap) (local.get 3)))
+                        (local.get $ptr))
+                    (func (export "inlet") (param i32 i32 i32 i32) (result i32)
+                        (i32.store8 (i32.const 0) (i32.const 0))
+                        (i32.store (i32.const 4) (local.get 0))
+                        (i32.store (i32.const 8) (local.get 1))
+                        (i32.const 0))
+                    (func (export "action") (param i32 i32 i32 i32) (result i32)
+                        (i32.store8 (i32.const 0) (i32.const 1))
+                        (i32.store (i32.const 4) (local.get 2))
+                        (i32.store (i32.const 8) (local.get 3))
+                        (i32.const 0))
+                    (func (export "outlet") (param i32 i32 i32 i32) (result i32)
+                        (loop $spin (br $spin))
+                        (i32.const 0)))
+                (core instance $i (instantiate $m))
+                (func (export "inlet")
+                    (param "body" string) (param "context" string)
+                    (result (result string (error string)))
+                    (canon lift (core func $i "inlet") (memory $i "memory")
+                        (realloc (func $i "realloc"))))
+                (func (export "action")
+                    (param "body" string) (param "context" string)
+                    (result (result string (error string)))
+                    (canon lift (core func $i "action") (memory $i "memory")
+                        (realloc (func $i "realloc"))))
+                (func (export "outlet")
+                    (param "body" string) (param "context" string)
+                    (result (result string (error string)))
+                    (canon lift (core func $i "outlet") (memory $i "memory")
+                        (realloc (func $i "realloc")))))"#
+        ))
+        .unwrap()
+    }
+
+    fn function(id: &str, component: &[u8]) -> Function {
+        Function {
+            id: id.to_string(),
+            user_id: "admin".to_string(),
+            name: id.to_string(),
+            type_: "filter".to_string(),
+            content: json!({
+                "manifest": { "type": "filter" },
+                "component": base64::engine::general_purpose::STANDARD.encode(component),
+            })
+            .to_string(),
+            meta: None,
+            meta_str: None,
+            valves: None,
+            valves_str: None,
+            is_active: true,
+            is_global: false,
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    fn runtime() -> PluginRuntime {
+        PluginRuntime::new(PluginLimits {
+            fuel: 10_000_000,
+            max_memory: 1024 * 1024,
+            timeout: Duration::from_secs(10),
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_call_hooks() {
+        let runtime = runtime();
+        let filter = function("echo", &component(1));
+        let body = json!({ "messages": [{ "role": "user", "content": "hi" }] });
+        let context = json!({ "valves": { "priority": 1 } });
+
+        assert_eq!(
+            runtime
+                .validate(&filter.content)
+                .await
+                .unwrap()
+                .function_type,
+            crate::plugins::manifest::FunctionType::Filter
+        );
+        assert_eq!(
+            runtime
+                .call(&filter, Hook::Inlet, &body, &context)
+                .await
+                .unwrap(),
+            Some(body.clone())
+        );
+        assert_eq!(
+            runtime
+                .call(&filter, Hook::Pipe, &body, &context)
+                .await
+                .unwrap(),
+            None
+        );
+        match runtime.call(&filter, Hook::Action, &body, &context).await {
+            Err(PluginError::Hook(message)) => assert_eq!(message, context.to_string()),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_enforces_limits() {
+        let runtime = runtime();
+        let body = json!({});
+
+        let spinner = function("spinner", &component(1));
+        assert!(matches!(
+            runtime.call(&spinner, Hook::Outlet, &body, &body).await,
+            Err(PluginError::FuelExhausted)
+        ));
+
+        // 32 pages is 2 MiB, over the 1 MiB memory limit
+        let greedy = function("greedy", &component(32));
+        assert!(matches!(
+            runtime.call(&greedy, Hook::Inlet, &body, &body).await,
+            Err(PluginError::Runtime(_))
+        ));
+
+        assert!(matches!(
+            runtime
+                .validate(&function("junk", b"\0asm junk").content)
+                .await,
+            Err(PluginError::Compile(_))
+        ));
+    }
+}
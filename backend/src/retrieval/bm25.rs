@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+
+/// Okapi BM25 index over an in-memory set of documents
+///
+/// Used for the keyword half of hybrid search, built on the fly from the
+/// chunks stored in a collection.
+pub struct BM25 {
+    k1: f32,
+    b: f32,
+    doc_freqs: Vec<HashMap<String, usize>>,
+    doc_lens: Vec<usize>,
+    idf: HashMap<String, f32>,
+    avgdl: f32,
+}
+
+impl BM25 {
+    /// Build an index with the standard parameters (k1 = 1.5, b = 0.75)
+    pub fn new(documents: &[String]) -> Self {
+        Self::with_params(documents, 1.5, 0.75)
+    }
+
+    pub fn with_params(documents: &[String], k1: f32, b: f32) -> Self {
+        let mut doc_freqs = Vec::with_capacity(documents.len());
+        let mut doc_lens = Vec::with_capacity(documents.len());
+        let mut df: HashMap<String, usize> = HashMap::new();
+
+        for document in documents {
+            let tokens = tokenize(document);
+            doc_lens.push(tokens.len());
+
+            let mut freqs: HashMap<String, usize> = HashMap::new();
+            for token in tokens {
+                *freqs.entry(token).or_insert(0) += 1;
+            }
+            for token in freqs.keys() {
+                *df.entry(token.clone()).or_insert(0) += 1;
+            }
+            doc_freqs.push(freqs);
+        }
+
+        let n = documents.len() as f32;
+        let avgdl = if documents.is_empty() {
+            0.0
+        } else {
+            doc_lens.iter().sum::<usize>() as f32 / n
+        };
+
+        // BM25+ style idf that never goes negative for very common terms
+        let idf = df
+            .into_iter()
+            .map(|(token, freq)| {
+                let freq = freq as f32;
+                (token, ((n - freq + 0.5) / (freq + 0.5) + 1.0).ln())
+            })
+            .collect();
+
+        Self {
+            k1,
+            b,
+            doc_freqs,
+            doc_lens,
+            idf,
+            avgdl,
+        }
+    }
+
+    /// Score every document against the query
+    pub fn scores(&self, query: &str) -> Vec<f32> {
+        let query_tokens = tokenize(query);
+
+        self.doc_freqs
+            .iter()
+            .zip(self.doc_lens.iter())
+            .map(|(freqs, &len)| {
+                let norm = if self.avgdl > 0.0 {
+                    1.0 - self.b + self.b * len as f32 / self.avgdl
+                } else {
+                    1.0
+                };
+
+                query_tokens
+                    .iter()
+                    .filter_map(|token| {
+                        let tf = *freqs.get(token)? as f32;
+                        let idf = self.idf.get(token).copied().unwrap_or(0.0);
+                        Some(idf * tf * (self.k1 + 1.0) / (tf + self.k1 * norm))
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+
+    /// Indices and scores of the `k` best matching documents, best first
+    ///
+    /// Documents that share no terms with the query are left out.
+    pub fn top_k(&self, query: &str, k: usize) -> Vec<(usize, f32)> {
+        let mut scored: Vec<(usize, f32)> = self
+            .scores(query)
+            .into_iter()
+            .enumerate()
+            .filter(|(_, score)| *score > 0.0)
+            .collect();
+
+        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        scored.truncate(k);
+        scored
+    }
+}
+
+/// Lowercase and split on anything that is not alphanumeric
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn corpus() -> Vec<String> {
+        vec![
+            "The quick brown fox jumps over the lazy dog".to_string(),
+            "Rust is a systems programming language".to_string(),
+            "The fox is quick, the fox is clever".to_string(),
+            "".to_string(),
+        ]
+    }
+
+    #[test]
+    fn test_top_k_ranks_term_frequency() {
+        let bm25 = BM25::new(&corpus());
+        let top = bm25.top_k("quick fox", 10);
+
+        assert_eq!(top.len(), 2);
+        assert_eq!(top[0].0, 2);
+        assert_eq!(top[1].0, 0);
+    }
+
+    #[test]
+    fn test_no_match_and_empty_corpus() {
+        let bm25 = BM25::new(&corpus());
+        assert!(bm25.top_k("database", 10).is_empty());
+
+        let empty = BM25::new(&[]);
+        assert!(empty.top_k("anything", 3).is_empty());
+    }
+
+    #[test]
+    fn test_tokenize_is_case_and_punctuation_insensitive() {
+        assert_eq!(
+            tokenize("Hello, WORLD! it's"),
+            vec!["hello", "world", "it", "s"]
+        );
+    }
+}
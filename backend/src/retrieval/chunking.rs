@@ -1,14 +1,54 @@
+use super::loaders::Document;
+use serde_json::{json, Value};
+use tiktoken_rs::CoreBPE;
 use unicode_segmentation::UnicodeSegmentation;
 
+/// Error types for text splitting
+#[derive(Debug, thiserror::Error)]
+pub enum SplitterError {
+    #[error("Invalid text splitter: {0}. Supported: character, token, markdown_header")]
+    UnknownSplitter(String),
+
+    #[error("Unknown tiktoken encoding: {0}")]
+    UnknownEncoding(String),
+
+    #[error("Configuration error: {0}")]
+    ConfigError(String),
+}
+
+/// Text splitter selected by the TEXT_SPLITTER setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextSplitterType {
+    /// Recursive character splitting (paragraphs, lines, words, characters)
+    Character,
+    /// Fixed windows of tiktoken tokens
+    Token,
+    /// Split on Markdown headings, then by characters
+    MarkdownHeader,
+}
+
+impl TextSplitterType {
+    pub fn from_str(splitter: &str) -> Result<Self, SplitterError> {
+        match splitter.trim().to_lowercase().as_str() {
+            "" | "character" => Ok(TextSplitterType::Character),
+            "token" => Ok(TextSplitterType::Token),
+            "markdown_header" => Ok(TextSplitterType::MarkdownHeader),
+            other => Err(SplitterError::UnknownSplitter(other.to_string())),
+        }
+    }
+}
+
 /// Configuration for text chunking
 #[derive(Debug, Clone)]
 pub struct ChunkingConfig {
-    /// Maximum chunk size in characters
+    /// Maximum chunk size (characters, or tokens for the token splitter)
     pub chunk_size: usize,
-    /// Overlap between chunks in characters
+    /// Overlap between chunks, in the same unit as `chunk_size`
     pub chunk_overlap: usize,
-    /// Separator to use for splitting text
-    pub separator: String,
+    /// Splitter built by `create_splitter`
+    pub splitter: TextSplitterType,
+    /// tiktoken encoding used by the token splitter
+    pub encoding_name: String,
 }
 
 impl Default for ChunkingConfig {
@@ -16,7 +56,8 @@ impl Default for ChunkingConfig {
         Self {
             chunk_size: 512,
             chunk_overlap: 50,
-            separator: "\n\n".to_string(),
+            splitter: TextSplitterType::Character,
+            encoding_name: "cl100k_base".to_string(),
         }
     }
 }
@@ -34,19 +75,439 @@ impl ChunkingConfig {
             .and_then(|s| s.parse().ok())
             .unwrap_or(50);
 
+        let splitter = std::env::var("TEXT_SPLITTER")
+            .ok()
+            .and_then(|s| TextSplitterType::from_str(&s).ok())
+            .unwrap_or(TextSplitterType::Character);
+
+        let encoding_name =
+            std::env::var("TIKTOKEN_ENCODING_NAME").unwrap_or_else(|_| "cl100k_base".to_string());
+
         Self {
             chunk_size,
             chunk_overlap,
-            separator: "\n\n".to_string(),
+            splitter,
+            encoding_name,
+        }
+    }
+
+    /// Create configuration from the RAG settings
+    pub fn from_config(config: &crate::config::Config) -> Result<Self, SplitterError> {
+        Ok(Self {
+            chunk_size: config.chunk_size,
+            chunk_overlap: config.chunk_overlap,
+            splitter: TextSplitterType::from_str(&config.text_splitter)?,
+            encoding_name: config.tiktoken_encoding_name.clone(),
+        })
+    }
+
+    /// Build the configured splitter
+    pub fn create_splitter(&self) -> Result<Box<dyn TextSplitter>, SplitterError> {
+        if self.chunk_size == 0 {
+            return Err(SplitterError::ConfigError(
+                "Chunk size must be greater than 0".to_string(),
+            ));
         }
+        if self.chunk_overlap >= self.chunk_size {
+            return Err(SplitterError::ConfigError(format!(
+                "Chunk overlap ({}) must be smaller than chunk size ({})",
+                self.chunk_overlap, self.chunk_size
+            )));
+        }
+
+        Ok(match self.splitter {
+            TextSplitterType::Character => Box::new(RecursiveCharacterTextSplitter::new(
+                self.chunk_size,
+                self.chunk_overlap,
+            )),
+            TextSplitterType::Token => Box::new(TokenTextSplitter::new(
+                &self.encoding_name,
+                self.chunk_size,
+                self.chunk_overlap,
+            )?),
+            TextSplitterType::MarkdownHeader => Box::new(MarkdownHeaderTextSplitter::new(
+                self.chunk_size,
+                self.chunk_overlap,
+            )),
+        })
+    }
+}
+
+/// Trait for strategies that split documents into chunks for embedding
+pub trait TextSplitter: Send + Sync {
+    /// Split raw text into chunks
+    fn split_text(&self, text: &str) -> Vec<String>;
+
+    /// Split documents, keeping their metadata and recording each chunk's
+    /// character offset in the source text as `start_index`
+    fn split_documents(&self, documents: Vec<Document>) -> Vec<Document> {
+        documents
+            .into_iter()
+            .flat_map(|doc| {
+                let chunks = self.split_text(&doc.page_content);
+                chunk_documents(&doc.page_content, chunks, &doc.metadata)
+            })
+            .collect()
+    }
+}
+
+/// Wrap chunks of `text` into documents carrying `metadata` and `start_index`
+fn chunk_documents(text: &str, chunks: Vec<String>, metadata: &Value) -> Vec<Document> {
+    let mut search_from = 0;
+    let mut char_offset = 0;
+    let mut byte_offset = 0;
+
+    chunks
+        .into_iter()
+        .map(|chunk| {
+            let mut chunk_metadata = if metadata.is_object() {
+                metadata.clone()
+            } else {
+                json!({})
+            };
+
+            let found = text[search_from..]
+                .find(chunk.as_str())
+                .map(|i| search_from + i)
+                .or_else(|| text.find(chunk.as_str()));
+            if let Some(start) = found {
+                // Count characters incrementally, chunks are found in order
+                if start < byte_offset {
+                    char_offset = 0;
+                    byte_offset = 0;
+                }
+                char_offset += text[byte_offset..start].chars().count();
+                byte_offset = start;
+                chunk_metadata["start_index"] = json!(char_offset);
+                search_from = start + chunk.chars().next().map_or(0, char::len_utf8);
+            }
+
+            Document::new(chunk, chunk_metadata)
+        })
+        .collect()
+}
+
+/// Recursive character splitter
+///
+/// Splits on paragraphs, then lines, then words, then characters, and merges
+/// the pieces back into chunks of at most `chunk_size` characters with
+/// `chunk_overlap` characters carried over between chunks. Matches
+/// LangChain's `RecursiveCharacterTextSplitter`.
+pub struct RecursiveCharacterTextSplitter {
+    chunk_size: usize,
+    chunk_overlap: usize,
+    separators: Vec<String>,
+}
+
+impl RecursiveCharacterTextSplitter {
+    pub fn new(chunk_size: usize, chunk_overlap: usize) -> Self {
+        Self {
+            chunk_size,
+            chunk_overlap,
+            separators: ["\n\n", "\n", " ", ""]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        }
+    }
+
+    fn split_recursive(&self, text: &str, separators: &[String]) -> Vec<String> {
+        let mut final_chunks = Vec::new();
+
+        // Use the first separator present in the text
+        let (separator, remaining) = separators
+            .iter()
+            .position(|s| s.is_empty() || text.contains(s.as_str()))
+            .map(|i| (separators[i].as_str(), &separators[i + 1..]))
+            .unwrap_or(("", &[]));
+
+        let mut good_splits: Vec<String> = Vec::new();
+        for split in split_keep_separator(text, separator) {
+            if split.chars().count() < self.chunk_size {
+                good_splits.push(split);
+                continue;
+            }
+
+            if !good_splits.is_empty() {
+                final_chunks.extend(self.merge_splits(&good_splits));
+                good_splits.clear();
+            }
+            if remaining.is_empty() {
+                final_chunks.push(split);
+            } else {
+                final_chunks.extend(self.split_recursive(&split, remaining));
+            }
+        }
+
+        if !good_splits.is_empty() {
+            final_chunks.extend(self.merge_splits(&good_splits));
+        }
+
+        final_chunks
+    }
+
+    /// Combine small splits into chunks, carrying over the overlap
+    fn merge_splits(&self, splits: &[String]) -> Vec<String> {
+        let mut chunks = Vec::new();
+        let mut current: std::collections::VecDeque<(&str, usize)> =
+            std::collections::VecDeque::new();
+        let mut total = 0;
+
+        for split in splits {
+            let len = split.chars().count();
+
+            if total + len > self.chunk_size && !current.is_empty() {
+                push_trimmed(&mut chunks, current.iter().map(|(s, _)| *s).collect());
+
+                // Drop pieces from the front until only the overlap remains
+                // and the next split fits
+                while total > self.chunk_overlap || (total + len > self.chunk_size && total > 0) {
+                    match current.pop_front() {
+                        Some((_, front_len)) => total -= front_len,
+                        None => break,
+                    }
+                }
+            }
+
+            current.push_back((split, len));
+            total += len;
+        }
+
+        push_trimmed(&mut chunks, current.iter().map(|(s, _)| *s).collect());
+        chunks
+    }
+}
+
+fn push_trimmed(chunks: &mut Vec<String>, pieces: Vec<&str>) {
+    let chunk = pieces.concat();
+    let chunk = chunk.trim();
+    if !chunk.is_empty() {
+        chunks.push(chunk.to_string());
+    }
+}
+
+/// Split on `separator`, keeping it at the start of the following piece
+fn split_keep_separator(text: &str, separator: &str) -> Vec<String> {
+    if separator.is_empty() {
+        return text.chars().map(String::from).collect();
+    }
+
+    let mut splits = Vec::new();
+    let mut last = 0;
+    for (idx, _) in text.match_indices(separator) {
+        if idx > last {
+            splits.push(text[last..idx].to_string());
+        }
+        last = idx;
+    }
+    if last < text.len() {
+        splits.push(text[last..].to_string());
+    }
+    splits
+}
+
+impl TextSplitter for RecursiveCharacterTextSplitter {
+    fn split_text(&self, text: &str) -> Vec<String> {
+        self.split_recursive(text, &self.separators)
+    }
+}
+
+/// Token splitter using a tiktoken encoding
+///
+/// Produces windows of `chunk_size` tokens that overlap by `chunk_overlap`
+/// tokens, like LangChain's `TokenTextSplitter`.
+pub struct TokenTextSplitter {
+    bpe: &'static CoreBPE,
+    chunk_size: usize,
+    chunk_overlap: usize,
+}
+
+impl TokenTextSplitter {
+    pub fn new(
+        encoding_name: &str,
+        chunk_size: usize,
+        chunk_overlap: usize,
+    ) -> Result<Self, SplitterError> {
+        if chunk_overlap >= chunk_size {
+            return Err(SplitterError::ConfigError(format!(
+                "Chunk overlap ({}) must be smaller than chunk size ({})",
+                chunk_overlap, chunk_size
+            )));
+        }
+
+        Ok(Self {
+            bpe: get_encoding(encoding_name)?,
+            chunk_size,
+            chunk_overlap,
+        })
+    }
+}
+
+/// Look up a tiktoken encoding by name
+pub fn get_encoding(encoding_name: &str) -> Result<&'static CoreBPE, SplitterError> {
+    match encoding_name {
+        "cl100k_base" => Ok(tiktoken_rs::cl100k_base_singleton()),
+        "o200k_base" => Ok(tiktoken_rs::o200k_base_singleton()),
+        "p50k_base" => Ok(tiktoken_rs::p50k_base_singleton()),
+        "p50k_edit" => Ok(tiktoken_rs::p50k_edit_singleton()),
+        "r50k_base" | "gpt2" => Ok(tiktoken_rs::r50k_base_singleton()),
+        other => Err(SplitterError::UnknownEncoding(other.to_string())),
+    }
+}
+
+impl TextSplitter for TokenTextSplitter {
+    fn split_text(&self, text: &str) -> Vec<String> {
+        let tokens = self.bpe.encode_ordinary(text);
+        let step = self.chunk_size - self.chunk_overlap;
+
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        while start < tokens.len() {
+            let end = (start + self.chunk_size).min(tokens.len());
+            let bytes: Vec<u8> = self
+                .bpe
+                ._decode_native_and_split(tokens[start..end].to_vec())
+                .flatten()
+                .collect();
+            // Windows can cut through a multi-byte character; tiktoken replaces those bytes too
+            chunks.push(String::from_utf8_lossy(&bytes).into_owned());
+
+            if end == tokens.len() {
+                break;
+            }
+            start += step;
+        }
+
+        chunks
+    }
+}
+
+/// Markdown header splitter
+///
+/// Splits text into sections at `#` to `######` headings (ignoring fenced
+/// code blocks), then splits long sections by characters. Each chunk gets
+/// `Header 1` ... `Header 6` metadata for its enclosing headings, as
+/// LangChain's `MarkdownHeaderTextSplitter` does, plus the joined
+/// `heading_path`.
+pub struct MarkdownHeaderTextSplitter {
+    section_splitter: RecursiveCharacterTextSplitter,
+}
+
+/// A Markdown section and the headings it sits under, outermost first
+struct MarkdownSection {
+    content: String,
+    headings: Vec<(usize, String)>,
+}
+
+impl MarkdownHeaderTextSplitter {
+    pub fn new(chunk_size: usize, chunk_overlap: usize) -> Self {
+        Self {
+            section_splitter: RecursiveCharacterTextSplitter::new(chunk_size, chunk_overlap),
+        }
+    }
+
+    fn split_sections(text: &str) -> Vec<MarkdownSection> {
+        let mut sections = Vec::new();
+        let mut headings: Vec<(usize, String)> = Vec::new();
+        let mut lines: Vec<&str> = Vec::new();
+        let mut fence: Option<&str> = None;
+
+        let mut flush = |lines: &mut Vec<&str>, headings: &[(usize, String)]| {
+            let content = lines.join("\n");
+            if !content.trim().is_empty() {
+                sections.push(MarkdownSection {
+                    content: content.trim().to_string(),
+                    headings: headings.to_vec(),
+                });
+            }
+            lines.clear();
+        };
+
+        for line in text.lines() {
+            let stripped = line.trim();
+
+            // Headings inside fenced code blocks are code, not structure
+            if let Some(marker) = ["```", "~~~"].into_iter().find(|m| stripped.starts_with(m)) {
+                match fence {
+                    Some(open) if open == marker => fence = None,
+                    None => fence = Some(marker),
+                    _ => {}
+                }
+            } else if fence.is_none() {
+                if let Some((level, title)) = parse_heading(stripped) {
+                    flush(&mut lines, &headings);
+                    while headings.last().is_some_and(|(l, _)| *l >= level) {
+                        headings.pop();
+                    }
+                    headings.push((level, title));
+                }
+            }
+
+            lines.push(line);
+        }
+        flush(&mut lines, &headings);
+
+        sections
+    }
+}
+
+/// Parse an ATX heading line into its level and title
+fn parse_heading(line: &str) -> Option<(usize, String)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+    Some((level, rest.trim().to_string()))
+}
+
+impl TextSplitter for MarkdownHeaderTextSplitter {
+    fn split_text(&self, text: &str) -> Vec<String> {
+        Self::split_sections(text)
+            .into_iter()
+            .flat_map(|section| self.section_splitter.split_text(&section.content))
+            .collect()
+    }
+
+    fn split_documents(&self, documents: Vec<Document>) -> Vec<Document> {
+        let mut result = Vec::new();
+
+        for doc in documents {
+            for section in Self::split_sections(&doc.page_content) {
+                let mut metadata = if doc.metadata.is_object() {
+                    doc.metadata.clone()
+                } else {
+                    json!({})
+                };
+                for (level, title) in &section.headings {
+                    metadata[format!("Header {}", level)] = json!(title);
+                }
+                if !section.headings.is_empty() {
+                    let path: Vec<&str> =
+                        section.headings.iter().map(|(_, t)| t.as_str()).collect();
+                    metadata["heading_path"] = json!(path.join(" > "));
+                }
+
+                let chunks = self.section_splitter.split_text(&section.content);
+                result.extend(chunk_documents(&doc.page_content, chunks, &metadata));
+            }
+        }
+
+        result
     }
 }
 
 /// Chunk text into smaller pieces with overlap
 ///
-/// This function splits text into chunks of approximately `chunk_size` characters,
-/// with `chunk_overlap` characters of overlap between consecutive chunks.
-/// It attempts to split on sentence boundaries when possible.
+/// This function splits text into chunks of approximately `chunk_size` bytes,
+/// with `chunk_overlap` bytes of overlap between consecutive chunks.
+/// It attempts to split on sentence boundaries when possible and never cuts
+/// through a multi-byte character.
+#[allow(dead_code)]
 pub fn chunk_text(text: &str, chunk_size: usize, chunk_overlap: usize) -> Vec<String> {
     if text.is_empty() {
         return vec![];
@@ -56,50 +517,59 @@ pub fn chunk_text(text: &str, chunk_size: usize, chunk_overlap: usize) -> Vec<St
         return vec![text.to_string()];
     }
 
+    let chunk_size = chunk_size.max(1);
     let mut chunks = Vec::new();
     let mut start = 0;
-
-    // Split into sentences first
-    let _sentences = split_into_sentences(text);
+    let mut prev_end = 0;
 
     while start < text.len() {
-        let mut end = (start + chunk_size).min(text.len());
+        let hard_end = ceil_char_boundary(text, (start + chunk_size).min(text.len()));
+        let mut end = hard_end;
 
-        // Try to end at a sentence boundary
+        // Try to end at a sentence boundary, as long as it gets past the previous chunk
         if end < text.len() {
-            // Find the last sentence boundary before end
-            let text_slice = &text[start..end];
-            if let Some(boundary) = find_last_sentence_boundary(text_slice) {
-                end = start + boundary + 1;
+            if let Some(boundary) = find_last_sentence_boundary(&text[start..end]) {
+                let boundary_end = ceil_char_boundary(text, start + boundary + 1);
+                if boundary_end > prev_end {
+                    end = boundary_end;
+                }
             }
         }
 
-        // Ensure we make progress
-        if end <= start {
-            end = (start + chunk_size).min(text.len());
-        }
-
         let chunk = text[start..end].trim().to_string();
         if !chunk.is_empty() {
             chunks.push(chunk);
         }
 
-        // Move start forward, accounting for overlap
         if end >= text.len() {
             break;
         }
-        start = end.saturating_sub(chunk_overlap);
 
-        // Ensure we make progress
-        if start >= end {
-            start = end;
-        }
+        // Move start forward, accounting for overlap, but always make progress
+        let next_start = floor_char_boundary(text, end.saturating_sub(chunk_overlap));
+        start = if next_start > start { next_start } else { end };
+        prev_end = end;
     }
 
     chunks
 }
 
+fn floor_char_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+fn ceil_char_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index += 1;
+    }
+    index
+}
+
 /// Split text into sentences
+#[allow(dead_code)]
 fn split_into_sentences(text: &str) -> Vec<String> {
     let sentence_endings = ['.', '!', '?', '\n'];
 
@@ -144,13 +614,12 @@ pub fn count_tokens_approx(text: &str) -> usize {
     (text.len() as f64 / 4.0).ceil() as usize
 }
 
-/// Chunk text with token-based sizing
+/// Chunk text with token-based sizing (cl100k_base)
 pub fn chunk_text_by_tokens(text: &str, max_tokens: usize, overlap_tokens: usize) -> Vec<String> {
-    // Convert token counts to approximate character counts
-    let chunk_size = max_tokens * 4;
-    let chunk_overlap = overlap_tokens * 4;
-
-    chunk_text(text, chunk_size, chunk_overlap)
+    match TokenTextSplitter::new("cl100k_base", max_tokens, overlap_tokens) {
+        Ok(splitter) => splitter.split_text(text),
+        Err(_) => chunk_text(text, max_tokens * 4, overlap_tokens * 4),
+    }
 }
 
 #[cfg(test)]
@@ -204,6 +673,109 @@ mod tests {
         assert!(tokens < 20); // Reasonable range
     }
 
+    #[test]
+    fn test_chunk_text_multibyte() {
+        let text = "Grüße aus München. Ça va très bien! 日本語のテキストです。".repeat(5);
+        let chunks = chunk_text(&text, 17, 5);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| !c.is_empty()));
+    }
+
+    #[test]
+    fn test_chunk_text_default_overlap_advances() {
+        // Sentence boundaries inside the default 100-byte overlap must not stall the loop
+        let text = "Kurz. Ça va. 日本語。\n".repeat(200);
+        let chunks = chunk_text(&text, 1000, 100);
+        assert!(chunks.len() > 1);
+        assert!(chunks.len() < text.len() / 100);
+        assert!(chunks.iter().all(|c| !c.is_empty() && c.len() <= 1000));
+    }
+
+    #[test]
+    fn test_recursive_character_splitter() {
+        let text =
+            "First paragraph with some words.\n\nSecond paragraph is here.\nIt has two lines.";
+        let splitter = RecursiveCharacterTextSplitter::new(30, 10);
+        let chunks = splitter.split_text(text);
+
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| c.chars().count() <= 30));
+        assert_eq!(chunks[0], "First paragraph with some");
+        // Up to 10 characters from the end of a chunk are carried into the next one
+        assert_eq!(chunks[1], "with some words.");
+    }
+
+    #[test]
+    fn test_split_documents_records_start_index() {
+        let text = "alpha beta gamma delta épsilon zeta eta theta";
+        let splitter = RecursiveCharacterTextSplitter::new(12, 0);
+        let docs = splitter.split_documents(vec![Document::new(
+            text.to_string(),
+            json!({"source": "greek.txt"}),
+        )]);
+
+        for doc in &docs {
+            let start = doc.metadata["start_index"].as_u64().unwrap() as usize;
+            let expected: String = text
+                .chars()
+                .skip(start)
+                .take(doc.page_content.chars().count())
+                .collect();
+            assert_eq!(doc.page_content, expected);
+            assert_eq!(doc.metadata["source"], "greek.txt");
+        }
+    }
+
+    #[test]
+    fn test_token_splitter() {
+        let text = "The quick brown fox jumps over the lazy dog. ".repeat(20);
+        let splitter = TokenTextSplitter::new("cl100k_base", 20, 5).unwrap();
+        let chunks = splitter.split_text(&text);
+        let bpe = get_encoding("cl100k_base").unwrap();
+
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| bpe.encode_ordinary(c).len() <= 20));
+        assert!(TokenTextSplitter::new("not_an_encoding", 20, 5).is_err());
+        assert!(TokenTextSplitter::new("cl100k_base", 20, 20).is_err());
+    }
+
+    #[test]
+    fn test_markdown_header_splitter() {
+        let text = "# Guide\nIntro text.\n\n## Install\nRun the installer.\n```sh\n# not a heading\n```\n\n## Usage\nUse it.\n\n# Appendix\nMore.";
+        let splitter = MarkdownHeaderTextSplitter::new(200, 0);
+        let docs = splitter.split_documents(vec![Document::new(text.to_string(), json!({}))]);
+
+        assert_eq!(docs.len(), 4);
+        assert_eq!(docs[0].metadata["Header 1"], "Guide");
+        assert_eq!(docs[1].metadata["heading_path"], "Guide > Install");
+        assert!(docs[1].page_content.contains("# not a heading"));
+        assert_eq!(docs[2].metadata["Header 2"], "Usage");
+        assert_eq!(docs[3].metadata["heading_path"], "Appendix");
+        assert!(docs[3].metadata.get("Header 2").is_none());
+    }
+
+    #[test]
+    fn test_create_splitter_from_config() {
+        assert_eq!(
+            TextSplitterType::from_str("").unwrap(),
+            TextSplitterType::Character
+        );
+        assert_eq!(
+            TextSplitterType::from_str("markdown_header").unwrap(),
+            TextSplitterType::MarkdownHeader
+        );
+        assert!(TextSplitterType::from_str("semantic").is_err());
+
+        let mut config = ChunkingConfig {
+            splitter: TextSplitterType::Token,
+            ..Default::default()
+        };
+        assert!(config.create_splitter().is_ok());
+
+        config.chunk_overlap = config.chunk_size;
+        assert!(config.create_splitter().is_err());
+    }
+
     #[test]
     fn test_split_into_sentences() {
         let text = "First sentence. Second sentence! Third sentence?";
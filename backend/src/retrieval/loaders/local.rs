@@ -0,0 +1,480 @@
+use async_trait::async_trait;
+use quick_xml::events::Event;
+use serde_json::json;
+use std::io::{Cursor, Read};
+
+use super::types::{Document, ExtractError, TextExtractor};
+
+/// Source code and plain-text extensions that are always read as UTF-8 text
+pub const KNOWN_SOURCE_EXT: &[&str] = &[
+    "go", "py", "java", "sh", "bat", "ps1", "cmd", "js", "ts", "css", "cpp", "hpp", "h", "c", "cs",
+    "sql", "log", "ini", "pl", "pm", "r", "dart", "dockerfile", "env", "php", "hs", "hsc", "lua",
+    "nginxconf", "conf", "m", "mm", "plsql", "perl", "rb", "rs", "db2", "scala", "bash", "swift",
+    "vue", "svelte", "msg", "ex", "exs", "erl", "tsx", "jsx", "lhs", "json", "yaml", "yml",
+    "toml", "xml", "txt",
+];
+
+/// Check whether a file should be treated as plain text
+pub fn is_text_file(content_type: &str, extension: &str) -> bool {
+    KNOWN_SOURCE_EXT.contains(&extension)
+        || (content_type.starts_with("text/")
+            && !matches!(content_type, "text/html" | "text/csv"))
+        || matches!(
+            content_type,
+            "application/json"
+                | "application/xml"
+                | "application/javascript"
+                | "application/x-yaml"
+                | "application/toml"
+        )
+}
+
+fn decode_utf8(bytes: &[u8], filename: &str) -> Result<String, ExtractError> {
+    String::from_utf8(bytes.to_vec()).map_err(|_| {
+        ExtractError::ParseError(format!("'{}' is not valid UTF-8 text", filename))
+    })
+}
+
+/// Extracts text from PDF files, one document per page
+pub struct PdfExtractor {
+    extract_images: bool,
+}
+
+impl PdfExtractor {
+    pub fn new(extract_images: bool) -> Self {
+        Self { extract_images }
+    }
+}
+
+#[async_trait]
+impl TextExtractor for PdfExtractor {
+    fn name(&self) -> &str {
+        "pdf"
+    }
+
+    fn supports(&self, content_type: &str, extension: &str) -> bool {
+        content_type == "application/pdf" || extension == "pdf"
+    }
+
+    async fn extract(
+        &self,
+        bytes: &[u8],
+        filename: &str,
+        _content_type: &str,
+    ) -> Result<Vec<Document>, ExtractError> {
+        if self.extract_images {
+            tracing::warn!(
+                "PDF_EXTRACT_IMAGES is enabled but the local extractor cannot OCR images in '{}'; use the tika engine instead",
+                filename
+            );
+        }
+
+        // pdf-extract is CPU bound and may panic on malformed input,
+        // so keep it off the async runtime and turn panics into errors
+        let data = bytes.to_vec();
+        let pages = tokio::task::spawn_blocking(move || {
+            pdf_extract::extract_text_from_mem_by_pages(&data)
+        })
+        .await
+        .map_err(|e| ExtractError::ParseError(format!("PDF extraction aborted: {}", e)))?
+        .map_err(|e| ExtractError::ParseError(e.to_string()))?;
+
+        let total_pages = pages.len();
+        Ok(pages
+            .into_iter()
+            .enumerate()
+            .filter(|(_, text)| !text.trim().is_empty())
+            .map(|(page, text)| {
+                Document::new(
+                    text.trim(),
+                    json!({
+                        "page": page,
+                        "page_label": (page + 1).to_string(),
+                        "total_pages": total_pages,
+                    }),
+                )
+            })
+            .collect())
+    }
+}
+
+/// Extracts paragraph text from Word (.docx) documents
+pub struct DocxExtractor;
+
+impl DocxExtractor {
+    /// Largest decompressed `word/document.xml` that is read, so a zip bomb
+    /// can't exhaust memory
+    const MAX_DOCUMENT_XML_SIZE: u64 = 64 * 1024 * 1024;
+
+    fn read_document_xml(bytes: &[u8], max_size: u64) -> Result<String, ExtractError> {
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes))
+            .map_err(|e| ExtractError::ParseError(format!("Invalid DOCX archive: {}", e)))?;
+        let entry = archive
+            .by_name("word/document.xml")
+            .map_err(|e| ExtractError::ParseError(format!("Missing document body: {}", e)))?;
+
+        let mut xml = String::new();
+        entry
+            .take(max_size + 1)
+            .read_to_string(&mut xml)
+            .map_err(|e| ExtractError::ParseError(e.to_string()))?;
+        if xml.len() as u64 > max_size {
+            return Err(ExtractError::ParseError(format!(
+                "Document body is larger than {} bytes",
+                max_size
+            )));
+        }
+        Ok(xml)
+    }
+
+    fn parse_document_xml(xml: &str) -> Result<String, ExtractError> {
+        let mut reader = quick_xml::Reader::from_str(xml);
+        let mut text = String::new();
+        let mut in_text = false;
+
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(e)) if e.name().as_ref() == b"w:t" => in_text = true,
+                Ok(Event::End(e)) => match e.name().as_ref() {
+                    b"w:t" => in_text = false,
+                    b"w:p" => text.push('\n'),
+                    _ => {}
+                },
+                Ok(Event::Empty(e)) => match e.name().as_ref() {
+                    b"w:tab" => text.push('\t'),
+                    b"w:br" | b"w:cr" => text.push('\n'),
+                    _ => {}
+                },
+                Ok(Event::Text(t)) if in_text => {
+                    let value = t
+                        .unescape()
+                        .map_err(|e| ExtractError::ParseError(e.to_string()))?;
+                    text.push_str(&value);
+                }
+                Ok(Event::Eof) => break,
+                Err(e) => return Err(ExtractError::ParseError(e.to_string())),
+                _ => {}
+            }
+        }
+
+        Ok(text.trim().to_string())
+    }
+}
+
+#[async_trait]
+impl TextExtractor for DocxExtractor {
+    fn name(&self) -> &str {
+        "docx"
+    }
+
+    fn supports(&self, content_type: &str, extension: &str) -> bool {
+        content_type == "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
+            || extension == "docx"
+    }
+
+    async fn extract(
+        &self,
+        bytes: &[u8],
+        _filename: &str,
+        _content_type: &str,
+    ) -> Result<Vec<Document>, ExtractError> {
+        let xml = Self::read_document_xml(bytes, Self::MAX_DOCUMENT_XML_SIZE)?;
+        let text = Self::parse_document_xml(&xml)?;
+        Ok(vec![Document::new(text, json!({}))])
+    }
+}
+
+/// Extracts readable text from HTML pages, skipping scripts and styles
+pub struct HtmlExtractor;
+
+impl HtmlExtractor {
+    const SKIPPED: &'static [&'static str] = &["script", "style", "noscript", "template", "head"];
+    const BLOCKS: &'static [&'static str] = &[
+        "p", "div", "br", "li", "tr", "h1", "h2", "h3", "h4", "h5", "h6", "section", "article",
+        "pre", "blockquote", "table", "ul", "ol",
+    ];
+
+    /// Page chrome dropped when extracting the readable part of a web page
+    const BOILERPLATE: &'static [&'static str] = &[
+        "nav", "header", "footer", "aside", "form", "iframe", "svg", "button", "menu", "dialog",
+    ];
+
+    /// Convert an HTML document into plain text
+    pub fn html_to_text(html: &str) -> String {
+        let document = scraper::Html::parse_document(html);
+        Self::element_text(document.root_element(), &[])
+    }
+
+    /// Extract the main content of a web page
+    ///
+    /// Uses the `<main>` or `<article>` element when the page has one and drops
+    /// navigation, headers, footers and other page chrome.
+    pub fn readable_text(html: &str) -> String {
+        let document = scraper::Html::parse_document(html);
+        let main = ["main", "article", "[role=main]"]
+            .iter()
+            .filter_map(|s| scraper::Selector::parse(s).ok())
+            .find_map(|selector| document.select(&selector).next());
+
+        Self::element_text(
+            main.unwrap_or_else(|| document.root_element()),
+            Self::BOILERPLATE,
+        )
+    }
+
+    /// Page title, if any
+    pub fn title(html: &str) -> Option<String> {
+        let document = scraper::Html::parse_document(html);
+        let selector = scraper::Selector::parse("title").ok()?;
+        let title = document
+            .select(&selector)
+            .next()?
+            .text()
+            .collect::<String>()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        Some(title).filter(|t| !t.is_empty())
+    }
+
+    fn element_text(root: scraper::ElementRef, extra_skipped: &[&str]) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        let mut current = String::new();
+        let mut current_block = None;
+
+        for node in root.descendants() {
+            let Some(text) = node.value().as_text() else {
+                continue;
+            };
+
+            let mut skipped = false;
+            let mut block = None;
+            for ancestor in node.ancestors() {
+                if let Some(element) = ancestor.value().as_element() {
+                    if Self::SKIPPED.contains(&element.name())
+                        || extra_skipped.contains(&element.name())
+                    {
+                        skipped = true;
+                        break;
+                    }
+                    if block.is_none() && Self::BLOCKS.contains(&element.name()) {
+                        block = Some(ancestor.id());
+                    }
+                }
+                // Only the extracted element's own subtree decides what is skipped
+                if ancestor.id() == root.id() {
+                    break;
+                }
+            }
+            if skipped {
+                continue;
+            }
+
+            let words = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            if words.is_empty() {
+                continue;
+            }
+
+            // Start a new line whenever the text belongs to a different block
+            if block != current_block && !current.is_empty() {
+                lines.push(std::mem::take(&mut current));
+            }
+            current_block = block;
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(&words);
+        }
+
+        if !current.is_empty() {
+            lines.push(current);
+        }
+        lines.join("\n")
+    }
+}
+
+#[async_trait]
+impl TextExtractor for HtmlExtractor {
+    fn name(&self) -> &str {
+        "html"
+    }
+
+    fn supports(&self, content_type: &str, extension: &str) -> bool {
+        content_type == "text/html" || matches!(extension, "html" | "htm")
+    }
+
+    async fn extract(
+        &self,
+        bytes: &[u8],
+        _filename: &str,
+        _content_type: &str,
+    ) -> Result<Vec<Document>, ExtractError> {
+        let html = String::from_utf8_lossy(bytes);
+        Ok(vec![Document::new(Self::html_to_text(&html), json!({}))])
+    }
+}
+
+/// Extracts CSV files, one document per row formatted as `column: value` lines
+pub struct CsvExtractor;
+
+#[async_trait]
+impl TextExtractor for CsvExtractor {
+    fn name(&self) -> &str {
+        "csv"
+    }
+
+    fn supports(&self, content_type: &str, extension: &str) -> bool {
+        content_type == "text/csv" || extension == "csv"
+    }
+
+    async fn extract(
+        &self,
+        bytes: &[u8],
+        _filename: &str,
+        _content_type: &str,
+    ) -> Result<Vec<Document>, ExtractError> {
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(bytes);
+        let headers = reader
+            .headers()
+            .map_err(|e| ExtractError::ParseError(e.to_string()))?
+            .clone();
+
+        let mut docs = Vec::new();
+        for (row, record) in reader.records().enumerate() {
+            let record = record.map_err(|e| ExtractError::ParseError(e.to_string()))?;
+            let content = headers
+                .iter()
+                .zip(record.iter())
+                .map(|(header, value)| format!("{}: {}", header.trim(), value.trim()))
+                .collect::<Vec<_>>()
+                .join("\n");
+            docs.push(Document::new(content, json!({ "row": row })));
+        }
+
+        Ok(docs)
+    }
+}
+
+/// Reads Markdown and other plain-text files as-is
+pub struct PlainTextExtractor;
+
+#[async_trait]
+impl TextExtractor for PlainTextExtractor {
+    fn name(&self) -> &str {
+        "text"
+    }
+
+    fn supports(&self, content_type: &str, extension: &str) -> bool {
+        matches!(extension, "md" | "markdown")
+            || content_type == "text/markdown"
+            || is_text_file(content_type, extension)
+    }
+
+    async fn extract(
+        &self,
+        bytes: &[u8],
+        filename: &str,
+        _content_type: &str,
+    ) -> Result<Vec<Document>, ExtractError> {
+        Ok(vec![Document::new(decode_utf8(bytes, filename)?, json!({}))])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn build_docx(document_xml: &str) -> Vec<u8> {
+        let mut buffer = Cursor::new(Vec::new());
+        {
+            let mut writer = zip::ZipWriter::new(&mut buffer);
+            writer
+                .start_file(
+                    "word/document.xml",
+                    zip::write::SimpleFileOptions::default(),
+                )
+                .unwrap();
+            writer.write_all(document_xml.as_bytes()).unwrap();
+            writer.finish().unwrap();
+        }
+        buffer.into_inner()
+    }
+
+    #[tokio::test]
+    async fn test_docx_extraction() {
+        let bytes = build_docx(
+            r#"<w:document><w:body>
+                <w:p><w:r><w:t>Hello</w:t></w:r><w:r><w:t xml:space="preserve"> world &amp; all</w:t></w:r></w:p>
+                <w:p><w:r><w:t>Second</w:t><w:tab/><w:t>para</w:t></w:r></w:p>
+            </w:body></w:document>"#,
+        );
+
+        let docs = DocxExtractor.extract(&bytes, "a.docx", "").await.unwrap();
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].page_content, "Hello world & all\nSecond\tpara");
+    }
+
+    #[test]
+    fn test_docx_body_size_is_limited() {
+        let bytes = build_docx(&format!("<w:document>{}</w:document>", " ".repeat(4096)));
+
+        assert!(DocxExtractor::read_document_xml(&bytes, 8192).is_ok());
+        let err = DocxExtractor::read_document_xml(&bytes, 1024).unwrap_err();
+        assert!(err.to_string().contains("larger than 1024 bytes"));
+    }
+
+    #[tokio::test]
+    async fn test_docx_rejects_invalid_archive() {
+        assert!(DocxExtractor.extract(b"not a zip", "a.docx", "").await.is_err());
+    }
+
+    #[test]
+    fn test_readable_text() {
+        let html = r#"<html><head><title> My  Page </title></head><body><form>
+            <header><nav><a href="/">Home</a></nav></header>
+            <main><h1>Article</h1><p>Body text</p><aside>Related</aside></main>
+            <footer>Copyright</footer></form></body></html>"#;
+
+        assert_eq!(HtmlExtractor::readable_text(html), "Article\nBody text");
+        assert_eq!(HtmlExtractor::title(html).as_deref(), Some("My Page"));
+
+        let html = "<body><nav>Menu</nav><div>Content</div><footer>Foot</footer></body>";
+        assert_eq!(HtmlExtractor::readable_text(html), "Content");
+    }
+
+    #[test]
+    fn test_html_to_text() {
+        let html = r#"<html><head><title>T</title><style>p{}</style></head>
+            <body><h1>Title</h1><p>Some <b>bold</b> text</p>
+            <script>alert(1)</script><ul><li>One</li><li>Two</li></ul></body></html>"#;
+
+        assert_eq!(
+            HtmlExtractor::html_to_text(html),
+            "Title\nSome bold text\nOne\nTwo"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_csv_extraction() {
+        let docs = CsvExtractor
+            .extract(b"name,age\nAlice,30\nBob,25\n", "people.csv", "text/csv")
+            .await
+            .unwrap();
+
+        assert_eq!(docs.len(), 2);
+        assert_eq!(docs[0].page_content, "name: Alice\nage: 30");
+        assert_eq!(docs[1].metadata["row"], 1);
+    }
+
+    #[test]
+    fn test_is_text_file() {
+        assert!(is_text_file("", "rs"));
+        assert!(is_text_file("text/plain", ""));
+        assert!(is_text_file("application/json", ""));
+        assert!(!is_text_file("text/html", "html"));
+        assert!(!is_text_file("application/pdf", "pdf"));
+    }
+}
@@ -0,0 +1,201 @@
+pub mod local;
+pub mod tika;
+pub mod types;
+pub mod url;
+pub mod youtube;
+
+pub use local::{
+    is_text_file, CsvExtractor, DocxExtractor, HtmlExtractor, PdfExtractor, PlainTextExtractor,
+};
+pub use tika::TikaExtractor;
+pub use types::{Document, ExtractError, TextExtractor};
+pub use url::UrlLoader;
+pub use youtube::YoutubeLoader;
+
+use crate::config::Config;
+use serde_json::json;
+use std::path::Path;
+use tracing::debug;
+
+/// Supported content extraction engines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtractionEngine {
+    /// Built-in extractors, no external services required
+    Local,
+    /// Apache Tika server
+    Tika,
+}
+
+impl ExtractionEngine {
+    /// Parse the CONTENT_EXTRACTION_ENGINE setting
+    pub fn from_str(s: &str) -> Result<Self, ExtractError> {
+        match s.trim().to_lowercase().as_str() {
+            "" | "default" | "local" => Ok(ExtractionEngine::Local),
+            "tika" => Ok(ExtractionEngine::Tika),
+            _ => Err(ExtractError::ConfigError(format!(
+                "Unsupported CONTENT_EXTRACTION_ENGINE: {}. Supported engines: local, tika",
+                s
+            ))),
+        }
+    }
+}
+
+/// Selects an extractor for a file based on the configured engine and its mime type
+pub struct Loader {
+    engine: ExtractionEngine,
+    extractors: Vec<Box<dyn TextExtractor>>,
+    tika: TikaExtractor,
+}
+
+impl Loader {
+    pub fn new(
+        client: reqwest::Client,
+        engine: &str,
+        tika_server_url: &str,
+        pdf_extract_images: bool,
+    ) -> Result<Self, ExtractError> {
+        Ok(Self {
+            engine: ExtractionEngine::from_str(engine)?,
+            extractors: vec![
+                Box::new(PdfExtractor::new(pdf_extract_images)),
+                Box::new(DocxExtractor),
+                Box::new(HtmlExtractor),
+                Box::new(CsvExtractor),
+                Box::new(PlainTextExtractor),
+            ],
+            tika: TikaExtractor::new(client, tika_server_url, pdf_extract_images),
+        })
+    }
+
+    /// Create a loader from the retrieval settings
+    pub fn from_config(client: reqwest::Client, config: &Config) -> Result<Self, ExtractError> {
+        Self::new(
+            client,
+            &config.content_extraction_engine,
+            &config.tika_server_url,
+            config.pdf_extract_images,
+        )
+    }
+
+    /// Register an additional local extractor, taking precedence over the built-in ones
+    #[allow(dead_code)]
+    pub fn register(&mut self, extractor: Box<dyn TextExtractor>) {
+        self.extractors.insert(0, extractor);
+    }
+
+    /// Extract documents from a file's contents
+    ///
+    /// `content_type` falls back to a guess from the filename when not provided.
+    /// Plain-text files are always read locally, everything else goes through
+    /// the configured engine.
+    pub async fn load(
+        &self,
+        bytes: &[u8],
+        filename: &str,
+        content_type: Option<&str>,
+    ) -> Result<Vec<Document>, ExtractError> {
+        let extension = Path::new(filename)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        let content_type = content_type
+            .filter(|ct| !ct.is_empty() && *ct != "application/octet-stream")
+            .map(|ct| ct.split(';').next().unwrap_or(ct).trim().to_lowercase())
+            .unwrap_or_else(|| {
+                mime_guess::from_path(filename)
+                    .first_raw()
+                    .unwrap_or("")
+                    .to_string()
+            });
+
+        let extractor: &dyn TextExtractor =
+            if self.engine == ExtractionEngine::Tika && !is_text_file(&content_type, &extension) {
+                &self.tika
+            } else if let Some(extractor) = self
+                .extractors
+                .iter()
+                .find(|e| e.supports(&content_type, &extension))
+            {
+                extractor.as_ref()
+            } else if std::str::from_utf8(bytes).is_ok() {
+                // Unknown types that decode as text are read as-is
+                &PlainTextExtractor
+            } else {
+                return Err(ExtractError::UnsupportedType(content_type));
+            };
+
+        debug!(
+            "Extracting '{}' ({}) with the {} extractor",
+            filename,
+            content_type,
+            extractor.name()
+        );
+
+        let docs = extractor.extract(bytes, filename, &content_type).await?;
+
+        Ok(docs
+            .into_iter()
+            .map(|mut doc| {
+                if !doc.metadata.is_object() {
+                    doc.metadata = json!({});
+                }
+                doc.metadata["source"] = json!(filename);
+                if doc.metadata.get("Content-Type").is_none() {
+                    doc.metadata["Content-Type"] = json!(content_type);
+                }
+                doc
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local_loader() -> Loader {
+        Loader::new(reqwest::Client::new(), "", "http://localhost:9998", false).unwrap()
+    }
+
+    #[test]
+    fn test_extraction_engine_from_str() {
+        assert_eq!(
+            ExtractionEngine::from_str("").unwrap(),
+            ExtractionEngine::Local
+        );
+        assert_eq!(
+            ExtractionEngine::from_str("Tika").unwrap(),
+            ExtractionEngine::Tika
+        );
+        assert!(ExtractionEngine::from_str("unsupported").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_load_selects_extractor_by_type() {
+        let loader = local_loader();
+
+        let docs = loader
+            .load(b"<p>Hello</p><p>World</p>", "page.html", None)
+            .await
+            .unwrap();
+        assert_eq!(docs[0].page_content, "Hello\nWorld");
+        assert_eq!(docs[0].metadata["source"], "page.html");
+        assert_eq!(docs[0].metadata["Content-Type"], "text/html");
+
+        let docs = loader
+            .load(b"# Title\n\nBody", "notes.md", Some("text/markdown"))
+            .await
+            .unwrap();
+        assert_eq!(docs[0].page_content, "# Title\n\nBody");
+    }
+
+    #[tokio::test]
+    async fn test_load_rejects_binary_without_extractor() {
+        let loader = local_loader();
+        assert!(loader
+            .load(&[0xff, 0xfe, 0x00, 0x81], "blob.bin", None)
+            .await
+            .is_err());
+    }
+}
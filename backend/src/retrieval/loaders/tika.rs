@@ -0,0 +1,91 @@
+use async_trait::async_trait;
+use serde_json::json;
+
+use super::types::{Document, ExtractError, TextExtractor};
+
+/// Extracts text through an Apache Tika server (`PUT /tika/text`)
+pub struct TikaExtractor {
+    client: reqwest::Client,
+    url: String,
+    extract_images: bool,
+}
+
+impl TikaExtractor {
+    pub fn new(client: reqwest::Client, url: impl Into<String>, extract_images: bool) -> Self {
+        Self {
+            client,
+            url: url.into(),
+            extract_images,
+        }
+    }
+
+    fn endpoint(&self) -> String {
+        format!("{}/tika/text", self.url.trim_end_matches('/'))
+    }
+}
+
+#[async_trait]
+impl TextExtractor for TikaExtractor {
+    fn name(&self) -> &str {
+        "tika"
+    }
+
+    fn supports(&self, _content_type: &str, _extension: &str) -> bool {
+        true
+    }
+
+    async fn extract(
+        &self,
+        bytes: &[u8],
+        _filename: &str,
+        content_type: &str,
+    ) -> Result<Vec<Document>, ExtractError> {
+        let mut request = self
+            .client
+            .put(self.endpoint())
+            .header("Accept", "application/json")
+            .body(bytes.to_vec());
+
+        if !content_type.is_empty() {
+            request = request.header("Content-Type", content_type);
+        }
+        if self.extract_images {
+            request = request
+                .header("X-Tika-PDFextractInlineImages", "true")
+                .header("X-Tika-PDFOcrStrategy", "auto");
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| ExtractError::EngineError(format!("Tika request failed: {}", e)))?;
+
+        if !response.status().is_success() {
+            return Err(ExtractError::EngineError(format!(
+                "Tika server returned {}",
+                response.status()
+            )));
+        }
+
+        let raw: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| ExtractError::EngineError(format!("Invalid Tika response: {}", e)))?;
+
+        let text = raw
+            .get("X-TIKA:content")
+            .and_then(|c| c.as_str())
+            .unwrap_or("<No text content found>")
+            .trim()
+            .to_string();
+        let detected_type = raw
+            .get("Content-Type")
+            .and_then(|c| c.as_str())
+            .unwrap_or(content_type);
+
+        Ok(vec![Document::new(
+            text,
+            json!({ "Content-Type": detected_type }),
+        )])
+    }
+}
@@ -0,0 +1,58 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+/// A loaded document ready to be split, embedded and stored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Document {
+    pub page_content: String,
+    pub metadata: serde_json::Value,
+}
+
+impl Document {
+    pub fn new(page_content: impl Into<String>, metadata: serde_json::Value) -> Self {
+        Self {
+            page_content: page_content.into(),
+            metadata,
+        }
+    }
+}
+
+/// Error types for text extraction
+#[derive(Debug, thiserror::Error)]
+pub enum ExtractError {
+    #[error("Unsupported content type: {0}")]
+    UnsupportedType(String),
+
+    #[error("Failed to parse document: {0}")]
+    ParseError(String),
+
+    #[error("Extraction engine error: {0}")]
+    EngineError(String),
+
+    #[error("Invalid configuration: {0}")]
+    ConfigError(String),
+
+    #[error("Failed to fetch: {0}")]
+    FetchError(String),
+
+    #[error("URL not allowed: {0}")]
+    Blocked(String),
+}
+
+/// Trait for turning raw file bytes into text documents
+#[async_trait]
+pub trait TextExtractor: Send + Sync {
+    /// Name of the extractor, used for logging
+    fn name(&self) -> &str;
+
+    /// Check whether this extractor can handle the given mime type / extension
+    fn supports(&self, content_type: &str, extension: &str) -> bool;
+
+    /// Extract text documents from the file contents
+    async fn extract(
+        &self,
+        bytes: &[u8],
+        filename: &str,
+        content_type: &str,
+    ) -> Result<Vec<Document>, ExtractError>;
+}
@@ -0,0 +1,442 @@
+use serde_json::json;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use url::Url;
+
+use super::local::HtmlExtractor;
+use super::types::{Document, ExtractError};
+use super::Loader;
+use crate::config::Config;
+use crate::retrieval::web::DomainFilter;
+
+/// Redirects followed before giving up
+const MAX_REDIRECTS: usize = 5;
+
+/// A response body read by [`UrlLoader::fetch`]
+pub struct FetchedPage {
+    /// Final URL after redirects
+    pub url: Url,
+    pub content_type: String,
+    pub bytes: Vec<u8>,
+}
+
+/// Loads web pages as documents
+///
+/// Only http(s) URLs that pass the WEB_FETCH_FILTER_LIST domain list are
+/// fetched. Hosts resolving to loopback, private or link-local addresses are
+/// refused unless ENABLE_RAG_LOCAL_WEB_FETCH is set or the host is explicitly
+/// allow-listed, and every redirect hop is checked again. The connection is
+/// pinned to the checked addresses so a second DNS lookup can't swap them.
+pub struct UrlLoader {
+    documents: Loader,
+    filter: DomainFilter,
+    allow_local: bool,
+    max_size: usize,
+    timeout: Duration,
+}
+
+impl UrlLoader {
+    pub fn new(
+        documents: Loader,
+        filter_list: &[String],
+        allow_local: bool,
+        max_size: usize,
+        timeout: Duration,
+    ) -> Self {
+        Self {
+            documents,
+            filter: DomainFilter::new(filter_list),
+            allow_local,
+            max_size,
+            timeout,
+        }
+    }
+
+    /// Create a loader from the web loader settings
+    ///
+    /// Non-HTML responses (PDFs, text files, ...) go through the configured
+    /// content extraction engine.
+    pub fn from_config(client: reqwest::Client, config: &Config) -> Result<Self, ExtractError> {
+        Ok(Self::new(
+            Loader::from_config(client, config)?,
+            &config.web_fetch_filter_list,
+            config.enable_rag_local_web_fetch,
+            config.web_loader_max_size,
+            Duration::from_secs(config.web_loader_timeout),
+        ))
+    }
+
+    /// Check a URL against the domain list and resolve it to addresses that
+    /// may be connected to
+    async fn resolve(&self, url: &Url) -> Result<Vec<SocketAddr>, ExtractError> {
+        let host = http_host(url)?;
+        if !self.filter.allows(host) {
+            return Err(ExtractError::Blocked(format!(
+                "{} is not allowed by the web fetch filter list",
+                host
+            )));
+        }
+
+        let addrs = lookup(host, url.port_or_known_default().unwrap_or(80)).await?;
+        if !self.allow_local && !self.filter.is_listed(host) {
+            ensure_public(host, &addrs)?;
+        }
+        Ok(addrs)
+    }
+
+    /// Fetch a URL, following redirects, within the size and time limits
+    pub async fn fetch(&self, url: &str) -> Result<FetchedPage, ExtractError> {
+        let mut url = Url::parse(url)
+            .map_err(|e| ExtractError::Blocked(format!("invalid URL '{}': {}", url, e)))?;
+
+        for _ in 0..=MAX_REDIRECTS {
+            let addrs = self.resolve(&url).await?;
+            let mut builder = reqwest::Client::builder()
+                .redirect(reqwest::redirect::Policy::none())
+                .timeout(self.timeout)
+                .user_agent("Mozilla/5.0 (compatible; open-webui)");
+            if let Some(domain) = url.domain() {
+                builder = builder.resolve_to_addrs(domain, &addrs);
+            }
+            let client = builder
+                .build()
+                .map_err(|e| ExtractError::FetchError(e.to_string()))?;
+
+            let mut response = client
+                .get(url.clone())
+                .send()
+                .await
+                .map_err(|e| ExtractError::FetchError(format!("{}: {}", url, e)))?;
+
+            if response.status().is_redirection() {
+                let location = response
+                    .headers()
+                    .get(reqwest::header::LOCATION)
+                    .and_then(|l| l.to_str().ok())
+                    .ok_or_else(|| {
+                        ExtractError::FetchError(format!("{}: redirect without location", url))
+                    })?;
+                url = url.join(location).map_err(|e| {
+                    ExtractError::FetchError(format!("invalid redirect '{}': {}", location, e))
+                })?;
+                continue;
+            }
+            if !response.status().is_success() {
+                return Err(ExtractError::FetchError(format!(
+                    "{} returned {}",
+                    url,
+                    response.status()
+                )));
+            }
+
+            let too_large = || {
+                ExtractError::FetchError(format!("{} is larger than {} bytes", url, self.max_size))
+            };
+            if response
+                .content_length()
+                .is_some_and(|len| len > self.max_size as u64)
+            {
+                return Err(too_large());
+            }
+
+            let content_type = response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("")
+                .to_string();
+
+            let mut bytes = Vec::new();
+            while let Some(chunk) = response
+                .chunk()
+                .await
+                .map_err(|e| ExtractError::FetchError(format!("{}: {}", url, e)))?
+            {
+                if bytes.len() + chunk.len() > self.max_size {
+                    return Err(too_large());
+                }
+                bytes.extend_from_slice(&chunk);
+            }
+
+            return Ok(FetchedPage {
+                url,
+                content_type,
+                bytes,
+            });
+        }
+
+        Err(ExtractError::FetchError(format!(
+            "{}: too many redirects",
+            url
+        )))
+    }
+
+    /// Fetch a URL and extract its readable text
+    pub async fn load(&self, url: &str) -> Result<Vec<Document>, ExtractError> {
+        let page = self.fetch(url).await?;
+        let mime = page
+            .content_type
+            .split(';')
+            .next()
+            .unwrap_or("")
+            .trim()
+            .to_lowercase();
+
+        let docs = if mime.is_empty() || mime == "text/html" || mime == "application/xhtml+xml" {
+            let html = String::from_utf8_lossy(&page.bytes);
+            vec![Document::new(
+                HtmlExtractor::readable_text(&html),
+                json!({
+                    "title": HtmlExtractor::title(&html),
+                    "Content-Type": "text/html",
+                }),
+            )]
+        } else {
+            let filename = page
+                .url
+                .path_segments()
+                .and_then(|mut segments| segments.next_back())
+                .filter(|name| !name.is_empty())
+                .unwrap_or("index")
+                .to_string();
+            self.documents
+                .load(&page.bytes, &filename, Some(&mime))
+                .await?
+        };
+
+        let docs: Vec<Document> = docs
+            .into_iter()
+            .filter(|doc| !doc.page_content.trim().is_empty())
+            .map(|mut doc| {
+                doc.metadata["source"] = json!(url);
+                doc
+            })
+            .collect();
+        if docs.is_empty() {
+            return Err(ExtractError::ParseError(format!(
+                "No content found at {}",
+                url
+            )));
+        }
+        Ok(docs)
+    }
+}
+
+/// Resolve an http(s) URL to the addresses a request to it may be pinned to,
+/// refusing hosts that resolve to loopback, private or link-local addresses
+pub async fn resolve_public(url: &Url) -> Result<Vec<SocketAddr>, ExtractError> {
+    let host = http_host(url)?;
+    let addrs = lookup(host, url.port_or_known_default().unwrap_or(80)).await?;
+    ensure_public(host, &addrs)?;
+    Ok(addrs)
+}
+
+/// Host of an http(s) URL, without the brackets of an IPv6 literal
+fn http_host(url: &Url) -> Result<&str, ExtractError> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(ExtractError::Blocked(format!(
+            "unsupported scheme '{}'",
+            url.scheme()
+        )));
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| ExtractError::Blocked("URL has no host".to_string()))?;
+    Ok(host.trim_start_matches('[').trim_end_matches(']'))
+}
+
+/// Resolve a host name; IP literals are taken as they are
+async fn lookup(host: &str, port: u16) -> Result<Vec<SocketAddr>, ExtractError> {
+    let addrs: Vec<SocketAddr> = match host.parse::<IpAddr>() {
+        Ok(ip) => vec![SocketAddr::new(ip, port)],
+        Err(_) => tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|e| ExtractError::FetchError(format!("Failed to resolve {}: {}", host, e)))?
+            .collect(),
+    };
+    if addrs.is_empty() {
+        return Err(ExtractError::FetchError(format!(
+            "{} did not resolve",
+            host
+        )));
+    }
+    Ok(addrs)
+}
+
+fn ensure_public(host: &str, addrs: &[SocketAddr]) -> Result<(), ExtractError> {
+    match addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+        Some(addr) => Err(ExtractError::Blocked(format!(
+            "{} resolves to the non-public address {}",
+            host,
+            addr.ip()
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Whether an address is reachable on the public internet
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                // Shared address space (100.64.0.0/10) and "this network" (0.0.0.0/8)
+                || (a == 100 && (b & 0xc0) == 64)
+                || a == 0)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(v4));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // Unique local (fc00::/7) and link-local (fe80::/10)
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+
+    fn loader(filter_list: &[&str], allow_local: bool) -> UrlLoader {
+        UrlLoader::new(
+            Loader::new(reqwest::Client::new(), "", "http://localhost:9998", false).unwrap(),
+            &filter_list
+                .iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>(),
+            allow_local,
+            1024,
+            Duration::from_secs(5),
+        )
+    }
+
+    async fn start_server() -> SocketAddr {
+        let server = HttpServer::new(|| {
+            App::new()
+                .route(
+                    "/page",
+                    web::get().to(|| async {
+                        HttpResponse::Ok().content_type("text/html").body(
+                            "<html><head><title>Doc</title></head><body>\
+                             <nav>Menu</nav><article><p>Hello</p><p>World</p></article></body></html>",
+                        )
+                    }),
+                )
+                .route(
+                    "/notes.txt",
+                    web::get().to(|| async {
+                        HttpResponse::Ok().content_type("text/plain").body("plain notes")
+                    }),
+                )
+                .route(
+                    "/large",
+                    web::get().to(|| async { HttpResponse::Ok().body("x".repeat(4096)) }),
+                )
+                .route(
+                    "/redirect",
+                    web::get().to(|req: HttpRequest| async move {
+                        let port = req.app_config().local_addr().port();
+                        HttpResponse::Found()
+                            .insert_header(("Location", format!("http://127.0.0.1:{}/page", port)))
+                            .finish()
+                    }),
+                )
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+        addr
+    }
+
+    #[test]
+    fn test_is_public_ip() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in ["93.184.216.34", "1.1.1.1", "2606:4700::1111"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_rejects_local_and_filtered_urls() {
+        let addr = start_server().await;
+
+        let err = loader(&[], false)
+            .load(&format!("http://{}/page", addr))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ExtractError::Blocked(_)), "{}", err);
+
+        let err = loader(&["!127.0.0.1"], true)
+            .load(&format!("http://{}/page", addr))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ExtractError::Blocked(_)), "{}", err);
+
+        let err = loader(&[], true)
+            .load("file:///etc/passwd")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ExtractError::Blocked(_)), "{}", err);
+
+        // An allow-listed host may not redirect to a local address that isn't
+        let err = loader(&["localhost"], false)
+            .load(&format!("http://localhost:{}/redirect", addr.port()))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ExtractError::Blocked(_)), "{}", err);
+    }
+
+    #[actix_web::test]
+    async fn test_loads_pages_within_limits() {
+        let addr = start_server().await;
+        let loader = loader(&["127.0.0.1"], false);
+
+        let url = format!("http://{}/redirect", addr);
+        let docs = loader.load(&url).await.unwrap();
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].page_content, "Hello\nWorld");
+        assert_eq!(docs[0].metadata["title"], "Doc");
+        assert_eq!(docs[0].metadata["source"], url.as_str());
+
+        let docs = loader
+            .load(&format!("http://{}/notes.txt", addr))
+            .await
+            .unwrap();
+        assert_eq!(docs[0].page_content, "plain notes");
+
+        let err = loader
+            .load(&format!("http://{}/large", addr))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("larger than"), "{}", err);
+    }
+}
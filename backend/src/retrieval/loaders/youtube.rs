@@ -0,0 +1,272 @@
+use quick_xml::events::Event;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::types::{Document, ExtractError};
+use crate::config::Config;
+
+const YOUTUBE_URL: &str = "https://www.youtube.com";
+
+/// Loads the caption track of a YouTube video as a document
+///
+/// The caption tracks are read from the watch page's player response; the
+/// first track matching YOUTUBE_LOADER_LANGUAGE (in order) is used, falling
+/// back to whichever track the video has.
+pub struct YoutubeLoader {
+    client: reqwest::Client,
+    languages: Vec<String>,
+    base_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CaptionTrack {
+    base_url: String,
+    #[serde(default)]
+    language_code: String,
+    #[serde(default)]
+    kind: Option<String>,
+}
+
+/// Extract the video id from the usual YouTube URL shapes
+pub fn video_id(url: &str) -> Option<String> {
+    let url = url::Url::parse(url).ok()?;
+    let host = url
+        .host_str()?
+        .trim_start_matches("www.")
+        .trim_start_matches("m.");
+    let id = match host {
+        "youtu.be" => url.path_segments()?.next()?.to_string(),
+        "youtube.com" | "music.youtube.com" | "youtube-nocookie.com" => {
+            let mut segments = url.path_segments()?;
+            match segments.next()? {
+                "watch" => url
+                    .query_pairs()
+                    .find(|(key, _)| key == "v")
+                    .map(|(_, v)| v.into_owned())?,
+                "shorts" | "embed" | "live" | "v" => segments.next()?.to_string(),
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+
+    let valid = id.len() == 11
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    valid.then_some(id)
+}
+
+/// Parse the JSON value that follows `key` in a page
+fn json_after(page: &str, key: &str) -> Option<Value> {
+    let start = page.find(key)? + key.len();
+    serde_json::Deserializer::from_str(&page[start..])
+        .into_iter::<Value>()
+        .next()?
+        .ok()
+}
+
+/// Join the text of a timedtext caption document
+fn parse_transcript(xml: &str) -> Result<String, ExtractError> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let mut lines = Vec::new();
+    loop {
+        match reader.read_event() {
+            Ok(Event::Text(text)) => {
+                let text = text
+                    .unescape()
+                    .map_err(|e| ExtractError::ParseError(e.to_string()))?;
+                // Caption text is HTML-escaped a second time
+                let text = quick_xml::escape::unescape(&text)
+                    .map(|t| t.into_owned())
+                    .unwrap_or_else(|_| text.into_owned());
+                let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                if !text.is_empty() {
+                    lines.push(text);
+                }
+            }
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(e) => return Err(ExtractError::ParseError(e.to_string())),
+        }
+    }
+    Ok(lines.join(" "))
+}
+
+impl YoutubeLoader {
+    pub fn new(languages: Vec<String>, proxy_url: &str) -> Result<Self, ExtractError> {
+        let mut builder = reqwest::Client::builder().timeout(std::time::Duration::from_secs(30));
+        if !proxy_url.is_empty() {
+            let proxy = reqwest::Proxy::all(proxy_url).map_err(|e| {
+                ExtractError::ConfigError(format!("Invalid YOUTUBE_LOADER_PROXY_URL: {}", e))
+            })?;
+            builder = builder.proxy(proxy);
+        }
+        Ok(Self {
+            client: builder
+                .build()
+                .map_err(|e| ExtractError::ConfigError(e.to_string()))?,
+            languages,
+            base_url: YOUTUBE_URL.to_string(),
+        })
+    }
+
+    /// Create a loader from the YouTube loader settings
+    pub fn from_config(config: &Config) -> Result<Self, ExtractError> {
+        Self::new(
+            config.youtube_loader_language.clone(),
+            &config.youtube_loader_proxy_url,
+        )
+    }
+
+    fn pick_track(&self, tracks: Vec<CaptionTrack>) -> Option<CaptionTrack> {
+        let position = self
+            .languages
+            .iter()
+            .find_map(|language| {
+                // Prefer manual captions over auto-generated ones
+                tracks
+                    .iter()
+                    .position(|t| &t.language_code == language && t.kind.is_none())
+                    .or_else(|| tracks.iter().position(|t| &t.language_code == language))
+            })
+            .unwrap_or(0);
+        tracks.into_iter().nth(position)
+    }
+
+    /// Load the transcript of the video at `url`
+    pub async fn load(&self, url: &str) -> Result<Vec<Document>, ExtractError> {
+        let id = video_id(url)
+            .ok_or_else(|| ExtractError::UnsupportedType(format!("Not a YouTube URL: {}", url)))?;
+
+        let page = self
+            .client
+            .get(format!("{}/watch", self.base_url))
+            .query(&[("v", id.as_str())])
+            .header("Accept-Language", "en-US,en;q=0.9")
+            .header("Cookie", "CONSENT=YES+1")
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| ExtractError::FetchError(e.to_string()))?
+            .text()
+            .await
+            .map_err(|e| ExtractError::FetchError(e.to_string()))?;
+
+        let tracks: Vec<CaptionTrack> = json_after(&page, "\"captionTracks\":")
+            .and_then(|tracks| serde_json::from_value(tracks).ok())
+            .unwrap_or_default();
+        let track = self.pick_track(tracks).ok_or_else(|| {
+            ExtractError::ParseError(format!("No transcript available for video {}", id))
+        })?;
+        let title = json_after(&page, "\"videoDetails\":")
+            .and_then(|details| details.get("title").cloned())
+            .unwrap_or(Value::Null);
+
+        let xml = self
+            .client
+            .get(&track.base_url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| ExtractError::FetchError(e.to_string()))?
+            .text()
+            .await
+            .map_err(|e| ExtractError::FetchError(e.to_string()))?;
+        let transcript = parse_transcript(&xml)?;
+        if transcript.is_empty() {
+            return Err(ExtractError::ParseError(format!(
+                "Empty transcript for video {}",
+                id
+            )));
+        }
+
+        Ok(vec![Document::new(
+            transcript,
+            json!({
+                "source": url,
+                "video_id": id,
+                "title": title,
+                "language": track.language_code,
+            }),
+        )])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_video_id() {
+        for url in [
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=42",
+            "https://youtu.be/dQw4w9WgXcQ?si=abc",
+            "https://m.youtube.com/shorts/dQw4w9WgXcQ",
+            "https://www.youtube.com/embed/dQw4w9WgXcQ",
+        ] {
+            assert_eq!(video_id(url).as_deref(), Some("dQw4w9WgXcQ"), "{}", url);
+        }
+        assert_eq!(video_id("https://www.youtube.com/channel/abc"), None);
+        assert_eq!(video_id("https://example.com/watch?v=dQw4w9WgXcQ"), None);
+        assert_eq!(video_id("https://youtu.be/short"), None);
+    }
+
+    #[actix_web::test]
+    async fn test_load_transcript() {
+        let server = HttpServer::new(|| {
+            App::new()
+                .route(
+                    "/watch",
+                    web::get().to(|req: HttpRequest| async move {
+                        let origin = format!("http://{}", req.connection_info().host());
+                        let player = json!({
+                            "captions": { "playerCaptionsTracklistRenderer": { "captionTracks": [
+                                { "baseUrl": format!("{}/timedtext?lang=en&kind=asr", origin), "languageCode": "en", "kind": "asr" },
+                                { "baseUrl": format!("{}/timedtext?lang=de", origin), "languageCode": "de" },
+                                { "baseUrl": format!("{}/timedtext?lang=en", origin), "languageCode": "en" },
+                            ]}},
+                            "videoDetails": { "videoId": "dQw4w9WgXcQ", "title": "Demo video" },
+                        });
+                        HttpResponse::Ok().body(format!(
+                            "<script>var ytInitialPlayerResponse = {};var meta = {{}};</script>",
+                            player
+                        ))
+                    }),
+                )
+                .route(
+                    "/timedtext",
+                    web::get().to(|query: web::Query<HashMap<String, String>>| async move {
+                        let body = match (query["lang"].as_str(), query.get("kind")) {
+                            ("en", None) => {
+                                r#"<?xml version="1.0" encoding="utf-8" ?><transcript><text start="0" dur="1.5">Hello there</text><text start="1.5" dur="2">it&amp;#39;s   a demo</text></transcript>"#
+                            }
+                            _ => r#"<transcript><text start="0" dur="1">wrong track</text></transcript>"#,
+                        };
+                        HttpResponse::Ok().content_type("text/xml").body(body)
+                    }),
+                )
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+
+        let loader = YoutubeLoader {
+            base_url: format!("http://{}", addr),
+            ..YoutubeLoader::new(vec!["fr".to_string(), "en".to_string()], "").unwrap()
+        };
+        let url = "https://youtu.be/dQw4w9WgXcQ";
+        let docs = loader.load(url).await.unwrap();
+
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].page_content, "Hello there it's a demo");
+        assert_eq!(docs[0].metadata["title"], "Demo video");
+        assert_eq!(docs[0].metadata["language"], "en");
+        assert_eq!(docs[0].metadata["source"], url);
+    }
+}
@@ -1,7 +1,13 @@
+pub mod bm25;
 pub mod chunking;
 pub mod embeddings;
+pub mod loaders;
+pub mod reranker;
 pub mod vector;
+pub mod web;
 
-pub use chunking::{chunk_text, ChunkingConfig};
+pub use chunking::ChunkingConfig;
 pub use embeddings::{EmbeddingError, EmbeddingFactory, EmbeddingFunction, EmbeddingProvider};
+pub use loaders::{Document, Loader};
+pub use reranker::Reranker;
 pub use vector::{VectorDB, VectorDBFactory, VectorError};
@@ -0,0 +1,292 @@
+use super::embeddings::EmbeddingError;
+use std::sync::Arc;
+use tracing::info;
+
+#[cfg(feature = "embeddings")]
+use candle_core::{Device, Tensor};
+#[cfg(feature = "embeddings")]
+use candle_nn::{Linear, Module, VarBuilder};
+#[cfg(feature = "embeddings")]
+use hf_hub::{api::sync::Api, Repo, RepoType};
+#[cfg(feature = "embeddings")]
+use std::path::PathBuf;
+#[cfg(feature = "embeddings")]
+use tokenizers::Tokenizer;
+#[cfg(feature = "embeddings")]
+use tokio::sync::RwLock;
+
+/// Trait for models that score (query, document) pairs
+#[async_trait::async_trait]
+pub trait Reranker: Send + Sync {
+    /// Relevance score for each document, in the same order, higher is better
+    async fn rerank(&self, query: &str, documents: &[String]) -> Result<Vec<f32>, EmbeddingError>;
+
+    /// Get the model name
+    fn model_name(&self) -> &str;
+}
+
+/// Cross-encoder reranker (local BERT-style sequence classification models)
+///
+/// Works with the `cross-encoder/ms-marco-*` family and other single-label
+/// BERT classifiers. Scores are passed through a sigmoid so they land in the
+/// same 0 -> 1 range as the vector search relevance scores.
+#[cfg(feature = "embeddings")]
+pub struct CrossEncoderReranker {
+    model_name: String,
+    model_path: PathBuf,
+    /// Cache for loaded model components
+    model_cache: Arc<RwLock<Option<CrossEncoderComponents>>>,
+}
+
+#[cfg(feature = "embeddings")]
+struct CrossEncoderComponents {
+    model: candle_transformers::models::bert::BertModel,
+    pooler: Linear,
+    classifier: Linear,
+    tokenizer: Tokenizer,
+    device: Device,
+}
+
+#[cfg(feature = "embeddings")]
+impl CrossEncoderReranker {
+    /// Create a new cross-encoder reranker, downloading the model if needed
+    pub fn new(model_name: String) -> Result<Self, EmbeddingError> {
+        info!("Initializing cross-encoder reranker: {}", model_name);
+
+        let model_path = Self::get_model_path(&model_name)?;
+
+        Ok(Self {
+            model_name,
+            model_path,
+            model_cache: Arc::new(RwLock::new(None)),
+        })
+    }
+
+    /// Get the local path to a model, downloading from HuggingFace Hub if needed
+    fn get_model_path(model_name: &str) -> Result<PathBuf, EmbeddingError> {
+        let model_path = PathBuf::from(model_name);
+        if model_path.exists() {
+            info!("Using local reranking model path: {:?}", model_path);
+            return Ok(model_path);
+        }
+
+        if let Ok(cache) = std::env::var("SENTENCE_TRANSFORMERS_HOME") {
+            std::env::set_var("HF_HOME", cache);
+        }
+
+        let api = Api::new().map_err(|e| {
+            EmbeddingError::ConfigError(format!("Failed to initialize HF API: {}", e))
+        })?;
+        let repo = api.repo(Repo::new(model_name.to_string(), RepoType::Model));
+
+        let config_path = repo.get("config.json").map_err(|e| {
+            EmbeddingError::ConfigError(format!(
+                "Failed to download reranking model config for {}: {}",
+                model_name, e
+            ))
+        })?;
+        repo.get("tokenizer.json").map_err(|e| {
+            EmbeddingError::ConfigError(format!("Failed to download tokenizer: {}", e))
+        })?;
+        if repo.get("model.safetensors").is_err() {
+            repo.get("pytorch_model.bin").map_err(|e| {
+                EmbeddingError::ConfigError(format!("Failed to download model weights: {}", e))
+            })?;
+        }
+
+        config_path
+            .parent()
+            .map(|p| p.to_path_buf())
+            .ok_or_else(|| EmbeddingError::ConfigError("Invalid model path".to_string()))
+    }
+
+    /// Load model components (lazy loading)
+    async fn load_model(&self) -> Result<(), EmbeddingError> {
+        {
+            let cache = self.model_cache.read().await;
+            if cache.is_some() {
+                return Ok(());
+            }
+        }
+
+        let model_path = self.model_path.clone();
+        let components =
+            tokio::task::spawn_blocking(move || Self::load_model_blocking(&model_path))
+                .await
+                .map_err(|e| EmbeddingError::ModelError(format!("Task join error: {}", e)))??;
+
+        let mut cache = self.model_cache.write().await;
+        *cache = Some(components);
+
+        Ok(())
+    }
+
+    /// Load model components (blocking operation)
+    fn load_model_blocking(model_path: &PathBuf) -> Result<CrossEncoderComponents, EmbeddingError> {
+        use candle_transformers::models::bert::{BertModel, Config as BertConfig};
+
+        info!("Loading reranking model from: {:?}", model_path);
+
+        let tokenizer = Tokenizer::from_file(model_path.join("tokenizer.json"))
+            .map_err(|e| EmbeddingError::ModelError(format!("Failed to load tokenizer: {}", e)))?;
+
+        let config_str = std::fs::read_to_string(model_path.join("config.json"))
+            .map_err(|e| EmbeddingError::ModelError(format!("Failed to read config: {}", e)))?;
+        let config: BertConfig = serde_json::from_str(&config_str)
+            .map_err(|e| EmbeddingError::ModelError(format!("Failed to parse config: {}", e)))?;
+
+        let device = Device::Cpu;
+
+        let weights_path = model_path.join("model.safetensors");
+        let weights_path_alt = model_path.join("pytorch_model.bin");
+        let vb = if weights_path.exists() {
+            unsafe {
+                VarBuilder::from_mmaped_safetensors(
+                    &[weights_path],
+                    candle_core::DType::F32,
+                    &device,
+                )
+                .map_err(|e| {
+                    EmbeddingError::ModelError(format!("Failed to load safetensors: {}", e))
+                })?
+            }
+        } else if weights_path_alt.exists() {
+            VarBuilder::from_pth(&weights_path_alt, candle_core::DType::F32, &device).map_err(
+                |e| EmbeddingError::ModelError(format!("Failed to load pytorch weights: {}", e)),
+            )?
+        } else {
+            return Err(EmbeddingError::ModelError(
+                "No model weights found (model.safetensors or pytorch_model.bin)".to_string(),
+            ));
+        };
+
+        let model = BertModel::load(vb.clone(), &config)
+            .map_err(|e| EmbeddingError::ModelError(format!("Failed to load BERT model: {}", e)))?;
+
+        // BertForSequenceClassification: tanh(pooler(CLS)) -> classifier
+        let pooler = candle_nn::linear(
+            config.hidden_size,
+            config.hidden_size,
+            vb.pp("bert.pooler.dense"),
+        )
+        .map_err(|e| EmbeddingError::ModelError(format!("Failed to load pooler: {}", e)))?;
+        let classifier = candle_nn::linear(config.hidden_size, 1, vb.pp("classifier"))
+            .map_err(|e| EmbeddingError::ModelError(format!("Failed to load classifier: {}", e)))?;
+
+        Ok(CrossEncoderComponents {
+            model,
+            pooler,
+            classifier,
+            tokenizer,
+            device,
+        })
+    }
+
+    /// Score a single (query, document) pair with the loaded model
+    fn score_pair(
+        components: &CrossEncoderComponents,
+        query: &str,
+        document: &str,
+    ) -> Result<f32, EmbeddingError> {
+        let model_error = |e: candle_core::Error| EmbeddingError::ModelError(e.to_string());
+
+        let encoding = components
+            .tokenizer
+            .encode((query, document), true)
+            .map_err(|e| EmbeddingError::ModelError(format!("Tokenization failed: {}", e)))?;
+
+        let token_ids = Tensor::new(encoding.get_ids(), &components.device)
+            .and_then(|t| t.unsqueeze(0))
+            .map_err(model_error)?;
+        let token_type_ids = Tensor::new(encoding.get_type_ids(), &components.device)
+            .and_then(|t| t.unsqueeze(0))
+            .map_err(model_error)?;
+        let attention_mask = Tensor::new(encoding.get_attention_mask(), &components.device)
+            .and_then(|t| t.unsqueeze(0))
+            .map_err(model_error)?;
+
+        let hidden = components
+            .model
+            .forward(&token_ids, &token_type_ids, Some(&attention_mask))
+            .map_err(model_error)?;
+
+        let cls = hidden
+            .narrow(1, 0, 1)
+            .and_then(|t| t.squeeze(1))
+            .map_err(model_error)?;
+        let pooled = components
+            .pooler
+            .forward(&cls)
+            .and_then(|t| t.tanh())
+            .map_err(model_error)?;
+        let logit = components
+            .classifier
+            .forward(&pooled)
+            .and_then(|t| t.flatten_all())
+            .and_then(|t| t.to_vec1::<f32>())
+            .map_err(model_error)?
+            .first()
+            .copied()
+            .ok_or_else(|| EmbeddingError::ModelError("Empty classifier output".to_string()))?;
+
+        Ok(1.0 / (1.0 + (-logit).exp()))
+    }
+}
+
+#[cfg(feature = "embeddings")]
+#[async_trait::async_trait]
+impl Reranker for CrossEncoderReranker {
+    async fn rerank(&self, query: &str, documents: &[String]) -> Result<Vec<f32>, EmbeddingError> {
+        if documents.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        self.load_model().await?;
+
+        let model_cache = self.model_cache.clone();
+        let query = query.to_string();
+        let documents = documents.to_vec();
+
+        tokio::task::spawn_blocking(move || {
+            let cache = futures::executor::block_on(model_cache.read());
+            let components = cache
+                .as_ref()
+                .ok_or_else(|| EmbeddingError::ModelError("Model not loaded".to_string()))?;
+
+            documents
+                .iter()
+                .map(|document| Self::score_pair(components, &query, document))
+                .collect()
+        })
+        .await
+        .map_err(|e| EmbeddingError::ModelError(format!("Task join error: {}", e)))?
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model_name
+    }
+}
+
+/// Create the configured reranker, if any
+///
+/// An empty model name disables cross-encoder reranking; hybrid search then
+/// falls back to embedding similarity.
+pub fn create_reranker(model_name: &str) -> Result<Option<Arc<dyn Reranker>>, EmbeddingError> {
+    if model_name.trim().is_empty() {
+        return Ok(None);
+    }
+
+    info!("Creating reranker: {}", model_name);
+
+    #[cfg(feature = "embeddings")]
+    {
+        let reranker = CrossEncoderReranker::new(model_name.to_string())?;
+        Ok(Some(Arc::new(reranker)))
+    }
+    #[cfg(not(feature = "embeddings"))]
+    {
+        Err(EmbeddingError::ConfigError(
+            "Local reranking support not compiled. Enable the 'embeddings' feature to use RAG_RERANKING_MODEL".to_string(),
+        ))
+    }
+}
@@ -106,8 +106,12 @@ impl ChromaClient {
     ) -> Result<ChromaCollection, VectorError> {
         debug!("Getting or creating collection: {}", collection_name);
 
+        // Use cosine space so search distances can be mapped to relevance scores
+        let mut metadata = Map::new();
+        metadata.insert("hnsw:space".to_string(), Value::String("cosine".to_string()));
+
         self.client
-            .get_or_create_collection(collection_name, None)
+            .get_or_create_collection(collection_name, Some(metadata))
             .await
             .map_err(|e| {
                 VectorError::DatabaseError(format!(
@@ -308,7 +312,13 @@ impl VectorDB for ChromaClient {
                     })
                     .collect()
             }),
-            distances: result.distances,
+            // Chroma returns cosine distance, 2 (worst) -> 0 (best). Re-order to 0 -> 1
+            distances: result.distances.map(|dists| {
+                dists
+                    .into_iter()
+                    .map(|dist_vec| dist_vec.into_iter().map(|d| (2.0 - d) / 2.0).collect())
+                    .collect()
+            }),
         };
 
         debug!(
@@ -1,4 +1,7 @@
 use super::chroma::{ChromaClient, ChromaConfig};
+use super::local::{LocalVectorConfig, LocalVectorDB};
+use super::pgvector::{PgVectorClient, PgVectorConfig};
+use super::qdrant::{QdrantClient, QdrantConfig};
 use super::types::{VectorDB, VectorError};
 use std::sync::Arc;
 use tracing::info;
@@ -7,7 +10,11 @@ use tracing::info;
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum VectorDBType {
     Chroma,
-    // Future: Qdrant, Milvus, etc.
+    /// Embedded SQLite store, no external server required
+    Local,
+    Qdrant,
+    PgVector,
+    // Future: Milvus, etc.
 }
 
 impl VectorDBType {
@@ -15,8 +22,11 @@ impl VectorDBType {
     pub fn from_str(s: &str) -> Result<Self, VectorError> {
         match s.to_lowercase().as_str() {
             "chroma" => Ok(VectorDBType::Chroma),
+            "local" => Ok(VectorDBType::Local),
+            "qdrant" => Ok(VectorDBType::Qdrant),
+            "pgvector" => Ok(VectorDBType::PgVector),
             _ => Err(VectorError::ConfigError(format!(
-                "Unsupported VECTOR_DB type: {}. Supported types: chroma",
+                "Unsupported VECTOR_DB type: {}. Supported types: chroma, local, qdrant, pgvector",
                 s
             ))),
         }
@@ -37,6 +47,21 @@ impl VectorDBFactory {
                 let client = ChromaClient::new(config).await?;
                 Ok(Arc::new(client))
             }
+            VectorDBType::Local => {
+                let config = LocalVectorConfig::from_env()?;
+                let client = LocalVectorDB::new(config).await?;
+                Ok(Arc::new(client))
+            }
+            VectorDBType::Qdrant => {
+                let config = QdrantConfig::from_env()?;
+                let client = QdrantClient::new(config).await?;
+                Ok(Arc::new(client))
+            }
+            VectorDBType::PgVector => {
+                let config = PgVectorConfig::from_env()?;
+                let client = PgVectorClient::new(config).await?;
+                Ok(Arc::new(client))
+            }
         }
     }
 
@@ -75,6 +100,18 @@ mod tests {
             VectorDBType::from_str("CHROMA").unwrap(),
             VectorDBType::Chroma
         );
+        assert_eq!(
+            VectorDBType::from_str("local").unwrap(),
+            VectorDBType::Local
+        );
+        assert_eq!(
+            VectorDBType::from_str("qdrant").unwrap(),
+            VectorDBType::Qdrant
+        );
+        assert_eq!(
+            VectorDBType::from_str("pgvector").unwrap(),
+            VectorDBType::PgVector
+        );
         assert!(VectorDBType::from_str("unsupported").is_err());
     }
 
@@ -0,0 +1,208 @@
+use super::types::VectorError;
+use serde_json::Value;
+
+/// Comparison operators supported in Chroma-style `where` filters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterOp {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    In,
+    Nin,
+}
+
+impl FilterOp {
+    fn from_str(op: &str) -> Result<Self, VectorError> {
+        match op {
+            "$eq" => Ok(FilterOp::Eq),
+            "$ne" => Ok(FilterOp::Ne),
+            "$gt" => Ok(FilterOp::Gt),
+            "$gte" => Ok(FilterOp::Gte),
+            "$lt" => Ok(FilterOp::Lt),
+            "$lte" => Ok(FilterOp::Lte),
+            "$in" => Ok(FilterOp::In),
+            "$nin" => Ok(FilterOp::Nin),
+            _ => Err(VectorError::OperationError(format!(
+                "Unsupported filter operator: {}",
+                op
+            ))),
+        }
+    }
+}
+
+/// Parsed metadata filter, using the Chroma `where` syntax as the common format
+///
+/// `{"file_id": "abc"}`, `{"page": {"$gte": 2}}` and
+/// `{"$or": [{"name": "a"}, {"name": "b"}]}` are all accepted. Objects with
+/// several keys are combined with `$and`. Backends either evaluate the filter
+/// directly or translate it into their native query language.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetadataFilter {
+    And(Vec<MetadataFilter>),
+    Or(Vec<MetadataFilter>),
+    Field {
+        key: String,
+        op: FilterOp,
+        value: Value,
+    },
+}
+
+impl MetadataFilter {
+    /// Parse a Chroma-style filter
+    pub fn parse(filter: &Value) -> Result<Self, VectorError> {
+        let obj = filter.as_object().ok_or_else(|| {
+            VectorError::OperationError(format!("Filter must be an object, got: {}", filter))
+        })?;
+
+        let mut clauses = Vec::with_capacity(obj.len());
+        for (key, value) in obj {
+            let clause = match key.as_str() {
+                "$and" | "$or" => {
+                    let items = value
+                        .as_array()
+                        .ok_or_else(|| {
+                            VectorError::OperationError(format!("{} expects an array", key))
+                        })?
+                        .iter()
+                        .map(Self::parse)
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if key == "$and" {
+                        MetadataFilter::And(items)
+                    } else {
+                        MetadataFilter::Or(items)
+                    }
+                }
+                _ => Self::parse_field(key, value)?,
+            };
+            clauses.push(clause);
+        }
+
+        if clauses.len() == 1 {
+            Ok(clauses.remove(0))
+        } else {
+            Ok(MetadataFilter::And(clauses))
+        }
+    }
+
+    fn parse_field(key: &str, value: &Value) -> Result<Self, VectorError> {
+        let (op, value) = match value.as_object() {
+            Some(ops) => {
+                if ops.len() != 1 {
+                    return Err(VectorError::OperationError(format!(
+                        "Filter on '{}' must contain exactly one operator",
+                        key
+                    )));
+                }
+                let (op, value) = ops.iter().next().unwrap();
+                (FilterOp::from_str(op)?, value.clone())
+            }
+            None => (FilterOp::Eq, value.clone()),
+        };
+
+        if matches!(op, FilterOp::In | FilterOp::Nin) && !value.is_array() {
+            return Err(VectorError::OperationError(format!(
+                "Filter on '{}' expects an array",
+                key
+            )));
+        }
+
+        Ok(MetadataFilter::Field {
+            key: key.to_string(),
+            op,
+            value,
+        })
+    }
+
+    /// Evaluate the filter against a metadata object
+    pub fn matches(&self, metadata: &Value) -> bool {
+        match self {
+            MetadataFilter::And(filters) => filters.iter().all(|f| f.matches(metadata)),
+            MetadataFilter::Or(filters) => filters.iter().any(|f| f.matches(metadata)),
+            MetadataFilter::Field { key, op, value } => {
+                let field = metadata.get(key);
+                match op {
+                    FilterOp::Eq => field.is_some_and(|f| values_equal(f, value)),
+                    FilterOp::Ne => !field.is_some_and(|f| values_equal(f, value)),
+                    FilterOp::Gt => compare(field, value).is_some_and(|o| o.is_gt()),
+                    FilterOp::Gte => compare(field, value).is_some_and(|o| o.is_ge()),
+                    FilterOp::Lt => compare(field, value).is_some_and(|o| o.is_lt()),
+                    FilterOp::Lte => compare(field, value).is_some_and(|o| o.is_le()),
+                    FilterOp::In => field.is_some_and(|f| array_contains(value, f)),
+                    FilterOp::Nin => !field.is_some_and(|f| array_contains(value, f)),
+                }
+            }
+        }
+    }
+}
+
+fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(x), Some(y)) => x == y,
+        _ => a == b,
+    }
+}
+
+fn compare(field: Option<&Value>, value: &Value) -> Option<std::cmp::Ordering> {
+    let field = field?;
+    match (field.as_f64(), value.as_f64()) {
+        (Some(x), Some(y)) => x.partial_cmp(&y),
+        _ => match (field.as_str(), value.as_str()) {
+            (Some(x), Some(y)) => Some(x.cmp(y)),
+            _ => None,
+        },
+    }
+}
+
+fn array_contains(array: &Value, item: &Value) -> bool {
+    array
+        .as_array()
+        .is_some_and(|values| values.iter().any(|v| values_equal(v, item)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_implicit_and() {
+        let filter = MetadataFilter::parse(&json!({"file_id": "a", "page": {"$gt": 1}})).unwrap();
+        match filter {
+            MetadataFilter::And(clauses) => assert_eq!(clauses.len(), 2),
+            other => panic!("expected And, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_operator() {
+        assert!(MetadataFilter::parse(&json!({"page": {"$near": 1}})).is_err());
+        assert!(MetadataFilter::parse(&json!({"page": {"$in": 1}})).is_err());
+        assert!(MetadataFilter::parse(&json!("file_id")).is_err());
+    }
+
+    #[test]
+    fn test_matches() {
+        let meta = json!({"file_id": "a", "page": 2, "name": "doc.pdf"});
+
+        let cases = [
+            (json!({"file_id": "a"}), true),
+            (json!({"file_id": {"$ne": "a"}}), false),
+            (json!({"page": {"$gte": 2.0}}), true),
+            (json!({"page": {"$lt": 2}}), false),
+            (json!({"name": {"$in": ["doc.pdf", "x"]}}), true),
+            (json!({"name": {"$nin": ["doc.pdf"]}}), false),
+            (json!({"missing": {"$ne": "x"}}), true),
+            (json!({"$or": [{"file_id": "b"}, {"page": 2}]}), true),
+            (json!({"$and": [{"file_id": "a"}, {"page": 3}]}), false),
+            (json!({}), true),
+        ];
+
+        for (filter, expected) in cases {
+            let parsed = MetadataFilter::parse(&filter).unwrap();
+            assert_eq!(parsed.matches(&meta), expected, "filter: {}", filter);
+        }
+    }
+}
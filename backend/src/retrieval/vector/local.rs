@@ -0,0 +1,597 @@
+use super::filter::MetadataFilter;
+use super::types::{GetResult, SearchResult, VectorDB, VectorError, VectorItem};
+use async_trait::async_trait;
+use serde_json::Value;
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+    Row, SqlitePool,
+};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+use tracing::{debug, info};
+
+const LOCAL_VECTOR_SCHEMA: &[&str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS vector_collection (
+        name TEXT PRIMARY KEY NOT NULL,
+        dimension INTEGER NOT NULL,
+        created_at INTEGER NOT NULL
+    )
+    "#,
+    r#"
+    CREATE TABLE IF NOT EXISTS vector_item (
+        collection_name TEXT NOT NULL,
+        id TEXT NOT NULL,
+        document TEXT NOT NULL,
+        embedding BLOB NOT NULL,
+        metadata TEXT NOT NULL,
+        PRIMARY KEY (collection_name, id)
+    )
+    "#,
+];
+
+/// Configuration for the embedded vector store
+#[derive(Debug, Clone)]
+pub struct LocalVectorConfig {
+    /// SQLite file holding the vectors, or `:memory:` for a throwaway store
+    pub path: String,
+}
+
+impl LocalVectorConfig {
+    /// Create configuration from environment variables
+    ///
+    /// Uses LOCAL_VECTOR_DB_PATH when set, otherwise `vector_db/vectors.sqlite3`
+    /// under CONFIG_DIR, next to the main database.
+    pub fn from_env() -> Result<Self, VectorError> {
+        let path = match std::env::var("LOCAL_VECTOR_DB_PATH") {
+            Ok(path) => crate::config::Config::expand_home_dir(&path),
+            Err(_) => {
+                let config_dir = std::env::var("CONFIG_DIR")
+                    .unwrap_or_else(|_| "~/.config/open-coreui".to_string());
+                PathBuf::from(crate::config::Config::expand_home_dir(&config_dir))
+                    .join("vector_db")
+                    .join("vectors.sqlite3")
+                    .to_string_lossy()
+                    .to_string()
+            }
+        };
+
+        Ok(Self { path })
+    }
+}
+
+/// A stored row of the embedded vector store
+struct StoredItem {
+    id: String,
+    document: String,
+    embedding: Vec<f32>,
+    metadata: Value,
+}
+
+/// Embedded vector store persisted in SQLite
+///
+/// Similarity search is an exact scan over the collection, which is plenty
+/// for desktop and single-box deployments without an external vector server.
+pub struct LocalVectorDB {
+    pool: SqlitePool,
+}
+
+impl LocalVectorDB {
+    /// Open (or create) the vector store described by the configuration
+    pub async fn new(config: LocalVectorConfig) -> Result<Self, VectorError> {
+        info!("Initializing local vector database: {}", config.path);
+
+        let (url, max_connections) = if config.path == ":memory:" {
+            // Every in-memory connection is a separate database
+            ("sqlite::memory:".to_string(), 1)
+        } else {
+            if let Some(parent) = PathBuf::from(&config.path).parent() {
+                std::fs::create_dir_all(parent).map_err(|e| {
+                    VectorError::ConfigError(format!(
+                        "Failed to create vector database directory: {}",
+                        e
+                    ))
+                })?;
+            }
+            (format!("sqlite://{}", config.path), 5)
+        };
+
+        let connect_options = SqliteConnectOptions::from_str(&url)
+            .map_err(|e| VectorError::ConfigError(e.to_string()))?
+            .create_if_missing(true);
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(max_connections)
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(connect_options)
+            .await
+            .map_err(|e| {
+                VectorError::ConnectionError(format!(
+                    "Failed to open local vector database: {}",
+                    e
+                ))
+            })?;
+
+        for statement in LOCAL_VECTOR_SCHEMA {
+            sqlx::query(statement)
+                .execute(&pool)
+                .await
+                .map_err(Self::db_error)?;
+        }
+
+        info!("Local vector database ready");
+        Ok(Self { pool })
+    }
+
+    fn db_error(e: sqlx::Error) -> VectorError {
+        VectorError::DatabaseError(e.to_string())
+    }
+
+    fn encode_embedding(vector: &[f32]) -> Vec<u8> {
+        vector.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    fn decode_embedding(bytes: &[u8]) -> Vec<f32> {
+        bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect()
+    }
+
+    fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+        let (mut dot, mut norm_a, mut norm_b) = (0.0f32, 0.0f32, 0.0f32);
+        for (x, y) in a.iter().zip(b.iter()) {
+            dot += x * y;
+            norm_a += x * x;
+            norm_b += y * y;
+        }
+        if norm_a == 0.0 || norm_b == 0.0 {
+            return 0.0;
+        }
+        dot / (norm_a.sqrt() * norm_b.sqrt())
+    }
+
+    async fn collection_dimension(&self, collection_name: &str) -> Result<Option<usize>, VectorError> {
+        let row = sqlx::query("SELECT dimension FROM vector_collection WHERE name = $1")
+            .bind(collection_name)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(Self::db_error)?;
+
+        Ok(row.map(|r| r.get::<i64, _>("dimension") as usize))
+    }
+
+    async fn ensure_collection(&self, collection_name: &str) -> Result<(), VectorError> {
+        if self.collection_dimension(collection_name).await?.is_none() {
+            return Err(VectorError::CollectionNotFound(collection_name.to_string()));
+        }
+        Ok(())
+    }
+
+    async fn load_items(&self, collection_name: &str) -> Result<Vec<StoredItem>, VectorError> {
+        let rows = sqlx::query(
+            "SELECT id, document, embedding, metadata FROM vector_item WHERE collection_name = $1 ORDER BY rowid",
+        )
+        .bind(collection_name)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Self::db_error)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| StoredItem {
+                id: row.get("id"),
+                document: row.get("document"),
+                embedding: Self::decode_embedding(&row.get::<Vec<u8>, _>("embedding")),
+                metadata: serde_json::from_str(&row.get::<String, _>("metadata"))
+                    .unwrap_or(Value::Null),
+            })
+            .collect())
+    }
+
+    /// Insert items, replacing existing ids when `replace` is set
+    async fn write_items(
+        &self,
+        collection_name: &str,
+        items: Vec<VectorItem>,
+        replace: bool,
+    ) -> Result<(), VectorError> {
+        if items.is_empty() {
+            debug!("No items to write into collection: {}", collection_name);
+            return Ok(());
+        }
+
+        let dimension = items[0].vector.len();
+        if let Some(item) = items.iter().find(|item| item.vector.len() != dimension) {
+            return Err(VectorError::OperationError(format!(
+                "Item '{}' has dimension {}, expected {}",
+                item.id,
+                item.vector.len(),
+                dimension
+            )));
+        }
+
+        match self.collection_dimension(collection_name).await? {
+            Some(existing) if existing != dimension => {
+                return Err(VectorError::OperationError(format!(
+                    "Collection '{}' expects embeddings of dimension {}, got {}",
+                    collection_name, existing, dimension
+                )));
+            }
+            Some(_) => {}
+            None => {
+                sqlx::query(
+                    "INSERT OR IGNORE INTO vector_collection (name, dimension, created_at) VALUES ($1, $2, $3)",
+                )
+                .bind(collection_name)
+                .bind(dimension as i64)
+                .bind(chrono::Utc::now().timestamp())
+                .execute(&self.pool)
+                .await
+                .map_err(Self::db_error)?;
+            }
+        }
+
+        let statement = if replace {
+            "INSERT OR REPLACE INTO vector_item (collection_name, id, document, embedding, metadata) VALUES ($1, $2, $3, $4, $5)"
+        } else {
+            "INSERT OR IGNORE INTO vector_item (collection_name, id, document, embedding, metadata) VALUES ($1, $2, $3, $4, $5)"
+        };
+
+        let mut tx = self.pool.begin().await.map_err(Self::db_error)?;
+        for item in &items {
+            let metadata = serde_json::to_string(&item.metadata)
+                .map_err(|e| VectorError::SerializationError(e.to_string()))?;
+
+            sqlx::query(statement)
+                .bind(collection_name)
+                .bind(&item.id)
+                .bind(&item.text)
+                .bind(Self::encode_embedding(&item.vector))
+                .bind(metadata)
+                .execute(&mut *tx)
+                .await
+                .map_err(Self::db_error)?;
+        }
+        tx.commit().await.map_err(Self::db_error)?;
+
+        Ok(())
+    }
+
+    fn to_get_result(items: Vec<StoredItem>) -> GetResult {
+        let mut ids = Vec::with_capacity(items.len());
+        let mut documents = Vec::with_capacity(items.len());
+        let mut metadatas = Vec::with_capacity(items.len());
+        for item in items {
+            ids.push(item.id);
+            documents.push(item.document);
+            metadatas.push(item.metadata);
+        }
+
+        GetResult {
+            ids: Some(vec![ids]),
+            documents: Some(vec![documents]),
+            metadatas: Some(vec![metadatas]),
+        }
+    }
+}
+
+#[async_trait]
+impl VectorDB for LocalVectorDB {
+    async fn has_collection(&self, collection_name: &str) -> Result<bool, VectorError> {
+        Ok(self.collection_dimension(collection_name).await?.is_some())
+    }
+
+    async fn delete_collection(&self, collection_name: &str) -> Result<(), VectorError> {
+        info!("Deleting collection: {}", collection_name);
+
+        let mut tx = self.pool.begin().await.map_err(Self::db_error)?;
+        sqlx::query("DELETE FROM vector_item WHERE collection_name = $1")
+            .bind(collection_name)
+            .execute(&mut *tx)
+            .await
+            .map_err(Self::db_error)?;
+        sqlx::query("DELETE FROM vector_collection WHERE name = $1")
+            .bind(collection_name)
+            .execute(&mut *tx)
+            .await
+            .map_err(Self::db_error)?;
+        tx.commit().await.map_err(Self::db_error)?;
+
+        Ok(())
+    }
+
+    async fn insert(
+        &self,
+        collection_name: &str,
+        items: Vec<VectorItem>,
+    ) -> Result<(), VectorError> {
+        info!(
+            "Inserting {} items into collection: {}",
+            items.len(),
+            collection_name
+        );
+        self.write_items(collection_name, items, false).await
+    }
+
+    async fn upsert(
+        &self,
+        collection_name: &str,
+        items: Vec<VectorItem>,
+    ) -> Result<(), VectorError> {
+        info!(
+            "Upserting {} items into collection: {}",
+            items.len(),
+            collection_name
+        );
+        self.write_items(collection_name, items, true).await
+    }
+
+    async fn search(
+        &self,
+        collection_name: &str,
+        vectors: Vec<Vec<f32>>,
+        limit: usize,
+    ) -> Result<SearchResult, VectorError> {
+        debug!(
+            "Searching collection '{}' with {} query vectors, limit: {}",
+            collection_name,
+            vectors.len(),
+            limit
+        );
+
+        self.ensure_collection(collection_name).await?;
+        let items = self.load_items(collection_name).await?;
+
+        let mut ids = Vec::with_capacity(vectors.len());
+        let mut documents = Vec::with_capacity(vectors.len());
+        let mut metadatas = Vec::with_capacity(vectors.len());
+        let mut distances = Vec::with_capacity(vectors.len());
+
+        for query in &vectors {
+            let mut scored: Vec<(usize, f32)> = items
+                .iter()
+                .enumerate()
+                .map(|(idx, item)| (idx, Self::cosine_similarity(query, &item.embedding)))
+                .collect();
+            scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+            scored.truncate(limit);
+
+            ids.push(scored.iter().map(|(i, _)| items[*i].id.clone()).collect());
+            documents.push(
+                scored
+                    .iter()
+                    .map(|(i, _)| items[*i].document.clone())
+                    .collect(),
+            );
+            metadatas.push(
+                scored
+                    .iter()
+                    .map(|(i, _)| items[*i].metadata.clone())
+                    .collect(),
+            );
+            // Cosine similarity is -1 (worst) -> 1 (best). Re-order to 0 -> 1
+            distances.push(scored.iter().map(|(_, s)| (s + 1.0) / 2.0).collect());
+        }
+
+        Ok(SearchResult {
+            ids: Some(ids),
+            documents: Some(documents),
+            metadatas: Some(metadatas),
+            distances: Some(distances),
+        })
+    }
+
+    async fn query(
+        &self,
+        collection_name: &str,
+        filter: Value,
+        limit: Option<usize>,
+    ) -> Result<GetResult, VectorError> {
+        debug!(
+            "Querying collection '{}' with filter: {:?}, limit: {:?}",
+            collection_name, filter, limit
+        );
+
+        let filter = MetadataFilter::parse(&filter)?;
+        self.ensure_collection(collection_name).await?;
+
+        let items = self
+            .load_items(collection_name)
+            .await?
+            .into_iter()
+            .filter(|item| filter.matches(&item.metadata))
+            .take(limit.unwrap_or(usize::MAX))
+            .collect();
+
+        Ok(Self::to_get_result(items))
+    }
+
+    async fn get(&self, collection_name: &str) -> Result<GetResult, VectorError> {
+        debug!("Getting all items from collection: {}", collection_name);
+
+        self.ensure_collection(collection_name).await?;
+        let items = self.load_items(collection_name).await?;
+
+        Ok(Self::to_get_result(items))
+    }
+
+    async fn delete(
+        &self,
+        collection_name: &str,
+        ids: Option<Vec<String>>,
+        filter: Option<Value>,
+    ) -> Result<(), VectorError> {
+        debug!(
+            "Deleting from collection '{}' - ids: {:?}, filter: {:?}",
+            collection_name, ids, filter
+        );
+
+        let mut targets = ids.unwrap_or_default();
+        if let Some(filter) = filter {
+            let filter = MetadataFilter::parse(&filter)?;
+            targets.extend(
+                self.load_items(collection_name)
+                    .await?
+                    .into_iter()
+                    .filter(|item| filter.matches(&item.metadata))
+                    .map(|item| item.id),
+            );
+        }
+
+        let mut tx = self.pool.begin().await.map_err(Self::db_error)?;
+        for id in &targets {
+            sqlx::query("DELETE FROM vector_item WHERE collection_name = $1 AND id = $2")
+                .bind(collection_name)
+                .bind(id)
+                .execute(&mut *tx)
+                .await
+                .map_err(Self::db_error)?;
+        }
+        tx.commit().await.map_err(Self::db_error)?;
+
+        Ok(())
+    }
+
+    async fn reset(&self) -> Result<(), VectorError> {
+        info!("Resetting local vector database");
+
+        let mut tx = self.pool.begin().await.map_err(Self::db_error)?;
+        sqlx::query("DELETE FROM vector_item")
+            .execute(&mut *tx)
+            .await
+            .map_err(Self::db_error)?;
+        sqlx::query("DELETE FROM vector_collection")
+            .execute(&mut *tx)
+            .await
+            .map_err(Self::db_error)?;
+        tx.commit().await.map_err(Self::db_error)?;
+
+        Ok(())
+    }
+
+    async fn get_collection_metadata(
+        &self,
+        collection_name: &str,
+    ) -> Result<HashMap<String, Value>, VectorError> {
+        let dimension = self
+            .collection_dimension(collection_name)
+            .await?
+            .ok_or_else(|| VectorError::CollectionNotFound(collection_name.to_string()))?;
+
+        let mut metadata = HashMap::new();
+        metadata.insert("dimension".to_string(), Value::from(dimension));
+        metadata.insert("hnsw:space".to_string(), Value::from("cosine"));
+        Ok(metadata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    async fn memory_db() -> LocalVectorDB {
+        LocalVectorDB::new(LocalVectorConfig {
+            path: ":memory:".to_string(),
+        })
+        .await
+        .unwrap()
+    }
+
+    fn item(id: &str, vector: Vec<f32>, metadata: Value) -> VectorItem {
+        VectorItem {
+            id: id.to_string(),
+            text: format!("doc {}", id),
+            vector,
+            metadata,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_insert_and_search() {
+        let db = memory_db().await;
+        db.insert(
+            "c",
+            vec![
+                item("a", vec![1.0, 0.0], json!({"file_id": "f1"})),
+                item("b", vec![0.0, 1.0], json!({"file_id": "f2"})),
+                item("c", vec![-1.0, 0.0], json!({"file_id": "f1"})),
+            ],
+        )
+        .await
+        .unwrap();
+
+        assert!(db.has_collection("c").await.unwrap());
+        assert!(!db.has_collection("missing").await.unwrap());
+
+        let result = db.search("c", vec![vec![1.0, 0.1]], 2).await.unwrap();
+        assert_eq!(result.ids.unwrap()[0], vec!["a", "b"]);
+        let distances = result.distances.unwrap();
+        assert!(distances[0][0] > distances[0][1]);
+        assert!(distances[0].iter().all(|d| (0.0..=1.0).contains(d)));
+
+        assert!(db.search("missing", vec![vec![1.0, 0.0]], 2).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_upsert_and_dimension_check() {
+        let db = memory_db().await;
+        db.insert("c", vec![item("a", vec![1.0, 0.0], json!({}))])
+            .await
+            .unwrap();
+
+        let mut updated = item("a", vec![0.0, 1.0], json!({"v": 2}));
+        updated.text = "updated".to_string();
+        db.upsert("c", vec![updated]).await.unwrap();
+
+        let all = db.get("c").await.unwrap();
+        assert_eq!(all.documents.unwrap()[0], vec!["updated"]);
+
+        assert!(db
+            .insert("c", vec![item("b", vec![1.0, 0.0, 0.0], json!({}))])
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_query_and_delete_with_filter() {
+        let db = memory_db().await;
+        db.insert(
+            "c",
+            vec![
+                item("a", vec![1.0, 0.0], json!({"file_id": "f1", "page": 1})),
+                item("b", vec![0.0, 1.0], json!({"file_id": "f2", "page": 2})),
+                item("c", vec![1.0, 1.0], json!({"file_id": "f1", "page": 3})),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let result = db
+            .query("c", json!({"file_id": "f1"}), None)
+            .await
+            .unwrap();
+        assert_eq!(result.ids.unwrap()[0], vec!["a", "c"]);
+
+        let result = db
+            .query("c", json!({"page": {"$gte": 2}}), Some(1))
+            .await
+            .unwrap();
+        assert_eq!(result.ids.unwrap()[0], vec!["b"]);
+
+        db.delete("c", None, Some(json!({"file_id": "f1"})))
+            .await
+            .unwrap();
+        db.delete("c", Some(vec!["b".to_string()]), None)
+            .await
+            .unwrap();
+        assert!(db.get("c").await.unwrap().ids.unwrap()[0].is_empty());
+
+        db.reset().await.unwrap();
+        assert!(!db.has_collection("c").await.unwrap());
+    }
+}
@@ -1,5 +1,9 @@
 pub mod chroma;
 pub mod factory;
+pub mod filter;
+pub mod local;
+pub mod pgvector;
+pub mod qdrant;
 pub mod types;
 
 pub use chroma::ChromaClient;
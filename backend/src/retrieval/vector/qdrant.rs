@@ -0,0 +1,621 @@
+use super::filter::{FilterOp, MetadataFilter};
+use super::types::{GetResult, SearchResult, VectorDB, VectorError, VectorItem};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tracing::{debug, info, warn};
+
+/// Page size used when scrolling through a collection
+const SCROLL_PAGE_SIZE: usize = 256;
+
+/// Configuration for Qdrant
+#[derive(Debug, Clone)]
+pub struct QdrantConfig {
+    pub url: String,
+    pub api_key: Option<String>,
+    /// Prefix added to every collection name so the instance can be shared
+    pub collection_prefix: String,
+}
+
+impl Default for QdrantConfig {
+    fn default() -> Self {
+        Self {
+            url: "http://localhost:6333".to_string(),
+            api_key: None,
+            collection_prefix: "open-webui".to_string(),
+        }
+    }
+}
+
+impl QdrantConfig {
+    /// Create configuration from environment variables
+    pub fn from_env() -> Result<Self, VectorError> {
+        let defaults = Self::default();
+
+        Ok(Self {
+            url: std::env::var("QDRANT_URI").unwrap_or(defaults.url),
+            api_key: std::env::var("QDRANT_API_KEY")
+                .ok()
+                .filter(|k| !k.is_empty()),
+            collection_prefix: std::env::var("QDRANT_COLLECTION_PREFIX")
+                .unwrap_or(defaults.collection_prefix),
+        })
+    }
+}
+
+/// Qdrant client implementation using the REST API
+///
+/// Points store the chunk text and metadata in their payload as
+/// `{"id", "text", "metadata"}`. Qdrant only accepts UUID or integer point ids,
+/// so other ids are mapped to a stable UUID and the original is kept in the payload.
+pub struct QdrantClient {
+    client: reqwest::Client,
+    config: QdrantConfig,
+}
+
+impl QdrantClient {
+    /// Create a new QdrantClient and check the server is reachable
+    pub async fn new(config: QdrantConfig) -> Result<Self, VectorError> {
+        info!(
+            "Initializing Qdrant client: {} (prefix: {})",
+            config.url, config.collection_prefix
+        );
+
+        let client = Self {
+            client: reqwest::Client::new(),
+            config,
+        };
+
+        client
+            .request(reqwest::Method::GET, "/collections")
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| {
+                VectorError::ConnectionError(format!("Failed to connect to Qdrant: {}", e))
+            })?;
+
+        info!("Successfully connected to Qdrant");
+        Ok(client)
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let url = format!("{}{}", self.config.url.trim_end_matches('/'), path);
+        let builder = self.client.request(method, url);
+        match &self.config.api_key {
+            Some(key) => builder.header("api-key", key),
+            None => builder,
+        }
+    }
+
+    fn collection_path(&self, collection_name: &str) -> String {
+        format!(
+            "/collections/{}_{}",
+            self.config.collection_prefix, collection_name
+        )
+    }
+
+    /// Send a request and return the `result` field of the response
+    async fn send(&self, builder: reqwest::RequestBuilder) -> Result<Value, VectorError> {
+        let response = builder
+            .send()
+            .await
+            .map_err(|e| VectorError::ConnectionError(e.to_string()))?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Err(VectorError::CollectionNotFound(
+                response.text().await.unwrap_or_default(),
+            ));
+        }
+        if !status.is_success() {
+            return Err(VectorError::OperationError(format!(
+                "Qdrant returned {}: {}",
+                status,
+                response.text().await.unwrap_or_default()
+            )));
+        }
+
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| VectorError::SerializationError(e.to_string()))?;
+        Ok(body.get("result").cloned().unwrap_or(Value::Null))
+    }
+
+    /// Map an item id to a valid Qdrant point id
+    fn point_id(id: &str) -> String {
+        if uuid::Uuid::parse_str(id).is_ok() {
+            return id.to_string();
+        }
+
+        let digest = Sha256::digest(id.as_bytes());
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(&digest[..16]);
+        uuid::Uuid::from_bytes(bytes).to_string()
+    }
+
+    /// Translate a Chroma-style filter into a Qdrant filter
+    pub fn translate_filter(filter: &Value) -> Result<Value, VectorError> {
+        let filter = MetadataFilter::parse(filter)?;
+        Ok(json!({ "must": [Self::condition(&filter)] }))
+    }
+
+    fn condition(filter: &MetadataFilter) -> Value {
+        match filter {
+            MetadataFilter::And(filters) => {
+                json!({ "must": filters.iter().map(Self::condition).collect::<Vec<_>>() })
+            }
+            MetadataFilter::Or(filters) => {
+                json!({ "should": filters.iter().map(Self::condition).collect::<Vec<_>>() })
+            }
+            MetadataFilter::Field { key, op, value } => {
+                let key = format!("metadata.{}", key);
+                match op {
+                    FilterOp::Eq => json!({ "key": key, "match": { "value": value } }),
+                    FilterOp::Ne => {
+                        json!({ "must_not": [{ "key": key, "match": { "value": value } }] })
+                    }
+                    FilterOp::Gt => json!({ "key": key, "range": { "gt": value } }),
+                    FilterOp::Gte => json!({ "key": key, "range": { "gte": value } }),
+                    FilterOp::Lt => json!({ "key": key, "range": { "lt": value } }),
+                    FilterOp::Lte => json!({ "key": key, "range": { "lte": value } }),
+                    FilterOp::In => json!({ "key": key, "match": { "any": value } }),
+                    FilterOp::Nin => json!({ "key": key, "match": { "except": value } }),
+                }
+            }
+        }
+    }
+
+    async fn ensure_collection(&self, collection_name: &str, dimension: usize) -> Result<(), VectorError> {
+        if self.has_collection(collection_name).await? {
+            return Ok(());
+        }
+
+        info!(
+            "Creating Qdrant collection '{}' with dimension {}",
+            collection_name, dimension
+        );
+        self.send(
+            self.request(reqwest::Method::PUT, &self.collection_path(collection_name))
+                .json(&json!({ "vectors": { "size": dimension, "distance": "Cosine" } })),
+        )
+        .await?;
+        Ok(())
+    }
+
+    fn item_to_point(item: &VectorItem) -> Value {
+        json!({
+            "id": Self::point_id(&item.id),
+            "vector": item.vector,
+            "payload": {
+                "id": item.id,
+                "text": item.text,
+                "metadata": item.metadata,
+            },
+        })
+    }
+
+    /// Split points into (ids, documents, metadatas)
+    fn unpack_points(points: &[Value]) -> (Vec<String>, Vec<String>, Vec<Value>) {
+        let mut ids = Vec::with_capacity(points.len());
+        let mut documents = Vec::with_capacity(points.len());
+        let mut metadatas = Vec::with_capacity(points.len());
+
+        for point in points {
+            let payload = point.get("payload").cloned().unwrap_or(Value::Null);
+            ids.push(
+                payload
+                    .get("id")
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string())
+                    .unwrap_or_else(|| match point.get("id") {
+                        Some(Value::String(s)) => s.clone(),
+                        Some(other) => other.to_string(),
+                        None => String::new(),
+                    }),
+            );
+            documents.push(
+                payload
+                    .get("text")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+            );
+            metadatas.push(payload.get("metadata").cloned().unwrap_or(json!({})));
+        }
+
+        (ids, documents, metadatas)
+    }
+
+    /// Scroll through all points matching an optional filter
+    async fn scroll(
+        &self,
+        collection_name: &str,
+        filter: Option<Value>,
+        limit: Option<usize>,
+    ) -> Result<GetResult, VectorError> {
+        let mut points: Vec<Value> = Vec::new();
+        let mut offset = Value::Null;
+
+        loop {
+            let page_size = match limit {
+                Some(limit) => SCROLL_PAGE_SIZE.min(limit - points.len()),
+                None => SCROLL_PAGE_SIZE,
+            };
+            if page_size == 0 {
+                break;
+            }
+
+            let mut body = json!({
+                "limit": page_size,
+                "with_payload": true,
+                "with_vector": false,
+            });
+            if let Some(filter) = &filter {
+                body["filter"] = filter.clone();
+            }
+            if !offset.is_null() {
+                body["offset"] = offset.clone();
+            }
+
+            let result = self
+                .send(
+                    self.request(
+                        reqwest::Method::POST,
+                        &format!("{}/points/scroll", self.collection_path(collection_name)),
+                    )
+                    .json(&body),
+                )
+                .await?;
+
+            if let Some(page) = result.get("points").and_then(|p| p.as_array()) {
+                points.extend(page.iter().cloned());
+            }
+
+            offset = result.get("next_page_offset").cloned().unwrap_or(Value::Null);
+            if offset.is_null() {
+                break;
+            }
+        }
+
+        let (ids, documents, metadatas) = Self::unpack_points(&points);
+        Ok(GetResult {
+            ids: Some(vec![ids]),
+            documents: Some(vec![documents]),
+            metadatas: Some(vec![metadatas]),
+        })
+    }
+}
+
+#[async_trait]
+impl VectorDB for QdrantClient {
+    async fn has_collection(&self, collection_name: &str) -> Result<bool, VectorError> {
+        match self
+            .send(self.request(reqwest::Method::GET, &self.collection_path(collection_name)))
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(VectorError::CollectionNotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn delete_collection(&self, collection_name: &str) -> Result<(), VectorError> {
+        info!("Deleting collection: {}", collection_name);
+
+        self.send(self.request(reqwest::Method::DELETE, &self.collection_path(collection_name)))
+            .await?;
+        Ok(())
+    }
+
+    async fn insert(
+        &self,
+        collection_name: &str,
+        items: Vec<VectorItem>,
+    ) -> Result<(), VectorError> {
+        // Qdrant has no insert-only write, points are always upserted
+        self.upsert(collection_name, items).await
+    }
+
+    async fn upsert(
+        &self,
+        collection_name: &str,
+        items: Vec<VectorItem>,
+    ) -> Result<(), VectorError> {
+        if items.is_empty() {
+            debug!("No items to upsert into collection: {}", collection_name);
+            return Ok(());
+        }
+
+        info!(
+            "Upserting {} items into collection: {}",
+            items.len(),
+            collection_name
+        );
+
+        self.ensure_collection(collection_name, items[0].vector.len())
+            .await?;
+
+        let points: Vec<Value> = items.iter().map(Self::item_to_point).collect();
+        self.send(
+            self.request(
+                reqwest::Method::PUT,
+                &format!("{}/points?wait=true", self.collection_path(collection_name)),
+            )
+            .json(&json!({ "points": points })),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn search(
+        &self,
+        collection_name: &str,
+        vectors: Vec<Vec<f32>>,
+        limit: usize,
+    ) -> Result<SearchResult, VectorError> {
+        debug!(
+            "Searching collection '{}' with {} query vectors, limit: {}",
+            collection_name,
+            vectors.len(),
+            limit
+        );
+
+        let mut ids = Vec::with_capacity(vectors.len());
+        let mut documents = Vec::with_capacity(vectors.len());
+        let mut metadatas = Vec::with_capacity(vectors.len());
+        let mut distances = Vec::with_capacity(vectors.len());
+
+        for vector in vectors {
+            let result = self
+                .send(
+                    self.request(
+                        reqwest::Method::POST,
+                        &format!("{}/points/search", self.collection_path(collection_name)),
+                    )
+                    .json(&json!({
+                        "vector": vector,
+                        "limit": limit,
+                        "with_payload": true,
+                    })),
+                )
+                .await?;
+
+            let points = result.as_array().cloned().unwrap_or_default();
+            let (point_ids, point_docs, point_metas) = Self::unpack_points(&points);
+
+            ids.push(point_ids);
+            documents.push(point_docs);
+            metadatas.push(point_metas);
+            // Qdrant returns cosine similarity, -1 (worst) -> 1 (best). Re-order to 0 -> 1
+            distances.push(
+                points
+                    .iter()
+                    .map(|p| {
+                        let score = p.get("score").and_then(|s| s.as_f64()).unwrap_or(-1.0);
+                        ((score + 1.0) / 2.0) as f32
+                    })
+                    .collect(),
+            );
+        }
+
+        Ok(SearchResult {
+            ids: Some(ids),
+            documents: Some(documents),
+            metadatas: Some(metadatas),
+            distances: Some(distances),
+        })
+    }
+
+    async fn query(
+        &self,
+        collection_name: &str,
+        filter: Value,
+        limit: Option<usize>,
+    ) -> Result<GetResult, VectorError> {
+        debug!(
+            "Querying collection '{}' with filter: {:?}, limit: {:?}",
+            collection_name, filter, limit
+        );
+
+        let filter = Self::translate_filter(&filter)?;
+        self.scroll(collection_name, Some(filter), limit).await
+    }
+
+    async fn get(&self, collection_name: &str) -> Result<GetResult, VectorError> {
+        debug!("Getting all items from collection: {}", collection_name);
+        self.scroll(collection_name, None, None).await
+    }
+
+    async fn delete(
+        &self,
+        collection_name: &str,
+        ids: Option<Vec<String>>,
+        filter: Option<Value>,
+    ) -> Result<(), VectorError> {
+        debug!(
+            "Deleting items from collection '{}' with ids: {:?}, filter: {:?}",
+            collection_name, ids, filter
+        );
+
+        let path = format!(
+            "{}/points/delete?wait=true",
+            self.collection_path(collection_name)
+        );
+
+        if let Some(ids) = ids {
+            let points: Vec<String> = ids.iter().map(|id| Self::point_id(id)).collect();
+            self.send(
+                self.request(reqwest::Method::POST, &path)
+                    .json(&json!({ "points": points })),
+            )
+            .await?;
+        }
+
+        if let Some(filter) = filter {
+            let filter = Self::translate_filter(&filter)?;
+            self.send(
+                self.request(reqwest::Method::POST, &path)
+                    .json(&json!({ "filter": filter })),
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn reset(&self) -> Result<(), VectorError> {
+        warn!("Resetting vector database (deleting all collections)");
+
+        let result = self
+            .send(self.request(reqwest::Method::GET, "/collections"))
+            .await?;
+        let prefix = format!("{}_", self.config.collection_prefix);
+
+        for name in result
+            .get("collections")
+            .and_then(|c| c.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|c| c.get("name").and_then(|n| n.as_str()))
+            .filter(|name| name.starts_with(&prefix))
+        {
+            self.send(self.request(reqwest::Method::DELETE, &format!("/collections/{}", name)))
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_translate_filter() {
+        let filter = QdrantClient::translate_filter(&json!({
+            "$or": [{"file_id": "a"}, {"page": {"$gte": 2}}],
+            "name": {"$nin": ["x"]},
+        }))
+        .unwrap();
+
+        assert_eq!(
+            filter,
+            json!({"must": [{"must": [
+                {"should": [
+                    {"key": "metadata.file_id", "match": {"value": "a"}},
+                    {"key": "metadata.page", "range": {"gte": 2}},
+                ]},
+                {"key": "metadata.name", "match": {"except": ["x"]}},
+            ]}]})
+        );
+    }
+
+    #[test]
+    fn test_point_id_is_stable_uuid() {
+        let uuid = "9b2d3c1e-8f4a-4b6c-9d0e-1f2a3b4c5d6e";
+        assert_eq!(QdrantClient::point_id(uuid), uuid);
+
+        let mapped = QdrantClient::point_id("file-1-chunk-0");
+        assert!(uuid::Uuid::parse_str(&mapped).is_ok());
+        assert_eq!(mapped, QdrantClient::point_id("file-1-chunk-0"));
+    }
+
+    /// Minimal stand-in for the Qdrant REST API that records request bodies
+    async fn stand_in(requests: Arc<Mutex<Vec<(String, Value)>>>) -> String {
+        let server = HttpServer::new(move || {
+            let requests = requests.clone();
+            App::new().default_service(web::to(
+                move |req: HttpRequest, body: web::Bytes| {
+                    let requests = requests.clone();
+                    async move {
+                        let path = req.path().to_string();
+                        let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+                        requests.lock().unwrap().push((path.clone(), body));
+
+                        if path == "/collections/open-webui_missing" {
+                            return HttpResponse::NotFound().json(json!({"status": "error"}));
+                        }
+                        let result = if path.ends_with("/points/search") {
+                            json!([{
+                                "id": "9b2d3c1e-8f4a-4b6c-9d0e-1f2a3b4c5d6e",
+                                "score": 0.5,
+                                "payload": {"id": "chunk-0", "text": "hello", "metadata": {"file_id": "f"}},
+                            }])
+                        } else {
+                            json!({"collections": []})
+                        };
+                        HttpResponse::Ok().json(json!({"result": result, "status": "ok"}))
+                    }
+                },
+            ))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+        format!("http://{}", addr)
+    }
+
+    #[actix_web::test]
+    async fn test_client_against_stand_in() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let url = stand_in(requests.clone()).await;
+
+        let client = QdrantClient::new(QdrantConfig {
+            url,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        assert!(client.has_collection("docs").await.unwrap());
+        assert!(!client.has_collection("missing").await.unwrap());
+
+        client
+            .upsert(
+                "docs",
+                vec![VectorItem {
+                    id: "chunk-0".to_string(),
+                    text: "hello".to_string(),
+                    vector: vec![1.0, 0.0],
+                    metadata: json!({"file_id": "f"}),
+                }],
+            )
+            .await
+            .unwrap();
+
+        let result = client.search("docs", vec![vec![1.0, 0.0]], 3).await.unwrap();
+        assert_eq!(result.ids.unwrap()[0], vec!["chunk-0"]);
+        assert_eq!(result.distances.unwrap()[0], vec![0.75]);
+
+        client
+            .delete("docs", None, Some(json!({"file_id": "f"})))
+            .await
+            .unwrap();
+
+        let requests = requests.lock().unwrap();
+        let (_, upsert) = requests
+            .iter()
+            .find(|(path, _)| path == "/collections/open-webui_docs/points")
+            .unwrap();
+        assert_eq!(upsert["points"][0]["payload"]["id"], "chunk-0");
+        assert_eq!(
+            upsert["points"][0]["id"],
+            json!(QdrantClient::point_id("chunk-0"))
+        );
+
+        let (_, delete) = requests.last().unwrap();
+        assert_eq!(
+            delete["filter"],
+            json!({"must": [{"key": "metadata.file_id", "match": {"value": "f"}}]})
+        );
+    }
+}
@@ -20,6 +20,9 @@ pub struct GetResult {
 }
 
 /// Result from search operations (includes distances)
+///
+/// Distances are normalized relevance scores in the range 0 -> 1 where higher
+/// means more similar, matching the Python backend's convention.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResult {
     pub ids: Option<Vec<Vec<String>>>,
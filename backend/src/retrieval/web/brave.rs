@@ -0,0 +1,147 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use super::{WebSearchError, WebSearchProvider, WebSearchResult};
+
+const BRAVE_SEARCH_URL: &str = "https://api.search.brave.com/res/v1/web/search";
+
+/// Searches with the Brave Search API
+pub struct BraveSearch {
+    client: reqwest::Client,
+    api_key: String,
+    endpoint: String,
+}
+
+#[derive(Deserialize)]
+struct BraveResponse {
+    #[serde(default)]
+    web: Option<BraveWebResults>,
+}
+
+#[derive(Deserialize)]
+struct BraveWebResults {
+    #[serde(default)]
+    results: Vec<BraveResult>,
+}
+
+#[derive(Deserialize)]
+struct BraveResult {
+    url: String,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+impl BraveSearch {
+    pub fn new(client: reqwest::Client, api_key: impl Into<String>) -> Self {
+        Self {
+            client,
+            api_key: api_key.into(),
+            endpoint: BRAVE_SEARCH_URL.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl WebSearchProvider for BraveSearch {
+    fn name(&self) -> &str {
+        "brave"
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        count: usize,
+    ) -> Result<Vec<WebSearchResult>, WebSearchError> {
+        let response = self
+            .client
+            .get(&self.endpoint)
+            .query(&[("q", query), ("count", &count.min(20).to_string())])
+            .header("Accept", "application/json")
+            .header("X-Subscription-Token", &self.api_key)
+            .send()
+            .await?
+            .error_for_status()?;
+        let body: BraveResponse = response
+            .json()
+            .await
+            .map_err(|e| WebSearchError::Parse(e.to_string()))?;
+
+        Ok(body
+            .web
+            .map(|web| web.results)
+            .unwrap_or_default()
+            .into_iter()
+            .take(count)
+            .map(|r| WebSearchResult {
+                link: r.url,
+                title: r.title,
+                snippet: r.description,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+    use serde_json::json;
+
+    #[actix_web::test]
+    async fn test_brave_search() {
+        let server = HttpServer::new(|| {
+            App::new().route(
+                "/search",
+                web::get().to(|req: HttpRequest| async move {
+                    if req
+                        .headers()
+                        .get("X-Subscription-Token")
+                        .and_then(|v| v.to_str().ok())
+                        != Some("brave-key")
+                    {
+                        return HttpResponse::Unauthorized().finish();
+                    }
+                    assert!(req.query_string().contains("count=1"));
+                    HttpResponse::Ok().json(json!({
+                        "web": {
+                            "results": [{
+                                "url": "https://www.rust-lang.org",
+                                "title": "Rust",
+                                "description": "A language empowering everyone",
+                            }]
+                        }
+                    }))
+                }),
+            )
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+
+        let provider = BraveSearch {
+            client: reqwest::Client::new(),
+            api_key: "brave-key".to_string(),
+            endpoint: format!("http://{}/search", addr),
+        };
+        let results = provider.search("rust", 1).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].link, "https://www.rust-lang.org");
+        assert_eq!(
+            results[0].snippet.as_deref(),
+            Some("A language empowering everyone")
+        );
+
+        let unauthorized = BraveSearch {
+            api_key: "wrong".to_string(),
+            ..provider
+        };
+        assert!(matches!(
+            unauthorized.search("rust", 1).await,
+            Err(WebSearchError::Request(_))
+        ));
+    }
+}
@@ -0,0 +1,163 @@
+use async_trait::async_trait;
+use scraper::{Html, Selector};
+
+use super::{WebSearchError, WebSearchProvider, WebSearchResult};
+
+const DUCKDUCKGO_HTML_URL: &str = "https://html.duckduckgo.com/html/";
+
+/// Searches DuckDuckGo by scraping its HTML-only results page
+///
+/// Needs no API key, but the page layout may change without notice.
+pub struct DuckDuckGoSearch {
+    client: reqwest::Client,
+    endpoint: String,
+}
+
+impl DuckDuckGoSearch {
+    pub fn new(client: reqwest::Client) -> Self {
+        Self {
+            client,
+            endpoint: DUCKDUCKGO_HTML_URL.to_string(),
+        }
+    }
+}
+
+/// Result links point at a `/l/?uddg=<target>` redirect; unwrap it
+fn resolve_link(href: &str) -> Option<String> {
+    let url = url::Url::parse("https://duckduckgo.com")
+        .ok()?
+        .join(href)
+        .ok()?;
+    if url.path() == "/l/" {
+        return url
+            .query_pairs()
+            .find(|(key, _)| key == "uddg")
+            .map(|(_, target)| target.into_owned());
+    }
+    Some(url.to_string())
+}
+
+/// Parse the results page, skipping ads
+fn parse_results(html: &str) -> Vec<WebSearchResult> {
+    let document = Html::parse_document(html);
+    let result_selector = Selector::parse("div.result").unwrap();
+    let link_selector = Selector::parse("a.result__a").unwrap();
+    let snippet_selector = Selector::parse(".result__snippet").unwrap();
+
+    let text = |element: scraper::ElementRef| {
+        element
+            .text()
+            .collect::<String>()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    document
+        .select(&result_selector)
+        .filter(|result| !result.value().classes().any(|c| c == "result--ad"))
+        .filter_map(|result| {
+            let anchor = result.select(&link_selector).next()?;
+            let link = resolve_link(anchor.value().attr("href")?)?;
+            Some(WebSearchResult {
+                link,
+                title: Some(text(anchor)).filter(|t| !t.is_empty()),
+                snippet: result
+                    .select(&snippet_selector)
+                    .next()
+                    .map(text)
+                    .filter(|t| !t.is_empty()),
+            })
+        })
+        .collect()
+}
+
+#[async_trait]
+impl WebSearchProvider for DuckDuckGoSearch {
+    fn name(&self) -> &str {
+        "duckduckgo"
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        count: usize,
+    ) -> Result<Vec<WebSearchResult>, WebSearchError> {
+        let html = self
+            .client
+            .post(&self.endpoint)
+            .form(&[("q", query)])
+            .header("User-Agent", "Mozilla/5.0 (compatible; open-webui)")
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        let mut results = parse_results(&html);
+        results.truncate(count);
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{web, App, HttpResponse, HttpServer};
+    use std::collections::HashMap;
+
+    const RESULTS_PAGE: &str = r##"<html><body>
+        <div class="result results_links result--ad">
+          <a class="result__a" href="https://ads.example/">Sponsored</a>
+        </div>
+        <div class="result results_links">
+          <h2><a class="result__a" href="//duckduckgo.com/l/?uddg=https%3A%2F%2Fwww.rust-lang.org%2F&amp;rut=abc">The <b>Rust</b> Programming Language</a></h2>
+          <a class="result__snippet" href="#">A language empowering <b>everyone</b>.</a>
+        </div>
+        <div class="result results_links">
+          <a class="result__a" href="https://doc.rust-lang.org/book/">The Book</a>
+        </div>
+    </body></html>"##;
+
+    #[actix_web::test]
+    async fn test_duckduckgo_search() {
+        let server = HttpServer::new(|| {
+            App::new().route(
+                "/html/",
+                web::post().to(|form: web::Form<HashMap<String, String>>| async move {
+                    assert_eq!(form["q"], "rust");
+                    HttpResponse::Ok()
+                        .content_type("text/html")
+                        .body(RESULTS_PAGE)
+                }),
+            )
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+
+        let provider = DuckDuckGoSearch {
+            client: reqwest::Client::new(),
+            endpoint: format!("http://{}/html/", addr),
+        };
+        let results = provider.search("rust", 5).await.unwrap();
+
+        assert_eq!(
+            results,
+            vec![
+                WebSearchResult {
+                    link: "https://www.rust-lang.org/".to_string(),
+                    title: Some("The Rust Programming Language".to_string()),
+                    snippet: Some("A language empowering everyone.".to_string()),
+                },
+                WebSearchResult {
+                    link: "https://doc.rust-lang.org/book/".to_string(),
+                    title: Some("The Book".to_string()),
+                    snippet: None,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use super::{WebSearchError, WebSearchProvider, WebSearchResult};
+
+const GOOGLE_PSE_URL: &str = "https://www.googleapis.com/customsearch/v1";
+
+/// The Custom Search JSON API returns at most 10 results per request
+const PAGE_SIZE: usize = 10;
+
+/// Searches with a Google Programmable Search Engine
+pub struct GooglePseSearch {
+    client: reqwest::Client,
+    api_key: String,
+    engine_id: String,
+    endpoint: String,
+}
+
+#[derive(Deserialize)]
+struct GooglePseResponse {
+    #[serde(default)]
+    items: Vec<GooglePseItem>,
+}
+
+#[derive(Deserialize)]
+struct GooglePseItem {
+    link: String,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    snippet: Option<String>,
+}
+
+impl GooglePseSearch {
+    pub fn new(
+        client: reqwest::Client,
+        api_key: impl Into<String>,
+        engine_id: impl Into<String>,
+    ) -> Self {
+        Self {
+            client,
+            api_key: api_key.into(),
+            engine_id: engine_id.into(),
+            endpoint: GOOGLE_PSE_URL.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl WebSearchProvider for GooglePseSearch {
+    fn name(&self) -> &str {
+        "google_pse"
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        count: usize,
+    ) -> Result<Vec<WebSearchResult>, WebSearchError> {
+        let mut results = Vec::new();
+
+        // Page through results since a single request is capped at 10
+        while results.len() < count {
+            let num = (count - results.len()).min(PAGE_SIZE);
+            let start = results.len() + 1;
+            let response = self
+                .client
+                .get(&self.endpoint)
+                .query(&[
+                    ("key", self.api_key.as_str()),
+                    ("cx", self.engine_id.as_str()),
+                    ("q", query),
+                    ("num", &num.to_string()),
+                    ("start", &start.to_string()),
+                ])
+                .send()
+                .await?
+                .error_for_status()?;
+            let body: GooglePseResponse = response
+                .json()
+                .await
+                .map_err(|e| WebSearchError::Parse(e.to_string()))?;
+
+            let page_len = body.items.len();
+            results.extend(body.items.into_iter().map(|item| WebSearchResult {
+                link: item.link,
+                title: item.title,
+                snippet: item.snippet,
+            }));
+            if page_len < num {
+                break;
+            }
+        }
+
+        results.truncate(count);
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{web, App, HttpResponse, HttpServer};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[actix_web::test]
+    async fn test_google_pse_search_pages() {
+        let server = HttpServer::new(|| {
+            App::new().route(
+                "/customsearch/v1",
+                web::get().to(|query: web::Query<HashMap<String, String>>| async move {
+                    assert_eq!(query["key"], "pse-key");
+                    assert_eq!(query["cx"], "engine");
+                    let start: usize = query["start"].parse().unwrap();
+                    let num: usize = query["num"].parse().unwrap();
+                    // 12 results in total
+                    let items: Vec<_> = (start..start + num)
+                        .filter(|i| *i <= 12)
+                        .map(|i| json!({ "link": format!("https://{}.example", i), "title": i.to_string() }))
+                        .collect();
+                    HttpResponse::Ok().json(json!({ "items": items }))
+                }),
+            )
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+
+        let provider = GooglePseSearch {
+            endpoint: format!("http://{}/customsearch/v1", addr),
+            ..GooglePseSearch::new(reqwest::Client::new(), "pse-key", "engine")
+        };
+
+        let results = provider.search("rust", 15).await.unwrap();
+        assert_eq!(results.len(), 12);
+        assert_eq!(results[0].link, "https://1.example");
+        assert_eq!(results[11].link, "https://12.example");
+
+        let results = provider.search("rust", 3).await.unwrap();
+        assert_eq!(results.len(), 3);
+    }
+}
@@ -0,0 +1,279 @@
+pub mod brave;
+pub mod duckduckgo;
+pub mod google_pse;
+pub mod searxng;
+pub mod tavily;
+
+pub use brave::BraveSearch;
+pub use duckduckgo::DuckDuckGoSearch;
+pub use google_pse::GooglePseSearch;
+pub use searxng::SearxngSearch;
+pub use tavily::TavilySearch;
+
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::loaders::{Document, UrlLoader};
+use crate::config::Config;
+
+/// A single hit returned by a search engine
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebSearchResult {
+    pub link: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub snippet: Option<String>,
+}
+
+/// Error types for web search
+#[derive(Debug, thiserror::Error)]
+pub enum WebSearchError {
+    #[error("Search request failed: {0}")]
+    Request(String),
+
+    #[error("Failed to parse search response: {0}")]
+    Parse(String),
+
+    #[error("Invalid configuration: {0}")]
+    Config(String),
+}
+
+impl From<reqwest::Error> for WebSearchError {
+    fn from(e: reqwest::Error) -> Self {
+        WebSearchError::Request(e.to_string())
+    }
+}
+
+/// A search engine queried by the `web_search` feature
+#[async_trait]
+pub trait WebSearchProvider: Send + Sync {
+    /// Name of the engine, used for logging
+    fn name(&self) -> &str;
+
+    /// Return up to `count` results for `query`
+    async fn search(
+        &self,
+        query: &str,
+        count: usize,
+    ) -> Result<Vec<WebSearchResult>, WebSearchError>;
+}
+
+/// Supported web search engines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebSearchEngine {
+    Searxng,
+    Brave,
+    Tavily,
+    DuckDuckGo,
+    GooglePse,
+}
+
+impl WebSearchEngine {
+    /// Parse the WEB_SEARCH_ENGINE setting
+    pub fn from_str(s: &str) -> Result<Self, WebSearchError> {
+        match s.trim().to_lowercase().as_str() {
+            "searxng" => Ok(WebSearchEngine::Searxng),
+            "brave" => Ok(WebSearchEngine::Brave),
+            "tavily" => Ok(WebSearchEngine::Tavily),
+            "duckduckgo" => Ok(WebSearchEngine::DuckDuckGo),
+            "google_pse" => Ok(WebSearchEngine::GooglePse),
+            "" => Err(WebSearchError::Config(
+                "WEB_SEARCH_ENGINE is not set".to_string(),
+            )),
+            _ => Err(WebSearchError::Config(format!(
+                "Unsupported WEB_SEARCH_ENGINE: {}. Supported engines: searxng, brave, tavily, duckduckgo, google_pse",
+                s
+            ))),
+        }
+    }
+}
+
+/// Create the search provider selected by the web search settings
+pub fn from_config(
+    client: reqwest::Client,
+    config: &Config,
+) -> Result<Box<dyn WebSearchProvider>, WebSearchError> {
+    fn required(value: &str, name: &str) -> Result<String, WebSearchError> {
+        if value.trim().is_empty() {
+            return Err(WebSearchError::Config(format!("{} is not set", name)));
+        }
+        Ok(value.trim().to_string())
+    }
+
+    Ok(
+        match WebSearchEngine::from_str(&config.web_search_engine)? {
+            WebSearchEngine::Searxng => Box::new(SearxngSearch::new(
+                client,
+                required(&config.searxng_query_url, "SEARXNG_QUERY_URL")?,
+            )),
+            WebSearchEngine::Brave => Box::new(BraveSearch::new(
+                client,
+                required(&config.brave_search_api_key, "BRAVE_SEARCH_API_KEY")?,
+            )),
+            WebSearchEngine::Tavily => Box::new(TavilySearch::new(
+                client,
+                required(&config.tavily_api_key, "TAVILY_API_KEY")?,
+            )),
+            WebSearchEngine::DuckDuckGo => Box::new(DuckDuckGoSearch::new(client)),
+            WebSearchEngine::GooglePse => Box::new(GooglePseSearch::new(
+                client,
+                required(&config.google_pse_api_key, "GOOGLE_PSE_API_KEY")?,
+                required(&config.google_pse_engine_id, "GOOGLE_PSE_ENGINE_ID")?,
+            )),
+        },
+    )
+}
+
+/// An allow/deny list of domains
+///
+/// Entries prefixed with `!` are blocked. When any other entries are present,
+/// only hosts on those domains (or their subdomains) are allowed.
+#[derive(Debug, Clone, Default)]
+pub struct DomainFilter {
+    allowed: Vec<String>,
+    blocked: Vec<String>,
+}
+
+impl DomainFilter {
+    pub fn new(filter_list: &[String]) -> Self {
+        let mut filter = Self::default();
+        for entry in filter_list
+            .iter()
+            .map(|d| d.trim())
+            .filter(|d| !d.is_empty())
+        {
+            let (list, domain) = match entry.strip_prefix('!') {
+                Some(domain) => (&mut filter.blocked, domain),
+                None => (&mut filter.allowed, entry),
+            };
+            list.push(domain.trim_start_matches('.').to_lowercase());
+        }
+        filter
+    }
+
+    fn matches(host: &str, domain: &str) -> bool {
+        host == domain || host.ends_with(&format!(".{}", domain))
+    }
+
+    /// Whether `host` passes the filter
+    pub fn allows(&self, host: &str) -> bool {
+        let host = host.to_lowercase();
+        if self.blocked.iter().any(|d| Self::matches(&host, d)) {
+            return false;
+        }
+        self.allowed.is_empty() || self.is_listed(&host)
+    }
+
+    /// Whether `host` is explicitly on the allow list
+    pub fn is_listed(&self, host: &str) -> bool {
+        let host = host.to_lowercase();
+        self.allowed.iter().any(|d| Self::matches(&host, d))
+    }
+}
+
+/// Apply WEB_SEARCH_DOMAIN_FILTER_LIST to search results
+pub fn filter_by_domain(
+    results: Vec<WebSearchResult>,
+    filter_list: &[String],
+) -> Vec<WebSearchResult> {
+    let filter = DomainFilter::new(filter_list);
+    results
+        .into_iter()
+        .filter(|result| {
+            url::Url::parse(&result.link)
+                .ok()
+                .and_then(|u| u.host_str().map(|host| filter.allows(host)))
+                .unwrap_or(false)
+        })
+        .collect()
+}
+
+/// Load result pages, at most `concurrency` at a time
+///
+/// Pages go through the URL loader's domain and address checks. The search
+/// snippet stands in for pages that fail to load.
+pub async fn load_results(
+    loader: &UrlLoader,
+    results: &[WebSearchResult],
+    concurrency: usize,
+) -> Vec<Document> {
+    stream::iter(results.iter().cloned())
+        .map(|result| async move {
+            let content = match loader.load(&result.link).await {
+                Ok(docs) => docs
+                    .into_iter()
+                    .map(|doc| doc.page_content)
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                Err(e) => {
+                    tracing::warn!("Failed to load {}: {}", result.link, e);
+                    result.snippet.clone().unwrap_or_default()
+                }
+            };
+            (!content.trim().is_empty()).then(|| {
+                Document::new(
+                    content,
+                    json!({
+                        "source": result.link,
+                        "title": result.title,
+                        "snippet": result.snippet,
+                    }),
+                )
+            })
+        })
+        .buffered(concurrency.max(1))
+        .filter_map(|doc| async move { doc })
+        .collect()
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(link: &str) -> WebSearchResult {
+        WebSearchResult {
+            link: link.to_string(),
+            title: None,
+            snippet: None,
+        }
+    }
+
+    #[test]
+    fn test_filter_by_domain() {
+        let results = vec![
+            result("https://docs.rs/serde"),
+            result("https://en.wikipedia.org/wiki/Rust"),
+            result("https://spam.example.com/page"),
+            result("not a url"),
+        ];
+
+        let kept = filter_by_domain(results.clone(), &[]);
+        assert_eq!(kept.len(), 3);
+
+        let kept = filter_by_domain(results.clone(), &["!example.com".to_string()]);
+        assert_eq!(
+            kept.iter().map(|r| r.link.as_str()).collect::<Vec<_>>(),
+            vec![
+                "https://docs.rs/serde",
+                "https://en.wikipedia.org/wiki/Rust"
+            ]
+        );
+
+        let kept = filter_by_domain(results, &["wikipedia.org".to_string()]);
+        assert_eq!(kept, vec![result("https://en.wikipedia.org/wiki/Rust")]);
+    }
+
+    #[test]
+    fn test_engine_from_str() {
+        assert_eq!(
+            WebSearchEngine::from_str("Google_PSE").unwrap(),
+            WebSearchEngine::GooglePse
+        );
+        assert!(WebSearchEngine::from_str("").is_err());
+        assert!(WebSearchEngine::from_str("altavista").is_err());
+    }
+}
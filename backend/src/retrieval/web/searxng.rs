@@ -0,0 +1,144 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use super::{WebSearchError, WebSearchProvider, WebSearchResult};
+
+/// Searches a SearXNG instance through its JSON API
+///
+/// The query URL may contain a `<query>` placeholder
+/// (e.g. `http://searxng:8080/search?q=<query>`); otherwise the query is sent
+/// as the `q` parameter.
+pub struct SearxngSearch {
+    client: reqwest::Client,
+    query_url: String,
+}
+
+#[derive(Deserialize)]
+struct SearxngResponse {
+    #[serde(default)]
+    results: Vec<SearxngResult>,
+}
+
+#[derive(Deserialize)]
+struct SearxngResult {
+    url: String,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    score: f64,
+}
+
+impl SearxngSearch {
+    pub fn new(client: reqwest::Client, query_url: impl Into<String>) -> Self {
+        Self {
+            client,
+            query_url: query_url.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl WebSearchProvider for SearxngSearch {
+    fn name(&self) -> &str {
+        "searxng"
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        count: usize,
+    ) -> Result<Vec<WebSearchResult>, WebSearchError> {
+        let mut request = if self.query_url.contains("<query>") {
+            self.client.get(
+                self.query_url
+                    .replace("<query>", &urlencoding::encode(query)),
+            )
+        } else {
+            self.client.get(&self.query_url).query(&[("q", query)])
+        };
+        request = request.query(&[("format", "json"), ("pageno", "1"), ("safesearch", "1")]);
+
+        let response = request
+            .header("Accept", "application/json")
+            .send()
+            .await?
+            .error_for_status()?;
+        let mut body: SearxngResponse = response
+            .json()
+            .await
+            .map_err(|e| WebSearchError::Parse(e.to_string()))?;
+
+        body.results.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        Ok(body
+            .results
+            .into_iter()
+            .take(count)
+            .map(|r| WebSearchResult {
+                link: r.url,
+                title: r.title,
+                snippet: r.content,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{web, App, HttpResponse, HttpServer};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[actix_web::test]
+    async fn test_searxng_search() {
+        let server = HttpServer::new(|| {
+            App::new().route(
+                "/search",
+                web::get().to(|query: web::Query<HashMap<String, String>>| async move {
+                    assert_eq!(query.get("q").map(String::as_str), Some("rust lang"));
+                    assert_eq!(query.get("format").map(String::as_str), Some("json"));
+                    HttpResponse::Ok().json(json!({
+                        "results": [
+                            { "url": "https://b.example", "title": "B", "content": "b", "score": 0.5 },
+                            { "url": "https://a.example", "title": "A", "content": "a", "score": 2.0 },
+                            { "url": "https://c.example", "score": 0.1 },
+                        ]
+                    }))
+                }),
+            )
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+
+        let provider = SearxngSearch::new(
+            reqwest::Client::new(),
+            format!("http://{}/search?q=<query>", addr),
+        );
+        let results = provider.search("rust lang", 2).await.unwrap();
+
+        assert_eq!(
+            results,
+            vec![
+                WebSearchResult {
+                    link: "https://a.example".to_string(),
+                    title: Some("A".to_string()),
+                    snippet: Some("a".to_string()),
+                },
+                WebSearchResult {
+                    link: "https://b.example".to_string(),
+                    title: Some("B".to_string()),
+                    snippet: Some("b".to_string()),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{WebSearchError, WebSearchProvider, WebSearchResult};
+
+const TAVILY_SEARCH_URL: &str = "https://api.tavily.com/search";
+
+/// Searches with the Tavily API
+pub struct TavilySearch {
+    client: reqwest::Client,
+    api_key: String,
+    endpoint: String,
+}
+
+#[derive(Deserialize)]
+struct TavilyResponse {
+    #[serde(default)]
+    results: Vec<TavilyResult>,
+}
+
+#[derive(Deserialize)]
+struct TavilyResult {
+    url: String,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    content: Option<String>,
+}
+
+impl TavilySearch {
+    pub fn new(client: reqwest::Client, api_key: impl Into<String>) -> Self {
+        Self {
+            client,
+            api_key: api_key.into(),
+            endpoint: TAVILY_SEARCH_URL.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl WebSearchProvider for TavilySearch {
+    fn name(&self) -> &str {
+        "tavily"
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        count: usize,
+    ) -> Result<Vec<WebSearchResult>, WebSearchError> {
+        let response = self
+            .client
+            .post(&self.endpoint)
+            .bearer_auth(&self.api_key)
+            .json(&json!({
+                "query": query,
+                "max_results": count,
+            }))
+            .send()
+            .await?
+            .error_for_status()?;
+        let body: TavilyResponse = response
+            .json()
+            .await
+            .map_err(|e| WebSearchError::Parse(e.to_string()))?;
+
+        Ok(body
+            .results
+            .into_iter()
+            .take(count)
+            .map(|r| WebSearchResult {
+                link: r.url,
+                title: r.title,
+                snippet: r.content,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+    use serde_json::Value;
+
+    #[actix_web::test]
+    async fn test_tavily_search() {
+        let server = HttpServer::new(|| {
+            App::new().route(
+                "/search",
+                web::post().to(|req: HttpRequest, body: web::Json<Value>| async move {
+                    assert_eq!(
+                        req.headers()
+                            .get("Authorization")
+                            .and_then(|v| v.to_str().ok()),
+                        Some("Bearer tvly-key")
+                    );
+                    assert_eq!(body["query"], "rust");
+                    assert_eq!(body["max_results"], 2);
+                    HttpResponse::Ok().json(json!({
+                        "query": "rust",
+                        "results": [
+                            { "url": "https://a.example", "title": "A", "content": "a" },
+                            { "url": "https://b.example", "title": "B", "content": "b" },
+                        ]
+                    }))
+                }),
+            )
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+
+        let provider = TavilySearch {
+            client: reqwest::Client::new(),
+            api_key: "tvly-key".to_string(),
+            endpoint: format!("http://{}/search", addr),
+        };
+        let results = provider.search("rust", 2).await.unwrap();
+        assert_eq!(
+            results.iter().map(|r| r.link.as_str()).collect::<Vec<_>>(),
+            vec!["https://a.example", "https://b.example"]
+        );
+    }
+}
@@ -1,13 +1,18 @@
 use actix_web::{web, HttpResponse};
+use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
 use crate::{
     error::AppError,
     middleware::{AuthMiddleware, AuthUser},
+    services::stt,
     AppState,
 };
 
+/// Largest upload accepted by `/transcriptions` (the OpenAI API limit)
+const MAX_TRANSCRIPTION_FILE_SIZE: usize = 25 * 1024 * 1024;
+
 #[derive(Debug, Serialize, Deserialize)]
 struct TTSConfigForm {
     #[serde(rename = "OPENAI_API_BASE_URL")]
@@ -304,26 +309,96 @@ async fn speech(
 async fn transcriptions(
     state: web::Data<AppState>,
     _auth_user: AuthUser,
-    _payload: actix_multipart::Multipart,
+    mut payload: actix_multipart::Multipart,
 ) -> Result<HttpResponse, AppError> {
-    let config = state.config.read().unwrap();
+    let mut filename = String::new();
+    let mut file_data = Vec::new();
+    let mut content_type = None;
+    let mut language = None;
+
+    while let Some(field) = payload.next().await {
+        let mut field =
+            field.map_err(|e| AppError::BadRequest(format!("Multipart error: {}", e)))?;
+
+        let content_disposition = field.content_disposition();
+        let field_name = content_disposition
+            .and_then(|cd| cd.get_name())
+            .unwrap_or("")
+            .to_string();
+
+        match field_name.as_str() {
+            "file" => {
+                filename = content_disposition
+                    .and_then(|cd| cd.get_filename())
+                    .unwrap_or("audio")
+                    .to_string();
+                content_type = field
+                    .content_type()
+                    .map(|ct| ct.essence_str().to_string())
+                    .filter(|ct| ct != "application/octet-stream");
+
+                while let Some(chunk) = field.next().await {
+                    let chunk = chunk
+                        .map_err(|e| AppError::BadRequest(format!("Chunk read error: {}", e)))?;
+                    if file_data.len() + chunk.len() > MAX_TRANSCRIPTION_FILE_SIZE {
+                        return Err(AppError::BadRequest(format!(
+                            "Audio file exceeds the {} MB limit",
+                            MAX_TRANSCRIPTION_FILE_SIZE / (1024 * 1024)
+                        )));
+                    }
+                    file_data.extend_from_slice(&chunk);
+                }
+            }
+            "language" => {
+                let mut value = Vec::new();
+                while let Some(chunk) = field.next().await {
+                    let chunk = chunk
+                        .map_err(|e| AppError::BadRequest(format!("Chunk read error: {}", e)))?;
+                    value.extend_from_slice(&chunk);
+                }
+                language = Some(String::from_utf8_lossy(&value).trim().to_string())
+                    .filter(|l| !l.is_empty());
+            }
+            _ => {}
+        }
+    }
 
-    // TODO: Implement file upload handling with multipart
-    // TODO: Forward to configured STT engine
-    // For now, return a placeholder
+    if file_data.is_empty() {
+        return Err(AppError::BadRequest("No audio file uploaded".to_string()));
+    }
 
-    if config.stt_engine == "openai" {
-        // Forward to OpenAI-compatible STT endpoint
-        // This requires proper multipart form handling
-        return Err(AppError::NotImplemented(
-            "STT endpoint not fully implemented yet".to_string(),
-        ));
+    let content_type = content_type.unwrap_or_else(|| {
+        mime_guess::from_path(&filename)
+            .first_or_octet_stream()
+            .to_string()
+    });
+
+    let config = state.config.read().unwrap().clone();
+
+    let supported_content_types = if config.stt_supported_content_types.is_empty() {
+        vec!["audio/*".to_string(), "video/webm".to_string()]
+    } else {
+        config.stt_supported_content_types.clone()
+    };
+    if !stt::is_supported_content_type(&content_type, &supported_content_types) {
+        return Err(AppError::BadRequest(format!(
+            "File type not supported: {}",
+            content_type
+        )));
     }
 
-    Err(AppError::NotImplemented(format!(
-        "STT engine '{}' not yet implemented",
-        config.stt_engine
-    )))
+    let input = stt::AudioInput {
+        data: file_data,
+        filename: filename.clone(),
+        content_type,
+        language,
+    };
+    let transcription = stt::transcribe(&config, &input).await?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "text": transcription.text,
+        "filename": filename,
+    })))
 }
 
 // Get available TTS models
@@ -0,0 +1,132 @@
+use actix_web::{http::header, web, HttpResponse};
+use bytes::Bytes;
+use serde::Deserialize;
+
+use crate::error::{AppError, AppResult};
+use crate::middleware::auth::{AuthMiddleware, AuthUser};
+use crate::models::audit::AuditLogFilter;
+use crate::services::audit::AuditService;
+use crate::utils::time::current_timestamp_seconds;
+use crate::AppState;
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 500;
+const EXPORT_BATCH_SIZE: i64 = 1000;
+
+#[derive(Debug, Deserialize)]
+pub struct AuditLogQuery {
+    pub page: Option<i64>,
+    pub limit: Option<i64>,
+    pub user_id: Option<String>,
+    pub resource: Option<String>,
+    pub method: Option<String>,
+    pub status_code: Option<i64>,
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+    pub query: Option<String>,
+}
+
+impl AuditLogQuery {
+    fn filter(&self) -> AuditLogFilter {
+        let non_empty = |value: &Option<String>| value.clone().filter(|v| !v.is_empty());
+        AuditLogFilter {
+            user_id: non_empty(&self.user_id),
+            resource: non_empty(&self.resource),
+            method: non_empty(&self.method),
+            status_code: self.status_code,
+            start: self.start,
+            end: self.end,
+            query: non_empty(&self.query),
+        }
+    }
+}
+
+fn require_admin(user: &AuthUser) -> AppResult<()> {
+    if user.user.role != "admin" {
+        return Err(AppError::Forbidden("Admin access required".to_string()));
+    }
+    Ok(())
+}
+
+// GET / - Page through the audit log, newest first
+async fn get_audit_logs(
+    state: web::Data<AppState>,
+    user: AuthUser,
+    query: web::Query<AuditLogQuery>,
+) -> AppResult<HttpResponse> {
+    require_admin(&user)?;
+
+    let page = query.page.unwrap_or(1).max(1);
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let skip = (page - 1) * limit;
+
+    let service = AuditService::new(&state.db);
+    let (items, total) = service.list(&query.filter(), skip, limit).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "items": items,
+        "total": total,
+        "page": page,
+        "limit": limit,
+    })))
+}
+
+// GET /export - Download the matching entries as JSON Lines, oldest first
+async fn export_audit_logs(
+    state: web::Data<AppState>,
+    user: AuthUser,
+    query: web::Query<AuditLogQuery>,
+) -> AppResult<HttpResponse> {
+    require_admin(&user)?;
+
+    let db = state.db.clone();
+    let filter = query.filter();
+    let stream = futures::stream::try_unfold(Some(0), move |skip| {
+        let db = db.clone();
+        let filter = filter.clone();
+        async move {
+            let Some(skip) = skip else {
+                return Ok::<_, AppError>(None);
+            };
+            let entries = AuditService::new(&db)
+                .export_page(&filter, skip, EXPORT_BATCH_SIZE)
+                .await?;
+            if entries.is_empty() {
+                return Ok(None);
+            }
+
+            let mut lines = String::new();
+            for entry in &entries {
+                lines.push_str(&serde_json::json!(entry).to_string());
+                lines.push('\n');
+            }
+            let next =
+                (entries.len() as i64 == EXPORT_BATCH_SIZE).then_some(skip + EXPORT_BATCH_SIZE);
+            Ok(Some((Bytes::from(lines), next)))
+        }
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!(
+                "attachment; filename=\"audit-log-{}.jsonl\"",
+                current_timestamp_seconds()
+            ),
+        ))
+        .streaming(stream))
+}
+
+pub fn create_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("")
+            .wrap(AuthMiddleware)
+            .route("", web::get().to(get_audit_logs))
+            .route("/", web::get().to(get_audit_logs))
+            .route("/export", web::get().to(export_audit_logs)),
+    );
+}
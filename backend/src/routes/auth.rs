@@ -976,40 +976,21 @@ async fn ldap_auth(
     req.validate()
         .map_err(|e| crate::error::AppError::Validation(e.to_string()))?;
 
-    let config = state.config.read().unwrap();
+    let (ldap_config, group_management, group_creation) = {
+        let config = state.config.read().unwrap();
 
-    // Check if LDAP is enabled
-    if !config.enable_ldap {
-        return Err(crate::error::AppError::BadRequest(
-            "LDAP authentication is not enabled".to_string(),
-        ));
-    }
+        // Check if LDAP is enabled
+        if !config.enable_ldap {
+            return Err(crate::error::AppError::BadRequest(
+                "LDAP authentication is not enabled".to_string(),
+            ));
+        }
 
-    // Build LDAP configuration from application config
-    let ldap_config = crate::services::ldap::LdapConfig {
-        server_url: format!(
-            "ldap://{}:{}",
-            config.ldap_server_host,
-            config.ldap_server_port.unwrap_or(389)
-        ),
-        bind_dn: config.ldap_app_dn.clone(),
-        bind_password: config.ldap_app_password.clone(),
-        search_base: config.ldap_search_base.clone(),
-        user_filter: format!(
-            "(&({}={}){})",
-            config.ldap_attribute_for_username, "{username}", config.ldap_search_filters
-        ),
-        group_filter: None,
-        user_attributes: crate::services::ldap::LdapUserAttributes {
-            username: config.ldap_attribute_for_username.clone(),
-            email: config.ldap_attribute_for_mail.clone(),
-            display_name: "cn".to_string(),
-            first_name: Some("givenName".to_string()),
-            last_name: Some("sn".to_string()),
-            member_of: Some("memberOf".to_string()),
-        },
-        enable_tls: config.ldap_use_tls,
-        verify_cert: config.ldap_validate_cert,
+        (
+            crate::services::ldap::LdapConfig::from_config(&config),
+            config.enable_ldap_group_management,
+            config.enable_ldap_group_creation,
+        )
     };
 
     let ldap_client = crate::services::ldap::LdapClient::new(ldap_config);
@@ -1018,7 +999,10 @@ async fn ldap_auth(
     let ldap_user = ldap_client
         .authenticate(&req.user.to_lowercase(), &req.password)
         .await
-        .map_err(|_| crate::error::AppError::InvalidCredentials)?;
+        .map_err(|e| {
+            tracing::warn!("LDAP authentication failed for {}: {}", req.user, e);
+            crate::error::AppError::InvalidCredentials
+        })?;
 
     // Find or create user in local database
     let user_service = crate::services::user::UserService::new(&state.db);
@@ -1054,6 +1038,18 @@ async fn ldap_auth(
         "Failed to create user".to_string(),
     ))?;
 
+    if group_management {
+        crate::services::ldap::sync_user_groups(
+            &state.db,
+            &user.id,
+            &ldap_user.groups,
+            group_creation,
+        )
+        .await?;
+    }
+
+    let config = state.config.read().unwrap();
+
     // Generate JWT token
     let token = create_jwt(&user.id, &config.webui_secret_key, &config.jwt_expires_in)?;
 
@@ -99,7 +99,11 @@ pub fn create_routes(cfg: &mut web::ServiceConfig) {
             .route("/models", web::post().to(set_models_config))
             .route("/suggestions", web::post().to(set_default_suggestions))
             .route("/tool_servers", web::get().to(get_tool_servers_config))
-            .route("/tool_servers", web::post().to(set_tool_servers_config)),
+            .route("/tool_servers", web::post().to(set_tool_servers_config))
+            .route(
+                "/tool_servers/verify",
+                web::post().to(verify_tool_server_connection),
+            ),
     );
 }
 
@@ -577,6 +581,7 @@ async fn set_tool_servers_config(
         let mut config = state.config.write().unwrap();
         config.tool_server_connections = form_data.tool_server_connections.clone();
     }
+    crate::services::openapi::clear_cache();
 
     // Persist to database (best-effort)
     let config = state.config.read().unwrap();
@@ -594,3 +599,36 @@ async fn set_tool_servers_config(
         tool_server_connections: config.tool_server_connections.clone(),
     }))
 }
+
+/// Connect to a tool server connection (not necessarily saved yet) and
+/// report what it offers
+async fn verify_tool_server_connection(
+    state: web::Data<AppState>,
+    auth_user: AuthUser,
+    form_data: web::Json<serde_json::Value>,
+) -> Result<HttpResponse, AppError> {
+    use crate::services::mcp::McpToolServer;
+    use crate::services::openapi::{parse_spec, OpenApiToolServer};
+
+    if auth_user.user.role != "admin" {
+        return Err(AppError::Forbidden("Admin access required".to_string()));
+    }
+
+    if let Some(server) = McpToolServer::from_connection(0, &form_data) {
+        return Ok(HttpResponse::Ok().json(server.inspect().await?));
+    }
+
+    let server = OpenApiToolServer::from_connection(0, &form_data).ok_or_else(|| {
+        AppError::BadRequest("Tool server connection needs a type and URL".to_string())
+    })?;
+    let spec = server.fetch_spec(&state.http_client).await?;
+    let specs: Vec<serde_json::Value> = parse_spec(&spec)
+        .iter()
+        .map(|tool| tool.to_openai_spec())
+        .collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "info": spec.get("info").cloned().unwrap_or_default(),
+        "specs": specs,
+    })))
+}
@@ -8,6 +8,7 @@ use crate::db::Database;
 use crate::error::{AppError, AppResult};
 use crate::middleware::auth::{AdminMiddleware, AuthUser};
 use crate::models::file::FileResponse;
+use crate::routes::retrieval::process_file_content;
 use crate::services::file::FileService;
 
 #[derive(Debug, Deserialize)]
@@ -45,6 +46,11 @@ async fn list_files(
     Ok(HttpResponse::Ok().json(responses))
 }
 
+#[derive(Debug, Deserialize)]
+struct UploadQuery {
+    process: Option<bool>,
+}
+
 #[derive(Debug, Deserialize)]
 struct ContentQuery {
     content: Option<bool>,
@@ -104,12 +110,14 @@ async fn upload_file(
     state: web::Data<crate::AppState>,
     db: web::Data<Database>,
     user: AuthUser,
+    query: web::Query<UploadQuery>,
     mut payload: Multipart,
 ) -> AppResult<HttpResponse> {
     let service = FileService::new(&db);
 
     let mut filename = String::new();
     let mut file_data = Vec::new();
+    let mut content_type = None;
 
     // Process multipart fields
     while let Some(field) = payload.next().await {
@@ -126,6 +134,10 @@ async fn upload_file(
                 .and_then(|cd| cd.get_filename())
                 .unwrap_or("unnamed")
                 .to_string();
+            content_type = field
+                .content_type()
+                .map(|ct| ct.essence_str().to_string())
+                .filter(|ct| ct != "application/octet-stream");
 
             // Read file data
             while let Some(chunk) = field.next().await {
@@ -159,20 +171,40 @@ async fn upload_file(
     f.write_all(&file_data)
         .map_err(|e| AppError::BadRequest(format!("Failed to write file: {}", e)))?;
 
-    // Calculate file hash
-    let hash = format!("{:x}", md5::compute(&file_data));
-
     // Create file metadata
     let meta = serde_json::json!({
         "source": "upload",
         "size": file_data.len(),
-        "content_type": mime_guess::from_path(&filename).first_or_octet_stream().to_string(),
+        "content_type": content_type.unwrap_or_else(|| {
+            mime_guess::from_path(&filename).first_or_octet_stream().to_string()
+        }),
     });
 
     // Create file record in database
     let file = service
-        .create_file(&file_id, &user.id, &filename, &hash, Some(meta))
+        .create_file(
+            &file_id,
+            &user.id,
+            &filename,
+            &file_path.to_string_lossy(),
+            Some(meta),
+        )
+        .await?;
+
+    if !query.process.unwrap_or(true) {
+        return Ok(HttpResponse::Ok().json(FileResponse::from(file)));
+    }
+
+    // Extract and index in the background, progress is reported via /{id}/process/status
+    let file = service
+        .update_file_data(&file.id, serde_json::json!({ "status": "pending" }))
         .await?;
+    let task_state = state.clone();
+    let mut task_file = file.clone();
+    task_file.parse_json_fields();
+    actix_web::rt::spawn(async move {
+        let _ = process_file_content(&task_state, &task_file, None, None).await;
+    });
 
     Ok(HttpResponse::Ok().json(FileResponse::from(file)))
 }
@@ -221,11 +253,20 @@ async fn get_file(
     Ok(HttpResponse::Ok().json(response))
 }
 
+#[derive(Debug, Deserialize)]
+struct ProcessStatusQuery {
+    stream: Option<bool>,
+}
+
+/// Longest time a status stream is kept open while a file is being processed
+const MAX_FILE_PROCESSING_DURATION: std::time::Duration = std::time::Duration::from_secs(3600 * 2);
+
 // GET /{id}/process/status - Get file process status
 async fn get_file_process_status(
     db: web::Data<Database>,
     user: AuthUser,
     file_id: web::Path<String>,
+    query: web::Query<ProcessStatusQuery>,
 ) -> AppResult<HttpResponse> {
     let service = FileService::new(&db);
 
@@ -246,20 +287,72 @@ async fn get_file_process_status(
         })));
     }
 
+    if query.stream.unwrap_or(false) {
+        return Ok(stream_file_process_status(db.clone(), file.id));
+    }
+
     file.parse_json_fields();
 
-    let status = if let Some(ref data) = file.data {
-        data.get("status")
-            .and_then(|s| s.as_str())
-            .unwrap_or("pending")
-            .to_string()
-    } else {
-        "pending".to_string()
-    };
+    Ok(HttpResponse::Ok().json(file_process_status(file.data.as_ref())))
+}
 
-    Ok(HttpResponse::Ok().json(serde_json::json!({
-        "status": status
-    })))
+/// Build the `{status, error?}` event for a file's processing state
+fn file_process_status(data: Option<&serde_json::Value>) -> serde_json::Value {
+    let status = data
+        .and_then(|d| d.get("status"))
+        .and_then(|s| s.as_str())
+        .unwrap_or("pending");
+
+    let mut event = serde_json::json!({ "status": status });
+    if status == "failed" {
+        event["error"] = data
+            .and_then(|d| d.get("error"))
+            .cloned()
+            .unwrap_or(serde_json::Value::Null);
+    }
+    event
+}
+
+/// Stream status updates as server-sent events until processing finishes
+fn stream_file_process_status(db: web::Data<Database>, file_id: String) -> HttpResponse {
+    let poll_interval = std::time::Duration::from_millis(500);
+    let max_polls = MAX_FILE_PROCESSING_DURATION.as_millis() / poll_interval.as_millis();
+
+    let stream = futures::stream::unfold((0u128, false), move |(polls, done)| {
+        let db = db.clone();
+        let file_id = file_id.clone();
+        async move {
+            if done || polls >= max_polls {
+                return None;
+            }
+            if polls > 0 {
+                tokio::time::sleep(poll_interval).await;
+            }
+
+            let service = FileService::new(&db);
+            let event = match service.get_file_by_id(&file_id).await {
+                Ok(Some(mut file)) => {
+                    file.parse_json_fields();
+                    file_process_status(file.data.as_ref())
+                }
+                _ => serde_json::json!({ "status": "not_found" }),
+            };
+            let finished = matches!(
+                event["status"].as_str(),
+                Some("completed" | "failed" | "not_found")
+            );
+
+            let chunk = web::Bytes::from(format!("data: {}\n\n", event));
+            Some((Ok::<_, actix_web::Error>(chunk), (polls + 1, finished)))
+        }
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream; charset=utf-8")
+        .append_header(("Cache-Control", "no-cache, no-transform"))
+        .append_header(("X-Accel-Buffering", "no"))
+        .insert_header(("Content-Encoding", "identity"))
+        .streaming(stream)
 }
 
 // GET /{id}/data/content - Get file data content
@@ -5,7 +5,11 @@ use validator::Validate;
 
 use crate::error::{AppError, AppResult};
 use crate::middleware::{AuthMiddleware, AuthUser};
+use crate::plugins::PluginPackage;
 use crate::services::function::FunctionService;
+use crate::services::user::UserService;
+use crate::utils::functions::valves_specs;
+use crate::utils::valves;
 use crate::AppState;
 
 #[derive(Debug, Serialize)]
@@ -151,6 +155,24 @@ pub fn create_routes(cfg: &mut web::ServiceConfig) {
     );
 }
 
+/// Type of a function, from its content
+///
+/// WebAssembly packages are compiled to check them and declare their type in
+/// their manifest; other content keeps the default type "pipe".
+async fn function_type(state: &AppState, content: &str) -> AppResult<String> {
+    if !PluginPackage::is_package(content) {
+        return Ok("pipe".to_string());
+    }
+    let runtime = state.plugin_runtime.as_ref().ok_or_else(|| {
+        AppError::NotImplemented("The WebAssembly function runtime is not available".to_string())
+    })?;
+    let manifest = runtime
+        .validate(content)
+        .await
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+    Ok(manifest.function_type.as_str().to_string())
+}
+
 async fn get_functions(
     state: web::Data<AppState>,
     _auth_user: AuthUser,
@@ -385,13 +407,13 @@ async fn create_new_function(
         return Err(AppError::Conflict("Function ID already exists".to_string()));
     }
 
-    // Create function with default type "pipe"
+    let function_type = function_type(&state, &form.content).await?;
     let function = function_service
         .create_function(
             &form.id.to_lowercase(),
             &auth_user.user.id,
             &form.name,
-            "pipe", // Default type
+            &function_type,
             &form.content,
             form.meta.clone(),
             true,  // is_active
@@ -511,11 +533,12 @@ async fn update_function_by_id(
         .await?
         .ok_or_else(|| AppError::NotFound("Function not found".to_string()))?;
 
+    let function_type = function_type(&state, &form.content).await?;
     let updated_function = function_service
         .update_function(
             &id,
             Some(&form.name),
-            Some("pipe"), // Default type
+            Some(&function_type),
             Some(&form.content),
             Some(form.meta.clone()),
             true,  // Keep active
@@ -561,21 +584,30 @@ async fn get_function_valves(
         .await?
         .ok_or_else(|| AppError::NotFound("Function not found".to_string()))?;
 
-    let valves = function.valves.unwrap_or(json!({}));
+    let valves = function.valves.clone().unwrap_or(json!({}));
+    let valves = match valves_specs(&function).0 {
+        Some(spec) => valves::mask_secrets(&valves, &spec),
+        None => valves,
+    };
     Ok(HttpResponse::Ok().json(valves))
 }
 
 async fn get_function_valves_spec(
-    _state: web::Data<AppState>,
+    state: web::Data<AppState>,
     auth_user: AuthUser,
-    _id: web::Path<String>,
+    id: web::Path<String>,
 ) -> AppResult<HttpResponse> {
     if auth_user.user.role != "admin" {
         return Err(AppError::Forbidden("Admin access required".to_string()));
     }
 
-    // TODO: Implement function module loading and Valves spec extraction
-    Ok(HttpResponse::Ok().json(json!(null)))
+    let function_service = FunctionService::new(&state.db);
+    let function = function_service
+        .get_function_by_id(&id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Function not found".to_string()))?;
+
+    Ok(HttpResponse::Ok().json(valves_specs(&function).0))
 }
 
 async fn update_function_valves(
@@ -589,55 +621,92 @@ async fn update_function_valves(
     }
 
     let function_service = FunctionService::new(&state.db);
-    let _function = function_service
+    let function = function_service
         .get_function_by_id(&id)
         .await?
         .ok_or_else(|| AppError::NotFound("Function not found".to_string()))?;
 
-    let valves_data = valves.into_inner();
+    let spec = valves_specs(&function).0;
+    let current = function.valves.clone().unwrap_or(json!({}));
+    let valves_data = valves::apply_update(&current, valves.into_inner(), spec.as_ref())?;
     function_service
         .update_function_valves(&id, valves_data.clone())
         .await?;
+
+    let valves_data = match spec {
+        Some(spec) => valves::mask_secrets(&valves_data, &spec),
+        None => valves_data,
+    };
     Ok(HttpResponse::Ok().json(valves_data))
 }
 
 async fn get_function_user_valves(
     state: web::Data<AppState>,
-    _auth_user: AuthUser,
+    auth_user: AuthUser,
     id: web::Path<String>,
 ) -> AppResult<HttpResponse> {
     let function_service = FunctionService::new(&state.db);
-    let _function = function_service
+    let function = function_service
         .get_function_by_id(&id)
         .await?
         .ok_or_else(|| AppError::NotFound("Function not found".to_string()))?;
 
-    // TODO: Get user valves from user settings
-    Ok(HttpResponse::Ok().json(json!({})))
+    let user_valves =
+        valves::user_valves(auth_user.user.settings.as_ref(), "functions", &function.id);
+    let user_valves = match valves_specs(&function).1 {
+        Some(spec) => valves::mask_secrets(&user_valves, &spec),
+        None => user_valves,
+    };
+    Ok(HttpResponse::Ok().json(user_valves))
 }
 
 async fn get_function_user_valves_spec(
-    _state: web::Data<AppState>,
+    state: web::Data<AppState>,
     _auth_user: AuthUser,
-    _id: web::Path<String>,
+    id: web::Path<String>,
 ) -> AppResult<HttpResponse> {
-    // TODO: Implement UserValves spec extraction
-    Ok(HttpResponse::Ok().json(json!(null)))
+    let function_service = FunctionService::new(&state.db);
+    let function = function_service
+        .get_function_by_id(&id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Function not found".to_string()))?;
+
+    Ok(HttpResponse::Ok().json(valves_specs(&function).1))
 }
 
 async fn update_function_user_valves(
     state: web::Data<AppState>,
-    _auth_user: AuthUser,
+    auth_user: AuthUser,
     id: web::Path<String>,
     valves: web::Json<serde_json::Value>,
 ) -> AppResult<HttpResponse> {
     let function_service = FunctionService::new(&state.db);
-    let _function = function_service
+    let function = function_service
         .get_function_by_id(&id)
         .await?
         .ok_or_else(|| AppError::NotFound("Function not found".to_string()))?;
 
-    // TODO: Update user valves in user settings
-    let valves_data = valves.into_inner();
-    Ok(HttpResponse::Ok().json(valves_data))
+    let spec = valves_specs(&function)
+        .1
+        .ok_or_else(|| AppError::BadRequest("This function has no user valves".to_string()))?;
+
+    // Re-read the settings so concurrent updates to other valves are kept
+    let user_service = UserService::new(&state.db);
+    let settings = user_service
+        .get_user_by_id(&auth_user.user.id)
+        .await?
+        .and_then(|u| u.settings);
+    let current = valves::user_valves(settings.as_ref(), "functions", &function.id);
+    let valves_data = valves::apply_update(&current, valves.into_inner(), Some(&spec))?;
+    let settings = valves::set_user_valves(
+        settings.as_ref(),
+        "functions",
+        &function.id,
+        valves_data.clone(),
+    );
+    user_service
+        .update_user_settings(&auth_user.user.id, &settings)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(valves::mask_secrets(&valves_data, &spec)))
 }
@@ -4,6 +4,7 @@ use serde::{Deserialize, Serialize};
 use crate::{
     error::AppError,
     middleware::{AuthMiddleware, AuthUser},
+    services::image::{self, ImageGenerationRequest},
     AppState,
 };
 
@@ -77,23 +78,26 @@ pub fn create_routes(cfg: &mut web::ServiceConfig) {
 
 #[derive(Debug, Deserialize)]
 struct GenerateImageForm {
-    model: String,
+    #[serde(default)]
+    model: Option<String>,
     prompt: String,
     #[serde(default)]
     size: Option<String>,
     #[serde(default)]
-    n: Option<i32>,
+    n: Option<usize>,
     #[serde(default)]
     negative_prompt: Option<String>,
+    #[serde(default)]
+    steps: Option<u32>,
 }
 
 /// POST /generations - Generate image
 async fn generate_image(
     state: web::Data<AppState>,
-    _auth_user: AuthUser,
-    _form_data: web::Json<GenerateImageForm>,
+    auth_user: AuthUser,
+    form_data: web::Json<GenerateImageForm>,
 ) -> Result<HttpResponse, AppError> {
-    let config = state.config.read().unwrap();
+    let config = state.config.read().unwrap().clone();
 
     if !config.enable_image_generation {
         return Err(AppError::Forbidden(
@@ -101,15 +105,23 @@ async fn generate_image(
         ));
     }
 
-    // TODO: Implement image generation based on configured engine
-    // - OpenAI DALL-E
-    // - Automatic1111
-    // - ComfyUI
-    // - Gemini
+    let form_data = form_data.into_inner();
+    let images = image::generate_images(
+        &state.db,
+        &config,
+        &auth_user.id,
+        ImageGenerationRequest {
+            prompt: form_data.prompt,
+            model: form_data.model,
+            size: form_data.size,
+            n: form_data.n,
+            negative_prompt: form_data.negative_prompt,
+            steps: form_data.steps,
+        },
+    )
+    .await?;
 
-    Err(AppError::NotImplemented(
-        "Image generation not yet implemented".to_string(),
-    ))
+    Ok(HttpResponse::Ok().json(images))
 }
 
 /// GET /models - Get available image generation models
@@ -191,12 +203,12 @@ async fn get_image_config(
         return Err(AppError::Forbidden("Admin access required".to_string()));
     }
 
-    let _config = state.config.read().unwrap();
+    let config = state.config.read().unwrap();
 
     Ok(HttpResponse::Ok().json(serde_json::json!({
-        "MODEL": "dall-e-2",
-        "IMAGE_SIZE": "512x512",
-        "IMAGE_STEPS": 50,
+        "MODEL": config.image_generation_model,
+        "IMAGE_SIZE": config.image_size,
+        "IMAGE_STEPS": config.image_steps,
     })))
 }
 
@@ -207,7 +219,7 @@ struct ImageConfigForm {
     #[serde(rename = "IMAGE_SIZE")]
     image_size: String,
     #[serde(rename = "IMAGE_STEPS")]
-    image_steps: i32,
+    image_steps: u32,
 }
 
 async fn update_image_config(
@@ -220,6 +232,15 @@ async fn update_image_config(
         return Err(AppError::Forbidden("Admin access required".to_string()));
     }
 
+    image::parse_size(&form_data.image_size)?;
+
+    {
+        let mut config = state.config.write().unwrap();
+        config.image_generation_model = form_data.model.clone();
+        config.image_size = form_data.image_size.clone();
+        config.image_steps = form_data.image_steps;
+    }
+
     // Persist to database
     let image_config_json = serde_json::json!({
         "model": form_data.model,
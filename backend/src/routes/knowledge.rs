@@ -699,10 +699,11 @@ async fn add_file_to_knowledge(
     }
 
     // Check if file exists
-    let file = file_service
+    let mut file = file_service
         .get_file_by_id(&form.file_id)
         .await?
         .ok_or_else(|| AppError::NotFound("File not found".to_string()))?;
+    file.parse_json_fields();
 
     // Check if file has been processed (has data)
     if file.data.is_none() {
@@ -713,10 +714,12 @@ async fn add_file_to_knowledge(
     if let Some((vector_db, embedding_provider)) =
         knowledge_vector::get_rag_components(&state.vector_db, &state.embedding_provider)
     {
+        let splitter = knowledge_vector::get_text_splitter(&state.config.read().unwrap())?;
         match knowledge_vector::process_and_index_file(
             &vector_db,
             &embedding_provider,
             &file_service,
+            splitter.as_ref(),
             &form.file_id,
             &knowledge_id,
         )
@@ -847,10 +850,12 @@ async fn update_file_in_knowledge(
         }
 
         // Re-index the file with updated content
+        let splitter = knowledge_vector::get_text_splitter(&state.config.read().unwrap())?;
         match knowledge_vector::process_and_index_file(
             &vector_db,
             &embedding_provider,
             &file_service,
+            splitter.as_ref(),
             &form.file_id,
             &knowledge_id,
         )
@@ -1180,11 +1185,13 @@ async fn reindex_all_knowledge(
                 // Process each file
                 let mut indexed_files = 0;
                 let mut failed_files = 0;
+                let splitter = knowledge_vector::get_text_splitter(&state.config.read().unwrap())?;
                 for file in files {
                     match knowledge_vector::process_and_index_file(
                         &vector_db,
                         &embedding_provider,
                         &file_service,
+                        splitter.as_ref(),
                         &file.id,
                         &knowledge_base.id,
                     )
@@ -1267,10 +1274,11 @@ async fn add_files_batch(
     // Validate all files exist first
     let mut validated_file_ids = Vec::new();
     for file_form in form.iter() {
-        let file = file_service
+        let mut file = file_service
             .get_file_by_id(&file_form.file_id)
             .await?
             .ok_or_else(|| AppError::NotFound(format!("File {} not found", file_form.file_id)))?;
+        file.parse_json_fields();
 
         // Check if file has been processed
         if file.data.is_none() {
@@ -1290,11 +1298,13 @@ async fn add_files_batch(
         let mut successful_files = Vec::new();
         let mut failed_files = Vec::new();
 
+        let splitter = knowledge_vector::get_text_splitter(&state.config.read().unwrap())?;
         for file_id in &validated_file_ids {
             match knowledge_vector::process_and_index_file(
                 &vector_db,
                 &embedding_provider,
                 &file_service,
+                splitter.as_ref(),
                 file_id,
                 &knowledge_id,
             )
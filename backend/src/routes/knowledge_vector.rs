@@ -1,6 +1,8 @@
 /// Helper functions for vector database operations in knowledge routes
+use crate::config::Config;
 use crate::error::{AppError, AppResult};
-use crate::retrieval::{chunk_text, EmbeddingProvider, VectorDB, VectorError};
+use crate::retrieval::chunking::TextSplitter;
+use crate::retrieval::{ChunkingConfig, Document, EmbeddingProvider, VectorDB, VectorError};
 use crate::services::file::FileService;
 use serde_json::json;
 use std::sync::Arc;
@@ -20,6 +22,7 @@ pub async fn process_and_index_file(
     vector_db: &Arc<dyn VectorDB>,
     embedding_provider: &Arc<dyn EmbeddingProvider>,
     file_service: &FileService<'_>,
+    splitter: &dyn TextSplitter,
     file_id: &str,
     knowledge_id: &str,
 ) -> AppResult<usize> {
@@ -29,10 +32,11 @@ pub async fn process_and_index_file(
     );
 
     // Get file
-    let file = file_service
+    let mut file = file_service
         .get_file_by_id(file_id)
         .await?
         .ok_or_else(|| AppError::NotFound(format!("File {} not found", file_id)))?;
+    file.parse_json_fields();
 
     // Check if file has data
     let file_data = file
@@ -48,21 +52,8 @@ pub async fn process_and_index_file(
         return Ok(0);
     }
 
-    // Chunk the content
-    let chunk_size = std::env::var("CHUNK_SIZE")
-        .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(512);
-    let chunk_overlap = std::env::var("CHUNK_OVERLAP")
-        .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(50);
-
-    debug!(
-        "Chunking content with size={}, overlap={}",
-        chunk_size, chunk_overlap
-    );
-    let chunks = chunk_text(&content, chunk_size, chunk_overlap);
+    // Chunk the content with the configured splitter
+    let chunks = splitter.split_documents(vec![Document::new(content, json!({}))]);
 
     if chunks.is_empty() {
         warn!("No chunks generated for file {}", file_id);
@@ -72,7 +63,7 @@ pub async fn process_and_index_file(
     info!("Generated {} chunks for file {}", chunks.len(), file_id);
 
     // Generate embeddings
-    let texts: Vec<String> = chunks.iter().map(|s| s.to_string()).collect();
+    let texts: Vec<String> = chunks.iter().map(|c| c.page_content.clone()).collect();
     let embeddings = embedding_provider
         .embed(texts)
         .await
@@ -85,19 +76,21 @@ pub async fn process_and_index_file(
         .into_iter()
         .zip(embeddings)
         .enumerate()
-        .map(
-            |(idx, (chunk, embedding))| crate::retrieval::vector::types::VectorItem {
+        .map(|(idx, (chunk, embedding))| {
+            // Splitter metadata (start_index, headings) plus the file details
+            let mut metadata = chunk.metadata;
+            metadata["file_id"] = json!(file_id);
+            metadata["knowledge_id"] = json!(knowledge_id);
+            metadata["chunk_index"] = json!(idx);
+            metadata["filename"] = json!(file.filename);
+
+            crate::retrieval::vector::types::VectorItem {
                 id: format!("{}-chunk-{}", file_id, idx),
-                text: chunk,
+                text: chunk.page_content,
                 vector: embedding,
-                metadata: json!({
-                    "file_id": file_id,
-                    "knowledge_id": knowledge_id,
-                    "chunk_index": idx,
-                    "filename": file.filename,
-                }),
-            },
-        )
+                metadata,
+            }
+        })
         .collect();
 
     let item_count = items.len();
@@ -258,6 +251,13 @@ pub fn get_rag_components(
     }
 }
 
+/// Build the text splitter configured in the RAG settings
+pub fn get_text_splitter(config: &Config) -> AppResult<Box<dyn TextSplitter>> {
+    ChunkingConfig::from_config(config)
+        .and_then(|chunking| chunking.create_splitter())
+        .map_err(|e| AppError::BadRequest(format!("Invalid chunking configuration: {}", e)))
+}
+
 /// Log RAG disabled warning
 pub fn log_rag_disabled(operation: &str) {
     debug!(
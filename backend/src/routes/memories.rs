@@ -1,12 +1,17 @@
 use actix_web::{web, HttpResponse};
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use uuid::Uuid;
 
-use crate::db::Database;
 use crate::error::AppResult;
-use crate::middleware::auth::AuthUser;
+use crate::middleware::auth::{AuthMiddleware, AuthUser};
 use crate::models::memory::MemoryResponse;
+use crate::retrieval::vector::types::SearchResult;
 use crate::services::memory::MemoryService;
+use crate::utils::memory::{
+    delete_memory_collection, delete_memory_vectors, query_memory_vectors, upsert_memory_vectors,
+};
+use crate::utils::retrieval::get_embedding_function;
+use crate::AppState;
 
 #[derive(Debug, Deserialize)]
 pub struct AddMemoryForm {
@@ -29,23 +34,32 @@ fn default_k() -> i64 {
     1
 }
 
-#[derive(Debug, Serialize)]
-pub struct QueryMemoryResult {
-    pub results: Vec<MemoryResponse>,
+/// Keep the SQLite table authoritative: vector index failures (e.g. when
+/// retrieval is not configured) are logged, and `/reset` rebuilds the index
+fn log_index_error(result: AppResult<()>, user_id: &str) {
+    if let Err(e) = result {
+        tracing::warn!("Failed to update memory index for user {}: {}", user_id, e);
+    }
 }
 
-// GET /ef - Get embeddings (testing endpoint)
-async fn get_embeddings(_user: AuthUser) -> AppResult<HttpResponse> {
-    // TODO: Implement embedding function integration
-    // For now, return a placeholder
+// GET /ef - Embed a sample text (testing endpoint)
+async fn get_embeddings(state: web::Data<AppState>, _user: AuthUser) -> AppResult<HttpResponse> {
+    let embedding_function = get_embedding_function(&state)?;
+    let embeddings = embedding_function
+        .embed_content(vec!["hello world".to_string()])
+        .await
+        .map_err(|e| {
+            crate::error::AppError::Internal(format!("Failed to generate embedding: {}", e))
+        })?;
+
     Ok(HttpResponse::Ok().json(serde_json::json!({
-        "result": "Embedding function not yet implemented"
+        "result": embeddings.into_iter().next().unwrap_or_default()
     })))
 }
 
 // GET / - Get memories by user
-async fn get_memories(db: web::Data<Database>, user: AuthUser) -> AppResult<HttpResponse> {
-    let service = MemoryService::new(&db);
+async fn get_memories(state: web::Data<AppState>, user: AuthUser) -> AppResult<HttpResponse> {
+    let service = MemoryService::new(&state.db);
     let memories = service.get_memories_by_user_id(&user.id).await?;
 
     let responses: Vec<MemoryResponse> = memories.into_iter().map(|m| m.into()).collect();
@@ -54,27 +68,21 @@ async fn get_memories(db: web::Data<Database>, user: AuthUser) -> AppResult<Http
 
 // POST /add - Add memory with vector upsert
 async fn add_memory(
-    db: web::Data<Database>,
+    state: web::Data<AppState>,
     user: AuthUser,
     form: web::Json<AddMemoryForm>,
 ) -> AppResult<HttpResponse> {
-    let service = MemoryService::new(&db);
+    let service = MemoryService::new(&state.db);
 
     let memory_id = Uuid::new_v4().to_string();
     let memory = service
         .create_memory(&memory_id, &user.id, &form.content, None)
         .await?;
 
-    // TODO: Implement vector DB upsert
-    // VECTOR_DB_CLIENT.upsert(
-    //     collection_name=f"user-memory-{user.id}",
-    //     items=[{
-    //         "id": memory.id,
-    //         "text": memory.content,
-    //         "vector": EMBEDDING_FUNCTION(memory.content),
-    //         "metadata": {"created_at": memory.created_at},
-    //     }],
-    // )
+    log_index_error(
+        upsert_memory_vectors(&state, &user.id, std::slice::from_ref(&memory)).await,
+        &user.id,
+    );
 
     let response: MemoryResponse = memory.into();
     Ok(HttpResponse::Ok().json(response))
@@ -82,11 +90,11 @@ async fn add_memory(
 
 // POST /query - Query memories with vector search
 async fn query_memory(
-    db: web::Data<Database>,
+    state: web::Data<AppState>,
     user: AuthUser,
     form: web::Json<QueryMemoryForm>,
 ) -> AppResult<HttpResponse> {
-    let service = MemoryService::new(&db);
+    let service = MemoryService::new(&state.db);
 
     // First check if user has any memories
     let memories = service.get_memories_by_user_id(&user.id).await?;
@@ -96,59 +104,66 @@ async fn query_memory(
         })));
     }
 
-    // TODO: Implement vector DB search
-    // For now, do a simple text search
-    let results = service
-        .query_memories(&user.id, &form.content, form.k)
-        .await?;
-
-    let responses: Vec<MemoryResponse> = results.into_iter().map(|m| m.into()).collect();
-    Ok(HttpResponse::Ok().json(serde_json::json!({
-        "results": responses
-    })))
+    let k = form.k.max(1);
+    let results = match query_memory_vectors(&state, &user.id, &form.content, k as usize).await {
+        Ok(results) => results,
+        Err(e) => {
+            // Without a vector index, fall back to substring matching
+            tracing::warn!("Memory vector search failed, using text search: {}", e);
+            let matches = service.query_memories(&user.id, &form.content, k).await?;
+            SearchResult {
+                ids: Some(vec![matches.iter().map(|m| m.id.clone()).collect()]),
+                documents: Some(vec![matches.iter().map(|m| m.content.clone()).collect()]),
+                metadatas: Some(vec![matches
+                    .iter()
+                    .map(|m| {
+                        serde_json::json!({
+                            "created_at": m.created_at,
+                            "updated_at": m.updated_at,
+                        })
+                    })
+                    .collect()]),
+                distances: None,
+            }
+        }
+    };
+    Ok(HttpResponse::Ok().json(results))
 }
 
-// POST /reset - Reset memory from vector DB
-async fn reset_memory(db: web::Data<Database>, user: AuthUser) -> AppResult<HttpResponse> {
-    let service = MemoryService::new(&db);
-
-    // TODO: Implement vector DB operations
-    // VECTOR_DB_CLIENT.delete_collection(f"user-memory-{user.id}")
+// POST /reset - Rebuild the user's memory collection from the database
+async fn reset_memory(state: web::Data<AppState>, user: AuthUser) -> AppResult<HttpResponse> {
+    let service = MemoryService::new(&state.db);
 
-    let _memories = service.get_memories_by_user_id(&user.id).await?;
+    delete_memory_collection(&state, &user.id).await?;
 
-    // TODO: Re-index all memories in vector DB
-    // VECTOR_DB_CLIENT.upsert(
-    //     collection_name=f"user-memory-{user.id}",
-    //     items=[...],
-    // )
+    let memories = service.get_memories_by_user_id(&user.id).await?;
+    upsert_memory_vectors(&state, &user.id, &memories).await?;
 
     Ok(HttpResponse::Ok().json(true))
 }
 
 // DELETE /delete/user - Delete all memories by user ID
 async fn delete_memories_by_user(
-    db: web::Data<Database>,
+    state: web::Data<AppState>,
     user: AuthUser,
 ) -> AppResult<HttpResponse> {
-    let service = MemoryService::new(&db);
+    let service = MemoryService::new(&state.db);
 
     service.delete_memories_by_user_id(&user.id).await?;
 
-    // TODO: Delete vector collection
-    // VECTOR_DB_CLIENT.delete_collection(f"user-memory-{user.id}")
+    log_index_error(delete_memory_collection(&state, &user.id).await, &user.id);
 
     Ok(HttpResponse::Ok().json(true))
 }
 
 // POST /{memory_id}/update - Update memory by ID
 async fn update_memory(
-    db: web::Data<Database>,
+    state: web::Data<AppState>,
     user: AuthUser,
     memory_id: web::Path<String>,
     form: web::Json<UpdateMemoryForm>,
 ) -> AppResult<HttpResponse> {
-    let service = MemoryService::new(&db);
+    let service = MemoryService::new(&state.db);
 
     // First verify the memory exists and belongs to the user
     let existing = service.get_memory_by_id(&memory_id).await?;
@@ -169,10 +184,12 @@ async fn update_memory(
         .update_memory(&memory_id, form.content.as_deref(), None)
         .await?;
 
-    // TODO: Update vector DB
-    // if form.content.is_some() {
-    //     VECTOR_DB_CLIENT.upsert(...)
-    // }
+    if form.content.is_some() {
+        log_index_error(
+            upsert_memory_vectors(&state, &user.id, std::slice::from_ref(&memory)).await,
+            &user.id,
+        );
+    }
 
     let response: MemoryResponse = memory.into();
     Ok(HttpResponse::Ok().json(response))
@@ -180,11 +197,11 @@ async fn update_memory(
 
 // DELETE /{memory_id} - Delete memory by ID
 async fn delete_memory(
-    db: web::Data<Database>,
+    state: web::Data<AppState>,
     user: AuthUser,
     memory_id: web::Path<String>,
 ) -> AppResult<HttpResponse> {
-    let service = MemoryService::new(&db);
+    let service = MemoryService::new(&state.db);
 
     // First verify the memory exists and belongs to the user
     let existing = service.get_memory_by_id(&memory_id).await?;
@@ -199,20 +216,21 @@ async fn delete_memory(
 
     service.delete_memory(&memory_id).await?;
 
-    // TODO: Delete from vector DB
-    // VECTOR_DB_CLIENT.delete(
-    //     collection_name=f"user-memory-{user.id}",
-    //     ids=[memory_id]
-    // )
+    log_index_error(
+        delete_memory_vectors(&state, &user.id, vec![memory_id.into_inner()]).await,
+        &user.id,
+    );
 
     Ok(HttpResponse::Ok().json(true))
 }
 
 pub fn create_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
-        web::scope("/memories")
+        web::scope("")
+            .wrap(AuthMiddleware)
             .route("/ef", web::get().to(get_embeddings))
             .route("", web::get().to(get_memories))
+            .route("/", web::get().to(get_memories))
             .route("/add", web::post().to(add_memory))
             .route("/query", web::post().to(query_memory))
             .route("/reset", web::post().to(reset_memory))
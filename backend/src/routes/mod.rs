@@ -1,4 +1,5 @@
 pub mod audio;
+pub mod audit;
 pub mod auth;
 pub mod cache;
 pub mod channels;
@@ -16,9 +17,11 @@ pub mod knowledge_vector; // Vector DB operations for knowledge
 pub mod memories;
 pub mod models;
 pub mod notes;
+pub mod ollama;
 pub mod openai;
 pub mod pipelines;
 pub mod prompts;
+pub mod quotas;
 pub mod retrieval;
 pub mod scim;
 pub mod tasks;
@@ -30,6 +33,7 @@ use actix_web::web;
 
 pub fn create_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(web::scope("/audio").configure(audio::create_routes))
+        .service(web::scope("/audit").configure(audit::create_routes))
         .service(web::scope("/auths").configure(auth::create_routes))
         .service(web::scope("/api/v1").configure(cache::configure))
         .service(web::scope("/channels").configure(channels::create_routes))
@@ -48,6 +52,7 @@ pub fn create_routes(cfg: &mut web::ServiceConfig) {
         .service(web::scope("/notes").configure(notes::create_routes))
         .service(web::scope("/pipelines").configure(pipelines::create_routes))
         .service(web::scope("/prompts").configure(prompts::create_routes))
+        .service(web::scope("/quotas").configure(quotas::create_routes))
         .service(web::scope("/retrieval").configure(retrieval::create_routes))
         .service(web::scope("/scim/v2").configure(scim::create_routes))
         .service(web::scope("/tasks").configure(tasks::create_routes))
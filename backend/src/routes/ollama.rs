@@ -0,0 +1,319 @@
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    error::AppError,
+    middleware::{AuthMiddleware, AuthUser},
+    services::ollama::{self, OllamaConnection},
+    AppState,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaConfigResponse {
+    #[serde(rename = "ENABLE_OLLAMA_API")]
+    enable_ollama_api: bool,
+    #[serde(rename = "OLLAMA_BASE_URLS")]
+    ollama_base_urls: Vec<String>,
+    #[serde(rename = "OLLAMA_API_CONFIGS")]
+    ollama_api_configs: serde_json::Value,
+}
+
+pub fn create_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("")
+            .wrap(AuthMiddleware)
+            .route("/config", web::get().to(get_config))
+            .route("/config/update", web::post().to(update_config))
+            .route("/api/version", web::get().to(get_version))
+            .route("/api/version/{url_idx}", web::get().to(get_version))
+            .route("/api/tags", web::get().to(get_tags))
+            .route("/api/tags/{url_idx}", web::get().to(get_tags))
+            .route("/api/show", web::post().to(show_model))
+            .route("/api/pull", web::post().to(pull_model))
+            .route("/api/pull/{url_idx}", web::post().to(pull_model))
+            .route("/api/delete", web::delete().to(delete_model))
+            .route("/api/delete/{url_idx}", web::delete().to(delete_model)),
+    );
+}
+
+fn require_admin(auth_user: &AuthUser) -> Result<(), AppError> {
+    if auth_user.user.role != "admin" {
+        return Err(AppError::Forbidden("Admin access required".to_string()));
+    }
+    Ok(())
+}
+
+fn config_response(config: &Config) -> OllamaConfigResponse {
+    OllamaConfigResponse {
+        enable_ollama_api: config.enable_ollama_api,
+        ollama_base_urls: config.ollama_base_urls.clone(),
+        ollama_api_configs: config.ollama_api_configs.clone(),
+    }
+}
+
+async fn get_config(
+    state: web::Data<AppState>,
+    auth_user: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&auth_user)?;
+
+    let config = state.config.read().unwrap();
+    Ok(HttpResponse::Ok().json(config_response(&config)))
+}
+
+async fn update_config(
+    state: web::Data<AppState>,
+    auth_user: AuthUser,
+    form_data: web::Json<OllamaConfigResponse>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&auth_user)?;
+
+    let config = {
+        let mut config = state.config.write().unwrap();
+        config.enable_ollama_api = form_data.enable_ollama_api;
+        config.ollama_base_urls = form_data
+            .ollama_base_urls
+            .iter()
+            .map(|url| url.trim().trim_end_matches('/').to_string())
+            .filter(|url| !url.is_empty())
+            .collect();
+        config.ollama_api_configs = form_data.ollama_api_configs.clone();
+        config.clone()
+    };
+
+    // Persist to database (best-effort, like the OpenAI connections)
+    let ollama_json = serde_json::json!({
+        "enable": config.enable_ollama_api,
+        "base_urls": config.ollama_base_urls,
+        "api_configs": config.ollama_api_configs,
+    });
+    let _ = crate::services::ConfigService::update_section(&state.db, "ollama", ollama_json).await;
+
+    Ok(HttpResponse::Ok().json(config_response(&config)))
+}
+
+fn enabled_config(state: &web::Data<AppState>) -> Result<Config, AppError> {
+    let config = state.config.read().unwrap().clone();
+    if !config.enable_ollama_api {
+        return Err(AppError::Forbidden("Ollama API is disabled".to_string()));
+    }
+    Ok(config)
+}
+
+fn connection(config: &Config, url_idx: usize) -> Result<OllamaConnection, AppError> {
+    OllamaConnection::from_config(config, url_idx)
+        .ok_or_else(|| AppError::NotFound(format!("Ollama connection {} not found", url_idx)))
+}
+
+/// Refresh the merged Ollama model list and record which connections serve each model
+pub async fn refresh_models(
+    state: &web::Data<AppState>,
+    config: &Config,
+) -> Vec<serde_json::Value> {
+    let models = ollama::get_all_models(&state.http_client, config).await;
+
+    let mut cache = state.models_cache.write().unwrap();
+    cache.retain(|_, model| model.get("owned_by").and_then(|o| o.as_str()) != Some("ollama"));
+    for model in &models {
+        if let Some(id) = model.get("model").and_then(|m| m.as_str()) {
+            cache.insert(
+                id.to_string(),
+                serde_json::json!({
+                    "id": id,
+                    "owned_by": "ollama",
+                    "urls": model["urls"],
+                    "urlIdx": model["urls"][0],
+                }),
+            );
+        }
+    }
+    models
+}
+
+/// Connection to route a chat completion to, if `model_id` is an Ollama model
+///
+/// Models the UI lists carry `owned_by`, other callers are looked up in the
+/// model cache, refreshing it once for models that have not been seen yet.
+pub async fn resolve_connection(
+    state: &web::Data<AppState>,
+    config: &Config,
+    model_id: &str,
+    model_item: &serde_json::Value,
+) -> Option<OllamaConnection> {
+    if !config.enable_ollama_api || config.ollama_base_urls.is_empty() {
+        return None;
+    }
+    let owned_by = model_item.get("owned_by").and_then(|o| o.as_str());
+    if owned_by.is_some_and(|o| o != "ollama") {
+        return None;
+    }
+
+    let cached = |state: &web::Data<AppState>| {
+        let cache = state.models_cache.read().unwrap();
+        cache.get(model_id).map(|model| {
+            (model.get("owned_by").and_then(|o| o.as_str()) == Some("ollama"))
+                .then(|| model.get("urlIdx").and_then(|i| i.as_u64()))
+                .flatten()
+        })
+    };
+
+    let url_idx = match cached(state) {
+        Some(idx) => idx,
+        None => {
+            refresh_models(state, config).await;
+            cached(state).flatten()
+        }
+    }?;
+
+    OllamaConnection::from_config(config, url_idx as usize).filter(|conn| conn.enabled())
+}
+
+/// First connection serving `model`, refreshing the model list if needed
+async fn connection_for_model(
+    state: &web::Data<AppState>,
+    config: &Config,
+    model: &str,
+) -> Result<OllamaConnection, AppError> {
+    resolve_connection(
+        state,
+        config,
+        model,
+        &serde_json::json!({ "owned_by": "ollama" }),
+    )
+    .await
+    .ok_or_else(|| AppError::NotFound(format!("Model {} not found", model)))
+}
+
+/// GET /api/version[/{url_idx}]
+async fn get_version(
+    state: web::Data<AppState>,
+    _auth_user: AuthUser,
+    url_idx: Option<web::Path<usize>>,
+) -> Result<HttpResponse, AppError> {
+    let config = enabled_config(&state)?;
+
+    let connections = match url_idx {
+        Some(idx) => vec![connection(&config, idx.into_inner())?],
+        None => OllamaConnection::all(&config),
+    };
+
+    // Report the lowest version across connections, as features depend on it
+    let mut lowest: Option<String> = None;
+    for conn in &connections {
+        match ollama::get_version(&state.http_client, conn).await {
+            Ok(version) => {
+                if let Some(v) = version.get("version").and_then(|v| v.as_str()) {
+                    if lowest.as_deref().is_none_or(|l| version_lt(v, l)) {
+                        lowest = Some(v.to_string());
+                    }
+                }
+            }
+            Err(e) => tracing::warn!("Failed to get Ollama version from {}: {}", conn.base_url, e),
+        }
+    }
+
+    let version = lowest
+        .ok_or_else(|| AppError::ExternalServiceError("Ollama is not reachable".to_string()))?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "version": version })))
+}
+
+fn version_lt(a: &str, b: &str) -> bool {
+    let parse = |v: &str| -> Vec<u64> {
+        v.split(|c: char| !c.is_ascii_digit())
+            .filter_map(|p| p.parse().ok())
+            .collect()
+    };
+    parse(a) < parse(b)
+}
+
+/// GET /api/tags[/{url_idx}] - Models in Ollama's native format
+async fn get_tags(
+    state: web::Data<AppState>,
+    _auth_user: AuthUser,
+    url_idx: Option<web::Path<usize>>,
+) -> Result<HttpResponse, AppError> {
+    let config = enabled_config(&state)?;
+
+    let models = match url_idx {
+        Some(idx) => {
+            let conn = connection(&config, idx.into_inner())?;
+            ollama::list_models(&state.http_client, &conn).await?
+        }
+        None => refresh_models(&state, &config).await,
+    };
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "models": models })))
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelNameForm {
+    #[serde(alias = "name")]
+    model: String,
+}
+
+/// POST /api/show - Model details (modelfile, parameters, template)
+async fn show_model(
+    state: web::Data<AppState>,
+    _auth_user: AuthUser,
+    form_data: web::Json<ModelNameForm>,
+) -> Result<HttpResponse, AppError> {
+    let config = enabled_config(&state)?;
+    let conn = connection_for_model(&state, &config, &form_data.model).await?;
+
+    let details = ollama::show_model(&state.http_client, &conn, &form_data.model).await?;
+    Ok(HttpResponse::Ok().json(details))
+}
+
+/// POST /api/pull[/{url_idx}] - Pull a model, streaming Ollama's NDJSON progress
+async fn pull_model(
+    state: web::Data<AppState>,
+    auth_user: AuthUser,
+    url_idx: Option<web::Path<usize>>,
+    form_data: web::Json<ModelNameForm>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&auth_user)?;
+    let config = enabled_config(&state)?;
+    let conn = connection(&config, url_idx.map(|i| i.into_inner()).unwrap_or(0))?;
+
+    let response = ollama::pull_model(&state.http_client, &conn, &form_data.model).await?;
+    let stream = futures::StreamExt::map(response.bytes_stream(), |chunk| {
+        chunk.map_err(actix_web::error::ErrorBadGateway)
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(stream))
+}
+
+/// DELETE /api/delete[/{url_idx}] - Delete a model
+async fn delete_model(
+    state: web::Data<AppState>,
+    auth_user: AuthUser,
+    url_idx: Option<web::Path<usize>>,
+    form_data: web::Json<ModelNameForm>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&auth_user)?;
+    let config = enabled_config(&state)?;
+    let conn = match url_idx {
+        Some(idx) => connection(&config, idx.into_inner())?,
+        None => connection_for_model(&state, &config, &form_data.model).await?,
+    };
+
+    ollama::delete_model(&state.http_client, &conn, &form_data.model).await?;
+    state.models_cache.write().unwrap().remove(&form_data.model);
+
+    Ok(HttpResponse::Ok().json(true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_lt() {
+        assert!(version_lt("0.5.7", "0.6.0"));
+        assert!(version_lt("0.9.9", "0.10.0"));
+        assert!(!version_lt("0.6.0", "0.6.0"));
+    }
+}
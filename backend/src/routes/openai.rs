@@ -5,6 +5,7 @@ use serde::{Deserialize, Serialize};
 use crate::{
     error::AppError,
     middleware::{AuthMiddleware, AuthUser},
+    services::providers::Provider,
     utils::chat_completion::{self, StreamingContext},
     AppState,
 };
@@ -172,49 +173,44 @@ async fn get_models(
                 }
             } else {
                 // Fetch models from the endpoint
-                match client
-                    .get(format!("{}/models", url))
-                    .header("Authorization", format!("Bearer {}", key))
-                    .header("Content-Type", "application/json")
+                let api_config = serde_json::Value::Object(api_config);
+                let provider = Provider::from_api_config(&api_config);
+                match provider
+                    .models_request(&client, url, key, &api_config)
                     .send()
                     .await
                 {
                     Ok(response) if response.status().is_success() => {
                         if let Ok(models_response) = response.json::<serde_json::Value>().await {
-                            if let Some(data) =
-                                models_response.get("data").and_then(|v| v.as_array())
-                            {
-                                for model in data {
-                                    if let Some(model_id) = model.get("id").and_then(|v| v.as_str())
-                                    {
-                                        // Filter out certain OpenAI models
-                                        if url.contains("api.openai.com") {
-                                            let should_skip = [
-                                                "babbage",
-                                                "dall-e",
-                                                "davinci",
-                                                "embedding",
-                                                "tts",
-                                                "whisper",
-                                            ]
-                                            .iter()
-                                            .any(|name| model_id.contains(name));
-
-                                            if should_skip {
-                                                continue;
-                                            }
+                            for model in &provider.openai_models(&models_response) {
+                                if let Some(model_id) = model.get("id").and_then(|v| v.as_str()) {
+                                    // Filter out certain OpenAI models
+                                    if url.contains("api.openai.com") {
+                                        let should_skip = [
+                                            "babbage",
+                                            "dall-e",
+                                            "davinci",
+                                            "embedding",
+                                            "tts",
+                                            "whisper",
+                                        ]
+                                        .iter()
+                                        .any(|name| model_id.contains(name));
+
+                                        if should_skip {
+                                            continue;
                                         }
-
-                                        all_models.push(serde_json::json!({
-                                            "id": model_id,
-                                            "name": model.get("name").and_then(|v| v.as_str()).unwrap_or(model_id),
-                                            "object": "model",
-                                            "owned_by": model.get("owned_by").and_then(|v| v.as_str()).unwrap_or("openai"),
-                                            "openai": model,
-                                            "connection_type": "external",
-                                            "urlIdx": idx
-                                        }));
                                     }
+
+                                    all_models.push(serde_json::json!({
+                                        "id": model_id,
+                                        "name": model.get("name").and_then(|v| v.as_str()).unwrap_or(model_id),
+                                        "object": "model",
+                                        "owned_by": model.get("owned_by").and_then(|v| v.as_str()).unwrap_or("openai"),
+                                        "openai": model,
+                                        "connection_type": "external",
+                                        "urlIdx": idx
+                                    }));
                                 }
                             }
                         }
@@ -237,7 +233,8 @@ async fn get_models(
     // Cache the models in app state (like Python's OPENAI_MODELS)
     {
         let mut cache = state.models_cache.write().unwrap();
-        cache.clear();
+        // Ollama entries are maintained by the /ollama routes
+        cache.retain(|_, model| model.get("owned_by").and_then(|o| o.as_str()) == Some("ollama"));
         for model in &all_models {
             if let Some(model_id) = model.get("id").and_then(|v| v.as_str()) {
                 cache.insert(model_id.to_string(), model.clone());
@@ -947,11 +944,12 @@ async fn chat_completions(
 /// Process streaming response and emit events via Socket.IO (wrapper function)
 /// Delegates to chat_completion module for actual implementation
 async fn process_streaming_via_socketio(
-    response: reqwest::Response,
+    stream: chat_completion::ChatByteStream,
     state: &web::Data<AppState>,
     user_id: &str,
     model_id: String,
     messages: Vec<serde_json::Value>,
+    request_messages: Vec<serde_json::Value>,
     chat_id: Option<String>,
     message_id: Option<String>,
     session_id: Option<String>,
@@ -959,8 +957,12 @@ async fn process_streaming_via_socketio(
     model_item: serde_json::Value,
     endpoint_url: String,
     endpoint_key: String,
+    endpoint_config: serde_json::Value,
+    connection_label: String,
     tool_ids: Vec<String>,
     tool_specs: Vec<serde_json::Value>,
+    tool_servers: Vec<crate::services::openapi::OpenApiToolServer>,
+    function_filters: crate::utils::functions::FunctionFilters,
 ) -> Result<(), Box<dyn std::error::Error>> {
     // Create streaming context
     let context = StreamingContext {
@@ -968,6 +970,7 @@ async fn process_streaming_via_socketio(
         user_id: user_id.to_string(),
         model_id,
         messages,
+        request_messages,
         chat_id,
         message_id,
         session_id,
@@ -975,13 +978,17 @@ async fn process_streaming_via_socketio(
         model_item,
         endpoint_url,
         endpoint_key,
+        endpoint_config,
+        connection_label,
         tool_ids,
         tool_specs,
+        tool_servers,
+        function_filters,
         delta_chunk_size: None, // TODO: Extract from request params when frontend supports it
     };
 
     // Delegate to chat_completion module
-    chat_completion::process_streaming_via_socketio(response, context).await
+    chat_completion::process_streaming_via_socketio(stream, context).await
 }
 
 // ============================================================================
@@ -993,6 +1000,37 @@ async fn process_streaming_via_socketio(
 // - Tool execution and multi-turn conversation logic
 // ============================================================================
 
+/// Whether the user may use a tool server connection with this access control
+async fn can_use_tool_server(
+    state: &web::Data<AppState>,
+    auth_user: &AuthUser,
+    access_control: &Option<serde_json::Value>,
+) -> bool {
+    use crate::services::group::GroupService;
+    use crate::utils::misc::has_access;
+    use std::collections::HashSet;
+
+    if auth_user.user.role == "admin" {
+        return true;
+    }
+
+    let user_group_ids: HashSet<String> = GroupService::new(&state.db)
+        .get_groups_by_member_id(&auth_user.user.id)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|g| g.id)
+        .collect();
+    let allowed = has_access(&auth_user.user.id, "read", access_control, &user_group_ids);
+    if !allowed {
+        tracing::warn!(
+            "User {} does not have access to a requested tool server",
+            auth_user.user.id
+        );
+    }
+    allowed
+}
+
 // Public handler for chat completions that can be called from main.rs
 pub async fn handle_chat_completions(
     state: web::Data<AppState>,
@@ -1018,6 +1056,19 @@ pub async fn handle_chat_completions(
         .ok_or_else(|| AppError::BadRequest("Model ID is required".to_string()))?
         .to_string();
 
+    // Requests/day and tokens/month quotas of the user and their groups; the
+    // request is counted right before it is sent upstream
+    let quota_service = crate::services::quota::QuotaService::new(&state.db);
+    if let Some(exceeded) = quota_service.check(&auth_user.user.id, &model_id).await? {
+        tracing::info!(
+            "User {} is over quota on {}: {}",
+            auth_user.user.id,
+            model_id,
+            exceeded.detail()
+        );
+        return Ok(crate::routes::quotas::quota_exceeded_response(&exceeded));
+    }
+
     // Extract model_item from payload (matching Python's behavior exactly)
     let mut payload_obj = payload.into_inner();
     let model_item = payload_obj
@@ -1050,6 +1101,26 @@ pub async fn handle_chat_completions(
         .and_then(|v| v.as_bool())
         .unwrap_or(false);
 
+    // Filter Functions rewrite the request here and the streamed chunks later
+    let function_metadata = serde_json::json!({
+        "chat_id": chat_id,
+        "message_id": message_id,
+        "session_id": session_id,
+        "model": model_id,
+    });
+    let function_filters = crate::utils::functions::FunctionFilters::load(
+        &state,
+        &crate::utils::functions::request_filter_ids(&payload_obj, &model_item),
+        &auth_user.user,
+        &function_metadata,
+    )
+    .await?;
+    if !function_filters.is_empty() {
+        payload_obj = function_filters
+            .apply(crate::plugins::Hook::Inlet, payload_obj)
+            .await?;
+    }
+
     // Extract messages for title generation before removing from payload
     let messages = payload_obj
         .get("messages")
@@ -1076,6 +1147,81 @@ pub async fn handle_chat_completions(
         tracing::info!("🔧 Tools requested in chat: {}", tool_ids.join(", "));
     }
 
+    // Web search runs once the model's connection is known, since it generates the queries
+    let web_search = payload_obj
+        .get("features")
+        .and_then(|f| f.get("web_search"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    // Memory feature: add the user's most relevant memories to the system prompt
+    let memory = payload_obj
+        .get("features")
+        .and_then(|f| f.get("memory"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    if memory {
+        payload_obj =
+            crate::utils::memory::process_memory(&state, payload_obj, &auth_user.user.id).await;
+    }
+
+    // Image generation feature: generate from the last user message and tell the model
+    let image_generation = payload_obj
+        .get("features")
+        .and_then(|f| f.get("image_generation"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    if image_generation {
+        let config = state.config.read().unwrap().clone();
+        if config.enable_image_generation {
+            let (updated, event) = crate::utils::chat_middleware::process_image_generation(
+                &state.db,
+                payload_obj,
+                &auth_user.user,
+                &config,
+            )
+            .await;
+            payload_obj = updated;
+
+            if let (Some(event), Some(socket_state)) = (event, state.socket_state.clone()) {
+                let emit = crate::socket::get_event_emitter(
+                    socket_state,
+                    auth_user.user.id.clone(),
+                    chat_id.clone(),
+                    message_id.clone(),
+                    session_id.clone(),
+                );
+                emit(event).await;
+            }
+        }
+    }
+
+    // Direct tool servers the user registered in their own settings
+    let direct_tool_servers = match payload_obj.get("tool_servers") {
+        Some(servers) if servers.as_array().is_some_and(|s| !s.is_empty()) => {
+            let user_permissions = state.config.read().unwrap().user_permissions.clone();
+            let allowed = auth_user.user.role == "admin"
+                || crate::utils::access_control::has_permission(
+                    &state.db,
+                    &auth_user.user.id,
+                    "features.direct_tool_servers",
+                    &user_permissions,
+                )
+                .await
+                .unwrap_or(false);
+            if allowed {
+                crate::services::openapi::OpenApiToolServer::from_direct(servers)
+            } else {
+                tracing::warn!(
+                    "User {} is not allowed to use direct tool servers",
+                    auth_user.user.id
+                );
+                Vec::new()
+            }
+        }
+        _ => Vec::new(),
+    };
+
     // Remove these from payload before forwarding to LLM API
     if let Some(obj) = payload_obj.as_object_mut() {
         obj.remove("session_id");
@@ -1092,10 +1238,14 @@ pub async fn handle_chat_completions(
 
     // Prepare tool specs storage (moved outside if block for later use)
     let mut all_tool_specs = Vec::new();
+    // Tools the user may use; only these are executed when the model calls them
+    let mut allowed_tool_ids = Vec::new();
 
-    // Load and inject tools if tool_ids are provided
-    if !tool_ids.is_empty() {
+    // Load and inject tools if tool_ids or direct tool servers are provided
+    if !tool_ids.is_empty() || !direct_tool_servers.is_empty() {
         use crate::models::tool_runtime::ToolDefinition;
+        use crate::services::mcp::McpToolServer;
+        use crate::services::openapi::OpenApiToolServer;
         use crate::services::tool::ToolService;
 
         tracing::info!(
@@ -1107,6 +1257,57 @@ pub async fn handle_chat_completions(
         let tool_service = ToolService::new(&state.db);
 
         for tool_id in &tool_ids {
+            // Tool servers configured by the admin
+            if tool_id.starts_with(OpenApiToolServer::ID_PREFIX) {
+                let connections = state.config.read().unwrap().tool_server_connections.clone();
+                if let Some(server) = McpToolServer::find(&connections, tool_id) {
+                    if can_use_tool_server(&state, &auth_user, &server.access_control).await {
+                        allowed_tool_ids.push(tool_id.clone());
+                        match server.list_tools().await {
+                            Ok(tools) => {
+                                tracing::info!(
+                                    "Loaded {} tool spec(s) from MCP server {}",
+                                    tools.len(),
+                                    server.name
+                                );
+                                all_tool_specs.extend(tools.iter().map(|t| t.to_openai_spec()));
+                            }
+                            Err(e) => {
+                                tracing::error!(
+                                    "Failed to list tools from MCP server {}: {}",
+                                    tool_id,
+                                    e
+                                );
+                            }
+                        }
+                    }
+                } else if let Some(server) = OpenApiToolServer::find(&connections, tool_id) {
+                    if can_use_tool_server(&state, &auth_user, &server.access_control).await {
+                        allowed_tool_ids.push(tool_id.clone());
+                        match server.tools(&state.http_client).await {
+                            Ok(tools) => {
+                                tracing::info!(
+                                    "Loaded {} tool spec(s) from OpenAPI server {}",
+                                    tools.len(),
+                                    server.name
+                                );
+                                all_tool_specs.extend(tools.iter().map(|t| t.to_openai_spec()));
+                            }
+                            Err(e) => {
+                                tracing::error!(
+                                    "Failed to load OpenAPI spec for {}: {}",
+                                    tool_id,
+                                    e
+                                );
+                            }
+                        }
+                    }
+                } else {
+                    tracing::warn!("Tool server {} not found", tool_id);
+                }
+                continue;
+            }
+
             match tool_service.get_tool_by_id(tool_id).await {
                 Ok(Some(tool)) => {
                     // Check access permissions
@@ -1137,6 +1338,7 @@ pub async fn handle_chat_completions(
                             continue;
                         }
                     }
+                    allowed_tool_ids.push(tool_id.clone());
 
                     // Parse tool definition and extract OpenAI specs
                     match ToolDefinition::from_json(&tool.content) {
@@ -1167,6 +1369,22 @@ pub async fn handle_chat_completions(
             }
         }
 
+        for server in &direct_tool_servers {
+            match server.tools(&state.http_client).await {
+                Ok(tools) => {
+                    tracing::info!(
+                        "Loaded {} tool spec(s) from direct tool server {}",
+                        tools.len(),
+                        server.url
+                    );
+                    all_tool_specs.extend(tools.iter().map(|t| t.to_openai_spec()));
+                }
+                Err(e) => {
+                    tracing::error!("Failed to load direct tool server {}: {}", server.url, e);
+                }
+            }
+        }
+
         // Inject tools into payload in OpenAI format
         if !all_tool_specs.is_empty() {
             tracing::info!(
@@ -1270,10 +1488,17 @@ pub async fn handle_chat_completions(
                     .unwrap_or_default();
                 let user_group_ids: HashSet<String> = groups.into_iter().map(|g| g.id).collect();
 
+                // Search attached files and collections with the latest user message
+                let queries: Vec<String> =
+                    crate::utils::retrieval::get_last_user_message(&messages)
+                        .into_iter()
+                        .collect();
+
                 // Extract sources from file items (notes, files, chats, etc.)
                 match crate::utils::retrieval::get_sources_from_items(
                     &state,
                     file_items.clone(),
+                    &queries,
                     &auth_user.user,
                     &user_group_ids,
                 )
@@ -1297,38 +1522,6 @@ pub async fn handle_chat_completions(
                             sources.len(),
                             unique_ids.len()
                         );
-
-                        // Inject sources into messages if we have any
-                        if !sources.is_empty() {
-                            // Get RAG template from config
-                            let rag_template = {
-                                let config = state.config.read().unwrap();
-                                config.rag_template.clone()
-                            };
-
-                            // Get mutable reference to messages array
-                            if let Some(messages_value) = payload_obj.get_mut("messages") {
-                                if let Some(messages_array) = messages_value.as_array_mut() {
-                                    match crate::utils::retrieval::inject_sources_into_messages(
-                                        sources.clone(),
-                                        messages_array,
-                                        &rag_template,
-                                    ) {
-                                        Ok(_) => {
-                                            tracing::info!(
-                                                "✅ Successfully injected RAG context into user message"
-                                            );
-                                        }
-                                        Err(e) => {
-                                            tracing::error!(
-                                                "❌ Failed to inject RAG context: {}",
-                                                e
-                                            );
-                                        }
-                                    }
-                                }
-                            }
-                        }
                     }
                     Err(e) => {
                         tracing::error!("❌ Failed to extract sources from file items: {}", e);
@@ -1359,9 +1552,38 @@ pub async fn handle_chat_completions(
         model_id
     );
 
+    // Ollama models are served through the native /api/chat endpoint
+    let ollama_connection = if is_direct {
+        None
+    } else {
+        let config = state.config.read().unwrap().clone();
+        crate::routes::ollama::resolve_connection(&state, &config, &model_id, &model_item).await
+    };
+
+    // Pipe Functions answer in place of a connection
+    let pipe = if is_direct || ollama_connection.is_some() {
+        None
+    } else {
+        crate::utils::functions::get_pipe(&state, &model_id).await?
+    };
+
     // If direct connections not enabled, just use regular OpenAI routing
     let config = state.config.read().unwrap();
-    let (url, key, api_config) = if is_direct && config.enable_direct_connections {
+    let (url, key, api_config) = if pipe.is_some() {
+        (String::new(), String::new(), serde_json::json!({}))
+    } else if let Some(conn) = &ollama_connection {
+        tracing::info!(
+            "Using Ollama connection {} (idx: {}) for model {}",
+            conn.base_url,
+            conn.idx,
+            model_id
+        );
+        (
+            conn.openai_base_url(),
+            conn.key.clone(),
+            conn.config.clone(),
+        )
+    } else if is_direct && config.enable_direct_connections {
         // Direct connection - look up URL and key from user settings using urlIdx
         // The frontend sends urlIdx which points to the user's directConnections settings
 
@@ -1605,41 +1827,190 @@ pub async fn handle_chat_completions(
         }
     };
 
-    // Prepare the request to the OpenAI-compatible endpoint
-    let client = reqwest::Client::new();
-    let mut request_builder = client
-        .post(format!("{}/chat/completions", url))
-        .header("Content-Type", "application/json");
+    drop(config);
 
-    // Add authorization header based on auth_type
-    let auth_type = api_config
-        .get("auth_type")
-        .and_then(|v| v.as_str())
-        .unwrap_or("bearer");
+    let emit = state.socket_state.clone().map(|socket_state| {
+        crate::socket::get_event_emitter(
+            socket_state,
+            auth_user.user.id.clone(),
+            chat_id.clone(),
+            message_id.clone(),
+            session_id.clone(),
+        )
+    });
 
-    match auth_type {
-        "none" => {
-            // No authentication
+    if web_search {
+        match crate::routes::retrieval::require_web_search_permission(&state, &auth_user).await {
+            Ok(()) => {
+                if let Some(emit) = &emit {
+                    emit(serde_json::json!({
+                        "type": "status",
+                        "data": {
+                            "action": "web_search",
+                            "description": "Generating search query",
+                            "done": false,
+                        }
+                    }))
+                    .await;
+                }
+
+                let queries = crate::utils::web_search::generate_search_queries(
+                    &state,
+                    &messages,
+                    &model_id,
+                    &url,
+                    &key,
+                    &api_config,
+                )
+                .await;
+                let status = match crate::utils::web_search::get_web_search_sources(
+                    &state, &queries,
+                )
+                .await
+                {
+                    Ok(web_sources) => {
+                        let urls: Vec<serde_json::Value> = web_sources
+                            .iter()
+                            .filter_map(|s| s.source.get("urls").and_then(|u| u.as_array()))
+                            .flatten()
+                            .cloned()
+                            .collect();
+                        sources.extend(web_sources);
+                        serde_json::json!({
+                            "action": "web_search",
+                            "description": format!("Searched {} sites", urls.len()),
+                            "query": queries.join(", "),
+                            "urls": urls,
+                            "done": true,
+                        })
+                    }
+                    Err(e) => {
+                        tracing::warn!("Web search failed: {}", e);
+                        serde_json::json!({
+                            "action": "web_search",
+                            "description": "An error occurred while searching the web",
+                            "query": queries.join(", "),
+                            "error": true,
+                            "done": true,
+                        })
+                    }
+                };
+                if let Some(emit) = &emit {
+                    emit(serde_json::json!({ "type": "status", "data": status })).await;
+                }
+            }
+            Err(e) => tracing::warn!("Skipping web search: {}", e),
         }
-        _ => {
-            // Default to bearer token for all other cases
-            if !key.is_empty() {
-                request_builder =
-                    request_builder.header("Authorization", format!("Bearer {}", key));
+    }
+
+    // Inject file and web search sources as RAG context and cite them in the message
+    if !sources.is_empty() {
+        let rag_template = state.config.read().unwrap().rag_template.clone();
+        if let Some(messages_array) = payload_obj
+            .get_mut("messages")
+            .and_then(|m| m.as_array_mut())
+        {
+            match crate::utils::retrieval::inject_sources_into_messages(
+                sources.clone(),
+                messages_array,
+                &rag_template,
+            ) {
+                Ok(_) => tracing::info!("✅ Successfully injected RAG context into user message"),
+                Err(e) => tracing::error!("❌ Failed to inject RAG context: {}", e),
             }
-        } // TODO: Add support for other auth types like "session", "system_oauth", "azure_ad"
+        }
+        if let Some(emit) = &emit {
+            emit(serde_json::json!({
+                "type": "chat:completion",
+                "data": { "sources": sources },
+            }))
+            .await;
+        }
     }
 
-    // Forward the modified payload (already extracted earlier)
+    // Prepare the request, translated for Ollama and non-OpenAI providers
+    let client = reqwest::Client::new();
+    let provider = Provider::from_api_config(&api_config);
+    // OpenAI-compatible upstreams only report the usage of a stream when asked
+    if provider == Provider::OpenAi && ollama_connection.is_none() && pipe.is_none() {
+        chat_completion::request_stream_usage(&mut payload_obj);
+    }
+    let request_builder = match &ollama_connection {
+        Some(conn) => conn
+            .request(&client, reqwest::Method::POST, "/api/chat")
+            .json(&crate::services::ollama::openai_to_ollama_chat(
+                &payload_obj,
+            )),
+        None => provider.chat_request(&client, &url, &key, &api_config, &payload_obj),
+    };
 
-    match request_builder.json(&payload_obj).send().await {
+    if let Some(exceeded) = quota_service
+        .record_request(&auth_user.user.id, &model_id)
+        .await?
+    {
+        tracing::info!(
+            "User {} is over quota on {}: {}",
+            auth_user.user.id,
+            model_id,
+            exceeded.detail()
+        );
+        return Ok(crate::routes::quotas::quota_exceeded_response(&exceeded));
+    }
+
+    // Upstream latency and token usage for /metrics and usage quotas
+    let connection_label = match &pipe {
+        Some(pipe) => format!("function:{}", pipe.id),
+        None if is_direct => "direct".to_string(),
+        None => crate::utils::metrics::connection_label(&url),
+    };
+    let llm_call = crate::utils::metrics::LlmCall::start(&model_id, connection_label.clone())
+        .on_usage(crate::services::quota::token_recorder(
+            state.db.clone(),
+            auth_user.user.id.clone(),
+            model_id.clone(),
+        ));
+
+    let upstream = match &pipe {
+        Some(pipe) => Ok(crate::utils::functions::run_pipe(
+            &state,
+            pipe,
+            &payload_obj,
+            &auth_user.user,
+            &function_metadata,
+        )
+        .await?),
+        None => request_builder.send().await,
+    };
+
+    match upstream {
         Ok(response) if response.status().is_success() => {
             // Check if it's a streaming response
             let content_type = response
                 .headers()
                 .get("content-type")
                 .and_then(|v| v.to_str().ok())
-                .unwrap_or("");
+                .unwrap_or("")
+                .to_string();
+            let is_ollama = ollama_connection.is_some();
+            let content_type = if is_ollama && content_type.contains("ndjson") {
+                "text/event-stream".to_string()
+            } else {
+                content_type
+            };
+            let into_stream = |response: reqwest::Response,
+                               llm_call: crate::utils::metrics::LlmCall|
+             -> chat_completion::ChatByteStream {
+                let stream: chat_completion::ChatByteStream = if is_ollama {
+                    Box::pin(crate::services::ollama::ndjson_to_openai_sse(
+                        response.bytes_stream(),
+                    ))
+                } else {
+                    provider.openai_stream(response)
+                };
+                function_filters
+                    .clone()
+                    .filter_stream(llm_call.instrument(stream))
+            };
 
             let is_stream = payload_obj
                 .get("stream")
@@ -1677,20 +2048,34 @@ pub async fn handle_chat_completions(
                     let session_id_owned = session_id.clone();
                     let model_id_owned = model_id.clone();
                     let messages_owned = messages.clone();
+                    let request_messages_owned = payload_obj
+                        .get("messages")
+                        .and_then(|v| v.as_array())
+                        .cloned()
+                        .unwrap_or_default();
                     let should_generate_title_owned = should_generate_title;
                     let model_item_owned = model_item.clone();
                     let url_owned = url.clone();
                     let key_owned = key.clone();
-                    let tool_ids_owned = tool_ids.clone();
+                    let api_config_owned = api_config.clone();
+                    let connection_label_owned = connection_label.clone();
+                    let tool_ids_owned = allowed_tool_ids.clone();
                     let all_tool_specs_owned = all_tool_specs.clone();
-
-                    tokio::spawn(async move {
+                    let direct_tool_servers_owned = direct_tool_servers.clone();
+                    let function_filters_owned = function_filters.clone();
+                    let stream = into_stream(response, llm_call);
+                    let task_chat_id = chat_id.clone();
+                    let task_user_id = user_id.clone();
+
+                    // Registered under the chat, so stopping the task drops the upstream stream
+                    let generation = async move {
                         if let Err(e) = process_streaming_via_socketio(
-                            response,
+                            stream,
                             &state_clone,
                             &user_id,
                             model_id_owned,
                             messages_owned,
+                            request_messages_owned,
                             chat_id,
                             message_id,
                             session_id_owned,
@@ -1698,32 +2083,47 @@ pub async fn handle_chat_completions(
                             model_item_owned,
                             url_owned,
                             key_owned,
+                            api_config_owned,
+                            connection_label_owned,
                             tool_ids_owned,
                             all_tool_specs_owned,
+                            direct_tool_servers_owned,
+                            function_filters_owned,
                         )
                         .await
                         {
                             tracing::error!("Error processing Socket.IO stream: {}", e);
                         }
-                    });
+                    };
+                    let task_id = state
+                        .task_manager
+                        .create_user_task(generation, task_chat_id, Some(task_user_id))
+                        .await?;
 
                     // Return an immediate success response
                     // The actual streaming happens via Socket.IO
                     Ok(HttpResponse::Ok().json(serde_json::json!({
-                        "status": "streaming",
-                        "message": "Streaming via Socket.IO"
+                        "status": true,
+                        "task_id": task_id
                     })))
                 } else {
                     // Use traditional HTTP SSE streaming (no Socket.IO)
                     tracing::debug!("Using HTTP SSE streaming (no Socket.IO metadata)");
-                    chat_completion::create_sse_stream(response)
+                    chat_completion::create_sse_stream(into_stream(response, llm_call))
                 }
             } else {
                 // Return JSON response
                 tracing::debug!("Returning JSON response");
                 if let Ok(json_response) = response.json::<serde_json::Value>().await {
+                    let json_response = if is_ollama {
+                        crate::services::ollama::ollama_to_openai_response(&json_response)
+                    } else {
+                        provider.openai_response(json_response)
+                    };
+                    llm_call.finish(&json_response);
                     Ok(HttpResponse::Ok().json(json_response))
                 } else {
+                    llm_call.failed();
                     Err(AppError::InternalServerError(
                         "Failed to parse response".to_string(),
                     ))
@@ -1731,6 +2131,7 @@ pub async fn handle_chat_completions(
             }
         }
         Ok(response) => {
+            llm_call.failed();
             let status = response.status();
             let error_text = response.text().await.unwrap_or_default();
             tracing::error!("OpenAI API error: {} - {}", status, error_text);
@@ -1740,6 +2141,7 @@ pub async fn handle_chat_completions(
             )))
         }
         Err(e) => {
+            llm_call.failed();
             tracing::error!("Error calling OpenAI API: {}", e);
             Err(AppError::InternalServerError(format!(
                 "Error calling OpenAI API: {}",
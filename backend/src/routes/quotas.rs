@@ -0,0 +1,179 @@
+use actix_web::{http::header, web, HttpResponse};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::error::{AppError, AppResult};
+use crate::middleware::{AdminMiddleware, AuthMiddleware, AuthUser};
+use crate::models::quota::{QuotaExceeded, QuotaForm};
+use crate::services::quota::QuotaService;
+use crate::utils::time::current_timestamp_seconds;
+use crate::AppState;
+
+pub fn create_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("")
+            .wrap(AdminMiddleware)
+            .route(web::get().to(get_quotas)),
+    )
+    .service(
+        web::resource("/")
+            .wrap(AdminMiddleware)
+            .route(web::get().to(get_quotas)),
+    )
+    .service(
+        web::resource("/create")
+            .wrap(AdminMiddleware)
+            .route(web::post().to(create_quota)),
+    )
+    .service(
+        web::resource("/usage")
+            .wrap(AuthMiddleware)
+            .route(web::get().to(get_usage)),
+    )
+    .service(
+        web::resource("/id/{id}")
+            .wrap(AdminMiddleware)
+            .route(web::get().to(get_quota_by_id)),
+    )
+    .service(
+        web::resource("/id/{id}/update")
+            .wrap(AdminMiddleware)
+            .route(web::post().to(update_quota_by_id)),
+    )
+    .service(
+        web::resource("/id/{id}/delete")
+            .wrap(AdminMiddleware)
+            .route(web::delete().to(delete_quota_by_id)),
+    );
+}
+
+/// The 429 returned when a chat completion request would exceed a quota
+pub fn quota_exceeded_response(exceeded: &QuotaExceeded) -> HttpResponse {
+    let retry_after = (exceeded.resets_at - current_timestamp_seconds()).max(1);
+
+    HttpResponse::TooManyRequests()
+        .insert_header((header::RETRY_AFTER, retry_after.to_string()))
+        .json(serde_json::json!({
+            "detail": exceeded.detail(),
+            "quota": exceeded,
+        }))
+}
+
+fn validate(form: &QuotaForm) -> AppResult<()> {
+    if !matches!(form.target_type.as_str(), "user" | "group") {
+        return Err(AppError::BadRequest(
+            "target_type must be user or group".to_string(),
+        ));
+    }
+    if form.target_id.is_empty() {
+        return Err(AppError::BadRequest("target_id is required".to_string()));
+    }
+    if form.requests_per_day.is_none() && form.tokens_per_month.is_none() {
+        return Err(AppError::BadRequest(
+            "Set requests_per_day, tokens_per_month or both".to_string(),
+        ));
+    }
+    if form.requests_per_day.is_some_and(|n| n < 0) || form.tokens_per_month.is_some_and(|n| n < 0)
+    {
+        return Err(AppError::BadRequest(
+            "Quota limits cannot be negative".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+async fn get_quotas(state: web::Data<AppState>, _auth_user: AuthUser) -> AppResult<HttpResponse> {
+    let quota_service = QuotaService::new(&state.db);
+
+    let quotas = quota_service.get_all_quotas().await?;
+
+    Ok(HttpResponse::Ok().json(quotas))
+}
+
+async fn create_quota(
+    state: web::Data<AppState>,
+    _auth_user: AuthUser,
+    payload: web::Json<QuotaForm>,
+) -> AppResult<HttpResponse> {
+    validate(&payload)?;
+    let quota_service = QuotaService::new(&state.db);
+
+    let quota = quota_service
+        .insert_quota(&Uuid::new_v4().to_string(), &payload)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(quota))
+}
+
+async fn get_quota_by_id(
+    state: web::Data<AppState>,
+    _auth_user: AuthUser,
+    id: web::Path<String>,
+) -> AppResult<HttpResponse> {
+    let quota_service = QuotaService::new(&state.db);
+
+    let quota = quota_service
+        .get_quota_by_id(&id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Quota not found".to_string()))?;
+
+    Ok(HttpResponse::Ok().json(quota))
+}
+
+async fn update_quota_by_id(
+    state: web::Data<AppState>,
+    _auth_user: AuthUser,
+    id: web::Path<String>,
+    payload: web::Json<QuotaForm>,
+) -> AppResult<HttpResponse> {
+    validate(&payload)?;
+    let quota_service = QuotaService::new(&state.db);
+
+    let quota = quota_service.update_quota(&id, &payload).await?;
+
+    Ok(HttpResponse::Ok().json(quota))
+}
+
+async fn delete_quota_by_id(
+    state: web::Data<AppState>,
+    _auth_user: AuthUser,
+    id: web::Path<String>,
+) -> AppResult<HttpResponse> {
+    let quota_service = QuotaService::new(&state.db);
+
+    let result = quota_service.delete_quota(&id).await?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UsageQuery {
+    pub user_id: Option<String>,
+}
+
+// GET /usage - The quotas and usage of the current user; admins may pass user_id
+async fn get_usage(
+    state: web::Data<AppState>,
+    auth_user: AuthUser,
+    query: web::Query<UsageQuery>,
+) -> AppResult<HttpResponse> {
+    let user_id = match &query.user_id {
+        Some(user_id) if *user_id != auth_user.id => {
+            if auth_user.role != "admin" {
+                return Err(AppError::Forbidden("Admin access required".to_string()));
+            }
+            user_id.clone()
+        }
+        _ => auth_user.id.clone(),
+    };
+    let quota_service = QuotaService::new(&state.db);
+
+    let quotas = quota_service.get_quotas_for_user(&user_id).await?;
+    let usage = quota_service.get_usage_by_user(&user_id).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "user_id": user_id,
+        "quotas": quotas,
+        "usage": usage,
+    })))
+}
@@ -1,13 +1,32 @@
 use actix_web::{web, HttpResponse};
 use serde::{Deserialize, Serialize};
-use serde_json::json;
+use serde_json::{json, Value};
+use std::{collections::HashSet, path::PathBuf};
 
 use crate::{
     error::{AppError, AppResult},
     middleware::{AuthMiddleware, AuthUser},
+    models::file::File,
+    retrieval::{
+        chunking::TextSplitterType,
+        loaders::{ExtractionEngine, UrlLoader, YoutubeLoader},
+        vector::VectorItem,
+        ChunkingConfig, Document, Loader,
+    },
+    services::{file::FileService, group::GroupService},
+    utils::{
+        misc::sha256_hash,
+        retrieval::{
+            can_read_collection, get_embedding_function, get_vector_db, query_collection, query_doc,
+        },
+        web_search::search_web,
+    },
     AppState,
 };
 
+const DUPLICATE_CONTENT: &str =
+    "Duplicate content detected. Please provide unique content to proceed.";
+
 #[derive(Debug, Serialize, Deserialize)]
 struct RetrievalConfigResponse {
     #[serde(rename = "RAG_TEMPLATE")]
@@ -28,12 +47,20 @@ struct RetrievalConfigResponse {
     hybrid_bm25_weight: f64,
     #[serde(rename = "CONTENT_EXTRACTION_ENGINE")]
     content_extraction_engine: String,
+    #[serde(rename = "TIKA_SERVER_URL", default)]
+    tika_server_url: Option<String>,
     #[serde(rename = "PDF_EXTRACT_IMAGES")]
     pdf_extract_images: bool,
     #[serde(rename = "CHUNK_SIZE")]
     chunk_size: usize,
     #[serde(rename = "CHUNK_OVERLAP")]
     chunk_overlap: usize,
+    #[serde(rename = "TEXT_SPLITTER", default)]
+    text_splitter: Option<String>,
+    #[serde(rename = "TIKTOKEN_ENCODING_NAME", default)]
+    tiktoken_encoding_name: Option<String>,
+    #[serde(default)]
+    web: Option<WebSearchConfigForm>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -62,19 +89,19 @@ struct EmbeddingConfigResponse {
 #[derive(Debug, Deserialize)]
 struct ProcessFileForm {
     file_id: String,
+    content: Option<String>,
+    collection_name: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct ProcessTextForm {
     name: String,
     content: String,
-    collection_name: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct ProcessYoutubeForm {
     url: String,
-    collection_name: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -84,7 +111,43 @@ struct ProcessWebForm {
 
 #[derive(Debug, Deserialize)]
 struct WebSearchForm {
-    query: String,
+    #[serde(default)]
+    query: Option<String>,
+    #[serde(default)]
+    queries: Vec<String>,
+}
+
+/// Web search settings, the `web` section of the retrieval config
+#[derive(Debug, Serialize, Deserialize)]
+struct WebSearchConfigForm {
+    #[serde(rename = "ENABLE_WEB_SEARCH", default)]
+    enable_web_search: Option<bool>,
+    #[serde(rename = "WEB_SEARCH_ENGINE", default)]
+    web_search_engine: Option<String>,
+    #[serde(rename = "WEB_SEARCH_RESULT_COUNT", default)]
+    web_search_result_count: Option<usize>,
+    #[serde(rename = "WEB_SEARCH_CONCURRENT_REQUESTS", default)]
+    web_search_concurrent_requests: Option<usize>,
+    #[serde(rename = "WEB_SEARCH_DOMAIN_FILTER_LIST", default)]
+    web_search_domain_filter_list: Option<Vec<String>>,
+    #[serde(rename = "BYPASS_WEB_SEARCH_EMBEDDING_AND_RETRIEVAL", default)]
+    bypass_web_search_embedding_and_retrieval: Option<bool>,
+    #[serde(rename = "SEARXNG_QUERY_URL", default)]
+    searxng_query_url: Option<String>,
+    #[serde(rename = "BRAVE_SEARCH_API_KEY", default)]
+    brave_search_api_key: Option<String>,
+    #[serde(rename = "TAVILY_API_KEY", default)]
+    tavily_api_key: Option<String>,
+    #[serde(rename = "GOOGLE_PSE_API_KEY", default)]
+    google_pse_api_key: Option<String>,
+    #[serde(rename = "GOOGLE_PSE_ENGINE_ID", default)]
+    google_pse_engine_id: Option<String>,
+    #[serde(rename = "WEB_FETCH_FILTER_LIST", default)]
+    web_fetch_filter_list: Option<Vec<String>>,
+    #[serde(rename = "YOUTUBE_LOADER_LANGUAGE", default)]
+    youtube_loader_language: Option<Vec<String>>,
+    #[serde(rename = "YOUTUBE_LOADER_PROXY_URL", default)]
+    youtube_loader_proxy_url: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -109,6 +172,7 @@ struct DeleteForm {
 #[derive(Debug, Deserialize)]
 struct ProcessFilesBatchForm {
     file_ids: Vec<String>,
+    collection_name: Option<String>,
 }
 
 pub fn create_routes(cfg: &mut web::ServiceConfig) {
@@ -170,24 +234,35 @@ async fn get_rag_config(
         "HYBRID_BM25_WEIGHT": config.hybrid_bm25_weight,
         // Content extraction settings
         "CONTENT_EXTRACTION_ENGINE": config.content_extraction_engine,
+        "TIKA_SERVER_URL": config.tika_server_url,
         "PDF_EXTRACT_IMAGES": config.pdf_extract_images,
         // Chunking settings
-        "TEXT_SPLITTER": "RecursiveCharacterTextSplitter",
+        "TEXT_SPLITTER": config.text_splitter,
+        "TIKTOKEN_ENCODING_NAME": config.tiktoken_encoding_name,
         "CHUNK_SIZE": config.chunk_size,
         "CHUNK_OVERLAP": config.chunk_overlap,
         // File upload settings
         "FILE_MAX_SIZE": 25,
         "FILE_MAX_COUNT": 10,
         // Reranking settings
-        "RAG_RERANKING_MODEL": "",
+        "RAG_RERANKING_MODEL": config.rag_reranking_model,
         "RAG_RERANKING_ENGINE": "",
         // Web search settings - nested object
         "web": {
             "ENABLE_WEB_SEARCH": config.enable_web_search,
-            "WEB_SEARCH_ENGINE": "",
-            "WEB_SEARCH_RESULT_COUNT": 3,
-            "YOUTUBE_LOADER_LANGUAGE": vec!["en"],
-            "YOUTUBE_LOADER_PROXY_URL": "",
+            "WEB_SEARCH_ENGINE": config.web_search_engine,
+            "WEB_SEARCH_RESULT_COUNT": config.web_search_result_count,
+            "WEB_SEARCH_CONCURRENT_REQUESTS": config.web_search_concurrent_requests,
+            "WEB_SEARCH_DOMAIN_FILTER_LIST": config.web_search_domain_filter_list,
+            "BYPASS_WEB_SEARCH_EMBEDDING_AND_RETRIEVAL": config.bypass_web_search_embedding_and_retrieval,
+            "SEARXNG_QUERY_URL": config.searxng_query_url,
+            "BRAVE_SEARCH_API_KEY": config.brave_search_api_key,
+            "TAVILY_API_KEY": config.tavily_api_key,
+            "GOOGLE_PSE_API_KEY": config.google_pse_api_key,
+            "GOOGLE_PSE_ENGINE_ID": config.google_pse_engine_id,
+            "WEB_FETCH_FILTER_LIST": config.web_fetch_filter_list,
+            "YOUTUBE_LOADER_LANGUAGE": config.youtube_loader_language,
+            "YOUTUBE_LOADER_PROXY_URL": config.youtube_loader_proxy_url,
             "YOUTUBE_LOADER_TRANSLATION": "",
         },
     })))
@@ -203,8 +278,26 @@ async fn update_rag_config(
         return Err(AppError::Forbidden("Admin access required".to_string()));
     }
 
+    ExtractionEngine::from_str(&form_data.content_extraction_engine)
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+
     let mut config = state.config.write().unwrap();
 
+    // Validate the chunking settings together before applying any of them
+    let mut chunking = ChunkingConfig::from_config(&config).unwrap_or_default();
+    chunking.chunk_size = form_data.chunk_size;
+    chunking.chunk_overlap = form_data.chunk_overlap;
+    if let Some(text_splitter) = &form_data.text_splitter {
+        chunking.splitter = TextSplitterType::from_str(text_splitter)
+            .map_err(|e| AppError::BadRequest(e.to_string()))?;
+    }
+    if let Some(encoding_name) = &form_data.tiktoken_encoding_name {
+        chunking.encoding_name = encoding_name.clone();
+    }
+    chunking
+        .create_splitter()
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+
     config.rag_template = form_data.rag_template.clone();
     config.rag_top_k = form_data.top_k;
     config.bypass_embedding_and_retrieval = form_data.bypass_embedding_and_retrieval;
@@ -214,9 +307,62 @@ async fn update_rag_config(
     config.relevance_threshold = form_data.relevance_threshold;
     config.hybrid_bm25_weight = form_data.hybrid_bm25_weight;
     config.content_extraction_engine = form_data.content_extraction_engine.clone();
+    if let Some(tika_server_url) = &form_data.tika_server_url {
+        config.tika_server_url = tika_server_url.clone();
+    }
     config.pdf_extract_images = form_data.pdf_extract_images;
     config.chunk_size = form_data.chunk_size;
     config.chunk_overlap = form_data.chunk_overlap;
+    if let Some(text_splitter) = &form_data.text_splitter {
+        config.text_splitter = text_splitter.clone();
+    }
+    if let Some(encoding_name) = &form_data.tiktoken_encoding_name {
+        config.tiktoken_encoding_name = encoding_name.clone();
+    }
+    if let Some(web) = &form_data.web {
+        if let Some(enable_web_search) = web.enable_web_search {
+            config.enable_web_search = enable_web_search;
+        }
+        if let Some(engine) = &web.web_search_engine {
+            config.web_search_engine = engine.clone();
+        }
+        if let Some(count) = web.web_search_result_count {
+            config.web_search_result_count = count;
+        }
+        if let Some(concurrent_requests) = web.web_search_concurrent_requests {
+            config.web_search_concurrent_requests = concurrent_requests;
+        }
+        if let Some(domain_filter_list) = &web.web_search_domain_filter_list {
+            config.web_search_domain_filter_list = domain_filter_list.clone();
+        }
+        if let Some(bypass) = web.bypass_web_search_embedding_and_retrieval {
+            config.bypass_web_search_embedding_and_retrieval = bypass;
+        }
+        if let Some(url) = &web.searxng_query_url {
+            config.searxng_query_url = url.clone();
+        }
+        if let Some(key) = &web.brave_search_api_key {
+            config.brave_search_api_key = key.clone();
+        }
+        if let Some(key) = &web.tavily_api_key {
+            config.tavily_api_key = key.clone();
+        }
+        if let Some(key) = &web.google_pse_api_key {
+            config.google_pse_api_key = key.clone();
+        }
+        if let Some(engine_id) = &web.google_pse_engine_id {
+            config.google_pse_engine_id = engine_id.clone();
+        }
+        if let Some(filter_list) = &web.web_fetch_filter_list {
+            config.web_fetch_filter_list = filter_list.clone();
+        }
+        if let Some(languages) = &web.youtube_loader_language {
+            config.youtube_loader_language = languages.clone();
+        }
+        if let Some(proxy_url) = &web.youtube_loader_proxy_url {
+            config.youtube_loader_proxy_url = proxy_url.clone();
+        }
+    }
 
     // TODO: Persist to database
 
@@ -231,7 +377,10 @@ async fn update_rag_config(
         "RELEVANCE_THRESHOLD": config.relevance_threshold,
         "HYBRID_BM25_WEIGHT": config.hybrid_bm25_weight,
         "CONTENT_EXTRACTION_ENGINE": config.content_extraction_engine,
+        "TIKA_SERVER_URL": config.tika_server_url,
         "PDF_EXTRACT_IMAGES": config.pdf_extract_images,
+        "TEXT_SPLITTER": config.text_splitter,
+        "TIKTOKEN_ENCODING_NAME": config.tiktoken_encoding_name,
         "CHUNK_SIZE": config.chunk_size,
         "CHUNK_OVERLAP": config.chunk_overlap,
     })))
@@ -323,128 +472,510 @@ async fn update_embedding_config(
 }
 
 async fn process_file(
-    _state: web::Data<AppState>,
-    _auth_user: AuthUser,
-    _form_data: web::Json<ProcessFileForm>,
+    state: web::Data<AppState>,
+    auth_user: AuthUser,
+    form_data: web::Json<ProcessFileForm>,
 ) -> AppResult<HttpResponse> {
-    // TODO: Implement file processing
-    // 1. Get file from database
-    // 2. Load file content
-    // 3. Split into chunks
-    // 4. Generate embeddings
-    // 5. Store in vector database
-    Ok(HttpResponse::Ok().json(json!({
-        "status": false,
-        "error": "File processing not yet implemented"
-    })))
+    let form_data = form_data.into_inner();
+    let file_service = FileService::new(&state.db);
+
+    let mut file = file_service
+        .get_file_by_id(&form_data.file_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("File not found".to_string()))?;
+    file.parse_json_fields();
+
+    if file.user_id != auth_user.user.id && auth_user.user.role != "admin" {
+        return Err(AppError::NotFound("File not found".to_string()));
+    }
+
+    let result =
+        process_file_content(&state, &file, form_data.content, form_data.collection_name).await?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+/// Extract a file's text, store it on the file and index it into the vector database
+///
+/// `content` replaces the stored text (user edits), `collection_name` adds the
+/// already processed content to an existing collection. Failures are recorded
+/// on the file as `status: failed` so upload progress can be reported.
+pub async fn process_file_content(
+    state: &AppState,
+    file: &File,
+    content: Option<String>,
+    collection_name: Option<String>,
+) -> AppResult<Value> {
+    let file_service = FileService::new(&state.db);
+
+    match process_file_inner(state, &file_service, file, content, collection_name).await {
+        Ok(result) => Ok(result),
+        Err(e) => {
+            tracing::error!("Failed to process file {}: {}", file.id, e);
+            update_file_data(
+                &file_service,
+                file,
+                json!({ "status": "failed", "error": e.to_string() }),
+            )
+            .await?;
+            Err(e)
+        }
+    }
+}
+
+async fn process_file_inner(
+    state: &AppState,
+    file_service: &FileService<'_>,
+    file: &File,
+    content: Option<String>,
+    collection_name: Option<String>,
+) -> AppResult<Value> {
+    let add_to_collection = collection_name.is_some();
+    let collection_name = collection_name.unwrap_or_else(|| format!("file-{}", file.id));
+
+    let file_metadata = json!({
+        "name": file.filename,
+        "created_by": file.user_id,
+        "file_id": file.id,
+        "source": file.filename,
+    });
+
+    let docs = if let Some(content) = content {
+        // Content was edited by the user: replace the stored content
+        update_file_data(file_service, file, json!({ "content": content })).await?;
+        vec![Document::new(content, file_metadata)]
+    } else if add_to_collection {
+        // File is being added to a collection: reuse the processed content
+        vec![Document::new(
+            get_file_content(state, file).await?,
+            file_metadata,
+        )]
+    } else {
+        let docs = load_file_documents(state, file)
+            .await?
+            .into_iter()
+            .map(|mut doc| {
+                crate::utils::misc::deep_update(&mut doc.metadata, &file_metadata);
+                doc
+            })
+            .collect::<Vec<_>>();
+        let content = docs
+            .iter()
+            .map(|doc| doc.page_content.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        update_file_data(file_service, file, json!({ "content": content })).await?;
+        docs
+    };
+
+    let text_content = docs
+        .iter()
+        .map(|doc| doc.page_content.as_str())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let hash = sha256_hash(&text_content);
+    file_service.update_file_hash(&file.id, &hash).await?;
+
+    let bypass_embedding = state.config.read().unwrap().bypass_embedding_and_retrieval;
+    let rag_available = state.vector_db.is_some() && state.embedding_provider.is_some();
+
+    if bypass_embedding {
+        tracing::debug!("Embedding bypassed, stored content of file {}", file.id);
+    } else if !rag_available && !add_to_collection {
+        tracing::warn!(
+            "Vector database or embedding provider not configured, skipping indexing of file {}",
+            file.id
+        );
+    } else {
+        let chunk_count = save_docs_to_vector_db(
+            state,
+            docs,
+            &collection_name,
+            Some(json!({
+                "file_id": file.id,
+                "name": file.filename,
+                "hash": hash,
+            })),
+            false,
+            true,
+            add_to_collection,
+        )
+        .await?;
+
+        tracing::info!(
+            "Indexed {} chunk(s) from file {} into collection {}",
+            chunk_count,
+            file.id,
+            collection_name
+        );
+
+        let mut meta = file.meta.clone().unwrap_or_else(|| json!({}));
+        meta["collection_name"] = json!(collection_name);
+        file_service.update_file_metadata(&file.id, meta).await?;
+    }
+
+    update_file_data(file_service, file, json!({ "status": "completed" })).await?;
+
+    Ok(json!({
+        "status": true,
+        "collection_name": collection_name,
+        "filename": file.filename,
+        "content": text_content,
+    }))
 }
 
 async fn process_text(
-    _state: web::Data<AppState>,
-    _auth_user: AuthUser,
-    _form_data: web::Json<ProcessTextForm>,
+    state: web::Data<AppState>,
+    auth_user: AuthUser,
+    form_data: web::Json<ProcessTextForm>,
 ) -> AppResult<HttpResponse> {
-    // TODO: Implement text processing
-    // 1. Split text into chunks
-    // 2. Generate embeddings
-    // 3. Store in vector database
+    let form_data = form_data.into_inner();
+
+    let mut collection_name = sha256_hash(&form_data.content);
+    collection_name.truncate(63);
+
+    let docs = vec![Document {
+        page_content: form_data.content.clone(),
+        metadata: json!({
+            "name": form_data.name,
+            "created_by": auth_user.user.id,
+        }),
+    }];
+
+    let chunk_count =
+        save_docs_to_vector_db(&state, docs, &collection_name, None, false, true, false).await?;
+
+    tracing::info!(
+        "Indexed {} chunk(s) of text '{}' into collection {}",
+        chunk_count,
+        form_data.name,
+        collection_name
+    );
+
     Ok(HttpResponse::Ok().json(json!({
-        "status": false,
-        "error": "Text processing not yet implemented"
+        "status": true,
+        "collection_name": collection_name,
+        "content": form_data.content,
     })))
 }
 
 async fn process_youtube(
-    _state: web::Data<AppState>,
+    state: web::Data<AppState>,
     _auth_user: AuthUser,
-    _form_data: web::Json<ProcessYoutubeForm>,
+    form_data: web::Json<ProcessYoutubeForm>,
 ) -> AppResult<HttpResponse> {
-    // TODO: Implement YouTube video processing
-    // 1. Extract video transcript
-    // 2. Split into chunks
-    // 3. Generate embeddings
-    // 4. Store in vector database
-    Ok(HttpResponse::Ok().json(json!({
-        "status": false,
-        "error": "YouTube processing not yet implemented"
-    })))
+    let form_data = form_data.into_inner();
+    let loader = {
+        let config = state.config.read().unwrap();
+        YoutubeLoader::from_config(&config).map_err(|e| AppError::BadRequest(e.to_string()))?
+    };
+
+    let docs = loader
+        .load(&form_data.url)
+        .await
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+
+    save_url_docs(&state, &form_data.url, docs).await
 }
 
 async fn process_web(
-    _state: web::Data<AppState>,
+    state: web::Data<AppState>,
     _auth_user: AuthUser,
-    _form_data: web::Json<ProcessWebForm>,
+    form_data: web::Json<ProcessWebForm>,
 ) -> AppResult<HttpResponse> {
-    // TODO: Implement web page processing
-    // 1. Fetch web page content
-    // 2. Extract text
-    // 3. Split into chunks
-    // 4. Generate embeddings
-    // 5. Store in vector database
+    let form_data = form_data.into_inner();
+    let loader = {
+        let config = state.config.read().unwrap();
+        UrlLoader::from_config(state.http_client.clone(), &config)
+            .map_err(|e| AppError::BadRequest(e.to_string()))?
+    };
+
+    let docs = loader
+        .load(&form_data.url)
+        .await
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+
+    save_url_docs(&state, &form_data.url, docs).await
+}
+
+/// Index documents loaded from a URL into a collection named after the URL's
+/// hash, replacing what was indexed for it before
+///
+/// The name is never taken from the request: the collection is overwritten, and
+/// a client-chosen name could point at another user's file or memories.
+async fn save_url_docs(
+    state: &AppState,
+    url: &str,
+    docs: Vec<Document>,
+) -> AppResult<HttpResponse> {
+    let mut collection_name = sha256_hash(url);
+    collection_name.truncate(63);
+    let content = docs
+        .iter()
+        .map(|doc| doc.page_content.as_str())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    save_docs_to_vector_db(state, docs, &collection_name, None, true, true, false).await?;
+
     Ok(HttpResponse::Ok().json(json!({
-        "status": false,
-        "error": "Web processing not yet implemented"
+        "status": true,
+        "collection_name": collection_name,
+        "filename": url,
+        "file": {
+            "data": { "content": content },
+            "meta": { "name": url, "source": url },
+        },
     })))
 }
 
 async fn process_web_search(
-    _state: web::Data<AppState>,
-    _auth_user: AuthUser,
-    _form_data: web::Json<WebSearchForm>,
+    state: web::Data<AppState>,
+    auth_user: AuthUser,
+    form_data: web::Json<WebSearchForm>,
 ) -> AppResult<HttpResponse> {
-    // TODO: Implement web search
-    // 1. Use configured search engine (Brave, DuckDuckGo, etc.)
-    // 2. Return search results
+    let form_data = form_data.into_inner();
+    require_web_search_permission(&state, &auth_user).await?;
+
+    let queries: Vec<String> = form_data
+        .query
+        .into_iter()
+        .chain(form_data.queries)
+        .map(|q| q.trim().to_string())
+        .filter(|q| !q.is_empty())
+        .collect();
+    if queries.is_empty() {
+        return Err(AppError::BadRequest("No search query provided".to_string()));
+    }
+
+    let outcome = search_web(&state, &queries).await?;
+    let urls: Vec<String> = outcome.results.iter().map(|r| r.link.clone()).collect();
+    let loaded_count = outcome.docs.len();
+
+    // Results overwrite the collection, so its name never comes from the request
+    let mut collection_name = format!("web-search-{}", sha256_hash(&queries.join("-")));
+    collection_name.truncate(63);
+
+    if !outcome.docs.is_empty() {
+        save_docs_to_vector_db(
+            &state,
+            outcome.docs,
+            &collection_name,
+            None,
+            true,
+            true,
+            false,
+        )
+        .await?;
+    }
+
     Ok(HttpResponse::Ok().json(json!({
-        "results": [],
-        "error": "Web search not yet implemented"
+        "status": true,
+        "collection_name": collection_name,
+        "collection_names": [collection_name],
+        "filenames": urls,
+        "items": outcome.results,
+        "loaded_count": loaded_count,
     })))
 }
 
+/// Web search must be enabled, and non-admins need the `features.web_search` permission
+pub async fn require_web_search_permission(
+    state: &AppState,
+    auth_user: &AuthUser,
+) -> AppResult<()> {
+    let (enabled, user_permissions) = {
+        let config = state.config.read().unwrap();
+        (config.enable_web_search, config.user_permissions.clone())
+    };
+    if !enabled {
+        return Err(AppError::BadRequest("Web search is disabled".to_string()));
+    }
+    if auth_user.user.role == "admin" {
+        return Ok(());
+    }
+    let allowed = crate::utils::access_control::has_permission(
+        &state.db,
+        &auth_user.user.id,
+        "features.web_search",
+        &user_permissions,
+    )
+    .await?;
+    if !allowed {
+        return Err(AppError::Forbidden(
+            "You do not have permission to use web search".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Fail unless the user may search every one of `collection_names`
+async fn require_collection_access(
+    state: &AppState,
+    auth_user: &AuthUser,
+    collection_names: &[String],
+) -> AppResult<()> {
+    let user_group_ids: HashSet<String> = GroupService::new(&state.db)
+        .get_groups_by_member_id(&auth_user.user.id)
+        .await?
+        .into_iter()
+        .map(|g| g.id)
+        .collect();
+
+    for collection_name in collection_names {
+        if !can_read_collection(&state.db, &auth_user.user, &user_group_ids, collection_name)
+            .await?
+        {
+            return Err(AppError::Forbidden(format!(
+                "Access denied to collection {}",
+                collection_name
+            )));
+        }
+    }
+    Ok(())
+}
+
 async fn process_files_batch(
-    _state: web::Data<AppState>,
-    _auth_user: AuthUser,
-    _form_data: web::Json<ProcessFilesBatchForm>,
+    state: web::Data<AppState>,
+    auth_user: AuthUser,
+    form_data: web::Json<ProcessFilesBatchForm>,
 ) -> AppResult<HttpResponse> {
-    // TODO: Implement batch file processing
-    // 1. Process multiple files in parallel
-    // 2. Generate embeddings for all
-    // 3. Store in vector database
+    let form_data = form_data.into_inner();
+    let file_service = FileService::new(&state.db);
+
+    let mut results = Vec::new();
+    let mut errors = Vec::new();
+
+    for file_id in &form_data.file_ids {
+        let outcome: AppResult<(String, usize)> = async {
+            let mut file = file_service
+                .get_file_by_id(file_id)
+                .await?
+                .ok_or_else(|| AppError::NotFound("File not found".to_string()))?;
+            file.parse_json_fields();
+
+            if file.user_id != auth_user.user.id && auth_user.user.role != "admin" {
+                return Err(AppError::NotFound("File not found".to_string()));
+            }
+
+            let collection_name = form_data
+                .collection_name
+                .clone()
+                .unwrap_or_else(|| format!("file-{}", file.id));
+
+            let content = get_file_content(&state, &file).await?;
+            let hash = sha256_hash(&content);
+
+            let docs = vec![Document {
+                page_content: content,
+                metadata: json!({
+                    "name": file.filename,
+                    "created_by": file.user_id,
+                    "file_id": file.id,
+                    "source": file.filename,
+                }),
+            }];
+
+            let chunk_count = save_docs_to_vector_db(
+                &state,
+                docs,
+                &collection_name,
+                Some(json!({
+                    "file_id": file.id,
+                    "name": file.filename,
+                    "hash": hash,
+                })),
+                false,
+                true,
+                true,
+            )
+            .await?;
+
+            file_service.update_file_hash(&file.id, &hash).await?;
+            update_file_data(&file_service, &file, json!({ "status": "completed" })).await?;
+
+            Ok((collection_name, chunk_count))
+        }
+        .await;
+
+        match outcome {
+            Ok((collection_name, chunk_count)) => results.push(json!({
+                "file_id": file_id,
+                "status": "completed",
+                "collection_name": collection_name,
+                "chunks": chunk_count,
+            })),
+            Err(e) => {
+                tracing::error!("Failed to process file {}: {}", file_id, e);
+                errors.push(json!({
+                    "file_id": file_id,
+                    "status": "failed",
+                    "error": e.to_string(),
+                }));
+            }
+        }
+    }
+
     Ok(HttpResponse::Ok().json(json!({
-        "status": false,
-        "error": "Batch file processing not yet implemented"
+        "results": results,
+        "errors": errors,
     })))
 }
 
 async fn query_doc_handler(
-    _state: web::Data<AppState>,
-    _auth_user: AuthUser,
-    _form_data: web::Json<QueryDocForm>,
+    state: web::Data<AppState>,
+    auth_user: AuthUser,
+    form_data: web::Json<QueryDocForm>,
 ) -> AppResult<HttpResponse> {
-    // TODO: Implement document query
-    // 1. Generate query embedding
-    // 2. Search vector database
-    // 3. Return relevant chunks
-    Ok(HttpResponse::Ok().json(json!({
-        "results": [],
-        "error": "Document query not yet implemented"
-    })))
+    require_collection_access(
+        &state,
+        &auth_user,
+        std::slice::from_ref(&form_data.collection_name),
+    )
+    .await?;
+
+    let vector_db = get_vector_db(&state)?;
+    let embedding_function = get_embedding_function(&state)?;
+    let k = form_data
+        .k
+        .unwrap_or_else(|| state.config.read().unwrap().rag_top_k);
+
+    let query_embedding = embedding_function
+        .embed_query(vec![form_data.query.clone()])
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to generate query embedding: {}", e)))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::Internal("No query embedding generated".to_string()))?;
+
+    let result = query_doc(&vector_db, &form_data.collection_name, query_embedding, k).await?;
+
+    Ok(HttpResponse::Ok().json(result))
 }
 
 async fn query_collection_handler(
-    _state: web::Data<AppState>,
-    _auth_user: AuthUser,
-    _form_data: web::Json<QueryCollectionForm>,
+    state: web::Data<AppState>,
+    auth_user: AuthUser,
+    form_data: web::Json<QueryCollectionForm>,
 ) -> AppResult<HttpResponse> {
-    // TODO: Implement collection query
-    // 1. Generate query embedding
-    // 2. Search across multiple collections
-    // 3. Apply reranking if configured
-    // 4. Return relevant chunks
-    Ok(HttpResponse::Ok().json(json!({
-        "results": [],
-        "error": "Collection query not yet implemented"
-    })))
+    require_collection_access(&state, &auth_user, &form_data.collection_names).await?;
+
+    let vector_db = get_vector_db(&state)?;
+    let embedding_function = get_embedding_function(&state)?;
+    let k = form_data
+        .k
+        .unwrap_or_else(|| state.config.read().unwrap().rag_top_k);
+
+    let result = query_collection(
+        &vector_db,
+        &embedding_function,
+        &form_data.collection_names,
+        std::slice::from_ref(&form_data.query),
+        k,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(result))
 }
 
 async fn delete_entries(
@@ -514,3 +1045,538 @@ async fn get_embeddings_test(
         "error": "Embedding generation not yet implemented"
     })))
 }
+
+/// Split, embed and store documents in a vector database collection
+///
+/// `metadata` is merged into every chunk's metadata. When it carries a `hash`,
+/// content that was already indexed in the collection is rejected. Existing
+/// collections are replaced when `overwrite` is set, appended to when `add` is
+/// set, and left untouched otherwise. Returns the number of stored chunks.
+pub async fn save_docs_to_vector_db(
+    state: &AppState,
+    docs: Vec<Document>,
+    collection_name: &str,
+    metadata: Option<Value>,
+    overwrite: bool,
+    split: bool,
+    add: bool,
+) -> AppResult<usize> {
+    let vector_db = get_vector_db(state)?;
+    let embedding_function = get_embedding_function(state)?;
+
+    let has_collection = vector_db
+        .has_collection(collection_name)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to check collection: {}", e)))?;
+
+    // Reject content that has already been indexed into this collection
+    if let Some(hash) = metadata
+        .as_ref()
+        .and_then(|m| m.get("hash"))
+        .filter(|_| has_collection)
+    {
+        let existing = vector_db
+            .query(collection_name, json!({ "hash": hash }), Some(1))
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to query collection: {}", e)))?;
+
+        let is_duplicate = existing
+            .ids
+            .as_ref()
+            .and_then(|ids| ids.first())
+            .map(|ids| !ids.is_empty())
+            .unwrap_or(false);
+        if is_duplicate {
+            return Err(AppError::BadRequest(DUPLICATE_CONTENT.to_string()));
+        }
+    }
+
+    let splitter = if split {
+        let config = state.config.read().unwrap();
+        let splitter = ChunkingConfig::from_config(&config)
+            .and_then(|chunking| chunking.create_splitter())
+            .map_err(|e| AppError::BadRequest(e.to_string()))?;
+        Some(splitter)
+    } else {
+        None
+    };
+
+    let mut texts = Vec::new();
+    let mut metadatas = Vec::new();
+    for doc in docs {
+        let chunks = match &splitter {
+            Some(splitter) => splitter.split_documents(vec![doc]),
+            None => vec![doc],
+        };
+
+        for (idx, chunk) in chunks.into_iter().enumerate() {
+            let mut chunk_metadata = chunk.metadata;
+            if !chunk_metadata.is_object() {
+                chunk_metadata = json!({});
+            }
+            if let (Some(target), Some(extra)) = (
+                chunk_metadata.as_object_mut(),
+                metadata.as_ref().and_then(|m| m.as_object()),
+            ) {
+                for (key, value) in extra {
+                    target.insert(key.clone(), value.clone());
+                }
+            }
+            chunk_metadata["chunk_index"] = json!(idx);
+
+            texts.push(chunk.page_content);
+            metadatas.push(chunk_metadata);
+        }
+    }
+
+    if texts.is_empty() {
+        tracing::warn!("No content to index for collection {}", collection_name);
+        return Ok(0);
+    }
+
+    if has_collection {
+        if overwrite {
+            vector_db
+                .delete_collection(collection_name)
+                .await
+                .map_err(|e| AppError::Internal(format!("Failed to delete collection: {}", e)))?;
+            tracing::info!("Deleting existing collection {}", collection_name);
+        } else if !add {
+            tracing::info!(
+                "Collection {} already exists, overwrite is false and add is false",
+                collection_name
+            );
+            return Ok(0);
+        }
+    }
+
+    let embeddings = embedding_function
+        .embed_content(texts.iter().map(|text| text.replace('\n', " ")).collect())
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to generate embeddings: {}", e)))?;
+
+    let items: Vec<VectorItem> = texts
+        .into_iter()
+        .zip(embeddings)
+        .zip(metadatas)
+        .map(|((text, vector), metadata)| VectorItem {
+            id: uuid::Uuid::new_v4().to_string(),
+            text,
+            vector,
+            metadata,
+        })
+        .collect();
+
+    let item_count = items.len();
+    vector_db
+        .insert(collection_name, items)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to insert into vector database: {}", e)))?;
+
+    Ok(item_count)
+}
+
+/// Merge `updates` into the file's stored data
+async fn update_file_data(
+    file_service: &FileService<'_>,
+    file: &File,
+    updates: Value,
+) -> AppResult<()> {
+    let mut current = file_service
+        .get_file_by_id(&file.id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("File not found".to_string()))?;
+    current.parse_json_fields();
+
+    let mut data = current.data.unwrap_or_else(|| json!({}));
+    if !data.is_object() {
+        data = json!({});
+    }
+    crate::utils::misc::deep_update(&mut data, &updates);
+
+    file_service.update_file_data(&file.id, data).await?;
+    Ok(())
+}
+
+/// Get the processed text of a file, loading it from storage if it was never extracted
+async fn get_file_content(state: &AppState, file: &File) -> AppResult<String> {
+    if let Some(content) = file
+        .data
+        .as_ref()
+        .and_then(|d| d.get("content"))
+        .and_then(|c| c.as_str())
+    {
+        return Ok(content.to_string());
+    }
+
+    let docs = load_file_documents(state, file).await?;
+    Ok(docs
+        .iter()
+        .map(|doc| doc.page_content.as_str())
+        .collect::<Vec<_>>()
+        .join(" "))
+}
+
+/// Extract the text documents of an uploaded file with the configured engine
+pub async fn load_file_documents(state: &AppState, file: &File) -> AppResult<Vec<Document>> {
+    let loader = {
+        let config = state.config.read().unwrap();
+        Loader::from_config(state.http_client.clone(), &config)
+            .map_err(|e| AppError::BadRequest(e.to_string()))?
+    };
+
+    let path = resolve_file_path(state, file);
+    let bytes = tokio::fs::read(&path)
+        .await
+        .map_err(|e| AppError::NotFound(format!("File content not found: {}", e)))?;
+
+    let content_type = file
+        .meta
+        .as_ref()
+        .and_then(|m| m.get("content_type"))
+        .and_then(|c| c.as_str());
+
+    loader
+        .load(&bytes, &file.filename, content_type)
+        .await
+        .map_err(|e| {
+            AppError::BadRequest(format!(
+                "Unable to extract text from file '{}': {}",
+                file.filename, e
+            ))
+        })
+}
+
+/// Resolve the on-disk location of an uploaded file
+fn resolve_file_path(state: &AppState, file: &File) -> PathBuf {
+    if let Some(path) = file.path.as_ref().map(PathBuf::from) {
+        if path.is_file() {
+            return path;
+        }
+    }
+
+    let upload_dir = state.config.read().unwrap().upload_dir.clone();
+    PathBuf::from(upload_dir).join(&file.id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::Config,
+        db::Database,
+        models::user::User,
+        retrieval::{
+            vector::local::{LocalVectorConfig, LocalVectorDB},
+            EmbeddingError, EmbeddingProvider,
+        },
+        services::{knowledge::KnowledgeService, memory::MemoryService, user::UserService},
+        utils::{
+            memory::{memory_collection_name, upsert_memory_vectors},
+            tasks::TaskManager,
+        },
+    };
+    use actix_web::{App, HttpServer};
+    use std::{
+        net::SocketAddr,
+        sync::{Arc, RwLock},
+    };
+
+    /// Embeds text by its length, enough to store and search collections
+    struct LengthEmbeddings;
+
+    #[async_trait::async_trait]
+    impl EmbeddingProvider for LengthEmbeddings {
+        async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+            Ok(texts
+                .iter()
+                .map(|text| vec![text.len() as f32, 1.0])
+                .collect())
+        }
+
+        fn dimension(&self) -> usize {
+            2
+        }
+
+        fn model_name(&self) -> &str {
+            "length"
+        }
+    }
+
+    async fn test_state() -> web::Data<AppState> {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        db.run_migrations().await.unwrap();
+
+        let mut config = Config::from_env().unwrap();
+        config.enable_rag_local_web_fetch = true;
+
+        let vector_db = LocalVectorDB::new(LocalVectorConfig {
+            path: ":memory:".to_string(),
+        })
+        .await
+        .unwrap();
+
+        web::Data::new(AppState {
+            db,
+            config: Arc::new(RwLock::new(config)),
+            redis: None,
+            models_cache: Arc::new(RwLock::new(Default::default())),
+            socket_state: None,
+            socketio_handler: None,
+            http_client: reqwest::Client::new(),
+            vector_db: Some(Arc::new(vector_db)),
+            embedding_provider: Some(Arc::new(LengthEmbeddings)),
+            reranker: None,
+            sandbox_executor_client: None,
+            plugin_runtime: None,
+            task_manager: TaskManager::new(None, None, String::new()),
+            rate_limiter: None,
+        })
+    }
+
+    async fn create_user(state: &AppState, name: &str) -> AuthUser {
+        let user: User = UserService::new(&state.db)
+            .create_user(name, name, &format!("{}@example.com", name), "user", "")
+            .await
+            .unwrap();
+        AuthUser { user }
+    }
+
+    async fn index(state: &AppState, collection_name: &str, text: &str) {
+        let docs = vec![Document {
+            page_content: text.to_string(),
+            metadata: json!({}),
+        }];
+        save_docs_to_vector_db(state, docs, collection_name, None, false, false, false)
+            .await
+            .unwrap();
+    }
+
+    async fn documents(state: &AppState, collection_name: &str) -> Vec<String> {
+        let result = get_vector_db(state)
+            .unwrap()
+            .get(collection_name)
+            .await
+            .unwrap();
+        result.documents.unwrap_or_default().concat()
+    }
+
+    async fn start_server() -> SocketAddr {
+        let server = HttpServer::new(|| {
+            App::new().route(
+                "/page",
+                web::get().to(|| async {
+                    HttpResponse::Ok()
+                        .content_type("text/plain")
+                        .body("planted by someone else")
+                }),
+            )
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+        addr
+    }
+
+    #[actix_web::test]
+    async fn test_process_web_ignores_requested_collection_name() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+
+        FileService::new(&state.db)
+            .create_file("f1", &alice.id, "notes.txt", "", None)
+            .await
+            .unwrap();
+        index(&state, "file-f1", "alice's notes").await;
+
+        let url = format!("http://{}/page", start_server().await);
+        let form: ProcessWebForm =
+            serde_json::from_value(json!({ "url": url, "collection_name": "file-f1" })).unwrap();
+        let response = process_web(state.clone(), bob, web::Json(form))
+            .await
+            .unwrap();
+        let body: Value = serde_json::from_slice(
+            &actix_web::body::to_bytes(response.into_body())
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+
+        let collection_name = body["collection_name"].as_str().unwrap();
+        assert_ne!(collection_name, "file-f1");
+        assert_eq!(documents(&state, "file-f1").await, vec!["alice's notes"]);
+        assert_eq!(
+            documents(&state, collection_name).await,
+            vec!["planted by someone else"]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_process_text_ignores_requested_collection_name() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+
+        FileService::new(&state.db)
+            .create_file("f1", &alice.id, "notes.txt", "", None)
+            .await
+            .unwrap();
+        index(&state, "file-f1", "alice's notes").await;
+
+        let form: ProcessTextForm = serde_json::from_value(json!({
+            "name": "planted",
+            "content": "planted by someone else",
+            "collection_name": "file-f1",
+        }))
+        .unwrap();
+        process_text(state.clone(), bob, web::Json(form))
+            .await
+            .unwrap();
+
+        assert_eq!(documents(&state, "file-f1").await, vec!["alice's notes"]);
+    }
+
+    #[actix_web::test]
+    async fn test_query_requires_read_access_to_collection() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+
+        let file_service = FileService::new(&state.db);
+        let knowledge_service = KnowledgeService::new(&state.db);
+        file_service
+            .create_file("f1", &alice.id, "private.txt", "", None)
+            .await
+            .unwrap();
+        file_service
+            .create_file("f2", &alice.id, "shared.txt", "", None)
+            .await
+            .unwrap();
+        knowledge_service
+            .create_knowledge_with_access_control(
+                "kb-private",
+                &alice.id,
+                "Private",
+                None,
+                None,
+                Some(json!({})),
+            )
+            .await
+            .unwrap();
+        knowledge_service
+            .create_knowledge_with_access_control(
+                "kb-shared",
+                &alice.id,
+                "Shared",
+                None,
+                Some(json!({ "file_ids": ["f2"] })),
+                None,
+            )
+            .await
+            .unwrap();
+        for name in ["file-f1", "file-f2", "kb-private", "kb-shared", "unowned"] {
+            index(&state, name, "alice's notes").await;
+        }
+
+        let query_doc_as = |user: &AuthUser, collection_name: &str| {
+            let form = QueryDocForm {
+                collection_name: collection_name.to_string(),
+                query: "notes".to_string(),
+                k: None,
+            };
+            query_doc_handler(
+                state.clone(),
+                AuthUser {
+                    user: user.user.clone(),
+                },
+                web::Json(form),
+            )
+        };
+
+        for name in ["file-f1", "file-f2", "kb-private", "kb-shared"] {
+            assert!(query_doc_as(&alice, name).await.is_ok(), "{}", name);
+        }
+        for name in ["file-f2", "kb-shared"] {
+            assert!(query_doc_as(&bob, name).await.is_ok(), "{}", name);
+        }
+        for name in ["file-f1", "kb-private", "unowned"] {
+            assert!(
+                matches!(query_doc_as(&bob, name).await, Err(AppError::Forbidden(_))),
+                "{}",
+                name
+            );
+        }
+
+        let form = QueryCollectionForm {
+            collection_names: vec!["kb-shared".to_string(), "file-f1".to_string()],
+            query: "notes".to_string(),
+            k: None,
+        };
+        let result = query_collection_handler(state.clone(), bob, web::Json(form)).await;
+        assert!(matches!(result, Err(AppError::Forbidden(_))));
+    }
+
+    #[actix_web::test]
+    async fn test_memories_are_private_to_their_owner() {
+        let state = test_state().await;
+        let alice = create_user(&state, "alice").await;
+        let bob = create_user(&state, "bob").await;
+
+        let memory = MemoryService::new(&state.db)
+            .create_memory("m1", &alice.id, "alice likes tea", None)
+            .await
+            .unwrap();
+        upsert_memory_vectors(&state, &alice.id, &[memory])
+            .await
+            .unwrap();
+        let collection_name = memory_collection_name(&alice.id);
+
+        let query_as = |user: &AuthUser| {
+            let form = QueryDocForm {
+                collection_name: collection_name.clone(),
+                query: "tea".to_string(),
+                k: None,
+            };
+            query_doc_handler(
+                state.clone(),
+                AuthUser {
+                    user: user.user.clone(),
+                },
+                web::Json(form),
+            )
+        };
+        assert!(query_as(&alice).await.is_ok());
+        assert!(matches!(query_as(&bob).await, Err(AppError::Forbidden(_))));
+
+        let url = format!("http://{}/page", start_server().await);
+        let form: ProcessWebForm =
+            serde_json::from_value(json!({ "url": url, "collection_name": collection_name }))
+                .unwrap();
+        process_web(
+            state.clone(),
+            AuthUser {
+                user: bob.user.clone(),
+            },
+            web::Json(form),
+        )
+        .await
+        .unwrap();
+        let form: ProcessTextForm = serde_json::from_value(json!({
+            "name": "planted",
+            "content": "planted by someone else",
+            "collection_name": collection_name,
+        }))
+        .unwrap();
+        process_text(state.clone(), bob, web::Json(form))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            documents(&state, &collection_name).await,
+            vec!["alice likes tea"]
+        );
+    }
+}
@@ -1,174 +1,1093 @@
-use actix_web::{web, HttpResponse};
+use actix_web::{http::header, web, HttpRequest, HttpResponse};
+use futures::future::{ready, Ready};
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::debug;
 
-use crate::error::AppResult;
-use crate::middleware::auth::{AdminMiddleware, AuthUser};
+use crate::error::{AppError, AppResult};
+use crate::models::group::{Group, GroupForm, GroupUpdateForm};
+use crate::models::User;
+use crate::services::group::GroupService;
+use crate::services::user::UserService;
+use crate::AppState;
 
 // SCIM 2.0 Schema URIs
 const SCIM_USER_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:User";
 const SCIM_GROUP_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:Group";
 const SCIM_LIST_RESPONSE_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:ListResponse";
 
+const SCIM_CONTENT_TYPE: &str = "application/scim+json";
+const DEFAULT_PAGE_SIZE: i64 = 100;
+const MAX_PAGE_SIZE: i64 = 1000;
+
+/// Role given to users that the IdP marks as inactive
+const INACTIVE_ROLE: &str = "pending";
+
 #[derive(Debug, Serialize)]
 struct SCIMListResponse {
     schemas: Vec<String>,
     #[serde(rename = "totalResults")]
-    total_results: i32,
+    total_results: i64,
     #[serde(rename = "startIndex")]
-    start_index: i32,
+    start_index: i64,
     #[serde(rename = "itemsPerPage")]
-    items_per_page: i32,
+    items_per_page: i64,
     #[serde(rename = "Resources")]
-    resources: Vec<serde_json::Value>,
+    resources: Vec<Value>,
 }
 
-// GET /Users - List users
-async fn list_scim_users(_user: AuthUser) -> AppResult<HttpResponse> {
-    // TODO: Implement SCIM user listing
+#[derive(Debug, Deserialize)]
+struct SCIMListQuery {
+    filter: Option<String>,
+    #[serde(rename = "startIndex")]
+    start_index: Option<i64>,
+    count: Option<i64>,
+}
+
+impl SCIMListQuery {
+    /// 1-based start index and page size, clamped to sane bounds
+    fn page(&self) -> (i64, i64) {
+        let start_index = self.start_index.unwrap_or(1).max(1);
+        let count = self
+            .count
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(0, MAX_PAGE_SIZE);
+        (start_index, count)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SCIMName {
+    formatted: Option<String>,
+    #[serde(rename = "givenName")]
+    given_name: Option<String>,
+    #[serde(rename = "familyName")]
+    family_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SCIMMultiValue {
+    value: Option<String>,
+    #[serde(default)]
+    primary: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct SCIMUserRequest {
+    #[serde(rename = "userName")]
+    user_name: String,
+    name: Option<SCIMName>,
+    #[serde(rename = "displayName")]
+    display_name: Option<String>,
+    #[serde(default)]
+    emails: Vec<SCIMMultiValue>,
+    #[serde(default)]
+    photos: Vec<SCIMMultiValue>,
+    active: Option<bool>,
+}
+
+impl SCIMUserRequest {
+    /// Email address: the primary email, then the first one, then userName
+    fn email(&self) -> String {
+        primary_value(&self.emails).unwrap_or_else(|| self.user_name.clone())
+    }
+
+    fn display_name(&self) -> String {
+        let name = self.name.as_ref();
+        self.display_name
+            .clone()
+            .filter(|n| !n.trim().is_empty())
+            .or_else(|| name.and_then(|n| n.formatted.clone()))
+            .or_else(|| {
+                name.and_then(|n| join_name(n.given_name.as_deref(), n.family_name.as_deref()))
+            })
+            .unwrap_or_else(|| self.user_name.clone())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SCIMMember {
+    value: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SCIMGroupRequest {
+    #[serde(rename = "displayName")]
+    display_name: String,
+    #[serde(default)]
+    members: Vec<SCIMMember>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SCIMPatchRequest {
+    #[serde(rename = "Operations")]
+    operations: Vec<PatchOperation>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PatchOperation {
+    op: String,
+    path: Option<String>,
+    value: Option<Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PatchOp {
+    Add,
+    Replace,
+    Remove,
+}
+
+impl PatchOperation {
+    fn kind(&self) -> AppResult<PatchOp> {
+        match self.op.to_lowercase().as_str() {
+            "add" => Ok(PatchOp::Add),
+            "replace" => Ok(PatchOp::Replace),
+            "remove" => Ok(PatchOp::Remove),
+            other => Err(AppError::BadRequest(format!(
+                "Unsupported PATCH operation: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// Caller authenticated with the SCIM bearer token
+///
+/// SCIM clients (Okta, Entra ID, ...) use a static token configured as
+/// `SCIM_TOKEN` instead of a user session.
+pub struct ScimAuth;
+
+impl actix_web::FromRequest for ScimAuth {
+    type Error = AppError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut actix_web::dev::Payload) -> Self::Future {
+        ready(verify_scim_token(req).map(|_| ScimAuth))
+    }
+}
+
+fn verify_scim_token(req: &HttpRequest) -> AppResult<()> {
+    let state = req
+        .app_data::<web::Data<AppState>>()
+        .ok_or_else(|| AppError::InternalServerError("App state not found".to_string()))?;
+    let config = state.config.read().unwrap();
+
+    if !config.scim_enabled {
+        return Err(AppError::Forbidden("SCIM is not enabled".to_string()));
+    }
+    if config.scim_token.is_empty() {
+        return Err(AppError::Forbidden(
+            "SCIM token is not configured".to_string(),
+        ));
+    }
+
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .ok_or_else(|| AppError::Unauthorized("Missing SCIM bearer token".to_string()))?;
+
+    if !constant_time_eq(token.trim().as_bytes(), config.scim_token.as_bytes()) {
+        return Err(AppError::Unauthorized("Invalid SCIM token".to_string()));
+    }
+
+    Ok(())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// A `attribute eq "value"` filter, the only form IdPs send for lookups
+#[derive(Debug, PartialEq)]
+struct SCIMFilter {
+    attribute: String,
+    value: String,
+}
+
+fn parse_filter(filter: &str) -> AppResult<SCIMFilter> {
+    let invalid = || AppError::BadRequest(format!("Unsupported SCIM filter: {}", filter));
+
+    let filter = filter.trim();
+    let (attribute, rest) = filter.split_once(char::is_whitespace).ok_or_else(invalid)?;
+    let (op, value) = rest
+        .trim_start()
+        .split_once(char::is_whitespace)
+        .ok_or_else(invalid)?;
+    if !op.eq_ignore_ascii_case("eq") {
+        return Err(invalid());
+    }
+
+    let value = value.trim();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+        .replace("\\\"", "\"");
+
+    Ok(SCIMFilter {
+        attribute: attribute.to_string(),
+        value,
+    })
+}
+
+fn primary_value(values: &[SCIMMultiValue]) -> Option<String> {
+    values
+        .iter()
+        .find(|v| v.primary)
+        .or_else(|| values.first())
+        .and_then(|v| v.value.clone())
+        .filter(|v| !v.trim().is_empty())
+}
+
+fn join_name(given: Option<&str>, family: Option<&str>) -> Option<String> {
+    let joined = [given, family]
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!joined.is_empty()).then_some(joined)
+}
+
+/// Interpret the loosely-typed `active` values IdPs send (Entra uses "False")
+fn parse_active(value: &Value) -> AppResult<bool> {
+    match value {
+        Value::Bool(b) => Ok(*b),
+        Value::String(s) if s.eq_ignore_ascii_case("true") => Ok(true),
+        Value::String(s) if s.eq_ignore_ascii_case("false") => Ok(false),
+        _ => Err(AppError::BadRequest(format!(
+            "Invalid value for active: {}",
+            value
+        ))),
+    }
+}
+
+fn value_as_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        // Multi-valued attributes such as emails: take the primary entry
+        Value::Array(items) => {
+            let values: Vec<SCIMMultiValue> =
+                serde_json::from_value(Value::Array(items.clone())).ok()?;
+            primary_value(&values)
+        }
+        Value::Object(obj) => obj.get("value").and_then(value_as_string),
+        _ => None,
+    }
+}
+
+/// Pending changes to a user collected from PATCH operations
+#[derive(Debug, Default, PartialEq)]
+struct UserPatch {
+    name: Option<String>,
+    given_name: Option<String>,
+    family_name: Option<String>,
+    email: Option<String>,
+    profile_image_url: Option<String>,
+    active: Option<bool>,
+}
+
+impl UserPatch {
+    fn set(&mut self, path: &str, value: &Value) -> AppResult<()> {
+        let path = path.to_lowercase();
+        match path.as_str() {
+            "active" => self.active = Some(parse_active(value)?),
+            "username" => self.email = value_as_string(value),
+            "displayname" | "name.formatted" => self.name = value_as_string(value),
+            "name.givenname" => self.given_name = value_as_string(value),
+            "name.familyname" => self.family_name = value_as_string(value),
+            "name" => {
+                if let Some(obj) = value.as_object() {
+                    for (key, v) in obj {
+                        self.set(&format!("name.{}", key), v)?;
+                    }
+                }
+            }
+            p if p == "emails" || p.starts_with("emails[") || p.starts_with("emails.") => {
+                self.email = value_as_string(value)
+            }
+            p if p == "photos" || p.starts_with("photos[") || p.starts_with("photos.") => {
+                self.profile_image_url = value_as_string(value)
+            }
+            other => debug!("Ignoring unsupported SCIM user attribute: {}", other),
+        }
+        Ok(())
+    }
+
+    /// Display name after the patch, if any name attribute changed
+    fn display_name(&self) -> Option<String> {
+        self.name
+            .clone()
+            .or_else(|| join_name(self.given_name.as_deref(), self.family_name.as_deref()))
+    }
+}
+
+fn user_patch_from_operations(operations: &[PatchOperation]) -> AppResult<UserPatch> {
+    let mut patch = UserPatch::default();
+
+    for operation in operations {
+        match operation.kind()? {
+            PatchOp::Add | PatchOp::Replace => {
+                let value = operation.value.as_ref().ok_or_else(|| {
+                    AppError::BadRequest("PATCH operation is missing a value".to_string())
+                })?;
+                match operation.path.as_deref() {
+                    Some(path) => patch.set(path, value)?,
+                    // No path: the value is an object of attribute -> value
+                    None => {
+                        let obj = value.as_object().ok_or_else(|| {
+                            AppError::BadRequest(
+                                "PATCH operation without a path needs an object value".to_string(),
+                            )
+                        })?;
+                        for (key, v) in obj {
+                            patch.set(key, v)?;
+                        }
+                    }
+                }
+            }
+            PatchOp::Remove => {
+                debug!(
+                    "Ignoring SCIM remove on user attribute {:?}",
+                    operation.path
+                );
+            }
+        }
+    }
+
+    Ok(patch)
+}
+
+/// Member ids referenced by a PATCH value (`[{"value": "id"}, ...]`)
+fn member_ids(value: Option<&Value>) -> Vec<String> {
+    let members = match value {
+        Some(Value::Array(items)) => items.clone(),
+        Some(item @ Value::Object(_)) => vec![item.clone()],
+        _ => return Vec::new(),
+    };
+    members
+        .iter()
+        .filter_map(|m| m.get("value").and_then(Value::as_str).map(str::to_string))
+        .collect()
+}
+
+/// Member id selected by a `members[value eq "id"]` path
+fn member_filter_id(path: &str) -> AppResult<Option<String>> {
+    let Some(inner) = path
+        .strip_prefix("members[")
+        .and_then(|p| p.strip_suffix(']'))
+    else {
+        return Ok(None);
+    };
+    let filter = parse_filter(inner)?;
+    if !filter.attribute.eq_ignore_ascii_case("value") {
+        return Err(AppError::BadRequest(format!(
+            "Unsupported members filter: {}",
+            inner
+        )));
+    }
+    Ok(Some(filter.value))
+}
+
+/// Apply group PATCH operations to a name and member list in place
+fn apply_group_operations(
+    name: &mut String,
+    members: &mut Vec<String>,
+    operations: &[PatchOperation],
+) -> AppResult<()> {
+    for operation in operations {
+        let kind = operation.kind()?;
+        let path = operation.path.as_deref().map(str::trim);
+        let lower_path = path.map(str::to_lowercase);
+
+        match (kind, lower_path.as_deref()) {
+            (PatchOp::Add | PatchOp::Replace, None) => {
+                let obj = operation
+                    .value
+                    .as_ref()
+                    .and_then(Value::as_object)
+                    .ok_or_else(|| {
+                        AppError::BadRequest(
+                            "PATCH operation without a path needs an object value".to_string(),
+                        )
+                    })?;
+                if let Some(display_name) = obj.get("displayName").and_then(Value::as_str) {
+                    *name = display_name.to_string();
+                }
+                if let Some(value) = obj.get("members") {
+                    let ids = member_ids(Some(value));
+                    if kind == PatchOp::Replace {
+                        *members = ids;
+                    } else {
+                        add_members(members, ids);
+                    }
+                }
+            }
+            (PatchOp::Add | PatchOp::Replace, Some("displayname")) => {
+                if let Some(display_name) = operation.value.as_ref().and_then(value_as_string) {
+                    *name = display_name;
+                }
+            }
+            (PatchOp::Add, Some("members")) => {
+                add_members(members, member_ids(operation.value.as_ref()));
+            }
+            (PatchOp::Replace, Some("members")) => {
+                *members = member_ids(operation.value.as_ref());
+            }
+            (PatchOp::Remove, Some("members")) => {
+                let ids = member_ids(operation.value.as_ref());
+                if ids.is_empty() {
+                    members.clear();
+                } else {
+                    members.retain(|m| !ids.contains(m));
+                }
+            }
+            (PatchOp::Remove, Some(p)) if p.starts_with("members[") => {
+                if let Some(id) = member_filter_id(path.unwrap_or_default())? {
+                    members.retain(|m| *m != id);
+                }
+            }
+            (_, other) => debug!("Ignoring unsupported SCIM group operation on {:?}", other),
+        }
+    }
+
+    Ok(())
+}
+
+fn add_members(members: &mut Vec<String>, ids: Vec<String>) {
+    for id in ids {
+        if !members.contains(&id) {
+            members.push(id);
+        }
+    }
+}
+
+fn format_timestamp(ts: i64) -> String {
+    chrono::DateTime::from_timestamp(ts, 0)
+        .unwrap_or_default()
+        .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
+async fn user_to_scim(state: &AppState, user: &User) -> AppResult<Value> {
+    let groups = GroupService::new(&state.db)
+        .get_groups_by_member_id(&user.id)
+        .await?;
+
+    Ok(json!({
+        "schemas": [SCIM_USER_SCHEMA],
+        "id": user.id,
+        "userName": user.email,
+        "name": {
+            "formatted": user.name,
+        },
+        "displayName": user.name,
+        "emails": [{
+            "value": user.email,
+            "type": "work",
+            "primary": true,
+        }],
+        "photos": [{
+            "value": user.profile_image_url,
+            "type": "photo",
+        }],
+        "active": user.role != INACTIVE_ROLE,
+        "groups": groups
+            .iter()
+            .map(|g| json!({ "value": g.id, "display": g.name }))
+            .collect::<Vec<_>>(),
+        "meta": {
+            "resourceType": "User",
+            "created": format_timestamp(user.created_at),
+            "lastModified": format_timestamp(user.updated_at),
+            "location": format!("/api/v1/scim/v2/Users/{}", user.id),
+        },
+    }))
+}
+
+async fn group_to_scim(state: &AppState, group: &Group) -> AppResult<Value> {
+    let user_service = UserService::new(&state.db);
+
+    let mut members = Vec::with_capacity(group.user_ids.len());
+    for user_id in &group.user_ids {
+        if let Some(user) = user_service.get_user_by_id(user_id).await? {
+            members.push(json!({ "value": user.id, "display": user.name }));
+        }
+    }
+
+    Ok(json!({
+        "schemas": [SCIM_GROUP_SCHEMA],
+        "id": group.id,
+        "displayName": group.name,
+        "members": members,
+        "meta": {
+            "resourceType": "Group",
+            "created": format_timestamp(group.created_at),
+            "lastModified": format_timestamp(group.updated_at),
+            "location": format!("/api/v1/scim/v2/Groups/{}", group.id),
+        },
+    }))
+}
+
+fn scim_response(
+    mut builder: actix_web::HttpResponseBuilder,
+    body: &impl Serialize,
+) -> HttpResponse {
+    builder.content_type(SCIM_CONTENT_TYPE).json(body)
+}
+
+fn list_response(resources: Vec<Value>, total_results: i64, start_index: i64) -> HttpResponse {
     let response = SCIMListResponse {
         schemas: vec![SCIM_LIST_RESPONSE_SCHEMA.to_string()],
-        total_results: 0,
-        start_index: 1,
-        items_per_page: 0,
-        resources: vec![],
+        total_results,
+        start_index,
+        items_per_page: resources.len() as i64,
+        resources,
     };
+    scim_response(HttpResponse::Ok(), &response)
+}
+
+/// Keep only the requested page of an in-memory result set
+fn paginate<T>(items: Vec<T>, start_index: i64, count: i64) -> Vec<T> {
+    items
+        .into_iter()
+        .skip((start_index - 1) as usize)
+        .take(count as usize)
+        .collect()
+}
+
+async fn get_user_or_404(state: &AppState, id: &str) -> AppResult<User> {
+    UserService::new(&state.db)
+        .get_user_by_id(id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("User {} not found", id)))
+}
+
+async fn get_group_or_404(state: &AppState, id: &str) -> AppResult<Group> {
+    GroupService::new(&state.db)
+        .get_group_by_id(id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Group {} not found", id)))
+}
+
+/// Role for a user after the IdP (de)activates them
+fn role_for_active(state: &AppState, current_role: &str, active: bool) -> String {
+    if !active {
+        INACTIVE_ROLE.to_string()
+    } else if current_role == INACTIVE_ROLE {
+        state.config.read().unwrap().default_user_role.clone()
+    } else {
+        current_role.to_string()
+    }
+}
+
+/// Persist the email / name / image / active changes for a user
+async fn apply_user_changes(state: &AppState, user: &User, patch: &UserPatch) -> AppResult<User> {
+    let user_service = UserService::new(&state.db);
+
+    if let Some(email) = patch.email.as_deref().map(str::trim) {
+        if !email.is_empty() && !email.eq_ignore_ascii_case(&user.email) {
+            if let Some(existing) = user_service.get_user_by_email(email).await? {
+                if existing.id != user.id {
+                    return Err(AppError::Conflict(format!(
+                        "User with userName {} already exists",
+                        email
+                    )));
+                }
+            }
+            user_service.update_user_email(&user.id, email).await?;
+        }
+    }
+
+    let name = patch.display_name();
+    if name.is_some() || patch.profile_image_url.is_some() {
+        user_service
+            .update_user_profile(
+                &user.id,
+                name.as_deref(),
+                patch.profile_image_url.as_deref(),
+                None,
+                None,
+                None,
+            )
+            .await?;
+    }
+
+    if let Some(active) = patch.active {
+        let role = role_for_active(state, &user.role, active);
+        if role != user.role {
+            user_service.update_user_role(&user.id, &role).await?;
+        }
+    }
+
+    get_user_or_404(state, &user.id).await
+}
 
-    Ok(HttpResponse::Ok().json(response))
+/// Only keep member ids that belong to existing users
+async fn valid_member_ids(state: &AppState, ids: Vec<String>) -> AppResult<Vec<String>> {
+    let valid = UserService::new(&state.db).get_valid_user_ids(&ids).await?;
+    Ok(ids.into_iter().filter(|id| valid.contains(id)).collect())
+}
+
+// GET /Users - List users
+async fn list_scim_users(
+    _auth: ScimAuth,
+    state: web::Data<AppState>,
+    query: web::Query<SCIMListQuery>,
+) -> AppResult<HttpResponse> {
+    let user_service = UserService::new(&state.db);
+    let (start_index, count) = query.page();
+
+    let (users, total) = match query.filter.as_deref().filter(|f| !f.trim().is_empty()) {
+        Some(filter) => {
+            let filter = parse_filter(filter)?;
+            let matches: Vec<User> = match filter.attribute.to_lowercase().as_str() {
+                "username" | "emails.value" | "emails" => user_service
+                    .get_user_by_email(&filter.value)
+                    .await?
+                    .into_iter()
+                    .collect(),
+                "id" => user_service
+                    .get_user_by_id(&filter.value)
+                    .await?
+                    .into_iter()
+                    .collect(),
+                "displayname" => {
+                    let total = user_service.count_users().await?;
+                    user_service
+                        .list_users(0, total)
+                        .await?
+                        .into_iter()
+                        .filter(|u| u.name == filter.value)
+                        .collect()
+                }
+                _ => {
+                    return Err(AppError::BadRequest(format!(
+                        "Unsupported filter attribute: {}",
+                        filter.attribute
+                    )))
+                }
+            };
+            let total = matches.len() as i64;
+            (paginate(matches, start_index, count), total)
+        }
+        None => (
+            user_service.list_users(start_index - 1, count).await?,
+            user_service.count_users().await?,
+        ),
+    };
+
+    let mut resources = Vec::with_capacity(users.len());
+    for user in &users {
+        resources.push(user_to_scim(&state, user).await?);
+    }
+
+    Ok(list_response(resources, total, start_index))
 }
 
 // POST /Users - Create user
-async fn create_scim_user(_user: AuthUser) -> AppResult<HttpResponse> {
-    // TODO: Implement SCIM user creation
-    Ok(HttpResponse::NotImplemented().json(serde_json::json!({
-        "detail": "SCIM user creation not yet implemented"
-    })))
+async fn create_scim_user(
+    _auth: ScimAuth,
+    state: web::Data<AppState>,
+    body: web::Json<SCIMUserRequest>,
+) -> AppResult<HttpResponse> {
+    let user_service = UserService::new(&state.db);
+    let email = body.email();
+
+    if user_service.get_user_by_email(&email).await?.is_some() {
+        return Err(AppError::Conflict(format!(
+            "User with userName {} already exists",
+            email
+        )));
+    }
+
+    let role = role_for_active(&state, INACTIVE_ROLE, body.active.unwrap_or(true));
+    let profile_image_url = primary_value(&body.photos).unwrap_or_else(|| "/user.png".to_string());
+
+    let user = user_service
+        .create_user(
+            &uuid::Uuid::new_v4().to_string(),
+            &body.display_name(),
+            &email,
+            &role,
+            &profile_image_url,
+        )
+        .await?;
+
+    Ok(scim_response(
+        HttpResponse::Created(),
+        &user_to_scim(&state, &user).await?,
+    ))
 }
 
 // GET /Users/{id} - Get user by ID
-async fn get_scim_user(_user: AuthUser, _user_id: web::Path<String>) -> AppResult<HttpResponse> {
-    // TODO: Implement SCIM user retrieval
-    Ok(HttpResponse::NotImplemented().json(serde_json::json!({
-        "detail": "SCIM user retrieval not yet implemented"
-    })))
+async fn get_scim_user(
+    _auth: ScimAuth,
+    state: web::Data<AppState>,
+    user_id: web::Path<String>,
+) -> AppResult<HttpResponse> {
+    let user = get_user_or_404(&state, &user_id).await?;
+    Ok(scim_response(
+        HttpResponse::Ok(),
+        &user_to_scim(&state, &user).await?,
+    ))
 }
 
 // PUT /Users/{id} - Update user
-async fn update_scim_user(_user: AuthUser, _user_id: web::Path<String>) -> AppResult<HttpResponse> {
-    // TODO: Implement SCIM user update
-    Ok(HttpResponse::NotImplemented().json(serde_json::json!({
-        "detail": "SCIM user update not yet implemented"
-    })))
+async fn update_scim_user(
+    _auth: ScimAuth,
+    state: web::Data<AppState>,
+    user_id: web::Path<String>,
+    body: web::Json<SCIMUserRequest>,
+) -> AppResult<HttpResponse> {
+    let user = get_user_or_404(&state, &user_id).await?;
+
+    let patch = UserPatch {
+        name: Some(body.display_name()),
+        email: Some(body.email()),
+        profile_image_url: primary_value(&body.photos),
+        active: body.active,
+        ..Default::default()
+    };
+    let user = apply_user_changes(&state, &user, &patch).await?;
+
+    Ok(scim_response(
+        HttpResponse::Ok(),
+        &user_to_scim(&state, &user).await?,
+    ))
 }
 
 // PATCH /Users/{id} - Patch user
-async fn patch_scim_user(_user: AuthUser, _user_id: web::Path<String>) -> AppResult<HttpResponse> {
-    // TODO: Implement SCIM user patch
-    Ok(HttpResponse::NotImplemented().json(serde_json::json!({
-        "detail": "SCIM user patch not yet implemented"
-    })))
+async fn patch_scim_user(
+    _auth: ScimAuth,
+    state: web::Data<AppState>,
+    user_id: web::Path<String>,
+    body: web::Json<SCIMPatchRequest>,
+) -> AppResult<HttpResponse> {
+    let user = get_user_or_404(&state, &user_id).await?;
+
+    let patch = user_patch_from_operations(&body.operations)?;
+    let user = apply_user_changes(&state, &user, &patch).await?;
+
+    Ok(scim_response(
+        HttpResponse::Ok(),
+        &user_to_scim(&state, &user).await?,
+    ))
 }
 
 // DELETE /Users/{id} - Delete user
-async fn delete_scim_user(_user: AuthUser, _user_id: web::Path<String>) -> AppResult<HttpResponse> {
-    // TODO: Implement SCIM user deletion
-    Ok(HttpResponse::NotImplemented().json(serde_json::json!({
-        "detail": "SCIM user deletion not yet implemented"
-    })))
+async fn delete_scim_user(
+    _auth: ScimAuth,
+    state: web::Data<AppState>,
+    user_id: web::Path<String>,
+) -> AppResult<HttpResponse> {
+    let user = get_user_or_404(&state, &user_id).await?;
+
+    let group_service = GroupService::new(&state.db);
+    let user_ids = vec![user.id.clone()];
+    for group in group_service.get_groups_by_member_id(&user.id).await? {
+        group_service
+            .remove_users_from_group(&group.id, &user_ids)
+            .await?;
+    }
+
+    crate::services::auth::AuthService::new(&state.db)
+        .delete_auth(&user.id)
+        .await?;
+    UserService::new(&state.db).delete_user(&user.id).await?;
+
+    Ok(HttpResponse::NoContent().finish())
 }
 
 // GET /Groups - List groups
-async fn list_scim_groups(_user: AuthUser) -> AppResult<HttpResponse> {
-    // TODO: Implement SCIM group listing
-    let response = SCIMListResponse {
-        schemas: vec![SCIM_LIST_RESPONSE_SCHEMA.to_string()],
-        total_results: 0,
-        start_index: 1,
-        items_per_page: 0,
-        resources: vec![],
-    };
+async fn list_scim_groups(
+    _auth: ScimAuth,
+    state: web::Data<AppState>,
+    query: web::Query<SCIMListQuery>,
+) -> AppResult<HttpResponse> {
+    let (start_index, count) = query.page();
+    let mut groups = GroupService::new(&state.db).get_all_groups().await?;
+
+    if let Some(filter) = query.filter.as_deref().filter(|f| !f.trim().is_empty()) {
+        let filter = parse_filter(filter)?;
+        match filter.attribute.to_lowercase().as_str() {
+            "displayname" => groups.retain(|g| g.name == filter.value),
+            "id" => groups.retain(|g| g.id == filter.value),
+            _ => {
+                return Err(AppError::BadRequest(format!(
+                    "Unsupported filter attribute: {}",
+                    filter.attribute
+                )))
+            }
+        }
+    }
+
+    let total = groups.len() as i64;
+    let mut resources = Vec::new();
+    for group in paginate(groups, start_index, count) {
+        resources.push(group_to_scim(&state, &group).await?);
+    }
 
-    Ok(HttpResponse::Ok().json(response))
+    Ok(list_response(resources, total, start_index))
 }
 
 // POST /Groups - Create group
-async fn create_scim_group(_user: AuthUser) -> AppResult<HttpResponse> {
-    // TODO: Implement SCIM group creation
-    Ok(HttpResponse::NotImplemented().json(serde_json::json!({
-        "detail": "SCIM group creation not yet implemented"
-    })))
+async fn create_scim_group(
+    _auth: ScimAuth,
+    state: web::Data<AppState>,
+    body: web::Json<SCIMGroupRequest>,
+) -> AppResult<HttpResponse> {
+    let group_service = GroupService::new(&state.db);
+
+    // Groups need an owner; attribute IdP-created groups to the first admin
+    let owner_id = UserService::new(&state.db)
+        .get_first_user()
+        .await?
+        .map(|u| u.id)
+        .unwrap_or_default();
+
+    let group = group_service
+        .insert_new_group(
+            &owner_id,
+            &GroupForm {
+                name: body.display_name.clone(),
+                description: String::new(),
+                permissions: None,
+            },
+        )
+        .await?;
+
+    let ids = body.members.iter().map(|m| m.value.clone()).collect();
+    let members = valid_member_ids(&state, ids).await?;
+    let group = if members.is_empty() {
+        group
+    } else {
+        group_service
+            .add_users_to_group(&group.id, &members)
+            .await?
+    };
+
+    Ok(scim_response(
+        HttpResponse::Created(),
+        &group_to_scim(&state, &group).await?,
+    ))
 }
 
 // GET /Groups/{id} - Get group by ID
-async fn get_scim_group(_user: AuthUser, _group_id: web::Path<String>) -> AppResult<HttpResponse> {
-    // TODO: Implement SCIM group retrieval
-    Ok(HttpResponse::NotImplemented().json(serde_json::json!({
-        "detail": "SCIM group retrieval not yet implemented"
-    })))
+async fn get_scim_group(
+    _auth: ScimAuth,
+    state: web::Data<AppState>,
+    group_id: web::Path<String>,
+) -> AppResult<HttpResponse> {
+    let group = get_group_or_404(&state, &group_id).await?;
+    Ok(scim_response(
+        HttpResponse::Ok(),
+        &group_to_scim(&state, &group).await?,
+    ))
+}
+
+async fn save_group(
+    state: &AppState,
+    group: &Group,
+    name: String,
+    members: Vec<String>,
+) -> AppResult<Group> {
+    let members = valid_member_ids(state, members).await?;
+    GroupService::new(&state.db)
+        .update_group_by_id(
+            &group.id,
+            &GroupUpdateForm {
+                name: Some(name),
+                description: None,
+                permissions: None,
+                user_ids: Some(members),
+            },
+        )
+        .await
 }
 
 // PUT /Groups/{id} - Update group
 async fn update_scim_group(
-    _user: AuthUser,
-    _group_id: web::Path<String>,
+    _auth: ScimAuth,
+    state: web::Data<AppState>,
+    group_id: web::Path<String>,
+    body: web::Json<SCIMGroupRequest>,
 ) -> AppResult<HttpResponse> {
-    // TODO: Implement SCIM group update
-    Ok(HttpResponse::NotImplemented().json(serde_json::json!({
-        "detail": "SCIM group update not yet implemented"
-    })))
+    let group = get_group_or_404(&state, &group_id).await?;
+
+    let members = body.members.iter().map(|m| m.value.clone()).collect();
+    let group = save_group(&state, &group, body.display_name.clone(), members).await?;
+
+    Ok(scim_response(
+        HttpResponse::Ok(),
+        &group_to_scim(&state, &group).await?,
+    ))
 }
 
 // PATCH /Groups/{id} - Patch group
 async fn patch_scim_group(
-    _user: AuthUser,
-    _group_id: web::Path<String>,
+    _auth: ScimAuth,
+    state: web::Data<AppState>,
+    group_id: web::Path<String>,
+    body: web::Json<SCIMPatchRequest>,
 ) -> AppResult<HttpResponse> {
-    // TODO: Implement SCIM group patch
-    Ok(HttpResponse::NotImplemented().json(serde_json::json!({
-        "detail": "SCIM group patch not yet implemented"
-    })))
+    let group = get_group_or_404(&state, &group_id).await?;
+
+    let mut name = group.name.clone();
+    let mut members = group.user_ids.clone();
+    apply_group_operations(&mut name, &mut members, &body.operations)?;
+    let group = save_group(&state, &group, name, members).await?;
+
+    Ok(scim_response(
+        HttpResponse::Ok(),
+        &group_to_scim(&state, &group).await?,
+    ))
 }
 
 // DELETE /Groups/{id} - Delete group
 async fn delete_scim_group(
-    _user: AuthUser,
-    _group_id: web::Path<String>,
+    _auth: ScimAuth,
+    state: web::Data<AppState>,
+    group_id: web::Path<String>,
 ) -> AppResult<HttpResponse> {
-    // TODO: Implement SCIM group deletion
-    Ok(HttpResponse::NotImplemented().json(serde_json::json!({
-        "detail": "SCIM group deletion not yet implemented"
-    })))
+    let group = get_group_or_404(&state, &group_id).await?;
+    GroupService::new(&state.db)
+        .delete_group_by_id(&group.id)
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
 }
 
 pub fn create_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
-        web::scope("/scim/v2")
-            .service(
-                web::resource("/Users")
-                    .wrap(AdminMiddleware)
-                    .route(web::get().to(list_scim_users))
-                    .route(web::post().to(create_scim_user)),
-            )
-            .service(
-                web::resource("/Users/{id}")
-                    .wrap(AdminMiddleware)
-                    .route(web::get().to(get_scim_user))
-                    .route(web::put().to(update_scim_user))
-                    .route(web::patch().to(patch_scim_user))
-                    .route(web::delete().to(delete_scim_user)),
-            )
-            .service(
-                web::resource("/Groups")
-                    .wrap(AdminMiddleware)
-                    .route(web::get().to(list_scim_groups))
-                    .route(web::post().to(create_scim_group)),
-            )
-            .service(
-                web::resource("/Groups/{id}")
-                    .wrap(AdminMiddleware)
-                    .route(web::get().to(get_scim_group))
-                    .route(web::put().to(update_scim_group))
-                    .route(web::patch().to(patch_scim_group))
-                    .route(web::delete().to(delete_scim_group)),
-            ),
+        web::resource("/Users")
+            .route(web::get().to(list_scim_users))
+            .route(web::post().to(create_scim_user)),
+    )
+    .service(
+        web::resource("/Users/{id}")
+            .route(web::get().to(get_scim_user))
+            .route(web::put().to(update_scim_user))
+            .route(web::patch().to(patch_scim_user))
+            .route(web::delete().to(delete_scim_user)),
+    )
+    .service(
+        web::resource("/Groups")
+            .route(web::get().to(list_scim_groups))
+            .route(web::post().to(create_scim_group)),
+    )
+    .service(
+        web::resource("/Groups/{id}")
+            .route(web::get().to(get_scim_group))
+            .route(web::put().to(update_scim_group))
+            .route(web::patch().to(patch_scim_group))
+            .route(web::delete().to(delete_scim_group)),
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ops(value: Value) -> Vec<PatchOperation> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_parse_filter() {
+        assert_eq!(
+            parse_filter(r#"userName eq "jane@example.com""#).unwrap(),
+            SCIMFilter {
+                attribute: "userName".to_string(),
+                value: "jane@example.com".to_string(),
+            }
+        );
+        assert_eq!(
+            parse_filter(r#"displayName EQ "Sales Team""#)
+                .unwrap()
+                .value,
+            "Sales Team"
+        );
+        assert!(parse_filter(r#"userName co "jane""#).is_err());
+        assert!(parse_filter("userName").is_err());
+    }
+
+    #[test]
+    fn test_user_patch_with_paths() {
+        let patch = user_patch_from_operations(&ops(json!([
+            {"op": "Replace", "path": "active", "value": "False"},
+            {"op": "replace", "path": "emails[type eq \"work\"].value", "value": "new@example.com"},
+            {"op": "add", "path": "name.givenName", "value": "Jane"},
+            {"op": "add", "path": "name.familyName", "value": "Doe"},
+            {"op": "add", "path": "title", "value": "Engineer"}
+        ])))
+        .unwrap();
+
+        assert_eq!(patch.active, Some(false));
+        assert_eq!(patch.email.as_deref(), Some("new@example.com"));
+        assert_eq!(patch.display_name().as_deref(), Some("Jane Doe"));
+    }
+
+    #[test]
+    fn test_user_patch_without_path() {
+        let patch = user_patch_from_operations(&ops(json!([
+            {"op": "replace", "value": {"active": true, "displayName": "Jane D", "userName": "jane@example.com"}}
+        ])))
+        .unwrap();
+
+        assert_eq!(patch.active, Some(true));
+        assert_eq!(patch.display_name().as_deref(), Some("Jane D"));
+        assert_eq!(patch.email.as_deref(), Some("jane@example.com"));
+
+        assert!(
+            user_patch_from_operations(&ops(json!([{"op": "move", "path": "active"}]))).is_err()
+        );
+    }
+
+    #[test]
+    fn test_group_patch_members() {
+        let mut name = "Old".to_string();
+        let mut members = vec!["a".to_string(), "b".to_string()];
+
+        apply_group_operations(
+            &mut name,
+            &mut members,
+            &ops(json!([
+                {"op": "add", "path": "members", "value": [{"value": "c"}, {"value": "a"}]},
+                {"op": "remove", "path": "members[value eq \"b\"]"},
+                {"op": "replace", "path": "displayName", "value": "New"}
+            ])),
+        )
+        .unwrap();
+        assert_eq!(name, "New");
+        assert_eq!(members, vec!["a", "c"]);
+
+        apply_group_operations(
+            &mut name,
+            &mut members,
+            &ops(json!([
+                {"op": "replace", "value": {"members": [{"value": "d"}]}},
+                {"op": "remove", "path": "members", "value": [{"value": "d"}]}
+            ])),
+        )
+        .unwrap();
+        assert!(members.is_empty());
+    }
+
+    #[test]
+    fn test_list_query_page_bounds() {
+        let query = SCIMListQuery {
+            filter: None,
+            start_index: Some(0),
+            count: Some(5000),
+        };
+        assert_eq!(query.page(), (1, MAX_PAGE_SIZE));
+        assert_eq!(paginate(vec![1, 2, 3, 4], 2, 2), vec![2, 3]);
+    }
+}
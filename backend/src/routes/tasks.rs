@@ -5,6 +5,7 @@ use serde_json::json;
 use crate::{
     error::AppError,
     middleware::{AuthMiddleware, AuthUser},
+    services::providers::Provider,
     AppState,
 };
 
@@ -406,14 +407,7 @@ async fn generate_queries(
         })));
     }
 
-    let template = if config.query_generation_prompt_template.is_empty() {
-        DEFAULT_QUERY_GENERATION_PROMPT_TEMPLATE.to_string()
-    } else {
-        config.query_generation_prompt_template.clone()
-    };
-
-    let messages_text = format_messages(&payload.messages);
-    let prompt = template.replace("{{MESSAGES}}", &messages_text);
+    let prompt = query_generation_prompt(&config, &payload.messages);
 
     drop(config);
 
@@ -584,42 +578,22 @@ async fn call_openai_completion(
         auth_user.user.email
     );
 
-    // Make the API request
+    // Make the API request, translated for non-OpenAI providers
     let client = reqwest::Client::new();
-    let mut request_builder = client
-        .post(format!("{}/chat/completions", url.trim_end_matches('/')))
-        .header("Content-Type", "application/json");
-
-    // Add authorization header based on auth_type
-    let auth_type = api_config
-        .get("auth_type")
-        .and_then(|v| v.as_str())
-        .unwrap_or("bearer");
-
-    match auth_type {
-        "none" => {
-            // No authentication
-            tracing::debug!("Task completion - no authentication required");
-        }
-        _ => {
-            // Default to bearer token for all other cases
-            if !key.is_empty() {
-                request_builder =
-                    request_builder.header("Authorization", format!("Bearer {}", key));
-                tracing::debug!("Task completion - using bearer token authentication");
-            } else {
-                tracing::warn!("Task completion - no API key available for model {}", model);
-            }
-        }
+    let provider = Provider::from_api_config(&api_config);
+    if key.is_empty() {
+        tracing::warn!("Task completion - no API key available for model {}", model);
     }
+    let request_builder =
+        provider.chat_request(&client, &url, &key, &api_config, &completion_payload);
 
-    match request_builder.json(&completion_payload).send().await {
+    match request_builder.send().await {
         Ok(response) if response.status().is_success() => {
             let json_response = response.json::<serde_json::Value>().await.map_err(|e| {
                 AppError::InternalServerError(format!("Failed to parse response: {}", e))
             })?;
 
-            Ok(HttpResponse::Ok().json(json_response))
+            Ok(HttpResponse::Ok().json(provider.openai_response(json_response)))
         }
         Ok(response) => {
             let status = response.status();
@@ -874,6 +848,21 @@ fn get_endpoint_and_route_request(
     }
 }
 
+/// Build the search query generation prompt for a conversation
+///
+/// Shared with the `web_search` chat feature so both generate queries the same way.
+pub(crate) fn query_generation_prompt(
+    config: &crate::config::Config,
+    messages: &[serde_json::Value],
+) -> String {
+    let template = if config.query_generation_prompt_template.is_empty() {
+        DEFAULT_QUERY_GENERATION_PROMPT_TEMPLATE
+    } else {
+        config.query_generation_prompt_template.as_str()
+    };
+    template.replace("{{MESSAGES}}", &format_messages(messages))
+}
+
 // Helper function to format messages
 fn format_messages(messages: &[serde_json::Value]) -> String {
     messages
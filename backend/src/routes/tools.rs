@@ -6,15 +6,31 @@ use validator::Validate;
 
 use crate::error::{AppError, AppResult};
 use crate::middleware::{AuthMiddleware, AuthUser};
-use crate::models::tool::ToolUserResponse;
-use crate::models::tool_runtime::{ExecutionContext, ToolExecutionRequest, UserContext};
+use crate::models::tool::{Tool, ToolUserResponse};
+use crate::models::tool_runtime::{
+    ExecutionContext, ToolDefinition, ToolExecutionRequest, UserContext,
+};
 use crate::services::group::GroupService;
+use crate::services::mcp::McpToolServer;
+use crate::services::openapi::OpenApiToolServer;
 use crate::services::tool::ToolService;
 use crate::services::tool_runtime::ToolRuntimeService;
 use crate::services::user::UserService;
 use crate::utils::misc::{has_access, has_permission};
+use crate::utils::valves;
 use crate::AppState;
 
+/// Valves and UserValves specs from a JSON tool's `environment` block
+fn tool_valves_specs(tool: &Tool) -> (Option<Value>, Option<Value>) {
+    match ToolDefinition::from_json(&tool.content) {
+        Ok(tool_def) => (
+            tool_def.environment.valves_spec(),
+            tool_def.environment.user_valves_spec(),
+        ),
+        Err(_) => (None, None),
+    }
+}
+
 /// Parse JSON tool definition and extract OpenAI-compatible function specs
 fn parse_json_tool_specs(content: &str) -> AppResult<Value> {
     // Parse the JSON content
@@ -394,16 +410,55 @@ async fn get_tools(state: web::Data<AppState>, auth_user: AuthUser) -> AppResult
         }
     }
 
-    // TODO: Implement OpenAPI Tool Servers integration
-    // TODO: Implement MCP Tool Servers integration
+    // Tool servers (OpenAPI and MCP) configured by the admin
+    let tool_server_connections = state.config.read().unwrap().tool_server_connections.clone();
+    let tool_servers: Vec<(String, String, String, Option<Value>)> =
+        OpenApiToolServer::from_connections(&tool_server_connections)
+            .into_iter()
+            .map(|s| (s.tool_id(), s.name, s.description, s.access_control))
+            .chain(
+                McpToolServer::from_connections(&tool_server_connections)
+                    .into_iter()
+                    .map(|s| (s.tool_id(), s.name, s.description, s.access_control)),
+            )
+            .collect();
+    let mut server_tools = Vec::new();
+    if !tool_servers.is_empty() {
+        let user_group_ids: HashSet<String> = GroupService::new(&state.db)
+            .get_groups_by_member_id(&auth_user.user.id)
+            .await?
+            .into_iter()
+            .map(|g| g.id)
+            .collect();
+        let now = chrono::Utc::now().timestamp();
+
+        for (id, name, description, access_control) in tool_servers {
+            if (auth_user.user.role == "admin" && bypass_admin_access)
+                || has_access(&auth_user.user.id, "read", &access_control, &user_group_ids)
+            {
+                server_tools.push(ToolUserResponse {
+                    user_id: id.clone(),
+                    id,
+                    name,
+                    meta: json!({ "description": description }),
+                    access_control,
+                    updated_at: now,
+                    created_at: now,
+                    has_user_valves: Some(false),
+                    user: None,
+                });
+            }
+        }
+    }
 
     let response: Vec<ToolUserResponse> = tools
         .into_iter()
         .map(|t| {
             let user = users_map.get(&t.user_id).cloned();
-            // TODO: Implement UserValves detection
-            ToolUserResponse::from_tool_and_user(t, user, Some(false))
+            let has_user_valves = tool_valves_specs(&t).1.is_some();
+            ToolUserResponse::from_tool_and_user(t, user, Some(has_user_valves))
         })
+        .chain(server_tools)
         .collect();
 
     Ok(HttpResponse::Ok().json(response))
@@ -670,18 +725,28 @@ async fn get_tool_valves(
         .await?
         .ok_or_else(|| AppError::NotFound("Tool not found".to_string()))?;
 
-    let valves = tool.valves.unwrap_or_else(|| json!({}));
+    let valves = tool.valves.clone().unwrap_or_else(|| json!({}));
+    let valves = match tool_valves_specs(&tool).0 {
+        Some(spec) => valves::mask_secrets(&valves, &spec),
+        None => valves,
+    };
     Ok(HttpResponse::Ok().json(valves))
 }
 
 // GET /id/{id}/valves/spec - Get tool valves spec
 async fn get_tool_valves_spec(
-    _state: web::Data<AppState>,
+    state: web::Data<AppState>,
     _auth_user: AuthUser,
-    _id: web::Path<String>,
+    id: web::Path<String>,
 ) -> AppResult<HttpResponse> {
-    // TODO: Implement tool module loading and Valves spec extraction
-    Ok(HttpResponse::Ok().json(json!(null)))
+    let tool_service = ToolService::new(&state.db);
+
+    let tool = tool_service
+        .get_tool_by_id(&id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Tool not found".to_string()))?;
+
+    Ok(HttpResponse::Ok().json(tool_valves_specs(&tool).0))
 }
 
 // POST /id/{id}/valves/update - Update tool valves
@@ -716,11 +781,17 @@ async fn update_tool_valves(
         }
     }
 
-    let valves_data = valves.into_inner();
+    let spec = tool_valves_specs(&tool).0;
+    let current = tool.valves.clone().unwrap_or_else(|| json!({}));
+    let valves_data = valves::apply_update(&current, valves.into_inner(), spec.as_ref())?;
     tool_service
         .update_tool_valves(&id, valves_data.clone())
         .await?;
 
+    let valves_data = match spec {
+        Some(spec) => valves::mask_secrets(&valves_data, &spec),
+        None => valves_data,
+    };
     Ok(HttpResponse::Ok().json(valves_data))
 }
 
@@ -732,24 +803,33 @@ async fn get_tool_user_valves(
 ) -> AppResult<HttpResponse> {
     let tool_service = ToolService::new(&state.db);
 
-    let _tool = tool_service
+    let tool = tool_service
         .get_tool_by_id(&id)
         .await?
         .ok_or_else(|| AppError::NotFound("Tool not found".to_string()))?;
 
-    // TODO: Get user valves from user settings
-    // For now, return empty object
-    Ok(HttpResponse::Ok().json(json!({})))
+    let user_valves = valves::user_valves(auth_user.user.settings.as_ref(), "tools", &tool.id);
+    let user_valves = match tool_valves_specs(&tool).1 {
+        Some(spec) => valves::mask_secrets(&user_valves, &spec),
+        None => user_valves,
+    };
+    Ok(HttpResponse::Ok().json(user_valves))
 }
 
 // GET /id/{id}/valves/user/spec - Get tool user valves spec
 async fn get_tool_user_valves_spec(
-    _state: web::Data<AppState>,
+    state: web::Data<AppState>,
     _auth_user: AuthUser,
-    _id: web::Path<String>,
+    id: web::Path<String>,
 ) -> AppResult<HttpResponse> {
-    // TODO: Implement UserValves spec extraction
-    Ok(HttpResponse::Ok().json(json!(null)))
+    let tool_service = ToolService::new(&state.db);
+
+    let tool = tool_service
+        .get_tool_by_id(&id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Tool not found".to_string()))?;
+
+    Ok(HttpResponse::Ok().json(tool_valves_specs(&tool).1))
 }
 
 // POST /id/{id}/valves/user/update - Update tool user valves
@@ -761,14 +841,30 @@ async fn update_tool_user_valves(
 ) -> AppResult<HttpResponse> {
     let tool_service = ToolService::new(&state.db);
 
-    let _tool = tool_service
+    let tool = tool_service
         .get_tool_by_id(&id)
         .await?
         .ok_or_else(|| AppError::NotFound("Tool not found".to_string()))?;
 
-    // TODO: Update user valves in user settings
-    let valves_data = valves.into_inner();
-    Ok(HttpResponse::Ok().json(valves_data))
+    let spec = tool_valves_specs(&tool)
+        .1
+        .ok_or_else(|| AppError::BadRequest("This tool has no user valves".to_string()))?;
+
+    // Re-read the settings so concurrent updates to other valves are kept
+    let user_service = UserService::new(&state.db);
+    let settings = user_service
+        .get_user_by_id(&auth_user.user.id)
+        .await?
+        .and_then(|u| u.settings);
+    let current = valves::user_valves(settings.as_ref(), "tools", &tool.id);
+    let valves_data = valves::apply_update(&current, valves.into_inner(), Some(&spec))?;
+    let settings =
+        valves::set_user_valves(settings.as_ref(), "tools", &tool.id, valves_data.clone());
+    user_service
+        .update_user_settings(&auth_user.user.id, &settings)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(valves::mask_secrets(&valves_data, &spec)))
 }
 
 #[derive(Debug, Deserialize)]
@@ -1750,16 +1846,39 @@ async fn get_tool_schema(
                 "properties": {
                     "required": {
                         "type": "array",
-                        "items": {"type": "string"},
-                        "description": "Required environment variables"
+                        "items": {"$ref": "#/definitions/env_var"},
+                        "description": "Required environment variables, set by an admin as valves"
                     },
                     "optional": {
                         "type": "array",
-                        "items": {"type": "string"},
-                        "description": "Optional environment variables"
+                        "items": {"$ref": "#/definitions/env_var"},
+                        "description": "Optional environment variables, set by an admin as valves"
+                    },
+                    "user": {
+                        "type": "array",
+                        "items": {"$ref": "#/definitions/env_var"},
+                        "description": "Environment variables each user sets as user valves"
                     }
                 }
             },
+            "env_var": {
+                "oneOf": [
+                    {"type": "string"},
+                    {
+                        "type": "object",
+                        "properties": {
+                            "name": {"type": "string"},
+                            "description": {"type": "string"},
+                            "default": {"type": "string"},
+                            "secret": {
+                                "type": "boolean",
+                                "description": "Mask the value when valves are read back"
+                            }
+                        },
+                        "required": ["name"]
+                    }
+                ]
+            },
             "rate_limit": {
                 "type": "object",
                 "required": ["requests", "window_seconds"],
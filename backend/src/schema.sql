@@ -329,3 +329,53 @@ CREATE TABLE IF NOT EXISTS config (
 
 CREATE INDEX IF NOT EXISTS idx_config_updated_at ON config(updated_at DESC);
 
+
+-- Audit log table (append-only)
+CREATE TABLE IF NOT EXISTS audit_log (
+    id TEXT PRIMARY KEY,
+    user_id TEXT,
+    user_email TEXT,
+    user_role TEXT,
+    method TEXT NOT NULL,
+    path TEXT NOT NULL,
+    route TEXT,
+    resource TEXT,
+    status_code INTEGER NOT NULL,
+    source_ip TEXT,
+    user_agent TEXT,
+    audit_level TEXT NOT NULL,
+    request_body TEXT,
+    response_body TEXT,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_created_at ON audit_log(created_at);
+CREATE INDEX IF NOT EXISTS idx_audit_log_user_id ON audit_log(user_id);
+CREATE INDEX IF NOT EXISTS idx_audit_log_resource ON audit_log(resource);
+
+CREATE TRIGGER IF NOT EXISTS audit_log_no_update BEFORE UPDATE ON audit_log BEGIN SELECT RAISE(ABORT, 'audit_log is append-only'); END;
+CREATE TRIGGER IF NOT EXISTS audit_log_no_delete BEFORE DELETE ON audit_log BEGIN SELECT RAISE(ABORT, 'audit_log is append-only'); END;
+
+-- Chat completion quotas for users and groups
+CREATE TABLE IF NOT EXISTS quota (
+    id TEXT PRIMARY KEY,
+    target_type TEXT NOT NULL,
+    target_id TEXT NOT NULL,
+    model_id TEXT,
+    requests_per_day INTEGER,
+    tokens_per_month INTEGER,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_quota_target ON quota(target_type, target_id);
+
+-- Chat completion usage per user, model and UTC day
+CREATE TABLE IF NOT EXISTS model_usage (
+    user_id TEXT NOT NULL,
+    model_id TEXT NOT NULL,
+    day TEXT NOT NULL,
+    requests INTEGER NOT NULL DEFAULT 0,
+    tokens INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (user_id, model_id, day)
+);
@@ -160,108 +160,24 @@ impl AudioService {
         Ok(audio_data)
     }
 
-    /// Speech-to-Text: Transcribe audio to text
+    /// Speech-to-Text: Transcribe audio to text with the configured STT engine
     pub async fn speech_to_text(
         &self,
         audio_data: Vec<u8>,
         request: STTRequest,
     ) -> AppResult<String> {
-        let engine = if self.config.stt_engine.is_empty() {
-            "whisper"
-        } else {
-            self.config.stt_engine.as_str()
-        };
-
-        match engine {
-            "whisper" | "openai" => self.openai_stt(audio_data, request).await,
-            "azure" => self.azure_stt(audio_data, request).await,
-            _ => Err(AppError::BadRequest("Unsupported STT engine".to_string())),
+        let mut config = self.config.clone();
+        if !request.model.is_empty() {
+            config.stt_model = request.model;
         }
-    }
-
-    async fn openai_stt(&self, audio_data: Vec<u8>, request: STTRequest) -> AppResult<String> {
-        let api_key = std::env::var("OPENAI_API_KEY")
-            .map_err(|_| AppError::InternalServerError("OpenAI API key not set".to_string()))?;
-
-        let form = reqwest::multipart::Form::new()
-            .part(
-                "file",
-                reqwest::multipart::Part::bytes(audio_data).file_name("audio.mp3"),
-            )
-            .text("model", request.model);
 
-        let form = if let Some(language) = request.language {
-            form.text("language", language)
-        } else {
-            form
+        let input = super::stt::AudioInput {
+            data: audio_data,
+            filename: "audio.mp3".to_string(),
+            content_type: "audio/mpeg".to_string(),
+            language: request.language,
         };
 
-        let response = self
-            .client
-            .post("https://api.openai.com/v1/audio/transcriptions")
-            .header("Authorization", format!("Bearer {}", api_key))
-            .multipart(form)
-            .send()
-            .await
-            .map_err(|e| AppError::ExternalServiceError(e.to_string()))?;
-
-        if !response.status().is_success() {
-            return Err(AppError::ExternalServiceError(format!(
-                "OpenAI STT failed: {}",
-                response.status()
-            )));
-        }
-
-        let result: serde_json::Value = response
-            .json()
-            .await
-            .map_err(|e| AppError::ExternalServiceError(e.to_string()))?;
-
-        let text = result["text"]
-            .as_str()
-            .ok_or_else(|| AppError::ExternalServiceError("No transcription text".to_string()))?
-            .to_string();
-
-        Ok(text)
-    }
-
-    async fn azure_stt(&self, audio_data: Vec<u8>, _request: STTRequest) -> AppResult<String> {
-        let api_key = std::env::var("AZURE_SPEECH_KEY")
-            .map_err(|_| AppError::InternalServerError("Azure Speech key not set".to_string()))?;
-        let region = std::env::var("AZURE_SPEECH_REGION").map_err(|_| {
-            AppError::InternalServerError("Azure Speech region not set".to_string())
-        })?;
-
-        let response = self
-            .client
-            .post(format!(
-                "https://{}.stt.speech.microsoft.com/speech/recognition/conversation/cognitiveservices/v1",
-                region
-            ))
-            .header("Ocp-Apim-Subscription-Key", api_key)
-            .header("Content-Type", "audio/wav")
-            .body(audio_data)
-            .send()
-            .await
-            .map_err(|e| AppError::ExternalServiceError(e.to_string()))?;
-
-        if !response.status().is_success() {
-            return Err(AppError::ExternalServiceError(format!(
-                "Azure STT failed: {}",
-                response.status()
-            )));
-        }
-
-        let result: serde_json::Value = response
-            .json()
-            .await
-            .map_err(|e| AppError::ExternalServiceError(e.to_string()))?;
-
-        let text = result["DisplayText"]
-            .as_str()
-            .ok_or_else(|| AppError::InternalServerError("No transcription text".to_string()))?
-            .to_string();
-
-        Ok(text)
+        Ok(super::stt::transcribe(&config, &input).await?.text)
     }
 }
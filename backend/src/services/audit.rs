@@ -0,0 +1,201 @@
+use sqlx::{QueryBuilder, Sqlite};
+
+use crate::db::Database;
+use crate::error::AppResult;
+use crate::models::audit::{AuditLog, AuditLogFilter};
+
+pub struct AuditService<'a> {
+    db: &'a Database,
+}
+
+impl<'a> AuditService<'a> {
+    pub fn new(db: &'a Database) -> Self {
+        AuditService { db }
+    }
+
+    /// Append an entry; the table rejects updates and deletes
+    pub async fn insert(&self, entry: &AuditLog) -> AppResult<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO audit_log (
+                id, user_id, user_email, user_role, method, path, route, resource,
+                status_code, source_ip, user_agent, audit_level, request_body,
+                response_body, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+            "#,
+        )
+        .bind(&entry.id)
+        .bind(&entry.user_id)
+        .bind(&entry.user_email)
+        .bind(&entry.user_role)
+        .bind(&entry.method)
+        .bind(&entry.path)
+        .bind(&entry.route)
+        .bind(&entry.resource)
+        .bind(entry.status_code)
+        .bind(&entry.source_ip)
+        .bind(&entry.user_agent)
+        .bind(&entry.audit_level)
+        .bind(&entry.request_body)
+        .bind(&entry.response_body)
+        .bind(entry.created_at)
+        .execute(&self.db.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// A page of matching entries, newest first, and the number of matches
+    pub async fn list(
+        &self,
+        filter: &AuditLogFilter,
+        skip: i64,
+        limit: i64,
+    ) -> AppResult<(Vec<AuditLog>, i64)> {
+        let items = self.select(filter, "DESC", skip, limit).await?;
+
+        let mut query_builder = QueryBuilder::new("SELECT COUNT(*) FROM audit_log WHERE 1 = 1");
+        push_filter(&mut query_builder, filter);
+        let total: i64 = query_builder
+            .build_query_scalar()
+            .fetch_one(&self.db.pool)
+            .await?;
+
+        Ok((items, total))
+    }
+
+    /// A page of matching entries, oldest first, for exports
+    pub async fn export_page(
+        &self,
+        filter: &AuditLogFilter,
+        skip: i64,
+        limit: i64,
+    ) -> AppResult<Vec<AuditLog>> {
+        self.select(filter, "ASC", skip, limit).await
+    }
+
+    async fn select(
+        &self,
+        filter: &AuditLogFilter,
+        order: &str,
+        skip: i64,
+        limit: i64,
+    ) -> AppResult<Vec<AuditLog>> {
+        let mut query_builder = QueryBuilder::new("SELECT * FROM audit_log WHERE 1 = 1");
+        push_filter(&mut query_builder, filter);
+        query_builder.push(format!(" ORDER BY created_at {order}, id {order} LIMIT "));
+        query_builder.push_bind(limit);
+        query_builder.push(" OFFSET ");
+        query_builder.push_bind(skip);
+
+        let entries = query_builder
+            .build_query_as::<AuditLog>()
+            .fetch_all(&self.db.pool)
+            .await?;
+
+        Ok(entries)
+    }
+}
+
+fn push_filter(query_builder: &mut QueryBuilder<'_, Sqlite>, filter: &AuditLogFilter) {
+    if let Some(user_id) = &filter.user_id {
+        query_builder.push(" AND user_id = ");
+        query_builder.push_bind(user_id.clone());
+    }
+    if let Some(resource) = &filter.resource {
+        query_builder.push(" AND resource = ");
+        query_builder.push_bind(resource.clone());
+    }
+    if let Some(method) = &filter.method {
+        query_builder.push(" AND method = ");
+        query_builder.push_bind(method.to_uppercase());
+    }
+    if let Some(status_code) = filter.status_code {
+        query_builder.push(" AND status_code = ");
+        query_builder.push_bind(status_code);
+    }
+    if let Some(start) = filter.start {
+        query_builder.push(" AND created_at >= ");
+        query_builder.push_bind(start);
+    }
+    if let Some(end) = filter.end {
+        query_builder.push(" AND created_at < ");
+        query_builder.push_bind(end);
+    }
+    if let Some(query) = &filter.query {
+        query_builder.push(" AND path LIKE ");
+        query_builder.push_bind(format!("%{}%", query));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, user_id: &str, resource: &str, created_at: i64) -> AuditLog {
+        AuditLog {
+            id: id.to_string(),
+            user_id: Some(user_id.to_string()),
+            user_email: None,
+            user_role: Some("admin".to_string()),
+            method: "POST".to_string(),
+            path: format!("/api/v1/{}/create", resource),
+            route: None,
+            resource: Some(resource.to_string()),
+            status_code: 200,
+            source_ip: None,
+            user_agent: None,
+            audit_level: "METADATA".to_string(),
+            request_body: None,
+            response_body: None,
+            created_at,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_audit_log_is_filtered_and_append_only() {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        db.run_migrations().await.unwrap();
+        let service = AuditService::new(&db);
+
+        service
+            .insert(&entry("a", "u1", "models", 100))
+            .await
+            .unwrap();
+        service
+            .insert(&entry("b", "u2", "tools", 200))
+            .await
+            .unwrap();
+        service
+            .insert(&entry("c", "u1", "tools", 300))
+            .await
+            .unwrap();
+
+        let filter = AuditLogFilter {
+            user_id: Some("u1".to_string()),
+            ..Default::default()
+        };
+        let (items, total) = service.list(&filter, 0, 1).await.unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(items[0].id, "c");
+
+        let filter = AuditLogFilter {
+            resource: Some("tools".to_string()),
+            end: Some(300),
+            ..Default::default()
+        };
+        let exported = service.export_page(&filter, 0, 10).await.unwrap();
+        assert_eq!(exported.len(), 1);
+        assert_eq!(exported[0].id, "b");
+
+        assert!(sqlx::query("DELETE FROM audit_log")
+            .execute(&db.pool)
+            .await
+            .is_err());
+        assert!(sqlx::query("UPDATE audit_log SET status_code = 500")
+            .execute(&db.pool)
+            .await
+            .is_err());
+    }
+}
@@ -144,6 +144,11 @@ impl ConfigService {
                 "api_base_urls": config.openai_api_base_urls,
                 "api_configs": config.openai_api_configs
             },
+            "ollama": {
+                "enable": config.enable_ollama_api,
+                "base_urls": config.ollama_base_urls,
+                "api_configs": config.ollama_api_configs
+            },
             "features": {
                 "enable_channels": config.enable_channels,
                 "enable_notes": config.enable_notes,
@@ -298,6 +303,15 @@ impl ConfigService {
             config.openai_api_configs.clone(),
         );
 
+        // Merge Ollama config
+        config.enable_ollama_api = get_bool(&["ollama", "enable"], config.enable_ollama_api);
+        config.ollama_base_urls =
+            get_vec_string(&["ollama", "base_urls"], config.ollama_base_urls.clone());
+        config.ollama_api_configs = get_json(
+            &["ollama", "api_configs"],
+            config.ollama_api_configs.clone(),
+        );
+
         // Merge Admin config
         config.show_admin_details =
             get_bool(&["admin", "show_admin_details"], config.show_admin_details);
@@ -199,6 +199,25 @@ impl<'a> FileService<'a> {
             .ok_or_else(|| AppError::NotFound("File not found".to_string()))
     }
 
+    pub async fn update_file_hash(&self, id: &str, hash: &str) -> AppResult<()> {
+        let now = current_timestamp_seconds();
+
+        sqlx::query(
+            r#"
+            UPDATE file
+            SET hash = $1, updated_at = $2
+            WHERE id = $3
+            "#,
+        )
+        .bind(hash)
+        .bind(now)
+        .bind(id)
+        .execute(&self.db.pool)
+        .await?;
+
+        Ok(())
+    }
+
     pub async fn search_files_by_pattern(
         &self,
         user_id: Option<&str>,
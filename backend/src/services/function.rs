@@ -14,6 +14,12 @@ impl<'a> FunctionService<'a> {
         FunctionService { db }
     }
 
+    fn parse_json_fields(mut function: Function) -> Function {
+        function.parse_meta();
+        function.parse_valves();
+        function
+    }
+
     pub async fn create_function(
         &self,
         id: &str,
@@ -55,7 +61,10 @@ impl<'a> FunctionService<'a> {
     pub async fn get_function_by_id(&self, id: &str) -> AppResult<Option<Function>> {
         let result = sqlx::query_as::<_, Function>(
             r#"
-            SELECT id, user_id, name, type, content, meta, is_active, is_global, created_at, updated_at
+            SELECT id, user_id, name, type AS type_, content,
+                   CAST(meta AS TEXT) AS meta_str,
+                   CAST(valves AS TEXT) AS valves_str,
+                   is_active, is_global, created_at, updated_at
             FROM function
             WHERE id = $8
             "#,
@@ -64,13 +73,16 @@ impl<'a> FunctionService<'a> {
         .fetch_optional(&self.db.pool)
         .await?;
 
-        Ok(result)
+        Ok(result.map(Self::parse_json_fields))
     }
 
     pub async fn get_functions_by_user_id(&self, user_id: &str) -> AppResult<Vec<Function>> {
         let functions = sqlx::query_as::<_, Function>(
             r#"
-            SELECT id, user_id, name, type, content, meta, is_active, is_global, created_at, updated_at
+            SELECT id, user_id, name, type AS type_, content,
+                   CAST(meta AS TEXT) AS meta_str,
+                   CAST(valves AS TEXT) AS valves_str,
+                   is_active, is_global, created_at, updated_at
             FROM function
             WHERE user_id = $6
             ORDER BY updated_at DESC
@@ -80,13 +92,16 @@ impl<'a> FunctionService<'a> {
         .fetch_all(&self.db.pool)
         .await?;
 
-        Ok(functions)
+        Ok(functions.into_iter().map(Self::parse_json_fields).collect())
     }
 
     pub async fn get_all_functions(&self) -> AppResult<Vec<Function>> {
         let functions = sqlx::query_as::<_, Function>(
             r#"
-            SELECT id, user_id, name, type, content, meta, is_active, is_global, created_at, updated_at
+            SELECT id, user_id, name, type AS type_, content,
+                   CAST(meta AS TEXT) AS meta_str,
+                   CAST(valves AS TEXT) AS valves_str,
+                   is_active, is_global, created_at, updated_at
             FROM function
             ORDER BY updated_at DESC
             "#,
@@ -94,13 +109,16 @@ impl<'a> FunctionService<'a> {
         .fetch_all(&self.db.pool)
         .await?;
 
-        Ok(functions)
+        Ok(functions.into_iter().map(Self::parse_json_fields).collect())
     }
 
     pub async fn get_global_functions(&self) -> AppResult<Vec<Function>> {
         let functions = sqlx::query_as::<_, Function>(
             r#"
-            SELECT id, user_id, name, type, content, meta, is_active, is_global, created_at, updated_at
+            SELECT id, user_id, name, type AS type_, content,
+                   CAST(meta AS TEXT) AS meta_str,
+                   CAST(valves AS TEXT) AS valves_str,
+                   is_active, is_global, created_at, updated_at
             FROM function
             WHERE is_global = 1
             ORDER BY updated_at DESC
@@ -109,7 +127,27 @@ impl<'a> FunctionService<'a> {
         .fetch_all(&self.db.pool)
         .await?;
 
-        Ok(functions)
+        Ok(functions.into_iter().map(Self::parse_json_fields).collect())
+    }
+
+    /// Active functions of one type (filter, action or pipe)
+    pub async fn get_active_functions_by_type(&self, type_: &str) -> AppResult<Vec<Function>> {
+        let functions = sqlx::query_as::<_, Function>(
+            r#"
+            SELECT id, user_id, name, type AS type_, content,
+                   CAST(meta AS TEXT) AS meta_str,
+                   CAST(valves AS TEXT) AS valves_str,
+                   is_active, is_global, created_at, updated_at
+            FROM function
+            WHERE type = $1 AND is_active = true
+            ORDER BY updated_at DESC
+            "#,
+        )
+        .bind(type_)
+        .fetch_all(&self.db.pool)
+        .await?;
+
+        Ok(functions.into_iter().map(Self::parse_json_fields).collect())
     }
 
     pub async fn update_function(
@@ -1,250 +1,1014 @@
-use crate::config::Config;
-use crate::error::{AppError, AppResult};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::services::file::FileService;
+
+/// Upper bound on images per request, matching the OpenAI API limit
+const MAX_IMAGES_PER_REQUEST: usize = 10;
 
-#[derive(Debug, Serialize, Deserialize)]
+const COMFYUI_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const COMFYUI_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// A text-to-image request; unset fields fall back to the configured defaults
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ImageGenerationRequest {
     pub prompt: String,
     pub model: Option<String>,
     pub size: Option<String>,
     pub n: Option<usize>,
-    pub quality: Option<String>,
-    pub style: Option<String>,
+    pub negative_prompt: Option<String>,
+    pub steps: Option<u32>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ImageGenerationResponse {
-    pub images: Vec<ImageData>,
+/// A request with the configured defaults applied
+#[derive(Debug, Clone)]
+pub struct ImageParams {
+    pub prompt: String,
+    pub negative_prompt: Option<String>,
+    /// Empty when neither the request nor `IMAGE_GENERATION_MODEL` names one
+    pub model: String,
+    pub width: u32,
+    pub height: u32,
+    pub n: usize,
+    pub steps: u32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ImageData {
-    pub url: Option<String>,
-    pub b64_json: Option<String>,
+impl ImageParams {
+    pub fn resolve(config: &Config, request: ImageGenerationRequest) -> AppResult<Self> {
+        if request.prompt.trim().is_empty() {
+            return Err(AppError::BadRequest("Prompt is required".to_string()));
+        }
+
+        let size = request
+            .size
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| config.image_size.clone());
+        let (width, height) = parse_size(&size)?;
+
+        Ok(ImageParams {
+            prompt: request.prompt,
+            negative_prompt: request.negative_prompt.filter(|p| !p.is_empty()),
+            model: request
+                .model
+                .filter(|m| !m.is_empty())
+                .unwrap_or_else(|| config.image_generation_model.clone()),
+            width,
+            height,
+            n: request.n.unwrap_or(1).clamp(1, MAX_IMAGES_PER_REQUEST),
+            steps: request.steps.unwrap_or(config.image_steps).max(1),
+        })
+    }
+
+    fn model_or<'a>(&'a self, default: &'a str) -> &'a str {
+        if self.model.is_empty() {
+            default
+        } else {
+            &self.model
+        }
+    }
 }
 
-#[allow(dead_code)]
-pub struct ImageService {
-    client: Client,
-    config: Config,
+/// Parse a `WIDTHxHEIGHT` size string
+pub fn parse_size(size: &str) -> AppResult<(u32, u32)> {
+    size.trim()
+        .split_once(['x', 'X'])
+        .and_then(|(w, h)| Some((w.trim().parse().ok()?, h.trim().parse().ok()?)))
+        .filter(|&(w, h): &(u32, u32)| w > 0 && h > 0)
+        .ok_or_else(|| AppError::BadRequest(format!("Invalid image size: {}", size)))
+}
+
+/// Raw image bytes returned by an engine
+#[derive(Debug, Clone)]
+pub struct GeneratedImage {
+    pub data: Vec<u8>,
+    pub content_type: String,
 }
 
-#[allow(dead_code)]
-impl ImageService {
-    pub fn new(config: Config) -> Self {
-        ImageService {
+/// A generated image saved to the file store
+#[derive(Debug, Clone, Serialize)]
+pub struct StoredImage {
+    pub id: String,
+    pub url: String,
+}
+
+/// An image generation backend selected by `IMAGE_GENERATION_ENGINE`
+#[async_trait::async_trait]
+pub trait ImageEngine: Send + Sync {
+    async fn generate(&self, params: &ImageParams) -> AppResult<Vec<GeneratedImage>>;
+
+    /// Engine name, for logging and file metadata
+    fn name(&self) -> &'static str;
+}
+
+/// Create the engine configured by `IMAGE_GENERATION_ENGINE`
+pub fn create_image_engine(config: &Config) -> AppResult<Arc<dyn ImageEngine>> {
+    match config.image_generation_engine.as_str() {
+        "" | "openai" => Ok(Arc::new(OpenAiImages {
             client: Client::new(),
-            config,
+            base_url: config
+                .images_openai_api_base_url
+                .trim_end_matches('/')
+                .to_string(),
+            api_key: config.images_openai_api_key.clone(),
+            api_version: config.images_openai_api_version.clone(),
+        })),
+        "automatic1111" => {
+            if config.automatic1111_base_url.is_empty() {
+                return Err(AppError::BadRequest(
+                    "AUTOMATIC1111_BASE_URL is required for the automatic1111 image engine"
+                        .to_string(),
+                ));
+            }
+            Ok(Arc::new(Automatic1111Images {
+                client: Client::new(),
+                base_url: config
+                    .automatic1111_base_url
+                    .trim_end_matches('/')
+                    .to_string(),
+                api_auth: config.automatic1111_api_auth.clone(),
+                cfg_scale: config.automatic1111_cfg_scale,
+                sampler: config.automatic1111_sampler.clone(),
+                scheduler: config.automatic1111_scheduler.clone(),
+            }))
+        }
+        "comfyui" => Ok(Arc::new(ComfyUiImages::from_config(config)?)),
+        "gemini" => {
+            if config.images_gemini_api_key.is_empty() {
+                return Err(AppError::BadRequest(
+                    "IMAGES_GEMINI_API_KEY is required for the gemini image engine".to_string(),
+                ));
+            }
+            Ok(Arc::new(GeminiImages {
+                client: Client::new(),
+                base_url: config
+                    .images_gemini_api_base_url
+                    .trim_end_matches('/')
+                    .to_string(),
+                api_key: config.images_gemini_api_key.clone(),
+            }))
         }
+        other => Err(AppError::BadRequest(format!(
+            "Unsupported image generation engine: {}",
+            other
+        ))),
     }
+}
 
-    pub async fn generate_image(
-        &self,
-        request: ImageGenerationRequest,
-    ) -> AppResult<ImageGenerationResponse> {
-        let engine = if self.config.image_generation_engine.is_empty() {
-            "openai"
-        } else {
-            self.config.image_generation_engine.as_str()
-        };
+/// Generate images with the configured engine and store them as files owned by `user_id`
+pub async fn generate_images(
+    db: &Database,
+    config: &Config,
+    user_id: &str,
+    request: ImageGenerationRequest,
+) -> AppResult<Vec<StoredImage>> {
+    let engine = create_image_engine(config)?;
+    let params = ImageParams::resolve(config, request)?;
 
-        match engine {
-            "openai" => self.openai_generate(request).await,
-            "automatic1111" => self.automatic1111_generate(request).await,
-            "comfyui" => self.comfyui_generate(request).await,
-            _ => Err(AppError::BadRequest(
-                "Unsupported image generation engine".to_string(),
-            )),
+    info!(
+        "Generating {} image(s) at {}x{} with {}",
+        params.n,
+        params.width,
+        params.height,
+        engine.name()
+    );
+    let images = engine.generate(&params).await?;
+    if images.is_empty() {
+        return Err(AppError::ExternalServiceError(format!(
+            "{} returned no images",
+            engine.name()
+        )));
+    }
+
+    let upload_dir = PathBuf::from(&config.upload_dir);
+    tokio::fs::create_dir_all(&upload_dir).await?;
+
+    let service = FileService::new(db);
+    let mut stored = Vec::with_capacity(images.len());
+    for image in images {
+        let file_id = uuid::Uuid::new_v4().to_string();
+        let file_path = upload_dir.join(&file_id);
+        tokio::fs::write(&file_path, &image.data).await?;
+
+        let filename = format!("generated-image.{}", image_extension(&image.content_type));
+        let meta = json!({
+            "source": "image_generation",
+            "engine": engine.name(),
+            "prompt": params.prompt,
+            "size": image.data.len(),
+            "content_type": image.content_type,
+        });
+        service
+            .create_file(
+                &file_id,
+                user_id,
+                &filename,
+                &file_path.to_string_lossy(),
+                Some(meta),
+            )
+            .await?;
+
+        stored.push(StoredImage {
+            url: format!("/api/v1/files/{}/content", file_id),
+            id: file_id,
+        });
+    }
+
+    Ok(stored)
+}
+
+fn image_extension(content_type: &str) -> &'static str {
+    match content_type {
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/webp" => "webp",
+        "image/gif" => "gif",
+        _ => "png",
+    }
+}
+
+/// Decode a base64 payload, which may be wrapped in a `data:` URL
+fn decode_base64_image(data: &str, default_content_type: &str) -> AppResult<GeneratedImage> {
+    let (content_type, encoded) = match data.strip_prefix("data:") {
+        Some(rest) => {
+            let (header, encoded) = rest.split_once(',').ok_or_else(|| {
+                AppError::ExternalServiceError("Malformed image data URL".to_string())
+            })?;
+            let content_type = header.split(';').next().unwrap_or(default_content_type);
+            (content_type, encoded)
         }
+        None => (default_content_type, data),
+    };
+
+    let data = STANDARD
+        .decode(encoded.trim())
+        .map_err(|e| AppError::ExternalServiceError(format!("Invalid image data: {}", e)))?;
+
+    Ok(GeneratedImage {
+        data,
+        content_type: content_type.to_string(),
+    })
+}
+
+/// Turn a non-2xx response into an error carrying the upstream body
+async fn ensure_success(response: reqwest::Response, engine: &str) -> AppResult<reqwest::Response> {
+    if response.status().is_success() {
+        return Ok(response);
     }
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    Err(AppError::ExternalServiceError(format!(
+        "{} image generation failed ({}): {}",
+        engine, status, body
+    )))
+}
 
-    async fn openai_generate(
-        &self,
-        request: ImageGenerationRequest,
-    ) -> AppResult<ImageGenerationResponse> {
-        let api_key = std::env::var("OPENAI_API_KEY")
-            .map_err(|_| AppError::InternalServerError("OpenAI API key not set".to_string()))?;
+async fn fetch_image(request: reqwest::RequestBuilder, engine: &str) -> AppResult<GeneratedImage> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| AppError::ExternalServiceError(e.to_string()))?;
+    let response = ensure_success(response, engine).await?;
 
-        let model = request.model.unwrap_or_else(|| "dall-e-3".to_string());
-        let size = request.size.unwrap_or_else(|| "1024x1024".to_string());
-        let n = request.n.unwrap_or(1);
-        let quality = request.quality.unwrap_or_else(|| "standard".to_string());
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|ct| ct.split(';').next().unwrap_or(ct).trim().to_string())
+        .filter(|ct| ct.starts_with("image/"))
+        .unwrap_or_else(|| "image/png".to_string());
+    let data = response
+        .bytes()
+        .await
+        .map_err(|e| AppError::ExternalServiceError(e.to_string()))?
+        .to_vec();
 
-        let response = self
+    Ok(GeneratedImage { data, content_type })
+}
+
+/// OpenAI (and Azure OpenAI) `/images/generations`
+struct OpenAiImages {
+    client: Client,
+    base_url: String,
+    api_key: String,
+    api_version: String,
+}
+
+#[async_trait::async_trait]
+impl ImageEngine for OpenAiImages {
+    async fn generate(&self, params: &ImageParams) -> AppResult<Vec<GeneratedImage>> {
+        let model = params.model_or("dall-e-2");
+        let mut body = json!({
+            "model": model,
+            "prompt": params.prompt,
+            "n": params.n,
+            "size": format!("{}x{}", params.width, params.height),
+        });
+        // gpt-image models always return base64 and reject the parameter
+        if !model.starts_with("gpt-image") {
+            body["response_format"] = json!("b64_json");
+        }
+
+        let mut request = self
             .client
-            .post("https://api.openai.com/v1/images/generations")
-            .header("Authorization", format!("Bearer {}", api_key))
-            .json(&serde_json::json!({
-                "model": model,
-                "prompt": request.prompt,
-                "n": n,
-                "size": size,
-                "quality": quality,
-            }))
+            .post(format!("{}/images/generations", self.base_url))
+            .json(&body);
+        if !self.api_version.is_empty() {
+            request = request.query(&[("api-version", &self.api_version)]);
+        }
+        if !self.api_key.is_empty() {
+            request = request
+                .bearer_auth(&self.api_key)
+                .header("api-key", &self.api_key);
+        }
+
+        let response = request
             .send()
             .await
             .map_err(|e| AppError::ExternalServiceError(e.to_string()))?;
+        let result: Value = ensure_success(response, self.name())
+            .await?
+            .json()
+            .await
+            .map_err(|e| AppError::ExternalServiceError(e.to_string()))?;
+
+        let mut images = Vec::new();
+        for item in result["data"].as_array().into_iter().flatten() {
+            if let Some(b64) = item["b64_json"].as_str() {
+                images.push(decode_base64_image(b64, "image/png")?);
+            } else if let Some(url) = item["url"].as_str() {
+                images.push(fetch_image(self.client.get(url), self.name()).await?);
+            }
+        }
+        Ok(images)
+    }
+
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+}
+
+/// Automatic1111 stable-diffusion-webui `txt2img`
+struct Automatic1111Images {
+    client: Client,
+    base_url: String,
+    /// `user:password` for the webui's `--api-auth`
+    api_auth: String,
+    cfg_scale: Option<f64>,
+    sampler: Option<String>,
+    scheduler: Option<String>,
+}
+
+#[async_trait::async_trait]
+impl ImageEngine for Automatic1111Images {
+    async fn generate(&self, params: &ImageParams) -> AppResult<Vec<GeneratedImage>> {
+        let mut body = json!({
+            "prompt": params.prompt,
+            "batch_size": params.n,
+            "width": params.width,
+            "height": params.height,
+            "steps": params.steps,
+        });
+        if let Some(negative_prompt) = &params.negative_prompt {
+            body["negative_prompt"] = json!(negative_prompt);
+        }
+        if let Some(cfg_scale) = self.cfg_scale {
+            body["cfg_scale"] = json!(cfg_scale);
+        }
+        if let Some(sampler) = self.sampler.as_ref().filter(|s| !s.is_empty()) {
+            body["sampler_name"] = json!(sampler);
+        }
+        if let Some(scheduler) = self.scheduler.as_ref().filter(|s| !s.is_empty()) {
+            body["scheduler"] = json!(scheduler);
+        }
+        if !params.model.is_empty() {
+            body["override_settings"] = json!({ "sd_model_checkpoint": params.model });
+        }
 
-        if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(AppError::ExternalServiceError(format!(
-                "OpenAI image generation failed: {}",
-                error_text
-            )));
+        let mut request = self
+            .client
+            .post(format!("{}/sdapi/v1/txt2img", self.base_url))
+            .json(&body);
+        if !self.api_auth.is_empty() {
+            request = request.header(
+                "Authorization",
+                format!("Basic {}", STANDARD.encode(&self.api_auth)),
+            );
         }
 
-        let result: serde_json::Value = response
+        let response = request
+            .send()
+            .await
+            .map_err(|e| AppError::ExternalServiceError(e.to_string()))?;
+        let result: Value = ensure_success(response, self.name())
+            .await?
             .json()
             .await
             .map_err(|e| AppError::ExternalServiceError(e.to_string()))?;
 
-        let images = result["data"]
+        result["images"]
             .as_array()
-            .ok_or_else(|| AppError::ExternalServiceError("No images in response".to_string()))?
-            .iter()
-            .map(|img| ImageData {
-                url: img["url"].as_str().map(String::from),
-                b64_json: img["b64_json"].as_str().map(String::from),
-            })
-            .collect();
+            .into_iter()
+            .flatten()
+            .filter_map(|img| img.as_str())
+            .map(|b64| decode_base64_image(b64, "image/png"))
+            .collect()
+    }
 
-        Ok(ImageGenerationResponse { images })
+    fn name(&self) -> &'static str {
+        "automatic1111"
     }
+}
 
-    async fn automatic1111_generate(
-        &self,
-        request: ImageGenerationRequest,
-    ) -> AppResult<ImageGenerationResponse> {
-        let base_url = std::env::var("AUTOMATIC1111_URL")
-            .unwrap_or_else(|_| "http://localhost:7860".to_string());
+/// Maps a request parameter onto inputs of a ComfyUI workflow
+///
+/// Mirrors the `COMFYUI_WORKFLOW_NODES` entries, e.g.
+/// `{"type": "prompt", "key": "text", "node_ids": ["6"]}`. An empty key uses
+/// the conventional input name for the type.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WorkflowNode {
+    #[serde(rename = "type")]
+    pub node_type: String,
+    #[serde(default)]
+    pub key: String,
+    #[serde(default)]
+    pub node_ids: Vec<String>,
+}
 
-        let n = request.n.unwrap_or(1);
+impl WorkflowNode {
+    fn input_key(&self) -> &str {
+        if !self.key.is_empty() {
+            return &self.key;
+        }
+        match self.node_type.as_str() {
+            "prompt" | "negative_prompt" => "text",
+            "model" => "ckpt_name",
+            "n" => "batch_size",
+            other => other,
+        }
+    }
+}
+
+/// Basic txt2img workflow, used when `COMFYUI_WORKFLOW` is empty
+const COMFYUI_DEFAULT_WORKFLOW: &str = r#"{
+  "3": {"class_type": "KSampler", "inputs": {"seed": 0, "steps": 20, "cfg": 8, "sampler_name": "euler", "scheduler": "normal", "denoise": 1, "model": ["4", 0], "positive": ["6", 0], "negative": ["7", 0], "latent_image": ["5", 0]}},
+  "4": {"class_type": "CheckpointLoaderSimple", "inputs": {"ckpt_name": "model.safetensors"}},
+  "5": {"class_type": "EmptyLatentImage", "inputs": {"width": 512, "height": 512, "batch_size": 1}},
+  "6": {"class_type": "CLIPTextEncode", "inputs": {"text": "", "clip": ["4", 1]}},
+  "7": {"class_type": "CLIPTextEncode", "inputs": {"text": "", "clip": ["4", 1]}},
+  "8": {"class_type": "VAEDecode", "inputs": {"samples": ["3", 0], "vae": ["4", 2]}},
+  "9": {"class_type": "SaveImage", "inputs": {"filename_prefix": "ComfyUI", "images": ["8", 0]}}
+}"#;
 
-        // Parse size
-        let (width, height) = if let Some(size) = request.size {
-            let parts: Vec<&str> = size.split('x').collect();
-            if parts.len() == 2 {
-                (
-                    parts[0].parse::<i32>().unwrap_or(512),
-                    parts[1].parse::<i32>().unwrap_or(512),
-                )
-            } else {
-                (512, 512)
+/// Node mapping for the default workflow, used when `COMFYUI_WORKFLOW_NODES` is empty
+fn default_workflow_nodes() -> Vec<WorkflowNode> {
+    [
+        ("prompt", "6"),
+        ("negative_prompt", "7"),
+        ("model", "4"),
+        ("width", "5"),
+        ("height", "5"),
+        ("n", "5"),
+        ("steps", "3"),
+        ("seed", "3"),
+    ]
+    .into_iter()
+    .map(|(node_type, id)| WorkflowNode {
+        node_type: node_type.to_string(),
+        key: String::new(),
+        node_ids: vec![id.to_string()],
+    })
+    .collect()
+}
+
+/// Write the request parameters into the workflow inputs named by `nodes`
+pub fn apply_workflow_nodes(
+    workflow: &mut Value,
+    nodes: &[WorkflowNode],
+    params: &ImageParams,
+    seed: u64,
+) -> AppResult<()> {
+    for node in nodes {
+        let value = match node.node_type.as_str() {
+            "prompt" => json!(params.prompt),
+            "negative_prompt" => json!(params.negative_prompt.as_deref().unwrap_or("")),
+            // Keep the workflow's own checkpoint unless a model was requested
+            "model" if params.model.is_empty() => continue,
+            "model" => json!(params.model),
+            "width" => json!(params.width),
+            "height" => json!(params.height),
+            "n" => json!(params.n),
+            "steps" => json!(params.steps),
+            "seed" => json!(seed),
+            other => {
+                warn!("Ignoring unknown ComfyUI workflow node type: {}", other);
+                continue;
             }
+        };
+
+        for node_id in &node.node_ids {
+            let inputs = workflow
+                .get_mut(node_id)
+                .and_then(|n| n.get_mut("inputs"))
+                .and_then(|i| i.as_object_mut())
+                .ok_or_else(|| {
+                    AppError::BadRequest(format!(
+                        "ComfyUI workflow has no node {} with inputs",
+                        node_id
+                    ))
+                })?;
+            inputs.insert(node.input_key().to_string(), value.clone());
+        }
+    }
+    Ok(())
+}
+
+/// State of a queued prompt as reported by `/history/{prompt_id}`
+#[derive(Debug, PartialEq)]
+enum ComfyUiStatus {
+    Pending,
+    Failed(String),
+    /// `(filename, subfolder, type)` of each saved image
+    Done(Vec<(String, String, String)>),
+}
+
+fn comfyui_status(history: &Value, prompt_id: &str) -> ComfyUiStatus {
+    let Some(entry) = history.get(prompt_id) else {
+        return ComfyUiStatus::Pending;
+    };
+
+    let status = &entry["status"];
+    if status["status_str"].as_str() == Some("error") {
+        let message = status["messages"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|m| m[0].as_str() == Some("execution_error"))
+            .filter_map(|m| m[1]["exception_message"].as_str())
+            .collect::<Vec<_>>()
+            .join("; ");
+        return ComfyUiStatus::Failed(if message.is_empty() {
+            "execution error".to_string()
         } else {
-            (512, 512)
+            message
+        });
+    }
+
+    let outputs = entry["outputs"].as_object();
+    let completed = status["completed"].as_bool().unwrap_or(false);
+    if !completed && outputs.is_none_or(|o| o.is_empty()) {
+        return ComfyUiStatus::Pending;
+    }
+
+    let images = outputs
+        .into_iter()
+        .flat_map(|o| o.values())
+        .flat_map(|node| node["images"].as_array().into_iter().flatten())
+        // Preview nodes write to the temp folder; only keep saved images
+        .filter(|img| img["type"].as_str() != Some("temp"))
+        .filter_map(|img| {
+            Some((
+                img["filename"].as_str()?.to_string(),
+                img["subfolder"].as_str().unwrap_or("").to_string(),
+                img["type"].as_str().unwrap_or("output").to_string(),
+            ))
+        })
+        .collect();
+    ComfyUiStatus::Done(images)
+}
+
+/// ComfyUI: queue the workflow, poll its history, then download the outputs
+struct ComfyUiImages {
+    client: Client,
+    base_url: String,
+    api_key: String,
+    workflow: Value,
+    nodes: Vec<WorkflowNode>,
+}
+
+impl ComfyUiImages {
+    fn from_config(config: &Config) -> AppResult<Self> {
+        if config.comfyui_base_url.is_empty() {
+            return Err(AppError::BadRequest(
+                "COMFYUI_BASE_URL is required for the comfyui image engine".to_string(),
+            ));
+        }
+
+        let workflow_json = if config.comfyui_workflow.trim().is_empty() {
+            COMFYUI_DEFAULT_WORKFLOW
+        } else {
+            config.comfyui_workflow.as_str()
         };
+        let workflow: Value = serde_json::from_str(workflow_json)
+            .map_err(|e| AppError::BadRequest(format!("Invalid COMFYUI_WORKFLOW: {}", e)))?;
+        if !workflow.is_object() {
+            return Err(AppError::BadRequest(
+                "COMFYUI_WORKFLOW must be a workflow in API format".to_string(),
+            ));
+        }
 
-        let response = self
+        let nodes: Vec<WorkflowNode> =
+            serde_json::from_value(config.comfyui_workflow_nodes.clone()).map_err(|e| {
+                AppError::BadRequest(format!("Invalid COMFYUI_WORKFLOW_NODES: {}", e))
+            })?;
+        let nodes = if nodes.is_empty() {
+            default_workflow_nodes()
+        } else {
+            nodes
+        };
+
+        Ok(ComfyUiImages {
+            client: Client::new(),
+            base_url: config.comfyui_base_url.trim_end_matches('/').to_string(),
+            api_key: config.comfyui_api_key.clone(),
+            workflow,
+            nodes,
+        })
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self
             .client
-            .post(format!("{}/sdapi/v1/txt2img", base_url))
-            .json(&serde_json::json!({
-                "prompt": request.prompt,
-                "negative_prompt": "",
-                "batch_size": n,
-                "width": width,
-                "height": height,
-                "steps": 20,
-                "cfg_scale": 7,
-                "sampler_name": "Euler a",
+            .request(method, format!("{}{}", self.base_url, path));
+        if self.api_key.is_empty() {
+            request
+        } else {
+            request.bearer_auth(&self.api_key)
+        }
+    }
+
+    async fn queue_prompt(&self, workflow: &Value) -> AppResult<String> {
+        let response = self
+            .request(reqwest::Method::POST, "/prompt")
+            .json(&json!({
+                "prompt": workflow,
+                "client_id": uuid::Uuid::new_v4().to_string(),
             }))
             .send()
             .await
             .map_err(|e| AppError::ExternalServiceError(e.to_string()))?;
-
-        if !response.status().is_success() {
-            return Err(AppError::ExternalServiceError(format!(
-                "Automatic1111 generation failed: {}",
-                response.status()
-            )));
-        }
-
-        let result: serde_json::Value = response
+        let result: Value = ensure_success(response, self.name())
+            .await?
             .json()
             .await
             .map_err(|e| AppError::ExternalServiceError(e.to_string()))?;
 
-        let images = result["images"]
-            .as_array()
-            .ok_or_else(|| AppError::ExternalServiceError("No images in response".to_string()))?
+        result["prompt_id"]
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| {
+                AppError::ExternalServiceError(format!(
+                    "ComfyUI did not queue the prompt: {}",
+                    result
+                ))
+            })
+    }
+
+    async fn wait_for_outputs(&self, prompt_id: &str) -> AppResult<Vec<(String, String, String)>> {
+        let started = Instant::now();
+        loop {
+            let response = self
+                .request(reqwest::Method::GET, &format!("/history/{}", prompt_id))
+                .send()
+                .await
+                .map_err(|e| AppError::ExternalServiceError(e.to_string()))?;
+            let history: Value = ensure_success(response, self.name())
+                .await?
+                .json()
+                .await
+                .map_err(|e| AppError::ExternalServiceError(e.to_string()))?;
+
+            match comfyui_status(&history, prompt_id) {
+                ComfyUiStatus::Done(images) => return Ok(images),
+                ComfyUiStatus::Failed(message) => {
+                    return Err(AppError::ExternalServiceError(format!(
+                        "ComfyUI prompt {} failed: {}",
+                        prompt_id, message
+                    )))
+                }
+                ComfyUiStatus::Pending => {}
+            }
+
+            if started.elapsed() >= COMFYUI_TIMEOUT {
+                return Err(AppError::Timeout(format!(
+                    "ComfyUI prompt {} did not finish within {}s",
+                    prompt_id,
+                    COMFYUI_TIMEOUT.as_secs()
+                )));
+            }
+            tokio::time::sleep(COMFYUI_POLL_INTERVAL).await;
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ImageEngine for ComfyUiImages {
+    async fn generate(&self, params: &ImageParams) -> AppResult<Vec<GeneratedImage>> {
+        let mut workflow = self.workflow.clone();
+        apply_workflow_nodes(
+            &mut workflow,
+            &self.nodes,
+            params,
+            rand::random::<u32>() as u64,
+        )?;
+
+        let prompt_id = self.queue_prompt(&workflow).await?;
+        info!("Queued ComfyUI prompt {}", prompt_id);
+
+        let outputs = self.wait_for_outputs(&prompt_id).await?;
+        let mut images = Vec::with_capacity(outputs.len());
+        for (filename, subfolder, folder_type) in outputs {
+            let request = self.request(reqwest::Method::GET, "/view").query(&[
+                ("filename", filename),
+                ("subfolder", subfolder),
+                ("type", folder_type),
+            ]);
+            images.push(fetch_image(request, self.name()).await?);
+        }
+        Ok(images)
+    }
+
+    fn name(&self) -> &'static str {
+        "comfyui"
+    }
+}
+
+/// Google Imagen (`:predict`) and Gemini image models (`:generateContent`)
+struct GeminiImages {
+    client: Client,
+    base_url: String,
+    api_key: String,
+}
+
+/// Closest aspect ratio Imagen accepts for the requested size
+fn imagen_aspect_ratio(width: u32, height: u32) -> &'static str {
+    const RATIOS: [(&str, f64); 5] = [
+        ("1:1", 1.0),
+        ("3:4", 0.75),
+        ("4:3", 4.0 / 3.0),
+        ("9:16", 9.0 / 16.0),
+        ("16:9", 16.0 / 9.0),
+    ];
+    let target = (width as f64 / height as f64).ln();
+    RATIOS
+        .iter()
+        .min_by(|a, b| {
+            let da = (a.1.ln() - target).abs();
+            let db = (b.1.ln() - target).abs();
+            da.total_cmp(&db)
+        })
+        .map(|r| r.0)
+        .unwrap_or("1:1")
+}
+
+fn gemini_images(result: &Value) -> AppResult<Vec<GeneratedImage>> {
+    // Imagen :predict
+    if let Some(predictions) = result["predictions"].as_array() {
+        return predictions
             .iter()
-            .map(|img| ImageData {
-                url: None,
-                b64_json: img.as_str().map(String::from),
+            .filter_map(|p| {
+                let data = p["bytesBase64Encoded"].as_str()?;
+                Some(decode_base64_image(
+                    data,
+                    p["mimeType"].as_str().unwrap_or("image/png"),
+                ))
             })
             .collect();
-
-        Ok(ImageGenerationResponse { images })
     }
 
-    async fn comfyui_generate(
-        &self,
-        request: ImageGenerationRequest,
-    ) -> AppResult<ImageGenerationResponse> {
-        let base_url =
-            std::env::var("COMFYUI_URL").unwrap_or_else(|_| "http://localhost:8188".to_string());
+    // Gemini :generateContent returns images as inline parts next to text
+    result["candidates"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|c| c["content"]["parts"].as_array().into_iter().flatten())
+        .filter_map(|part| {
+            let inline = part.get("inlineData").or_else(|| part.get("inline_data"))?;
+            let data = inline["data"].as_str()?;
+            let mime_type = inline
+                .get("mimeType")
+                .or_else(|| inline.get("mime_type"))
+                .and_then(|m| m.as_str())
+                .unwrap_or("image/png");
+            Some(decode_base64_image(data, mime_type))
+        })
+        .collect()
+}
 
-        // ComfyUI requires a workflow JSON
-        // This is a simplified example - in practice you'd need a full workflow
-        let workflow = serde_json::json!({
-            "prompt": {
-                "1": {
-                    "inputs": {
-                        "text": request.prompt,
-                    },
-                    "class_type": "CLIPTextEncode",
-                },
-                "2": {
-                    "inputs": {
-                        "samples": ["3", 0],
-                        "vae": ["4", 0],
-                    },
-                    "class_type": "VAEDecode",
-                },
-                "3": {
-                    "inputs": {
-                        "seed": 12345,
-                        "steps": 20,
-                        "cfg": 7.0,
-                        "sampler_name": "euler",
-                        "scheduler": "normal",
-                        "positive": ["1", 0],
-                        "model": ["4", 0],
-                    },
-                    "class_type": "KSampler",
-                },
-                "4": {
-                    "inputs": {
-                        "ckpt_name": "model.safetensors",
+#[async_trait::async_trait]
+impl ImageEngine for GeminiImages {
+    async fn generate(&self, params: &ImageParams) -> AppResult<Vec<GeneratedImage>> {
+        let model = params.model_or("imagen-3.0-generate-002");
+        let (url, body) = if model.starts_with("imagen") {
+            (
+                format!("{}/models/{}:predict", self.base_url, model),
+                json!({
+                    "instances": [{ "prompt": params.prompt }],
+                    "parameters": {
+                        "sampleCount": params.n,
+                        "aspectRatio": imagen_aspect_ratio(params.width, params.height),
                     },
-                    "class_type": "CheckpointLoaderSimple",
-                },
-            }
-        });
+                }),
+            )
+        } else {
+            (
+                format!("{}/models/{}:generateContent", self.base_url, model),
+                json!({
+                    "contents": [{ "parts": [{ "text": params.prompt }] }],
+                    "generationConfig": { "responseModalities": ["TEXT", "IMAGE"] },
+                }),
+            )
+        };
 
         let response = self
             .client
-            .post(format!("{}/prompt", base_url))
-            .json(&workflow)
+            .post(url)
+            .header("x-goog-api-key", &self.api_key)
+            .json(&body)
             .send()
             .await
             .map_err(|e| AppError::ExternalServiceError(e.to_string()))?;
+        let result: Value = ensure_success(response, self.name())
+            .await?
+            .json()
+            .await
+            .map_err(|e| AppError::ExternalServiceError(e.to_string()))?;
+
+        gemini_images(&result)
+    }
+
+    fn name(&self) -> &'static str {
+        "gemini"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        if !response.status().is_success() {
-            return Err(AppError::ExternalServiceError(format!(
-                "ComfyUI generation failed: {}",
-                response.status()
-            )));
+    fn params() -> ImageParams {
+        ImageParams {
+            prompt: "a red fox".to_string(),
+            negative_prompt: None,
+            model: String::new(),
+            width: 768,
+            height: 512,
+            n: 2,
+            steps: 30,
         }
+    }
 
-        // ComfyUI workflow is more complex and requires polling for results
-        // This is a simplified response
-        Ok(ImageGenerationResponse {
-            images: vec![ImageData {
-                url: Some(format!("{}/view", base_url)),
-                b64_json: None,
-            }],
-        })
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1024x768").unwrap(), (1024, 768));
+        assert_eq!(parse_size(" 512 X 512 ").unwrap(), (512, 512));
+        assert!(parse_size("512").is_err());
+        assert!(parse_size("0x512").is_err());
+        assert!(parse_size("axb").is_err());
+    }
+
+    #[test]
+    fn test_resolve_applies_config_defaults() {
+        let mut config = Config::from_env().unwrap();
+        config.image_size = "640x480".to_string();
+        config.image_steps = 25;
+        config.image_generation_model = "sdxl".to_string();
+
+        let params = ImageParams::resolve(
+            &config,
+            ImageGenerationRequest {
+                prompt: "cat".to_string(),
+                n: Some(50),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!((params.width, params.height), (640, 480));
+        assert_eq!(params.steps, 25);
+        assert_eq!(params.model, "sdxl");
+        assert_eq!(params.n, MAX_IMAGES_PER_REQUEST);
+
+        assert!(ImageParams::resolve(&config, ImageGenerationRequest::default()).is_err());
+    }
+
+    #[test]
+    fn test_apply_default_workflow_nodes() {
+        let mut workflow: Value = serde_json::from_str(COMFYUI_DEFAULT_WORKFLOW).unwrap();
+        let mut params = params();
+        params.negative_prompt = Some("blurry".to_string());
+
+        apply_workflow_nodes(&mut workflow, &default_workflow_nodes(), &params, 42).unwrap();
+
+        assert_eq!(workflow["6"]["inputs"]["text"], "a red fox");
+        assert_eq!(workflow["7"]["inputs"]["text"], "blurry");
+        assert_eq!(workflow["5"]["inputs"]["width"], 768);
+        assert_eq!(workflow["5"]["inputs"]["height"], 512);
+        assert_eq!(workflow["5"]["inputs"]["batch_size"], 2);
+        assert_eq!(workflow["3"]["inputs"]["steps"], 30);
+        assert_eq!(workflow["3"]["inputs"]["seed"], 42);
+        // No model requested keeps the workflow's checkpoint
+        assert_eq!(workflow["4"]["inputs"]["ckpt_name"], "model.safetensors");
+        // Links between nodes are left alone
+        assert_eq!(workflow["6"]["inputs"]["clip"], json!(["4", 1]));
+    }
+
+    #[test]
+    fn test_apply_custom_workflow_nodes() {
+        let mut workflow = json!({
+            "10": {"inputs": {"prompt": ""}},
+            "11": {"inputs": {"unet_name": "flux.safetensors"}},
+        });
+        let nodes: Vec<WorkflowNode> = serde_json::from_value(json!([
+            {"type": "prompt", "key": "prompt", "node_ids": ["10"]},
+            {"type": "model", "key": "unet_name", "node_ids": ["11"]},
+        ]))
+        .unwrap();
+        let mut params = params();
+        params.model = "flux-dev.safetensors".to_string();
+
+        apply_workflow_nodes(&mut workflow, &nodes, &params, 1).unwrap();
+        assert_eq!(workflow["10"]["inputs"]["prompt"], "a red fox");
+        assert_eq!(
+            workflow["11"]["inputs"]["unet_name"],
+            "flux-dev.safetensors"
+        );
+
+        let missing: Vec<WorkflowNode> = serde_json::from_value(json!([
+            {"type": "prompt", "node_ids": ["99"]},
+        ]))
+        .unwrap();
+        assert!(apply_workflow_nodes(&mut workflow, &missing, &params, 1).is_err());
+    }
+
+    #[test]
+    fn test_comfyui_status() {
+        assert_eq!(comfyui_status(&json!({}), "p1"), ComfyUiStatus::Pending);
+
+        let done = json!({"p1": {
+            "status": {"status_str": "success", "completed": true},
+            "outputs": {
+                "9": {"images": [{"filename": "out_0001.png", "subfolder": "", "type": "output"}]},
+                "12": {"images": [{"filename": "preview.png", "subfolder": "", "type": "temp"}]},
+            },
+        }});
+        assert_eq!(
+            comfyui_status(&done, "p1"),
+            ComfyUiStatus::Done(vec![(
+                "out_0001.png".to_string(),
+                String::new(),
+                "output".to_string()
+            )])
+        );
+
+        let failed = json!({"p1": {
+            "status": {
+                "status_str": "error",
+                "completed": false,
+                "messages": [["execution_error", {"exception_message": "out of memory"}]],
+            },
+            "outputs": {},
+        }});
+        assert_eq!(
+            comfyui_status(&failed, "p1"),
+            ComfyUiStatus::Failed("out of memory".to_string())
+        );
+    }
+
+    #[test]
+    fn test_gemini_response_parsing() {
+        let png = STANDARD.encode(b"png-bytes");
+
+        let imagen = json!({"predictions": [{"bytesBase64Encoded": png, "mimeType": "image/png"}]});
+        let images = gemini_images(&imagen).unwrap();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].data, b"png-bytes");
+
+        let gemini = json!({"candidates": [{"content": {"parts": [
+            {"text": "Here is your image"},
+            {"inlineData": {"mimeType": "image/jpeg", "data": png}},
+        ]}}]});
+        let images = gemini_images(&gemini).unwrap();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].content_type, "image/jpeg");
+
+        assert_eq!(imagen_aspect_ratio(1024, 1024), "1:1");
+        assert_eq!(imagen_aspect_ratio(1920, 1080), "16:9");
+        assert_eq!(imagen_aspect_ratio(768, 1024), "3:4");
+    }
+
+    #[test]
+    fn test_decode_data_url() {
+        let image = decode_base64_image("data:image/webp;base64,aGk=", "image/png").unwrap();
+        assert_eq!(image.content_type, "image/webp");
+        assert_eq!(image.data, b"hi");
+        assert_eq!(image_extension(&image.content_type), "webp");
     }
 }
@@ -1,7 +1,21 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use ldap3::{ldap_escape, LdapConnAsync, LdapConnSettings, Scope, SearchEntry};
 use serde::{Deserialize, Serialize};
-use tracing::info;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::models::group::{GroupForm, GroupUpdateForm};
+use crate::services::group::GroupService;
+use crate::services::user::UserService;
 
-use crate::error::AppResult;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_GROUP_FILTER: &str =
+    "(|(objectClass=groupOfNames)(objectClass=groupOfUniqueNames)(objectClass=posixGroup))";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LdapConfig {
@@ -14,6 +28,11 @@ pub struct LdapConfig {
     pub user_attributes: LdapUserAttributes,
     pub enable_tls: bool,
     pub verify_cert: bool,
+    #[serde(default)]
+    pub ca_cert_file: Option<String>,
+    /// LDAP group names (CN) whose members get the admin role
+    #[serde(default)]
+    pub admin_groups: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,156 +63,598 @@ pub struct LdapGroup {
     pub members: Vec<String>,
 }
 
-#[allow(dead_code)]
-pub struct LdapClient {
+impl LdapConfig {
+    /// Build the client configuration from the `LDAP_*` settings
+    pub fn from_config(config: &Config) -> Self {
+        let (scheme, default_port) = if config.ldap_use_tls {
+            ("ldaps", 636)
+        } else {
+            ("ldap", 389)
+        };
+
+        if config
+            .ldap_ciphers
+            .as_deref()
+            .is_some_and(|c| !c.is_empty())
+        {
+            warn!("LDAP_CIPHERS is not supported by the native TLS backend and is ignored");
+        }
+
+        Self {
+            server_url: format!(
+                "{}://{}:{}",
+                scheme,
+                config.ldap_server_host,
+                config.ldap_server_port.unwrap_or(default_port)
+            ),
+            bind_dn: config.ldap_app_dn.clone(),
+            bind_password: config.ldap_app_password.clone(),
+            search_base: config.ldap_search_base.clone(),
+            user_filter: format!(
+                "(&({}={}){})",
+                config.ldap_attribute_for_username, "{username}", config.ldap_search_filters
+            ),
+            group_filter: None,
+            user_attributes: LdapUserAttributes {
+                username: config.ldap_attribute_for_username.clone(),
+                email: config.ldap_attribute_for_mail.clone(),
+                display_name: "cn".to_string(),
+                first_name: Some("givenName".to_string()),
+                last_name: Some("sn".to_string()),
+                member_of: Some(config.ldap_attribute_for_groups.clone()),
+            },
+            enable_tls: config.ldap_use_tls,
+            verify_cert: config.ldap_validate_cert,
+            ca_cert_file: config.ldap_ca_cert_file.clone(),
+            admin_groups: config.ldap_admin_groups.clone(),
+        }
+    }
+
+    /// Attributes to request for user entries
+    fn user_attribute_names(&self) -> Vec<String> {
+        let attrs = &self.user_attributes;
+        [
+            Some(&attrs.username),
+            Some(&attrs.email),
+            Some(&attrs.display_name),
+            attrs.first_name.as_ref(),
+            attrs.last_name.as_ref(),
+            attrs.member_of.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect()
+    }
+}
+
+/// An entry returned by a directory search
+#[derive(Debug, Clone, Default)]
+pub struct LdapEntry {
+    pub dn: String,
+    pub attrs: HashMap<String, Vec<String>>,
+}
+
+impl LdapEntry {
+    /// All values of an attribute (attribute names are case-insensitive)
+    pub fn values(&self, name: &str) -> &[String] {
+        self.attrs
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, values)| values.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// First non-empty value of an attribute
+    pub fn first(&self, name: &str) -> Option<String> {
+        self.values(name)
+            .iter()
+            .find(|v| !v.trim().is_empty())
+            .cloned()
+    }
+}
+
+impl From<SearchEntry> for LdapEntry {
+    fn from(entry: SearchEntry) -> Self {
+        Self {
+            dn: entry.dn,
+            attrs: entry.attrs,
+        }
+    }
+}
+
+/// An open connection to the directory
+#[async_trait::async_trait]
+pub trait LdapSession: Send {
+    /// Simple bind; fails on invalid credentials
+    async fn bind(&mut self, dn: &str, password: &str) -> AppResult<()>;
+
+    /// Subtree search returning the requested attributes
+    async fn search(
+        &mut self,
+        base: &str,
+        filter: &str,
+        attrs: &[String],
+    ) -> AppResult<Vec<LdapEntry>>;
+
+    async fn unbind(&mut self) -> AppResult<()>;
+}
+
+/// Opens directory sessions, so tests can swap in an in-memory directory
+#[async_trait::async_trait]
+pub trait LdapConnector: Send + Sync {
+    async fn connect(&self) -> AppResult<Box<dyn LdapSession>>;
+}
+
+/// Network connector backed by `ldap3`
+pub struct Ldap3Connector {
     config: LdapConfig,
 }
 
-#[allow(dead_code)]
-impl LdapClient {
+impl Ldap3Connector {
     pub fn new(config: LdapConfig) -> Self {
         Self { config }
     }
 
-    /// Authenticate user with LDAP
-    pub async fn authenticate(&self, username: &str, _password: &str) -> AppResult<LdapUser> {
-        info!("Authenticating user via LDAP: {}", username);
+    fn settings(&self) -> AppResult<LdapConnSettings> {
+        let mut settings = LdapConnSettings::new()
+            .set_conn_timeout(CONNECT_TIMEOUT)
+            .set_no_tls_verify(!self.config.verify_cert);
 
-        // In production, use ldap3 crate for actual LDAP operations
-        // This is a simplified implementation showing the structure
+        if let Some(path) = self
+            .config
+            .ca_cert_file
+            .as_deref()
+            .filter(|p| !p.is_empty())
+        {
+            let pem = std::fs::read(path).map_err(|e| {
+                AppError::InternalServerError(format!(
+                    "Failed to read LDAP CA certificate {}: {}",
+                    path, e
+                ))
+            })?;
+            let cert = native_tls::Certificate::from_pem(&pem).map_err(|e| {
+                AppError::InternalServerError(format!("Invalid LDAP CA certificate: {}", e))
+            })?;
+            let connector = native_tls::TlsConnector::builder()
+                .add_root_certificate(cert)
+                .danger_accept_invalid_certs(!self.config.verify_cert)
+                .danger_accept_invalid_hostnames(!self.config.verify_cert)
+                .build()
+                .map_err(|e| {
+                    AppError::InternalServerError(format!("Failed to configure LDAP TLS: {}", e))
+                })?;
+            settings = settings.set_connector(connector);
+        }
 
-        // 1. Connect to LDAP server
-        // let mut ldap = LdapConnAsync::new(&self.config.server_url).await?;
+        Ok(settings)
+    }
+}
 
-        // 2. Bind with service account
-        // ldap.simple_bind(&self.config.bind_dn, &self.config.bind_password).await?;
+#[async_trait::async_trait]
+impl LdapConnector for Ldap3Connector {
+    async fn connect(&self) -> AppResult<Box<dyn LdapSession>> {
+        let (conn, ldap) = LdapConnAsync::with_settings(self.settings()?, &self.config.server_url)
+            .await
+            .map_err(|e| {
+                AppError::ExternalServiceError(format!(
+                    "Failed to connect to LDAP server {}: {}",
+                    self.config.server_url, e
+                ))
+            })?;
+        ldap3::drive!(conn);
+
+        Ok(Box::new(Ldap3Session { ldap }))
+    }
+}
 
-        // 3. Search for user
-        let user_filter = self.config.user_filter.replace("{username}", username);
-        info!("LDAP search filter: {}", user_filter);
+struct Ldap3Session {
+    ldap: ldap3::Ldap,
+}
 
-        // let (rs, _res) = ldap
-        //     .search(&self.config.search_base, Scope::Subtree, &user_filter, vec!["*"])
-        //     .await?
-        //     .success()?;
+#[async_trait::async_trait]
+impl LdapSession for Ldap3Session {
+    async fn bind(&mut self, dn: &str, password: &str) -> AppResult<()> {
+        self.ldap
+            .simple_bind(dn, password)
+            .await
+            .and_then(|res| res.success())
+            .map_err(|e| AppError::Auth(format!("LDAP bind failed: {}", e)))?;
+        Ok(())
+    }
 
-        // if rs.is_empty() {
-        //     return Err(AppError::Auth("User not found in LDAP".to_string()));
-        // }
+    async fn search(
+        &mut self,
+        base: &str,
+        filter: &str,
+        attrs: &[String],
+    ) -> AppResult<Vec<LdapEntry>> {
+        let (entries, _) = self
+            .ldap
+            .search(base, Scope::Subtree, filter, attrs.to_vec())
+            .await
+            .and_then(|res| res.success())
+            .map_err(|e| AppError::ExternalServiceError(format!("LDAP search failed: {}", e)))?;
+
+        Ok(entries
+            .into_iter()
+            .map(|entry| SearchEntry::construct(entry).into())
+            .collect())
+    }
 
-        // let entry = &rs[0];
-        // let user_dn = entry.dn.clone();
+    async fn unbind(&mut self) -> AppResult<()> {
+        self.ldap
+            .unbind()
+            .await
+            .map_err(|e| AppError::ExternalServiceError(format!("LDAP unbind failed: {}", e)))
+    }
+}
 
-        // 4. Try to bind with user credentials
-        // let mut user_ldap = LdapConnAsync::new(&self.config.server_url).await?;
-        // user_ldap.simple_bind(&user_dn, password).await
-        //     .map_err(|_| AppError::InvalidCredentials)?;
+pub struct LdapClient {
+    config: LdapConfig,
+    connector: Arc<dyn LdapConnector>,
+}
 
-        // 5. Get user attributes
-        // let username = self.get_attribute(entry, &self.config.user_attributes.username)?;
-        // let email = self.get_attribute(entry, &self.config.user_attributes.email)?;
-        // let display_name = self.get_attribute(entry, &self.config.user_attributes.display_name)?;
+impl LdapClient {
+    pub fn new(config: LdapConfig) -> Self {
+        let connector = Arc::new(Ldap3Connector::new(config.clone()));
+        Self::with_connector(config, connector)
+    }
+
+    pub fn with_connector(config: LdapConfig, connector: Arc<dyn LdapConnector>) -> Self {
+        Self { config, connector }
+    }
+
+    /// Connect and bind with the service account (anonymous when no bind DN is set)
+    async fn service_session(&self) -> AppResult<Box<dyn LdapSession>> {
+        let mut session = self.connector.connect().await?;
+        if !self.config.bind_dn.is_empty() {
+            session
+                .bind(&self.config.bind_dn, &self.config.bind_password)
+                .await?;
+        }
+        Ok(session)
+    }
+
+    /// Authenticate user with LDAP
+    ///
+    /// Looks the user up with the service account, then re-binds as the
+    /// user's DN to verify the password.
+    pub async fn authenticate(&self, username: &str, password: &str) -> AppResult<LdapUser> {
+        info!("Authenticating user via LDAP: {}", username);
+
+        // An empty password would be an unauthenticated bind, which most
+        // servers accept without checking anything
+        if password.is_empty() {
+            return Err(AppError::InvalidCredentials);
+        }
+
+        let mut session = self.service_session().await?;
+
+        let user_filter = self
+            .config
+            .user_filter
+            .replace("{username}", &ldap_escape(username));
+        let mut entries = session
+            .search(
+                &self.config.search_base,
+                &user_filter,
+                &self.config.user_attribute_names(),
+            )
+            .await?;
+
+        let entry = match entries.len() {
+            1 => entries.remove(0),
+            0 => return Err(AppError::Auth("User not found in LDAP".to_string())),
+            _ => {
+                return Err(AppError::Auth(
+                    "LDAP search returned more than one user".to_string(),
+                ))
+            }
+        };
+
+        session
+            .bind(&entry.dn, password)
+            .await
+            .map_err(|_| AppError::InvalidCredentials)?;
+
+        let mut user = self.entry_to_user(&entry)?;
+        if user.groups.is_empty() {
+            user.groups = self
+                .search_member_groups(session.as_mut(), &user.dn, &user.username)
+                .await?;
+        }
 
-        // 6. Get user groups
-        let groups = self.get_user_groups(username).await?;
+        let _ = session.unbind().await;
 
         info!("Successfully authenticated user via LDAP: {}", username);
+        Ok(user)
+    }
+
+    /// Map a directory entry onto an `LdapUser`
+    fn entry_to_user(&self, entry: &LdapEntry) -> AppResult<LdapUser> {
+        let attrs = &self.config.user_attributes;
+
+        let username = entry
+            .first(&attrs.username)
+            .ok_or_else(|| AppError::Auth("LDAP user has no username attribute".to_string()))?;
+        let email = entry
+            .first(&attrs.email)
+            .filter(|e| e.contains('@'))
+            .ok_or_else(|| {
+                AppError::Auth("User does not have a valid email address in LDAP".to_string())
+            })?
+            .to_lowercase();
+        let first_name = attrs.first_name.as_deref().and_then(|a| entry.first(a));
+        let last_name = attrs.last_name.as_deref().and_then(|a| entry.first(a));
+        let display_name = entry
+            .first(&attrs.display_name)
+            .unwrap_or_else(|| username.clone());
+        let groups = attrs
+            .member_of
+            .as_deref()
+            .map(|a| entry.values(a).to_vec())
+            .unwrap_or_default();
 
-        // Return mock user for now
         Ok(LdapUser {
-            username: username.to_string(),
-            email: format!("{}@example.com", username),
-            display_name: username.to_string(),
-            first_name: Some(username.to_string()),
-            last_name: None,
+            username,
+            email,
+            display_name,
+            first_name,
+            last_name,
             groups,
-            dn: format!("uid={},{}", username, self.config.search_base),
+            dn: entry.dn.clone(),
         })
     }
 
+    /// Group DNs that list the user as a member
+    ///
+    /// Used when the server does not maintain a `memberOf` attribute.
+    async fn search_member_groups(
+        &self,
+        session: &mut dyn LdapSession,
+        user_dn: &str,
+        username: &str,
+    ) -> AppResult<Vec<String>> {
+        let filter = format!(
+            "(&{}(|(member={dn})(uniqueMember={dn})(memberUid={uid})))",
+            self.group_filter(),
+            dn = ldap_escape(user_dn),
+            uid = ldap_escape(username),
+        );
+        let groups = session
+            .search(&self.config.search_base, &filter, &["cn".to_string()])
+            .await?;
+
+        Ok(groups.into_iter().map(|g| g.dn).collect())
+    }
+
+    fn group_filter(&self) -> &str {
+        self.config
+            .group_filter
+            .as_deref()
+            .unwrap_or(DEFAULT_GROUP_FILTER)
+    }
+
     /// Get user's LDAP groups
+    #[allow(dead_code)]
     pub async fn get_user_groups(&self, username: &str) -> AppResult<Vec<String>> {
         info!("Fetching LDAP groups for user: {}", username);
 
-        // In production:
-        // 1. Search for user to get DN
-        // 2. Search for groups where member = user DN
-        // 3. Parse group names
+        let mut session = self.service_session().await?;
+        let user_filter = self
+            .config
+            .user_filter
+            .replace("{username}", &ldap_escape(username));
+        let entries = session
+            .search(
+                &self.config.search_base,
+                &user_filter,
+                &self.config.user_attribute_names(),
+            )
+            .await?;
+        let entry = entries
+            .first()
+            .ok_or_else(|| AppError::NotFound("User not found in LDAP".to_string()))?;
+
+        let mut groups = self
+            .config
+            .user_attributes
+            .member_of
+            .as_deref()
+            .map(|a| entry.values(a).to_vec())
+            .unwrap_or_default();
+        if groups.is_empty() {
+            groups = self
+                .search_member_groups(session.as_mut(), &entry.dn, username)
+                .await?;
+        }
 
-        // For now, return empty list
-        Ok(vec![])
+        let _ = session.unbind().await;
+        Ok(groups)
     }
 
-    /// Sync LDAP groups to local database
+    /// List the groups defined in the directory
+    #[allow(dead_code)]
     pub async fn sync_groups(&self) -> AppResult<Vec<LdapGroup>> {
         info!("Syncing LDAP groups");
 
-        let _group_filter = self
-            .config
-            .group_filter
-            .as_deref()
-            .unwrap_or("(objectClass=groupOfNames)");
-
-        // In production:
-        // 1. Connect and bind
-        // 2. Search for all groups
-        // 3. Parse group members
-        // 4. Update local database
-
-        Ok(vec![])
+        let mut session = self.service_session().await?;
+        let attrs = ["cn", "member", "uniqueMember", "memberUid"].map(String::from);
+        let entries = session
+            .search(&self.config.search_base, self.group_filter(), &attrs)
+            .await?;
+        let _ = session.unbind().await;
+
+        Ok(entries
+            .into_iter()
+            .map(|entry| {
+                let members = ["member", "uniqueMember", "memberUid"]
+                    .iter()
+                    .flat_map(|a| entry.values(a).to_vec())
+                    .collect();
+                LdapGroup {
+                    name: entry.first("cn").unwrap_or_else(|| group_name(&entry.dn)),
+                    dn: entry.dn,
+                    members,
+                }
+            })
+            .collect())
     }
 
     /// Validate LDAP configuration
+    ///
+    /// Connects, binds with the service account and searches the base DN.
+    #[allow(dead_code)]
     pub async fn validate_config(&self) -> AppResult<()> {
         info!("Validating LDAP configuration");
 
-        // In production:
-        // 1. Try to connect to LDAP server
-        // 2. Try to bind with service account
-        // 3. Verify search base exists
+        let mut session = self.service_session().await?;
+        session
+            .search(
+                &self.config.search_base,
+                "(objectClass=*)",
+                &["1.1".to_string()],
+            )
+            .await?;
+        let _ = session.unbind().await;
 
         Ok(())
     }
 
     /// Search for users in LDAP
-    pub async fn search_users(&self, query: &str, _limit: usize) -> AppResult<Vec<LdapUser>> {
+    #[allow(dead_code)]
+    pub async fn search_users(&self, query: &str, limit: usize) -> AppResult<Vec<LdapUser>> {
         info!("Searching LDAP users: {}", query);
 
-        // In production:
-        // 1. Build search filter
-        // 2. Execute LDAP search
-        // 3. Parse results
-
-        Ok(vec![])
-    }
-
-    /// Get attribute value from LDAP entry
-    fn get_attribute(&self, _entry: &str, _attr_name: &str) -> AppResult<String> {
-        // Parse LDAP entry and extract attribute
-        // This is a placeholder
-        Ok(String::new())
+        let attrs = &self.config.user_attributes;
+        let query = ldap_escape(query);
+        let filter = format!(
+            "(|({}=*{q}*)({}=*{q}*)({}=*{q}*))",
+            attrs.username,
+            attrs.email,
+            attrs.display_name,
+            q = query
+        );
+
+        let mut session = self.service_session().await?;
+        let entries = session
+            .search(
+                &self.config.search_base,
+                &filter,
+                &self.config.user_attribute_names(),
+            )
+            .await?;
+        let _ = session.unbind().await;
+
+        Ok(entries
+            .iter()
+            .filter_map(|entry| self.entry_to_user(entry).ok())
+            .take(limit)
+            .collect())
     }
 
     /// Map LDAP groups to application roles
+    ///
+    /// Groups are matched by name (the CN of a group DN) against the
+    /// configured admin groups; everyone else is a regular user.
     pub fn map_groups_to_roles(&self, groups: &[String]) -> Vec<String> {
-        // Map LDAP group names to application role names
-        // This could be configured with a mapping table
-        let mut roles = Vec::new();
-
-        for group in groups {
-            if group.contains("admin") || group.contains("administrators") {
-                roles.push("admin".to_string());
-            } else if group.contains("user") {
-                roles.push("user".to_string());
-            }
+        let is_admin = groups.iter().any(|group| {
+            let name = group_name(group);
+            self.config
+                .admin_groups
+                .iter()
+                .any(|admin| admin.eq_ignore_ascii_case(&name))
+        });
+
+        if is_admin {
+            vec!["admin".to_string()]
+        } else {
+            vec!["user".to_string()]
         }
+    }
+}
+
+/// Group name from a DN (`cn=Engineering,ou=groups,...` -> `Engineering`)
+///
+/// Values that are not DNs are returned unchanged.
+pub fn group_name(group: &str) -> String {
+    let first_rdn = group.split(',').next().unwrap_or(group);
+    match first_rdn.split_once('=') {
+        Some((_, value)) => value.trim().to_string(),
+        None => group.trim().to_string(),
+    }
+}
 
-        if roles.is_empty() {
-            roles.push("user".to_string()); // Default role
+/// Make the user's local group memberships match their LDAP groups
+///
+/// The user is added to local groups whose name matches an LDAP group and
+/// removed from the others. With `create_missing`, LDAP groups without a
+/// local counterpart are created.
+pub async fn sync_user_groups(
+    db: &Database,
+    user_id: &str,
+    ldap_groups: &[String],
+    create_missing: bool,
+) -> AppResult<()> {
+    let group_service = GroupService::new(db);
+    let names: Vec<String> = ldap_groups.iter().map(|g| group_name(g)).collect();
+    let user_ids = vec![user_id.to_string()];
+    let groups = group_service.get_all_groups().await?;
+
+    for group in &groups {
+        let in_ldap = names.iter().any(|n| n.eq_ignore_ascii_case(&group.name));
+        let is_member = group.user_ids.iter().any(|id| id == user_id);
+
+        if in_ldap && !is_member {
+            group_service
+                .add_users_to_group(&group.id, &user_ids)
+                .await?;
+        } else if !in_ldap && is_member {
+            group_service
+                .remove_users_from_group(&group.id, &user_ids)
+                .await?;
         }
+    }
 
-        roles
+    if create_missing {
+        // Created groups are owned by the first (admin) user
+        let owner_id = UserService::new(db)
+            .get_first_user()
+            .await?
+            .map(|u| u.id)
+            .unwrap_or_else(|| user_id.to_string());
+
+        for name in &names {
+            if groups.iter().any(|g| g.name.eq_ignore_ascii_case(name)) {
+                continue;
+            }
+            info!("Creating group {} from LDAP", name);
+            let group = group_service
+                .insert_new_group(
+                    &owner_id,
+                    &GroupForm {
+                        name: name.clone(),
+                        description: format!("Synced from LDAP group {}", name),
+                        permissions: None,
+                    },
+                )
+                .await?;
+            group_service
+                .update_group_by_id(
+                    &group.id,
+                    &GroupUpdateForm {
+                        name: None,
+                        description: None,
+                        permissions: None,
+                        user_ids: Some(user_ids.clone()),
+                    },
+                )
+                .await?;
+        }
     }
+
+    Ok(())
 }
 
 /// LDAP connection pool management
@@ -218,14 +679,13 @@ impl LdapPool {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_group_to_role_mapping() {
-        let config = LdapConfig {
+    fn test_config() -> LdapConfig {
+        LdapConfig {
             server_url: "ldap://localhost:389".to_string(),
             bind_dn: "cn=admin,dc=example,dc=com".to_string(),
             bind_password: "password".to_string(),
             search_base: "dc=example,dc=com".to_string(),
-            user_filter: "(uid={username})".to_string(),
+            user_filter: "(&(uid={username})(objectClass=inetOrgPerson))".to_string(),
             group_filter: None,
             user_attributes: LdapUserAttributes {
                 username: "uid".to_string(),
@@ -237,12 +697,214 @@ mod tests {
             },
             enable_tls: false,
             verify_cert: true,
-        };
+            ca_cert_file: None,
+            admin_groups: vec!["admin".to_string(), "administrators".to_string()],
+        }
+    }
+
+    /// In-memory stand-in for an OpenLDAP server
+    ///
+    /// Filters are evaluated as a conjunction of their `(attr=value)`
+    /// assertions, which covers the user and group lookups made here.
+    struct FakeDirectory {
+        entries: Vec<(LdapEntry, String)>,
+    }
+
+    fn entry(dn: &str, password: &str, attrs: &[(&str, &[&str])]) -> (LdapEntry, String) {
+        let attrs = attrs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.iter().map(|s| s.to_string()).collect()))
+            .collect();
+        (
+            LdapEntry {
+                dn: dn.to_string(),
+                attrs,
+            },
+            password.to_string(),
+        )
+    }
+
+    impl FakeDirectory {
+        fn new() -> Self {
+            Self {
+                entries: vec![
+                    entry("cn=admin,dc=example,dc=com", "password", &[]),
+                    entry(
+                        "uid=alice,ou=people,dc=example,dc=com",
+                        "wonderland",
+                        &[
+                            ("objectClass", &["inetOrgPerson"]),
+                            ("uid", &["alice"]),
+                            ("mail", &["Alice@Example.com"]),
+                            ("cn", &["Alice Liddell"]),
+                            ("memberOf", &["cn=admin,ou=groups,dc=example,dc=com"]),
+                        ],
+                    ),
+                    entry(
+                        "uid=bob,ou=people,dc=example,dc=com",
+                        "builder",
+                        &[
+                            ("objectClass", &["inetOrgPerson"]),
+                            ("uid", &["bob"]),
+                            ("mail", &["bob@example.com"]),
+                        ],
+                    ),
+                    entry(
+                        "cn=engineering,ou=groups,dc=example,dc=com",
+                        "",
+                        &[
+                            ("objectClass", &["groupOfNames"]),
+                            ("cn", &["engineering"]),
+                            ("member", &["uid=bob,ou=people,dc=example,dc=com"]),
+                        ],
+                    ),
+                ],
+            }
+        }
+
+        fn matches(entry: &LdapEntry, filter: &str) -> bool {
+            Self::eval(entry, filter).0
+        }
+
+        /// Evaluate the filter at the start of `filter`, returning the rest
+        fn eval<'f>(entry: &LdapEntry, filter: &'f str) -> (bool, &'f str) {
+            let inner = filter
+                .strip_prefix('(')
+                .expect("filter must start with '('");
+            match inner.chars().next() {
+                Some(op @ ('&' | '|')) => {
+                    let mut rest = &inner[1..];
+                    let mut results = Vec::new();
+                    while rest.starts_with('(') {
+                        let (result, next) = Self::eval(entry, rest);
+                        results.push(result);
+                        rest = next;
+                    }
+                    let result = if op == '&' {
+                        results.iter().all(|r| *r)
+                    } else {
+                        results.iter().any(|r| *r)
+                    };
+                    (result, &rest[1..])
+                }
+                _ => {
+                    let end = inner.find(')').expect("unterminated filter");
+                    let (attr, value) = inner[..end].split_once('=').expect("invalid assertion");
+                    let values = entry.values(attr);
+                    let result = if value == "*" {
+                        !values.is_empty()
+                    } else {
+                        values.iter().any(|v| v.eq_ignore_ascii_case(value))
+                    };
+                    (result, &inner[end + 1..])
+                }
+            }
+        }
+    }
+
+    struct FakeConnector(Arc<FakeDirectory>);
+
+    struct FakeSession(Arc<FakeDirectory>);
+
+    #[async_trait::async_trait]
+    impl LdapConnector for FakeConnector {
+        async fn connect(&self) -> AppResult<Box<dyn LdapSession>> {
+            Ok(Box::new(FakeSession(self.0.clone())))
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl LdapSession for FakeSession {
+        async fn bind(&mut self, dn: &str, password: &str) -> AppResult<()> {
+            self.0
+                .entries
+                .iter()
+                .find(|(e, p)| e.dn == dn && *p == password)
+                .map(|_| ())
+                .ok_or_else(|| AppError::Auth("invalid credentials".to_string()))
+        }
+
+        async fn search(
+            &mut self,
+            _base: &str,
+            filter: &str,
+            _attrs: &[String],
+        ) -> AppResult<Vec<LdapEntry>> {
+            Ok(self
+                .0
+                .entries
+                .iter()
+                .filter(|(e, _)| FakeDirectory::matches(e, filter))
+                .map(|(e, _)| e.clone())
+                .collect())
+        }
 
-        let client = LdapClient::new(config);
+        async fn unbind(&mut self) -> AppResult<()> {
+            Ok(())
+        }
+    }
+
+    fn fake_client() -> LdapClient {
+        LdapClient::with_connector(
+            test_config(),
+            Arc::new(FakeConnector(Arc::new(FakeDirectory::new()))),
+        )
+    }
+
+    #[test]
+    fn test_group_to_role_mapping() {
+        let client = LdapClient::new(test_config());
         let groups = vec!["cn=admin,dc=example,dc=com".to_string()];
         let roles = client.map_groups_to_roles(&groups);
 
         assert!(roles.contains(&"admin".to_string()));
+
+        let roles = client.map_groups_to_roles(&["cn=sysadmins,dc=example,dc=com".to_string()]);
+        assert_eq!(roles, vec!["user".to_string()]);
+    }
+
+    #[test]
+    fn test_group_name() {
+        assert_eq!(
+            group_name("cn=Engineering,ou=groups,dc=example,dc=com"),
+            "Engineering"
+        );
+        assert_eq!(group_name("engineering"), "engineering");
+    }
+
+    #[tokio::test]
+    async fn test_authenticate_maps_attributes() {
+        let user = fake_client()
+            .authenticate("alice", "wonderland")
+            .await
+            .unwrap();
+
+        assert_eq!(user.username, "alice");
+        assert_eq!(user.email, "alice@example.com");
+        assert_eq!(user.display_name, "Alice Liddell");
+        assert_eq!(user.dn, "uid=alice,ou=people,dc=example,dc=com");
+        assert_eq!(user.groups, vec!["cn=admin,ou=groups,dc=example,dc=com"]);
+    }
+
+    #[tokio::test]
+    async fn test_authenticate_rejects_bad_credentials() {
+        let client = fake_client();
+
+        assert!(client.authenticate("alice", "wrong").await.is_err());
+        assert!(client.authenticate("alice", "").await.is_err());
+        assert!(client.authenticate("carol", "anything").await.is_err());
+        // Filter metacharacters in the username must not widen the search
+        assert!(client.authenticate("*", "wonderland").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_groups_found_by_membership_search() {
+        let user = fake_client().authenticate("bob", "builder").await.unwrap();
+
+        assert_eq!(user.display_name, "bob");
+        assert_eq!(
+            user.groups,
+            vec!["cn=engineering,ou=groups,dc=example,dc=com"]
+        );
     }
 }
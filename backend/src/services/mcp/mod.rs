@@ -0,0 +1,808 @@
+mod transport;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+use tracing::{error, info};
+
+use crate::error::{AppError, AppResult};
+use transport::Transport;
+
+/// MCP protocol revision requested during `initialize`
+pub const PROTOCOL_VERSION: &str = "2025-06-18";
+
+const DEFAULT_TIMEOUT_SECS: u64 = 60;
+
+/// Upper bound on `nextCursor` pages followed for a single listing
+const MAX_LIST_PAGES: usize = 100;
+
+/// How the client reaches an MCP server
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum McpTransport {
+    #[default]
+    #[serde(alias = "http", alias = "streamable-http")]
+    StreamableHttp,
+    Sse,
+    Stdio,
+}
+
+/// Model Context Protocol (MCP) server connection settings
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct McpServerConfig {
+    #[serde(default)]
+    pub name: String,
+    /// Server URL for the HTTP transports
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub transport: McpTransport,
+    /// Executable and arguments for the stdio transport
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// `bearer` (default when a token is set) or `none`
+    #[serde(default)]
+    pub auth_type: Option<String>,
+    #[serde(default)]
+    pub auth_token: Option<String>,
+    /// Extra headers sent with every HTTP request, e.g. custom API keys
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Per-request timeout in seconds
+    #[serde(default)]
+    pub timeout: Option<u64>,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl McpServerConfig {
+    fn request_timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout.unwrap_or(DEFAULT_TIMEOUT_SECS))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpTool {
+    pub name: String,
+    pub description: String,
+    pub input_schema: Value,
+    pub server: String,
+}
+
+impl McpTool {
+    /// Function spec in the OpenAI `tools` format
+    pub fn to_openai_spec(&self) -> Value {
+        json!({
+            "name": self.name,
+            "description": self.description,
+            "parameters": self.input_schema,
+        })
+    }
+}
+
+/// Result of a `tools/call` request
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpToolResult {
+    #[serde(default)]
+    pub content: Vec<Value>,
+    #[serde(default)]
+    pub structured_content: Option<Value>,
+    #[serde(default)]
+    pub is_error: bool,
+}
+
+impl McpToolResult {
+    /// Collapse the result into a single value for the model: structured
+    /// content when present, otherwise the text blocks (parsed when they hold
+    /// JSON), otherwise the raw content blocks
+    pub fn output(&self) -> Value {
+        if let Some(structured) = &self.structured_content {
+            return structured.clone();
+        }
+
+        let texts: Vec<&str> = self
+            .content
+            .iter()
+            .filter(|block| block.get("type").and_then(|t| t.as_str()) == Some("text"))
+            .filter_map(|block| block.get("text").and_then(|t| t.as_str()))
+            .collect();
+        match texts.as_slice() {
+            [] => Value::Array(self.content.clone()),
+            [text] => {
+                serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string()))
+            }
+            texts => Value::String(texts.join("\n")),
+        }
+    }
+}
+
+/// An initialized session with one MCP server
+pub struct McpClient {
+    name: String,
+    transport: Box<dyn Transport>,
+    next_id: AtomicU64,
+    timeout: Duration,
+    server_info: Value,
+    capabilities: Value,
+}
+
+impl McpClient {
+    /// Connect and run the `initialize` handshake
+    pub async fn connect(config: &McpServerConfig) -> AppResult<Self> {
+        let mut client = Self {
+            name: config.name.clone(),
+            transport: transport::connect(config).await?,
+            next_id: AtomicU64::new(1),
+            timeout: config.request_timeout(),
+            server_info: Value::Null,
+            capabilities: Value::Null,
+        };
+
+        let result = client
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": {
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                    },
+                }),
+            )
+            .await?;
+        if let Some(version) = result.get("protocolVersion").and_then(|v| v.as_str()) {
+            client.transport.set_protocol_version(version);
+        }
+        client.server_info = result.get("serverInfo").cloned().unwrap_or_default();
+        client.capabilities = result.get("capabilities").cloned().unwrap_or_default();
+
+        client
+            .transport
+            .notify(json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }))
+            .await?;
+
+        info!("Connected to MCP server: {}", client.name);
+        Ok(client)
+    }
+
+    pub fn server_info(&self) -> &Value {
+        &self.server_info
+    }
+
+    pub fn capabilities(&self) -> &Value {
+        &self.capabilities
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.transport.is_closed()
+    }
+
+    /// Send a JSON-RPC request and return its `result`
+    pub async fn request(&self, method: &str, params: Value) -> AppResult<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        let mut response = self.transport.request(message, self.timeout).await?;
+
+        if let Some(err) = response.get("error") {
+            return Err(AppError::ExternalServiceError(format!(
+                "MCP server '{}' returned error {}: {}",
+                self.name,
+                err.get("code").and_then(|c| c.as_i64()).unwrap_or_default(),
+                err.get("message")
+                    .and_then(|m| m.as_str())
+                    .unwrap_or("unknown error"),
+            )));
+        }
+        Ok(response
+            .get_mut("result")
+            .map(Value::take)
+            .unwrap_or_default())
+    }
+
+    /// Collect every page of a `*/list` method
+    async fn list_all(&self, method: &str, key: &str) -> AppResult<Vec<Value>> {
+        let mut items = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        for _ in 0..MAX_LIST_PAGES {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let mut result = self.request(method, params).await?;
+            if let Some(Value::Array(page)) = result.get_mut(key).map(Value::take) {
+                items.extend(page);
+            }
+
+            match result.get("nextCursor").and_then(|c| c.as_str()) {
+                Some(next) if cursor.as_deref() != Some(next) => cursor = Some(next.to_string()),
+                _ => return Ok(items),
+            }
+        }
+        Ok(items)
+    }
+
+    pub async fn list_tools(&self) -> AppResult<Vec<McpTool>> {
+        let tools = self.list_all("tools/list", "tools").await?;
+        Ok(tools
+            .into_iter()
+            .filter_map(|tool| {
+                Some(McpTool {
+                    name: tool.get("name")?.as_str()?.to_string(),
+                    description: tool
+                        .get("description")
+                        .and_then(|d| d.as_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                    input_schema: tool
+                        .get("inputSchema")
+                        .cloned()
+                        .unwrap_or_else(|| json!({ "type": "object", "properties": {} })),
+                    server: self.name.clone(),
+                })
+            })
+            .collect())
+    }
+
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> AppResult<McpToolResult> {
+        let result = self
+            .request(
+                "tools/call",
+                json!({ "name": name, "arguments": arguments }),
+            )
+            .await?;
+        serde_json::from_value(result)
+            .map_err(|e| AppError::ExternalServiceError(format!("Invalid MCP tool result: {}", e)))
+    }
+
+    pub async fn list_resources(&self) -> AppResult<Vec<Value>> {
+        self.list_all("resources/list", "resources").await
+    }
+
+    pub async fn list_prompts(&self) -> AppResult<Vec<Value>> {
+        self.list_all("prompts/list", "prompts").await
+    }
+
+    pub async fn ping(&self) -> bool {
+        self.request("ping", json!({})).await.is_ok()
+    }
+
+    pub async fn close(&self) {
+        self.transport.close().await;
+    }
+}
+
+type Sessions = HashMap<String, (McpServerConfig, Arc<McpClient>)>;
+
+/// Open sessions keyed by caller-chosen name, reused across requests
+static SESSIONS: Lazy<Mutex<Sessions>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Get the live session for `key`, connecting when there is none or when the
+/// server's configuration changed since it was opened
+pub async fn session(key: &str, config: &McpServerConfig) -> AppResult<Arc<McpClient>> {
+    {
+        let sessions = SESSIONS.lock().await;
+        if let Some((open_config, client)) = sessions.get(key) {
+            if open_config == config && !client.is_closed() {
+                return Ok(client.clone());
+            }
+        }
+    }
+
+    let client = Arc::new(McpClient::connect(config).await?);
+    let previous = SESSIONS
+        .lock()
+        .await
+        .insert(key.to_string(), (config.clone(), client.clone()));
+    if let Some((_, previous)) = previous {
+        previous.close().await;
+    }
+    Ok(client)
+}
+
+async fn evict(key: &str) {
+    if let Some((_, client)) = SESSIONS.lock().await.remove(key) {
+        client.close().await;
+    }
+}
+
+/// Run `f` against the session for `key`, reconnecting once if the session
+/// turns out to be dead
+async fn with_session<T, F, Fut>(key: &str, config: &McpServerConfig, f: F) -> AppResult<T>
+where
+    F: Fn(Arc<McpClient>) -> Fut,
+    Fut: std::future::Future<Output = AppResult<T>>,
+{
+    let client = session(key, config).await?;
+    match f(client.clone()).await {
+        Err(e) if client.is_closed() => {
+            info!("MCP session {} was lost ({}), reconnecting", key, e);
+            evict(key).await;
+            f(session(key, config).await?).await
+        }
+        result => result,
+    }
+}
+
+pub async fn list_tools(key: &str, config: &McpServerConfig) -> AppResult<Vec<McpTool>> {
+    with_session(
+        key,
+        config,
+        |client| async move { client.list_tools().await },
+    )
+    .await
+}
+
+pub async fn call_tool(
+    key: &str,
+    config: &McpServerConfig,
+    name: &str,
+    arguments: Value,
+) -> AppResult<McpToolResult> {
+    with_session(key, config, |client| {
+        let arguments = arguments.clone();
+        async move { client.call_tool(name, arguments).await }
+    })
+    .await
+}
+
+/// An MCP server configured as an admin tool server connection
+/// (`TOOL_SERVER_CONNECTIONS` entries with `"type": "mcp"`)
+#[derive(Debug, Clone)]
+pub struct McpToolServer {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub access_control: Option<Value>,
+    pub config: McpServerConfig,
+}
+
+impl McpToolServer {
+    pub const ID_PREFIX: &'static str = "server:mcp:";
+
+    /// Tool id used by the tool picker and in `tool_ids` of chat requests
+    pub fn tool_id(&self) -> String {
+        format!("{}{}", Self::ID_PREFIX, self.id)
+    }
+
+    /// Enabled MCP connections from the tool server connection list
+    pub fn from_connections(connections: &Value) -> Vec<Self> {
+        connections
+            .as_array()
+            .map(|connections| {
+                connections
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, conn)| conn["config"]["enable"].as_bool() != Some(false))
+                    .filter_map(|(idx, conn)| Self::from_connection(idx, conn))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Look up the server behind a `server:mcp:{id}` tool id
+    pub fn find(connections: &Value, tool_id: &str) -> Option<Self> {
+        let id = tool_id.strip_prefix(Self::ID_PREFIX)?;
+        Self::from_connections(connections)
+            .into_iter()
+            .find(|server| server.id == id)
+    }
+
+    /// Parse one connection entry, whether enabled or not; `idx` is its
+    /// position in the list, used as id when the entry has none
+    pub fn from_connection(idx: usize, conn: &Value) -> Option<Self> {
+        if conn.get("type").and_then(|t| t.as_str()) != Some("mcp") {
+            return None;
+        }
+        let settings = conn.get("config").cloned().unwrap_or_default();
+
+        let info = conn.get("info").cloned().unwrap_or_default();
+        let id = info
+            .get("id")
+            .and_then(|id| id.as_str())
+            .filter(|id| !id.is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| idx.to_string());
+        let name = info
+            .get("name")
+            .and_then(|n| n.as_str())
+            .filter(|n| !n.is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| id.clone());
+
+        let mut config: McpServerConfig = match serde_json::from_value(conn.clone()) {
+            Ok(config) => config,
+            Err(e) => {
+                error!("Invalid MCP tool server connection {}: {}", id, e);
+                return None;
+            }
+        };
+        config.name = name.clone();
+        if config.auth_token.is_none() {
+            config.auth_token = conn.get("key").and_then(|k| k.as_str()).map(str::to_string);
+        }
+
+        Some(Self {
+            id,
+            name,
+            description: info
+                .get("description")
+                .and_then(|d| d.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            access_control: settings
+                .get("access_control")
+                .filter(|ac| !ac.is_null())
+                .cloned(),
+            config,
+        })
+    }
+
+    pub async fn list_tools(&self) -> AppResult<Vec<McpTool>> {
+        list_tools(&self.tool_id(), &self.config).await
+    }
+
+    /// Connect on a fresh session and describe everything the server offers
+    pub async fn inspect(&self) -> AppResult<Value> {
+        let client = McpClient::connect(&self.config).await?;
+        let result = async {
+            let tools = client.list_tools().await?;
+            let resources = if client.capabilities().get("resources").is_some() {
+                client.list_resources().await?
+            } else {
+                vec![]
+            };
+            let prompts = if client.capabilities().get("prompts").is_some() {
+                client.list_prompts().await?
+            } else {
+                vec![]
+            };
+            Ok(json!({
+                "server_info": client.server_info(),
+                "capabilities": client.capabilities(),
+                "tools": tools,
+                "resources": resources,
+                "prompts": prompts,
+            }))
+        }
+        .await;
+        client.close().await;
+        result
+    }
+
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> AppResult<McpToolResult> {
+        call_tool(&self.tool_id(), &self.config, name, arguments).await
+    }
+}
+
+/// Tool server manager over several MCP servers, sharing pooled sessions
+#[allow(dead_code)]
+pub struct ToolServerManager {
+    servers: Vec<McpServerConfig>,
+}
+
+#[allow(dead_code)]
+impl ToolServerManager {
+    pub fn new(servers: Vec<McpServerConfig>) -> Self {
+        Self { servers }
+    }
+
+    fn session_key(server: &McpServerConfig) -> String {
+        format!("manager:{}", server.name)
+    }
+
+    /// Discover available tools from all enabled servers
+    pub async fn get_available_tools(&self) -> AppResult<Vec<McpTool>> {
+        let mut all_tools = Vec::new();
+
+        for server in self.servers.iter().filter(|s| s.enabled) {
+            match list_tools(&Self::session_key(server), server).await {
+                Ok(tools) => {
+                    info!(
+                        "Discovered {} tools from server: {}",
+                        tools.len(),
+                        server.name
+                    );
+                    all_tools.extend(tools);
+                }
+                Err(e) => {
+                    error!("Failed to discover tools from {}: {}", server.name, e);
+                }
+            }
+        }
+
+        Ok(all_tools)
+    }
+
+    /// Execute a tool call on the server that provides it
+    pub async fn execute_tool(
+        &self,
+        tool_name: &str,
+        arguments: Value,
+    ) -> AppResult<McpToolResult> {
+        let tools = self.get_available_tools().await?;
+        let tool = tools
+            .iter()
+            .find(|t| t.name == tool_name)
+            .ok_or_else(|| AppError::NotFound(format!("Tool not found: {}", tool_name)))?;
+
+        let server = self
+            .servers
+            .iter()
+            .find(|s| s.name == tool.server)
+            .ok_or_else(|| {
+                AppError::InternalServerError("Server configuration not found".to_string())
+            })?;
+
+        call_tool(&Self::session_key(server), server, tool_name, arguments).await
+    }
+
+    /// Ping every enabled server
+    pub async fn health_check(&self) -> Vec<(String, bool)> {
+        let mut results = Vec::new();
+
+        for server in self.servers.iter().filter(|s| s.enabled) {
+            let is_healthy = match session(&Self::session_key(server), server).await {
+                Ok(client) => client.ping().await,
+                Err(_) => false,
+            };
+            results.push((server.name.clone(), is_healthy));
+        }
+
+        results
+    }
+
+    /// Add a new server at runtime
+    pub fn add_server(&mut self, server: McpServerConfig) {
+        self.servers.push(server);
+    }
+
+    /// Remove a server
+    pub fn remove_server(&mut self, name: &str) -> bool {
+        let initial_len = self.servers.len();
+        self.servers.retain(|s| s.name != name);
+        self.servers.len() < initial_len
+    }
+
+    /// Enable/disable a server
+    pub fn set_server_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        if let Some(server) = self.servers.iter_mut().find(|s| s.name == name) {
+            server.enabled = enabled;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+
+    #[test]
+    fn test_mcp_client_creation() {
+        let servers = vec![McpServerConfig {
+            name: "test-server".to_string(),
+            url: "http://localhost:8000".to_string(),
+            enabled: true,
+            ..Default::default()
+        }];
+
+        let manager = ToolServerManager::new(servers);
+        assert_eq!(manager.servers.len(), 1);
+    }
+
+    #[test]
+    fn test_add_remove_server() {
+        let mut manager = ToolServerManager::new(vec![]);
+
+        let server = McpServerConfig {
+            name: "test-server".to_string(),
+            url: "http://localhost:8000".to_string(),
+            enabled: true,
+            ..Default::default()
+        };
+
+        manager.add_server(server);
+        assert_eq!(manager.servers.len(), 1);
+
+        assert!(manager.remove_server("test-server"));
+        assert_eq!(manager.servers.len(), 0);
+    }
+
+    #[test]
+    fn test_tool_servers_from_connections() {
+        let connections = json!([
+            { "type": "openapi", "url": "http://localhost:9000" },
+            {
+                "type": "mcp",
+                "url": "http://localhost:8000/mcp",
+                "key": "secret",
+                "config": { "enable": true, "access_control": null },
+                "info": { "id": "docs", "name": "Docs", "description": "Search docs" },
+            },
+            {
+                "type": "mcp",
+                "transport": "stdio",
+                "command": "mcp-server-git",
+                "args": ["--repository", "."],
+                "config": { "enable": false },
+            },
+        ]);
+
+        let servers = McpToolServer::from_connections(&connections);
+        assert_eq!(servers.len(), 1);
+        assert_eq!(servers[0].tool_id(), "server:mcp:docs");
+        assert_eq!(servers[0].config.name, "Docs");
+        assert_eq!(servers[0].config.transport, McpTransport::StreamableHttp);
+        assert_eq!(servers[0].config.auth_token.as_deref(), Some("secret"));
+        assert!(servers[0].access_control.is_none());
+        assert!(McpToolServer::find(&connections, "server:mcp:docs").is_some());
+        assert!(McpToolServer::find(&connections, "server:mcp:1").is_none());
+    }
+
+    #[test]
+    fn test_tool_result_output() {
+        let text: McpToolResult = serde_json::from_value(json!({
+            "content": [{ "type": "text", "text": "{\"temp\": 21}" }],
+        }))
+        .unwrap();
+        assert_eq!(text.output(), json!({ "temp": 21 }));
+
+        let structured: McpToolResult = serde_json::from_value(json!({
+            "content": [{ "type": "text", "text": "21 degrees" }],
+            "structuredContent": { "temp": 21 },
+            "isError": false,
+        }))
+        .unwrap();
+        assert_eq!(structured.output(), json!({ "temp": 21 }));
+    }
+
+    /// Streamable HTTP stand-in: answers `initialize` with a session id and
+    /// serves `tools/list` in two pages, the second one as an SSE stream
+    async fn streamable_http_server(req: HttpRequest, body: web::Json<Value>) -> HttpResponse {
+        let method = body["method"].as_str().unwrap_or_default();
+        let id = body["id"].clone();
+        if method == "initialize" {
+            return HttpResponse::Ok()
+                .insert_header(("Mcp-Session-Id", "session-1"))
+                .json(json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "result": {
+                        "protocolVersion": PROTOCOL_VERSION,
+                        "capabilities": { "tools": {} },
+                        "serverInfo": { "name": "stub", "version": "1.0" },
+                    },
+                }));
+        }
+        let session = req
+            .headers()
+            .get("mcp-session-id")
+            .and_then(|v| v.to_str().ok());
+        if session != Some("session-1") {
+            return HttpResponse::BadRequest().finish();
+        }
+
+        match method {
+            "notifications/initialized" => HttpResponse::Accepted().finish(),
+            "tools/list" if body["params"]["cursor"].is_null() => HttpResponse::Ok().json(json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": {
+                    "tools": [{ "name": "echo", "inputSchema": { "type": "object" } }],
+                    "nextCursor": "page-2",
+                },
+            })),
+            "tools/list" => {
+                let response = json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "result": { "tools": [{ "name": "time", "description": "Current time" }] },
+                });
+                HttpResponse::Ok()
+                    .content_type("text/event-stream")
+                    .body(format!(
+                        "event: message\ndata: {}\n\nevent: message\ndata: {}\n\n",
+                        json!({ "jsonrpc": "2.0", "method": "notifications/progress", "params": {} }),
+                        response
+                    ))
+            }
+            "tools/call" => HttpResponse::Ok().json(json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": {
+                    "content": [{ "type": "text", "text": body["params"]["arguments"]["text"] }],
+                },
+            })),
+            _ => HttpResponse::Ok().json(json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32601, "message": "Method not found" },
+            })),
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_streamable_http_session() {
+        let server =
+            HttpServer::new(|| App::new().route("/mcp", web::post().to(streamable_http_server)))
+                .workers(1)
+                .bind(("127.0.0.1", 0))
+                .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+
+        let config = McpServerConfig {
+            name: "stub".to_string(),
+            url: format!("http://{}/mcp", addr),
+            enabled: true,
+            ..Default::default()
+        };
+        let client = McpClient::connect(&config).await.unwrap();
+        assert_eq!(client.server_info()["name"], "stub");
+
+        let tools = client.list_tools().await.unwrap();
+        let names: Vec<&str> = tools.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["echo", "time"]);
+        assert_eq!(tools[1].description, "Current time");
+
+        let result = client
+            .call_tool("echo", json!({ "text": "hello" }))
+            .await
+            .unwrap();
+        assert_eq!(result.output(), json!("hello"));
+
+        let err = client.list_prompts().await.unwrap_err();
+        assert!(err.to_string().contains("-32601"));
+    }
+
+    #[actix_web::test]
+    async fn test_stdio_session() {
+        let script = r#"while IFS= read -r line; do
+  case "$line" in
+    *'"initialize"'*) printf '%s\n' '{"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2025-06-18","capabilities":{},"serverInfo":{"name":"stdio-stub"}}}' ;;
+    *'"tools/list"'*) printf '%s\n' '{"jsonrpc":"2.0","id":"srv","method":"ping"}' '{"jsonrpc":"2.0","id":2,"result":{"tools":[{"name":"echo"}]}}' ;;
+  esac
+done"#;
+        let config = McpServerConfig {
+            name: "stdio-stub".to_string(),
+            transport: McpTransport::Stdio,
+            command: Some("sh".to_string()),
+            args: vec!["-c".to_string(), script.to_string()],
+            enabled: true,
+            ..Default::default()
+        };
+
+        let client = session("test:stdio", &config).await.unwrap();
+        assert_eq!(client.server_info()["name"], "stdio-stub");
+        assert!(Arc::ptr_eq(
+            &client,
+            &session("test:stdio", &config).await.unwrap()
+        ));
+
+        let tools = client.list_tools().await.unwrap();
+        assert_eq!(tools[0].name, "echo");
+        assert_eq!(tools[0].server, "stdio-stub");
+
+        evict("test:stdio").await;
+        assert!(client.is_closed());
+    }
+}
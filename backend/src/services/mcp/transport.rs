@@ -0,0 +1,635 @@
+//! MCP transports: stdio subprocesses, legacy HTTP+SSE and Streamable HTTP
+//!
+//! Every transport moves JSON-RPC 2.0 messages. Requests carry a numeric id
+//! and resolve once the matching response arrives; anything else the server
+//! sends (notifications, its own requests) is handled by the [`Dispatcher`].
+
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use eventsource_stream::Eventsource;
+use futures::StreamExt;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, CONTENT_TYPE};
+use reqwest::{Client, StatusCode, Url};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::{mpsc, oneshot};
+
+use super::{McpServerConfig, McpTransport};
+use crate::error::{AppError, AppResult};
+
+const SESSION_ID_HEADER: &str = "mcp-session-id";
+const PROTOCOL_VERSION_HEADER: &str = "mcp-protocol-version";
+
+/// A bidirectional JSON-RPC channel to one MCP server
+#[async_trait]
+pub(crate) trait Transport: Send + Sync {
+    /// Send a request and wait for the response carrying the same id
+    async fn request(&self, message: Value, timeout: Duration) -> AppResult<Value>;
+
+    /// Send a notification, which gets no response
+    async fn notify(&self, message: Value) -> AppResult<()>;
+
+    /// Record the protocol version agreed on during `initialize`
+    fn set_protocol_version(&self, _version: &str) {}
+
+    /// Whether the connection is gone and the session has to be re-established
+    fn is_closed(&self) -> bool;
+
+    async fn close(&self);
+}
+
+/// Open the transport described by `config`
+pub(crate) async fn connect(config: &McpServerConfig) -> AppResult<Box<dyn Transport>> {
+    Ok(match config.transport {
+        McpTransport::StreamableHttp => Box::new(StreamableHttpTransport::new(config)?),
+        McpTransport::Sse => Box::new(SseTransport::connect(config).await?),
+        McpTransport::Stdio => Box::new(StdioTransport::spawn(config)?),
+    })
+}
+
+fn transport_error(server: &str, message: impl std::fmt::Display) -> AppError {
+    AppError::ExternalServiceError(format!("MCP server '{}': {}", server, message))
+}
+
+/// Headers sent with every HTTP request: custom headers plus bearer auth
+fn auth_headers(config: &McpServerConfig) -> AppResult<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in &config.headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| AppError::BadRequest(format!("Invalid MCP header '{}': {}", name, e)))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|e| AppError::BadRequest(format!("Invalid MCP header value: {}", e)))?;
+        headers.insert(name, value);
+    }
+
+    let token = config.auth_token.as_deref().filter(|t| !t.is_empty());
+    if let (Some(token), "bearer") = (token, config.auth_type.as_deref().unwrap_or("bearer")) {
+        let value = HeaderValue::from_str(&format!("Bearer {}", token))
+            .map_err(|e| AppError::BadRequest(format!("Invalid MCP auth token: {}", e)))?;
+        headers.insert(reqwest::header::AUTHORIZATION, value);
+    }
+    Ok(headers)
+}
+
+/// Matches responses to pending requests for transports that receive
+/// messages on a separate channel from the one they send on
+#[derive(Default)]
+struct Dispatcher {
+    pending: Mutex<HashMap<u64, oneshot::Sender<Value>>>,
+    closed: AtomicBool,
+}
+
+impl Dispatcher {
+    fn register(&self, id: u64) -> oneshot::Receiver<Value> {
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id, tx);
+        rx
+    }
+
+    fn forget(&self, id: u64) {
+        self.pending.lock().unwrap().remove(&id);
+    }
+
+    /// Route one incoming message, returning the replies owed to the server
+    fn dispatch(&self, message: Value) -> Vec<Value> {
+        match message {
+            Value::Array(batch) => batch
+                .into_iter()
+                .flat_map(|message| self.dispatch(message))
+                .collect(),
+            message => {
+                if let Some(reply) = server_request_reply(&message) {
+                    return vec![reply];
+                }
+                if message.get("method").is_none() {
+                    if let Some(id) = message.get("id").and_then(|id| id.as_u64()) {
+                        if let Some(tx) = self.pending.lock().unwrap().remove(&id) {
+                            let _ = tx.send(message);
+                        }
+                    }
+                }
+                vec![]
+            }
+        }
+    }
+
+    /// Fail every pending request; later requests fail immediately
+    fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        self.pending.lock().unwrap().clear();
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    async fn wait(
+        &self,
+        server: &str,
+        id: u64,
+        rx: oneshot::Receiver<Value>,
+        timeout: Duration,
+    ) -> AppResult<Value> {
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(_)) => Err(transport_error(server, "connection closed")),
+            Err(_) => {
+                self.forget(id);
+                Err(AppError::Timeout(format!(
+                    "MCP server '{}' did not respond within {}s",
+                    server,
+                    timeout.as_secs()
+                )))
+            }
+        }
+    }
+}
+
+/// Answer a request the server sent to us: `ping` is the only one we serve
+fn server_request_reply(message: &Value) -> Option<Value> {
+    let method = message.get("method")?.as_str()?;
+    let id = message.get("id")?.clone();
+    Some(if method == "ping" {
+        json!({ "jsonrpc": "2.0", "id": id, "result": {} })
+    } else {
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": -32601, "message": format!("Method not found: {}", method) },
+        })
+    })
+}
+
+fn message_id(message: &Value) -> u64 {
+    message
+        .get("id")
+        .and_then(|id| id.as_u64())
+        .unwrap_or_default()
+}
+
+/// Find the response to request `id` in a JSON body, which may be a batch
+fn find_response(body: Value, id: u64) -> Option<Value> {
+    match body {
+        Value::Array(batch) => batch.into_iter().find_map(|m| find_response(m, id)),
+        message if message.get("method").is_none() && message_id(&message) == id => Some(message),
+        _ => None,
+    }
+}
+
+/// A local server run as a subprocess, speaking newline-delimited JSON on
+/// stdin/stdout. The process lives as long as the session.
+struct StdioTransport {
+    name: String,
+    outgoing: mpsc::UnboundedSender<String>,
+    dispatcher: Arc<Dispatcher>,
+    child: Mutex<Child>,
+}
+
+impl StdioTransport {
+    fn spawn(config: &McpServerConfig) -> AppResult<Self> {
+        let command = config
+            .command
+            .as_deref()
+            .filter(|c| !c.is_empty())
+            .ok_or_else(|| {
+                AppError::BadRequest(format!("MCP server '{}' has no command", config.name))
+            })?;
+
+        let mut child = Command::new(command)
+            .args(&config.args)
+            .envs(&config.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| transport_error(&config.name, format!("failed to start: {}", e)))?;
+
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+
+        let dispatcher = Arc::new(Dispatcher::default());
+        let (outgoing, mut outgoing_rx) = mpsc::unbounded_channel::<String>();
+
+        tokio::spawn(async move {
+            while let Some(line) = outgoing_rx.recv().await {
+                if stdin.write_all(line.as_bytes()).await.is_err()
+                    || stdin.write_all(b"\n").await.is_err()
+                    || stdin.flush().await.is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        let reader_dispatcher = dispatcher.clone();
+        let replies = outgoing.clone();
+        let name = config.name.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<Value>(&line) {
+                    Ok(message) => {
+                        for reply in reader_dispatcher.dispatch(message) {
+                            let _ = replies.send(reply.to_string());
+                        }
+                    }
+                    Err(e) => tracing::warn!("MCP server '{}' sent invalid JSON: {}", name, e),
+                }
+            }
+            tracing::info!("MCP server '{}' closed its stdout", name);
+            reader_dispatcher.close();
+        });
+
+        let name = config.name.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                tracing::debug!("MCP server '{}' stderr: {}", name, line);
+            }
+        });
+
+        Ok(Self {
+            name: config.name.clone(),
+            outgoing,
+            dispatcher,
+            child: Mutex::new(child),
+        })
+    }
+
+    fn send(&self, message: &Value) -> AppResult<()> {
+        if self.dispatcher.is_closed() {
+            return Err(transport_error(&self.name, "process exited"));
+        }
+        self.outgoing
+            .send(message.to_string())
+            .map_err(|_| transport_error(&self.name, "process exited"))
+    }
+}
+
+#[async_trait]
+impl Transport for StdioTransport {
+    async fn request(&self, message: Value, timeout: Duration) -> AppResult<Value> {
+        let id = message_id(&message);
+        let rx = self.dispatcher.register(id);
+        if let Err(e) = self.send(&message) {
+            self.dispatcher.forget(id);
+            return Err(e);
+        }
+        self.dispatcher.wait(&self.name, id, rx, timeout).await
+    }
+
+    async fn notify(&self, message: Value) -> AppResult<()> {
+        self.send(&message)
+    }
+
+    fn is_closed(&self) -> bool {
+        self.dispatcher.is_closed()
+    }
+
+    async fn close(&self) {
+        self.dispatcher.close();
+        let _ = self.child.lock().unwrap().start_kill();
+    }
+}
+
+/// The 2024-11-05 HTTP+SSE transport: a long-lived GET stream delivers
+/// server messages, and client messages are POSTed to the endpoint the
+/// server announces in its first `endpoint` event.
+struct SseTransport {
+    name: String,
+    http: Client,
+    endpoint: Url,
+    headers: HeaderMap,
+    dispatcher: Arc<Dispatcher>,
+    reader: tokio::task::JoinHandle<()>,
+}
+
+impl SseTransport {
+    async fn connect(config: &McpServerConfig) -> AppResult<Self> {
+        let headers = auth_headers(config)?;
+        let url = Url::parse(&config.url)
+            .map_err(|e| AppError::BadRequest(format!("Invalid MCP server URL: {}", e)))?;
+        let http = Client::new();
+
+        let response = http
+            .get(url.clone())
+            .headers(headers.clone())
+            .header(ACCEPT, "text/event-stream")
+            .send()
+            .await
+            .map_err(|e| transport_error(&config.name, e))?;
+        if !response.status().is_success() {
+            return Err(transport_error(
+                &config.name,
+                format!("SSE connection failed with {}", response.status()),
+            ));
+        }
+
+        let mut events = response.bytes_stream().eventsource();
+        let endpoint = tokio::time::timeout(config.request_timeout(), async {
+            while let Some(event) = events.next().await {
+                match event {
+                    Ok(event) if event.event == "endpoint" => return Some(event.data),
+                    Ok(_) => continue,
+                    Err(_) => return None,
+                }
+            }
+            None
+        })
+        .await
+        .ok()
+        .flatten()
+        .ok_or_else(|| transport_error(&config.name, "no endpoint event received"))?;
+        let endpoint = url
+            .join(endpoint.trim())
+            .map_err(|e| transport_error(&config.name, format!("invalid endpoint: {}", e)))?;
+
+        let dispatcher = Arc::new(Dispatcher::default());
+        let reader_dispatcher = dispatcher.clone();
+        let reply_http = http.clone();
+        let reply_endpoint = endpoint.clone();
+        let reply_headers = headers.clone();
+        let name = config.name.clone();
+        let reader = tokio::spawn(async move {
+            while let Some(event) = events.next().await {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(e) => {
+                        tracing::warn!("MCP server '{}' SSE stream failed: {}", name, e);
+                        break;
+                    }
+                };
+                if !(event.event.is_empty() || event.event == "message") {
+                    continue;
+                }
+                let Ok(message) = serde_json::from_str::<Value>(&event.data) else {
+                    tracing::warn!("MCP server '{}' sent invalid JSON", name);
+                    continue;
+                };
+                for reply in reader_dispatcher.dispatch(message) {
+                    let _ = reply_http
+                        .post(reply_endpoint.clone())
+                        .headers(reply_headers.clone())
+                        .json(&reply)
+                        .send()
+                        .await;
+                }
+            }
+            reader_dispatcher.close();
+        });
+
+        Ok(Self {
+            name: config.name.clone(),
+            http,
+            endpoint,
+            headers,
+            dispatcher,
+            reader,
+        })
+    }
+
+    async fn post(&self, message: &Value) -> AppResult<()> {
+        if self.dispatcher.is_closed() {
+            return Err(transport_error(&self.name, "SSE stream closed"));
+        }
+        let response = self
+            .http
+            .post(self.endpoint.clone())
+            .headers(self.headers.clone())
+            .json(message)
+            .send()
+            .await
+            .map_err(|e| transport_error(&self.name, e))?;
+        if !response.status().is_success() {
+            return Err(transport_error(
+                &self.name,
+                format!("message rejected with {}", response.status()),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Transport for SseTransport {
+    async fn request(&self, message: Value, timeout: Duration) -> AppResult<Value> {
+        let id = message_id(&message);
+        let rx = self.dispatcher.register(id);
+        if let Err(e) = self.post(&message).await {
+            self.dispatcher.forget(id);
+            return Err(e);
+        }
+        self.dispatcher.wait(&self.name, id, rx, timeout).await
+    }
+
+    async fn notify(&self, message: Value) -> AppResult<()> {
+        self.post(&message).await
+    }
+
+    fn is_closed(&self) -> bool {
+        self.dispatcher.is_closed()
+    }
+
+    async fn close(&self) {
+        self.dispatcher.close();
+        self.reader.abort();
+    }
+}
+
+/// The Streamable HTTP transport: every message is a POST, answered either
+/// with a JSON body or with an SSE stream that ends in the response. The
+/// server may assign an `Mcp-Session-Id` that later requests must echo.
+struct StreamableHttpTransport {
+    name: String,
+    http: Client,
+    url: Url,
+    headers: HeaderMap,
+    session_id: RwLock<Option<String>>,
+    protocol_version: RwLock<Option<String>>,
+    closed: AtomicBool,
+}
+
+impl StreamableHttpTransport {
+    fn new(config: &McpServerConfig) -> AppResult<Self> {
+        Ok(Self {
+            name: config.name.clone(),
+            http: Client::new(),
+            url: Url::parse(&config.url)
+                .map_err(|e| AppError::BadRequest(format!("Invalid MCP server URL: {}", e)))?,
+            headers: auth_headers(config)?,
+            session_id: RwLock::new(None),
+            protocol_version: RwLock::new(None),
+            closed: AtomicBool::new(false),
+        })
+    }
+
+    fn session_headers(&self) -> HeaderMap {
+        let mut headers = self.headers.clone();
+        let session_id = self.session_id.read().unwrap();
+        let protocol_version = self.protocol_version.read().unwrap();
+        for (name, value) in [
+            (SESSION_ID_HEADER, session_id.as_deref()),
+            (PROTOCOL_VERSION_HEADER, protocol_version.as_deref()),
+        ] {
+            if let Some(value) = value.and_then(|v| HeaderValue::from_str(v).ok()) {
+                headers.insert(name, value);
+            }
+        }
+        headers
+    }
+
+    async fn post(&self, message: &Value) -> AppResult<reqwest::Response> {
+        if self.is_closed() {
+            return Err(transport_error(&self.name, "session closed"));
+        }
+        let response = self
+            .http
+            .post(self.url.clone())
+            .headers(self.session_headers())
+            .header(ACCEPT, "application/json, text/event-stream")
+            .json(message)
+            .send()
+            .await
+            .map_err(|e| transport_error(&self.name, e))?;
+
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND && self.session_id.read().unwrap().is_some() {
+            // The server dropped our session; a new one has to be initialized
+            self.closed.store(true, Ordering::SeqCst);
+            return Err(transport_error(&self.name, "session expired"));
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(transport_error(
+                &self.name,
+                format!("request failed with {}: {}", status, body),
+            ));
+        }
+
+        if let Some(session_id) = response
+            .headers()
+            .get(SESSION_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+        {
+            *self.session_id.write().unwrap() = Some(session_id.to_string());
+        }
+        Ok(response)
+    }
+
+    async fn read_response(&self, response: reqwest::Response, id: u64) -> AppResult<Value> {
+        let is_event_stream = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/event-stream"));
+
+        if !is_event_stream {
+            let body: Value = response
+                .json()
+                .await
+                .map_err(|e| transport_error(&self.name, format!("invalid response: {}", e)))?;
+            return find_response(body, id)
+                .ok_or_else(|| transport_error(&self.name, "response is missing"));
+        }
+
+        let mut events = response.bytes_stream().eventsource();
+        while let Some(event) = events.next().await {
+            let event = event.map_err(|e| transport_error(&self.name, e))?;
+            let Ok(message) = serde_json::from_str::<Value>(&event.data) else {
+                continue;
+            };
+            if let Some(reply) = server_request_reply(&message) {
+                self.post(&reply).await?;
+                continue;
+            }
+            if let Some(response) = find_response(message, id) {
+                return Ok(response);
+            }
+        }
+        Err(transport_error(
+            &self.name,
+            "stream ended without a response",
+        ))
+    }
+}
+
+#[async_trait]
+impl Transport for StreamableHttpTransport {
+    async fn request(&self, message: Value, timeout: Duration) -> AppResult<Value> {
+        let id = message_id(&message);
+        tokio::time::timeout(timeout, async {
+            let response = self.post(&message).await?;
+            self.read_response(response, id).await
+        })
+        .await
+        .map_err(|_| {
+            AppError::Timeout(format!(
+                "MCP server '{}' did not respond within {}s",
+                self.name,
+                timeout.as_secs()
+            ))
+        })?
+    }
+
+    async fn notify(&self, message: Value) -> AppResult<()> {
+        self.post(&message).await.map(|_| ())
+    }
+
+    fn set_protocol_version(&self, version: &str) {
+        *self.protocol_version.write().unwrap() = Some(version.to_string());
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    async fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        if self.session_id.read().unwrap().is_some() {
+            // Explicitly terminate the session; servers may not support it
+            let _ = self
+                .http
+                .delete(self.url.clone())
+                .headers(self.session_headers())
+                .send()
+                .await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dispatcher_routes_responses_and_answers_pings() {
+        let dispatcher = Dispatcher::default();
+        let mut rx = dispatcher.register(7);
+
+        let replies = dispatcher.dispatch(json!([
+            { "jsonrpc": "2.0", "method": "notifications/message", "params": {} },
+            { "jsonrpc": "2.0", "id": "srv-1", "method": "ping" },
+            { "jsonrpc": "2.0", "id": 7, "result": { "ok": true } },
+        ]));
+
+        assert_eq!(
+            replies,
+            vec![json!({ "jsonrpc": "2.0", "id": "srv-1", "result": {} })]
+        );
+        assert_eq!(rx.try_recv().unwrap()["result"]["ok"], true);
+
+        dispatcher.close();
+        assert!(dispatcher.is_closed());
+    }
+}
@@ -49,7 +49,7 @@ impl<'a> MemoryService<'a> {
     pub async fn get_memory_by_id(&self, id: &str) -> AppResult<Option<Memory>> {
         let result = sqlx::query_as::<_, Memory>(
             r#"
-            SELECT id, user_id, content, meta, created_at, updated_at
+            SELECT id, user_id, content, COALESCE(meta, 'null') as meta, created_at, updated_at
             FROM memory
             WHERE id = $1
             "#,
@@ -64,7 +64,7 @@ impl<'a> MemoryService<'a> {
     pub async fn get_memories_by_user_id(&self, user_id: &str) -> AppResult<Vec<Memory>> {
         let memories = sqlx::query_as::<_, Memory>(
             r#"
-            SELECT id, user_id, content, meta, created_at, updated_at
+            SELECT id, user_id, content, COALESCE(meta, 'null') as meta, created_at, updated_at
             FROM memory
             WHERE user_id = $1
             ORDER BY updated_at DESC
@@ -80,7 +80,7 @@ impl<'a> MemoryService<'a> {
     pub async fn get_all_memories(&self) -> AppResult<Vec<Memory>> {
         let memories = sqlx::query_as::<_, Memory>(
             r#"
-            SELECT id, user_id, content, meta, created_at, updated_at
+            SELECT id, user_id, content, COALESCE(meta, 'null') as meta, created_at, updated_at
             FROM memory
             ORDER BY updated_at DESC
             "#,
@@ -154,7 +154,7 @@ impl<'a> MemoryService<'a> {
 
         let memories = sqlx::query_as::<_, Memory>(
             r#"
-            SELECT id, user_id, content, meta, created_at, updated_at
+            SELECT id, user_id, content, COALESCE(meta, 'null') as meta, created_at, updated_at
             FROM memory
             WHERE user_id = $1 AND content LIKE $2
             ORDER BY updated_at DESC
@@ -1,4 +1,5 @@
 pub mod audio;
+pub mod audit;
 pub mod auth;
 pub mod channel;
 pub mod chat;
@@ -19,14 +20,21 @@ pub mod models;
 pub mod note;
 pub mod oauth;
 pub mod oauth_client;
+pub mod ollama;
+pub mod openapi;
 pub mod pipeline;
 pub mod prompt;
+pub mod providers;
+pub mod quota;
 pub mod rag;
 pub mod sandbox_executor;
 pub mod static_files;
+pub mod stt;
 pub mod tool;
 pub mod tool_runtime;
 pub mod user;
+#[cfg(feature = "whisper")]
+pub mod whisper;
 
 pub use auth::*;
 pub use config::*;
@@ -1,5 +1,6 @@
 use crate::config::Config;
 use crate::error::{AppError, AppResult};
+use crate::services::providers::Provider;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
@@ -103,6 +104,9 @@ impl ModelService {
             }
         }
 
+        // Fetch Ollama models
+        all_models.extend(self.fetch_ollama_models().await);
+
         // Fetch function/pipeline models
         match self.fetch_function_models(db).await {
             Ok(models) => all_models.extend(models),
@@ -135,6 +139,9 @@ impl ModelService {
             }
         }
 
+        // Fetch Ollama models
+        base_models.extend(self.fetch_ollama_models().await);
+
         // Fetch function models
         match self.fetch_function_models(db).await {
             Ok(models) => base_models.extend(models),
@@ -230,6 +237,42 @@ impl ModelService {
         Ok(all_models)
     }
 
+    /// Fetch models from the configured Ollama connections
+    async fn fetch_ollama_models(&self) -> Vec<Model> {
+        crate::services::ollama::get_all_models(&self.client, &self.config)
+            .await
+            .into_iter()
+            .filter_map(|model| {
+                let id = model.get("model").or_else(|| model.get("name"))?.as_str()?;
+                Some(Model {
+                    id: id.to_string(),
+                    name: Some(
+                        model
+                            .get("name")
+                            .and_then(|n| n.as_str())
+                            .unwrap_or(id)
+                            .to_string(),
+                    ),
+                    object: "model".to_string(),
+                    created: model
+                        .get("modified_at")
+                        .and_then(|m| m.as_str())
+                        .and_then(|m| chrono::DateTime::parse_from_rfc3339(m).ok())
+                        .map(|m| m.timestamp())
+                        .unwrap_or(0),
+                    owned_by: "ollama".to_string(),
+                    info: Some(ModelInfo {
+                        meta: None,
+                        params: Some(json!({ "urlIdx": model["urls"][0] })),
+                    }),
+                    pipeline: None,
+                    tags: None,
+                    arena: None,
+                })
+            })
+            .collect()
+    }
+
     /// Fetch models from a single OpenAI-compatible endpoint
     async fn fetch_models_from_endpoint(
         &self,
@@ -242,19 +285,22 @@ impl ModelService {
             .and_then(|v| v.as_bool())
             .unwrap_or(false);
 
-        let url = if is_azure {
+        let api_config = config.map(|c| Value::Object(c.clone())).unwrap_or_default();
+        let provider = Provider::from_api_config(&api_config);
+
+        let mut request = if is_azure {
             let api_version = config
                 .and_then(|c| c.get("api_version"))
                 .and_then(|v| v.as_str())
                 .unwrap_or("2023-05-15");
-            format!("{}/models?api-version={}", base_url, api_version)
+            self.client
+                .get(format!("{}/models?api-version={}", base_url, api_version))
         } else {
-            format!("{}/models", base_url)
+            // Bearer token for OpenAI-compatible APIs, provider headers otherwise
+            provider.models_request(&self.client, base_url, api_key, &api_config)
         };
 
-        let mut request = self.client.get(&url);
-
-        // Add authentication based on config
+        // Azure authentication based on config
         if is_azure {
             let auth_type = config
                 .and_then(|c| c.get("auth_type"))
@@ -271,11 +317,6 @@ impl ModelService {
                     request = request.header("api-key", api_key);
                 }
             }
-        } else {
-            // Standard Bearer token for OpenAI and compatible APIs
-            if !api_key.is_empty() {
-                request = request.header("Authorization", format!("Bearer {}", api_key));
-            }
         }
 
         let response = request.send().await?;
@@ -291,40 +332,36 @@ impl ModelService {
 
         let response_data: Value = response.json().await?;
 
-        let models: Vec<Model> = response_data
-            .get("data")
-            .and_then(|d| d.as_array())
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|v| {
-                        let id = v.get("id")?.as_str()?;
-                        let name = v
-                            .get("name")
-                            .and_then(|n| n.as_str().map(|s| s.to_string()))
-                            .or_else(|| Some(id.to_string()));
-                        Some(Model {
-                            id: id.to_string(),
-                            name,
-                            object: v
-                                .get("object")
-                                .and_then(|o| o.as_str())
-                                .unwrap_or("model")
-                                .to_string(),
-                            created: v.get("created").and_then(|c| c.as_i64()).unwrap_or(0),
-                            owned_by: v
-                                .get("owned_by")
-                                .and_then(|o| o.as_str())
-                                .unwrap_or("openai")
-                                .to_string(),
-                            info: None,
-                            pipeline: None,
-                            tags: None,
-                            arena: None,
-                        })
-                    })
-                    .collect()
+        let models: Vec<Model> = provider
+            .openai_models(&response_data)
+            .iter()
+            .filter_map(|v| {
+                let id = v.get("id")?.as_str()?;
+                let name = v
+                    .get("name")
+                    .and_then(|n| n.as_str().map(|s| s.to_string()))
+                    .or_else(|| Some(id.to_string()));
+                Some(Model {
+                    id: id.to_string(),
+                    name,
+                    object: v
+                        .get("object")
+                        .and_then(|o| o.as_str())
+                        .unwrap_or("model")
+                        .to_string(),
+                    created: v.get("created").and_then(|c| c.as_i64()).unwrap_or(0),
+                    owned_by: v
+                        .get("owned_by")
+                        .and_then(|o| o.as_str())
+                        .unwrap_or("openai")
+                        .to_string(),
+                    info: None,
+                    pipeline: None,
+                    tags: None,
+                    arena: None,
+                })
             })
-            .unwrap_or_default();
+            .collect();
 
         Ok(models)
     }
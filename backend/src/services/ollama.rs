@@ -0,0 +1,816 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::stream::{Stream, StreamExt};
+use reqwest::{Client, Method, RequestBuilder};
+use serde_json::{json, Map, Value};
+use tracing::warn;
+
+use crate::config::Config;
+use crate::error::{AppError, AppResult};
+use crate::utils::time::current_timestamp_seconds;
+
+/// Listing models should not hold up `/api/models` when a server is down
+const MODEL_LIST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// One entry of `OLLAMA_BASE_URLS` with its per-connection settings
+#[derive(Debug, Clone)]
+pub struct OllamaConnection {
+    pub idx: usize,
+    pub base_url: String,
+    pub key: String,
+    pub config: Value,
+}
+
+impl OllamaConnection {
+    /// Connection at `idx`, or `None` when out of range
+    pub fn from_config(config: &Config, idx: usize) -> Option<Self> {
+        let base_url = config.ollama_base_urls.get(idx)?;
+        let api_config = config
+            .ollama_api_configs
+            .get(idx.to_string())
+            .or_else(|| config.ollama_api_configs.get(base_url))
+            .cloned()
+            .unwrap_or_else(|| json!({}));
+
+        Some(OllamaConnection {
+            idx,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            key: api_config
+                .get("key")
+                .and_then(|k| k.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            config: api_config,
+        })
+    }
+
+    /// All connections not disabled through `OLLAMA_API_CONFIGS`
+    pub fn all(config: &Config) -> Vec<Self> {
+        if !config.enable_ollama_api {
+            return Vec::new();
+        }
+        (0..config.ollama_base_urls.len())
+            .filter_map(|idx| Self::from_config(config, idx))
+            .filter(|conn| conn.enabled())
+            .collect()
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config
+            .get("enable")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true)
+    }
+
+    /// Ollama's OpenAI-compatible API, used for follow-up requests (tool
+    /// results, title generation) that only speak the OpenAI format
+    pub fn openai_base_url(&self) -> String {
+        format!("{}/v1", self.base_url)
+    }
+
+    pub fn request(&self, client: &Client, method: Method, path: &str) -> RequestBuilder {
+        let request = client.request(method, format!("{}{}", self.base_url, path));
+        if self.key.is_empty() {
+            request
+        } else {
+            request.bearer_auth(&self.key)
+        }
+    }
+}
+
+async fn send_json(request: RequestBuilder) -> AppResult<Value> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| AppError::ExternalServiceError(format!("Ollama: {}", e)))?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(AppError::ExternalServiceError(format!(
+            "Ollama error ({}): {}",
+            status,
+            ollama_error_message(&body)
+        )));
+    }
+    // Some endpoints (e.g. delete) answer with an empty body
+    let body = response
+        .bytes()
+        .await
+        .map_err(|e| AppError::ExternalServiceError(format!("Ollama: {}", e)))?;
+    if body.is_empty() {
+        return Ok(json!({}));
+    }
+    serde_json::from_slice(&body)
+        .map_err(|e| AppError::ExternalServiceError(format!("Invalid Ollama response: {}", e)))
+}
+
+/// Ollama reports errors as `{"error": "..."}`
+pub fn ollama_error_message(body: &str) -> String {
+    serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|v| v.get("error").and_then(|e| e.as_str()).map(String::from))
+        .unwrap_or_else(|| body.to_string())
+}
+
+/// `GET /api/tags` on a single connection
+pub async fn list_models(client: &Client, conn: &OllamaConnection) -> AppResult<Vec<Value>> {
+    let result = send_json(
+        conn.request(client, Method::GET, "/api/tags")
+            .timeout(MODEL_LIST_TIMEOUT),
+    )
+    .await?;
+    Ok(result["models"].as_array().cloned().unwrap_or_default())
+}
+
+/// Models from every enabled connection, merged by name
+///
+/// Each entry is the `/api/tags` object with a `urls` list of the connection
+/// indices that serve it, in configuration order.
+pub async fn get_all_models(client: &Client, config: &Config) -> Vec<Value> {
+    let connections = OllamaConnection::all(config);
+    let responses = futures::future::join_all(
+        connections
+            .iter()
+            .map(|conn| async move { (conn, list_models(client, conn).await) }),
+    )
+    .await;
+
+    let mut merged: Vec<Value> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    for (conn, result) in responses {
+        let models = match result {
+            Ok(models) => models,
+            Err(e) => {
+                warn!("Failed to list Ollama models from {}: {}", conn.base_url, e);
+                continue;
+            }
+        };
+
+        let allowed: Option<Vec<&str>> = conn
+            .config
+            .get("model_ids")
+            .and_then(|ids| ids.as_array())
+            .filter(|ids| !ids.is_empty())
+            .map(|ids| ids.iter().filter_map(|id| id.as_str()).collect());
+
+        for mut model in models {
+            let Some(name) = model
+                .get("model")
+                .or_else(|| model.get("name"))
+                .and_then(|n| n.as_str())
+                .map(String::from)
+            else {
+                continue;
+            };
+            if allowed
+                .as_ref()
+                .is_some_and(|ids| !ids.contains(&name.as_str()))
+            {
+                continue;
+            }
+
+            match positions.get(&name) {
+                Some(&pos) => {
+                    if let Some(urls) = merged[pos]["urls"].as_array_mut() {
+                        urls.push(json!(conn.idx));
+                    }
+                }
+                None => {
+                    model["urls"] = json!([conn.idx]);
+                    positions.insert(name, merged.len());
+                    merged.push(model);
+                }
+            }
+        }
+    }
+    merged
+}
+
+pub async fn get_version(client: &Client, conn: &OllamaConnection) -> AppResult<Value> {
+    send_json(conn.request(client, Method::GET, "/api/version")).await
+}
+
+pub async fn show_model(client: &Client, conn: &OllamaConnection, model: &str) -> AppResult<Value> {
+    send_json(
+        conn.request(client, Method::POST, "/api/show")
+            .json(&json!({ "model": model })),
+    )
+    .await
+}
+
+pub async fn delete_model(client: &Client, conn: &OllamaConnection, model: &str) -> AppResult<()> {
+    send_json(
+        conn.request(client, Method::DELETE, "/api/delete")
+            .json(&json!({ "model": model })),
+    )
+    .await
+    .map(|_| ())
+}
+
+/// Start a pull; the response streams NDJSON progress lines
+pub async fn pull_model(
+    client: &Client,
+    conn: &OllamaConnection,
+    model: &str,
+) -> AppResult<reqwest::Response> {
+    let response = conn
+        .request(client, Method::POST, "/api/pull")
+        .json(&json!({ "model": model, "stream": true }))
+        .send()
+        .await
+        .map_err(|e| AppError::ExternalServiceError(format!("Ollama: {}", e)))?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(AppError::ExternalServiceError(format!(
+            "Ollama error ({}): {}",
+            status,
+            ollama_error_message(&body)
+        )));
+    }
+    Ok(response)
+}
+
+/// OpenAI sampling parameters and their Ollama `options` names
+const OPTION_MAPPINGS: [(&str, &str); 9] = [
+    ("temperature", "temperature"),
+    ("top_p", "top_p"),
+    ("top_k", "top_k"),
+    ("min_p", "min_p"),
+    ("seed", "seed"),
+    ("stop", "stop"),
+    ("frequency_penalty", "frequency_penalty"),
+    ("presence_penalty", "presence_penalty"),
+    ("max_tokens", "num_predict"),
+];
+
+/// Translate an OpenAI chat completion request into an Ollama `/api/chat` body
+pub fn openai_to_ollama_chat(payload: &Value) -> Value {
+    let mut body = json!({
+        "model": payload.get("model").cloned().unwrap_or(Value::Null),
+        "messages": convert_messages(
+            payload
+                .get("messages")
+                .and_then(|m| m.as_array())
+                .map(Vec::as_slice)
+                .unwrap_or_default(),
+        ),
+        // OpenAI defaults to a single response, Ollama to streaming
+        "stream": payload.get("stream").and_then(|s| s.as_bool()).unwrap_or(false),
+    });
+
+    let mut options = payload
+        .get("options")
+        .and_then(|o| o.as_object())
+        .cloned()
+        .unwrap_or_default();
+    for (openai_key, ollama_key) in OPTION_MAPPINGS {
+        if let Some(value) = payload.get(openai_key).filter(|v| !v.is_null()) {
+            options.insert(ollama_key.to_string(), value.clone());
+        }
+    }
+    if let Some(value) = payload
+        .get("max_completion_tokens")
+        .filter(|v| !v.is_null())
+    {
+        options.insert("num_predict".to_string(), value.clone());
+    }
+    if !options.is_empty() {
+        body["options"] = Value::Object(options);
+    }
+
+    if let Some(tools) = payload.get("tools").filter(|t| t.is_array()) {
+        body["tools"] = tools.clone();
+    }
+    if let Some(format) = payload.get("response_format") {
+        match format.get("type").and_then(|t| t.as_str()) {
+            Some("json_object") => body["format"] = json!("json"),
+            Some("json_schema") => {
+                if let Some(schema) = format.pointer("/json_schema/schema") {
+                    body["format"] = schema.clone();
+                }
+            }
+            _ => {}
+        }
+    }
+    for key in ["keep_alive", "think"] {
+        if let Some(value) = payload.get(key) {
+            body[key] = value.clone();
+        }
+    }
+    body
+}
+
+fn convert_messages(messages: &[Value]) -> Vec<Value> {
+    // Ollama identifies tool results by function name rather than call id
+    let mut tool_names: HashMap<&str, &str> = HashMap::new();
+
+    messages
+        .iter()
+        .map(|message| {
+            let mut converted = Map::new();
+            converted.insert(
+                "role".to_string(),
+                message.get("role").cloned().unwrap_or(json!("user")),
+            );
+
+            let mut images = Vec::new();
+            let content = match message.get("content") {
+                Some(Value::String(text)) => text.clone(),
+                Some(Value::Array(parts)) => {
+                    let mut texts = Vec::new();
+                    for part in parts {
+                        match part.get("type").and_then(|t| t.as_str()) {
+                            Some("text") => {
+                                if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
+                                    texts.push(text);
+                                }
+                            }
+                            Some("image_url") => {
+                                let url = part
+                                    .pointer("/image_url/url")
+                                    .and_then(|u| u.as_str())
+                                    .unwrap_or_default();
+                                match url.split_once(";base64,") {
+                                    Some((_, data)) => images.push(json!(data)),
+                                    None => {
+                                        warn!("Ollama only accepts inline images, skipping {}", url)
+                                    }
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                    texts.join("\n")
+                }
+                _ => String::new(),
+            };
+            converted.insert("content".to_string(), json!(content));
+            if !images.is_empty() {
+                converted.insert("images".to_string(), Value::Array(images));
+            }
+
+            if let Some(tool_calls) = message.get("tool_calls").and_then(|t| t.as_array()) {
+                let calls: Vec<Value> = tool_calls
+                    .iter()
+                    .map(|call| {
+                        let name = call.pointer("/function/name").and_then(|n| n.as_str());
+                        if let (Some(id), Some(name)) =
+                            (call.get("id").and_then(|i| i.as_str()), name)
+                        {
+                            tool_names.insert(id, name);
+                        }
+                        // OpenAI sends arguments as a JSON string, Ollama as an object
+                        let arguments = match call.pointer("/function/arguments") {
+                            Some(Value::String(args)) => {
+                                serde_json::from_str(args).unwrap_or_else(|_| json!({}))
+                            }
+                            Some(args) => args.clone(),
+                            None => json!({}),
+                        };
+                        json!({ "function": { "name": name, "arguments": arguments } })
+                    })
+                    .collect();
+                converted.insert("tool_calls".to_string(), Value::Array(calls));
+            }
+
+            if let Some(name) = message
+                .get("tool_call_id")
+                .and_then(|id| id.as_str())
+                .and_then(|id| tool_names.get(id))
+            {
+                converted.insert("tool_name".to_string(), json!(name));
+            }
+
+            Value::Object(converted)
+        })
+        .collect()
+}
+
+fn openai_tool_calls(calls: &[Value], first_index: usize) -> Vec<Value> {
+    calls
+        .iter()
+        .enumerate()
+        .map(|(i, call)| {
+            let arguments = match call.pointer("/function/arguments") {
+                Some(Value::String(args)) => args.clone(),
+                Some(args) => args.to_string(),
+                None => "{}".to_string(),
+            };
+            json!({
+                "index": first_index + i,
+                "id": format!("call_{}", uuid::Uuid::new_v4().simple()),
+                "type": "function",
+                "function": {
+                    "name": call.pointer("/function/name").cloned().unwrap_or(Value::Null),
+                    "arguments": arguments,
+                },
+            })
+        })
+        .collect()
+}
+
+fn usage(response: &Value) -> Value {
+    let prompt_tokens = response["prompt_eval_count"].as_u64().unwrap_or(0);
+    let completion_tokens = response["eval_count"].as_u64().unwrap_or(0);
+    json!({
+        "prompt_tokens": prompt_tokens,
+        "completion_tokens": completion_tokens,
+        "total_tokens": prompt_tokens + completion_tokens,
+    })
+}
+
+fn finish_reason(response: &Value, had_tool_calls: bool) -> Value {
+    if had_tool_calls {
+        return json!("tool_calls");
+    }
+    match response["done_reason"].as_str() {
+        Some("length") => json!("length"),
+        _ => json!("stop"),
+    }
+}
+
+/// Translate a non-streaming `/api/chat` response into a chat completion
+pub fn ollama_to_openai_response(response: &Value) -> Value {
+    let message = &response["message"];
+    let tool_calls = message["tool_calls"]
+        .as_array()
+        .map(|calls| openai_tool_calls(calls, 0))
+        .unwrap_or_default();
+
+    let mut openai_message = json!({
+        "role": "assistant",
+        "content": message["content"].as_str().unwrap_or_default(),
+    });
+    if let Some(thinking) = message["thinking"].as_str().filter(|t| !t.is_empty()) {
+        openai_message["reasoning_content"] = json!(thinking);
+    }
+    let had_tool_calls = !tool_calls.is_empty();
+    if had_tool_calls {
+        openai_message["tool_calls"] = Value::Array(tool_calls);
+    }
+
+    json!({
+        "id": format!("chatcmpl-{}", uuid::Uuid::new_v4()),
+        "object": "chat.completion",
+        "created": current_timestamp_seconds(),
+        "model": response["model"],
+        "choices": [{
+            "index": 0,
+            "message": openai_message,
+            "finish_reason": finish_reason(response, had_tool_calls),
+        }],
+        "usage": usage(response),
+    })
+}
+
+/// Incrementally turns Ollama's NDJSON chat stream into OpenAI SSE chunks
+pub struct StreamTranslator {
+    id: String,
+    created: i64,
+    buffer: Vec<u8>,
+    tool_calls: usize,
+    finished: bool,
+}
+
+impl Default for StreamTranslator {
+    fn default() -> Self {
+        StreamTranslator {
+            id: format!("chatcmpl-{}", uuid::Uuid::new_v4()),
+            created: current_timestamp_seconds(),
+            buffer: Vec::new(),
+            tool_calls: 0,
+            finished: false,
+        }
+    }
+}
+
+impl StreamTranslator {
+    /// Feed raw bytes; returns SSE text for every complete line received
+    pub fn push(&mut self, bytes: &[u8]) -> String {
+        self.buffer.extend_from_slice(bytes);
+        let mut out = String::new();
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            self.translate_line(&line, &mut out);
+        }
+        out
+    }
+
+    /// Flush a trailing line without newline and terminate the SSE stream
+    pub fn finish(&mut self) -> String {
+        let mut out = String::new();
+        let rest = std::mem::take(&mut self.buffer);
+        self.translate_line(&rest, &mut out);
+        if !self.finished {
+            self.finished = true;
+            out.push_str("data: [DONE]\n\n");
+        }
+        out
+    }
+
+    fn translate_line(&mut self, line: &[u8], out: &mut String) {
+        if self.finished {
+            return;
+        }
+        let Ok(line) = std::str::from_utf8(line) else {
+            return;
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        let chunk: Value = match serde_json::from_str(line) {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                warn!("Skipping malformed Ollama stream line: {}", e);
+                return;
+            }
+        };
+
+        if let Some(error) = chunk.get("error") {
+            let message = error
+                .as_str()
+                .map(String::from)
+                .unwrap_or_else(|| error.to_string());
+            out.push_str(&sse_data(&json!({ "error": { "message": message } })));
+            out.push_str("data: [DONE]\n\n");
+            self.finished = true;
+            return;
+        }
+
+        let message = &chunk["message"];
+        let mut delta = Map::new();
+        if let Some(content) = message["content"].as_str().filter(|c| !c.is_empty()) {
+            delta.insert("content".to_string(), json!(content));
+        }
+        if let Some(thinking) = message["thinking"].as_str().filter(|t| !t.is_empty()) {
+            delta.insert("reasoning_content".to_string(), json!(thinking));
+        }
+        if let Some(calls) = message["tool_calls"].as_array().filter(|c| !c.is_empty()) {
+            delta.insert(
+                "tool_calls".to_string(),
+                Value::Array(openai_tool_calls(calls, self.tool_calls)),
+            );
+            self.tool_calls += calls.len();
+        }
+
+        let done = chunk["done"].as_bool().unwrap_or(false);
+        if delta.is_empty() && !done {
+            return;
+        }
+
+        let mut openai_chunk = json!({
+            "id": self.id,
+            "object": "chat.completion.chunk",
+            "created": self.created,
+            "model": chunk["model"],
+            "choices": [{
+                "index": 0,
+                "delta": delta,
+                "finish_reason": if done { finish_reason(&chunk, self.tool_calls > 0) } else { Value::Null },
+            }],
+        });
+        if done {
+            openai_chunk["usage"] = usage(&chunk);
+        }
+        out.push_str(&sse_data(&openai_chunk));
+
+        if done {
+            out.push_str("data: [DONE]\n\n");
+            self.finished = true;
+        }
+    }
+}
+
+fn sse_data(value: &Value) -> String {
+    format!("data: {}\n\n", value)
+}
+
+/// Wrap an Ollama NDJSON chat stream as an OpenAI-style SSE byte stream
+pub fn ndjson_to_openai_sse<S>(stream: S) -> impl Stream<Item = Result<Bytes, reqwest::Error>>
+where
+    S: Stream<Item = Result<Bytes, reqwest::Error>> + Send + 'static,
+{
+    futures::stream::unfold(
+        (Box::pin(stream), StreamTranslator::default(), false),
+        |(mut stream, mut translator, ended)| async move {
+            if ended {
+                return None;
+            }
+            loop {
+                match stream.next().await {
+                    Some(Ok(bytes)) => {
+                        let out = translator.push(&bytes);
+                        if !out.is_empty() {
+                            return Some((Ok(Bytes::from(out)), (stream, translator, false)));
+                        }
+                    }
+                    Some(Err(e)) => return Some((Err(e), (stream, translator, true))),
+                    None => {
+                        let out = translator.finish();
+                        return (!out.is_empty())
+                            .then(|| (Ok(Bytes::from(out)), (stream, translator, true)));
+                    }
+                }
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+
+    fn sse_events(sse: &str) -> Vec<Value> {
+        sse.lines()
+            .filter_map(|l| l.strip_prefix("data: "))
+            .filter(|d| *d != "[DONE]")
+            .map(|d| serde_json::from_str(d).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_openai_to_ollama_chat() {
+        let body = openai_to_ollama_chat(&json!({
+            "model": "llama3.2",
+            "stream": true,
+            "temperature": 0.2,
+            "max_tokens": 64,
+            "response_format": {"type": "json_object"},
+            "messages": [
+                {"role": "user", "content": [
+                    {"type": "text", "text": "What is this?"},
+                    {"type": "image_url", "image_url": {"url": "data:image/png;base64,aGk="}},
+                ]},
+                {"role": "assistant", "content": "", "tool_calls": [{
+                    "id": "call_1", "type": "function",
+                    "function": {"name": "get_weather", "arguments": "{\"city\":\"Oslo\"}"},
+                }]},
+                {"role": "tool", "tool_call_id": "call_1", "content": "rainy"},
+            ],
+        }));
+
+        assert_eq!(body["stream"], true);
+        assert_eq!(body["format"], "json");
+        assert_eq!(
+            body["options"],
+            json!({"temperature": 0.2, "num_predict": 64})
+        );
+        assert_eq!(body["messages"][0]["content"], "What is this?");
+        assert_eq!(body["messages"][0]["images"], json!(["aGk="]));
+        assert_eq!(
+            body["messages"][1]["tool_calls"][0]["function"]["arguments"],
+            json!({"city": "Oslo"})
+        );
+        assert_eq!(body["messages"][2]["tool_name"], "get_weather");
+    }
+
+    #[test]
+    fn test_stream_translation_across_chunk_boundaries() {
+        let ndjson = concat!(
+            r#"{"model":"llama3.2","message":{"role":"assistant","content":"Hel"},"done":false}"#,
+            "\n",
+            r#"{"model":"llama3.2","message":{"role":"assistant","content":"lo"},"done":false}"#,
+            "\n",
+            r#"{"model":"llama3.2","message":{"role":"assistant","content":""},"done":true,"done_reason":"stop","prompt_eval_count":5,"eval_count":2}"#,
+            "\n",
+        );
+
+        let mut translator = StreamTranslator::default();
+        let mut sse = String::new();
+        // Split mid-line to exercise buffering
+        for piece in ndjson.as_bytes().chunks(7) {
+            sse.push_str(&translator.push(piece));
+        }
+        sse.push_str(&translator.finish());
+
+        let events = sse_events(&sse);
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0]["choices"][0]["delta"]["content"], "Hel");
+        assert_eq!(events[1]["choices"][0]["delta"]["content"], "lo");
+        assert_eq!(events[2]["choices"][0]["finish_reason"], "stop");
+        assert_eq!(events[2]["usage"]["total_tokens"], 7);
+        assert_eq!(events[0]["id"], events[2]["id"]);
+        assert_eq!(sse.matches("data: [DONE]").count(), 1);
+    }
+
+    #[test]
+    fn test_stream_translation_of_tool_calls_and_errors() {
+        let mut translator = StreamTranslator::default();
+        let sse = translator.push(
+            concat!(
+                r#"{"model":"m","message":{"role":"assistant","content":"","tool_calls":[{"function":{"name":"get_weather","arguments":{"city":"Oslo"}}}]},"done":false}"#,
+                "\n",
+                r#"{"model":"m","message":{"role":"assistant","content":""},"done":true}"#,
+                "\n",
+            )
+            .as_bytes(),
+        );
+        let events = sse_events(&sse);
+        let call = &events[0]["choices"][0]["delta"]["tool_calls"][0];
+        assert_eq!(call["index"], 0);
+        assert_eq!(call["function"]["arguments"], r#"{"city":"Oslo"}"#);
+        assert_eq!(events[1]["choices"][0]["finish_reason"], "tool_calls");
+
+        let mut translator = StreamTranslator::default();
+        let sse = translator.push(b"{\"error\":\"model not found\"}\n");
+        assert_eq!(sse_events(&sse)[0]["error"]["message"], "model not found");
+        assert!(translator.finish().is_empty());
+    }
+
+    #[test]
+    fn test_ollama_to_openai_response() {
+        let response = ollama_to_openai_response(&json!({
+            "model": "llama3.2",
+            "message": {"role": "assistant", "content": "Hi there", "thinking": "greet"},
+            "done": true,
+            "done_reason": "length",
+            "prompt_eval_count": 3,
+            "eval_count": 4,
+        }));
+        assert_eq!(response["object"], "chat.completion");
+        assert_eq!(response["choices"][0]["message"]["content"], "Hi there");
+        assert_eq!(
+            response["choices"][0]["message"]["reasoning_content"],
+            "greet"
+        );
+        assert_eq!(response["choices"][0]["finish_reason"], "length");
+        assert_eq!(response["usage"]["completion_tokens"], 4);
+    }
+
+    /// Minimal stand-in for an Ollama server serving `models`
+    async fn stand_in(models: &'static [&'static str]) -> String {
+        let server = HttpServer::new(move || {
+            App::new().default_service(web::to(move |req: HttpRequest, body: web::Bytes| async move {
+                let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+                match req.path() {
+                    "/api/tags" => HttpResponse::Ok().json(json!({
+                        "models": models.iter().map(|m| json!({"name": m, "model": m})).collect::<Vec<_>>(),
+                    })),
+                    "/api/show" if models.contains(&body["model"].as_str().unwrap_or_default()) => {
+                        HttpResponse::Ok().json(json!({"template": "{{ .Prompt }}"}))
+                    }
+                    "/api/chat" => HttpResponse::Ok().content_type("application/x-ndjson").body(concat!(
+                        r#"{"model":"llama3.2","message":{"role":"assistant","content":"Hi"},"done":false}"#,
+                        "\n",
+                        r#"{"model":"llama3.2","message":{"role":"assistant","content":""},"done":true,"eval_count":1}"#,
+                        "\n",
+                    )),
+                    _ => HttpResponse::NotFound().json(json!({"error": "model not found"})),
+                }
+            }))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+        format!("http://{}", addr)
+    }
+
+    #[actix_web::test]
+    async fn test_client_against_stand_in() {
+        let first = stand_in(&["llama3.2", "qwen3"]).await;
+        let second = stand_in(&["qwen3"]).await;
+
+        let mut config = Config::from_env().unwrap();
+        config.enable_ollama_api = true;
+        config.ollama_base_urls = vec![first, second, "http://127.0.0.1:1".to_string()];
+        config.ollama_api_configs = json!({"2": {"enable": false}});
+        let client = Client::new();
+
+        assert_eq!(OllamaConnection::all(&config).len(), 2);
+        let models = get_all_models(&client, &config).await;
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[0]["urls"], json!([0]));
+        assert_eq!(models[1]["urls"], json!([0, 1]));
+
+        let conn = OllamaConnection::from_config(&config, 1).unwrap();
+        assert!(show_model(&client, &conn, "qwen3").await.is_ok());
+        let err = show_model(&client, &conn, "llama3.2").await.unwrap_err();
+        assert!(err.to_string().contains("model not found"));
+
+        let response = conn
+            .request(&client, Method::POST, "/api/chat")
+            .json(&openai_to_ollama_chat(
+                &json!({"model": "llama3.2", "stream": true, "messages": []}),
+            ))
+            .send()
+            .await
+            .unwrap();
+        let sse: Vec<Bytes> = ndjson_to_openai_sse(response.bytes_stream())
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        let sse = String::from_utf8(sse.concat()).unwrap();
+        let events = sse_events(&sse);
+        assert_eq!(events[0]["choices"][0]["delta"]["content"], "Hi");
+        assert_eq!(events[1]["choices"][0]["finish_reason"], "stop");
+        assert!(sse.ends_with("data: [DONE]\n\n"));
+    }
+}
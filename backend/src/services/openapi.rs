@@ -0,0 +1,714 @@
+//! OpenAPI tool servers: every operation in an OpenAPI 3 spec becomes a
+//! function tool, executed by mapping the model's arguments back onto the
+//! operation's path, query, header and JSON body parameters.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+use reqwest::{Client, Method};
+use serde_json::{json, Map, Value};
+use url::Url;
+
+use crate::error::{AppError, AppResult};
+use crate::retrieval::loaders::url::resolve_public;
+
+const DEFAULT_SPEC_PATH: &str = "openapi.json";
+
+/// How long a fetched spec is trusted before it is downloaded again
+const SPEC_CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// Request timeout of clients built for direct servers, matching the shared client
+const DIRECT_REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// `$ref` chains deeper than this are left unresolved (recursive schemas)
+const MAX_REF_DEPTH: usize = 16;
+
+type SpecCache = HashMap<String, (Instant, Arc<Vec<OpenApiTool>>)>;
+
+/// Parsed specs by spec URL
+static SPEC_CACHE: Lazy<RwLock<SpecCache>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Forget fetched specs, e.g. after the tool server connections changed
+pub fn clear_cache() {
+    SPEC_CACHE.write().unwrap().clear();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParamLocation {
+    Path,
+    Query,
+    Header,
+}
+
+/// How the JSON request body is assembled from the arguments
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RequestBody {
+    None,
+    /// Object body whose properties are flattened into the function parameters
+    Fields,
+    /// Any other body, passed as the `body` argument
+    Value,
+}
+
+/// One operation of an OpenAPI spec, exposed as a function tool
+#[derive(Debug, Clone)]
+pub struct OpenApiTool {
+    pub name: String,
+    pub description: String,
+    /// JSON schema of the function arguments
+    pub parameters: Value,
+    pub method: Method,
+    pub path: String,
+    params: Vec<(String, ParamLocation)>,
+    body: RequestBody,
+}
+
+impl OpenApiTool {
+    /// Function spec in the OpenAI `tools` format
+    pub fn to_openai_spec(&self) -> Value {
+        json!({
+            "name": self.name,
+            "description": self.description,
+            "parameters": self.parameters,
+        })
+    }
+}
+
+/// Follow local `$ref`s (`#/components/...`) through a schema
+fn resolve_refs(spec: &Value, value: &Value, depth: usize) -> Value {
+    if depth > MAX_REF_DEPTH {
+        return json!({});
+    }
+    match value {
+        Value::Object(obj) => {
+            if let Some(target) = obj
+                .get("$ref")
+                .and_then(|r| r.as_str())
+                .and_then(|r| r.strip_prefix('#'))
+                .and_then(|pointer| spec.pointer(pointer))
+            {
+                return resolve_refs(spec, target, depth + 1);
+            }
+            Value::Object(
+                obj.iter()
+                    .map(|(k, v)| (k.clone(), resolve_refs(spec, v, depth + 1)))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|v| resolve_refs(spec, v, depth + 1))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Function names may only hold `[a-zA-Z0-9_-]`, up to 64 characters
+fn function_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    name.trim_matches('_').chars().take(64).collect()
+}
+
+/// Turn every operation of an OpenAPI 3 spec into a function tool
+pub fn parse_spec(spec: &Value) -> Vec<OpenApiTool> {
+    let Some(paths) = spec.get("paths").and_then(|p| p.as_object()) else {
+        return vec![];
+    };
+
+    let mut tools = Vec::new();
+    for (path, item) in paths {
+        let item = resolve_refs(spec, item, 0);
+        let shared_params = item
+            .get("parameters")
+            .and_then(|p| p.as_array())
+            .cloned()
+            .unwrap_or_default();
+
+        for method in [
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ] {
+            let Some(operation) = item.get(method.as_str().to_lowercase()) else {
+                continue;
+            };
+
+            let mut properties = Map::new();
+            let mut required: Vec<Value> = Vec::new();
+            let mut params = Vec::new();
+
+            let own_params = operation
+                .get("parameters")
+                .and_then(|p| p.as_array())
+                .cloned()
+                .unwrap_or_default();
+            for param in shared_params.iter().chain(own_params.iter()) {
+                let Some(name) = param.get("name").and_then(|n| n.as_str()) else {
+                    continue;
+                };
+                let location = match param.get("in").and_then(|i| i.as_str()) {
+                    Some("path") => ParamLocation::Path,
+                    Some("query") => ParamLocation::Query,
+                    Some("header") => ParamLocation::Header,
+                    _ => continue,
+                };
+
+                let mut schema = param
+                    .get("schema")
+                    .cloned()
+                    .unwrap_or_else(|| json!({ "type": "string" }));
+                if let Some(description) = param.get("description") {
+                    schema["description"] = description.clone();
+                }
+                properties.insert(name.to_string(), schema);
+                if (location == ParamLocation::Path
+                    || param.get("required").and_then(|r| r.as_bool()) == Some(true))
+                    && !required.contains(&json!(name))
+                {
+                    required.push(json!(name));
+                }
+                params.retain(|(existing, _): &(String, ParamLocation)| existing != name);
+                params.push((name.to_string(), location));
+            }
+
+            let mut body = RequestBody::None;
+            if let Some(request_body) = operation.get("requestBody") {
+                let schema = request_body
+                    .pointer("/content/application~1json/schema")
+                    .cloned()
+                    .unwrap_or_default();
+                let body_required =
+                    request_body.get("required").and_then(|r| r.as_bool()) == Some(true);
+
+                match schema.get("properties").and_then(|p| p.as_object()) {
+                    Some(fields) => {
+                        body = RequestBody::Fields;
+                        properties.extend(fields.clone());
+                        if let Some(fields_required) =
+                            schema.get("required").and_then(|r| r.as_array())
+                        {
+                            required.extend(fields_required.iter().cloned());
+                        }
+                    }
+                    None if !schema.is_null() => {
+                        body = RequestBody::Value;
+                        properties.insert("body".to_string(), schema);
+                        if body_required {
+                            required.push(json!("body"));
+                        }
+                    }
+                    None => {}
+                }
+            }
+
+            let name = operation
+                .get("operationId")
+                .and_then(|id| id.as_str())
+                .map(function_name)
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| {
+                    function_name(&format!("{}{}", method.as_str().to_lowercase(), path))
+                });
+            let description = ["summary", "description"]
+                .iter()
+                .find_map(|key| operation.get(*key).and_then(|d| d.as_str()))
+                .unwrap_or_default()
+                .to_string();
+
+            tools.push(OpenApiTool {
+                name,
+                description,
+                parameters: json!({
+                    "type": "object",
+                    "properties": properties,
+                    "required": required,
+                }),
+                method: method.clone(),
+                path: path.clone(),
+                params,
+                body,
+            });
+        }
+    }
+    tools
+}
+
+/// An OpenAPI tool server, configured by an admin under
+/// `TOOL_SERVER_CONNECTIONS` or sent by the client as a direct tool server
+#[derive(Debug, Clone)]
+pub struct OpenApiToolServer {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub access_control: Option<Value>,
+    /// Base URL operations are called against
+    pub url: String,
+    /// Spec location, relative to `url` or absolute
+    pub path: String,
+    /// `bearer` (with `key`), `session` (the user's own token) or `none`
+    pub auth_type: String,
+    pub key: String,
+    /// Spec sent inline instead of being fetched from `path`
+    pub spec: Option<Value>,
+    /// Sent by the client, so only public addresses are contacted
+    pub direct: bool,
+}
+
+impl OpenApiToolServer {
+    pub const ID_PREFIX: &'static str = "server:";
+
+    /// Tool id used by the tool picker and in `tool_ids` of chat requests
+    pub fn tool_id(&self) -> String {
+        format!("{}{}", Self::ID_PREFIX, self.id)
+    }
+
+    /// Enabled OpenAPI connections from the tool server connection list
+    pub fn from_connections(connections: &Value) -> Vec<Self> {
+        connections
+            .as_array()
+            .map(|connections| {
+                connections
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, conn)| conn["config"]["enable"].as_bool() != Some(false))
+                    .filter_map(|(idx, conn)| Self::from_connection(idx, conn))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Look up the server behind a `server:{id}` tool id
+    pub fn find(connections: &Value, tool_id: &str) -> Option<Self> {
+        let id = tool_id.strip_prefix(Self::ID_PREFIX)?;
+        Self::from_connections(connections)
+            .into_iter()
+            .find(|server| server.id == id)
+    }
+
+    /// Parse one connection entry, whether enabled or not; `idx` is its
+    /// position in the list, used as id when the entry has none
+    pub fn from_connection(idx: usize, conn: &Value) -> Option<Self> {
+        if !matches!(
+            conn.get("type").and_then(|t| t.as_str()),
+            None | Some("openapi")
+        ) {
+            return None;
+        }
+        let url = conn.get("url").and_then(|u| u.as_str())?.to_string();
+        let info = conn.get("info").cloned().unwrap_or_default();
+        let id = info
+            .get("id")
+            .and_then(|id| id.as_str())
+            .filter(|id| !id.is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| idx.to_string());
+
+        Some(Self {
+            name: info
+                .get("name")
+                .and_then(|n| n.as_str())
+                .filter(|n| !n.is_empty())
+                .map(str::to_string)
+                .unwrap_or_else(|| url.clone()),
+            description: info
+                .get("description")
+                .and_then(|d| d.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            access_control: conn
+                .pointer("/config/access_control")
+                .filter(|ac| !ac.is_null())
+                .cloned(),
+            path: conn
+                .get("path")
+                .and_then(|p| p.as_str())
+                .filter(|p| !p.is_empty())
+                .unwrap_or(DEFAULT_SPEC_PATH)
+                .to_string(),
+            auth_type: conn
+                .get("auth_type")
+                .and_then(|a| a.as_str())
+                .unwrap_or("bearer")
+                .to_string(),
+            key: conn
+                .get("key")
+                .and_then(|k| k.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            spec: None,
+            direct: false,
+            id,
+            url,
+        })
+    }
+
+    /// Direct tool servers from the `tool_servers` field of a chat request;
+    /// they may carry their spec inline as `openapi`
+    pub fn from_direct(servers: &Value) -> Vec<Self> {
+        servers
+            .as_array()
+            .map(|servers| {
+                servers
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(idx, server)| {
+                        let mut server = Self::from_connection(idx, server).map(|s| Self {
+                            spec: server.get("openapi").filter(|s| s.is_object()).cloned(),
+                            direct: true,
+                            ..s
+                        })?;
+                        server.id = format!("direct:{}", idx);
+                        if let Some(title) = server
+                            .spec
+                            .as_ref()
+                            .and_then(|spec| spec.pointer("/info/title"))
+                            .and_then(|t| t.as_str())
+                        {
+                            server.name = title.to_string();
+                        }
+                        Some(server)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn spec_url(&self) -> String {
+        if self.path.starts_with("http://") || self.path.starts_with("https://") {
+            self.path.clone()
+        } else {
+            format!(
+                "{}/{}",
+                self.url.trim_end_matches('/'),
+                self.path.trim_start_matches('/')
+            )
+        }
+    }
+
+    /// The client to reach `url` with. Direct tool servers must resolve to
+    /// public addresses, and the connection is pinned to the checked ones
+    /// without following redirects.
+    async fn client_for(&self, client: &Client, url: &str) -> AppResult<Client> {
+        if !self.direct {
+            return Ok(client.clone());
+        }
+
+        let parsed = Url::parse(url)
+            .map_err(|e| AppError::BadRequest(format!("Invalid tool server URL {}: {}", url, e)))?;
+        let addrs = resolve_public(&parsed)
+            .await
+            .map_err(|e| AppError::Forbidden(format!("Tool server {}: {}", url, e)))?;
+        let mut builder = Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .timeout(DIRECT_REQUEST_TIMEOUT);
+        if let Some(domain) = parsed.domain() {
+            builder = builder.resolve_to_addrs(domain, &addrs);
+        }
+        Ok(builder.build()?)
+    }
+
+    /// Download the spec, which may be JSON or YAML
+    pub async fn fetch_spec(&self, client: &Client) -> AppResult<Value> {
+        if let Some(spec) = &self.spec {
+            return Ok(spec.clone());
+        }
+
+        let client = self.client_for(client, &self.spec_url()).await?;
+        let mut request = client.get(self.spec_url());
+        if self.auth_type == "bearer" && !self.key.is_empty() {
+            request = request.bearer_auth(&self.key);
+        }
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(AppError::ExternalServiceError(format!(
+                "Failed to fetch OpenAPI spec from {}: {}",
+                self.spec_url(),
+                response.status()
+            )));
+        }
+
+        let text = response.text().await?;
+        serde_json::from_str(&text)
+            .or_else(|_| serde_yaml::from_str(&text))
+            .map_err(|e| AppError::ExternalServiceError(format!("Invalid OpenAPI spec: {}", e)))
+    }
+
+    /// The server's tools, from the spec cache when it is fresh
+    pub async fn tools(&self, client: &Client) -> AppResult<Arc<Vec<OpenApiTool>>> {
+        if let Some(spec) = &self.spec {
+            return Ok(Arc::new(parse_spec(spec)));
+        }
+
+        let spec_url = self.spec_url();
+        if let Some((fetched_at, tools)) = SPEC_CACHE.read().unwrap().get(&spec_url) {
+            if fetched_at.elapsed() < SPEC_CACHE_TTL {
+                return Ok(tools.clone());
+            }
+        }
+
+        let tools = Arc::new(parse_spec(&self.fetch_spec(client).await?));
+        SPEC_CACHE
+            .write()
+            .unwrap()
+            .insert(spec_url, (Instant::now(), tools.clone()));
+        Ok(tools)
+    }
+
+    /// Call `tool` with the model's arguments. `session_token` is only sent
+    /// to servers using `session` auth.
+    pub async fn call(
+        &self,
+        client: &Client,
+        tool: &OpenApiTool,
+        arguments: &Map<String, Value>,
+        session_token: Option<&str>,
+    ) -> AppResult<Value> {
+        let mut path = tool.path.clone();
+        let mut query = Vec::new();
+        let mut headers = Vec::new();
+        let mut fields = arguments.clone();
+
+        for (name, location) in &tool.params {
+            let Some(value) = fields.remove(name) else {
+                continue;
+            };
+            let value = match value {
+                Value::String(s) => s,
+                other => other.to_string(),
+            };
+            match location {
+                ParamLocation::Path => {
+                    path = path.replace(&format!("{{{}}}", name), &urlencoding::encode(&value))
+                }
+                ParamLocation::Query => query.push((name.clone(), value)),
+                ParamLocation::Header => headers.push((name.clone(), value)),
+            }
+        }
+
+        let url = format!("{}{}", self.url.trim_end_matches('/'), path);
+        let client = self.client_for(client, &url).await?;
+        let mut request = client.request(tool.method.clone(), &url).query(&query);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        match tool.body {
+            RequestBody::None => {}
+            RequestBody::Fields => request = request.json(&fields),
+            RequestBody::Value => {
+                request = request.json(fields.get("body").unwrap_or(&Value::Null))
+            }
+        }
+        match (self.auth_type.as_str(), session_token) {
+            ("bearer", _) if !self.key.is_empty() => request = request.bearer_auth(&self.key),
+            ("session", Some(token)) => request = request.bearer_auth(token),
+            _ => {}
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        let text = response.text().await?;
+        if !status.is_success() {
+            return Err(AppError::ExternalServiceError(format!(
+                "Tool server returned {}: {}",
+                status, text
+            )));
+        }
+        Ok(serde_json::from_str(&text).unwrap_or(Value::String(text)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+
+    fn pets_spec() -> Value {
+        json!({
+            "openapi": "3.0.0",
+            "info": { "title": "Pets", "version": "1.0" },
+            "paths": {
+                "/pets/{pet_id}": {
+                    "parameters": [
+                        { "name": "pet_id", "in": "path", "schema": { "type": "integer" } }
+                    ],
+                    "get": {
+                        "operationId": "get_pet",
+                        "summary": "Get a pet",
+                        "parameters": [
+                            { "name": "verbose", "in": "query", "schema": { "type": "boolean" } }
+                        ],
+                    },
+                    "put": {
+                        "operationId": "update_pet",
+                        "requestBody": {
+                            "content": {
+                                "application/json": {
+                                    "schema": { "$ref": "#/components/schemas/Pet" }
+                                }
+                            }
+                        },
+                    },
+                },
+                "/pets": { "post": { "summary": "Create a pet" } },
+            },
+            "components": {
+                "schemas": {
+                    "Pet": {
+                        "type": "object",
+                        "properties": { "name": { "type": "string" } },
+                        "required": ["name"],
+                    }
+                }
+            },
+        })
+    }
+
+    #[test]
+    fn test_parse_spec() {
+        let tools = parse_spec(&pets_spec());
+        let tool = |name: &str| tools.iter().find(|t| t.name == name).unwrap();
+        assert_eq!(tools.len(), 3);
+        assert_eq!(tool("post_pets").method, Method::POST);
+
+        let get_pet = tool("get_pet");
+        assert_eq!(get_pet.description, "Get a pet");
+        assert_eq!(get_pet.parameters["required"], json!(["pet_id"]));
+        assert_eq!(
+            get_pet.parameters["properties"]["verbose"]["type"],
+            "boolean"
+        );
+
+        let update_pet = tool("update_pet");
+        assert_eq!(update_pet.body, RequestBody::Fields);
+        assert_eq!(update_pet.parameters["required"], json!(["pet_id", "name"]));
+        assert_eq!(
+            update_pet.parameters["properties"]["name"]["type"],
+            "string"
+        );
+    }
+
+    #[test]
+    fn test_tool_servers_from_connections() {
+        let connections = json!([
+            { "type": "mcp", "url": "http://localhost:8000/mcp" },
+            {
+                "url": "http://localhost:9000",
+                "path": "spec.json",
+                "auth_type": "session",
+                "config": { "enable": true, "access_control": {} },
+                "info": { "id": "weather", "name": "Weather" },
+            },
+            { "type": "openapi", "url": "http://localhost:9001", "config": { "enable": false } },
+        ]);
+
+        let servers = OpenApiToolServer::from_connections(&connections);
+        assert_eq!(servers.len(), 1);
+        assert_eq!(servers[0].tool_id(), "server:weather");
+        assert_eq!(servers[0].spec_url(), "http://localhost:9000/spec.json");
+        assert_eq!(servers[0].access_control, Some(json!({})));
+        assert!(OpenApiToolServer::find(&connections, "server:weather").is_some());
+        assert!(OpenApiToolServer::find(&connections, "server:mcp:0").is_none());
+
+        let direct = OpenApiToolServer::from_direct(&json!([
+            { "url": "http://localhost:9002", "openapi": pets_spec() }
+        ]));
+        assert_eq!(direct[0].name, "Pets");
+        assert!(direct[0].spec.is_some());
+    }
+
+    async fn update_pet(
+        req: HttpRequest,
+        path: web::Path<String>,
+        query: web::Query<HashMap<String, String>>,
+        body: web::Json<Value>,
+    ) -> HttpResponse {
+        let auth = req
+            .headers()
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        HttpResponse::Ok().json(json!({
+            "pet_id": path.into_inner(),
+            "query": query.into_inner(),
+            "body": body.into_inner(),
+            "auth": auth,
+        }))
+    }
+
+    #[actix_web::test]
+    async fn test_call_maps_arguments() {
+        let server =
+            HttpServer::new(|| App::new().route("/api/pets/{pet_id}", web::put().to(update_pet)))
+                .workers(1)
+                .bind(("127.0.0.1", 0))
+                .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+
+        let tool_server = OpenApiToolServer {
+            url: format!("http://{}/api", addr),
+            auth_type: "session".to_string(),
+            spec: Some(pets_spec()),
+            ..OpenApiToolServer::from_connection(0, &json!({ "url": "" })).unwrap()
+        };
+        let client = Client::new();
+        let tools = tool_server.tools(&client).await.unwrap();
+        let tool = tools.iter().find(|t| t.name == "update_pet").unwrap();
+
+        let arguments = json!({ "pet_id": 7, "name": "Rex" });
+        let result = tool_server
+            .call(
+                &client,
+                tool,
+                arguments.as_object().unwrap(),
+                Some("user-token"),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(result["pet_id"], "7");
+        assert_eq!(result["body"], json!({ "name": "Rex" }));
+        assert_eq!(result["auth"], "Bearer user-token");
+    }
+
+    #[actix_web::test]
+    async fn test_direct_server_on_private_address_is_refused() {
+        let servers = json!([{ "url": "http://127.0.0.1:1/api", "path": "openapi.json" }]);
+        let direct = OpenApiToolServer::from_direct(&servers).remove(0);
+        assert!(direct.direct);
+        assert!(matches!(
+            direct.fetch_spec(&Client::new()).await,
+            Err(AppError::Forbidden(_))
+        ));
+
+        let direct = OpenApiToolServer {
+            spec: Some(pets_spec()),
+            ..direct
+        };
+        let tools = direct.tools(&Client::new()).await.unwrap();
+        let tool = tools.iter().find(|t| t.name == "update_pet").unwrap();
+        let arguments = json!({ "pet_id": 7 });
+        assert!(matches!(
+            direct
+                .call(&Client::new(), tool, arguments.as_object().unwrap(), None)
+                .await,
+            Err(AppError::Forbidden(_))
+        ));
+    }
+}
@@ -0,0 +1,469 @@
+//! Anthropic Messages API (`/v1/messages`)
+
+use std::collections::HashMap;
+
+use serde_json::{json, Map, Value};
+
+use super::{
+    chat_completion, content_text, parse_data_url, tool_call_arguments, usage, ChunkWriter,
+    StreamEventHandler,
+};
+
+const DEFAULT_API_VERSION: &str = "2023-06-01";
+
+/// The Messages API requires `max_tokens`, OpenAI clients usually omit it
+const DEFAULT_MAX_TOKENS: u64 = 4096;
+
+pub fn api_version(api_config: &Value) -> &str {
+    api_config
+        .get("anthropic_version")
+        .and_then(|v| v.as_str())
+        .unwrap_or(DEFAULT_API_VERSION)
+}
+
+/// Models from `/v1/models`, in the OpenAI `/models` shape
+pub fn openai_models(body: &Value) -> Vec<Value> {
+    body["data"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|model| {
+            let id = model["id"].as_str()?;
+            Some(json!({
+                "id": id,
+                "name": model["display_name"].as_str().unwrap_or(id),
+                "object": "model",
+                "created": model["created_at"]
+                    .as_str()
+                    .and_then(|c| chrono::DateTime::parse_from_rfc3339(c).ok())
+                    .map(|c| c.timestamp())
+                    .unwrap_or(0),
+                "owned_by": "anthropic",
+            }))
+        })
+        .collect()
+}
+
+/// Translate an OpenAI chat completion request into a Messages API body
+pub fn to_messages_request(payload: &Value, api_config: &Value) -> Value {
+    let messages = payload
+        .get("messages")
+        .and_then(|m| m.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+
+    let system: Vec<String> = messages
+        .iter()
+        .filter(|m| matches!(m["role"].as_str(), Some("system") | Some("developer")))
+        .map(|m| content_text(m.get("content")))
+        .filter(|text| !text.is_empty())
+        .collect();
+
+    let max_tokens = payload
+        .get("max_tokens")
+        .or_else(|| payload.get("max_completion_tokens"))
+        .and_then(|v| v.as_u64())
+        .or_else(|| api_config.get("max_tokens").and_then(|v| v.as_u64()))
+        .unwrap_or(DEFAULT_MAX_TOKENS);
+
+    let mut body = json!({
+        "model": payload.get("model").cloned().unwrap_or(Value::Null),
+        "messages": convert_messages(messages),
+        "max_tokens": max_tokens,
+    });
+    if !system.is_empty() {
+        body["system"] = json!(system.join("\n\n"));
+    }
+    for key in ["temperature", "top_p", "top_k", "stream"] {
+        if let Some(value) = payload.get(key).filter(|v| !v.is_null()) {
+            body[key] = value.clone();
+        }
+    }
+    match payload.get("stop") {
+        Some(Value::String(stop)) => body["stop_sequences"] = json!([stop]),
+        Some(stop @ Value::Array(_)) => body["stop_sequences"] = stop.clone(),
+        _ => {}
+    }
+
+    if let Some(tools) = payload.get("tools").and_then(|t| t.as_array()) {
+        let tools: Vec<Value> = tools
+            .iter()
+            .filter_map(|tool| {
+                let function = tool.get("function")?;
+                Some(json!({
+                    "name": function.get("name")?,
+                    "description": function.get("description").cloned().unwrap_or(json!("")),
+                    "input_schema": function
+                        .get("parameters")
+                        .cloned()
+                        .unwrap_or_else(|| json!({ "type": "object", "properties": {} })),
+                }))
+            })
+            .collect();
+        if !tools.is_empty() {
+            body["tools"] = Value::Array(tools);
+        }
+    }
+    match payload.get("tool_choice") {
+        Some(Value::String(choice)) => match choice.as_str() {
+            "auto" => body["tool_choice"] = json!({ "type": "auto" }),
+            "required" => body["tool_choice"] = json!({ "type": "any" }),
+            "none" => body["tool_choice"] = json!({ "type": "none" }),
+            _ => {}
+        },
+        Some(choice) => {
+            if let Some(name) = choice.pointer("/function/name") {
+                body["tool_choice"] = json!({ "type": "tool", "name": name });
+            }
+        }
+        None => {}
+    }
+
+    body
+}
+
+fn image_block(url: &str) -> Value {
+    match parse_data_url(url) {
+        Some((media_type, data)) => json!({
+            "type": "image",
+            "source": { "type": "base64", "media_type": media_type, "data": data },
+        }),
+        None => json!({ "type": "image", "source": { "type": "url", "url": url } }),
+    }
+}
+
+fn content_blocks(content: Option<&Value>) -> Vec<Value> {
+    match content {
+        Some(Value::String(text)) if !text.is_empty() => {
+            vec![json!({ "type": "text", "text": text })]
+        }
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|part| match part.get("type").and_then(|t| t.as_str()) {
+                Some("text") => part
+                    .get("text")
+                    .and_then(|t| t.as_str())
+                    .filter(|t| !t.is_empty())
+                    .map(|text| json!({ "type": "text", "text": text })),
+                Some("image_url") => part
+                    .pointer("/image_url/url")
+                    .and_then(|u| u.as_str())
+                    .map(image_block),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn convert_messages(messages: &[Value]) -> Vec<Value> {
+    let mut converted: Vec<Value> = Vec::new();
+
+    for message in messages {
+        let (role, blocks) = match message["role"].as_str() {
+            Some("system") | Some("developer") => continue,
+            Some("assistant") => {
+                let mut blocks = content_blocks(message.get("content"));
+                for call in message["tool_calls"].as_array().into_iter().flatten() {
+                    blocks.push(json!({
+                        "type": "tool_use",
+                        "id": call["id"],
+                        "name": call.pointer("/function/name").cloned().unwrap_or(Value::Null),
+                        "input": tool_call_arguments(call),
+                    }));
+                }
+                ("assistant", blocks)
+            }
+            Some("tool") => (
+                "user",
+                vec![json!({
+                    "type": "tool_result",
+                    "tool_use_id": message["tool_call_id"],
+                    "content": content_text(message.get("content")),
+                })],
+            ),
+            _ => ("user", content_blocks(message.get("content"))),
+        };
+        if blocks.is_empty() {
+            continue;
+        }
+
+        // Roles must alternate, so consecutive turns (e.g. several tool results) are merged
+        match converted.last_mut() {
+            Some(last) if last["role"] == role => {
+                if let Some(content) = last["content"].as_array_mut() {
+                    content.extend(blocks);
+                }
+            }
+            _ => converted.push(json!({ "role": role, "content": blocks })),
+        }
+    }
+
+    converted
+}
+
+fn finish_reason(stop_reason: Option<&str>) -> &'static str {
+    match stop_reason {
+        Some("max_tokens") => "length",
+        Some("tool_use") => "tool_calls",
+        Some("refusal") => "content_filter",
+        _ => "stop",
+    }
+}
+
+fn message_usage(usage_value: &Value) -> Value {
+    usage(
+        usage_value["input_tokens"].as_u64().unwrap_or(0),
+        usage_value["output_tokens"].as_u64().unwrap_or(0),
+    )
+}
+
+/// Translate a Messages API response into a chat completion
+pub fn to_openai_response(response: &Value) -> Value {
+    let mut text = String::new();
+    let mut reasoning = String::new();
+    let mut tool_calls = Vec::new();
+
+    for block in response["content"].as_array().into_iter().flatten() {
+        match block["type"].as_str() {
+            Some("text") => text.push_str(block["text"].as_str().unwrap_or_default()),
+            Some("thinking") => reasoning.push_str(block["thinking"].as_str().unwrap_or_default()),
+            Some("tool_use") => tool_calls.push(json!({
+                "id": block["id"],
+                "type": "function",
+                "function": {
+                    "name": block["name"],
+                    "arguments": block["input"].to_string(),
+                },
+            })),
+            _ => {}
+        }
+    }
+
+    let mut message = json!({ "role": "assistant", "content": text });
+    if !reasoning.is_empty() {
+        message["reasoning_content"] = json!(reasoning);
+    }
+    if !tool_calls.is_empty() {
+        message["tool_calls"] = Value::Array(tool_calls);
+    }
+
+    chat_completion(
+        &response["model"],
+        message,
+        finish_reason(response["stop_reason"].as_str()),
+        message_usage(&response["usage"]),
+    )
+}
+
+/// Translates Messages API stream events into OpenAI deltas
+#[derive(Default)]
+pub struct StreamEvents {
+    /// Content block index to OpenAI tool call index
+    tool_blocks: HashMap<u64, usize>,
+    input_tokens: u64,
+}
+
+impl StreamEventHandler for StreamEvents {
+    fn handle(&mut self, event: &Value, writer: &mut ChunkWriter) {
+        match event["type"].as_str() {
+            Some("message_start") => {
+                let message = &event["message"];
+                writer.model = message["model"].clone();
+                self.input_tokens = message["usage"]["input_tokens"].as_u64().unwrap_or(0);
+                writer.delta(json!({ "role": "assistant", "content": "" }));
+            }
+            Some("content_block_start") => {
+                let block = &event["content_block"];
+                if block["type"] == "tool_use" {
+                    let index = self.tool_blocks.len();
+                    self.tool_blocks
+                        .insert(event["index"].as_u64().unwrap_or(0), index);
+                    writer.delta(json!({
+                        "tool_calls": [{
+                            "index": index,
+                            "id": block["id"],
+                            "type": "function",
+                            "function": { "name": block["name"], "arguments": "" },
+                        }],
+                    }));
+                }
+            }
+            Some("content_block_delta") => {
+                let delta = &event["delta"];
+                let mut openai_delta = Map::new();
+                match delta["type"].as_str() {
+                    Some("text_delta") => {
+                        openai_delta.insert("content".to_string(), delta["text"].clone());
+                    }
+                    Some("thinking_delta") => {
+                        openai_delta
+                            .insert("reasoning_content".to_string(), delta["thinking"].clone());
+                    }
+                    Some("input_json_delta") => {
+                        let Some(index) = event["index"]
+                            .as_u64()
+                            .and_then(|i| self.tool_blocks.get(&i))
+                        else {
+                            return;
+                        };
+                        openai_delta.insert(
+                            "tool_calls".to_string(),
+                            json!([{
+                                "index": index,
+                                "function": { "arguments": delta["partial_json"] },
+                            }]),
+                        );
+                    }
+                    _ => return,
+                }
+                writer.delta(Value::Object(openai_delta));
+            }
+            Some("message_delta") => {
+                let output_tokens = event["usage"]["output_tokens"].as_u64().unwrap_or(0);
+                writer.finish(
+                    finish_reason(event["delta"]["stop_reason"].as_str()),
+                    usage(self.input_tokens, output_tokens),
+                );
+            }
+            Some("message_stop") => writer.done(),
+            Some("error") => {
+                let message = event["error"]["message"]
+                    .as_str()
+                    .map(String::from)
+                    .unwrap_or_else(|| event["error"].to_string());
+                writer.error(&message);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::sse_events;
+    use super::super::StreamTranslator;
+    use super::*;
+
+    #[test]
+    fn test_to_messages_request() {
+        let payload = json!({
+            "model": "claude-sonnet-4-5",
+            "stream": true,
+            "stop": "END",
+            "messages": [
+                { "role": "system", "content": "Be brief." },
+                { "role": "user", "content": [
+                    { "type": "text", "text": "What is this?" },
+                    { "type": "image_url", "image_url": { "url": "data:image/png;base64,iVBORw0" } },
+                ]},
+                { "role": "assistant", "content": null, "tool_calls": [{
+                    "id": "toolu_1", "type": "function",
+                    "function": { "name": "lookup", "arguments": "{\"q\":\"png\"}" },
+                }]},
+                { "role": "tool", "tool_call_id": "toolu_1", "content": "an image format" },
+                { "role": "user", "content": "Thanks" },
+            ],
+            "tools": [{ "type": "function", "function": {
+                "name": "lookup", "description": "Look things up",
+                "parameters": { "type": "object", "properties": { "q": { "type": "string" } } },
+            }}],
+            "tool_choice": "required",
+        });
+
+        let body = to_messages_request(&payload, &json!({}));
+        assert_eq!(body["system"], "Be brief.");
+        assert_eq!(body["max_tokens"], DEFAULT_MAX_TOKENS);
+        assert_eq!(body["stop_sequences"], json!(["END"]));
+        assert_eq!(body["tool_choice"], json!({ "type": "any" }));
+        assert_eq!(
+            body["tools"][0]["input_schema"]["properties"]["q"]["type"],
+            "string"
+        );
+
+        let messages = body["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(
+            messages[0]["content"][1]["source"],
+            json!({ "type": "base64", "media_type": "image/png", "data": "iVBORw0" })
+        );
+        assert_eq!(messages[1]["content"][0]["type"], "tool_use");
+        assert_eq!(messages[1]["content"][0]["input"], json!({ "q": "png" }));
+        // The tool result and the following user turn share one user message
+        assert_eq!(messages[2]["role"], "user");
+        assert_eq!(messages[2]["content"][0]["type"], "tool_result");
+        assert_eq!(messages[2]["content"][0]["tool_use_id"], "toolu_1");
+        assert_eq!(messages[2]["content"][1]["text"], "Thanks");
+    }
+
+    #[test]
+    fn test_to_openai_response() {
+        let response = to_openai_response(&json!({
+            "model": "claude-sonnet-4-5",
+            "content": [
+                { "type": "thinking", "thinking": "Need a lookup." },
+                { "type": "text", "text": "Let me check." },
+                { "type": "tool_use", "id": "toolu_1", "name": "lookup", "input": { "q": "x" } },
+            ],
+            "stop_reason": "tool_use",
+            "usage": { "input_tokens": 10, "output_tokens": 5 },
+        }));
+
+        let choice = &response["choices"][0];
+        assert_eq!(choice["finish_reason"], "tool_calls");
+        assert_eq!(choice["message"]["content"], "Let me check.");
+        assert_eq!(choice["message"]["reasoning_content"], "Need a lookup.");
+        assert_eq!(
+            choice["message"]["tool_calls"][0]["function"]["arguments"],
+            "{\"q\":\"x\"}"
+        );
+        assert_eq!(response["usage"]["total_tokens"], 15);
+    }
+
+    #[test]
+    fn test_stream_translation() {
+        let events = [
+            json!({ "type": "message_start", "message": { "model": "claude", "usage": { "input_tokens": 7 } } }),
+            json!({ "type": "content_block_start", "index": 0, "content_block": { "type": "text", "text": "" } }),
+            json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "text_delta", "text": "Hi" } }),
+            json!({ "type": "ping" }),
+            json!({ "type": "content_block_start", "index": 1, "content_block": { "type": "tool_use", "id": "toolu_1", "name": "lookup", "input": {} } }),
+            json!({ "type": "content_block_delta", "index": 1, "delta": { "type": "input_json_delta", "partial_json": "{\"q\":" } }),
+            json!({ "type": "content_block_delta", "index": 1, "delta": { "type": "input_json_delta", "partial_json": "\"x\"}" } }),
+            json!({ "type": "message_delta", "delta": { "stop_reason": "tool_use" }, "usage": { "output_tokens": 3 } }),
+            json!({ "type": "message_stop" }),
+        ];
+        let sse: String = events
+            .iter()
+            .map(|e| format!("event: {}\ndata: {}\n\n", e["type"].as_str().unwrap(), e))
+            .collect();
+
+        // Feed in small pieces to cross event boundaries
+        let mut translator = StreamTranslator::new(StreamEvents::default());
+        let mut out = String::new();
+        for piece in sse.as_bytes().chunks(7) {
+            out.push_str(&translator.push(piece));
+        }
+        out.push_str(&translator.finish());
+
+        assert_eq!(out.matches("data: [DONE]").count(), 1);
+        let chunks = sse_events(&out);
+        assert_eq!(chunks[0]["model"], "claude");
+        assert_eq!(chunks[1]["choices"][0]["delta"]["content"], "Hi");
+        assert_eq!(
+            chunks[2]["choices"][0]["delta"]["tool_calls"][0]["id"],
+            "toolu_1"
+        );
+        let arguments: String = chunks[3..5]
+            .iter()
+            .map(|c| {
+                c["choices"][0]["delta"]["tool_calls"][0]["function"]["arguments"]
+                    .as_str()
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(arguments, "{\"q\":\"x\"}");
+        assert_eq!(chunks[5]["choices"][0]["finish_reason"], "tool_calls");
+        assert_eq!(chunks[5]["usage"]["total_tokens"], 10);
+    }
+}
@@ -0,0 +1,547 @@
+//! Gemini API `generateContent` / `streamGenerateContent`
+
+use std::collections::HashMap;
+
+use serde_json::{json, Map, Value};
+
+use super::{
+    chat_completion, content_text, new_tool_call_id, parse_data_url, tool_call_arguments, usage,
+    ChunkWriter, StreamEventHandler,
+};
+
+/// OpenAI sampling parameters and their `generationConfig` names
+const GENERATION_CONFIG_MAPPINGS: [(&str, &str); 9] = [
+    ("temperature", "temperature"),
+    ("top_p", "topP"),
+    ("top_k", "topK"),
+    ("max_tokens", "maxOutputTokens"),
+    ("max_completion_tokens", "maxOutputTokens"),
+    ("n", "candidateCount"),
+    ("seed", "seed"),
+    ("presence_penalty", "presencePenalty"),
+    ("frequency_penalty", "frequencyPenalty"),
+];
+
+fn model_name(payload: &Value) -> &str {
+    let model = payload["model"].as_str().unwrap_or_default();
+    model.strip_prefix("models/").unwrap_or(model)
+}
+
+/// Endpoint for `payload`, which selects the model and whether to stream
+pub fn generate_content_url(base_url: &str, payload: &Value) -> String {
+    if payload["stream"].as_bool().unwrap_or(false) {
+        format!(
+            "{}/models/{}:streamGenerateContent?alt=sse",
+            base_url,
+            model_name(payload)
+        )
+    } else {
+        format!(
+            "{}/models/{}:generateContent",
+            base_url,
+            model_name(payload)
+        )
+    }
+}
+
+/// Chat models from `/v1beta/models`, in the OpenAI `/models` shape
+pub fn openai_models(body: &Value) -> Vec<Value> {
+    body["models"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|model| {
+            model["supportedGenerationMethods"]
+                .as_array()
+                .is_some_and(|methods| methods.iter().any(|m| m == "generateContent"))
+        })
+        .filter_map(|model| {
+            let name = model["name"].as_str()?;
+            let id = name.strip_prefix("models/").unwrap_or(name);
+            Some(json!({
+                "id": id,
+                "name": model["displayName"].as_str().unwrap_or(id),
+                "object": "model",
+                "created": 0,
+                "owned_by": "google",
+            }))
+        })
+        .collect()
+}
+
+/// Translate an OpenAI chat completion request into a `generateContent` body
+pub fn to_generate_content_request(payload: &Value) -> Value {
+    let messages = payload
+        .get("messages")
+        .and_then(|m| m.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+
+    let mut body = json!({ "contents": convert_messages(messages) });
+
+    let system: Vec<Value> = messages
+        .iter()
+        .filter(|m| matches!(m["role"].as_str(), Some("system") | Some("developer")))
+        .map(|m| content_text(m.get("content")))
+        .filter(|text| !text.is_empty())
+        .map(|text| json!({ "text": text }))
+        .collect();
+    if !system.is_empty() {
+        body["systemInstruction"] = json!({ "parts": system });
+    }
+
+    let mut generation_config = Map::new();
+    for (openai_key, gemini_key) in GENERATION_CONFIG_MAPPINGS {
+        if let Some(value) = payload.get(openai_key).filter(|v| !v.is_null()) {
+            generation_config.insert(gemini_key.to_string(), value.clone());
+        }
+    }
+    match payload.get("stop") {
+        Some(Value::String(stop)) => {
+            generation_config.insert("stopSequences".to_string(), json!([stop]));
+        }
+        Some(stop @ Value::Array(_)) => {
+            generation_config.insert("stopSequences".to_string(), stop.clone());
+        }
+        _ => {}
+    }
+    if let Some(format) = payload.get("response_format") {
+        match format["type"].as_str() {
+            Some("json_object") => {
+                generation_config.insert("responseMimeType".to_string(), json!("application/json"));
+            }
+            Some("json_schema") => {
+                generation_config.insert("responseMimeType".to_string(), json!("application/json"));
+                if let Some(schema) = format.pointer("/json_schema/schema") {
+                    generation_config.insert("responseJsonSchema".to_string(), schema.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+    if !generation_config.is_empty() {
+        body["generationConfig"] = Value::Object(generation_config);
+    }
+
+    if let Some(tools) = payload.get("tools").and_then(|t| t.as_array()) {
+        let declarations: Vec<Value> = tools
+            .iter()
+            .filter_map(|tool| {
+                let function = tool.get("function")?;
+                let mut declaration = json!({
+                    "name": function.get("name")?,
+                    "description": function.get("description").cloned().unwrap_or(json!("")),
+                });
+                if let Some(parameters) = function.get("parameters") {
+                    declaration["parametersJsonSchema"] = parameters.clone();
+                }
+                Some(declaration)
+            })
+            .collect();
+        if !declarations.is_empty() {
+            body["tools"] = json!([{ "functionDeclarations": declarations }]);
+        }
+    }
+    let calling_config = match payload.get("tool_choice") {
+        Some(Value::String(choice)) => match choice.as_str() {
+            "auto" => Some(json!({ "mode": "AUTO" })),
+            "required" => Some(json!({ "mode": "ANY" })),
+            "none" => Some(json!({ "mode": "NONE" })),
+            _ => None,
+        },
+        Some(choice) => choice
+            .pointer("/function/name")
+            .map(|name| json!({ "mode": "ANY", "allowedFunctionNames": [name] })),
+        None => None,
+    };
+    if let Some(calling_config) = calling_config {
+        body["toolConfig"] = json!({ "functionCallingConfig": calling_config });
+    }
+
+    body
+}
+
+fn image_part(url: &str) -> Value {
+    match parse_data_url(url) {
+        Some((mime_type, data)) => json!({ "inlineData": { "mimeType": mime_type, "data": data } }),
+        None => {
+            let mime_type = mime_guess::from_path(url).first_or(mime::IMAGE_JPEG);
+            json!({ "fileData": { "mimeType": mime_type.essence_str(), "fileUri": url } })
+        }
+    }
+}
+
+fn content_parts(content: Option<&Value>) -> Vec<Value> {
+    match content {
+        Some(Value::String(text)) if !text.is_empty() => vec![json!({ "text": text })],
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|part| match part.get("type").and_then(|t| t.as_str()) {
+                Some("text") => part
+                    .get("text")
+                    .and_then(|t| t.as_str())
+                    .filter(|t| !t.is_empty())
+                    .map(|text| json!({ "text": text })),
+                Some("image_url") => part
+                    .pointer("/image_url/url")
+                    .and_then(|u| u.as_str())
+                    .map(image_part),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn convert_messages(messages: &[Value]) -> Vec<Value> {
+    // Function responses are matched to calls by name rather than call id
+    let mut tool_names: HashMap<&str, &Value> = HashMap::new();
+    let mut contents: Vec<Value> = Vec::new();
+
+    for message in messages {
+        let (role, parts) = match message["role"].as_str() {
+            Some("system") | Some("developer") => continue,
+            Some("assistant") => {
+                let mut parts = content_parts(message.get("content"));
+                for call in message["tool_calls"].as_array().into_iter().flatten() {
+                    let name = call.pointer("/function/name").unwrap_or(&Value::Null);
+                    if let Some(id) = call["id"].as_str() {
+                        tool_names.insert(id, name);
+                    }
+                    let mut part = json!({
+                        "functionCall": { "name": name, "args": tool_call_arguments(call) },
+                    });
+                    if let Some(signature) = call.pointer("/extra_content/google/thought_signature")
+                    {
+                        part["thoughtSignature"] = signature.clone();
+                    }
+                    parts.push(part);
+                }
+                ("model", parts)
+            }
+            Some("tool") => {
+                let name = message["tool_call_id"]
+                    .as_str()
+                    .and_then(|id| tool_names.get(id).copied())
+                    .or_else(|| message.get("name"))
+                    .cloned()
+                    .unwrap_or(Value::Null);
+                let text = content_text(message.get("content"));
+                // `response` must be an object, plain results are wrapped
+                let response = match serde_json::from_str::<Value>(&text) {
+                    Ok(object @ Value::Object(_)) => object,
+                    _ => json!({ "result": text }),
+                };
+                (
+                    "user",
+                    vec![json!({ "functionResponse": { "name": name, "response": response } })],
+                )
+            }
+            _ => ("user", content_parts(message.get("content"))),
+        };
+        if parts.is_empty() {
+            continue;
+        }
+
+        match contents.last_mut() {
+            Some(last) if last["role"] == role => {
+                if let Some(existing) = last["parts"].as_array_mut() {
+                    existing.extend(parts);
+                }
+            }
+            _ => contents.push(json!({ "role": role, "parts": parts })),
+        }
+    }
+
+    contents
+}
+
+fn finish_reason(reason: Option<&str>, had_tool_calls: bool) -> &'static str {
+    match reason {
+        Some("MAX_TOKENS") => "length",
+        Some("SAFETY")
+        | Some("RECITATION")
+        | Some("BLOCKLIST")
+        | Some("PROHIBITED_CONTENT")
+        | Some("SPII") => "content_filter",
+        _ if had_tool_calls => "tool_calls",
+        _ => "stop",
+    }
+}
+
+fn response_usage(response: &Value) -> Value {
+    let metadata = &response["usageMetadata"];
+    usage(
+        metadata["promptTokenCount"].as_u64().unwrap_or(0),
+        metadata["candidatesTokenCount"].as_u64().unwrap_or(0)
+            + metadata["thoughtsTokenCount"].as_u64().unwrap_or(0),
+    )
+}
+
+/// Content, reasoning and tool calls of the first candidate
+struct CandidateParts {
+    text: String,
+    reasoning: String,
+    tool_calls: Vec<Value>,
+}
+
+fn candidate_parts(response: &Value) -> CandidateParts {
+    let mut parts = CandidateParts {
+        text: String::new(),
+        reasoning: String::new(),
+        tool_calls: Vec::new(),
+    };
+
+    for part in response["candidates"][0]["content"]["parts"]
+        .as_array()
+        .into_iter()
+        .flatten()
+    {
+        if let Some(call) = part.get("functionCall") {
+            let mut tool_call = json!({
+                "id": call["id"].as_str().map(String::from).unwrap_or_else(new_tool_call_id),
+                "type": "function",
+                "function": {
+                    "name": call["name"],
+                    "arguments": call.get("args").cloned().unwrap_or_else(|| json!({})).to_string(),
+                },
+            });
+            if let Some(signature) = part.get("thoughtSignature") {
+                tool_call["extra_content"] =
+                    json!({ "google": { "thought_signature": signature } });
+            }
+            parts.tool_calls.push(tool_call);
+        } else if let Some(text) = part["text"].as_str() {
+            if part["thought"].as_bool().unwrap_or(false) {
+                parts.reasoning.push_str(text);
+            } else {
+                parts.text.push_str(text);
+            }
+        }
+    }
+
+    parts
+}
+
+/// Translate a `generateContent` response into a chat completion
+pub fn to_openai_response(response: &Value) -> Value {
+    let parts = candidate_parts(response);
+    let reason = response["candidates"][0]["finishReason"]
+        .as_str()
+        .or_else(|| {
+            response["promptFeedback"]["blockReason"]
+                .as_str()
+                .map(|_| "SAFETY")
+        });
+    let finish_reason = finish_reason(reason, !parts.tool_calls.is_empty());
+
+    let mut message = json!({ "role": "assistant", "content": parts.text });
+    if !parts.reasoning.is_empty() {
+        message["reasoning_content"] = json!(parts.reasoning);
+    }
+    if !parts.tool_calls.is_empty() {
+        message["tool_calls"] = Value::Array(parts.tool_calls);
+    }
+
+    chat_completion(
+        &response["modelVersion"],
+        message,
+        finish_reason,
+        response_usage(response),
+    )
+}
+
+/// Translates `streamGenerateContent?alt=sse` responses into OpenAI deltas
+#[derive(Default)]
+pub struct StreamEvents {
+    tool_calls: usize,
+}
+
+impl StreamEventHandler for StreamEvents {
+    fn handle(&mut self, event: &Value, writer: &mut ChunkWriter) {
+        if let Some(error) = event.get("error") {
+            let message = error["message"]
+                .as_str()
+                .map(String::from)
+                .unwrap_or_else(|| error.to_string());
+            writer.error(&message);
+            return;
+        }
+        if writer.model.is_null() {
+            writer.model = event["modelVersion"].clone();
+        }
+
+        let parts = candidate_parts(event);
+        let mut delta = Map::new();
+        if !parts.text.is_empty() {
+            delta.insert("content".to_string(), json!(parts.text));
+        }
+        if !parts.reasoning.is_empty() {
+            delta.insert("reasoning_content".to_string(), json!(parts.reasoning));
+        }
+        if !parts.tool_calls.is_empty() {
+            let calls: Vec<Value> = parts
+                .tool_calls
+                .into_iter()
+                .map(|mut call| {
+                    call["index"] = json!(self.tool_calls);
+                    self.tool_calls += 1;
+                    call
+                })
+                .collect();
+            delta.insert("tool_calls".to_string(), Value::Array(calls));
+        }
+        if !delta.is_empty() {
+            writer.delta(Value::Object(delta));
+        }
+
+        // Every chunk carries usage, only the last one a finish reason
+        let reason = event["candidates"][0]["finishReason"].as_str().or_else(|| {
+            event["promptFeedback"]["blockReason"]
+                .as_str()
+                .map(|_| "SAFETY")
+        });
+        if reason.is_some() {
+            writer.finish(
+                finish_reason(reason, self.tool_calls > 0),
+                response_usage(event),
+            );
+            writer.done();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::sse_events;
+    use super::super::StreamTranslator;
+    use super::*;
+
+    #[test]
+    fn test_to_generate_content_request() {
+        let payload = json!({
+            "model": "gemini-2.5-flash",
+            "max_tokens": 256,
+            "temperature": 0.2,
+            "messages": [
+                { "role": "system", "content": "Be brief." },
+                { "role": "user", "content": [
+                    { "type": "text", "text": "Weather here?" },
+                    { "type": "image_url", "image_url": { "url": "data:image/jpeg;base64,/9j/4AAQ" } },
+                ]},
+                { "role": "assistant", "content": "", "tool_calls": [{
+                    "id": "call_1", "type": "function",
+                    "function": { "name": "get_weather", "arguments": "{\"city\":\"Oslo\"}" },
+                    "extra_content": { "google": { "thought_signature": "sig" } },
+                }]},
+                { "role": "tool", "tool_call_id": "call_1", "content": "rainy" },
+            ],
+            "tools": [{ "type": "function", "function": {
+                "name": "get_weather", "parameters": { "type": "object" },
+            }}],
+            "tool_choice": { "type": "function", "function": { "name": "get_weather" } },
+        });
+
+        let body = to_generate_content_request(&payload);
+        assert_eq!(body["systemInstruction"]["parts"][0]["text"], "Be brief.");
+        assert_eq!(body["generationConfig"]["maxOutputTokens"], 256);
+        assert_eq!(body["generationConfig"]["temperature"], 0.2);
+        assert_eq!(
+            body["tools"][0]["functionDeclarations"][0]["name"],
+            "get_weather"
+        );
+        assert_eq!(
+            body["toolConfig"]["functionCallingConfig"],
+            json!({ "mode": "ANY", "allowedFunctionNames": ["get_weather"] })
+        );
+
+        let contents = body["contents"].as_array().unwrap();
+        assert_eq!(contents.len(), 3);
+        assert_eq!(
+            contents[0]["parts"][1]["inlineData"],
+            json!({ "mimeType": "image/jpeg", "data": "/9j/4AAQ" })
+        );
+        assert_eq!(contents[1]["role"], "model");
+        assert_eq!(
+            contents[1]["parts"][0]["functionCall"]["args"],
+            json!({ "city": "Oslo" })
+        );
+        assert_eq!(contents[1]["parts"][0]["thoughtSignature"], "sig");
+        assert_eq!(
+            contents[2]["parts"][0]["functionResponse"],
+            json!({ "name": "get_weather", "response": { "result": "rainy" } })
+        );
+    }
+
+    #[test]
+    fn test_to_openai_response() {
+        let response = to_openai_response(&json!({
+            "modelVersion": "gemini-2.5-flash",
+            "candidates": [{
+                "content": { "role": "model", "parts": [
+                    { "text": "Checking the forecast.", "thought": true },
+                    { "functionCall": { "name": "get_weather", "args": { "city": "Oslo" } } },
+                ]},
+                "finishReason": "STOP",
+            }],
+            "usageMetadata": { "promptTokenCount": 12, "candidatesTokenCount": 4 },
+        }));
+
+        let choice = &response["choices"][0];
+        assert_eq!(choice["finish_reason"], "tool_calls");
+        assert_eq!(
+            choice["message"]["reasoning_content"],
+            "Checking the forecast."
+        );
+        assert_eq!(
+            choice["message"]["tool_calls"][0]["function"]["arguments"],
+            "{\"city\":\"Oslo\"}"
+        );
+        assert_eq!(response["usage"]["total_tokens"], 16);
+    }
+
+    #[test]
+    fn test_stream_translation() {
+        let events = [
+            json!({ "modelVersion": "gemini-2.5-flash", "candidates": [{ "content": { "parts": [{ "text": "Hel" }] } }] }),
+            json!({ "candidates": [{ "content": { "parts": [{ "text": "lo" }] } }] }),
+            json!({
+                "candidates": [{ "content": { "parts": [{ "text": "!" }] }, "finishReason": "MAX_TOKENS" }],
+                "usageMetadata": { "promptTokenCount": 3, "candidatesTokenCount": 2 },
+            }),
+        ];
+        let sse: String = events
+            .iter()
+            .map(|e| format!("data: {}\r\n\r\n", e))
+            .collect();
+
+        let mut translator = StreamTranslator::new(StreamEvents::default());
+        let mut out = String::new();
+        for piece in sse.as_bytes().chunks(5) {
+            out.push_str(&translator.push(piece));
+        }
+        out.push_str(&translator.finish());
+
+        assert_eq!(out.matches("data: [DONE]").count(), 1);
+        let chunks = sse_events(&out);
+        let text: String = chunks
+            .iter()
+            .filter_map(|c| c["choices"][0]["delta"]["content"].as_str())
+            .collect();
+        assert_eq!(text, "Hello!");
+        assert!(chunks.iter().all(|c| c["model"] == "gemini-2.5-flash"));
+        let last = chunks.last().unwrap();
+        assert_eq!(last["choices"][0]["finish_reason"], "length");
+        assert_eq!(last["usage"]["total_tokens"], 5);
+    }
+
+    #[test]
+    fn test_stream_error() {
+        let mut translator = StreamTranslator::new(StreamEvents::default());
+        let out =
+            translator.push(b"data: {\"error\":{\"code\":429,\"message\":\"Quota exceeded\"}}\n\n");
+        assert_eq!(sse_events(&out)[0]["error"]["message"], "Quota exceeded");
+        assert!(out.ends_with("data: [DONE]\n\n"));
+        assert_eq!(translator.finish(), "");
+    }
+}
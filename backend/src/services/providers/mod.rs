@@ -0,0 +1,439 @@
+//! Adapters for chat connections that do not speak OpenAI `/chat/completions`
+//!
+//! A connection opts in with `"provider"` in its `OPENAI_API_CONFIGS` entry.
+//! Requests are translated from the OpenAI format on the way out, responses and
+//! SSE streams back into chat completion objects, so the rest of the chat
+//! pipeline only ever deals with the OpenAI format.
+
+pub mod anthropic;
+pub mod gemini;
+
+use bytes::Bytes;
+use futures::stream::StreamExt;
+use reqwest::{Client, RequestBuilder, Response};
+use serde_json::{json, Value};
+use tracing::warn;
+
+use crate::utils::chat_completion::ChatByteStream;
+use crate::utils::time::current_timestamp_seconds;
+
+/// Wire format spoken by an upstream chat connection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Provider {
+    #[default]
+    OpenAi,
+    Anthropic,
+    Gemini,
+}
+
+impl Provider {
+    /// Provider configured for a connection, defaulting to OpenAI
+    pub fn from_api_config(api_config: &Value) -> Self {
+        match api_config
+            .get("provider")
+            .and_then(|p| p.as_str())
+            .map(|p| p.to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("anthropic") => Provider::Anthropic,
+            Some("gemini") | Some("google") => Provider::Gemini,
+            _ => Provider::OpenAi,
+        }
+    }
+
+    fn authorize(self, request: RequestBuilder, key: &str, api_config: &Value) -> RequestBuilder {
+        let auth_type = api_config
+            .get("auth_type")
+            .and_then(|v| v.as_str())
+            .unwrap_or("bearer");
+        if key.is_empty() || auth_type == "none" {
+            return request;
+        }
+
+        match self {
+            Provider::OpenAi => request.bearer_auth(key),
+            Provider::Anthropic => request.header("x-api-key", key),
+            Provider::Gemini => request.header("x-goog-api-key", key),
+        }
+    }
+
+    /// Request listing the connection's models
+    pub fn models_request(
+        self,
+        client: &Client,
+        base_url: &str,
+        key: &str,
+        api_config: &Value,
+    ) -> RequestBuilder {
+        let base_url = base_url.trim_end_matches('/');
+        let request = match self {
+            Provider::OpenAi => client.get(format!("{}/models", base_url)),
+            Provider::Anthropic => client
+                .get(format!("{}/models?limit=1000", base_url))
+                .header("anthropic-version", anthropic::api_version(api_config)),
+            Provider::Gemini => client.get(format!("{}/models?pageSize=1000", base_url)),
+        };
+        self.authorize(request, key, api_config)
+    }
+
+    /// Model objects from a models listing, in the OpenAI `/models` shape
+    pub fn openai_models(self, body: &Value) -> Vec<Value> {
+        match self {
+            Provider::OpenAi => body
+                .get("data")
+                .and_then(|d| d.as_array())
+                .cloned()
+                .unwrap_or_default(),
+            Provider::Anthropic => anthropic::openai_models(body),
+            Provider::Gemini => gemini::openai_models(body),
+        }
+    }
+
+    /// Chat request for an OpenAI chat completion `payload`
+    pub fn chat_request(
+        self,
+        client: &Client,
+        base_url: &str,
+        key: &str,
+        api_config: &Value,
+        payload: &Value,
+    ) -> RequestBuilder {
+        let base_url = base_url.trim_end_matches('/');
+        let request = match self {
+            Provider::OpenAi => client
+                .post(format!("{}/chat/completions", base_url))
+                .json(payload),
+            Provider::Anthropic => client
+                .post(format!("{}/messages", base_url))
+                .header("anthropic-version", anthropic::api_version(api_config))
+                .json(&anthropic::to_messages_request(payload, api_config)),
+            Provider::Gemini => client
+                .post(gemini::generate_content_url(base_url, payload))
+                .json(&gemini::to_generate_content_request(payload)),
+        };
+        self.authorize(
+            request.header("Content-Type", "application/json"),
+            key,
+            api_config,
+        )
+    }
+
+    /// Translate a non-streaming chat response into a chat completion
+    pub fn openai_response(self, body: Value) -> Value {
+        match self {
+            Provider::OpenAi => body,
+            Provider::Anthropic => anthropic::to_openai_response(&body),
+            Provider::Gemini => gemini::to_openai_response(&body),
+        }
+    }
+
+    /// Chat response stream as OpenAI SSE chunks
+    pub fn openai_stream(self, response: Response) -> ChatByteStream {
+        match self {
+            Provider::OpenAi => Box::pin(response.bytes_stream()),
+            Provider::Anthropic => translate_stream(response, anthropic::StreamEvents::default()),
+            Provider::Gemini => translate_stream(response, gemini::StreamEvents::default()),
+        }
+    }
+}
+
+/// Split a `data:` URL into its media type and base64 payload
+pub(crate) fn parse_data_url(url: &str) -> Option<(&str, &str)> {
+    let (media_type, data) = url.strip_prefix("data:")?.split_once(";base64,")?;
+    Some((media_type, data))
+}
+
+/// Text of an OpenAI message `content`, joining text parts
+pub(crate) fn content_text(content: Option<&Value>) -> String {
+    match content {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter(|part| part.get("type").and_then(|t| t.as_str()) == Some("text"))
+            .filter_map(|part| part.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// OpenAI tool call arguments as a JSON object
+pub(crate) fn tool_call_arguments(call: &Value) -> Value {
+    match call.pointer("/function/arguments") {
+        Some(Value::String(args)) => serde_json::from_str(args).unwrap_or_else(|_| json!({})),
+        Some(Value::Null) | None => json!({}),
+        Some(args) => args.clone(),
+    }
+}
+
+pub(crate) fn new_tool_call_id() -> String {
+    format!("call_{}", uuid::Uuid::new_v4().simple())
+}
+
+pub(crate) fn usage(prompt_tokens: u64, completion_tokens: u64) -> Value {
+    json!({
+        "prompt_tokens": prompt_tokens,
+        "completion_tokens": completion_tokens,
+        "total_tokens": prompt_tokens + completion_tokens,
+    })
+}
+
+pub(crate) fn chat_completion(
+    model: &Value,
+    message: Value,
+    finish_reason: &str,
+    usage: Value,
+) -> Value {
+    json!({
+        "id": format!("chatcmpl-{}", uuid::Uuid::new_v4()),
+        "object": "chat.completion",
+        "created": current_timestamp_seconds(),
+        "model": model,
+        "choices": [{
+            "index": 0,
+            "message": message,
+            "finish_reason": finish_reason,
+        }],
+        "usage": usage,
+    })
+}
+
+/// Writes OpenAI `chat.completion.chunk` SSE events
+pub(crate) struct ChunkWriter {
+    id: String,
+    created: i64,
+    pub model: Value,
+    out: String,
+    done: bool,
+}
+
+impl Default for ChunkWriter {
+    fn default() -> Self {
+        ChunkWriter {
+            id: format!("chatcmpl-{}", uuid::Uuid::new_v4()),
+            created: current_timestamp_seconds(),
+            model: Value::Null,
+            out: String::new(),
+            done: false,
+        }
+    }
+}
+
+impl ChunkWriter {
+    fn chunk(&mut self, delta: Value, finish_reason: Value, usage: Option<Value>) {
+        let mut chunk = json!({
+            "id": self.id,
+            "object": "chat.completion.chunk",
+            "created": self.created,
+            "model": self.model,
+            "choices": [{
+                "index": 0,
+                "delta": delta,
+                "finish_reason": finish_reason,
+            }],
+        });
+        if let Some(usage) = usage {
+            chunk["usage"] = usage;
+        }
+        self.out.push_str(&format!("data: {}\n\n", chunk));
+    }
+
+    pub fn delta(&mut self, delta: Value) {
+        self.chunk(delta, Value::Null, None);
+    }
+
+    pub fn finish(&mut self, finish_reason: &str, usage: Value) {
+        self.chunk(json!({}), json!(finish_reason), Some(usage));
+    }
+
+    pub fn error(&mut self, message: &str) {
+        self.out.push_str(&format!(
+            "data: {}\n\n",
+            json!({ "error": { "message": message } })
+        ));
+        self.done();
+    }
+
+    pub fn done(&mut self) {
+        if !self.done {
+            self.done = true;
+            self.out.push_str("data: [DONE]\n\n");
+        }
+    }
+}
+
+/// Provider specific handling of the JSON events of an SSE stream
+pub(crate) trait StreamEventHandler: Send + 'static {
+    fn handle(&mut self, event: &Value, writer: &mut ChunkWriter);
+
+    /// Called when the upstream stream ends
+    fn end(&mut self, _writer: &mut ChunkWriter) {}
+}
+
+/// Incrementally turns a provider SSE stream into OpenAI SSE chunks
+pub(crate) struct StreamTranslator<H> {
+    buffer: Vec<u8>,
+    writer: ChunkWriter,
+    handler: H,
+}
+
+impl<H: StreamEventHandler> StreamTranslator<H> {
+    pub fn new(handler: H) -> Self {
+        StreamTranslator {
+            buffer: Vec::new(),
+            writer: ChunkWriter::default(),
+            handler,
+        }
+    }
+
+    /// Feed raw bytes; returns SSE text for every complete event line received
+    pub fn push(&mut self, bytes: &[u8]) -> String {
+        self.buffer.extend_from_slice(bytes);
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            self.line(&line);
+        }
+        std::mem::take(&mut self.writer.out)
+    }
+
+    /// Flush a trailing line without newline and terminate the SSE stream
+    pub fn finish(&mut self) -> String {
+        let rest = std::mem::take(&mut self.buffer);
+        self.line(&rest);
+        if !self.writer.done {
+            self.handler.end(&mut self.writer);
+            self.writer.done();
+        }
+        std::mem::take(&mut self.writer.out)
+    }
+
+    fn line(&mut self, line: &[u8]) {
+        if self.writer.done {
+            return;
+        }
+        let Ok(line) = std::str::from_utf8(line) else {
+            return;
+        };
+        // `event:` lines are redundant, both providers repeat the type in the data
+        let Some(data) = line.trim().strip_prefix("data:").map(str::trim) else {
+            return;
+        };
+        if data.is_empty() {
+            return;
+        }
+        match serde_json::from_str::<Value>(data) {
+            Ok(event) => self.handler.handle(&event, &mut self.writer),
+            Err(e) => warn!("Skipping malformed stream event: {}", e),
+        }
+    }
+}
+
+fn translate_stream<H: StreamEventHandler>(response: Response, handler: H) -> ChatByteStream {
+    let stream = futures::stream::unfold(
+        (
+            Box::pin(response.bytes_stream()),
+            StreamTranslator::new(handler),
+            false,
+        ),
+        |(mut stream, mut translator, ended)| async move {
+            if ended {
+                return None;
+            }
+            loop {
+                match stream.next().await {
+                    Some(Ok(bytes)) => {
+                        let out = translator.push(&bytes);
+                        if !out.is_empty() {
+                            return Some((Ok(Bytes::from(out)), (stream, translator, false)));
+                        }
+                    }
+                    Some(Err(e)) => return Some((Err(e), (stream, translator, true))),
+                    None => {
+                        let out = translator.finish();
+                        return Some((Ok(Bytes::from(out)), (stream, translator, true)));
+                    }
+                }
+            }
+        },
+    );
+    Box::pin(stream)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// JSON payloads of the `data:` events in `sse`
+    pub(crate) fn sse_events(sse: &str) -> Vec<Value> {
+        sse.split("\n\n")
+            .filter_map(|event| event.strip_prefix("data: "))
+            .filter(|data| *data != "[DONE]")
+            .map(|data| serde_json::from_str(data).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_provider_from_api_config() {
+        assert_eq!(Provider::from_api_config(&json!({})), Provider::OpenAi);
+        assert_eq!(
+            Provider::from_api_config(&json!({ "provider": "Anthropic" })),
+            Provider::Anthropic
+        );
+        assert_eq!(
+            Provider::from_api_config(&json!({ "provider": "gemini" })),
+            Provider::Gemini
+        );
+    }
+
+    #[test]
+    fn test_chat_request_headers() {
+        let client = Client::new();
+        let payload = json!({ "model": "gemini-2.0-flash", "messages": [], "stream": true });
+
+        let request = Provider::Anthropic
+            .chat_request(
+                &client,
+                "https://api.anthropic.com/v1/",
+                "sk",
+                &json!({}),
+                &payload,
+            )
+            .build()
+            .unwrap();
+        assert_eq!(
+            request.url().as_str(),
+            "https://api.anthropic.com/v1/messages"
+        );
+        assert_eq!(request.headers()["x-api-key"], "sk");
+        assert_eq!(request.headers()["anthropic-version"], "2023-06-01");
+
+        let request = Provider::Gemini
+            .chat_request(
+                &client,
+                "https://generativelanguage.googleapis.com/v1beta",
+                "key",
+                &json!({}),
+                &payload,
+            )
+            .build()
+            .unwrap();
+        assert_eq!(
+            request.url().as_str(),
+            "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.0-flash:streamGenerateContent?alt=sse"
+        );
+        assert_eq!(request.headers()["x-goog-api-key"], "key");
+        assert!(request.headers().get("authorization").is_none());
+
+        let request = Provider::OpenAi
+            .chat_request(
+                &client,
+                "http://localhost",
+                "sk",
+                &json!({ "auth_type": "none" }),
+                &payload,
+            )
+            .build()
+            .unwrap();
+        assert!(request.headers().get("authorization").is_none());
+    }
+}
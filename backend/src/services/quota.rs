@@ -0,0 +1,540 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, QueryBuilder, Sqlite};
+
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::models::quota::{Quota, QuotaExceeded, QuotaForm};
+use crate::services::group::GroupService;
+use crate::utils::time::current_timestamp_seconds;
+
+/// A user's usage of one model
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ModelUsage {
+    pub model_id: String,
+    pub requests_today: i64,
+    pub tokens_this_month: i64,
+}
+
+/// Reads one limit of a quota
+type QuotaLimit = fn(&Quota) -> Option<i64>;
+
+/// One limit that applies to a request
+#[derive(Debug, Clone, PartialEq)]
+struct Limit {
+    quota_id: String,
+    target_type: String,
+    model_id: Option<String>,
+    limit_type: &'static str,
+    limit: i64,
+}
+
+/// The limits a user has on `model_id`, from quotas for that model and for
+/// all models
+///
+/// For each model scope a user's own quota replaces the quotas of their
+/// groups. Members of several groups get the most generous group limit, and
+/// no limit at all if any of those groups leaves it unset, as with group
+/// permissions.
+fn effective_limits(quotas: &[Quota], model_id: &str) -> Vec<Limit> {
+    let mut scopes: Vec<Option<&str>> = vec![None, Some(model_id)];
+    scopes.retain(|scope| {
+        quotas
+            .iter()
+            .any(|quota| quota.model_id.as_deref() == *scope)
+    });
+
+    let mut limits = Vec::new();
+    for scope in scopes {
+        let in_scope: Vec<&Quota> = quotas
+            .iter()
+            .filter(|quota| quota.model_id.as_deref() == scope)
+            .collect();
+        let own: Vec<&Quota> = in_scope
+            .iter()
+            .copied()
+            .filter(|quota| quota.target_type == "user")
+            .collect();
+        let candidates = if own.is_empty() { in_scope } else { own };
+
+        let fields: [(&'static str, QuotaLimit); 2] = [
+            ("requests_per_day", |quota| quota.requests_per_day),
+            ("tokens_per_month", |quota| quota.tokens_per_month),
+        ];
+        for (limit_type, field) in fields {
+            if candidates.iter().any(|quota| field(quota).is_none()) {
+                continue;
+            }
+            if let Some(quota) = candidates.iter().max_by_key(|quota| field(quota)) {
+                limits.push(Limit {
+                    quota_id: quota.id.clone(),
+                    target_type: quota.target_type.clone(),
+                    model_id: quota.model_id.clone(),
+                    limit_type,
+                    limit: field(quota).unwrap_or_default(),
+                });
+            }
+        }
+    }
+    limits
+}
+
+/// The UTC day, the `LIKE` pattern of the UTC month, and when each resets
+fn periods(now: DateTime<Utc>) -> (String, String, i64, i64) {
+    let today = now.date_naive();
+    let tomorrow = today + Duration::days(1);
+    let next_month = if today.month() == 12 {
+        NaiveDate::from_ymd_opt(today.year() + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(today.year(), today.month() + 1, 1)
+    }
+    .unwrap_or(tomorrow);
+    let midnight = |date: NaiveDate| {
+        date.and_hms_opt(0, 0, 0)
+            .map(|time| time.and_utc().timestamp())
+            .unwrap_or_default()
+    };
+
+    (
+        today.format("%Y-%m-%d").to_string(),
+        today.format("%Y-%m-%%").to_string(),
+        midnight(tomorrow),
+        midnight(next_month),
+    )
+}
+
+pub struct QuotaService<'a> {
+    db: &'a Database,
+}
+
+impl<'a> QuotaService<'a> {
+    pub fn new(db: &'a Database) -> Self {
+        QuotaService { db }
+    }
+
+    pub async fn get_all_quotas(&self) -> AppResult<Vec<Quota>> {
+        let quotas = sqlx::query_as::<_, Quota>(
+            r#"
+            SELECT id, target_type, target_id, model_id, requests_per_day,
+                   tokens_per_month, created_at, updated_at
+            FROM quota
+            ORDER BY created_at DESC
+            "#,
+        )
+        .fetch_all(&self.db.pool)
+        .await?;
+
+        Ok(quotas)
+    }
+
+    pub async fn get_quota_by_id(&self, id: &str) -> AppResult<Option<Quota>> {
+        let quota = sqlx::query_as::<_, Quota>(
+            r#"
+            SELECT id, target_type, target_id, model_id, requests_per_day,
+                   tokens_per_month, created_at, updated_at
+            FROM quota
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.db.pool)
+        .await?;
+
+        Ok(quota)
+    }
+
+    pub async fn insert_quota(&self, id: &str, form: &QuotaForm) -> AppResult<Quota> {
+        let now = current_timestamp_seconds();
+
+        sqlx::query(
+            r#"
+            INSERT INTO quota (id, target_type, target_id, model_id, requests_per_day,
+                               tokens_per_month, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+        )
+        .bind(id)
+        .bind(&form.target_type)
+        .bind(&form.target_id)
+        .bind(&form.model_id)
+        .bind(form.requests_per_day)
+        .bind(form.tokens_per_month)
+        .bind(now)
+        .bind(now)
+        .execute(&self.db.pool)
+        .await?;
+
+        self.get_quota_by_id(id)
+            .await?
+            .ok_or_else(|| AppError::InternalServerError("Failed to create quota".to_string()))
+    }
+
+    pub async fn update_quota(&self, id: &str, form: &QuotaForm) -> AppResult<Quota> {
+        sqlx::query(
+            r#"
+            UPDATE quota
+            SET target_type = $1, target_id = $2, model_id = $3, requests_per_day = $4,
+                tokens_per_month = $5, updated_at = $6
+            WHERE id = $7
+            "#,
+        )
+        .bind(&form.target_type)
+        .bind(&form.target_id)
+        .bind(&form.model_id)
+        .bind(form.requests_per_day)
+        .bind(form.tokens_per_month)
+        .bind(current_timestamp_seconds())
+        .bind(id)
+        .execute(&self.db.pool)
+        .await?;
+
+        self.get_quota_by_id(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Quota not found".to_string()))
+    }
+
+    pub async fn delete_quota(&self, id: &str) -> AppResult<bool> {
+        let result = sqlx::query("DELETE FROM quota WHERE id = $1")
+            .bind(id)
+            .execute(&self.db.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Quotas set for the user and for the groups they belong to
+    pub async fn get_quotas_for_user(&self, user_id: &str) -> AppResult<Vec<Quota>> {
+        let quotas = self.get_all_quotas().await?;
+        if quotas.is_empty() {
+            return Ok(quotas);
+        }
+
+        let group_ids: Vec<String> = GroupService::new(self.db)
+            .get_groups_by_member_id(user_id)
+            .await?
+            .into_iter()
+            .map(|group| group.id)
+            .collect();
+
+        Ok(quotas
+            .into_iter()
+            .filter(|quota| match quota.target_type.as_str() {
+                "user" => quota.target_id == user_id,
+                "group" => group_ids.contains(&quota.target_id),
+                _ => false,
+            })
+            .collect())
+    }
+
+    /// Requests and tokens used on days matching `day`, for one model or all
+    async fn usage(
+        &self,
+        user_id: &str,
+        model_id: Option<&str>,
+        day: &str,
+    ) -> AppResult<(i64, i64)> {
+        let usage: (i64, i64) = sqlx::query_as(
+            r#"
+            SELECT COALESCE(SUM(requests), 0), COALESCE(SUM(tokens), 0)
+            FROM model_usage
+            WHERE user_id = $1 AND day LIKE $2 AND ($3 IS NULL OR model_id = $3)
+            "#,
+        )
+        .bind(user_id)
+        .bind(day)
+        .bind(model_id)
+        .fetch_one(&self.db.pool)
+        .await?;
+
+        Ok(usage)
+    }
+
+    /// The first quota a new chat completion request would exceed, if any
+    pub async fn check(&self, user_id: &str, model_id: &str) -> AppResult<Option<QuotaExceeded>> {
+        let quotas = self.get_quotas_for_user(user_id).await?;
+        let (today, month, day_resets_at, month_resets_at) = periods(Utc::now());
+
+        for limit in effective_limits(&quotas, model_id) {
+            let (used, resets_at) = if limit.limit_type == "requests_per_day" {
+                let (requests, _) = self
+                    .usage(user_id, limit.model_id.as_deref(), &today)
+                    .await?;
+                (requests, day_resets_at)
+            } else {
+                let (_, tokens) = self
+                    .usage(user_id, limit.model_id.as_deref(), &month)
+                    .await?;
+                (tokens, month_resets_at)
+            };
+
+            if used >= limit.limit {
+                return Ok(Some(QuotaExceeded {
+                    quota_id: limit.quota_id,
+                    target_type: limit.target_type,
+                    model_id: limit.model_id,
+                    limit_type: limit.limit_type.to_string(),
+                    limit: limit.limit,
+                    used,
+                    resets_at,
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn add_usage(
+        &self,
+        user_id: &str,
+        model_id: &str,
+        requests: i64,
+        tokens: i64,
+    ) -> AppResult<()> {
+        let (today, ..) = periods(Utc::now());
+
+        sqlx::query(
+            r#"
+            INSERT INTO model_usage (user_id, model_id, day, requests, tokens)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (user_id, model_id, day) DO UPDATE
+            SET requests = requests + excluded.requests, tokens = tokens + excluded.tokens
+            "#,
+        )
+        .bind(user_id)
+        .bind(model_id)
+        .bind(&today)
+        .bind(requests)
+        .bind(tokens)
+        .execute(&self.db.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Count a chat completion request, unless it would exceed a quota
+    ///
+    /// The limits are checked by the same statement that adds the request, so
+    /// concurrent requests can't both take the last one that is left.
+    pub async fn record_request(
+        &self,
+        user_id: &str,
+        model_id: &str,
+    ) -> AppResult<Option<QuotaExceeded>> {
+        let quotas = self.get_quotas_for_user(user_id).await?;
+        let limits = effective_limits(&quotas, model_id);
+
+        // A rejected insert may only mean that a period ended in between
+        for _ in 0..2 {
+            let (today, month, ..) = periods(Utc::now());
+
+            let mut query = QueryBuilder::<Sqlite>::new(
+                "INSERT INTO model_usage (user_id, model_id, day, requests, tokens) SELECT ",
+            );
+            query
+                .push_bind(user_id)
+                .push(", ")
+                .push_bind(model_id)
+                .push(", ")
+                .push_bind(today.clone())
+                .push(", 1, 0 WHERE 1");
+            for limit in &limits {
+                let (column, day) = if limit.limit_type == "requests_per_day" {
+                    ("requests", &today)
+                } else {
+                    ("tokens", &month)
+                };
+                query
+                    .push(format!(
+                        " AND (SELECT COALESCE(SUM({}), 0) FROM model_usage WHERE user_id = ",
+                        column
+                    ))
+                    .push_bind(user_id)
+                    .push(" AND day LIKE ")
+                    .push_bind(day.clone());
+                if let Some(model_id) = &limit.model_id {
+                    query.push(" AND model_id = ").push_bind(model_id.clone());
+                }
+                query.push(") < ").push_bind(limit.limit);
+            }
+            query.push(
+                " ON CONFLICT (user_id, model_id, day) DO UPDATE SET requests = requests + 1",
+            );
+
+            if query.build().execute(&self.db.pool).await?.rows_affected() > 0 {
+                return Ok(None);
+            }
+            if let Some(exceeded) = self.check(user_id, model_id).await? {
+                return Ok(Some(exceeded));
+            }
+        }
+
+        Err(AppError::InternalServerError(
+            "Failed to record quota usage".to_string(),
+        ))
+    }
+
+    pub async fn record_tokens(&self, user_id: &str, model_id: &str, tokens: i64) -> AppResult<()> {
+        self.add_usage(user_id, model_id, 0, tokens).await
+    }
+
+    /// A user's requests today and tokens this month, per model
+    pub async fn get_usage_by_user(&self, user_id: &str) -> AppResult<Vec<ModelUsage>> {
+        let (today, month, ..) = periods(Utc::now());
+
+        let usage = sqlx::query_as::<_, ModelUsage>(
+            r#"
+            SELECT model_id,
+                   COALESCE(SUM(CASE WHEN day = $2 THEN requests ELSE 0 END), 0) AS requests_today,
+                   COALESCE(SUM(tokens), 0) AS tokens_this_month
+            FROM model_usage
+            WHERE user_id = $1 AND day LIKE $3
+            GROUP BY model_id
+            ORDER BY model_id
+            "#,
+        )
+        .bind(user_id)
+        .bind(&today)
+        .bind(&month)
+        .fetch_all(&self.db.pool)
+        .await?;
+
+        Ok(usage)
+    }
+}
+
+/// Callback for [`crate::utils::metrics::LlmCall::on_usage`] that adds the
+/// tokens of a call to the user's usage in the background
+pub fn token_recorder(
+    db: Database,
+    user_id: String,
+    model_id: String,
+) -> impl FnOnce(u64) + Send + 'static {
+    move |tokens| {
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        handle.spawn(async move {
+            if let Err(e) = QuotaService::new(&db)
+                .record_tokens(&user_id, &model_id, tokens as i64)
+                .await
+            {
+                tracing::warn!("Failed to record token usage: {}", e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn quota(
+        id: &str,
+        target_type: &str,
+        model_id: Option<&str>,
+        requests_per_day: Option<i64>,
+        tokens_per_month: Option<i64>,
+    ) -> Quota {
+        Quota {
+            id: id.to_string(),
+            target_type: target_type.to_string(),
+            target_id: "target".to_string(),
+            model_id: model_id.map(str::to_string),
+            requests_per_day,
+            tokens_per_month,
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_effective_limits() {
+        let quotas = vec![
+            quota("all", "group", None, Some(100), Some(1_000_000)),
+            quota("gpt-a", "group", Some("gpt"), Some(10), None),
+            quota("gpt-b", "group", Some("gpt"), Some(20), Some(5000)),
+            quota("other", "group", Some("other"), Some(1), None),
+        ];
+
+        // The most generous group wins, and an unset limit is no limit
+        let limits = effective_limits(&quotas, "gpt");
+        let summary: Vec<(&str, &str, i64)> = limits
+            .iter()
+            .map(|l| (l.quota_id.as_str(), l.limit_type, l.limit))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("all", "requests_per_day", 100),
+                ("all", "tokens_per_month", 1_000_000),
+                ("gpt-b", "requests_per_day", 20),
+            ]
+        );
+
+        // The user's own quota replaces their groups' for the same model
+        let mut quotas = quotas;
+        quotas.push(quota("mine", "user", Some("gpt"), Some(5), None));
+        let limits = effective_limits(&quotas, "gpt");
+        assert!(limits.iter().any(|l| l.quota_id == "mine" && l.limit == 5));
+        assert!(!limits.iter().any(|l| l.quota_id.starts_with("gpt-")));
+        assert_eq!(effective_limits(&quotas, "unlisted").len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_check_counts_usage() {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        db.run_migrations().await.unwrap();
+        let service = QuotaService::new(&db);
+
+        let form = QuotaForm {
+            target_type: "user".to_string(),
+            target_id: "u1".to_string(),
+            model_id: Some("gpt".to_string()),
+            requests_per_day: Some(2),
+            tokens_per_month: None,
+        };
+        service.insert_quota("q1", &form).await.unwrap();
+
+        // Concurrent requests never take more than the limit
+        let recorded =
+            futures::future::join_all((0..5).map(|_| service.record_request("u1", "gpt"))).await;
+        assert_eq!(
+            recorded
+                .iter()
+                .filter(|r| r.as_ref().unwrap().is_none())
+                .count(),
+            2
+        );
+        service.record_tokens("u1", "gpt", 42).await.unwrap();
+
+        let exceeded = service.check("u1", "gpt").await.unwrap().unwrap();
+        assert_eq!(exceeded.quota_id, "q1");
+        assert_eq!((exceeded.used, exceeded.limit), (2, 2));
+        let rejected = service.record_request("u1", "gpt").await.unwrap().unwrap();
+        assert_eq!(rejected.quota_id, "q1");
+        assert!(service.check("u1", "other").await.unwrap().is_none());
+        assert!(service.check("u2", "gpt").await.unwrap().is_none());
+
+        let usage = service.get_usage_by_user("u1").await.unwrap();
+        assert_eq!(usage.len(), 1);
+        assert_eq!(
+            (usage[0].requests_today, usage[0].tokens_this_month),
+            (2, 42)
+        );
+    }
+
+    #[test]
+    fn test_periods() {
+        let now = Utc.with_ymd_and_hms(2025, 12, 31, 18, 30, 0).unwrap();
+        let (day, month, day_resets_at, month_resets_at) = periods(now);
+        assert_eq!(day, "2025-12-31");
+        assert_eq!(month, "2025-12-%");
+        let new_year = Utc
+            .with_ymd_and_hms(2026, 1, 1, 0, 0, 0)
+            .unwrap()
+            .timestamp();
+        assert_eq!(day_resets_at, new_year);
+        assert_eq!(month_resets_at, new_year);
+    }
+}
@@ -0,0 +1,412 @@
+use std::sync::Arc;
+
+use reqwest::Client;
+use serde_json::{json, Value};
+use tracing::info;
+
+use crate::config::Config;
+use crate::error::{AppError, AppResult};
+
+/// Uploaded audio to transcribe
+#[derive(Debug, Clone)]
+pub struct AudioInput {
+    pub data: Vec<u8>,
+    pub filename: String,
+    pub content_type: String,
+    /// ISO-639-1 language hint, if the client sent one
+    pub language: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Transcription {
+    pub text: String,
+}
+
+/// A speech-to-text backend selected by `STT_ENGINE`
+#[async_trait::async_trait]
+pub trait SttEngine: Send + Sync {
+    async fn transcribe(&self, input: &AudioInput) -> AppResult<Transcription>;
+
+    /// Engine name, for logging
+    fn name(&self) -> &'static str;
+}
+
+/// Create the engine configured by `STT_ENGINE`
+///
+/// An empty engine (or `whisper`) selects the local Whisper model, which is
+/// only available when built with the `whisper` feature.
+pub fn create_stt_engine(config: &Config) -> AppResult<Arc<dyn SttEngine>> {
+    match config.stt_engine.as_str() {
+        "openai" => Ok(Arc::new(OpenAiStt {
+            client: Client::new(),
+            base_url: config
+                .stt_openai_api_base_url
+                .trim_end_matches('/')
+                .to_string(),
+            api_key: config.stt_openai_api_key.clone(),
+            model: config.stt_model.clone(),
+        })),
+        "deepgram" => {
+            if config.deepgram_api_key.is_empty() {
+                return Err(AppError::BadRequest(
+                    "DEEPGRAM_API_KEY is required for the deepgram STT engine".to_string(),
+                ));
+            }
+            Ok(Arc::new(DeepgramStt {
+                client: Client::new(),
+                api_key: config.deepgram_api_key.clone(),
+                model: config.stt_model.clone(),
+            }))
+        }
+        "azure" => {
+            if config.audio_stt_azure_api_key.is_empty() {
+                return Err(AppError::BadRequest(
+                    "AUDIO_STT_AZURE_API_KEY is required for the azure STT engine".to_string(),
+                ));
+            }
+            Ok(Arc::new(AzureStt::from_config(config)?))
+        }
+        "" | "whisper" => local_whisper(config),
+        other => Err(AppError::BadRequest(format!(
+            "Unsupported STT engine: {}",
+            other
+        ))),
+    }
+}
+
+#[cfg(feature = "whisper")]
+fn local_whisper(config: &Config) -> AppResult<Arc<dyn SttEngine>> {
+    Ok(super::whisper::WhisperStt::get_or_load(
+        &config.whisper_model,
+    ))
+}
+
+#[cfg(not(feature = "whisper"))]
+fn local_whisper(_config: &Config) -> AppResult<Arc<dyn SttEngine>> {
+    Err(AppError::BadRequest(
+        "Local Whisper support not compiled. Enable the 'whisper' feature or set STT_ENGINE"
+            .to_string(),
+    ))
+}
+
+/// Whether `content_type` matches one of the configured patterns
+///
+/// Patterns are MIME types with an optional `*` wildcard (`audio/*`).
+pub fn is_supported_content_type(content_type: &str, patterns: &[String]) -> bool {
+    let content_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+
+    patterns
+        .iter()
+        .map(|p| p.trim().to_lowercase())
+        .filter(|p| !p.is_empty())
+        .any(|pattern| match pattern.split_once('*') {
+            Some((prefix, suffix)) => {
+                content_type.len() >= prefix.len() + suffix.len()
+                    && content_type.starts_with(prefix)
+                    && content_type.ends_with(suffix)
+            }
+            None => content_type == pattern,
+        })
+}
+
+async fn error_for_status(engine: &str, response: reqwest::Response) -> AppResult<Value> {
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(AppError::ExternalServiceError(format!(
+            "{} STT failed ({}): {}",
+            engine, status, error_text
+        )));
+    }
+
+    response.json().await.map_err(|e| {
+        AppError::ExternalServiceError(format!("Invalid {} STT response: {}", engine, e))
+    })
+}
+
+/// OpenAI-compatible `/audio/transcriptions` endpoint
+pub struct OpenAiStt {
+    client: Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+}
+
+#[async_trait::async_trait]
+impl SttEngine for OpenAiStt {
+    async fn transcribe(&self, input: &AudioInput) -> AppResult<Transcription> {
+        let part = reqwest::multipart::Part::bytes(input.data.clone())
+            .file_name(input.filename.clone())
+            .mime_str(&input.content_type)
+            .map_err(|e| AppError::BadRequest(format!("Invalid content type: {}", e)))?;
+
+        let mut form = reqwest::multipart::Form::new()
+            .part("file", part)
+            .text("model", self.model.clone());
+        if let Some(language) = &input.language {
+            form = form.text("language", language.clone());
+        }
+
+        let response = self
+            .client
+            .post(format!("{}/audio/transcriptions", self.base_url))
+            .bearer_auth(&self.api_key)
+            .multipart(form)
+            .send()
+            .await
+            .map_err(|e| AppError::ExternalServiceError(format!("STT request failed: {}", e)))?;
+
+        let result = error_for_status("OpenAI", response).await?;
+        let text = result["text"]
+            .as_str()
+            .ok_or_else(|| AppError::ExternalServiceError("No transcription text".to_string()))?;
+
+        Ok(Transcription {
+            text: text.trim().to_string(),
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+}
+
+/// Deepgram pre-recorded audio API
+pub struct DeepgramStt {
+    client: Client,
+    api_key: String,
+    model: String,
+}
+
+/// Transcript text from a Deepgram `/v1/listen` response
+fn parse_deepgram_response(result: &Value) -> AppResult<String> {
+    let channels = result["results"]["channels"].as_array().ok_or_else(|| {
+        AppError::ExternalServiceError("Deepgram response has no channels".to_string())
+    })?;
+
+    let text = channels
+        .iter()
+        .filter_map(|channel| channel["alternatives"][0]["transcript"].as_str())
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Ok(text)
+}
+
+#[async_trait::async_trait]
+impl SttEngine for DeepgramStt {
+    async fn transcribe(&self, input: &AudioInput) -> AppResult<Transcription> {
+        let mut query = vec![("smart_format", "true".to_string())];
+        // STT_MODEL defaults to OpenAI's model name, which Deepgram rejects
+        if !self.model.is_empty() && self.model != "whisper-1" {
+            query.push(("model", self.model.clone()));
+        }
+        match &input.language {
+            Some(language) => query.push(("language", language.clone())),
+            None => query.push(("detect_language", "true".to_string())),
+        }
+
+        let response = self
+            .client
+            .post("https://api.deepgram.com/v1/listen")
+            .query(&query)
+            .header("Authorization", format!("Token {}", self.api_key))
+            .header("Content-Type", &input.content_type)
+            .body(input.data.clone())
+            .send()
+            .await
+            .map_err(|e| AppError::ExternalServiceError(format!("STT request failed: {}", e)))?;
+
+        let result = error_for_status("Deepgram", response).await?;
+        Ok(Transcription {
+            text: parse_deepgram_response(&result)?,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "deepgram"
+    }
+}
+
+/// Azure AI Speech fast transcription API
+pub struct AzureStt {
+    client: Client,
+    api_key: String,
+    endpoint: String,
+    locales: Vec<String>,
+    max_speakers: u32,
+}
+
+impl AzureStt {
+    fn from_config(config: &Config) -> AppResult<Self> {
+        let base_url = if !config.audio_stt_azure_base_url.is_empty() {
+            config
+                .audio_stt_azure_base_url
+                .trim_end_matches('/')
+                .to_string()
+        } else if !config.audio_stt_azure_region.is_empty() {
+            format!(
+                "https://{}.api.cognitive.microsoft.com",
+                config.audio_stt_azure_region
+            )
+        } else {
+            return Err(AppError::BadRequest(
+                "AUDIO_STT_AZURE_REGION or AUDIO_STT_AZURE_BASE_URL is required for the azure STT engine"
+                    .to_string(),
+            ));
+        };
+
+        Ok(Self {
+            client: Client::new(),
+            api_key: config.audio_stt_azure_api_key.clone(),
+            endpoint: format!(
+                "{}/speechtotext/transcriptions:transcribe?api-version=2024-11-15",
+                base_url
+            ),
+            locales: config
+                .audio_stt_azure_locales
+                .split(',')
+                .map(|l| l.trim().to_string())
+                .filter(|l| !l.is_empty())
+                .collect(),
+            max_speakers: config.audio_stt_azure_max_speakers.parse().unwrap_or(1),
+        })
+    }
+
+    fn definition(&self, language: Option<&str>) -> Value {
+        let locales: Vec<String> = match language {
+            Some(language) => vec![language.to_string()],
+            None => self.locales.clone(),
+        };
+
+        let mut definition = json!({ "locales": locales });
+        if self.max_speakers > 1 {
+            definition["diarization"] = json!({
+                "maxSpeakers": self.max_speakers,
+                "enabled": true,
+            });
+        }
+        definition
+    }
+}
+
+/// Transcript text from an Azure fast transcription response
+fn parse_azure_response(result: &Value) -> AppResult<String> {
+    let phrases = result["combinedPhrases"].as_array().ok_or_else(|| {
+        AppError::ExternalServiceError("Azure response has no combinedPhrases".to_string())
+    })?;
+
+    Ok(phrases
+        .iter()
+        .filter_map(|p| p["text"].as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim()
+        .to_string())
+}
+
+#[async_trait::async_trait]
+impl SttEngine for AzureStt {
+    async fn transcribe(&self, input: &AudioInput) -> AppResult<Transcription> {
+        let part = reqwest::multipart::Part::bytes(input.data.clone())
+            .file_name(input.filename.clone())
+            .mime_str(&input.content_type)
+            .map_err(|e| AppError::BadRequest(format!("Invalid content type: {}", e)))?;
+        let form = reqwest::multipart::Form::new().part("audio", part).text(
+            "definition",
+            self.definition(input.language.as_deref()).to_string(),
+        );
+
+        let response = self
+            .client
+            .post(&self.endpoint)
+            .header("Ocp-Apim-Subscription-Key", &self.api_key)
+            .multipart(form)
+            .send()
+            .await
+            .map_err(|e| AppError::ExternalServiceError(format!("STT request failed: {}", e)))?;
+
+        let result = error_for_status("Azure", response).await?;
+        Ok(Transcription {
+            text: parse_azure_response(&result)?,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "azure"
+    }
+}
+
+/// Transcribe with the configured engine
+pub async fn transcribe(config: &Config, input: &AudioInput) -> AppResult<Transcription> {
+    let engine = create_stt_engine(config)?;
+    info!(
+        "Transcribing {} ({} bytes) with {} STT engine",
+        input.filename,
+        input.data.len(),
+        engine.name()
+    );
+    engine.transcribe(input).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supported_content_types() {
+        let patterns = vec!["audio/*".to_string(), "video/webm".to_string()];
+
+        assert!(is_supported_content_type("audio/wav", &patterns));
+        assert!(is_supported_content_type(
+            "audio/webm;codecs=opus",
+            &patterns
+        ));
+        assert!(is_supported_content_type("Video/WebM", &patterns));
+        assert!(!is_supported_content_type("video/mp4", &patterns));
+        assert!(!is_supported_content_type("application/pdf", &patterns));
+        assert!(!is_supported_content_type("audio/wav", &[]));
+    }
+
+    #[test]
+    fn test_parse_deepgram_response() {
+        let result = json!({
+            "results": {
+                "channels": [
+                    {"alternatives": [{"transcript": "hello world", "confidence": 0.98}]},
+                    {"alternatives": [{"transcript": ""}]}
+                ]
+            }
+        });
+        assert_eq!(parse_deepgram_response(&result).unwrap(), "hello world");
+        assert!(parse_deepgram_response(&json!({})).is_err());
+    }
+
+    #[test]
+    fn test_parse_azure_response() {
+        let result = json!({
+            "durationMilliseconds": 1200,
+            "combinedPhrases": [{"text": "Hello there."}],
+            "phrases": []
+        });
+        assert_eq!(parse_azure_response(&result).unwrap(), "Hello there.");
+    }
+
+    #[test]
+    fn test_unknown_engine_is_rejected() {
+        let mut config = Config::from_env().unwrap();
+        config.stt_engine = "nonexistent".to_string();
+        assert!(create_stt_engine(&config).is_err());
+
+        config.stt_engine = "deepgram".to_string();
+        config.deepgram_api_key = String::new();
+        assert!(create_stt_engine(&config).is_err());
+    }
+}
@@ -1,8 +1,12 @@
 use crate::db::Database;
 use crate::error::{AppError, AppResult};
+use crate::models::tool::Tool;
 use crate::models::tool_runtime::*;
+use crate::services::mcp;
 use crate::services::tool::ToolService;
+use crate::services::user::UserService;
 use crate::utils::template::TemplateEngine;
+use crate::utils::valves;
 use evalexpr::{
     eval_with_context, ContextWithMutableVariables, DefaultNumericTypes, HashMapContext,
     Value as EvalValue,
@@ -103,11 +107,40 @@ impl ToolRuntimeService {
         cache.retain(|_, entry| entry.expires_at > now);
     }
 
+    /// The request's environment with the tool's valves and the user's
+    /// UserValves layered over it
+    async fn resolve_environment(
+        &self,
+        db: &Database,
+        tool: &Tool,
+        tool_def: &ToolDefinition,
+        context: &ExecutionContext,
+    ) -> AppResult<HashMap<String, String>> {
+        let mut environment = context.environment.clone();
+
+        if let Some(spec) = tool_def.environment.valves_spec() {
+            let admin_valves = valves::with_defaults(tool.valves.as_ref(), Some(&spec));
+            environment.extend(valves::to_environment(&admin_valves));
+        }
+
+        if let (Some(spec), Some(user)) = (tool_def.environment.user_valves_spec(), &context.user) {
+            let settings = UserService::new(db)
+                .get_user_by_id(&user.id)
+                .await?
+                .and_then(|u| u.settings);
+            let user_valves = valves::user_valves(settings.as_ref(), "tools", &tool.id);
+            let user_valves = valves::with_defaults(Some(&user_valves), Some(&spec));
+            environment.extend(valves::to_environment(&user_valves));
+        }
+
+        Ok(environment)
+    }
+
     /// Execute a tool by ID and name
     pub async fn execute_tool(
         &self,
         db: &Database,
-        request: ToolExecutionRequest,
+        mut request: ToolExecutionRequest,
     ) -> AppResult<ToolExecutionResponse> {
         let start_time = Instant::now();
 
@@ -156,6 +189,9 @@ impl ToolRuntimeService {
         self.validate_parameters(tool_spec, &request.parameters)?;
 
         // Validate required environment variables
+        request.context.environment = self
+            .resolve_environment(db, &tool, &tool_def, &request.context)
+            .await?;
         self.validate_environment(&tool_def.environment, &request.context.environment)?;
 
         // Execute with error handling strategy
@@ -354,10 +390,10 @@ impl ToolRuntimeService {
         environment: &HashMap<String, String>,
     ) -> AppResult<()> {
         for env_var in &env_config.required {
-            if !environment.contains_key(env_var) {
+            if !environment.contains_key(env_var.name()) {
                 return Err(AppError::BadRequest(format!(
                     "Missing required environment variable: {}",
-                    env_var
+                    env_var.name()
                 )));
             }
         }
@@ -593,6 +629,9 @@ impl ToolRuntimeService {
     }
 
     /// Execute MCP tool
+    ///
+    /// Tool definitions can only reach HTTP servers; stdio servers spawn local
+    /// processes and are limited to admin-configured tool server connections.
     async fn execute_mcp_tool(
         &self,
         mcp_servers: &HashMap<String, McpServerConfig>,
@@ -605,30 +644,44 @@ impl ToolRuntimeService {
             .get(server_name)
             .ok_or_else(|| AppError::NotFound(format!("MCP server not found: {}", server_name)))?;
 
-        // Build MCP request
-        let url = format!("{}/tools/{}/execute", server_config.url, tool_name);
-        let mut request_builder = self.http_client.post(&url).json(&serde_json::json!({
-            "arguments": parameters
-        }));
-
-        // Add authentication
-        if let Some(token) = &server_config.auth_token {
-            if server_config.auth_type.as_deref() == Some("bearer") {
-                request_builder = request_builder.bearer_auth(token);
+        let transport = match server_config.transport.as_deref() {
+            None | Some("streamable_http") | Some("http") => mcp::McpTransport::StreamableHttp,
+            Some("sse") => mcp::McpTransport::Sse,
+            Some(other) => {
+                return Err(AppError::BadRequest(format!(
+                    "Unsupported MCP transport for tool definitions: {}",
+                    other
+                )))
             }
-        }
-
-        // Execute MCP request
-        let response = request_builder
-            .send()
-            .await
-            .map_err(|e| AppError::InternalServerError(format!("MCP request failed: {}", e)))?;
+        };
+        let config = mcp::McpServerConfig {
+            name: server_name.to_string(),
+            url: server_config.url.clone(),
+            transport,
+            auth_type: server_config.auth_type.clone(),
+            auth_token: server_config.auth_token.clone(),
+            headers: server_config.headers.clone(),
+            enabled: true,
+            ..Default::default()
+        };
 
-        let result: Value = response.json().await.map_err(|e| {
-            AppError::InternalServerError(format!("Failed to parse MCP response: {}", e))
-        })?;
+        let session_key = format!("tool:{}@{}", server_name, server_config.url);
+        let result = mcp::call_tool(
+            &session_key,
+            &config,
+            tool_name,
+            serde_json::json!(parameters),
+        )
+        .await?;
+        if result.is_error {
+            return Err(AppError::ExternalServiceError(format!(
+                "MCP tool {} failed: {}",
+                tool_name,
+                result.output()
+            )));
+        }
 
-        Ok((result, None))
+        Ok((result.output(), None))
     }
 
     /// Execute built-in function tool
@@ -672,7 +725,7 @@ impl ToolRuntimeService {
         tool_id: &str,
         chain_name: &str,
         initial_parameters: HashMap<String, Value>,
-        context: ExecutionContext,
+        mut context: ExecutionContext,
     ) -> AppResult<ToolExecutionResponse> {
         let start_time = Instant::now();
 
@@ -691,6 +744,9 @@ impl ToolRuntimeService {
         let chain = tool_def
             .find_chain(chain_name)
             .ok_or_else(|| AppError::NotFound(format!("Tool chain not found: {}", chain_name)))?;
+        context.environment = self
+            .resolve_environment(db, &tool, &tool_def, &context)
+            .await?;
 
         // Execute chain steps
         let mut current_parameters = initial_parameters;
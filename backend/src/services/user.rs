@@ -194,6 +194,30 @@ impl<'a> UserService<'a> {
         Ok(())
     }
 
+    /// Change a user's email, keeping the auth record's login email in sync
+    pub async fn update_user_email(&self, id: &str, email: &str) -> AppResult<()> {
+        sqlx::query(
+            r#"
+            UPDATE "user"
+            SET email = $1, updated_at = $2
+            WHERE id = $3
+            "#,
+        )
+        .bind(email)
+        .bind(current_timestamp_seconds())
+        .bind(id)
+        .execute(&self.db.pool)
+        .await?;
+
+        sqlx::query("UPDATE auth SET email = $1 WHERE id = $2")
+            .bind(email)
+            .bind(id)
+            .execute(&self.db.pool)
+            .await?;
+
+        Ok(())
+    }
+
     pub async fn delete_user(&self, id: &str) -> AppResult<()> {
         sqlx::query(r#"DELETE FROM "user" WHERE id = $1"#)
             .bind(id)
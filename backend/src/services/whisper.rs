@@ -0,0 +1,606 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use candle_core::{Device, IndexOp, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::whisper::{self as m, audio, model::Whisper};
+use hf_hub::{api::sync::Api, Repo, RepoType};
+use once_cell::sync::Lazy;
+use tokenizers::Tokenizer;
+use tracing::info;
+
+use super::stt::{AudioInput, SttEngine, Transcription};
+use crate::error::{AppError, AppResult};
+
+/// Loaded models, keyed by `WHISPER_MODEL`, shared across requests
+static WHISPER_MODELS: Lazy<Mutex<HashMap<String, Arc<WhisperStt>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn model_error(e: impl std::fmt::Display) -> AppError {
+    AppError::InternalServerError(format!("Whisper error: {}", e))
+}
+
+/// Local Whisper speech-to-text using candle
+///
+/// `WHISPER_MODEL` is either a size (`tiny`, `base`, `small`, `medium`,
+/// `large-v3`, optionally with `.en`), a HuggingFace repo id, or a local
+/// directory containing `config.json`, `tokenizer.json` and
+/// `model.safetensors`.
+pub struct WhisperStt {
+    model_name: String,
+    components: Arc<Mutex<Option<WhisperComponents>>>,
+}
+
+struct WhisperComponents {
+    model: Whisper,
+    config: m::Config,
+    tokenizer: Tokenizer,
+    mel_filters: Vec<f32>,
+    suppress_tokens: Tensor,
+    language_tokens: Vec<(String, u32)>,
+    sot_token: u32,
+    transcribe_token: u32,
+    eot_token: u32,
+    no_timestamps_token: u32,
+    device: Device,
+}
+
+impl WhisperStt {
+    /// Return the cached engine for a model
+    ///
+    /// The model itself is downloaded and loaded on the first transcription.
+    pub fn get_or_load(model_name: &str) -> Arc<Self> {
+        let mut models = WHISPER_MODELS.lock().unwrap();
+        models
+            .entry(model_name.to_string())
+            .or_insert_with(|| {
+                Arc::new(Self {
+                    model_name: model_name.to_string(),
+                    components: Arc::new(Mutex::new(None)),
+                })
+            })
+            .clone()
+    }
+
+    fn get_model_path(model_name: &str) -> AppResult<PathBuf> {
+        let local_path = PathBuf::from(model_name);
+        if local_path.is_dir() {
+            return Ok(local_path);
+        }
+
+        let repo_id = if model_name.contains('/') {
+            model_name.to_string()
+        } else {
+            format!("openai/whisper-{}", model_name)
+        };
+        info!("Downloading Whisper model {}", repo_id);
+
+        let api = Api::new().map_err(model_error)?;
+        let repo = api.repo(Repo::new(repo_id.clone(), RepoType::Model));
+        let config_path = repo.get("config.json").map_err(|e| {
+            AppError::BadRequest(format!(
+                "Failed to download Whisper model {}: {}",
+                repo_id, e
+            ))
+        })?;
+        repo.get("tokenizer.json").map_err(model_error)?;
+        repo.get("model.safetensors").map_err(model_error)?;
+
+        config_path
+            .parent()
+            .map(Path::to_path_buf)
+            .ok_or_else(|| model_error("invalid model path"))
+    }
+
+    fn load_components(model_name: &str) -> AppResult<WhisperComponents> {
+        let model_path = Self::get_model_path(model_name)?;
+        let model_path = model_path.as_path();
+        info!("Loading Whisper model from {:?}", model_path);
+        let device = Device::Cpu;
+
+        let config: m::Config = serde_json::from_str(
+            &std::fs::read_to_string(model_path.join("config.json")).map_err(model_error)?,
+        )
+        .map_err(model_error)?;
+        let tokenizer =
+            Tokenizer::from_file(model_path.join("tokenizer.json")).map_err(model_error)?;
+
+        let vb = unsafe {
+            VarBuilder::from_mmaped_safetensors(
+                &[model_path.join("model.safetensors")],
+                m::DTYPE,
+                &device,
+            )
+            .map_err(model_error)?
+        };
+        let model = Whisper::load(&vb, config.clone()).map_err(model_error)?;
+
+        let token = |name: &str| {
+            tokenizer
+                .token_to_id(name)
+                .ok_or_else(|| model_error(format!("tokenizer has no {} token", name)))
+        };
+        let sot_token = token(m::SOT_TOKEN)?;
+        let transcribe_token = token(m::TRANSCRIBE_TOKEN)?;
+        let eot_token = token(m::EOT_TOKEN)?;
+        let no_timestamps_token = token(m::NO_TIMESTAMPS_TOKEN)?;
+
+        // Language tokens look like <|en|>, <|haw|>; English-only models have none
+        let mut language_tokens: Vec<(String, u32)> = tokenizer
+            .get_vocab(true)
+            .into_iter()
+            .filter_map(|(token, id)| {
+                let code = token.strip_prefix("<|")?.strip_suffix("|>")?;
+                (code.len() <= 3 && code.chars().all(|c| c.is_ascii_lowercase()))
+                    .then(|| (code.to_string(), id))
+            })
+            .collect();
+        language_tokens.sort_by_key(|(_, id)| *id);
+
+        let suppress: Vec<f32> = (0..config.vocab_size as u32)
+            .map(|i| {
+                if config.suppress_tokens.contains(&i) {
+                    f32::NEG_INFINITY
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let suppress_tokens = Tensor::new(suppress.as_slice(), &device).map_err(model_error)?;
+
+        let mel_filters = mel_filters(config.num_mel_bins);
+
+        Ok(WhisperComponents {
+            model,
+            config,
+            tokenizer,
+            mel_filters,
+            suppress_tokens,
+            language_tokens,
+            sot_token,
+            transcribe_token,
+            eot_token,
+            no_timestamps_token,
+            device,
+        })
+    }
+
+    fn transcribe_blocking(
+        model_name: &str,
+        components: &Mutex<Option<WhisperComponents>>,
+        input: &AudioInput,
+    ) -> AppResult<String> {
+        let extension = Path::new(&input.filename)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_string);
+        let pcm = decode_audio(input.data.clone(), extension.as_deref())?;
+
+        let mut guard = components.lock().unwrap();
+        if guard.is_none() {
+            *guard = Some(Self::load_components(model_name)?);
+        }
+        let components = guard.as_mut().expect("components loaded above");
+
+        components
+            .transcribe(&pcm, input.language.as_deref())
+            .map_err(model_error)
+    }
+}
+
+impl WhisperComponents {
+    fn transcribe(&mut self, pcm: &[f32], language: Option<&str>) -> candle_core::Result<String> {
+        let n_mels = self.config.num_mel_bins;
+        let mel = audio::pcm_to_mel(&self.config, pcm, &self.mel_filters);
+        let mel_len = mel.len();
+        let mel = Tensor::from_vec(mel, (1, n_mels, mel_len / n_mels), &self.device)?;
+
+        // The spectrogram is padded with silence, only decode the real audio
+        let content_frames = usize::min(mel.dims3()?.2, pcm.len().div_ceil(m::HOP_LENGTH));
+        let mut seek = 0;
+        let mut segments = Vec::new();
+
+        while seek < content_frames {
+            let segment_size = usize::min(content_frames - seek, m::N_FRAMES);
+            let mel_segment = mel.narrow(2, seek, segment_size)?;
+            let audio_features = self.model.encoder.forward(&mel_segment, true)?;
+
+            let language_token = self.language_token(&audio_features, language)?;
+            let text = self.decode(&audio_features, language_token)?;
+            if !text.trim().is_empty() {
+                segments.push(text.trim().to_string());
+            }
+
+            seek += segment_size;
+        }
+
+        Ok(segments.join(" "))
+    }
+
+    /// Language token from the hint, or the most likely one for the audio
+    fn language_token(
+        &mut self,
+        audio_features: &Tensor,
+        language: Option<&str>,
+    ) -> candle_core::Result<Option<u32>> {
+        if self.language_tokens.is_empty() {
+            return Ok(None);
+        }
+        if let Some(language) = language {
+            let code = language
+                .split(['-', '_'])
+                .next()
+                .unwrap_or(language)
+                .to_lowercase();
+            if let Some((_, id)) = self.language_tokens.iter().find(|(c, _)| *c == code) {
+                return Ok(Some(*id));
+            }
+        }
+
+        let tokens = Tensor::new(&[[self.sot_token]], &self.device)?;
+        let ys = self.model.decoder.forward(&tokens, audio_features, true)?;
+        let logits = self
+            .model
+            .decoder
+            .final_linear(&ys.i((..1, ..1))?)?
+            .i(0)?
+            .i(0)?;
+        let ids: Vec<u32> = self.language_tokens.iter().map(|(_, id)| *id).collect();
+        let language_logits =
+            logits.index_select(&Tensor::new(ids.as_slice(), &self.device)?, 0)?;
+        let best = language_logits.argmax(0)?.to_scalar::<u32>()? as usize;
+
+        Ok(Some(ids[best]))
+    }
+
+    /// Greedy decoding of one 30 second segment
+    fn decode(
+        &mut self,
+        audio_features: &Tensor,
+        language_token: Option<u32>,
+    ) -> candle_core::Result<String> {
+        let mut tokens = vec![self.sot_token];
+        tokens.extend(language_token);
+        tokens.push(self.transcribe_token);
+        tokens.push(self.no_timestamps_token);
+        let prompt_len = tokens.len();
+
+        let sample_len = self.config.max_target_positions / 2;
+        for i in 0..sample_len {
+            let tokens_t = Tensor::new(tokens.as_slice(), &self.device)?.unsqueeze(0)?;
+            let ys = self
+                .model
+                .decoder
+                .forward(&tokens_t, audio_features, i == 0)?;
+
+            let (_, seq_len, _) = ys.dims3()?;
+            let logits = self
+                .model
+                .decoder
+                .final_linear(&ys.i((..1, seq_len - 1..))?)?
+                .i(0)?
+                .i(0)?
+                .broadcast_add(&self.suppress_tokens)?;
+            let next_token = logits.argmax(0)?.to_scalar::<u32>()?;
+
+            if next_token == self.eot_token || tokens.len() >= self.config.max_target_positions {
+                break;
+            }
+            tokens.push(next_token);
+        }
+
+        self.tokenizer
+            .decode(&tokens[prompt_len..], true)
+            .map_err(|e| candle_core::Error::Msg(e.to_string()))
+    }
+}
+
+#[async_trait::async_trait]
+impl SttEngine for WhisperStt {
+    async fn transcribe(&self, input: &AudioInput) -> AppResult<Transcription> {
+        let model_name = self.model_name.clone();
+        let components = self.components.clone();
+        let input = input.clone();
+
+        let text = tokio::task::spawn_blocking(move || {
+            Self::transcribe_blocking(&model_name, &components, &input)
+        })
+        .await
+        .map_err(|e| model_error(format!("task join error: {}", e)))??;
+
+        Ok(Transcription { text })
+    }
+
+    fn name(&self) -> &'static str {
+        "whisper"
+    }
+}
+
+/// Decode any supported container/codec into 16 kHz mono samples
+fn decode_audio(data: Vec<u8>, extension: Option<&str>) -> AppResult<Vec<f32>> {
+    use symphonia::core::audio::SampleBuffer;
+    use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL, CODEC_TYPE_OPUS};
+    use symphonia::core::errors::Error as SymphoniaError;
+    use symphonia::core::formats::FormatOptions;
+    use symphonia::core::io::MediaSourceStream;
+    use symphonia::core::meta::MetadataOptions;
+    use symphonia::core::probe::Hint;
+
+    let unsupported =
+        |e: SymphoniaError| AppError::BadRequest(format!("Unsupported audio file: {}", e));
+
+    let mss = MediaSourceStream::new(Box::new(std::io::Cursor::new(data)), Default::default());
+    let mut hint = Hint::new();
+    if let Some(extension) = extension {
+        hint.with_extension(extension);
+    }
+
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(unsupported)?;
+    let mut format = probed.format;
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| AppError::BadRequest("No audio track found".to_string()))?;
+    // symphonia has no Opus decoder; the web recorder converts to WAV first
+    if track.codec_params.codec == CODEC_TYPE_OPUS {
+        return Err(AppError::BadRequest(
+            "Opus audio is not supported by local Whisper, send WAV, MP3, FLAC or AAC instead"
+                .to_string(),
+        ));
+    }
+    let track_id = track.id;
+    let sample_rate = track
+        .codec_params
+        .sample_rate
+        .ok_or_else(|| AppError::BadRequest("Unknown audio sample rate".to_string()))?;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(unsupported)?;
+
+    let mut samples = Vec::new();
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break
+            }
+            Err(e) => return Err(unsupported(e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // Skip corrupt packets rather than failing the whole file
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(unsupported(e)),
+        };
+
+        let spec = *decoded.spec();
+        let channels = spec.channels.count().max(1);
+        let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        buffer.copy_interleaved_ref(decoded);
+        samples.extend(
+            buffer
+                .samples()
+                .chunks(channels)
+                .map(|frame| frame.iter().sum::<f32>() / channels as f32),
+        );
+    }
+
+    Ok(resample(&samples, sample_rate as usize, m::SAMPLE_RATE))
+}
+
+/// Linear-interpolation resampling, good enough for speech recognition
+fn resample(samples: &[f32], from_rate: usize, to_rate: usize) -> Vec<f32> {
+    if from_rate == to_rate || samples.is_empty() {
+        return samples.to_vec();
+    }
+
+    let ratio = from_rate as f64 / to_rate as f64;
+    let out_len = (samples.len() as f64 / ratio).floor() as usize;
+    (0..out_len)
+        .map(|i| {
+            let pos = i as f64 * ratio;
+            let idx = pos.floor() as usize;
+            let frac = (pos - idx as f64) as f32;
+            let a = samples[idx];
+            let b = samples.get(idx + 1).copied().unwrap_or(a);
+            a + (b - a) * frac
+        })
+        .collect()
+}
+
+fn hz_to_mel(hz: f64) -> f64 {
+    // Slaney scale: linear below 1 kHz, logarithmic above
+    let f_sp = 200.0 / 3.0;
+    let min_log_hz = 1000.0;
+    let min_log_mel = min_log_hz / f_sp;
+    let logstep = (6.4f64).ln() / 27.0;
+    if hz >= min_log_hz {
+        min_log_mel + (hz / min_log_hz).ln() / logstep
+    } else {
+        hz / f_sp
+    }
+}
+
+fn mel_to_hz(mel: f64) -> f64 {
+    let f_sp = 200.0 / 3.0;
+    let min_log_hz = 1000.0;
+    let min_log_mel = min_log_hz / f_sp;
+    let logstep = (6.4f64).ln() / 27.0;
+    if mel >= min_log_mel {
+        min_log_hz * (logstep * (mel - min_log_mel)).exp()
+    } else {
+        f_sp * mel
+    }
+}
+
+/// Slaney-normalised mel filterbank, laid out as `[n_mels][N_FFT / 2 + 1]`
+///
+/// Matches `librosa.filters.mel(sr=16000, n_fft=400, n_mels=n_mels)`, which
+/// is what the Whisper checkpoints were trained with.
+fn mel_filters(n_mels: usize) -> Vec<f32> {
+    let n_freqs = m::N_FFT / 2 + 1;
+    let sample_rate = m::SAMPLE_RATE as f64;
+
+    let fft_freqs: Vec<f64> = (0..n_freqs)
+        .map(|i| i as f64 * sample_rate / m::N_FFT as f64)
+        .collect();
+    let max_mel = hz_to_mel(sample_rate / 2.0);
+    let mel_points: Vec<f64> = (0..n_mels + 2)
+        .map(|i| mel_to_hz(max_mel * i as f64 / (n_mels + 1) as f64))
+        .collect();
+
+    let mut filters = vec![0.0f32; n_mels * n_freqs];
+    for m_idx in 0..n_mels {
+        let (lower, center, upper) = (
+            mel_points[m_idx],
+            mel_points[m_idx + 1],
+            mel_points[m_idx + 2],
+        );
+        let norm = 2.0 / (upper - lower);
+        for (f_idx, &freq) in fft_freqs.iter().enumerate() {
+            let rising = (freq - lower) / (center - lower);
+            let falling = (upper - freq) / (upper - center);
+            let weight = rising.min(falling).max(0.0);
+            filters[m_idx * n_freqs + f_idx] = (weight * norm) as f32;
+        }
+    }
+    filters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resample() {
+        let samples: Vec<f32> = (0..48).map(|i| i as f32).collect();
+        let resampled = resample(&samples, 48_000, 16_000);
+
+        assert_eq!(resampled.len(), 16);
+        assert_eq!(resampled[1], 3.0);
+        assert_eq!(resample(&samples, 16_000, 16_000), samples);
+    }
+
+    #[test]
+    fn test_mel_filters_shape() {
+        let filters = mel_filters(80);
+        assert_eq!(filters.len(), 80 * (m::N_FFT / 2 + 1));
+        // Every band has some weight and nothing is negative
+        for band in filters.chunks(m::N_FFT / 2 + 1) {
+            assert!(band.iter().any(|w| *w > 0.0));
+            assert!(band.iter().all(|w| *w >= 0.0));
+        }
+    }
+
+    #[test]
+    fn test_decode_wav() {
+        // 0.1 s of a 440 Hz tone, 8 kHz mono 16-bit PCM
+        let rate = 8_000u32;
+        let pcm: Vec<i16> = (0..800)
+            .map(|i| {
+                ((i as f32 * 440.0 * std::f32::consts::TAU / rate as f32).sin() * 8000.0) as i16
+            })
+            .collect();
+        let mut wav = Vec::new();
+        let data_len = (pcm.len() * 2) as u32;
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&rate.to_le_bytes());
+        wav.extend_from_slice(&(rate * 2).to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        for sample in pcm {
+            wav.extend_from_slice(&sample.to_le_bytes());
+        }
+
+        let samples = decode_audio(wav, Some("wav")).unwrap();
+        assert_eq!(samples.len(), 1600);
+        assert!(samples.iter().any(|s| s.abs() > 0.1));
+    }
+
+    /// An EBML element with an 8-byte size
+    fn ebml(id: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut element = id.to_vec();
+        element.push(0x01);
+        element.extend_from_slice(&(data.len() as u64).to_be_bytes()[1..]);
+        element.extend_from_slice(data);
+        element
+    }
+
+    #[test]
+    fn test_decode_webm_opus_is_refused() {
+        // A WebM file holding a single 20 ms Opus packet, as browsers record
+        let mut opus_head = b"OpusHead".to_vec();
+        opus_head.extend_from_slice(&[1, 1]);
+        opus_head.extend_from_slice(&312u16.to_le_bytes());
+        opus_head.extend_from_slice(&48_000u32.to_le_bytes());
+        opus_head.extend_from_slice(&[0, 0, 0]);
+
+        let header = [
+            ebml(&[0x42, 0x86], &[1]),
+            ebml(&[0x42, 0xF7], &[1]),
+            ebml(&[0x42, 0xF2], &[4]),
+            ebml(&[0x42, 0xF3], &[8]),
+            ebml(&[0x42, 0x82], b"webm"),
+            ebml(&[0x42, 0x87], &[4]),
+            ebml(&[0x42, 0x85], &[2]),
+        ]
+        .concat();
+        let track = [
+            ebml(&[0xD7], &[1]),
+            ebml(&[0x73, 0xC5], &[1]),
+            ebml(&[0x83], &[2]),
+            ebml(&[0x86], b"A_OPUS"),
+            ebml(&[0x63, 0xA2], &opus_head),
+            ebml(
+                &[0xE1],
+                &[ebml(&[0xB5], &48_000f64.to_be_bytes()), ebml(&[0x9F], &[1])].concat(),
+            ),
+        ]
+        .concat();
+        let segment = [
+            ebml(
+                &[0x15, 0x49, 0xA9, 0x66],
+                &ebml(&[0x2A, 0xD7, 0xB1], &1_000_000u32.to_be_bytes()),
+            ),
+            ebml(&[0x16, 0x54, 0xAE, 0x6B], &ebml(&[0xAE], &track)),
+            ebml(
+                &[0x1F, 0x43, 0xB6, 0x75],
+                &[
+                    ebml(&[0xE7], &[0]),
+                    ebml(&[0xA3], &[0x81, 0, 0, 0x80, 0xF8, 0xFF, 0xFE]),
+                ]
+                .concat(),
+            ),
+        ]
+        .concat();
+        let webm = [
+            ebml(&[0x1A, 0x45, 0xDF, 0xA3], &header),
+            ebml(&[0x18, 0x53, 0x80, 0x67], &segment),
+        ]
+        .concat();
+
+        let err = decode_audio(webm, Some("webm")).unwrap_err();
+        assert!(err.to_string().contains("Opus audio is not supported"));
+    }
+}
@@ -6,10 +6,11 @@
 /// - Channel events (channel-events)
 /// - Yjs collaborative editing (ydoc:*)
 /// - Usage tracking
+/// - Messages from other nodes (via Redis)
 use crate::db::Database;
 use crate::socketio::manager::SocketIOManager;
 use crate::socketio::protocol::{EnginePacket, SocketPacket};
-use crate::socketio::redis_adapter::RedisAdapter;
+use crate::socketio::redis_adapter::{RedisAdapter, RedisMessage, RedisMessageType};
 use crate::socketio::ydoc::YDocManager;
 use actix_web::web;
 use serde::{Deserialize, Serialize};
@@ -22,8 +23,12 @@ use tokio::sync::RwLock;
 /// This allows us to send messages to specific sessions
 type ConnectionRegistry = Arc<RwLock<HashMap<String, tokio::sync::mpsc::UnboundedSender<String>>>>;
 
+/// How often each node publishes its rooms to the others
+const ROOM_SNAPSHOT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
 use crate::socketio::metrics::SocketIOMetrics;
 use crate::socketio::presence::PresenceManager;
+use crate::socketio::prometheus::PrometheusExporter;
 use crate::socketio::rate_limit::RateLimiter;
 use crate::socketio::recovery::RecoveryManager;
 
@@ -88,6 +93,14 @@ impl EventHandler {
         &self.recovery_manager
     }
 
+    /// Prometheus exporter over this handler's metrics and managers
+    pub fn prometheus_exporter(&self) -> PrometheusExporter {
+        PrometheusExporter::new(self.metrics.clone())
+            .with_rate_limiter(self.rate_limiter.clone())
+            .with_presence_manager(self.presence_manager.clone())
+            .with_recovery_manager(self.recovery_manager.clone())
+    }
+
     /// Get auth endpoint
     pub fn auth_endpoint(&self) -> &str {
         &self.auth_endpoint
@@ -138,16 +151,25 @@ impl EventHandler {
             tracing::error!("Failed to remove user {} from ydoc documents: {}", sid, e);
         }
 
+        self.publish(RedisMessageType::SessionClosed {
+            session_id: sid.to_string(),
+        })
+        .await;
+
         tracing::info!("Unregistered connection: {}", sid);
     }
 
-    /// Emit event to a specific session
-    pub async fn emit_to_session(
-        &self,
-        sid: &str,
-        event: &str,
-        data: JsonValue,
-    ) -> Result<(), String> {
+    /// Publish a message to the other nodes, if Redis is configured
+    async fn publish(&self, message_type: RedisMessageType) {
+        if let Some(redis) = &self.redis_adapter {
+            if let Err(e) = redis.publish_message(message_type).await {
+                tracing::warn!("Failed to publish to Redis: {}", e);
+            }
+        }
+    }
+
+    /// Send an event to a session connected to this node
+    async fn send_local(&self, sid: &str, event: &str, data: JsonValue) -> Result<(), String> {
         let connections = self.connections.read().await;
         if let Some(sender) = connections.get(sid) {
             let socket_packet = SocketPacket::event("/", event, data);
@@ -162,25 +184,104 @@ impl EventHandler {
         }
     }
 
+    /// Send an event to the user's sessions on this node
+    async fn send_to_local_user(&self, user_id: &str, event: &str, data: &JsonValue) -> usize {
+        let mut sent = 0;
+        for sid in self.manager.get_user_sessions(user_id).await {
+            if self.send_local(&sid, event, data.clone()).await.is_ok() {
+                sent += 1;
+            }
+        }
+        sent
+    }
+
+    /// Send an event to the room's sessions on this node
+    async fn send_to_local_room(
+        &self,
+        room: &str,
+        event: &str,
+        data: &JsonValue,
+        exclude_sid: Option<&str>,
+    ) -> usize {
+        let mut sent = 0;
+        for sid in self.manager.get_room_sessions(room).await {
+            if Some(sid.as_str()) == exclude_sid {
+                continue;
+            }
+            if self.send_local(&sid, event, data.clone()).await.is_ok() {
+                sent += 1;
+            }
+        }
+        sent
+    }
+
+    /// Join a room and tell the other nodes
+    pub async fn join_room(&self, sid: &str, room: &str) -> Result<(), String> {
+        self.manager.join_room(sid, room).await?;
+        self.publish(RedisMessageType::RoomJoined {
+            room: room.to_string(),
+            session_id: sid.to_string(),
+        })
+        .await;
+        Ok(())
+    }
+
+    /// Leave a room and tell the other nodes
+    pub async fn leave_room(&self, sid: &str, room: &str) -> Result<(), String> {
+        self.manager.leave_room(sid, room).await?;
+        self.publish(RedisMessageType::RoomLeft {
+            room: room.to_string(),
+            session_id: sid.to_string(),
+        })
+        .await;
+        Ok(())
+    }
+
+    /// Emit event to a specific session
+    ///
+    /// Sessions not connected to this node are reached through Redis.
+    pub async fn emit_to_session(
+        &self,
+        sid: &str,
+        event: &str,
+        data: JsonValue,
+    ) -> Result<(), String> {
+        match self.send_local(sid, event, data.clone()).await {
+            Err(_) if self.redis_adapter.is_some() => {
+                self.publish(RedisMessageType::Emit {
+                    user_id: None,
+                    session_id: Some(sid.to_string()),
+                    room: None,
+                    event: event.to_string(),
+                    data,
+                })
+                .await;
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
     /// Emit event to all sessions of a user
+    ///
+    /// Returns the number of sessions reached on this node; sessions on
+    /// other nodes are reached through Redis.
     pub async fn emit_to_user(
         &self,
         user_id: &str,
         event: &str,
         data: JsonValue,
     ) -> Result<usize, String> {
-        let sids = self.manager.get_user_sessions(user_id).await;
-        let mut sent = 0;
+        let sent = self.send_to_local_user(user_id, event, &data).await;
 
-        for sid in sids {
-            if self
-                .emit_to_session(&sid, event, data.clone())
-                .await
-                .is_ok()
-            {
-                sent += 1;
-            }
-        }
+        self.publish(RedisMessageType::Emit {
+            user_id: Some(user_id.to_string()),
+            session_id: None,
+            room: None,
+            event: event.to_string(),
+            data,
+        })
+        .await;
 
         Ok(sent)
     }
@@ -193,42 +294,139 @@ impl EventHandler {
         data: JsonValue,
         exclude_sid: Option<&str>,
     ) -> Result<usize, String> {
-        // Broadcast to local sessions
-        let sids = self.manager.get_room_sessions(room).await;
-        let mut sent = 0;
+        let sent = self
+            .send_to_local_room(room, event, &data, exclude_sid)
+            .await;
 
-        for sid in sids {
-            if Some(sid.as_str()) == exclude_sid {
-                continue;
-            }
+        // Publish to Redis for cross-server broadcasting
+        self.publish(RedisMessageType::Broadcast {
+            room: room.to_string(),
+            event: event.to_string(),
+            data,
+            exclude_sid: exclude_sid.map(|s| s.to_string()),
+        })
+        .await;
 
-            if self
-                .emit_to_session(&sid, event, data.clone())
-                .await
-                .is_ok()
-            {
-                sent += 1;
+        Ok(sent)
+    }
+
+    /// Route a message published by another node to the sessions on this one
+    pub async fn handle_redis_message(&self, message: RedisMessage) {
+        let server_id = message.server_id;
+        match message.message_type {
+            RedisMessageType::Emit {
+                user_id,
+                session_id,
+                room,
+                event,
+                data,
+            } => {
+                if let Some(user_id) = user_id {
+                    self.send_to_local_user(&user_id, &event, &data).await;
+                } else if let Some(sid) = session_id {
+                    // Only the node holding the session delivers it
+                    let _ = self.send_local(&sid, &event, data).await;
+                } else if let Some(room) = room {
+                    self.send_to_local_room(&room, &event, &data, None).await;
+                }
+            }
+            RedisMessageType::Broadcast {
+                room,
+                event,
+                data,
+                exclude_sid,
+            } => {
+                self.send_to_local_room(&room, &event, &data, exclude_sid.as_deref())
+                    .await;
+            }
+            RedisMessageType::UserJoined { .. } => {}
+            RedisMessageType::UserLeft { session_id, .. }
+            | RedisMessageType::SessionClosed { session_id } => {
+                self.manager
+                    .remove_remote_session(&server_id, &session_id)
+                    .await;
+            }
+            RedisMessageType::RoomJoined { room, session_id } => {
+                self.manager
+                    .add_remote_room_member(&server_id, &room, &session_id)
+                    .await;
+            }
+            RedisMessageType::RoomLeft { room, session_id } => {
+                self.manager
+                    .remove_remote_room_member(&server_id, &room, &session_id)
+                    .await;
+            }
+            RedisMessageType::YdocUpdate {
+                document_id,
+                update,
+                data,
+                exclude_sid,
+            } => {
+                self.ydoc_manager
+                    .append_remote_update(&document_id, update)
+                    .await;
+                let room = format!("doc_{}", document_id);
+                self.send_to_local_room(
+                    &room,
+                    "ydoc:document:update",
+                    &data,
+                    exclude_sid.as_deref(),
+                )
+                .await;
+            }
+            RedisMessageType::SyncRequest => self.publish_room_snapshot().await,
+            RedisMessageType::RoomSnapshot { rooms } => {
+                self.manager.set_remote_rooms(&server_id, rooms).await;
             }
         }
+    }
 
-        // Publish to Redis for cross-server broadcasting
-        if let Some(redis) = &self.redis_adapter {
+    async fn publish_room_snapshot(&self) {
+        let rooms = self.manager.get_rooms_snapshot().await;
+        self.publish(RedisMessageType::RoomSnapshot { rooms }).await;
+    }
+
+    /// Subscribe to the other nodes' messages, and keep them informed of
+    /// this node's rooms
+    ///
+    /// Messages are handled one at a time, in the order they were published.
+    pub fn spawn_redis_listener(&self) {
+        let Some(redis) = self.redis_adapter.clone() else {
+            return;
+        };
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel::<RedisMessage>();
+
+        tokio::spawn(async move {
             if let Err(e) = redis
-                .publish_broadcast(
-                    room.to_string(),
-                    event.to_string(),
-                    data.clone(),
-                    exclude_sid.map(|s| s.to_string()),
-                )
+                .subscribe(move |message| {
+                    let _ = sender.send(message);
+                })
                 .await
             {
-                tracing::warn!("Failed to publish to Redis: {}", e);
-            } else {
-                tracing::debug!("Published broadcast to Redis for room: {}", room);
+                tracing::error!("Redis subscription error: {:?}", e);
             }
-        }
+        });
 
-        Ok(sent)
+        let handler = self.clone();
+        tokio::spawn(async move {
+            while let Some(message) = receiver.recv().await {
+                handler.handle_redis_message(message).await;
+            }
+        });
+
+        let handler = self.clone();
+        tokio::spawn(async move {
+            handler.publish(RedisMessageType::SyncRequest).await;
+            loop {
+                handler.publish_room_snapshot().await;
+                // Nodes that missed three snapshots are gone
+                handler
+                    .manager
+                    .prune_remote_rooms(3 * ROOM_SNAPSHOT_INTERVAL.as_secs() as i64)
+                    .await;
+                tokio::time::sleep(ROOM_SNAPSHOT_INTERVAL).await;
+            }
+        });
     }
 
     /// Handle authentication (user-join event)
@@ -310,7 +508,7 @@ impl EventHandler {
 
                 for channel in channels {
                     let room = format!("channel:{}", channel.id);
-                    if let Err(e) = self.manager.join_room(sid, &room).await {
+                    if let Err(e) = self.join_room(sid, &room).await {
                         tracing::warn!(
                             "Failed to join user {} to channel {}: {}",
                             user_id,
@@ -410,7 +608,7 @@ impl EventHandler {
             .ok_or("Missing channel_id")?;
 
         let room = format!("channel:{}", channel_id);
-        self.manager.join_room(sid, &room).await?;
+        self.join_room(sid, &room).await?;
 
         tracing::info!("Session {} joined channel room: {}", sid, channel_id);
         Ok(())
@@ -424,7 +622,7 @@ impl EventHandler {
             .ok_or("Missing channel_id")?;
 
         let room = format!("channel:{}", channel_id);
-        self.manager.leave_room(sid, &room).await?;
+        self.leave_room(sid, &room).await?;
 
         tracing::info!("Session {} left channel room: {}", sid, channel_id);
         Ok(())
@@ -509,7 +707,7 @@ impl EventHandler {
 
         // Join the Socket.IO room
         let room = format!("doc_{}", doc_id);
-        self.manager.join_room(sid, &room).await?;
+        self.join_room(sid, &room).await?;
 
         // Add user to Yjs document
         self.ydoc_manager.add_user(doc_id, sid).await?;
@@ -517,8 +715,8 @@ impl EventHandler {
         // Get the current document state
         let state_update = self.ydoc_manager.get_state_as_update(doc_id).await?;
 
-        // Get all active session IDs in the room
-        let active_sessions = self.manager.get_room_sessions(&room).await;
+        // Get all active session IDs in the room, on every node
+        let active_sessions = self.manager.get_all_room_sessions(&room).await;
 
         // Send the document state to the joining client
         let state_data = serde_json::json!({
@@ -562,7 +760,7 @@ impl EventHandler {
             .ok_or("Missing document_id")?;
 
         let room = format!("doc_{}", doc_id);
-        self.manager.leave_room(sid, &room).await?;
+        self.leave_room(sid, &room).await?;
 
         // Remove user from Yjs document
         self.ydoc_manager.remove_user(doc_id, sid).await?;
@@ -611,7 +809,7 @@ impl EventHandler {
 
         // Store the update in Yjs manager
         self.ydoc_manager
-            .append_update(doc_id, update_bytes)
+            .append_update(doc_id, update_bytes.clone())
             .await?;
 
         // Broadcast update to all other clients in the room
@@ -624,8 +822,16 @@ impl EventHandler {
             "socket_id": sid,
         });
 
-        self.broadcast_to_room(&room, "ydoc:document:update", broadcast_data, Some(sid))
-            .await?;
+        self.send_to_local_room(&room, "ydoc:document:update", &broadcast_data, Some(sid))
+            .await;
+        // Other nodes record the update as well as relaying it
+        self.publish(RedisMessageType::YdocUpdate {
+            document_id: doc_id.to_string(),
+            update: update_bytes,
+            data: broadcast_data,
+            exclude_sid: Some(sid.to_string()),
+        })
+        .await;
 
         tracing::debug!("Stored and broadcasted Yjs update for document: {}", doc_id);
 
@@ -689,12 +895,16 @@ impl EventHandler {
 
         // Check if session is in the room
         let room = format!("doc_{}", doc_id);
-        let room_sessions = self.manager.get_room_sessions(&room).await;
-
-        if !room_sessions.contains(&sid.to_string()) {
+        if !self
+            .manager
+            .get_room_sessions(&room)
+            .await
+            .contains(&sid.to_string())
+        {
             tracing::warn!("Session {} not in room {}, cannot send state", sid, room);
             return Err("Not in document room".to_string());
         }
+        let room_sessions = self.manager.get_all_room_sessions(&room).await;
 
         // Check if document exists
         if !self.ydoc_manager.document_exists(doc_id).await? {
@@ -742,18 +952,9 @@ impl EventHandler {
 
         // Broadcast awareness update to all clients in the room (including sender for awareness)
         // Awareness needs to be sent to all including sender for cursor sync
-        let room_sids = self.manager.get_room_sessions(&room).await;
-
-        let mut sent = 0;
-        for session_sid in room_sids {
-            if self
-                .emit_to_session(&session_sid, "ydoc:awareness:update", data.clone())
-                .await
-                .is_ok()
-            {
-                sent += 1;
-            }
-        }
+        let sent = self
+            .broadcast_to_room(&room, "ydoc:awareness:update", data.clone(), None)
+            .await?;
 
         tracing::debug!(
             "Broadcasted Yjs awareness update for document {} to {} clients",
@@ -970,3 +1171,181 @@ pub async fn handle_health_check(
         rooms: *stats.get("rooms").unwrap_or(&0),
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::socketio::{
+        PresenceConfig, RateLimitConfig, RecoveryConfig, RecoveryManager, YDocManager,
+    };
+    use serde_json::json;
+    use std::time::Duration;
+    use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+    async fn node(redis_url: Option<&str>, server_id: &str) -> EventHandler {
+        EventHandler::new(
+            SocketIOManager::new(),
+            String::new(),
+            YDocManager::new(None),
+            redis_url.map(|url| Arc::new(RedisAdapter::new(url, server_id.to_string()).unwrap())),
+            SocketIOMetrics::new(),
+            Arc::new(RateLimiter::new(RateLimitConfig::default())),
+            Arc::new(PresenceManager::new(PresenceConfig::default())),
+            Arc::new(RecoveryManager::new(None, RecoveryConfig::default())),
+            Database::new("sqlite::memory:").await.unwrap(),
+        )
+    }
+
+    /// Connect an authenticated client for `user_id` in room `doc_doc-1`
+    async fn connect(
+        handler: &EventHandler,
+        sid: &str,
+        user_id: &str,
+    ) -> UnboundedReceiver<String> {
+        let (sender, receiver) = unbounded_channel();
+        handler.manager().create_session(sid).await;
+        handler
+            .manager()
+            .set_session_user(sid, json!({ "id": user_id }))
+            .await
+            .unwrap();
+        handler.register_connection(sid, sender).await;
+        handler.join_room(sid, "doc_doc-1").await.unwrap();
+        receiver
+    }
+
+    async fn next_packet(receiver: &mut UnboundedReceiver<String>) -> String {
+        tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .expect("no packet received")
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_routes_redis_messages_to_local_sessions() {
+        let handler = node(None, "node-a").await;
+        let mut client = connect(&handler, "sid-1", "user-1").await;
+        let from_b = |message_type| RedisMessage {
+            server_id: "node-b".to_string(),
+            message_type,
+        };
+
+        handler
+            .handle_redis_message(from_b(RedisMessageType::Emit {
+                user_id: Some("user-1".to_string()),
+                session_id: None,
+                room: None,
+                event: "chat-events".to_string(),
+                data: json!({ "token": "hello" }),
+            }))
+            .await;
+        let packet = next_packet(&mut client).await;
+        assert!(packet.contains("chat-events") && packet.contains("hello"));
+
+        handler
+            .handle_redis_message(from_b(RedisMessageType::RoomJoined {
+                room: "doc_doc-1".to_string(),
+                session_id: "sid-2".to_string(),
+            }))
+            .await;
+        assert_eq!(
+            handler
+                .manager()
+                .get_all_room_sessions("doc_doc-1")
+                .await
+                .len(),
+            2
+        );
+
+        handler
+            .handle_redis_message(from_b(RedisMessageType::YdocUpdate {
+                document_id: "doc-1".to_string(),
+                update: vec![1, 2, 3],
+                data: json!({ "document_id": "doc-1", "socket_id": "sid-2" }),
+                exclude_sid: Some("sid-2".to_string()),
+            }))
+            .await;
+        assert!(next_packet(&mut client)
+            .await
+            .contains("ydoc:document:update"));
+        assert_eq!(
+            handler.ydoc_manager.get_updates("doc-1").await.unwrap(),
+            vec![vec![1, 2, 3]]
+        );
+
+        handler
+            .handle_redis_message(from_b(RedisMessageType::SessionClosed {
+                session_id: "sid-2".to_string(),
+            }))
+            .await;
+        assert_eq!(
+            handler.manager().get_all_room_sessions("doc_doc-1").await,
+            vec!["sid-1".to_string()]
+        );
+    }
+
+    // Requires Redis, e.g. docker run -p 6379:6379 redis:7, and only runs
+    // when SOCKETIO_REDIS_URL points at it (see CLI.md)
+    #[tokio::test]
+    async fn test_two_nodes_over_redis() {
+        let Ok(redis_url) = std::env::var("SOCKETIO_REDIS_URL") else {
+            return;
+        };
+        let node_a = node(Some(&redis_url), "test-node-a").await;
+        let node_b = node(Some(&redis_url), "test-node-b").await;
+        node_a.spawn_redis_listener();
+        node_b.spawn_redis_listener();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let mut client_a = connect(&node_a, "sid-a", "user-a").await;
+        let mut client_b = connect(&node_b, "sid-b", "user-b").await;
+
+        // Streaming tokens emitted on one node reach a client of the other
+        for token in ["Hel", "lo"] {
+            node_a
+                .emit_to_user("user-b", "chat-events", json!({ "token": token }))
+                .await
+                .unwrap();
+        }
+        assert!(next_packet(&mut client_b).await.contains("Hel"));
+        assert!(next_packet(&mut client_b).await.contains("lo"));
+
+        // Room membership is visible across nodes
+        for _ in 0..50 {
+            if node_a
+                .manager()
+                .get_all_room_sessions("doc_doc-1")
+                .await
+                .len()
+                == 2
+            {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        let mut sessions = node_a.manager().get_all_room_sessions("doc_doc-1").await;
+        sessions.sort();
+        assert_eq!(sessions, vec!["sid-a", "sid-b"]);
+
+        // Yjs updates are relayed and recorded on the other node
+        node_a
+            .handle_ydoc_update("sid-a", json!({ "document_id": "doc-1", "update": [7, 8] }))
+            .await
+            .unwrap();
+        assert!(next_packet(&mut client_b)
+            .await
+            .contains("ydoc:document:update"));
+        assert_eq!(
+            node_b.ydoc_manager.get_updates("doc-1").await.unwrap(),
+            vec![vec![7, 8]]
+        );
+
+        // Room broadcasts skip the excluded sender on every node
+        node_b
+            .broadcast_to_room("doc_doc-1", "typing:start", json!({}), Some("sid-b"))
+            .await
+            .unwrap();
+        assert!(next_packet(&mut client_a).await.contains("typing:start"));
+        assert!(client_b.try_recv().is_err());
+    }
+}
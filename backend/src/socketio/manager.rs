@@ -4,6 +4,7 @@
 /// - Sessions (sid -> user data)
 /// - User pools (user_id -> [sids])
 /// - Rooms (room_id -> [sids])
+/// - Room membership on other nodes (server_id -> room_id -> [sids])
 /// - Usage tracking (model_id -> {sid -> timestamp})
 use serde_json::Value as JsonValue;
 use std::collections::{HashMap, HashSet};
@@ -42,6 +43,13 @@ impl Session {
     }
 }
 
+/// Rooms of another node, as last reported over Redis
+#[derive(Debug, Clone, Default)]
+struct RemoteRooms {
+    rooms: HashMap<String, HashSet<String>>,
+    updated_at: i64,
+}
+
 /// Socket.IO Manager
 ///
 /// Thread-safe manager for all Socket.IO sessions, rooms, and connections
@@ -59,6 +67,9 @@ pub struct SocketIOManager {
     /// Usage pool: model_id -> {sid -> timestamp}
     usage_pool: Arc<RwLock<HashMap<String, HashMap<String, i64>>>>,
 
+    /// Remote rooms: server_id -> rooms of that node
+    remote_rooms: Arc<RwLock<HashMap<String, RemoteRooms>>>,
+
     /// Configuration
     ping_interval: u64,
     ping_timeout: u64,
@@ -71,6 +82,7 @@ impl SocketIOManager {
             user_pool: Arc::new(RwLock::new(HashMap::new())),
             rooms: Arc::new(RwLock::new(HashMap::new())),
             usage_pool: Arc::new(RwLock::new(HashMap::new())),
+            remote_rooms: Arc::new(RwLock::new(HashMap::new())),
             ping_interval: 25_000, // 25 seconds
             ping_timeout: 20_000,  // 20 seconds
         }
@@ -217,6 +229,91 @@ impl SocketIOManager {
             .unwrap_or_default()
     }
 
+    /// Get all sessions in a room, including those on other nodes
+    pub async fn get_all_room_sessions(&self, room: &str) -> Vec<String> {
+        let mut sids = self.get_room_sessions(room).await;
+        let remote_rooms = self.remote_rooms.read().await;
+        for remote in remote_rooms.values() {
+            if let Some(remote_sids) = remote.rooms.get(room) {
+                sids.extend(remote_sids.iter().cloned());
+            }
+        }
+        sids
+    }
+
+    /// Local room membership, for other nodes
+    pub async fn get_rooms_snapshot(&self) -> HashMap<String, Vec<String>> {
+        let rooms = self.rooms.read().await;
+        rooms
+            .iter()
+            .map(|(room, sids)| (room.clone(), sids.iter().cloned().collect()))
+            .collect()
+    }
+
+    /// Replace the room membership of another node
+    pub async fn set_remote_rooms(&self, server_id: &str, rooms: HashMap<String, Vec<String>>) {
+        let mut remote_rooms = self.remote_rooms.write().await;
+        remote_rooms.insert(
+            server_id.to_string(),
+            RemoteRooms {
+                rooms: rooms
+                    .into_iter()
+                    .map(|(room, sids)| (room, sids.into_iter().collect()))
+                    .collect(),
+                updated_at: chrono::Utc::now().timestamp(),
+            },
+        );
+    }
+
+    /// Record a session on another node joining a room
+    pub async fn add_remote_room_member(&self, server_id: &str, room: &str, sid: &str) {
+        let mut remote_rooms = self.remote_rooms.write().await;
+        let remote = remote_rooms.entry(server_id.to_string()).or_default();
+        remote
+            .rooms
+            .entry(room.to_string())
+            .or_default()
+            .insert(sid.to_string());
+        remote.updated_at = chrono::Utc::now().timestamp();
+    }
+
+    /// Record a session on another node leaving a room
+    pub async fn remove_remote_room_member(&self, server_id: &str, room: &str, sid: &str) {
+        let mut remote_rooms = self.remote_rooms.write().await;
+        if let Some(remote) = remote_rooms.get_mut(server_id) {
+            if let Some(sids) = remote.rooms.get_mut(room) {
+                sids.remove(sid);
+                if sids.is_empty() {
+                    remote.rooms.remove(room);
+                }
+            }
+        }
+    }
+
+    /// Drop a closed session of another node from all rooms
+    pub async fn remove_remote_session(&self, server_id: &str, sid: &str) {
+        let mut remote_rooms = self.remote_rooms.write().await;
+        if let Some(remote) = remote_rooms.get_mut(server_id) {
+            for sids in remote.rooms.values_mut() {
+                sids.remove(sid);
+            }
+            remote.rooms.retain(|_, sids| !sids.is_empty());
+        }
+    }
+
+    /// Forget nodes that have not reported their rooms for `max_age_seconds`
+    pub async fn prune_remote_rooms(&self, max_age_seconds: i64) {
+        let now = chrono::Utc::now().timestamp();
+        let mut remote_rooms = self.remote_rooms.write().await;
+        remote_rooms.retain(|server_id, remote| {
+            let fresh = now - remote.updated_at <= max_age_seconds;
+            if !fresh {
+                tracing::info!("Forgetting rooms of unresponsive node {}", server_id);
+            }
+            fresh
+        });
+    }
+
     /// Get all sessions for a user
     pub async fn get_user_sessions(&self, user_id: &str) -> Vec<String> {
         let user_pool = self.user_pool.read().await;
@@ -335,5 +432,25 @@ mod tests {
         manager.leave_room(sid1, "room-a").await.unwrap();
         let room_sessions = manager.get_room_sessions("room-a").await;
         assert_eq!(room_sessions.len(), 1);
+
+        // Members on other nodes count towards the room, not local delivery
+        manager
+            .add_remote_room_member("server-b", "room-a", "sid-3")
+            .await;
+        assert_eq!(manager.get_room_sessions("room-a").await.len(), 1);
+        assert_eq!(manager.get_all_room_sessions("room-a").await.len(), 2);
+
+        manager.remove_remote_session("server-b", "sid-3").await;
+        assert_eq!(manager.get_all_room_sessions("room-a").await.len(), 1);
+
+        manager
+            .set_remote_rooms(
+                "server-b",
+                HashMap::from([("room-a".to_string(), vec!["sid-4".to_string()])]),
+            )
+            .await;
+        assert_eq!(manager.get_all_room_sessions("room-a").await.len(), 2);
+        manager.prune_remote_rooms(-1).await;
+        assert_eq!(manager.get_all_room_sessions("room-a").await.len(), 1);
     }
 }
@@ -8,14 +8,15 @@ use crate::socketio::presence::PresenceManager;
 use crate::socketio::rate_limit::RateLimiter;
 use crate::socketio::recovery::RecoveryManager;
 use std::fmt::Write;
+use std::sync::Arc;
 
 /// Prometheus metrics exporter
 pub struct PrometheusExporter {
     metrics: SocketIOMetrics,
     health_monitor: Option<HealthMonitor>,
-    presence_manager: Option<PresenceManager>,
-    rate_limiter: Option<RateLimiter>,
-    recovery_manager: Option<RecoveryManager>,
+    presence_manager: Option<Arc<PresenceManager>>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    recovery_manager: Option<Arc<RecoveryManager>>,
     circuit_breakers: Vec<(String, CircuitBreaker)>,
 }
 
@@ -36,17 +37,17 @@ impl PrometheusExporter {
         self
     }
 
-    pub fn with_presence_manager(mut self, manager: PresenceManager) -> Self {
+    pub fn with_presence_manager(mut self, manager: Arc<PresenceManager>) -> Self {
         self.presence_manager = Some(manager);
         self
     }
 
-    pub fn with_rate_limiter(mut self, limiter: RateLimiter) -> Self {
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
         self.rate_limiter = Some(limiter);
         self
     }
 
-    pub fn with_recovery_manager(mut self, manager: RecoveryManager) -> Self {
+    pub fn with_recovery_manager(mut self, manager: Arc<RecoveryManager>) -> Self {
         self.recovery_manager = Some(manager);
         self
     }
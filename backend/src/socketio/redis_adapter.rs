@@ -3,9 +3,12 @@
 /// Enables horizontal scaling by using Redis pub/sub to broadcast events
 /// across multiple server instances
 use futures_util::StreamExt;
+use redis::aio::ConnectionManager;
 use redis::AsyncCommands;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use tokio::sync::OnceCell;
 
 /// Redis message for inter-server communication
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,6 +41,30 @@ pub enum RedisMessageType {
         user_id: String,
         session_id: String,
     },
+    RoomJoined {
+        room: String,
+        session_id: String,
+    },
+    RoomLeft {
+        room: String,
+        session_id: String,
+    },
+    SessionClosed {
+        session_id: String,
+    },
+    /// Yjs update to record and fan out to the document's room
+    YdocUpdate {
+        document_id: String,
+        update: Vec<u8>,
+        data: JsonValue,
+        exclude_sid: Option<String>,
+    },
+    /// Ask every node to publish a room snapshot
+    SyncRequest,
+    /// All rooms of the sending node with their sessions
+    RoomSnapshot {
+        rooms: HashMap<String, Vec<String>>,
+    },
 }
 
 use std::time::Duration;
@@ -46,6 +73,8 @@ use tokio::time::sleep;
 /// Redis adapter for Socket.IO scaling
 pub struct RedisAdapter {
     redis_client: redis::Client,
+    /// Shared publishing connection, reconnected automatically
+    connection: OnceCell<ConnectionManager>,
     server_id: String,
     channel: String,
     reconnect_attempts: usize,
@@ -60,6 +89,7 @@ impl RedisAdapter {
 
         Ok(Self {
             redis_client,
+            connection: OnceCell::new(),
             server_id,
             channel: "socketio:events".to_string(),
             reconnect_attempts: 10,
@@ -78,6 +108,7 @@ impl RedisAdapter {
 
         Ok(Self {
             redis_client,
+            connection: OnceCell::new(),
             server_id,
             channel: "socketio:events".to_string(),
             reconnect_attempts,
@@ -95,8 +126,16 @@ impl RedisAdapter {
         let serialized = serde_json::to_string(&message)?;
 
         for attempt in 0..self.reconnect_attempts {
-            match self.redis_client.get_multiplexed_async_connection().await {
-                Ok(mut conn) => match conn.publish::<_, _, ()>(&self.channel, &serialized).await {
+            let connection = self
+                .connection
+                .get_or_try_init(|| ConnectionManager::new(self.redis_client.clone()))
+                .await;
+            match connection {
+                Ok(conn) => match conn
+                    .clone()
+                    .publish::<_, _, ()>(&self.channel, &serialized)
+                    .await
+                {
                     Ok(_) => {
                         tracing::debug!("Published message to Redis: {:?}", message.message_type);
                         return Ok(());
@@ -189,6 +228,19 @@ impl RedisAdapter {
         Ok(())
     }
 
+    /// Publish a message from this server
+    pub async fn publish_message(
+        &self,
+        message_type: RedisMessageType,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let message = RedisMessage {
+            server_id: self.server_id.clone(),
+            message_type,
+        };
+
+        self.publish(message).await
+    }
+
     /// Publish emit event
     pub async fn publish_emit(
         &self,
@@ -535,9 +535,25 @@ pub async fn emit_to_session(
     Ok(())
 }
 
-/// Broadcast event to multiple sessions
+/// Broadcast event to multiple sessions, wherever they are connected
+///
+/// Returns the number of sessions the event was handed to.
 #[allow(dead_code)]
-pub async fn broadcast_to_sessions(_sids: Vec<String>, _event: &str, _data: serde_json::Value) {
-    // This is now handled by EventHandler in events.rs
-    // Keeping this as a placeholder for backward compatibility
+pub async fn broadcast_to_sessions(
+    event_handler: &EventHandler,
+    sids: Vec<String>,
+    event: &str,
+    data: serde_json::Value,
+) -> usize {
+    let mut sent = 0;
+    for sid in sids {
+        if event_handler
+            .emit_to_session(&sid, event, data.clone())
+            .await
+            .is_ok()
+        {
+            sent += 1;
+        }
+    }
+    sent
 }
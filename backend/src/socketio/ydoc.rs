@@ -90,6 +90,16 @@ impl YDocManager {
         Ok(())
     }
 
+    /// Record an update received from another node
+    ///
+    /// The originating node has already persisted it to Redis, so it is only
+    /// kept in memory here.
+    pub async fn append_remote_update(&self, doc_id: &str, update: Vec<u8>) {
+        let sanitized_id = self.sanitize_doc_id(doc_id);
+        let mut updates = self.updates.write().await;
+        updates.entry(sanitized_id).or_default().push(update);
+    }
+
     /// Get all updates for a document
     pub async fn get_updates(&self, doc_id: &str) -> Result<Vec<Vec<u8>>, String> {
         let sanitized_id = self.sanitize_doc_id(doc_id);
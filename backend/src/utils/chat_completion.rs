@@ -14,6 +14,8 @@ use crate::{
         execute_code_block, format_execution_result, get_code_interpreter_timeout,
         get_sandbox_client, is_code_interpreter_enabled, CodeBlockDetector,
     },
+    services::{mcp::McpToolServer, openapi::OpenApiToolServer, providers::Provider},
+    utils::{functions::FunctionFilters, metrics::LlmCall},
     AppState,
 };
 
@@ -46,12 +48,20 @@ JSON format: { "title": "your concise title here" }
 {{MESSAGES:END:2}}
 </chat_history>"#;
 
+/// Body of an upstream streaming response in OpenAI SSE format
+pub type ChatByteStream =
+    std::pin::Pin<Box<dyn futures::Stream<Item = Result<Bytes, reqwest::Error>> + Send>>;
+
 /// Context for streaming chat completions
 pub struct StreamingContext {
     pub state: web::Data<AppState>,
     pub user_id: String,
     pub model_id: String,
+    /// Messages as sent by the client, used for title generation
     pub messages: Vec<Value>,
+    /// Messages as sent upstream, with memory, RAG and web search context;
+    /// tool rounds continue from these
+    pub request_messages: Vec<Value>,
     pub chat_id: Option<String>,
     pub message_id: Option<String>,
     pub session_id: Option<String>,
@@ -59,17 +69,38 @@ pub struct StreamingContext {
     pub model_item: Value,
     pub endpoint_url: String,
     pub endpoint_key: String,
+    pub endpoint_config: Value,
+    /// Connection label of the upstream for metrics
+    pub connection_label: String,
     pub tool_ids: Vec<String>,
     pub tool_specs: Vec<Value>,
+    /// Direct tool servers sent with the request
+    pub tool_servers: Vec<OpenApiToolServer>,
+    /// Filter Functions applied to every streamed response
+    pub function_filters: FunctionFilters,
     pub delta_chunk_size: Option<usize>,
 }
 
+/// Ask an OpenAI-compatible upstream to end a stream with a `usage` chunk,
+/// unless the request already says whether it wants one
+pub fn request_stream_usage(payload: &mut Value) {
+    if payload.get("stream").and_then(|s| s.as_bool()) != Some(true) {
+        return;
+    }
+    if let Some(obj) = payload.as_object_mut() {
+        let options = obj.entry("stream_options").or_insert_with(|| json!({}));
+        if let Some(options) = options.as_object_mut() {
+            options.entry("include_usage").or_insert(json!(true));
+        }
+    }
+}
+
 /// Create an HTTP SSE streaming response
 /// This is used when Socket.IO metadata is not present (API calls, integrations, etc.)
-pub fn create_sse_stream(response: reqwest::Response) -> Result<HttpResponse, AppError> {
+pub fn create_sse_stream(stream: ChatByteStream) -> Result<HttpResponse, AppError> {
     tracing::debug!("Creating HTTP SSE streaming response");
 
-    let stream = response.bytes_stream().map(move |result| match result {
+    let stream = stream.map(move |result| match result {
         Ok(bytes) => {
             // Forward immediately without ANY processing
             Ok::<Bytes, actix_web::Error>(bytes)
@@ -96,7 +127,7 @@ pub fn create_sse_stream(response: reqwest::Response) -> Result<HttpResponse, Ap
 /// Process streaming response and emit events via Socket.IO
 /// This mimics Python's middleware.py process_chat_response streaming logic
 pub async fn process_streaming_via_socketio(
-    response: reqwest::Response,
+    mut stream: ChatByteStream,
     context: StreamingContext,
 ) -> Result<(), Box<dyn std::error::Error>> {
     // Get socket state
@@ -118,7 +149,6 @@ pub async fn process_streaming_via_socketio(
     );
 
     // Stream the response with batching like Python backend
-    let mut stream = response.bytes_stream();
     let mut content = String::new();
 
     // Delta batching to prevent flooding frontend
@@ -357,6 +387,11 @@ pub async fn process_streaming_via_socketio(
                                                     delta_count = 0;
                                                 }
 
+                                                // Tool calls continue the response in the tool loop
+                                                if has_tool_calls {
+                                                    continue;
+                                                }
+
                                                 // Mark as done and send final data with finish_reason
                                                 data["done"] = json!(true);
                                                 let completion_event = json!({
@@ -471,7 +506,33 @@ fn accumulate_tool_calls(
     }
 }
 
-/// Execute tools and continue with multi-turn conversation
+/// Order streamed tool calls by their index
+fn sorted_tool_calls(collected_tool_calls: HashMap<usize, Value>) -> Vec<Value> {
+    let mut tool_calls: Vec<_> = collected_tool_calls.into_iter().collect();
+    tool_calls.sort_by_key(|(index, _)| *index);
+    tool_calls.into_iter().map(|(_, tc)| tc).collect()
+}
+
+/// Payload of a `chat:tool` event, also persisted with the assistant message
+fn tool_call_record(tool_call: &Value, round: usize, status: &str, result: Option<&str>) -> Value {
+    let mut record = json!({
+        "id": tool_call.get("id").cloned().unwrap_or(Value::Null),
+        "name": tool_call.pointer("/function/name").cloned().unwrap_or(Value::Null),
+        "arguments": tool_call.pointer("/function/arguments").cloned().unwrap_or(json!("{}")),
+        "round": round,
+        "status": status,
+    });
+    if let Some(result) = result {
+        record["result"] = json!(result);
+    }
+    record
+}
+
+/// Execute tools and continue the conversation until the model stops calling them
+///
+/// Every round runs its tool calls concurrently and sends the results back to
+/// the model. After `tool_calling_max_rounds` rounds the follow-up request is
+/// made without tools, so the model has to produce a final answer.
 async fn execute_tools_and_continue(
     collected_tool_calls: HashMap<usize, Value>,
     content: String,
@@ -481,90 +542,154 @@ async fn execute_tools_and_continue(
         + Clone,
     delta_chunk_size: usize,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    tracing::info!(
-        "🔧 Executing {} tool(s) after stream completion",
-        collected_tool_calls.len()
-    );
+    let max_rounds = context
+        .state
+        .config
+        .read()
+        .unwrap()
+        .tool_calling_max_rounds
+        .max(1);
+
+    let mut tool_calls = sorted_tool_calls(collected_tool_calls);
+    let mut messages = context.request_messages.clone();
+    let mut contents = vec![content];
+    let mut records: Vec<Value> = Vec::new();
+    let mut round = 0;
+
+    while !tool_calls.is_empty() {
+        round += 1;
+        tracing::info!(
+            "🔧 Tool round {}/{}: executing {} tool(s)",
+            round,
+            max_rounds,
+            tool_calls.len()
+        );
 
-    // Convert collected_tool_calls HashMap to Vec, sorted by index
-    let mut tool_calls_vec: Vec<_> = collected_tool_calls.into_iter().collect();
-    tool_calls_vec.sort_by_key(|(index, _)| *index);
-    let final_tool_calls: Vec<Value> = tool_calls_vec.into_iter().map(|(_, tc)| tc).collect();
+        for tool_call in &tool_calls {
+            event_emitter(json!({
+                "type": "chat:tool",
+                "data": tool_call_record(tool_call, round, "running", None),
+            }))
+            .await;
+        }
 
-    tracing::debug!("Final tool_calls to execute: {:?}", final_tool_calls);
+        // Tool calls of one round are independent of each other
+        let results = futures::future::join_all(tool_calls.iter().map(|tool_call| {
+            execute_single_tool(
+                tool_call,
+                &context.state,
+                &context.user_id,
+                &context.tool_ids,
+                &context.tool_servers,
+            )
+        }))
+        .await;
 
-    // Execute each tool and collect results
-    let mut tool_results: Vec<Value> = Vec::new();
+        messages.push(json!({
+            "role": "assistant",
+            "content": contents.last().cloned().unwrap_or_default(),
+            "tool_calls": tool_calls,
+        }));
+        for (tool_call, result) in tool_calls.iter().zip(results) {
+            let (status, output) = match result {
+                Ok(output) => ("done", output),
+                Err(error) => ("error", error),
+            };
+            let record = tool_call_record(tool_call, round, status, Some(&output));
+            event_emitter(json!({ "type": "chat:tool", "data": record.clone() })).await;
+            records.push(record);
+
+            messages.push(json!({
+                "role": "tool",
+                "tool_call_id": tool_call.get("id").cloned().unwrap_or(Value::Null),
+                "content": output,
+            }));
+        }
 
-    for tool_call in &final_tool_calls {
-        let result = execute_single_tool(
-            tool_call,
-            &context.state,
-            &context.user_id,
-            &context.tool_ids,
-        )
-        .await;
-        tool_results.push(result);
-    }
+        if let (Some(cid), Some(mid)) = (context.chat_id.as_ref(), context.message_id.as_ref()) {
+            let _ = upsert_chat_message(
+                &context.state.db,
+                cid,
+                mid,
+                json!({
+                    "role": "assistant",
+                    "content": join_contents(&contents),
+                    "tool_calls": records,
+                    "done": false,
+                    "model": context.model_id.clone(),
+                }),
+            )
+            .await;
+        }
 
-    // Emit tool results to frontend
-    tracing::info!(
-        "📤 Emitting {} tool result(s) to frontend",
-        tool_results.len()
-    );
-    for result in &tool_results {
-        let tool_result_event = json!({
-            "type": "chat:completion",
-            "data": {
-                "content": format!("\n\n**Tool Result:**\n{}", result.get("content").and_then(|c| c.as_str()).unwrap_or(""))
+        let allow_tools = round < max_rounds;
+        let llm_call = LlmCall::start(&context.model_id, context.connection_label.clone())
+            .on_usage(crate::services::quota::token_recorder(
+                context.state.db.clone(),
+                context.user_id.clone(),
+                context.model_id.clone(),
+            ));
+        let stream = match make_tool_response_request(
+            &context.state.http_client,
+            &context.endpoint_url,
+            &context.endpoint_key,
+            &context.endpoint_config,
+            &context.model_id,
+            &messages,
+            if allow_tools {
+                &context.tool_specs
+            } else {
+                &[]
+            },
+        )
+        .await
+        {
+            Ok(stream) => llm_call.instrument(stream),
+            Err(e) => {
+                llm_call.failed();
+                return Err(e);
             }
-        });
-        event_emitter(tool_result_event).await;
-    }
-
-    // Multi-turn: Make a new chat completion request with tool results
-    tracing::info!(
-        "🔄 Starting multi-turn: sending tool results back to LLM for natural language response"
-    );
+        };
+        let stream = context.function_filters.clone().filter_stream(stream);
 
-    // Build new messages array
-    let mut new_messages = context.messages.clone();
+        let (round_content, next_tool_calls) =
+            stream_tool_round(stream, &event_emitter, delta_chunk_size).await?;
+        contents.push(round_content);
 
-    // Add assistant message with tool_calls
-    new_messages.push(json!({
-        "role": "assistant",
-        "content": "",
-        "tool_calls": final_tool_calls
-    }));
+        tool_calls = next_tool_calls;
+        if !allow_tools && !tool_calls.is_empty() {
+            tracing::warn!(
+                "Model requested more tools after {} rounds, stopping the tool loop",
+                max_rounds
+            );
+            tool_calls.clear();
+        }
+    }
 
-    // Add tool result messages
-    for result in &tool_results {
-        new_messages.push(result.clone());
+    let final_content = join_contents(&contents);
+    event_emitter(json!({
+        "type": "chat:completion",
+        "data": { "content": final_content, "done": true },
+    }))
+    .await;
+
+    if let (Some(cid), Some(mid)) = (context.chat_id.as_ref(), context.message_id.as_ref()) {
+        let _ = upsert_chat_message(
+            &context.state.db,
+            cid,
+            mid,
+            json!({
+                "role": "assistant",
+                "content": final_content,
+                "tool_calls": records,
+                "done": true,
+                "model": context.model_id.clone(),
+            }),
+        )
+        .await;
     }
 
-    // Make second request to LLM
-    let second_response = make_tool_response_request(
-        &context.state.http_client,
-        &context.endpoint_url,
-        &context.endpoint_key,
-        &context.model_id,
-        &new_messages,
-        &context.tool_specs,
-    )
-    .await?;
-
-    // Stream the second response
-    stream_second_response(
-        second_response,
-        event_emitter,
-        delta_chunk_size,
-        &context.state,
-        &context.chat_id,
-        &context.message_id,
-        &context.model_id,
-        content,
-    )
-    .await?;
+    tracing::info!("✅ Tool loop completed after {} round(s)", round);
 
     // Generate title if requested
     if context.should_generate_title && context.chat_id.is_some() {
@@ -574,14 +699,24 @@ async fn execute_tools_and_continue(
     Ok(())
 }
 
-/// Execute a single tool
+/// Assistant text across tool rounds
+fn join_contents(contents: &[String]) -> String {
+    contents
+        .iter()
+        .map(|c| c.trim())
+        .filter(|c| !c.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Execute a single tool, returning its output or an error message for the model
 async fn execute_single_tool(
     tool_call: &Value,
     state: &web::Data<AppState>,
     user_id: &str,
     tool_ids: &[String],
-) -> Value {
-    let tool_call_id = tool_call.get("id").and_then(|v| v.as_str()).unwrap_or("");
+    tool_servers: &[OpenApiToolServer],
+) -> Result<String, String> {
     let tool_name = tool_call
         .get("function")
         .and_then(|f| f.get("name"))
@@ -591,6 +726,7 @@ async fn execute_single_tool(
         .get("function")
         .and_then(|f| f.get("arguments"))
         .and_then(|a| a.as_str())
+        .filter(|a| !a.trim().is_empty())
         .unwrap_or("{}");
 
     tracing::info!(
@@ -600,21 +736,94 @@ async fn execute_single_tool(
     );
 
     // Parse arguments
-    let tool_args: HashMap<String, Value> = match serde_json::from_str(tool_args_str) {
-        Ok(args) => args,
-        Err(e) => {
-            tracing::error!("Failed to parse tool arguments: {}", e);
-            return json!({
-                "role": "tool",
-                "tool_call_id": tool_call_id,
-                "content": format!("Error: Failed to parse arguments - {}", e)
-            });
+    let tool_args: HashMap<String, Value> = serde_json::from_str(tool_args_str).map_err(|e| {
+        tracing::error!("Failed to parse tool arguments: {}", e);
+        format!("Error: Failed to parse arguments - {}", e)
+    })?;
+
+    // Tools served by MCP tool servers
+    let connections = state.config.read().unwrap().tool_server_connections.clone();
+    for tool_id in tool_ids
+        .iter()
+        .filter(|id| id.starts_with(McpToolServer::ID_PREFIX))
+    {
+        let Some(server) = McpToolServer::find(&connections, tool_id) else {
+            continue;
+        };
+        let provides_tool = match server.list_tools().await {
+            Ok(tools) => tools.iter().any(|t| t.name == tool_name),
+            Err(e) => {
+                tracing::error!("Failed to list tools from MCP server {}: {}", tool_id, e);
+                false
+            }
+        };
+        if !provides_tool {
+            continue;
         }
-    };
 
-    // Find and execute the tool
-    let mut tool_result_content = format!("Error: Tool '{}' not found", tool_name);
+        return match server.call_tool(tool_name, json!(tool_args)).await {
+            Ok(result) if result.is_error => {
+                Err(format!("Error executing tool: {}", result.output()))
+            }
+            Ok(result) => {
+                let output = serde_json::to_string(&result.output())
+                    .map_err(|_| "Error serializing result".to_string())?;
+                tracing::info!("✅ MCP tool executed successfully: {}", output);
+                Ok(output)
+            }
+            Err(e) => {
+                tracing::error!("❌ MCP tool execution error: {}", e);
+                Err(format!("Error executing tool: {}", e))
+            }
+        };
+    }
+
+    // Tools served by OpenAPI tool servers, configured or sent with the request
+    let openapi_servers = tool_ids
+        .iter()
+        .filter(|id| !id.starts_with(McpToolServer::ID_PREFIX))
+        .filter_map(|id| OpenApiToolServer::find(&connections, id))
+        .chain(tool_servers.iter().cloned());
+    for server in openapi_servers {
+        let tools = match server.tools(&state.http_client).await {
+            Ok(tools) => tools,
+            Err(e) => {
+                tracing::error!("Failed to load OpenAPI spec for {}: {}", server.url, e);
+                continue;
+            }
+        };
+        let Some(tool) = tools.iter().find(|t| t.name == tool_name) else {
+            continue;
+        };
 
+        let session_token = {
+            let config = state.config.read().unwrap();
+            crate::utils::auth::create_jwt(user_id, &config.webui_secret_key, "5m").ok()
+        };
+        let arguments: serde_json::Map<String, Value> = tool_args.into_iter().collect();
+        return match server
+            .call(
+                &state.http_client,
+                tool,
+                &arguments,
+                session_token.as_deref(),
+            )
+            .await
+        {
+            Ok(result) => {
+                let output = serde_json::to_string(&result)
+                    .map_err(|_| "Error serializing result".to_string())?;
+                tracing::info!("✅ OpenAPI tool executed successfully: {}", output);
+                Ok(output)
+            }
+            Err(e) => {
+                tracing::error!("❌ OpenAPI tool execution error: {}", e);
+                Err(format!("Error executing tool: {}", e))
+            }
+        };
+    }
+
+    // Find and execute the tool
     for tool_id in tool_ids {
         let tool_service = crate::services::tool::ToolService::new(&state.db);
         if let Ok(Some(tool)) = tool_service.get_tool_by_id(tool_id).await {
@@ -658,212 +867,158 @@ async fn execute_single_tool(
                     let exec_request = crate::models::tool_runtime::ToolExecutionRequest {
                         tool_id: tool_id.clone(),
                         tool_name: tool_name.to_string(),
-                        parameters: tool_args.clone(),
+                        parameters: tool_args,
                         context: execution_context,
                     };
 
-                    match runtime_service.execute_tool(&state.db, exec_request).await {
+                    return match runtime_service.execute_tool(&state.db, exec_request).await {
                         Ok(exec_response) => {
-                            tool_result_content = serde_json::to_string(&exec_response.result)
-                                .unwrap_or_else(|_| "Error serializing result".to_string());
-                            tracing::info!(
-                                "✅ Tool executed successfully: {}",
-                                tool_result_content
-                            );
+                            let output = serde_json::to_string(&exec_response.result)
+                                .map_err(|_| "Error serializing result".to_string())?;
+                            tracing::info!("✅ Tool executed successfully: {}", output);
+                            Ok(output)
                         }
                         Err(e) => {
-                            tool_result_content = format!("Error executing tool: {}", e);
                             tracing::error!("❌ Tool execution error: {}", e);
+                            Err(format!("Error executing tool: {}", e))
                         }
-                    }
-                    break;
+                    };
                 }
             }
         }
     }
 
-    json!({
-        "role": "tool",
-        "tool_call_id": tool_call_id,
-        "content": tool_result_content
-    })
+    Err(format!("Error: Tool '{}' not found", tool_name))
 }
 
-/// Make a second request to LLM with tool results
+/// Request the next response from the LLM with the tool results so far
+///
+/// Passing no `tool_specs` forces a final answer.
 async fn make_tool_response_request(
     client: &reqwest::Client,
     endpoint_url: &str,
     endpoint_key: &str,
+    endpoint_config: &Value,
     model_id: &str,
     messages: &[Value],
     tool_specs: &[Value],
-) -> Result<reqwest::Response, Box<dyn std::error::Error>> {
-    let mut request_builder = client
-        .post(format!("{}/chat/completions", endpoint_url))
-        .header("Content-Type", "application/json");
-
-    if !endpoint_key.is_empty() {
-        request_builder =
-            request_builder.header("Authorization", format!("Bearer {}", endpoint_key));
-    }
-
-    let payload = json!({
+) -> Result<ChatByteStream, Box<dyn std::error::Error>> {
+    let mut payload = json!({
         "model": model_id,
         "messages": messages,
         "stream": true,
-        "tools": tool_specs.iter().map(|spec| json!({
-            "type": "function",
-            "function": spec
-        })).collect::<Vec<_>>(),
-        "tool_choice": "auto"
     });
+    if !tool_specs.is_empty() {
+        payload["tools"] = tool_specs
+            .iter()
+            .map(|spec| json!({ "type": "function", "function": spec }))
+            .collect();
+        payload["tool_choice"] = json!("auto");
+    }
 
-    tracing::info!("🔄 Sending second request to LLM with tool results");
+    tracing::info!("🔄 Sending tool results back to LLM");
 
-    let response = request_builder.json(&payload).send().await?;
+    let provider = Provider::from_api_config(endpoint_config);
+    if provider == Provider::OpenAi {
+        request_stream_usage(&mut payload);
+    }
+    let response = provider
+        .chat_request(
+            client,
+            endpoint_url,
+            endpoint_key,
+            endpoint_config,
+            &payload,
+        )
+        .send()
+        .await?;
 
     if !response.status().is_success() {
-        return Err(format!("Second request failed with status: {}", response.status()).into());
+        return Err(format!(
+            "Tool follow-up request failed with status: {}",
+            response.status()
+        )
+        .into());
     }
 
-    Ok(response)
+    Ok(provider.openai_stream(response))
 }
 
-/// Stream the second response from tool execution
-async fn stream_second_response(
-    response: reqwest::Response,
-    event_emitter: impl Fn(Value) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send>>
-        + Send,
+/// Stream one follow-up response, returning its content and any new tool calls
+async fn stream_tool_round(
+    mut stream: ChatByteStream,
+    event_emitter: &(impl Fn(Value) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send>>
+          + Send),
     delta_chunk_size: usize,
-    state: &web::Data<AppState>,
-    chat_id: &Option<String>,
-    message_id: &Option<String>,
-    model_id: &str,
-    previous_content: String,
-) -> Result<(), Box<dyn std::error::Error>> {
-    tracing::info!("✅ Second request successful, streaming response...");
-
-    let mut second_stream = response.bytes_stream();
-    let mut second_content = String::new();
-    let mut second_delta_count = 0;
-    let mut second_last_delta: Option<Value> = None;
-
-    while let Some(chunk_result) = second_stream.next().await {
-        match chunk_result {
-            Ok(chunk) => {
-                if let Ok(text) = std::str::from_utf8(&chunk) {
-                    for line in text.lines() {
-                        let line = line.trim();
-                        if line.is_empty() {
-                            continue;
-                        }
-
-                        if line.starts_with("data: ") {
-                            let data_str = &line[6..];
-                            if data_str == "[DONE]" {
-                                // Flush pending delta
-                                if let Some(pending) = second_last_delta.take() {
-                                    let event = json!({
-                                        "type": "chat:completion",
-                                        "data": pending
-                                    });
-                                    event_emitter(event).await;
-                                }
-                                break;
-                            }
-
-                            if let Ok(mut data) = serde_json::from_str::<Value>(data_str) {
-                                if let Some(choices) =
-                                    data.get("choices").and_then(|c| c.as_array())
-                                {
-                                    if let Some(first_choice) = choices.first() {
-                                        if let Some(delta) = first_choice.get("delta") {
-                                            if let Some(delta_content) =
-                                                delta.get("content").and_then(|c| c.as_str())
-                                            {
-                                                second_content.push_str(delta_content);
-                                                second_delta_count += 1;
-                                                second_last_delta = Some(data.clone());
-
-                                                if second_delta_count >= delta_chunk_size {
-                                                    let event = json!({
-                                                        "type": "chat:completion",
-                                                        "data": data
-                                                    });
-                                                    event_emitter(event).await;
-                                                    second_delta_count = 0;
-                                                    second_last_delta = None;
-                                                }
-                                            }
-                                        }
-
-                                        // Check for finish_reason
-                                        if let Some(finish_reason) =
-                                            first_choice.get("finish_reason")
-                                        {
-                                            if !finish_reason.is_null() {
-                                                tracing::info!(
-                                                    "✅ Second stream finished with reason: {:?}",
-                                                    finish_reason
-                                                );
-
-                                                // Flush pending delta
-                                                if let Some(pending) = second_last_delta.take() {
-                                                    let event = json!({
-                                                        "type": "chat:completion",
-                                                        "data": pending
-                                                    });
-                                                    event_emitter(event).await;
-                                                }
+) -> Result<(String, Vec<Value>), Box<dyn std::error::Error>> {
+    let mut content = String::new();
+    let mut collected_tool_calls: HashMap<usize, Value> = HashMap::new();
+    let mut delta_count = 0;
+    let mut last_delta: Option<Value> = None;
 
-                                                // Send final message with done flag
-                                                data["done"] = json!(true);
-                                                let event = json!({
-                                                    "type": "chat:completion",
-                                                    "data": data
-                                                });
-                                                event_emitter(event).await;
+    'stream: while let Some(chunk_result) = stream.next().await {
+        let chunk = match chunk_result {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                tracing::error!("❌ Error in tool follow-up stream: {}", e);
+                break;
+            }
+        };
+        let Ok(text) = std::str::from_utf8(&chunk) else {
+            continue;
+        };
 
-                                                // Update database
-                                                if let (Some(cid), Some(mid)) =
-                                                    (chat_id.as_ref(), message_id.as_ref())
-                                                {
-                                                    let final_content = format!(
-                                                        "{}\n\n{}",
-                                                        previous_content, second_content
-                                                    );
-                                                    let _ = upsert_chat_message(
-                                                        &state.db,
-                                                        cid,
-                                                        mid,
-                                                        json!({
-                                                            "role": "assistant",
-                                                            "content": final_content,
-                                                            "done": true,
-                                                            "model": model_id,
-                                                        }),
-                                                    )
-                                                    .await;
-                                                }
-                                                break;
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
+        for line in text.lines() {
+            let Some(data_str) = line.trim().strip_prefix("data: ") else {
+                continue;
+            };
+            if data_str == "[DONE]" {
+                break 'stream;
+            }
+            let Ok(data) = serde_json::from_str::<Value>(data_str) else {
+                continue;
+            };
+            let Some(first_choice) = data.pointer("/choices/0") else {
+                continue;
+            };
+
+            if let Some(delta_content) = first_choice
+                .pointer("/delta/content")
+                .and_then(|c| c.as_str())
+            {
+                content.push_str(delta_content);
+                delta_count += 1;
+                last_delta = Some(data.clone());
+
+                if delta_count >= delta_chunk_size {
+                    event_emitter(json!({ "type": "chat:completion", "data": data })).await;
+                    delta_count = 0;
+                    last_delta = None;
                 }
             }
-            Err(e) => {
-                tracing::error!("❌ Error in second stream: {}", e);
-                break;
+
+            if let Some(tool_calls) = first_choice
+                .pointer("/delta/tool_calls")
+                .and_then(|t| t.as_array())
+            {
+                accumulate_tool_calls(tool_calls, &mut collected_tool_calls);
+            }
+
+            if first_choice
+                .get("finish_reason")
+                .is_some_and(|reason| !reason.is_null())
+            {
+                break 'stream;
             }
         }
     }
 
-    tracing::info!("✅ Multi-turn conversation completed successfully");
-    Ok(())
+    // Flush pending delta
+    if let Some(pending) = last_delta {
+        event_emitter(json!({ "type": "chat:completion", "data": pending })).await;
+    }
+
+    Ok((content, sorted_tool_calls(collected_tool_calls)))
 }
 
 /// Upsert a message to a chat
@@ -949,11 +1104,20 @@ async fn spawn_title_generation(context: StreamingContext) {
         context.chat_id.as_ref().unwrap()
     );
 
-    tokio::spawn(async move {
+    let task_manager = context.state.task_manager.clone();
+    let chat_id = context.chat_id.clone();
+    let user_id = context.user_id.clone();
+    let job = async move {
         if let Err(e) = generate_and_update_title(context).await {
             tracing::error!("Failed to generate title: {}", e);
         }
-    });
+    };
+    if let Err(e) = task_manager
+        .create_user_task(job, chat_id, Some(user_id))
+        .await
+    {
+        tracing::error!("Failed to start title generation: {}", e);
+    }
 }
 
 /// Generate title and update chat
@@ -1030,34 +1194,32 @@ async fn generate_and_update_title(
         "stream": false
     });
 
-    let url = format!(
-        "{}/chat/completions",
-        context.endpoint_url.trim_end_matches('/')
+    tracing::info!(
+        "🏷️  Sending title generation request to: {}",
+        context.endpoint_url
     );
-
-    tracing::info!("🏷️  Sending title generation request to: {}", url);
     tracing::debug!("🏷️  Title payload: {:?}", title_payload);
 
-    // Use shared HTTP client for title generation
-    let mut request_builder = context
-        .state
-        .http_client
-        .post(&url)
-        .timeout(std::time::Duration::from_secs(30)) // 30 sec timeout for title gen
-        .header("Content-Type", "application/json");
-
-    if !context.endpoint_key.is_empty() {
-        request_builder =
-            request_builder.header("Authorization", format!("Bearer {}", context.endpoint_key));
-        tracing::debug!("🏷️  API key is present");
-    } else {
+    if context.endpoint_key.is_empty() {
         tracing::warn!("🏷️  NO API KEY provided for title generation!");
     }
 
-    match request_builder.json(&title_payload).send().await {
+    // Use shared HTTP client for title generation
+    let provider = Provider::from_api_config(&context.endpoint_config);
+    let request_builder = provider
+        .chat_request(
+            &context.state.http_client,
+            &context.endpoint_url,
+            &context.endpoint_key,
+            &context.endpoint_config,
+            &title_payload,
+        )
+        .timeout(std::time::Duration::from_secs(30)); // 30 sec timeout for title gen
+
+    match request_builder.send().await {
         Ok(response) if response.status().is_success() => {
             tracing::info!("🏷️  Title generation response received successfully");
-            let json_response = response.json::<Value>().await?;
+            let json_response = provider.openai_response(response.json::<Value>().await?);
             tracing::debug!("🏷️  Response JSON: {:?}", json_response);
 
             // Extract title from response
@@ -1189,3 +1351,93 @@ async fn generate_and_update_title(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn sse(chunks: &[Value]) -> ChatByteStream {
+        let mut items: Vec<Result<Bytes, reqwest::Error>> = chunks
+            .iter()
+            .map(|chunk| Ok(Bytes::from(format!("data: {}\n\n", chunk))))
+            .collect();
+        items.push(Ok(Bytes::from_static(b"data: [DONE]\n\n")));
+        Box::pin(futures::stream::iter(items))
+    }
+
+    #[actix_web::test]
+    async fn test_stream_tool_round_collects_tool_calls() {
+        let stream = sse(&[
+            json!({ "choices": [{ "delta": { "content": "Checking " } }] }),
+            json!({ "choices": [{ "delta": { "content": "both." } }] }),
+            json!({ "choices": [{ "delta": { "tool_calls": [
+                { "index": 1, "id": "call_b", "function": { "name": "time", "arguments": "" } },
+            ] } }] }),
+            json!({ "choices": [{ "delta": { "tool_calls": [
+                { "index": 0, "id": "call_a", "function": { "name": "weather", "arguments": "{\"city\":" } },
+            ] } }] }),
+            json!({ "choices": [{ "delta": { "tool_calls": [
+                { "index": 0, "function": { "arguments": "\"Oslo\"}" } },
+            ] } }] }),
+            json!({ "choices": [{ "delta": {}, "finish_reason": "tool_calls" }] }),
+        ]);
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        let emitter = move |event: Value| {
+            let recorded = recorded.clone();
+            Box::pin(async move { recorded.lock().unwrap().push(event) })
+                as std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send>>
+        };
+
+        let (content, tool_calls) = stream_tool_round(stream, &emitter, 10).await.unwrap();
+        assert_eq!(content, "Checking both.");
+        assert_eq!(tool_calls.len(), 2);
+        assert_eq!(tool_calls[0]["id"], "call_a");
+        assert_eq!(
+            tool_calls[0]["function"]["arguments"],
+            "{\"city\":\"Oslo\"}"
+        );
+        assert_eq!(tool_calls[1]["function"]["name"], "time");
+        // Batched deltas are flushed once the stream ends
+        assert_eq!(events.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_tool_call_record_and_contents() {
+        let tool_call = json!({
+            "id": "call_a",
+            "function": { "name": "weather", "arguments": "{\"city\":\"Oslo\"}" },
+        });
+        let record = tool_call_record(&tool_call, 2, "done", Some("rainy"));
+        assert_eq!(record["name"], "weather");
+        assert_eq!(record["round"], 2);
+        assert_eq!(record["result"], "rainy");
+        assert!(tool_call_record(&tool_call, 1, "running", None)
+            .get("result")
+            .is_none());
+
+        let contents = [
+            "Let me check.".to_string(),
+            String::new(),
+            " It rains. ".to_string(),
+        ];
+        assert_eq!(join_contents(&contents), "Let me check.\n\nIt rains.");
+    }
+
+    #[test]
+    fn test_request_stream_usage() {
+        let mut payload = json!({ "model": "gpt", "stream": true });
+        request_stream_usage(&mut payload);
+        assert_eq!(payload["stream_options"], json!({ "include_usage": true }));
+
+        let mut payload = json!({ "stream": true, "stream_options": { "include_usage": false } });
+        request_stream_usage(&mut payload);
+        assert_eq!(payload["stream_options"]["include_usage"], json!(false));
+
+        let mut payload = json!({ "stream": false });
+        request_stream_usage(&mut payload);
+        assert!(payload.get("stream_options").is_none());
+    }
+}
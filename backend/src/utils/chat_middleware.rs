@@ -545,6 +545,109 @@ async fn process_features(
     Ok(form_data)
 }
 
+/// Generate an image from the last user message
+///
+/// The model is told whether generation succeeded via the system message, and
+/// the stored images are returned as a `chat:message:files` event. Failures
+/// are reported to the model rather than failing the whole request.
+pub async fn process_image_generation(
+    db: &Database,
+    mut form_data: Value,
+    user: &crate::models::user::User,
+    config: &crate::config::Config,
+) -> (Value, Option<Value>) {
+    let prompt = form_data
+        .get("messages")
+        .and_then(|m| m.as_array())
+        .and_then(|messages| get_last_user_message_text(messages))
+        .unwrap_or_default();
+    if prompt.trim().is_empty() {
+        return (form_data, None);
+    }
+
+    let request = crate::services::image::ImageGenerationRequest {
+        prompt,
+        ..Default::default()
+    };
+    let (context, event) =
+        match crate::services::image::generate_images(db, config, &user.id, request).await {
+            Ok(images) => {
+                let files: Vec<Value> = images
+                    .iter()
+                    .map(|image| json!({ "type": "image", "url": image.url }))
+                    .collect();
+                (
+                    "The requested image has been generated and is now being shown to the user. \
+                     Let them know that it has been generated."
+                        .to_string(),
+                    Some(json!({
+                        "type": "chat:message:files",
+                        "data": { "files": files },
+                    })),
+                )
+            }
+            Err(e) => {
+                tracing::warn!("Image generation failed: {}", e);
+                (
+                    format!(
+                        "Unable to generate an image, tell the user that an error occurred: {}",
+                        e
+                    ),
+                    None,
+                )
+            }
+        };
+
+    if let Some(messages) = form_data.get_mut("messages").and_then(|m| m.as_array_mut()) {
+        append_system_context(messages, &format!("<context>{}</context>", context));
+    }
+    (form_data, event)
+}
+
+/// Text of the last user message, joining the text parts of multimodal content
+fn get_last_user_message_text(messages: &[Value]) -> Option<String> {
+    let message = messages
+        .iter()
+        .rev()
+        .find(|m| m.get("role").and_then(|r| r.as_str()) == Some("user"))?;
+
+    match message.get("content")? {
+        Value::String(text) => Some(text.clone()),
+        Value::Array(parts) => Some(
+            parts
+                .iter()
+                .filter(|p| p.get("type").and_then(|t| t.as_str()) == Some("text"))
+                .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ),
+        _ => None,
+    }
+}
+
+/// Append to the system message, inserting one if the conversation has none
+pub(crate) fn append_system_context(messages: &mut Vec<Value>, context: &str) {
+    let existing = messages
+        .iter_mut()
+        .find(|m| m.get("role").and_then(|r| r.as_str()) == Some("system"));
+
+    match existing {
+        Some(message) => {
+            let content = message
+                .get("content")
+                .and_then(|c| c.as_str())
+                .unwrap_or_default();
+            let content = if content.is_empty() {
+                context.to_string()
+            } else {
+                format!("{}\n{}", content, context)
+            };
+            message["content"] = json!(content);
+        }
+        None => messages.insert(0, json!({ "role": "system", "content": context })),
+    }
+}
+
 /// Setup tools for function calling
 fn setup_tools(
     form_data: Value,
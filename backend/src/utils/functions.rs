@@ -0,0 +1,415 @@
+//! Running filter, action and pipe Functions
+//!
+//! Only WebAssembly Functions (see [`crate::plugins`]) can run; Functions
+//! whose content is Python source are skipped. Hooks receive the request
+//! body and a context holding the user, the Function's valves and the chat
+//! metadata.
+
+use bytes::Bytes;
+use futures::StreamExt;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::{
+    error::{AppError, AppResult},
+    models::{function::Function, user::User},
+    plugins::{Hook, PluginError, PluginPackage, PluginRuntime},
+    services::function::FunctionService,
+    utils::{chat_completion::ChatByteStream, valves},
+    AppState,
+};
+
+/// Valves and UserValves specs from a WebAssembly Function's manifest
+pub fn valves_specs(function: &Function) -> (Option<Value>, Option<Value>) {
+    if !PluginPackage::is_package(&function.content) {
+        return (None, None);
+    }
+    match PluginPackage::parse_manifest(&function.content) {
+        Ok(manifest) => (
+            Some(manifest.valves).filter(|spec| !spec.is_null()),
+            Some(manifest.user_valves).filter(|spec| !spec.is_null()),
+        ),
+        Err(_) => (None, None),
+    }
+}
+
+fn function_context(function: &Function, user: &User, metadata: &Value) -> Value {
+    let (spec, user_spec) = valves_specs(function);
+    let user_valves = valves::user_valves(user.settings.as_ref(), "functions", &function.id);
+    json!({
+        "user": {
+            "id": user.id,
+            "email": user.email,
+            "name": user.name,
+            "role": user.role,
+            "valves": valves::with_defaults(Some(&user_valves), user_spec.as_ref()),
+        },
+        "valves": valves::with_defaults(function.valves.as_ref(), spec.as_ref()),
+        "metadata": metadata,
+    })
+}
+
+fn plugin_error(function_id: &str, error: PluginError) -> AppError {
+    match error {
+        // The Function rejected the request on purpose
+        PluginError::Hook(message) => AppError::BadRequest(message),
+        e => AppError::InternalServerError(format!("Function {}: {}", function_id, e)),
+    }
+}
+
+fn runtime(state: &AppState) -> AppResult<&Arc<PluginRuntime>> {
+    state.plugin_runtime.as_ref().ok_or_else(|| {
+        AppError::NotImplemented("The WebAssembly function runtime is not available".to_string())
+    })
+}
+
+/// Filter ids requested for a chat: `filter_ids` plus the model's `filterIds`
+pub fn request_filter_ids(payload: &Value, model_item: &Value) -> Vec<String> {
+    let mut ids: Vec<String> = [
+        payload.get("filter_ids"),
+        model_item.pointer("/info/meta/filterIds"),
+    ]
+    .into_iter()
+    .flatten()
+    .filter_map(|ids| ids.as_array())
+    .flatten()
+    .filter_map(|id| id.as_str().map(String::from))
+    .collect();
+    ids.sort();
+    ids.dedup();
+    ids
+}
+
+/// The filter Functions applied to one chat request
+#[derive(Clone)]
+pub struct FunctionFilters {
+    runtime: Option<Arc<PluginRuntime>>,
+    filters: Arc<Vec<(Function, Value)>>,
+}
+
+impl FunctionFilters {
+    /// Load the global filters and those in `filter_ids`, ordered by their
+    /// `priority` valve
+    pub async fn load(
+        state: &AppState,
+        filter_ids: &[String],
+        user: &User,
+        metadata: &Value,
+    ) -> AppResult<Self> {
+        let Some(runtime) = state.plugin_runtime.clone() else {
+            return Ok(Self {
+                runtime: None,
+                filters: Arc::new(Vec::new()),
+            });
+        };
+
+        let mut filters: Vec<(Function, Value)> = FunctionService::new(&state.db)
+            .get_active_functions_by_type("filter")
+            .await?
+            .into_iter()
+            .filter(|f| f.is_global || filter_ids.contains(&f.id))
+            .filter(|f| {
+                let runnable = PluginPackage::is_package(&f.content);
+                if !runnable {
+                    tracing::debug!("Skipping filter {}: not a WebAssembly function", f.id);
+                }
+                runnable
+            })
+            .map(|f| {
+                let context = function_context(&f, user, metadata);
+                (f, context)
+            })
+            .collect();
+        filters.sort_by_key(|(_, context)| {
+            context
+                .pointer("/valves/priority")
+                .and_then(|p| p.as_i64())
+                .unwrap_or(0)
+        });
+
+        Ok(Self {
+            runtime: Some(runtime),
+            filters: Arc::new(filters),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+
+    /// Pass `body` through the `inlet` or `outlet` hook of every filter
+    pub async fn apply(&self, hook: Hook, mut body: Value) -> AppResult<Value> {
+        let Some(runtime) = &self.runtime else {
+            return Ok(body);
+        };
+        for (function, context) in self.filters.iter() {
+            if let Some(result) = runtime
+                .call(function, hook, &body, context)
+                .await
+                .map_err(|e| plugin_error(&function.id, e))?
+            {
+                tracing::debug!("Applied {} filter {}", hook.export_name(), function.id);
+                body = result;
+            }
+        }
+        Ok(body)
+    }
+
+    /// Rewrite one SSE event with the `stream` hooks; failures keep the chunk
+    async fn filter_event(&self, event: &str) -> String {
+        let mut output = String::new();
+        for line in event.lines() {
+            let chunk = line
+                .strip_prefix("data:")
+                .map(str::trim_start)
+                .filter(|data| *data != "[DONE]")
+                .and_then(|data| serde_json::from_str::<Value>(data).ok());
+            let (Some(mut chunk), Some(runtime)) = (chunk, &self.runtime) else {
+                output.push_str(line);
+                output.push('\n');
+                continue;
+            };
+
+            for (function, context) in self.filters.iter() {
+                match runtime.call(function, Hook::Stream, &chunk, context).await {
+                    Ok(Some(result)) => chunk = result,
+                    Ok(None) => {}
+                    Err(e) => tracing::warn!("Stream filter {} failed: {}", function.id, e),
+                }
+            }
+            output.push_str(&format!("data: {}\n", chunk));
+        }
+        output.push('\n');
+        output
+    }
+
+    /// Run the `stream` hooks over every chunk of an OpenAI-style SSE stream
+    pub fn filter_stream(self, stream: ChatByteStream) -> ChatByteStream {
+        if self.is_empty() {
+            return stream;
+        }
+
+        Box::pin(futures::stream::unfold(
+            (stream, Vec::new(), self, false),
+            |(mut stream, mut buffer, filters, mut done)| async move {
+                loop {
+                    if let Some(end) = event_end(&buffer) {
+                        let event: Vec<u8> = buffer.drain(..end).collect();
+                        let event = filters.filter_event(&String::from_utf8_lossy(&event)).await;
+                        return Some((Ok(Bytes::from(event)), (stream, buffer, filters, done)));
+                    }
+                    if done {
+                        if buffer.is_empty() {
+                            return None;
+                        }
+                        let rest = Bytes::from(std::mem::take(&mut buffer));
+                        return Some((Ok(rest), (stream, buffer, filters, done)));
+                    }
+                    match stream.next().await {
+                        Some(Ok(bytes)) => buffer.extend_from_slice(&bytes),
+                        Some(Err(e)) => return Some((Err(e), (stream, buffer, filters, done))),
+                        None => done = true,
+                    }
+                }
+            },
+        ))
+    }
+}
+
+/// Length of the first complete SSE event in `buffer`, blank line included
+fn event_end(buffer: &[u8]) -> Option<usize> {
+    buffer
+        .windows(2)
+        .position(|w| w == b"\n\n")
+        .map(|idx| idx + 2)
+}
+
+/// The active WebAssembly pipe Function serving `model_id`, if any
+pub async fn get_pipe(state: &AppState, model_id: &str) -> AppResult<Option<Function>> {
+    if state.plugin_runtime.is_none() {
+        return Ok(None);
+    }
+    let function = FunctionService::new(&state.db)
+        .get_function_by_id(model_id)
+        .await?;
+    Ok(function
+        .filter(|f| f.is_active && f.type_ == "pipe" && PluginPackage::is_package(&f.content)))
+}
+
+/// Answer a chat request with a pipe Function, in the shape an OpenAI
+/// connection would reply
+pub async fn run_pipe(
+    state: &AppState,
+    function: &Function,
+    body: &Value,
+    user: &User,
+    metadata: &Value,
+) -> AppResult<reqwest::Response> {
+    let output = runtime(state)?
+        .call(
+            function,
+            Hook::Pipe,
+            body,
+            &function_context(function, user, metadata),
+        )
+        .await
+        .map_err(|e| plugin_error(&function.id, e))?
+        .ok_or_else(|| {
+            AppError::BadRequest(format!("Function {} has no pipe hook", function.id))
+        })?;
+
+    let stream = body
+        .get("stream")
+        .and_then(|s| s.as_bool())
+        .unwrap_or(false);
+    let (content_type, body) = pipe_response(&function.id, output, stream);
+    http::Response::builder()
+        .header(http::header::CONTENT_TYPE, content_type)
+        .body(body)
+        .map(reqwest::Response::from)
+        .map_err(|e| AppError::Internal(e.to_string()))
+}
+
+/// Wrap a pipe's output in a chat completion, or a one-chunk SSE stream
+///
+/// Pipes return either the reply text or a complete chat completion.
+fn pipe_response(model: &str, output: Value, stream: bool) -> (&'static str, String) {
+    let content = match &output {
+        Value::String(text) => text.clone(),
+        _ if output.get("choices").is_some() => {
+            if !stream {
+                return ("application/json", output.to_string());
+            }
+            output
+                .pointer("/choices/0/message/content")
+                .and_then(|c| c.as_str())
+                .unwrap_or_default()
+                .to_string()
+        }
+        other => other.to_string(),
+    };
+
+    let id = format!("{}-{}", model, uuid::Uuid::new_v4());
+    let created = chrono::Utc::now().timestamp();
+    if stream {
+        let chunk = json!({
+            "id": id,
+            "object": "chat.completion.chunk",
+            "created": created,
+            "model": model,
+            "choices": [{
+                "index": 0,
+                "delta": { "role": "assistant", "content": content },
+                "finish_reason": "stop",
+            }],
+        });
+        (
+            "text/event-stream",
+            format!("data: {}\n\ndata: [DONE]\n\n", chunk),
+        )
+    } else {
+        let completion = json!({
+            "id": id,
+            "object": "chat.completion",
+            "created": created,
+            "model": model,
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": content },
+                "finish_reason": "stop",
+            }],
+        });
+        ("application/json", completion.to_string())
+    }
+}
+
+/// Run the action Function behind `action_id`
+///
+/// `action_id` is the Function id, optionally followed by `.` and the id of
+/// one of its sub-actions, which is passed on in the metadata.
+pub async fn run_action(
+    state: &AppState,
+    action_id: &str,
+    body: &Value,
+    user: &User,
+) -> AppResult<Value> {
+    let (function_id, sub_action) = match action_id.split_once('.') {
+        Some((function_id, sub_action)) => (function_id, Some(sub_action)),
+        None => (action_id, None),
+    };
+    let function = FunctionService::new(&state.db)
+        .get_function_by_id(function_id)
+        .await?
+        .filter(|f| f.is_active && f.type_ == "action")
+        .ok_or_else(|| AppError::NotFound(format!("Action {} not found", action_id)))?;
+    if !PluginPackage::is_package(&function.content) {
+        return Err(AppError::NotImplemented(format!(
+            "Action {} is not a WebAssembly function",
+            action_id
+        )));
+    }
+
+    let metadata = json!({
+        "chat_id": body.get("chat_id"),
+        "message_id": body.get("id"),
+        "session_id": body.get("session_id"),
+        "model": body.get("model"),
+        "action_id": sub_action,
+    });
+    runtime(state)?
+        .call(
+            &function,
+            Hook::Action,
+            body,
+            &function_context(&function, user, &metadata),
+        )
+        .await
+        .map_err(|e| plugin_error(&function.id, e))?
+        .ok_or_else(|| AppError::BadRequest(format!("Function {} has no action hook", function.id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_filter_ids() {
+        let payload = json!({ "filter_ids": ["b", "a"] });
+        let model_item = json!({ "info": { "meta": { "filterIds": ["a", "c"] } } });
+        assert_eq!(
+            request_filter_ids(&payload, &model_item),
+            vec!["a", "b", "c"]
+        );
+        assert!(request_filter_ids(&json!({}), &json!({})).is_empty());
+    }
+
+    #[test]
+    fn test_event_end() {
+        assert_eq!(event_end(b"data: {}\n\ndata: [DONE]\n\n"), Some(10));
+        assert_eq!(event_end(b"data: {\"a\""), None);
+    }
+
+    #[test]
+    fn test_pipe_response() {
+        let (content_type, body) = pipe_response("echo", json!("Hello"), false);
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(content_type, "application/json");
+        assert_eq!(body["object"], "chat.completion");
+        assert_eq!(body["choices"][0]["message"]["content"], "Hello");
+
+        let completion = json!({ "choices": [{ "message": { "content": "Hi" } }] });
+        let (_, body) = pipe_response("echo", completion.clone(), false);
+        assert_eq!(serde_json::from_str::<Value>(&body).unwrap(), completion);
+
+        let (content_type, body) = pipe_response("echo", completion, true);
+        assert_eq!(content_type, "text/event-stream");
+        let chunk: Value = serde_json::from_str(
+            body.strip_prefix("data: ")
+                .and_then(|b| b.split("\n\n").next())
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(chunk["choices"][0]["delta"]["content"], "Hi");
+        assert!(body.ends_with("data: [DONE]\n\n"));
+    }
+}
@@ -0,0 +1,221 @@
+//! Embedding-backed user memories
+//!
+//! Memories live in the `memory` table; each user's memories are mirrored
+//! into a `user-memory-{user_id}` vector collection so they can be searched
+//! by similarity and injected into chats. The generic retrieval endpoints
+//! never write to these collections, and only let a user query their own.
+
+use serde_json::{json, Value};
+
+use crate::{
+    error::{AppError, AppResult},
+    models::memory::Memory,
+    retrieval::vector::types::{SearchResult, VectorItem},
+    utils::{
+        chat_middleware::append_system_context,
+        retrieval::{get_embedding_function, get_last_user_message, get_vector_db, query_doc},
+    },
+    AppState,
+};
+
+/// Memories injected into the system prompt of a chat request
+const MEMORY_CONTEXT_K: usize = 3;
+
+/// Vector collection holding a user's memories
+pub fn memory_collection_name(user_id: &str) -> String {
+    format!("user-memory-{}", user_id)
+}
+
+/// Embed memories and upsert them into their owner's collection
+pub async fn upsert_memory_vectors(
+    state: &AppState,
+    user_id: &str,
+    memories: &[Memory],
+) -> AppResult<()> {
+    if memories.is_empty() {
+        return Ok(());
+    }
+    let vector_db = get_vector_db(state)?;
+    let embedding_function = get_embedding_function(state)?;
+
+    let vectors = embedding_function
+        .embed_content(memories.iter().map(|m| m.content.clone()).collect())
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to embed memories: {}", e)))?;
+
+    let items = memories
+        .iter()
+        .zip(vectors)
+        .map(|(memory, vector)| VectorItem {
+            id: memory.id.clone(),
+            text: memory.content.clone(),
+            vector,
+            metadata: json!({
+                "created_at": memory.created_at,
+                "updated_at": memory.updated_at,
+            }),
+        })
+        .collect();
+
+    vector_db
+        .upsert(&memory_collection_name(user_id), items)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to index memories: {}", e)))
+}
+
+/// Remove memories from their owner's collection
+pub async fn delete_memory_vectors(
+    state: &AppState,
+    user_id: &str,
+    memory_ids: Vec<String>,
+) -> AppResult<()> {
+    let vector_db = get_vector_db(state)?;
+    let collection_name = memory_collection_name(user_id);
+
+    let has_collection = vector_db
+        .has_collection(&collection_name)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to check collection: {}", e)))?;
+    if !has_collection {
+        return Ok(());
+    }
+
+    vector_db
+        .delete(&collection_name, Some(memory_ids), None)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to delete memory vectors: {}", e)))
+}
+
+/// Drop a user's whole memory collection
+pub async fn delete_memory_collection(state: &AppState, user_id: &str) -> AppResult<()> {
+    let vector_db = get_vector_db(state)?;
+    let collection_name = memory_collection_name(user_id);
+
+    let has_collection = vector_db
+        .has_collection(&collection_name)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to check collection: {}", e)))?;
+    if !has_collection {
+        return Ok(());
+    }
+
+    vector_db
+        .delete_collection(&collection_name)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to delete memory collection: {}", e)))
+}
+
+/// Find the user's memories most similar to `query`
+pub async fn query_memory_vectors(
+    state: &AppState,
+    user_id: &str,
+    query: &str,
+    k: usize,
+) -> AppResult<SearchResult> {
+    let vector_db = get_vector_db(state)?;
+    let embedding_function = get_embedding_function(state)?;
+
+    let query_embedding = embedding_function
+        .embed_query(vec![query.to_string()])
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to generate query embedding: {}", e)))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::Internal("No query embedding returned".to_string()))?;
+
+    query_doc(
+        &vector_db,
+        &memory_collection_name(user_id),
+        query_embedding,
+        k,
+    )
+    .await
+}
+
+/// Numbered, dated list of the memories in a search result
+fn format_memory_context(result: &SearchResult) -> String {
+    let documents = result
+        .documents
+        .as_ref()
+        .and_then(|d| d.first())
+        .cloned()
+        .unwrap_or_default();
+    let metadatas = result
+        .metadatas
+        .as_ref()
+        .and_then(|m| m.first())
+        .cloned()
+        .unwrap_or_default();
+
+    documents
+        .iter()
+        .enumerate()
+        .map(|(idx, document)| {
+            let created_at = metadatas
+                .get(idx)
+                .and_then(|m| m.get("created_at"))
+                .and_then(|c| c.as_i64())
+                .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+                .map(|dt| dt.format("%Y-%m-%d").to_string())
+                .unwrap_or_else(|| "Unknown Date".to_string());
+            format!("{}. [{}] {}\n", idx + 1, created_at, document)
+        })
+        .collect()
+}
+
+/// Add the user's memories most relevant to the last user message to the
+/// system message
+///
+/// Best effort: when retrieval is not configured or the search fails, the
+/// request is forwarded unchanged.
+pub async fn process_memory(state: &AppState, mut form_data: Value, user_id: &str) -> Value {
+    let Some(query) = form_data
+        .get("messages")
+        .and_then(|m| m.as_array())
+        .and_then(|messages| get_last_user_message(messages))
+        .filter(|q| !q.trim().is_empty())
+    else {
+        return form_data;
+    };
+
+    let result = match query_memory_vectors(state, user_id, &query, MEMORY_CONTEXT_K).await {
+        Ok(result) => result,
+        Err(e) => {
+            tracing::warn!("Memory lookup failed for user {}: {}", user_id, e);
+            return form_data;
+        }
+    };
+
+    let user_context = format_memory_context(&result);
+    if user_context.is_empty() {
+        return form_data;
+    }
+
+    if let Some(messages) = form_data.get_mut("messages").and_then(|m| m.as_array_mut()) {
+        append_system_context(messages, &format!("User Context:\n{}\n", user_context));
+    }
+    form_data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_memory_context() {
+        let result = SearchResult {
+            ids: Some(vec![vec!["m1".to_string(), "m2".to_string()]]),
+            documents: Some(vec![vec![
+                "Prefers metric units".to_string(),
+                "Lives in Lisbon".to_string(),
+            ]]),
+            metadatas: Some(vec![vec![json!({ "created_at": 1700000000 }), json!({})]]),
+            distances: Some(vec![vec![0.9, 0.8]]),
+        };
+
+        assert_eq!(
+            format_memory_context(&result),
+            "1. [2023-11-14] Prefers metric units\n2. [Unknown Date] Lives in Lisbon\n"
+        );
+    }
+}
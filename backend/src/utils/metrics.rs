@@ -0,0 +1,751 @@
+//! Prometheus metrics for the HTTP API, upstream model calls and retrieval
+//!
+//! Metrics are recorded into a process-wide registry (see [`metrics`]) and
+//! rendered in the Prometheus text format by [`render`], together with the
+//! cache statistics of [`CacheManager`] and the Socket.IO gauges.
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use once_cell::sync::Lazy;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use crate::cache_manager::CacheManager;
+use crate::retrieval::embeddings::EmbeddingError;
+use crate::retrieval::vector::{GetResult, SearchResult, VectorItem};
+use crate::retrieval::{EmbeddingProvider, VectorDB, VectorError};
+use crate::socketio::PrometheusExporter;
+use crate::utils::cache::CacheStats;
+use crate::utils::chat_completion::ChatByteStream;
+
+/// Upper bounds of the latency buckets, in seconds
+const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0,
+];
+
+/// Upper bounds of the throughput buckets, in tokens per second
+const THROUGHPUT_BUCKETS: &[f64] = &[
+    1.0, 5.0, 10.0, 20.0, 40.0, 60.0, 80.0, 100.0, 150.0, 200.0, 400.0,
+];
+
+static METRICS: Lazy<Metrics> = Lazy::new(Metrics::new);
+
+/// The process-wide metrics registry
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn format_labels(names: &[&str], values: &[String], extra: Option<(&str, &str)>) -> String {
+    let mut pairs: Vec<String> = names
+        .iter()
+        .zip(values)
+        .map(|(name, value)| format!("{}=\"{}\"", name, escape_label(value)))
+        .collect();
+    if let Some((name, value)) = extra {
+        pairs.push(format!("{}=\"{}\"", name, value));
+    }
+    if pairs.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", pairs.join(","))
+    }
+}
+
+#[derive(Default)]
+struct HistogramSeries {
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+/// A histogram with one series per combination of label values
+struct Histogram {
+    name: &'static str,
+    help: &'static str,
+    labels: &'static [&'static str],
+    bounds: &'static [f64],
+    series: Mutex<HashMap<Vec<String>, HistogramSeries>>,
+}
+
+impl Histogram {
+    fn new(
+        name: &'static str,
+        help: &'static str,
+        labels: &'static [&'static str],
+        bounds: &'static [f64],
+    ) -> Self {
+        Self {
+            name,
+            help,
+            labels,
+            bounds,
+            series: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn observe(&self, labels: &[&str], value: f64) {
+        let key = labels.iter().map(|l| l.to_string()).collect();
+        let mut series = self.series.lock().unwrap();
+        let series = series.entry(key).or_insert_with(|| HistogramSeries {
+            buckets: vec![0; self.bounds.len()],
+            ..Default::default()
+        });
+        for (bucket, bound) in series.buckets.iter_mut().zip(self.bounds) {
+            if value <= *bound {
+                *bucket += 1;
+            }
+        }
+        series.sum += value;
+        series.count += 1;
+    }
+
+    fn render(&self, out: &mut String) -> std::fmt::Result {
+        writeln!(out, "# HELP {} {}", self.name, self.help)?;
+        writeln!(out, "# TYPE {} histogram", self.name)?;
+        let series = self.series.lock().unwrap();
+        let sorted: BTreeMap<_, _> = series.iter().collect();
+        for (values, series) in sorted {
+            for (bound, count) in self.bounds.iter().zip(&series.buckets) {
+                let le = bound.to_string();
+                let labels = format_labels(self.labels, values, Some(("le", &le)));
+                writeln!(out, "{}_bucket{} {}", self.name, labels, count)?;
+            }
+            let labels = format_labels(self.labels, values, Some(("le", "+Inf")));
+            writeln!(out, "{}_bucket{} {}", self.name, labels, series.count)?;
+            let labels = format_labels(self.labels, values, None);
+            writeln!(out, "{}_sum{} {}", self.name, labels, series.sum)?;
+            writeln!(out, "{}_count{} {}", self.name, labels, series.count)?;
+        }
+        writeln!(out)
+    }
+}
+
+/// A counter with one series per combination of label values
+struct Counter {
+    name: &'static str,
+    help: &'static str,
+    labels: &'static [&'static str],
+    series: Mutex<HashMap<Vec<String>, u64>>,
+}
+
+impl Counter {
+    fn new(name: &'static str, help: &'static str, labels: &'static [&'static str]) -> Self {
+        Self {
+            name,
+            help,
+            labels,
+            series: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn inc_by(&self, labels: &[&str], value: u64) {
+        let key = labels.iter().map(|l| l.to_string()).collect();
+        *self.series.lock().unwrap().entry(key).or_insert(0) += value;
+    }
+
+    fn render(&self, out: &mut String) -> std::fmt::Result {
+        writeln!(out, "# HELP {} {}", self.name, self.help)?;
+        writeln!(out, "# TYPE {} counter", self.name)?;
+        let series = self.series.lock().unwrap();
+        let sorted: BTreeMap<_, _> = series.iter().collect();
+        for (values, count) in sorted {
+            let labels = format_labels(self.labels, values, None);
+            writeln!(out, "{}{} {}", self.name, labels, count)?;
+        }
+        writeln!(out)
+    }
+}
+
+/// Metrics registry
+pub struct Metrics {
+    http_request_duration: Histogram,
+    llm_requests: Counter,
+    llm_request_duration: Histogram,
+    llm_time_to_first_token: Histogram,
+    llm_tokens: Counter,
+    llm_throughput: Histogram,
+    embedding_duration: Histogram,
+    embedding_texts: Counter,
+    vector_db_duration: Histogram,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        Self {
+            http_request_duration: Histogram::new(
+                "http_request_duration_seconds",
+                "HTTP request latency by route",
+                &["method", "route", "status"],
+                LATENCY_BUCKETS,
+            ),
+            llm_requests: Counter::new(
+                "llm_requests_total",
+                "Upstream chat completion requests by outcome",
+                &["model", "connection", "outcome"],
+            ),
+            llm_request_duration: Histogram::new(
+                "llm_request_duration_seconds",
+                "Upstream chat completion latency, until the last token",
+                &["model", "connection"],
+                LATENCY_BUCKETS,
+            ),
+            llm_time_to_first_token: Histogram::new(
+                "llm_time_to_first_token_seconds",
+                "Time from sending a streaming chat completion to its first token",
+                &["model", "connection"],
+                LATENCY_BUCKETS,
+            ),
+            llm_tokens: Counter::new(
+                "llm_tokens_total",
+                "Tokens processed by upstream models",
+                &["model", "connection", "type"],
+            ),
+            llm_throughput: Histogram::new(
+                "llm_output_tokens_per_second",
+                "Completion tokens per second, after the first token when streaming",
+                &["model", "connection"],
+                THROUGHPUT_BUCKETS,
+            ),
+            embedding_duration: Histogram::new(
+                "embedding_request_duration_seconds",
+                "Embedding request latency",
+                &["model", "outcome"],
+                LATENCY_BUCKETS,
+            ),
+            embedding_texts: Counter::new("embedding_texts_total", "Texts embedded", &["model"]),
+            vector_db_duration: Histogram::new(
+                "vector_db_operation_duration_seconds",
+                "Vector database operation latency",
+                &["operation", "outcome"],
+                LATENCY_BUCKETS,
+            ),
+        }
+    }
+
+    /// Record a handled HTTP request; `route` is the matched route pattern
+    pub fn observe_http_request(&self, method: &str, route: &str, status: u16, seconds: f64) {
+        self.http_request_duration
+            .observe(&[method, route, &status.to_string()], seconds);
+    }
+
+    /// Record an embedding request of `texts` texts
+    pub fn observe_embedding(&self, model: &str, texts: usize, seconds: f64, success: bool) {
+        self.embedding_duration
+            .observe(&[model, outcome(success)], seconds);
+        if success {
+            self.embedding_texts.inc_by(&[model], texts as u64);
+        }
+    }
+
+    /// Record a vector database operation
+    pub fn observe_vector_db(&self, operation: &str, seconds: f64, success: bool) {
+        self.vector_db_duration
+            .observe(&[operation, outcome(success)], seconds);
+    }
+
+    fn observe_llm(&self, call: &LlmCall, usage: &LlmUsage) {
+        let labels = [call.model.as_str(), call.connection.as_str()];
+        let finished = Instant::now();
+        self.llm_requests
+            .inc_by(&[labels[0], labels[1], outcome(usage.success)], 1);
+        if !usage.success {
+            return;
+        }
+        self.llm_request_duration
+            .observe(&labels, (finished - call.started).as_secs_f64());
+        if let Some(first_token) = usage.first_token {
+            self.llm_time_to_first_token
+                .observe(&labels, (first_token - call.started).as_secs_f64());
+        }
+        if let Some(prompt_tokens) = usage.prompt_tokens {
+            self.llm_tokens
+                .inc_by(&[labels[0], labels[1], "prompt"], prompt_tokens);
+        }
+        if usage.completion_tokens > 0 {
+            self.llm_tokens.inc_by(
+                &[labels[0], labels[1], "completion"],
+                usage.completion_tokens,
+            );
+            let generating = finished - usage.first_token.unwrap_or(call.started);
+            if generating.as_secs_f64() > 0.0 {
+                self.llm_throughput.observe(
+                    &labels,
+                    usage.completion_tokens as f64 / generating.as_secs_f64(),
+                );
+            }
+        }
+    }
+
+    fn render(&self, out: &mut String) -> std::fmt::Result {
+        self.http_request_duration.render(out)?;
+        self.llm_requests.render(out)?;
+        self.llm_request_duration.render(out)?;
+        self.llm_time_to_first_token.render(out)?;
+        self.llm_tokens.render(out)?;
+        self.llm_throughput.render(out)?;
+        self.embedding_duration.render(out)?;
+        self.embedding_texts.render(out)?;
+        self.vector_db_duration.render(out)
+    }
+}
+
+fn outcome(success: bool) -> &'static str {
+    if success {
+        "success"
+    } else {
+        "error"
+    }
+}
+
+/// Reads one value out of a cache's statistics
+type CacheValue = fn(&CacheStats) -> f64;
+
+/// Render all metrics in the Prometheus text format
+pub async fn render(socketio: Option<PrometheusExporter>) -> Result<String, std::fmt::Error> {
+    let mut out = String::new();
+    metrics().render(&mut out)?;
+
+    if let Some(manager) = CacheManager::get() {
+        let stats = manager.get_stats().await;
+        let caches = [
+            ("app", &stats.app_cache),
+            ("session", &stats.session_cache),
+            ("model", &stats.model_cache),
+            ("api", &stats.api_cache),
+        ];
+        let series: [(&str, &str, &str, CacheValue); 4] = [
+            ("cache_hits_total", "counter", "Cache hits", |s| {
+                s.hits as f64
+            }),
+            ("cache_misses_total", "counter", "Cache misses", |s| {
+                s.misses as f64
+            }),
+            (
+                "cache_hit_ratio",
+                "gauge",
+                "Share of cache lookups that hit",
+                CacheStats::hit_rate,
+            ),
+            ("cache_entries", "gauge", "Entries in the cache", |s| {
+                s.size as f64
+            }),
+        ];
+        for (name, kind, help, value) in series {
+            writeln!(out, "# HELP {} {}", name, help)?;
+            writeln!(out, "# TYPE {} {}", name, kind)?;
+            for (cache, stats) in caches {
+                writeln!(out, "{}{{cache=\"{}\"}} {}", name, cache, value(stats))?;
+            }
+            writeln!(out)?;
+        }
+    }
+
+    if let Some(exporter) = socketio {
+        out.push_str(&exporter.export().await?);
+    }
+    Ok(out)
+}
+
+/// Label for an upstream connection: the host (and port) of its base URL
+pub fn connection_label(base_url: &str) -> String {
+    url::Url::parse(base_url)
+        .ok()
+        .and_then(|url| {
+            let host = url.host_str()?.to_string();
+            Some(match url.port() {
+                Some(port) => format!("{}:{}", host, port),
+                None => host,
+            })
+        })
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[derive(Default)]
+struct LlmUsage {
+    success: bool,
+    first_token: Option<Instant>,
+    prompt_tokens: Option<u64>,
+    completion_tokens: u64,
+}
+
+/// Called with the total tokens of a successful call
+type UsageCallback = Box<dyn FnOnce(u64) + Send>;
+
+/// An upstream chat completion being timed
+pub struct LlmCall {
+    model: String,
+    connection: String,
+    started: Instant,
+    on_usage: Option<UsageCallback>,
+}
+
+impl LlmCall {
+    /// Start timing a request to `model` over `connection`
+    pub fn start(model: &str, connection: String) -> Self {
+        Self {
+            model: model.to_string(),
+            connection,
+            started: Instant::now(),
+            on_usage: None,
+        }
+    }
+
+    /// Also report the prompt and completion tokens of a successful call to
+    /// `callback`, for usage quotas
+    pub fn on_usage(mut self, callback: impl FnOnce(u64) + Send + 'static) -> Self {
+        self.on_usage = Some(Box::new(callback));
+        self
+    }
+
+    fn report(mut self, usage: LlmUsage) {
+        metrics().observe_llm(&self, &usage);
+        if let (true, Some(callback)) = (usage.success, self.on_usage.take()) {
+            callback(usage.prompt_tokens.unwrap_or(0) + usage.completion_tokens);
+        }
+    }
+
+    /// The request failed before a response arrived
+    pub fn failed(self) {
+        self.report(LlmUsage::default());
+    }
+
+    /// Record a non-streaming response in OpenAI format
+    pub fn finish(self, response: &Value) {
+        let usage = response.get("usage");
+        let tokens = |field: &str| usage.and_then(|u| u.get(field)).and_then(|v| v.as_u64());
+        self.report(LlmUsage {
+            success: true,
+            first_token: None,
+            prompt_tokens: tokens("prompt_tokens"),
+            completion_tokens: tokens("completion_tokens").unwrap_or(0),
+        });
+    }
+
+    /// Time a streaming response in OpenAI SSE format
+    ///
+    /// Tokens are taken from the final `usage` chunk when the upstream sends
+    /// one, and counted as content deltas otherwise. The call is recorded when
+    /// the stream ends or is dropped.
+    pub fn instrument(self, stream: ChatByteStream) -> ChatByteStream {
+        Box::pin(InstrumentedStream {
+            inner: stream,
+            call: Some(self),
+            usage: LlmUsage {
+                success: true,
+                ..Default::default()
+            },
+            deltas: 0,
+            line: String::new(),
+        })
+    }
+}
+
+struct InstrumentedStream {
+    inner: ChatByteStream,
+    call: Option<LlmCall>,
+    usage: LlmUsage,
+    deltas: u64,
+    line: String,
+}
+
+impl InstrumentedStream {
+    fn read_chunk(&mut self, chunk: &[u8]) {
+        self.line.push_str(&String::from_utf8_lossy(chunk));
+        while let Some(end) = self.line.find('\n') {
+            let line: String = self.line.drain(..=end).collect();
+            let Some(data) = line.trim().strip_prefix("data:") else {
+                continue;
+            };
+            let Ok(event) = serde_json::from_str::<Value>(data.trim()) else {
+                continue;
+            };
+
+            let delta = event.pointer("/choices/0/delta");
+            let has_token = delta.is_some_and(|delta| {
+                ["content", "reasoning_content", "reasoning"]
+                    .iter()
+                    .any(|field| {
+                        delta
+                            .get(field)
+                            .and_then(|v| v.as_str())
+                            .is_some_and(|s| !s.is_empty())
+                    })
+                    || delta.get("tool_calls").is_some()
+            });
+            if has_token {
+                self.usage.first_token.get_or_insert_with(Instant::now);
+                self.deltas += 1;
+            }
+            if let Some(usage) = event.get("usage").filter(|u| u.is_object()) {
+                if let Some(tokens) = usage.get("prompt_tokens").and_then(|v| v.as_u64()) {
+                    self.usage.prompt_tokens = Some(tokens);
+                }
+                if let Some(tokens) = usage.get("completion_tokens").and_then(|v| v.as_u64()) {
+                    self.usage.completion_tokens = tokens;
+                }
+            }
+        }
+    }
+
+    fn record(&mut self) {
+        if let Some(call) = self.call.take() {
+            let mut usage = std::mem::take(&mut self.usage);
+            if usage.completion_tokens == 0 {
+                usage.completion_tokens = self.deltas;
+            }
+            call.report(usage);
+        }
+    }
+}
+
+impl Stream for InstrumentedStream {
+    type Item = Result<Bytes, reqwest::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let polled = self.inner.poll_next_unpin(cx);
+        match &polled {
+            Poll::Ready(Some(Ok(chunk))) => self.read_chunk(chunk),
+            Poll::Ready(Some(Err(_))) => self.usage.success = false,
+            Poll::Ready(None) => self.record(),
+            Poll::Pending => {}
+        }
+        polled
+    }
+}
+
+impl Drop for InstrumentedStream {
+    fn drop(&mut self) {
+        self.record();
+    }
+}
+
+/// Embedding provider that records the latency of every request
+pub struct InstrumentedEmbeddings {
+    inner: Arc<dyn EmbeddingProvider>,
+}
+
+impl InstrumentedEmbeddings {
+    pub fn wrap(inner: Arc<dyn EmbeddingProvider>) -> Arc<dyn EmbeddingProvider> {
+        Arc::new(Self { inner })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmbeddingProvider for InstrumentedEmbeddings {
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        let count = texts.len();
+        let started = Instant::now();
+        let result = self.inner.embed(texts).await;
+        metrics().observe_embedding(
+            self.inner.model_name(),
+            count,
+            started.elapsed().as_secs_f64(),
+            result.is_ok(),
+        );
+        result
+    }
+
+    async fn embed_with_prefix(
+        &self,
+        texts: Vec<String>,
+        prefix: Option<&str>,
+    ) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        let count = texts.len();
+        let started = Instant::now();
+        let result = self.inner.embed_with_prefix(texts, prefix).await;
+        metrics().observe_embedding(
+            self.inner.model_name(),
+            count,
+            started.elapsed().as_secs_f64(),
+            result.is_ok(),
+        );
+        result
+    }
+
+    fn dimension(&self) -> usize {
+        self.inner.dimension()
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+}
+
+/// Vector database that records the latency of every operation
+pub struct InstrumentedVectorDB {
+    inner: Arc<dyn VectorDB>,
+}
+
+impl InstrumentedVectorDB {
+    pub fn wrap(inner: Arc<dyn VectorDB>) -> Arc<dyn VectorDB> {
+        Arc::new(Self { inner })
+    }
+}
+
+async fn timed<T>(
+    operation: &str,
+    future: impl std::future::Future<Output = Result<T, VectorError>>,
+) -> Result<T, VectorError> {
+    let started = Instant::now();
+    let result = future.await;
+    metrics().observe_vector_db(operation, started.elapsed().as_secs_f64(), result.is_ok());
+    result
+}
+
+#[async_trait::async_trait]
+impl VectorDB for InstrumentedVectorDB {
+    async fn has_collection(&self, collection_name: &str) -> Result<bool, VectorError> {
+        timed("has_collection", self.inner.has_collection(collection_name)).await
+    }
+
+    async fn delete_collection(&self, collection_name: &str) -> Result<(), VectorError> {
+        timed(
+            "delete_collection",
+            self.inner.delete_collection(collection_name),
+        )
+        .await
+    }
+
+    async fn insert(
+        &self,
+        collection_name: &str,
+        items: Vec<VectorItem>,
+    ) -> Result<(), VectorError> {
+        timed("insert", self.inner.insert(collection_name, items)).await
+    }
+
+    async fn upsert(
+        &self,
+        collection_name: &str,
+        items: Vec<VectorItem>,
+    ) -> Result<(), VectorError> {
+        timed("upsert", self.inner.upsert(collection_name, items)).await
+    }
+
+    async fn search(
+        &self,
+        collection_name: &str,
+        vectors: Vec<Vec<f32>>,
+        limit: usize,
+    ) -> Result<SearchResult, VectorError> {
+        timed("search", self.inner.search(collection_name, vectors, limit)).await
+    }
+
+    async fn query(
+        &self,
+        collection_name: &str,
+        filter: Value,
+        limit: Option<usize>,
+    ) -> Result<GetResult, VectorError> {
+        timed("query", self.inner.query(collection_name, filter, limit)).await
+    }
+
+    async fn get(&self, collection_name: &str) -> Result<GetResult, VectorError> {
+        timed("get", self.inner.get(collection_name)).await
+    }
+
+    async fn delete(
+        &self,
+        collection_name: &str,
+        ids: Option<Vec<String>>,
+        filter: Option<Value>,
+    ) -> Result<(), VectorError> {
+        timed("delete", self.inner.delete(collection_name, ids, filter)).await
+    }
+
+    async fn reset(&self) -> Result<(), VectorError> {
+        timed("reset", self.inner.reset()).await
+    }
+
+    async fn get_collection_metadata(
+        &self,
+        collection_name: &str,
+    ) -> Result<HashMap<String, Value>, VectorError> {
+        timed(
+            "get_collection_metadata",
+            self.inner.get_collection_metadata(collection_name),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_histograms_and_counters() {
+        let metrics = Metrics::new();
+        metrics.observe_http_request("GET", "/api/v1/chats/{id}", 200, 0.02);
+        metrics.observe_http_request("GET", "/api/v1/chats/{id}", 200, 3.0);
+        metrics.observe_embedding("text-embedding-3-small", 4, 0.3, true);
+        metrics.observe_vector_db("search", 0.01, false);
+
+        let mut out = String::new();
+        metrics.render(&mut out).unwrap();
+        let labels = r#"method="GET",route="/api/v1/chats/{id}",status="200""#;
+        assert!(out.contains(&format!(
+            "http_request_duration_seconds_bucket{{{},le=\"0.025\"}} 1",
+            labels
+        )));
+        assert!(out.contains(&format!(
+            "http_request_duration_seconds_bucket{{{},le=\"5\"}} 2",
+            labels
+        )));
+        assert!(out.contains(&format!(
+            "http_request_duration_seconds_count{{{}}} 2",
+            labels
+        )));
+        assert!(out.contains(r#"embedding_texts_total{model="text-embedding-3-small"} 4"#));
+        assert!(out.contains(
+            r#"vector_db_operation_duration_seconds_count{operation="search",outcome="error"} 1"#
+        ));
+        assert!(out.contains("# TYPE llm_tokens_total counter"));
+        assert_eq!(escape_label("a\"b\\"), r#"a\"b\\"#);
+    }
+
+    #[tokio::test]
+    async fn test_instrumented_stream_counts_tokens() {
+        let chunks = [
+            "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\ndata: {\"choices\":[{\"delta\":{\"content\":\"Hel",
+            "lo\"}}]}\n\ndata: {\"choices\":[{\"delta\":{\"content\":\" world\"}}]}\n\n",
+            "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":7,\"completion_tokens\":3}}\n\ndata: [DONE]\n\n",
+        ];
+        let stream: ChatByteStream = Box::pin(futures::stream::iter(
+            chunks.map(|chunk| Ok(Bytes::from(chunk))),
+        ));
+        let model = "test-instrumented-stream";
+        let call = LlmCall::start(model, connection_label("http://localhost:11434/v1"));
+        let mut stream = call.instrument(stream);
+        while stream.next().await.is_some() {}
+
+        let mut out = String::new();
+        metrics().render(&mut out).unwrap();
+        let labels = format!("model=\"{}\",connection=\"localhost:11434\"", model);
+        assert!(out.contains(&format!(
+            "llm_tokens_total{{{},type=\"completion\"}} 3",
+            labels
+        )));
+        assert!(out.contains(&format!("llm_tokens_total{{{},type=\"prompt\"}} 7", labels)));
+        assert!(out.contains(&format!(
+            "llm_time_to_first_token_seconds_count{{{}}} 1",
+            labels
+        )));
+        assert!(out.contains(&format!(
+            "llm_requests_total{{{},outcome=\"success\"}} 1",
+            labels
+        )));
+    }
+}
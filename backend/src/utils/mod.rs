@@ -5,6 +5,9 @@ pub mod chat;
 pub mod chat_completion;
 pub mod chat_middleware;
 pub mod embeddings;
+pub mod functions;
+pub mod memory;
+pub mod metrics;
 pub mod misc;
 pub mod password;
 pub mod pipeline;
@@ -12,5 +15,7 @@ pub mod retrieval;
 pub mod tasks;
 pub mod template;
 pub mod time;
+pub mod valves;
 pub mod version;
 pub mod webhook;
+pub mod web_search;
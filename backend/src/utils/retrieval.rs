@@ -3,15 +3,28 @@
 // and inject them as context into chat messages (RAG - Retrieval Augmented Generation)
 
 use crate::{
+    config::Config,
+    db::Database,
     error::{AppError, AppResult},
     models::{chat::Chat, file::File, note::Note, user::User},
-    services::{chat::ChatService, file::FileService, note::NoteService},
-    utils::misc::{get_message_list, has_access},
+    retrieval::{
+        bm25::BM25,
+        vector::{GetResult, SearchResult},
+        EmbeddingFunction, Reranker, VectorDB,
+    },
+    services::{
+        chat::ChatService, file::FileService, knowledge::KnowledgeService, note::NoteService,
+    },
+    utils::{
+        memory::memory_collection_name,
+        misc::{get_message_list, has_access, sha256_hash},
+    },
     AppState,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 /// Default RAG template for injecting context into user messages
 pub const DEFAULT_RAG_TEMPLATE: &str = r#"### Task:
@@ -55,6 +68,7 @@ pub struct FileItem {
     pub collection_name: Option<String>,
     pub file: Option<Value>, // Contains data.content if available
     pub content: Option<String>,
+    pub collection_names: Option<Vec<String>>,
 }
 
 /// Source result after processing a file item
@@ -63,6 +77,8 @@ pub struct Source {
     pub source: Value,
     pub document: Vec<String>,
     pub metadata: Vec<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub distances: Option<Vec<f32>>,
 }
 
 /// Get last user message content from messages array
@@ -131,10 +147,543 @@ pub fn rag_template(template: &str, context: &str, query: &str) -> String {
         .replace("[query]", query)
 }
 
+/// Build the embedding function for the configured provider and prefixes
+pub fn get_embedding_function(state: &AppState) -> AppResult<EmbeddingFunction> {
+    let provider = state.embedding_provider.clone().ok_or_else(|| {
+        AppError::BadRequest(
+            "Embedding provider is not configured. Set RAG_EMBEDDING_ENGINE to enable retrieval."
+                .to_string(),
+        )
+    })?;
+
+    let config = state.config.read().unwrap();
+    Ok(EmbeddingFunction::new(
+        provider,
+        config.rag_embedding_query_prefix.clone(),
+        config.rag_embedding_content_prefix.clone(),
+    ))
+}
+
+/// Get the configured vector database client
+pub fn get_vector_db(state: &AppState) -> AppResult<Arc<dyn VectorDB>> {
+    state.vector_db.clone().ok_or_else(|| {
+        AppError::BadRequest(
+            "Vector database is not configured. Set ENABLE_RAG=true and VECTOR_DB to enable retrieval."
+                .to_string(),
+        )
+    })
+}
+
+/// Search a single collection with a precomputed query embedding
+pub async fn query_doc(
+    vector_db: &Arc<dyn VectorDB>,
+    collection_name: &str,
+    query_embedding: Vec<f32>,
+    k: usize,
+) -> AppResult<SearchResult> {
+    let result = vector_db
+        .search(collection_name, vec![query_embedding], k)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to query collection: {}", e)))?;
+
+    tracing::debug!(
+        "query_doc: collection {} returned {} result(s)",
+        collection_name,
+        result
+            .ids
+            .as_ref()
+            .and_then(|ids| ids.first())
+            .map(|ids| ids.len())
+            .unwrap_or(0)
+    );
+
+    Ok(result)
+}
+
+/// Query several collections with several queries and merge the results
+///
+/// Collections that fail to be searched (e.g. they do not exist yet) are skipped.
+pub async fn query_collection(
+    vector_db: &Arc<dyn VectorDB>,
+    embedding_function: &EmbeddingFunction,
+    collection_names: &[String],
+    queries: &[String],
+    k: usize,
+) -> AppResult<SearchResult> {
+    let query_embeddings = embedding_function
+        .embed_query(queries.to_vec())
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to generate query embedding: {}", e)))?;
+
+    let mut results = Vec::new();
+    for query_embedding in query_embeddings {
+        for collection_name in collection_names {
+            match query_doc(vector_db, collection_name, query_embedding.clone(), k).await {
+                Ok(result) => results.push(result),
+                Err(e) => {
+                    tracing::warn!("Failed to query collection {}: {}", collection_name, e);
+                }
+            }
+        }
+    }
+
+    Ok(merge_and_sort_query_results(results, k))
+}
+
+/// Merge search results, dropping duplicate documents and keeping the top `k` by score
+pub fn merge_and_sort_query_results(results: Vec<SearchResult>, k: usize) -> SearchResult {
+    let mut combined: HashMap<String, (f32, String, String, Value)> = HashMap::new();
+
+    for result in results {
+        let ids = result
+            .ids
+            .and_then(|v| v.into_iter().next())
+            .unwrap_or_default();
+        let documents = result
+            .documents
+            .and_then(|v| v.into_iter().next())
+            .unwrap_or_default();
+        let metadatas = result
+            .metadatas
+            .and_then(|v| v.into_iter().next())
+            .unwrap_or_default();
+        let distances = result
+            .distances
+            .and_then(|v| v.into_iter().next())
+            .unwrap_or_default();
+
+        for (idx, document) in documents.into_iter().enumerate() {
+            let distance = distances.get(idx).copied().unwrap_or(0.0);
+            let doc_hash = sha256_hash(&document);
+
+            // Keep the best score for each unique document
+            if let Some(existing) = combined.get(&doc_hash) {
+                if existing.0 >= distance {
+                    continue;
+                }
+            }
+
+            combined.insert(
+                doc_hash,
+                (
+                    distance,
+                    ids.get(idx).cloned().unwrap_or_default(),
+                    document,
+                    metadatas.get(idx).cloned().unwrap_or_else(|| json!({})),
+                ),
+            );
+        }
+    }
+
+    let mut sorted: Vec<_> = combined.into_values().collect();
+    sorted.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+    sorted.truncate(k);
+
+    let mut ids = Vec::with_capacity(sorted.len());
+    let mut documents = Vec::with_capacity(sorted.len());
+    let mut metadatas = Vec::with_capacity(sorted.len());
+    let mut distances = Vec::with_capacity(sorted.len());
+    for (distance, id, document, metadata) in sorted {
+        distances.push(distance);
+        ids.push(id);
+        documents.push(document);
+        metadatas.push(metadata);
+    }
+
+    SearchResult {
+        ids: Some(vec![ids]),
+        documents: Some(vec![documents]),
+        metadatas: Some(vec![metadatas]),
+        distances: Some(vec![distances]),
+    }
+}
+
+/// Settings for hybrid (BM25 + vector) search, taken from the RAG config
+#[derive(Debug, Clone, Copy)]
+pub struct HybridSearchConfig {
+    /// Candidates taken from each of the BM25 and vector rankings
+    pub k: usize,
+    /// Results kept after reranking
+    pub k_reranker: usize,
+    /// Minimum rerank score, 0 disables the filter
+    pub relevance_threshold: f32,
+    /// Weight of the BM25 ranking in the fusion, the vector ranking gets the rest
+    pub bm25_weight: f32,
+}
+
+impl HybridSearchConfig {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            k: config.rag_top_k,
+            k_reranker: config.top_k_reranker.max(1) as usize,
+            relevance_threshold: config.relevance_threshold as f32,
+            bm25_weight: config.hybrid_bm25_weight.clamp(0.0, 1.0) as f32,
+        }
+    }
+}
+
+/// Weighted reciprocal rank fusion of several rankings of the same document set
+///
+/// Each ranking is a list of document indices, best first, with its weight.
+/// Returns the fused order of every index that appears in any ranking.
+pub fn reciprocal_rank_fusion(rankings: &[(Vec<usize>, f32)], c: f32) -> Vec<usize> {
+    let mut scores: HashMap<usize, f32> = HashMap::new();
+    let mut order: Vec<usize> = Vec::new();
+
+    for (ranking, weight) in rankings {
+        for (rank, &idx) in ranking.iter().enumerate() {
+            let entry = scores.entry(idx).or_insert_with(|| {
+                order.push(idx);
+                0.0
+            });
+            *entry += weight / (rank as f32 + 1.0 + c);
+        }
+    }
+
+    // Stable sort keeps first-seen order for ties
+    order.sort_by(|a, b| {
+        scores[b]
+            .partial_cmp(&scores[a])
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    order
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+/// Documents of a collection loaded for keyword search
+struct HybridCollection {
+    name: String,
+    ids: Vec<String>,
+    documents: Vec<String>,
+    metadatas: Vec<Value>,
+    bm25: BM25,
+}
+
+impl HybridCollection {
+    fn new(name: &str, result: GetResult) -> Self {
+        let ids = result
+            .ids
+            .and_then(|v| v.into_iter().next())
+            .unwrap_or_default();
+        let documents = result
+            .documents
+            .and_then(|v| v.into_iter().next())
+            .unwrap_or_default();
+        let metadatas = result
+            .metadatas
+            .and_then(|v| v.into_iter().next())
+            .unwrap_or_default();
+        let bm25 = BM25::new(&documents);
+
+        Self {
+            name: name.to_string(),
+            ids,
+            documents,
+            metadatas,
+            bm25,
+        }
+    }
+}
+
+/// Hybrid search of a single collection for one query
+///
+/// BM25 and vector candidates are fused with weighted reciprocal rank fusion,
+/// then rescored by the cross-encoder (or by embedding similarity when no
+/// reranker is configured), filtered by the relevance threshold and cut to
+/// `k_reranker`.
+async fn query_doc_with_hybrid_search(
+    vector_db: &Arc<dyn VectorDB>,
+    embedding_function: &EmbeddingFunction,
+    reranker: Option<&Arc<dyn Reranker>>,
+    collection: &HybridCollection,
+    query: &str,
+    query_embedding: &[f32],
+    config: HybridSearchConfig,
+) -> AppResult<SearchResult> {
+    let mut rankings = Vec::new();
+
+    if config.bm25_weight > 0.0 {
+        let bm25_ranking = collection
+            .bm25
+            .top_k(query, config.k)
+            .into_iter()
+            .map(|(idx, _)| idx)
+            .collect();
+        rankings.push((bm25_ranking, config.bm25_weight));
+    }
+
+    if config.bm25_weight < 1.0 {
+        let positions: HashMap<&str, usize> = collection
+            .ids
+            .iter()
+            .enumerate()
+            .map(|(idx, id)| (id.as_str(), idx))
+            .collect();
+        let vector_result = query_doc(
+            vector_db,
+            &collection.name,
+            query_embedding.to_vec(),
+            config.k,
+        )
+        .await?;
+        let vector_ranking = vector_result
+            .ids
+            .and_then(|v| v.into_iter().next())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|id| positions.get(id.as_str()).copied())
+            .collect();
+        rankings.push((vector_ranking, 1.0 - config.bm25_weight));
+    }
+
+    let candidates = reciprocal_rank_fusion(&rankings, 60.0);
+    let candidate_docs: Vec<String> = candidates
+        .iter()
+        .map(|&idx| collection.documents[idx].clone())
+        .collect();
+
+    let scores = match reranker {
+        Some(reranker) => reranker
+            .rerank(query, &candidate_docs)
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to rerank documents: {}", e)))?,
+        None if candidate_docs.is_empty() => Vec::new(),
+        None => embedding_function
+            .embed_content(candidate_docs)
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to embed documents: {}", e)))?
+            .iter()
+            .map(|embedding| cosine_similarity(query_embedding, embedding))
+            .collect(),
+    };
+
+    let mut scored: Vec<(usize, f32)> = candidates
+        .into_iter()
+        .zip(scores)
+        .filter(|(_, score)| *score >= config.relevance_threshold)
+        .collect();
+    scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    scored.truncate(config.k_reranker);
+
+    tracing::debug!(
+        "query_doc_with_hybrid_search: collection {} returned {} result(s)",
+        collection.name,
+        scored.len()
+    );
+
+    Ok(SearchResult {
+        ids: Some(vec![scored
+            .iter()
+            .map(|(idx, _)| collection.ids.get(*idx).cloned().unwrap_or_default())
+            .collect()]),
+        documents: Some(vec![scored
+            .iter()
+            .map(|(idx, _)| collection.documents[*idx].clone())
+            .collect()]),
+        metadatas: Some(vec![scored
+            .iter()
+            .map(|(idx, _)| {
+                collection
+                    .metadatas
+                    .get(*idx)
+                    .cloned()
+                    .unwrap_or_else(|| json!({}))
+            })
+            .collect()]),
+        distances: Some(vec![scored.iter().map(|(_, score)| *score).collect()]),
+    })
+}
+
+/// Hybrid search over several collections with several queries
+///
+/// Fails only when no collection could be searched, so callers can fall back
+/// to plain vector search.
+pub async fn query_collection_with_hybrid_search(
+    vector_db: &Arc<dyn VectorDB>,
+    embedding_function: &EmbeddingFunction,
+    reranker: Option<&Arc<dyn Reranker>>,
+    collection_names: &[String],
+    queries: &[String],
+    config: HybridSearchConfig,
+) -> AppResult<SearchResult> {
+    let query_embeddings = embedding_function
+        .embed_query(queries.to_vec())
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to generate query embedding: {}", e)))?;
+
+    let mut results = Vec::new();
+
+    for collection_name in collection_names {
+        let collection = match vector_db.get(collection_name).await {
+            Ok(result) => HybridCollection::new(collection_name, result),
+            Err(e) => {
+                tracing::warn!("Failed to load collection {}: {}", collection_name, e);
+                continue;
+            }
+        };
+
+        for (query, query_embedding) in queries.iter().zip(&query_embeddings) {
+            match query_doc_with_hybrid_search(
+                vector_db,
+                embedding_function,
+                reranker,
+                &collection,
+                query,
+                query_embedding,
+                config,
+            )
+            .await
+            {
+                Ok(result) => results.push(result),
+                Err(e) => {
+                    tracing::warn!(
+                        "Hybrid search failed for collection {}: {}",
+                        collection_name,
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    if results.is_empty() && !collection_names.is_empty() && !queries.is_empty() {
+        return Err(AppError::Internal(
+            "Hybrid search failed for all collections".to_string(),
+        ));
+    }
+
+    Ok(merge_and_sort_query_results(results, config.k_reranker))
+}
+
+/// Search collections using the configured retrieval mode
+///
+/// Uses hybrid search when enabled, falling back to plain vector search if it
+/// fails for every collection.
+pub async fn query_collections(
+    state: &AppState,
+    collection_names: &[String],
+    queries: &[String],
+) -> AppResult<SearchResult> {
+    let vector_db = get_vector_db(state)?;
+    let embedding_function = get_embedding_function(state)?;
+    let (hybrid, hybrid_config) = {
+        let config = state.config.read().unwrap();
+        (
+            config.enable_rag_hybrid_search,
+            HybridSearchConfig::from_config(&config),
+        )
+    };
+
+    if hybrid {
+        match query_collection_with_hybrid_search(
+            &vector_db,
+            &embedding_function,
+            state.reranker.as_ref(),
+            collection_names,
+            queries,
+            hybrid_config,
+        )
+        .await
+        {
+            Ok(result) => return Ok(result),
+            Err(e) => {
+                tracing::warn!("{}. Using non-hybrid search as fallback.", e);
+            }
+        }
+    }
+
+    query_collection(
+        &vector_db,
+        &embedding_function,
+        collection_names,
+        queries,
+        hybrid_config.k,
+    )
+    .await
+}
+
+/// Whether `user` may read `file`: they own it, are an admin, or can read a
+/// knowledge base that contains it
+async fn can_read_file(
+    knowledge_service: &KnowledgeService<'_>,
+    user: &User,
+    user_group_ids: &HashSet<String>,
+    file: &File,
+) -> AppResult<bool> {
+    if user.role == "admin" || file.user_id == user.id {
+        return Ok(true);
+    }
+
+    Ok(knowledge_service
+        .get_all_knowledge()
+        .await?
+        .iter()
+        .any(|knowledge| {
+            let contains_file = knowledge
+                .data
+                .as_ref()
+                .and_then(|d| d.get("file_ids"))
+                .and_then(|ids| ids.as_array())
+                .is_some_and(|ids| ids.iter().any(|id| id.as_str() == Some(&file.id)));
+            contains_file
+                && (knowledge.user_id == user.id
+                    || has_access(&user.id, "read", &knowledge.access_control, user_group_ids))
+        }))
+}
+
+/// Whether `user` may search the vector collection `collection_name`
+///
+/// A `file-{id}` collection follows the file's read access, a knowledge base's
+/// collection its access control, and a memory collection belongs to its user
+/// alone. Any other collection is only readable by admins.
+pub async fn can_read_collection(
+    db: &Database,
+    user: &User,
+    user_group_ids: &HashSet<String>,
+    collection_name: &str,
+) -> AppResult<bool> {
+    if user.role == "admin" {
+        return Ok(true);
+    }
+    if collection_name.starts_with("user-memory-") {
+        return Ok(collection_name == memory_collection_name(&user.id));
+    }
+
+    let knowledge_service = KnowledgeService::new(db);
+    if let Some(file_id) = collection_name.strip_prefix("file-") {
+        return match FileService::new(db).get_file_by_id(file_id).await? {
+            Some(file) => can_read_file(&knowledge_service, user, user_group_ids, &file).await,
+            None => Ok(false),
+        };
+    }
+
+    Ok(knowledge_service
+        .get_knowledge_by_id(collection_name)
+        .await?
+        .is_some_and(|knowledge| {
+            knowledge.user_id == user.id
+                || has_access(&user.id, "read", &knowledge.access_control, user_group_ids)
+        }))
+}
+
 /// Process file items and extract sources for RAG
+///
+/// Files and knowledge collections are searched with `queries` unless full
+/// context is requested, in which case their whole content is used.
 pub async fn get_sources_from_items(
     state: &AppState,
     items: Vec<FileItem>,
+    queries: &[String],
     user: &User,
     user_group_ids: &HashSet<String>,
 ) -> AppResult<Vec<Source>> {
@@ -143,9 +692,18 @@ pub async fn get_sources_from_items(
     let note_service = NoteService::new(&state.db);
     let file_service = FileService::new(&state.db);
     let chat_service = ChatService::new(&state.db);
+    let knowledge_service = KnowledgeService::new(&state.db);
+
+    let full_context = {
+        let config = state.config.read().unwrap();
+        config.rag_full_context || config.bypass_embedding_and_retrieval
+    };
 
     for item in items {
         let mut query_result: Option<(Vec<String>, Vec<Value>)> = None;
+        let mut distances: Option<Vec<f32>> = None;
+        let mut collection_names: Vec<String> = Vec::new();
+        let item_full_context = full_context || item.context.as_deref() == Some("full");
 
         match item.item_type.as_str() {
             "text" => {
@@ -314,7 +872,7 @@ pub async fn get_sources_from_items(
             "file" => {
                 // File attachment
                 // Check if full context mode or if file data is embedded
-                if item.context.as_deref() == Some("full") {
+                if item_full_context {
                     if let Some(file_data) = &item.file {
                         if let Some(content) = file_data
                             .get("data")
@@ -350,7 +908,15 @@ pub async fn get_sources_from_items(
                     } else if let Some(file_id) = &item.id {
                         // Fallback: fetch from database
                         match file_service.get_file_by_id(file_id).await? {
-                            Some(mut file) => {
+                            Some(mut file)
+                                if can_read_file(
+                                    &knowledge_service,
+                                    user,
+                                    user_group_ids,
+                                    &file,
+                                )
+                                .await? =>
+                            {
                                 file.parse_json_fields();
 
                                 let content = file
@@ -375,14 +941,107 @@ pub async fn get_sources_from_items(
                                     content.len()
                                 );
                             }
+                            Some(_) => {
+                                tracing::warn!(
+                                    "❌ User {} does not have access to file {}",
+                                    user.id,
+                                    file_id
+                                );
+                            }
                             None => {
                                 tracing::warn!("⚠️ File {} not found", file_id);
                             }
                         }
                     }
+                } else if let Some(file_id) = &item.id {
+                    // Only the file's own collection is searched, never one named by the client
+                    match file_service.get_file_by_id(file_id).await? {
+                        Some(file)
+                            if can_read_file(&knowledge_service, user, user_group_ids, &file)
+                                .await? =>
+                        {
+                            collection_names.push(format!("file-{}", file.id));
+                        }
+                        Some(_) => {
+                            tracing::warn!(
+                                "❌ User {} does not have access to file {}",
+                                user.id,
+                                file_id
+                            );
+                        }
+                        None => {
+                            tracing::warn!("⚠️ File {} not found", file_id);
+                        }
+                    }
+                }
+            }
+
+            "collection" => {
+                // Knowledge base attachment
+                if let Some(knowledge_id) = &item.id {
+                    match knowledge_service.get_knowledge_by_id(knowledge_id).await? {
+                        Some(knowledge)
+                            if user.role == "admin"
+                                || knowledge.user_id == user.id
+                                || has_access(
+                                    &user.id,
+                                    "read",
+                                    &knowledge.access_control,
+                                    user_group_ids,
+                                ) =>
+                        {
+                            if item_full_context {
+                                let file_ids: Vec<String> = knowledge
+                                    .data
+                                    .as_ref()
+                                    .and_then(|d| d.get("file_ids"))
+                                    .and_then(|ids| ids.as_array())
+                                    .map(|ids| {
+                                        ids.iter()
+                                            .filter_map(|id| id.as_str().map(String::from))
+                                            .collect()
+                                    })
+                                    .unwrap_or_default();
+
+                                let mut documents = Vec::new();
+                                let mut metadatas = Vec::new();
+                                for file_id in file_ids {
+                                    if let Some(mut file) =
+                                        file_service.get_file_by_id(&file_id).await?
+                                    {
+                                        file.parse_json_fields();
+                                        let content = file
+                                            .data
+                                            .as_ref()
+                                            .and_then(|d| d.get("content"))
+                                            .and_then(|c| c.as_str())
+                                            .unwrap_or("");
+                                        documents.push(content.to_string());
+                                        metadatas.push(json!({
+                                            "file_id": file.id,
+                                            "name": file.filename,
+                                            "source": file.filename
+                                        }));
+                                    }
+                                }
+                                query_result = Some((documents, metadatas));
+                            } else {
+                                // Access was checked on the knowledge base, so search only its collection
+                                collection_names = vec![knowledge.id.clone()];
+                            }
+                        }
+                        Some(_) => {
+                            tracing::warn!(
+                                "❌ User {} does not have access to knowledge {}",
+                                user.id,
+                                knowledge_id
+                            );
+                        }
+                        None => {
+                            tracing::warn!("⚠️ Knowledge {} not found", knowledge_id);
+                        }
+                    }
                 }
-                // Note: Vector search/embedding retrieval is not yet implemented
-                // TODO: Implement collection-based retrieval with embeddings
             }
 
             _ => {
@@ -390,6 +1049,32 @@ pub async fn get_sources_from_items(
             }
         }
 
+        // Search the item's collections with the user's queries
+        if query_result.is_none() && !collection_names.is_empty() && !queries.is_empty() {
+            match query_collections(state, &collection_names, queries).await {
+                Ok(result) => {
+                    query_result = Some((
+                        result
+                            .documents
+                            .and_then(|v| v.into_iter().next())
+                            .unwrap_or_default(),
+                        result
+                            .metadatas
+                            .and_then(|v| v.into_iter().next())
+                            .unwrap_or_default(),
+                    ));
+                    distances = result.distances.and_then(|v| v.into_iter().next());
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "⚠️ Failed to retrieve from collections {:?}: {}",
+                        collection_names,
+                        e
+                    );
+                }
+            }
+        }
+
         // Add to sources if we got a result
         if let Some((documents, metadatas)) = query_result {
             let source_info = json!({
@@ -402,6 +1087,7 @@ pub async fn get_sources_from_items(
                 source: source_info,
                 document: documents,
                 metadata: metadatas,
+                distances,
             });
         }
     }
@@ -483,3 +1169,236 @@ pub fn inject_sources_into_messages(
 
     Ok(sources)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search_result(docs: &[(&str, f32)]) -> SearchResult {
+        SearchResult {
+            ids: Some(vec![docs
+                .iter()
+                .map(|(d, _)| format!("id-{}", d))
+                .collect()]),
+            documents: Some(vec![docs.iter().map(|(d, _)| d.to_string()).collect()]),
+            metadatas: Some(vec![docs.iter().map(|_| json!({})).collect()]),
+            distances: Some(vec![docs.iter().map(|(_, s)| *s).collect()]),
+        }
+    }
+
+    #[test]
+    fn test_merge_and_sort_query_results() {
+        let merged = merge_and_sort_query_results(
+            vec![
+                search_result(&[("alpha", 0.4), ("beta", 0.9)]),
+                search_result(&[("gamma", 0.7), ("alpha", 0.8)]),
+            ],
+            2,
+        );
+
+        let documents = merged.documents.unwrap().remove(0);
+        let distances = merged.distances.unwrap().remove(0);
+        assert_eq!(documents, vec!["beta", "alpha"]);
+        assert_eq!(distances, vec![0.9, 0.8]);
+    }
+
+    #[test]
+    fn test_merge_and_sort_query_results_empty() {
+        let merged = merge_and_sort_query_results(vec![], 5);
+        assert!(merged.documents.unwrap()[0].is_empty());
+    }
+
+    #[test]
+    fn test_reciprocal_rank_fusion() {
+        // 2 is ranked well by both lists and wins, 3 only appears in the lighter list
+        let fused = reciprocal_rank_fusion(&[(vec![1, 2], 0.5), (vec![2, 0, 3], 0.5)], 60.0);
+        assert_eq!(fused[0], 2);
+        assert_eq!(fused.len(), 4);
+        assert_eq!(fused.last(), Some(&3));
+
+        let weighted = reciprocal_rank_fusion(&[(vec![1], 0.9), (vec![0], 0.1)], 60.0);
+        assert_eq!(weighted, vec![1, 0]);
+    }
+
+    /// Embeds text as counts over a tiny fixed vocabulary
+    struct VocabEmbeddings;
+
+    #[async_trait::async_trait]
+    impl crate::retrieval::EmbeddingProvider for VocabEmbeddings {
+        async fn embed(
+            &self,
+            texts: Vec<String>,
+        ) -> Result<Vec<Vec<f32>>, crate::retrieval::EmbeddingError> {
+            const VOCAB: [&str; 4] = ["rust", "python", "memory", "garbage"];
+            Ok(texts
+                .iter()
+                .map(|text| {
+                    let text = text.to_lowercase();
+                    VOCAB
+                        .iter()
+                        .map(|word| text.matches(word).count() as f32 + 0.01)
+                        .collect()
+                })
+                .collect())
+        }
+
+        fn dimension(&self) -> usize {
+            4
+        }
+
+        fn model_name(&self) -> &str {
+            "vocab"
+        }
+    }
+
+    /// Scores documents by whether they mention "borrow"
+    struct KeywordReranker;
+
+    #[async_trait::async_trait]
+    impl Reranker for KeywordReranker {
+        async fn rerank(
+            &self,
+            _query: &str,
+            documents: &[String],
+        ) -> Result<Vec<f32>, crate::retrieval::EmbeddingError> {
+            Ok(documents
+                .iter()
+                .map(|d| if d.contains("borrow") { 0.9 } else { 0.1 })
+                .collect())
+        }
+
+        fn model_name(&self) -> &str {
+            "keyword"
+        }
+    }
+
+    async fn hybrid_fixture() -> (Arc<dyn VectorDB>, EmbeddingFunction) {
+        use crate::retrieval::vector::{
+            local::{LocalVectorConfig, LocalVectorDB},
+            VectorItem,
+        };
+
+        let provider: Arc<dyn crate::retrieval::EmbeddingProvider> = Arc::new(VocabEmbeddings);
+        let embedding_function = EmbeddingFunction::new(provider, String::new(), String::new());
+
+        let texts = [
+            "Rust manages memory with the borrow checker",
+            "Python uses garbage collection for memory",
+            "Rust has no garbage collector",
+            "Bananas are yellow",
+        ];
+        let vectors = embedding_function
+            .embed_content(texts.iter().map(|t| t.to_string()).collect())
+            .await
+            .unwrap();
+        let items = texts
+            .iter()
+            .zip(vectors)
+            .enumerate()
+            .map(|(idx, (text, vector))| VectorItem {
+                id: format!("doc-{}", idx),
+                text: text.to_string(),
+                vector,
+                metadata: json!({"idx": idx}),
+            })
+            .collect();
+
+        let vector_db: Arc<dyn VectorDB> = Arc::new(
+            LocalVectorDB::new(LocalVectorConfig {
+                path: ":memory:".to_string(),
+            })
+            .await
+            .unwrap(),
+        );
+        vector_db.insert("docs", items).await.unwrap();
+
+        (vector_db, embedding_function)
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_search_with_reranker() {
+        let (vector_db, embedding_function) = hybrid_fixture().await;
+        let reranker: Arc<dyn Reranker> = Arc::new(KeywordReranker);
+        let config = HybridSearchConfig {
+            k: 4,
+            k_reranker: 3,
+            relevance_threshold: 0.5,
+            bm25_weight: 0.5,
+        };
+
+        let result = query_collection_with_hybrid_search(
+            &vector_db,
+            &embedding_function,
+            Some(&reranker),
+            &["docs".to_string()],
+            &["rust memory".to_string()],
+            config,
+        )
+        .await
+        .unwrap();
+
+        // Only the document the reranker likes survives the threshold
+        let documents = result.documents.unwrap().remove(0);
+        assert_eq!(
+            documents,
+            vec!["Rust manages memory with the borrow checker"]
+        );
+        assert_eq!(result.distances.unwrap()[0], vec![0.9]);
+        assert_eq!(result.metadatas.unwrap()[0][0]["idx"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_search_without_reranker() {
+        let (vector_db, embedding_function) = hybrid_fixture().await;
+        let config = HybridSearchConfig {
+            k: 4,
+            k_reranker: 2,
+            relevance_threshold: 0.0,
+            bm25_weight: 1.0,
+        };
+
+        let result = query_collection_with_hybrid_search(
+            &vector_db,
+            &embedding_function,
+            None,
+            &["docs".to_string()],
+            &["garbage".to_string()],
+            config,
+        )
+        .await
+        .unwrap();
+
+        // BM25 only: the two documents mentioning "garbage", scored by embedding similarity
+        let mut documents = result.documents.unwrap().remove(0);
+        documents.sort();
+        assert_eq!(
+            documents,
+            vec![
+                "Python uses garbage collection for memory",
+                "Rust has no garbage collector"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_search_fails_for_missing_collections() {
+        let (vector_db, embedding_function) = hybrid_fixture().await;
+        let config = HybridSearchConfig {
+            k: 4,
+            k_reranker: 2,
+            relevance_threshold: 0.0,
+            bm25_weight: 0.5,
+        };
+
+        let result = query_collection_with_hybrid_search(
+            &vector_db,
+            &embedding_function,
+            None,
+            &["missing".to_string()],
+            &["rust".to_string()],
+            config,
+        )
+        .await;
+        assert!(result.is_err());
+    }
+}
@@ -1,11 +1,19 @@
+//! Tracking and cancelling background work
+//!
+//! Chat generations and their follow-up jobs (such as title generation) run
+//! as tasks registered under the chat they belong to. With Redis, tasks are
+//! also recorded in `{prefix}:tasks`, so any node can list them, and stop
+//! requests for tasks running on another node are published on
+//! `{prefix}:tasks:commands`.
+
 use futures::StreamExt;
 use redis::AsyncCommands;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use tokio::task::JoinHandle;
+use tokio::sync::{oneshot, RwLock};
+use tokio::task::AbortHandle;
 use tracing::{error, info, warn};
 use uuid::Uuid;
 
@@ -15,6 +23,9 @@ use crate::error::{AppError, AppResult};
 pub struct Task {
     pub id: String,
     pub item_id: Option<String>,
+    /// User the task runs for
+    #[serde(default)]
+    pub user_id: Option<String>,
     pub status: TaskStatus,
     pub created_at: i64,
 }
@@ -23,15 +34,34 @@ pub struct Task {
 #[serde(rename_all = "lowercase")]
 pub enum TaskStatus {
     Running,
-    Completed,
-    Failed,
-    Cancelled,
 }
 
-#[allow(dead_code)]
+struct RunningTask {
+    task: Task,
+    handle: AbortHandle,
+}
+
+/// Removes a task's records once its future completes, fails or is aborted
+struct CleanupGuard {
+    manager: TaskManager,
+    task_id: String,
+}
+
+impl Drop for CleanupGuard {
+    fn drop(&mut self) {
+        let manager = self.manager.clone();
+        let task_id = std::mem::take(&mut self.task_id);
+        // No runtime left to clean up with while shutting down
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move { manager.cleanup_task(&task_id).await });
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct TaskManager {
-    // In-memory task storage
-    tasks: Arc<RwLock<HashMap<String, JoinHandle<()>>>>,
+    // Tasks running on this node
+    tasks: Arc<RwLock<HashMap<String, RunningTask>>>,
     // Item ID -> Task IDs mapping
     item_tasks: Arc<RwLock<HashMap<String, Vec<String>>>>,
     // Redis pool (optional)
@@ -42,7 +72,6 @@ pub struct TaskManager {
     redis_key_prefix: String,
 }
 
-#[allow(dead_code)]
 impl TaskManager {
     pub fn new(
         redis: Option<deadpool_redis::Pool>,
@@ -58,24 +87,70 @@ impl TaskManager {
         }
     }
 
+    fn tasks_key(&self) -> String {
+        format!("{}:tasks", self.redis_key_prefix)
+    }
+
+    fn item_tasks_key(&self, item_id: &str) -> String {
+        format!("{}:tasks:item:{}", self.redis_key_prefix, item_id)
+    }
+
+    fn commands_channel(&self) -> String {
+        format!("{}:tasks:commands", self.redis_key_prefix)
+    }
+
     /// Create a new task
     pub async fn create_task<F>(&self, future: F, item_id: Option<String>) -> AppResult<String>
     where
         F: std::future::Future<Output = ()> + Send + 'static,
     {
-        let task_id = Uuid::new_v4().to_string();
+        self.create_user_task(future, item_id, None).await
+    }
 
-        // Create the tokio task
-        let task_handle = tokio::spawn(future);
+    /// Create a new task run on behalf of `user_id`
+    pub async fn create_user_task<F>(
+        &self,
+        future: F,
+        item_id: Option<String>,
+        user_id: Option<String>,
+    ) -> AppResult<String>
+    where
+        F: std::future::Future<Output = ()> + Send + 'static,
+    {
+        let task = Task {
+            id: Uuid::new_v4().to_string(),
+            item_id,
+            user_id,
+            status: TaskStatus::Running,
+            created_at: chrono::Utc::now().timestamp(),
+        };
+        let task_id = task.id.clone();
+
+        // The future only starts once the task is recorded, so a task that
+        // finishes right away can't be cleaned up before it is stored
+        let (registered_tx, registered_rx) = oneshot::channel::<()>();
+        let guard = CleanupGuard {
+            manager: self.clone(),
+            task_id: task_id.clone(),
+        };
+        let handle = tokio::spawn(async move {
+            let _guard = guard;
+            if registered_rx.await.is_ok() {
+                future.await;
+            }
+        });
 
         // Store in memory
-        self.tasks
-            .write()
-            .await
-            .insert(task_id.clone(), task_handle);
+        self.tasks.write().await.insert(
+            task_id.clone(),
+            RunningTask {
+                task: task.clone(),
+                handle: handle.abort_handle(),
+            },
+        );
 
         // Store item mapping
-        if let Some(item_id) = &item_id {
+        if let Some(item_id) = &task.item_id {
             let mut item_tasks = self.item_tasks.write().await;
             item_tasks
                 .entry(item_id.clone())
@@ -86,70 +161,86 @@ impl TaskManager {
         // Store in Redis if available
         if let Some(redis) = &self.redis {
             if let Ok(mut conn) = redis.get().await {
-                let tasks_key = format!("{}:tasks", self.redis_key_prefix);
-                let item_value = item_id.as_deref().unwrap_or("");
-
-                // Store task -> item_id mapping
+                let record = json!(task).to_string();
                 let _: Result<(), redis::RedisError> =
-                    conn.hset(&tasks_key, &task_id, item_value).await;
+                    conn.hset(self.tasks_key(), &task_id, record).await;
 
-                // Store item -> task mapping
-                if let Some(item_id) = &item_id {
-                    let item_tasks_key =
-                        format!("{}:tasks:item:{}", self.redis_key_prefix, item_id);
+                if let Some(item_id) = &task.item_id {
                     let _: Result<(), redis::RedisError> =
-                        conn.sadd(&item_tasks_key, &task_id).await;
+                        conn.sadd(self.item_tasks_key(item_id), &task_id).await;
                 }
             }
         }
 
-        info!("Created task {} for item {:?}", task_id, item_id);
+        let _ = registered_tx.send(());
+
+        info!("Created task {} for item {:?}", task_id, task.item_id);
         Ok(task_id)
     }
 
-    /// Stop a task by ID
-    pub async fn stop_task(&self, task_id: &str) -> AppResult<()> {
-        // Find the item_id for this task
-        let item_id = {
-            let item_tasks = self.item_tasks.read().await;
-            item_tasks
-                .iter()
-                .find(|(_, tasks)| tasks.contains(&task_id.to_string()))
-                .map(|(id, _)| id.clone())
-        };
-
-        // Stop local task
-        if let Some(handle) = self.tasks.write().await.remove(task_id) {
-            handle.abort();
-            info!("Stopped local task {}", task_id);
+    /// A running task, on this node or (with Redis) any other
+    pub async fn get_task(&self, task_id: &str) -> Option<Task> {
+        if let Some(running) = self.tasks.read().await.get(task_id) {
+            return Some(running.task.clone());
         }
 
-        // Send stop command via Redis pub/sub if available
-        if let Some(redis) = &self.redis {
-            if let Ok(mut conn) = redis.get().await {
-                let pubsub_channel = format!("{}:tasks:commands", self.redis_key_prefix);
-                let command = json!({
-                    "action": "stop",
-                    "task_id": task_id,
-                });
-
-                let _: Result<(), redis::RedisError> =
-                    conn.publish(&pubsub_channel, command.to_string()).await;
+        let mut conn = self.redis.as_ref()?.get().await.ok()?;
+        let record: Option<String> = conn.hget(self.tasks_key(), task_id).await.ok()?;
+        record.map(|record| parse_record(task_id, &record))
+    }
 
-                info!("Published stop command for task {} to Redis", task_id);
+    /// Stop a task by ID, wherever it runs
+    pub async fn stop_task(&self, task_id: &str) -> AppResult<Task> {
+        let task = self
+            .get_task(task_id)
+            .await
+            .ok_or_else(|| AppError::NotFound("Task not found".to_string()))?;
+
+        if !self.abort_local_task(task_id).await {
+            // Running on another node; its listener aborts it
+            if let Some(redis) = &self.redis {
+                if let Ok(mut conn) = redis.get().await {
+                    let command = json!({
+                        "action": "stop",
+                        "task_id": task_id,
+                    });
+
+                    let _: Result<(), redis::RedisError> = conn
+                        .publish(self.commands_channel(), command.to_string())
+                        .await;
+
+                    info!("Published stop command for task {} to Redis", task_id);
+                }
             }
         }
 
-        // Clean up task data
-        self.cleanup_task(task_id, item_id).await?;
+        // Drop the records now, even if the node running the task is gone
+        self.cleanup_task(task_id).await;
 
-        Ok(())
+        Ok(task)
     }
 
-    /// Cleanup task data after completion
-    async fn cleanup_task(&self, task_id: &str, item_id: Option<String>) -> AppResult<()> {
+    /// Abort a task running on this node; its guard cleans up after it
+    async fn abort_local_task(&self, task_id: &str) -> bool {
+        match self.tasks.read().await.get(task_id) {
+            Some(running) => {
+                running.handle.abort();
+                info!("Stopped local task {}", task_id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove a task's records
+    async fn cleanup_task(&self, task_id: &str) {
         // Remove from memory
-        self.tasks.write().await.remove(task_id);
+        let mut item_id = self
+            .tasks
+            .write()
+            .await
+            .remove(task_id)
+            .and_then(|running| running.task.item_id);
 
         // Remove from item mapping
         if let Some(item_id) = &item_id {
@@ -165,58 +256,58 @@ impl TaskManager {
         // Clean up Redis if available
         if let Some(redis) = &self.redis {
             if let Ok(mut conn) = redis.get().await {
-                let tasks_key = format!("{}:tasks", self.redis_key_prefix);
-
-                // Get item_id from Redis if not provided
-                let redis_item_id: Result<Option<String>, redis::RedisError> =
-                    conn.hget(&tasks_key, task_id).await;
-
-                let item_id_to_clean = item_id.or_else(|| redis_item_id.ok().flatten());
+                let tasks_key = self.tasks_key();
+
+                // Tasks from other nodes are only known by their record
+                if item_id.is_none() {
+                    let record: Result<Option<String>, redis::RedisError> =
+                        conn.hget(&tasks_key, task_id).await;
+                    item_id = record
+                        .ok()
+                        .flatten()
+                        .and_then(|record| parse_record(task_id, &record).item_id);
+                }
 
-                // Remove task from hash
                 let _: Result<(), redis::RedisError> = conn.hdel(&tasks_key, task_id).await;
 
-                // Remove from item set
-                if let Some(item_id) = item_id_to_clean {
-                    if !item_id.is_empty() {
-                        let item_tasks_key =
-                            format!("{}:tasks:item:{}", self.redis_key_prefix, item_id);
-                        let _: Result<(), redis::RedisError> =
-                            conn.srem(&item_tasks_key, task_id).await;
-                    }
+                if let Some(item_id) = item_id {
+                    let _: Result<(), redis::RedisError> =
+                        conn.srem(self.item_tasks_key(&item_id), task_id).await;
                 }
             }
         }
-
-        Ok(())
     }
 
     /// List all active tasks
-    pub async fn list_tasks(&self) -> AppResult<Vec<String>> {
+    pub async fn list_tasks(&self) -> AppResult<Vec<Task>> {
         // Get local tasks
-        let local_tasks: Vec<String> = self.tasks.read().await.keys().cloned().collect();
+        let mut all_tasks: Vec<Task> = self
+            .tasks
+            .read()
+            .await
+            .values()
+            .map(|running| running.task.clone())
+            .collect();
 
         // If Redis is available, also get remote tasks
         if let Some(redis) = &self.redis {
             if let Ok(mut conn) = redis.get().await {
-                let tasks_key = format!("{}:tasks", self.redis_key_prefix);
-                let redis_tasks: Result<Vec<String>, redis::RedisError> =
-                    conn.hkeys(&tasks_key).await;
+                let records: Result<HashMap<String, String>, redis::RedisError> =
+                    conn.hgetall(self.tasks_key()).await;
 
-                if let Ok(tasks) = redis_tasks {
+                if let Ok(records) = records {
                     // Combine local and Redis tasks (deduplicated)
-                    let mut all_tasks: Vec<String> = local_tasks;
-                    for task in tasks {
-                        if !all_tasks.contains(&task) {
-                            all_tasks.push(task);
+                    for (task_id, record) in records {
+                        if !all_tasks.iter().any(|task| task.id == task_id) {
+                            all_tasks.push(parse_record(&task_id, &record));
                         }
                     }
-                    return Ok(all_tasks);
                 }
             }
         }
 
-        Ok(local_tasks)
+        all_tasks.sort_by_key(|task| task.created_at);
+        Ok(all_tasks)
     }
 
     /// List tasks for a specific item (e.g., chat, note)
@@ -233,9 +324,8 @@ impl TaskManager {
         // If Redis is available, also get remote tasks
         if let Some(redis) = &self.redis {
             if let Ok(mut conn) = redis.get().await {
-                let item_tasks_key = format!("{}:tasks:item:{}", self.redis_key_prefix, item_id);
                 let redis_tasks: Result<Vec<String>, redis::RedisError> =
-                    conn.smembers(&item_tasks_key).await;
+                    conn.smembers(self.item_tasks_key(item_id)).await;
 
                 if let Ok(tasks) = redis_tasks {
                     // Combine local and Redis tasks (deduplicated)
@@ -267,87 +357,91 @@ impl TaskManager {
     }
 
     /// Start listening to Redis pub/sub commands
-    pub async fn start_redis_listener(&self) -> AppResult<()> {
-        if let Some(redis) = &self.redis {
-            let pubsub_channel = format!("{}:tasks:commands", self.redis_key_prefix);
-
-            info!("Starting Redis task command listener on {}", pubsub_channel);
-
-            let tasks = self.tasks.clone();
-            let _redis_clone = redis.clone();
-            let _redis_key_prefix = self.redis_key_prefix.clone();
+    pub fn start_redis_listener(&self) -> AppResult<()> {
+        if self.redis.is_none() {
+            return Ok(());
+        }
+        let pubsub_channel = self.commands_channel();
 
-            // Get a dedicated connection for pubsub by creating a new client
-            let redis_url = self
-                .redis_url
-                .clone()
-                .ok_or_else(|| AppError::Redis("Redis URL not configured".to_string()))?;
+        info!("Starting Redis task command listener on {}", pubsub_channel);
 
-            let client =
-                redis::Client::open(redis_url).map_err(|e| AppError::Redis(e.to_string()))?;
+        // Get a dedicated connection for pubsub by creating a new client
+        let redis_url = self
+            .redis_url
+            .clone()
+            .ok_or_else(|| AppError::Redis("Redis URL not configured".to_string()))?;
 
-            tokio::spawn(async move {
-                let mut pubsub = match client.get_async_pubsub().await {
-                    Ok(pubsub) => pubsub,
-                    Err(e) => {
-                        error!("Failed to get Redis pubsub connection: {}", e);
-                        return;
-                    }
-                };
+        let client = redis::Client::open(redis_url).map_err(|e| AppError::Redis(e.to_string()))?;
+        let manager = self.clone();
 
-                if let Err(e) = pubsub.subscribe(&pubsub_channel).await {
-                    error!("Failed to subscribe to Redis channel: {}", e);
+        tokio::spawn(async move {
+            let mut pubsub = match client.get_async_pubsub().await {
+                Ok(pubsub) => pubsub,
+                Err(e) => {
+                    error!("Failed to get Redis pubsub connection: {}", e);
                     return;
                 }
+            };
 
-                let mut message_stream = pubsub.on_message();
-                loop {
-                    match message_stream.next().await {
-                        Some(msg) => {
-                            let payload: String = match msg.get_payload() {
-                                Ok(p) => p,
-                                Err(e) => {
-                                    error!("Failed to get message payload: {}", e);
-                                    continue;
-                                }
-                            };
+            if let Err(e) = pubsub.subscribe(&pubsub_channel).await {
+                error!("Failed to subscribe to Redis channel: {}", e);
+                return;
+            }
 
-                            match serde_json::from_str::<serde_json::Value>(&payload) {
-                                Ok(command) => {
-                                    if let Some("stop") =
-                                        command.get("action").and_then(|v| v.as_str())
+            let mut message_stream = pubsub.on_message();
+            loop {
+                match message_stream.next().await {
+                    Some(msg) => {
+                        let payload: String = match msg.get_payload() {
+                            Ok(p) => p,
+                            Err(e) => {
+                                error!("Failed to get message payload: {}", e);
+                                continue;
+                            }
+                        };
+
+                        match serde_json::from_str::<serde_json::Value>(&payload) {
+                            Ok(command) => {
+                                if let Some("stop") = command.get("action").and_then(|v| v.as_str())
+                                {
+                                    if let Some(task_id) =
+                                        command.get("task_id").and_then(|v| v.as_str())
                                     {
-                                        if let Some(task_id) =
-                                            command.get("task_id").and_then(|v| v.as_str())
-                                        {
-                                            // Stop local task if it exists
-                                            if let Some(handle) =
-                                                tasks.write().await.remove(task_id)
-                                            {
-                                                handle.abort();
-                                                info!("Stopped task {} via Redis command", task_id);
-                                            }
+                                        if manager.abort_local_task(task_id).await {
+                                            info!("Stopped task {} via Redis command", task_id);
                                         }
                                     }
                                 }
-                                Err(e) => {
-                                    error!("Failed to parse command JSON: {}", e);
-                                }
+                            }
+                            Err(e) => {
+                                error!("Failed to parse command JSON: {}", e);
                             }
                         }
-                        None => {
-                            warn!("Redis pub/sub connection closed");
-                            break;
-                        }
+                    }
+                    None => {
+                        warn!("Redis pub/sub connection closed");
+                        break;
                     }
                 }
-            });
-        }
+            }
+        });
 
         Ok(())
     }
 }
 
+/// A task's Redis record; records written before tasks were stored as JSON
+/// hold just the item ID
+fn parse_record(task_id: &str, record: &str) -> Task {
+    serde_json::from_str(record).unwrap_or_else(|_| Task {
+        id: task_id.to_string(),
+        item_id: Some(record.to_string()).filter(|item_id| !item_id.is_empty()),
+        user_id: None,
+        status: TaskStatus::Running,
+        created_at: 0,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -370,7 +464,35 @@ mod tests {
 
         let tasks = manager.list_tasks().await.unwrap();
         assert_eq!(tasks.len(), 1);
-        assert_eq!(tasks[0], task_id);
+        assert_eq!(tasks[0].id, task_id);
+    }
+
+    #[tokio::test]
+    async fn test_finished_tasks_are_removed() {
+        let manager = TaskManager::new(None, None, "test".to_string());
+
+        let task_id = manager
+            .create_task(async {}, Some("chat".to_string()))
+            .await
+            .unwrap();
+        let failing_id = manager
+            .create_task(async { panic!("task failed") }, Some("chat".to_string()))
+            .await
+            .unwrap();
+
+        for _ in 0..100 {
+            if manager.list_tasks().await.unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+        }
+        assert!(manager.list_tasks().await.unwrap().is_empty());
+        assert!(manager.list_tasks_by_item("chat").await.unwrap().is_empty());
+        assert!(manager.get_task(&task_id).await.is_none());
+        assert!(matches!(
+            manager.stop_task(&failing_id).await,
+            Err(AppError::NotFound(_))
+        ));
     }
 
     #[tokio::test]
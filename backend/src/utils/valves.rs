@@ -0,0 +1,220 @@
+//! Valves and UserValves of tools and functions
+//!
+//! A valves spec is a JSON Schema object whose properties are the valves.
+//! Admin valves are stored in the tool or function row; UserValves live in
+//! the user's settings under `tools.valves.<id>` or `functions.valves.<id>`.
+//! Properties marked secret (`"input": {"type": "password"}`) are masked
+//! whenever valves are returned to a client.
+
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+use crate::error::{AppError, AppResult};
+
+/// Placeholder returned instead of a secret valve's value
+pub const SECRET_MASK: &str = "********";
+
+fn properties(spec: &Value) -> Option<&Map<String, Value>> {
+    spec.get("properties").and_then(|p| p.as_object())
+}
+
+fn is_secret(property: &Value) -> bool {
+    property.pointer("/input/type").and_then(|t| t.as_str()) == Some("password")
+        || property.get("format").and_then(|f| f.as_str()) == Some("password")
+}
+
+/// Stored valves with secret values replaced by [`SECRET_MASK`]
+pub fn mask_secrets(valves: &Value, spec: &Value) -> Value {
+    let mut masked = valves.clone();
+    let (Some(properties), Some(values)) = (properties(spec), masked.as_object_mut()) else {
+        return masked;
+    };
+    for (name, property) in properties {
+        if !is_secret(property) {
+            continue;
+        }
+        if let Some(value) = values.get_mut(name) {
+            if !value.is_null() && value.as_str() != Some("") {
+                *value = json!(SECRET_MASK);
+            }
+        }
+    }
+    masked
+}
+
+/// Valves to store after an update from a client
+///
+/// Only properties in `spec` are kept, and secrets sent back as
+/// [`SECRET_MASK`] keep their current value. Without a spec the update is
+/// stored as is.
+pub fn apply_update(current: &Value, update: Value, spec: Option<&Value>) -> AppResult<Value> {
+    let Value::Object(update) = update else {
+        return Err(AppError::BadRequest(
+            "Valves must be a JSON object".to_string(),
+        ));
+    };
+    let Some(properties) = spec.and_then(properties) else {
+        return Ok(Value::Object(update));
+    };
+
+    let mut valves = Map::new();
+    for (name, value) in update {
+        let Some(property) = properties.get(&name) else {
+            continue;
+        };
+        let value = if is_secret(property) && value.as_str() == Some(SECRET_MASK) {
+            current.get(&name).cloned().unwrap_or(Value::Null)
+        } else {
+            value
+        };
+        valves.insert(name, value);
+    }
+    Ok(Value::Object(valves))
+}
+
+/// Stored valves over the spec's defaults
+pub fn with_defaults(valves: Option<&Value>, spec: Option<&Value>) -> Value {
+    let mut merged = Map::new();
+    if let Some(properties) = spec.and_then(properties) {
+        for (name, property) in properties {
+            if let Some(default) = property.get("default") {
+                merged.insert(name.clone(), default.clone());
+            }
+        }
+    }
+    if let Some(Value::Object(values)) = valves {
+        for (name, value) in values {
+            if !value.is_null() {
+                merged.insert(name.clone(), value.clone());
+            }
+        }
+    }
+    Value::Object(merged)
+}
+
+/// Valves as environment variables for `{{env.X}}` templates; empty values
+/// are left out
+pub fn to_environment(valves: &Value) -> HashMap<String, String> {
+    valves
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, value)| {
+            let value = match value {
+                Value::Null => return None,
+                Value::String(s) if s.is_empty() => return None,
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            Some((name.clone(), value))
+        })
+        .collect()
+}
+
+/// A user's UserValves for the tool or function `id`; `kind` is `tools` or
+/// `functions`
+pub fn user_valves(settings: Option<&Value>, kind: &str, id: &str) -> Value {
+    settings
+        .and_then(|s| s.get(kind))
+        .and_then(|k| k.get("valves"))
+        .and_then(|v| v.get(id))
+        .cloned()
+        .unwrap_or_else(|| json!({}))
+}
+
+/// User settings with the UserValves for `id` replaced
+pub fn set_user_valves(settings: Option<&Value>, kind: &str, id: &str, valves: Value) -> Value {
+    let mut settings = match settings {
+        Some(Value::Object(settings)) => settings.clone(),
+        _ => Map::new(),
+    };
+    let kind = settings
+        .entry(kind)
+        .and_modify(|k| {
+            if !k.is_object() {
+                *k = json!({});
+            }
+        })
+        .or_insert_with(|| json!({}));
+    let all = kind
+        .as_object_mut()
+        .unwrap()
+        .entry("valves")
+        .and_modify(|v| {
+            if !v.is_object() {
+                *v = json!({});
+            }
+        })
+        .or_insert_with(|| json!({}));
+    all.as_object_mut().unwrap().insert(id.to_string(), valves);
+    Value::Object(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> Value {
+        json!({
+            "title": "Valves",
+            "type": "object",
+            "properties": {
+                "API_KEY": { "type": "string", "input": { "type": "password" } },
+                "BASE_URL": { "type": "string", "default": "https://api.example.com" },
+                "LIMIT": { "type": "integer", "default": 5 }
+            }
+        })
+    }
+
+    #[test]
+    fn test_secrets_are_masked_and_kept() {
+        let stored = json!({ "API_KEY": "sk-123", "BASE_URL": "https://other.example.com" });
+        let masked = mask_secrets(&stored, &spec());
+        assert_eq!(masked["API_KEY"], SECRET_MASK);
+        assert_eq!(masked["BASE_URL"], "https://other.example.com");
+
+        // Sending the masked form back keeps the secret; unknown valves are dropped
+        let mut update = masked.clone();
+        update["UNKNOWN"] = json!("x");
+        let updated = apply_update(&stored, update, Some(&spec())).unwrap();
+        assert_eq!(updated, stored);
+
+        let updated = apply_update(&stored, json!({ "API_KEY": "sk-456" }), Some(&spec())).unwrap();
+        assert_eq!(updated, json!({ "API_KEY": "sk-456" }));
+        assert!(apply_update(&stored, json!([]), Some(&spec())).is_err());
+    }
+
+    #[test]
+    fn test_environment_from_valves() {
+        let valves = with_defaults(
+            Some(&json!({ "API_KEY": "sk-123", "BASE_URL": null })),
+            Some(&spec()),
+        );
+        let environment = to_environment(&valves);
+        assert_eq!(environment["API_KEY"], "sk-123");
+        assert_eq!(environment["BASE_URL"], "https://api.example.com");
+        assert_eq!(environment["LIMIT"], "5");
+        assert!(!to_environment(&json!({ "EMPTY": "" })).contains_key("EMPTY"));
+    }
+
+    #[test]
+    fn test_user_valves_in_settings() {
+        let settings = json!({ "ui": { "theme": "dark" }, "tools": "junk" });
+        let settings = set_user_valves(
+            Some(&settings),
+            "tools",
+            "weather",
+            json!({ "UNITS": "metric" }),
+        );
+        assert_eq!(settings["ui"]["theme"], "dark");
+        assert_eq!(
+            user_valves(Some(&settings), "tools", "weather"),
+            json!({ "UNITS": "metric" })
+        );
+        assert_eq!(
+            user_valves(Some(&settings), "functions", "weather"),
+            json!({})
+        );
+        assert_eq!(user_valves(None, "tools", "weather"), json!({}));
+    }
+}
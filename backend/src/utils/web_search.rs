@@ -0,0 +1,256 @@
+//! The `web_search` chat feature
+//!
+//! Search queries are generated from the conversation with the same prompt as
+//! `/tasks/queries/completions`, the configured engine is queried, and the
+//! result pages are loaded, indexed into a temporary collection and searched
+//! so that only their relevant parts reach the model as cited sources.
+
+use serde_json::{json, Value};
+use std::collections::HashSet;
+
+use crate::{
+    error::{AppError, AppResult},
+    retrieval::{
+        loaders::UrlLoader,
+        web::{self, WebSearchResult},
+        Document,
+    },
+    services::providers::Provider,
+    utils::{
+        misc::sha256_hash,
+        retrieval::{get_last_user_message, get_vector_db, query_collections, Source},
+    },
+    AppState,
+};
+
+/// Pages found for a set of queries
+pub struct WebSearchOutcome {
+    pub results: Vec<WebSearchResult>,
+    pub docs: Vec<Document>,
+}
+
+/// Run every query against the configured engine and load the result pages
+///
+/// Results are de-duplicated by link and filtered by
+/// WEB_SEARCH_DOMAIN_FILTER_LIST. Fails only when every query failed.
+pub async fn search_web(state: &AppState, queries: &[String]) -> AppResult<WebSearchOutcome> {
+    let (provider, loader, count, concurrency, domain_filter) = {
+        let config = state.config.read().unwrap();
+        if !config.enable_web_search {
+            return Err(AppError::BadRequest("Web search is disabled".to_string()));
+        }
+        let provider = web::from_config(state.http_client.clone(), &config)
+            .map_err(|e| AppError::BadRequest(e.to_string()))?;
+        let loader = UrlLoader::from_config(state.http_client.clone(), &config)
+            .map_err(|e| AppError::BadRequest(e.to_string()))?;
+        (
+            provider,
+            loader,
+            config.web_search_result_count,
+            config.web_search_concurrent_requests,
+            config.web_search_domain_filter_list.clone(),
+        )
+    };
+
+    let mut results = Vec::new();
+    let mut seen = HashSet::new();
+    let mut last_error = None;
+    for query in queries {
+        match provider.search(query, count).await {
+            Ok(hits) => results.extend(hits.into_iter().filter(|r| seen.insert(r.link.clone()))),
+            Err(e) => {
+                tracing::warn!("{} search failed for {:?}: {}", provider.name(), query, e);
+                last_error = Some(e);
+            }
+        }
+    }
+    if let (true, Some(e)) = (results.is_empty(), last_error) {
+        return Err(AppError::ExternalServiceError(e.to_string()));
+    }
+
+    let results = web::filter_by_domain(results, &domain_filter);
+    let docs = web::load_results(&loader, &results, concurrency).await;
+    Ok(WebSearchOutcome { results, docs })
+}
+
+/// Search the web and return the relevant parts of the result pages as a source
+///
+/// Unless BYPASS_WEB_SEARCH_EMBEDDING_AND_RETRIEVAL is set, the pages are
+/// indexed into a temporary collection that is dropped after it is queried.
+pub async fn get_web_search_sources(
+    state: &AppState,
+    queries: &[String],
+) -> AppResult<Vec<Source>> {
+    let outcome = search_web(state, queries).await?;
+    if outcome.docs.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let source = json!({
+        "type": "web_search",
+        "name": queries.join(", "),
+        "urls": outcome.results.iter().map(|r| r.link.clone()).collect::<Vec<_>>(),
+    });
+
+    let bypass = state
+        .config
+        .read()
+        .unwrap()
+        .bypass_web_search_embedding_and_retrieval;
+    if bypass {
+        let (document, metadata) = outcome
+            .docs
+            .into_iter()
+            .map(|doc| (doc.page_content, doc.metadata))
+            .unzip();
+        return Ok(vec![Source {
+            source,
+            document,
+            metadata,
+            distances: None,
+        }]);
+    }
+
+    // Unique per request, so concurrent searches for the same queries don't
+    // drop each other's collection. Names are capped at 63 characters by some
+    // vector databases, which cuts the query hash short.
+    let mut collection_name = format!(
+        "web-search-{}-{}",
+        uuid::Uuid::new_v4().simple(),
+        sha256_hash(&queries.join("-"))
+    );
+    collection_name.truncate(63);
+
+    crate::routes::retrieval::save_docs_to_vector_db(
+        state,
+        outcome.docs,
+        &collection_name,
+        None,
+        true,
+        true,
+        false,
+    )
+    .await?;
+    let result = query_collections(state, std::slice::from_ref(&collection_name), queries).await;
+
+    if let Err(e) = get_vector_db(state)?
+        .delete_collection(&collection_name)
+        .await
+    {
+        tracing::warn!("Failed to drop collection {}: {}", collection_name, e);
+    }
+
+    let result = result?;
+    Ok(vec![Source {
+        source,
+        document: result
+            .documents
+            .and_then(|d| d.into_iter().next())
+            .unwrap_or_default(),
+        metadata: result
+            .metadatas
+            .and_then(|m| m.into_iter().next())
+            .unwrap_or_default(),
+        distances: result.distances.and_then(|d| d.into_iter().next()),
+    }])
+}
+
+/// Ask the chat model for search queries, falling back to the last user message
+///
+/// `url`, `key` and `api_config` are the resolved connection for `model_id`.
+pub async fn generate_search_queries(
+    state: &AppState,
+    messages: &[Value],
+    model_id: &str,
+    url: &str,
+    key: &str,
+    api_config: &Value,
+) -> Vec<String> {
+    let fallback = || get_last_user_message(messages).into_iter().collect();
+
+    let prompt = {
+        let config = state.config.read().unwrap();
+        if !config.enable_search_query_generation {
+            return fallback();
+        }
+        crate::routes::tasks::query_generation_prompt(&config, messages)
+    };
+
+    let payload = json!({
+        "model": model_id,
+        "messages": [{ "role": "user", "content": prompt }],
+        "max_tokens": 100,
+        "temperature": 0.3,
+        "stream": false,
+    });
+    let provider = Provider::from_api_config(api_config);
+    let response = provider
+        .chat_request(&state.http_client, url, key, api_config, &payload)
+        .timeout(std::time::Duration::from_secs(30))
+        .send()
+        .await;
+
+    let content = match response {
+        Ok(response) if response.status().is_success() => response
+            .json::<Value>()
+            .await
+            .ok()
+            .map(|body| provider.openai_response(body))
+            .and_then(|body| {
+                body.pointer("/choices/0/message/content")
+                    .and_then(|c| c.as_str())
+                    .map(String::from)
+            }),
+        Ok(response) => {
+            tracing::warn!("Query generation failed with status {}", response.status());
+            None
+        }
+        Err(e) => {
+            tracing::warn!("Query generation request failed: {}", e);
+            None
+        }
+    };
+
+    let queries = content.map(|c| parse_queries(&c)).unwrap_or_default();
+    if queries.is_empty() {
+        fallback()
+    } else {
+        queries
+    }
+}
+
+/// Extract `{"queries": [...]}` from a model response
+fn parse_queries(content: &str) -> Vec<String> {
+    let (Some(start), Some(end)) = (content.find('{'), content.rfind('}')) else {
+        return Vec::new();
+    };
+    if end < start {
+        return Vec::new();
+    }
+    serde_json::from_str::<Value>(&content[start..=end])
+        .ok()
+        .and_then(|v| v.get("queries").and_then(|q| q.as_array()).cloned())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|q| q.as_str())
+        .map(|q| q.trim().to_string())
+        .filter(|q| !q.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_queries() {
+        assert_eq!(
+            parse_queries(
+                "Sure!\n```json\n{ \"queries\": [\"rust async\", \" \", \"tokio\"] }\n```"
+            ),
+            vec!["rust async".to_string(), "tokio".to_string()]
+        );
+        assert!(parse_queries("no json here").is_empty());
+        assert!(parse_queries("} {").is_empty());
+    }
+}
@@ -980,6 +980,52 @@ export const blobToFile = (blob, fileName) => {
 	return file;
 };
 
+export const audioBlobToWav = async (blob: Blob, sampleRate = 16000): Promise<Blob> => {
+	// Decode the recording (usually Opus) with the browser's own codecs
+	const audioContext = new AudioContext();
+	let decoded: AudioBuffer;
+	try {
+		decoded = await audioContext.decodeAudioData(await blob.arrayBuffer());
+	} finally {
+		audioContext.close();
+	}
+
+	// Downmix to mono and resample
+	const length = Math.max(1, Math.ceil(decoded.duration * sampleRate));
+	const offlineContext = new OfflineAudioContext(1, length, sampleRate);
+	const source = offlineContext.createBufferSource();
+	source.buffer = decoded;
+	source.connect(offlineContext.destination);
+	source.start();
+	const samples = (await offlineContext.startRendering()).getChannelData(0);
+
+	// 16-bit PCM WAV
+	const view = new DataView(new ArrayBuffer(44 + samples.length * 2));
+	const writeString = (offset: number, value: string) => {
+		for (let i = 0; i < value.length; i++) {
+			view.setUint8(offset + i, value.charCodeAt(i));
+		}
+	};
+	writeString(0, 'RIFF');
+	view.setUint32(4, 36 + samples.length * 2, true);
+	writeString(8, 'WAVEfmt ');
+	view.setUint32(16, 16, true);
+	view.setUint16(20, 1, true);
+	view.setUint16(22, 1, true);
+	view.setUint32(24, sampleRate, true);
+	view.setUint32(28, sampleRate * 2, true);
+	view.setUint16(32, 2, true);
+	view.setUint16(34, 16, true);
+	writeString(36, 'data');
+	view.setUint32(40, samples.length * 2, true);
+	samples.forEach((sample, i) => {
+		const clamped = Math.max(-1, Math.min(1, sample));
+		view.setInt16(44 + i * 2, clamped < 0 ? clamped * 0x8000 : clamped * 0x7fff, true);
+	});
+
+	return new Blob([view], { type: 'audio/wav' });
+};
+
 export const getPromptVariables = (user_name, user_location) => {
 	return {
 		'{{USER_NAME}}': user_name,